@@ -749,6 +749,93 @@ public class CallManager {
 
     Connection connection = ringrtcGetActiveConnection(nativeCallManager);
     connection.setAudioEnabled(enable);
+
+    ringrtcSetAudioEnable(nativeCallManager, enable);
+  }
+
+  /**
+   *
+   * Configures when the user is considered to be talking while muted,
+   * for the active call, every group call, and those created later.
+   *
+   * @param minLevel           a captured audio level (0-32767) at or above this counts as talking
+   * @param minDurationMillis  how long talking must go on before notifying
+   * @param maxPauseMillis     quiet shorter than this (such as between words) doesn't end talking
+   * @param minIntervalMillis  how long to wait before notifying again if talking goes on
+   *
+   * @throws CallException for native code failures
+   *
+   */
+  public void setTalkingWhileMutedConfig(int  minLevel,
+                                         long minDurationMillis,
+                                         long maxPauseMillis,
+                                         long minIntervalMillis)
+    throws CallException
+  {
+    checkCallManagerExists();
+
+    Log.i(TAG, "setTalkingWhileMutedConfig():");
+
+    ringrtcSetTalkingWhileMutedConfig(nativeCallManager, minLevel, minDurationMillis, maxPauseMillis, minIntervalMillis);
+  }
+
+  /**
+   *
+   * Adds a step to the processing of video captured for group calls,
+   * current and future: cropping around the center to an aspect ratio.
+   * Direct calls aren't processed.
+   *
+   * @param width   width of the aspect ratio, such as 16 for 16:9
+   * @param height  height of the aspect ratio, such as 9 for 16:9
+   *
+   * @throws CallException for native code failures
+   *
+   */
+  public void addVideoFrameCropToAspectRatio(int width, int height)
+    throws CallException
+  {
+    checkCallManagerExists();
+
+    Log.i(TAG, "addVideoFrameCropToAspectRatio(): " + width + ":" + height);
+    ringrtcAddVideoFrameCropToAspectRatio(nativeCallManager, width, height);
+  }
+
+  /**
+   *
+   * Adds a step to the processing of video captured for group calls,
+   * current and future: scaling to a fixed size.
+   * Direct calls aren't processed.
+   *
+   * @param width   width to scale to, in pixels
+   * @param height  height to scale to, in pixels
+   *
+   * @throws CallException for native code failures
+   *
+   */
+  public void addVideoFrameScale(int width, int height)
+    throws CallException
+  {
+    checkCallManagerExists();
+
+    Log.i(TAG, "addVideoFrameScale(): " + width + "x" + height);
+    ringrtcAddVideoFrameScale(nativeCallManager, width, height);
+  }
+
+  /**
+   *
+   * Removes every step added to the processing of video captured
+   * for group calls.
+   *
+   * @throws CallException for native code failures
+   *
+   */
+  public void clearVideoFrameProcessors()
+    throws CallException
+  {
+    checkCallManagerExists();
+
+    Log.i(TAG, "clearVideoFrameProcessors():");
+    ringrtcClearVideoFrameProcessors(nativeCallManager);
   }
 
   /**
@@ -790,6 +877,24 @@ public class CallManager {
     ringrtcUpdateBandwidthMode(nativeCallManager, bandwidthMode.ordinal());
   }
 
+  /**
+   *
+   * Like updateBandwidthMode, but with arbitrary limits.
+   *
+   * @param maxSendKbps     the most to send, for all media
+   * @param maxReceiveKbps  the most to ask the other side to send, for all media
+   *
+   * @throws CallException for native code failures
+   *
+   */
+  public void updateBandwidthPolicy(long maxSendKbps, long maxReceiveKbps)
+    throws CallException
+  {
+    checkCallManagerExists();
+
+    ringrtcUpdateBandwidthPolicy(nativeCallManager, maxSendKbps, maxReceiveKbps);
+  }
+
   /**
    *
    * Notification from application to hangup the active call.
@@ -1119,6 +1224,13 @@ public class CallManager {
     observer.onAudioLevels(remote, capturedLevel, receivedLevel);
   }
 
+  @CalledByNative
+  private void onTalkingWhileMuted(Remote remote) {
+    Log.i(TAG, "onTalkingWhileMuted():");
+
+    observer.onTalkingWhileMuted(remote);
+  }
+
   // A faster version of PeerConnection.AdapterType.fromNativeIndex.
   // It also won't return null.
   @NonNull
@@ -1306,6 +1418,32 @@ public class CallManager {
     groupCall.handleAudioLevels(capturedLevel, receivedLevels);
   }
 
+  @CalledByNative
+  private void handleTalkingWhileMuted(long clientId) {
+    Log.i(TAG, "handleTalkingWhileMuted():");
+
+    GroupCall groupCall = this.groupCallByClientId.get(clientId);
+    if (groupCall == null) {
+      Log.w(TAG, "groupCall not found by clientId: " + clientId);
+      return;
+    }
+
+    groupCall.handleTalkingWhileMuted();
+  }
+
+  @CalledByNative
+  private void handleSpotlightChanged(long clientId, @Nullable Long demuxId) {
+    Log.i(TAG, "handleSpotlightChanged():");
+
+    GroupCall groupCall = this.groupCallByClientId.get(clientId);
+    if (groupCall == null) {
+      Log.w(TAG, "groupCall not found by clientId: " + clientId);
+      return;
+    }
+
+    groupCall.handleSpotlightChanged(demuxId);
+  }
+
   @CalledByNative
   private void handleJoinStateChanged(long clientId, GroupCall.JoinState joinState) {
     Log.i(TAG, "handleJoinStateChanged():");
@@ -1641,7 +1779,12 @@ public class CallManager {
      * (Default) No specific constraints, but keep a relatively
      * high bitrate to ensure good quality.
      */
-    NORMAL;
+    NORMAL,
+
+    /**
+     * Intended for 1080p video on good networks.
+     */
+    HIGH;
 
     @CalledByNative
     static BandwidthMode fromNativeIndex(int nativeIndex) {
@@ -1744,6 +1887,14 @@ public class CallManager {
      */
     void onAudioLevels(Remote remote, int capturedLevel, int receivedLevel);
 
+    /**
+     *
+     * Notification that the user seems to be talking while muted
+     *
+     * @param remote  remote peer of the call
+     */
+    void onTalkingWhileMuted(Remote remote);
+
     /**
      *
      * Notification of that the call is completely concluded
@@ -2009,6 +2160,30 @@ public class CallManager {
     CallContext ringrtcGetActiveCallContext(long nativeCallManager)
     throws CallException;
 
+  private native
+    void ringrtcSetAudioEnable(long nativeCallManager, boolean enable)
+    throws CallException;
+
+  private native
+    void ringrtcSetTalkingWhileMutedConfig(long nativeCallManager,
+                                           int  minLevel,
+                                           long minDurationMillis,
+                                           long maxPauseMillis,
+                                           long minIntervalMillis)
+    throws CallException;
+
+  private native
+    void ringrtcAddVideoFrameCropToAspectRatio(long nativeCallManager, int width, int height)
+    throws CallException;
+
+  private native
+    void ringrtcAddVideoFrameScale(long nativeCallManager, int width, int height)
+    throws CallException;
+
+  private native
+    void ringrtcClearVideoFrameProcessors(long nativeCallManager)
+    throws CallException;
+
   private native
     void ringrtcSetVideoEnable(long nativeCallManager, boolean enable)
     throws CallException;
@@ -2017,6 +2192,10 @@ public class CallManager {
     void ringrtcUpdateBandwidthMode(long nativeCallManager, int bandwidthMode)
     throws CallException;
 
+  private native
+    void ringrtcUpdateBandwidthPolicy(long nativeCallManager, long maxSendKbps, long maxReceiveKbps)
+    throws CallException;
+
   private native
     void ringrtcDrop(long nativeCallManager, long callId)
     throws CallException;
@@ -335,6 +335,44 @@ public final class GroupCall {
         ringrtcSetOutgoingVideoMuted(nativeCallManager, this.clientId, muted);
     }
 
+    /**
+     *
+     * Switch to (or back from) audio-only. Unlike BandwidthMode.VERY_LOW,
+     * this stops receiving and decoding video, stops sending video, and
+     * tells the other devices.
+     *
+     * @param audioOnly      true to switch to audio-only
+     *
+     * @throws CallException for native code failures
+     *
+     */
+    public void setAudioOnly(boolean audioOnly)
+        throws CallException
+    {
+        Log.i(TAG, "setAudioOnly():");
+
+        ringrtcSetAudioOnly(nativeCallManager, this.clientId, audioOnly);
+    }
+
+    /**
+     *
+     * Puts a device (which may be this one) in the spotlight for everyone
+     * in the call, or takes it out. RingRTC doesn't check who is allowed
+     * to do this; that's up to the application.
+     *
+     * @param demuxId        the device to put in the spotlight, or null to clear it
+     *
+     * @throws CallException for native code failures
+     *
+     */
+    public void setSpotlight(@Nullable Long demuxId)
+        throws CallException
+    {
+        Log.i(TAG, "setSpotlight():");
+
+        ringrtcSetSpotlight(nativeCallManager, this.clientId, demuxId);
+    }
+
     /**	
      *
      * Links the camera to the outgoing video track.
@@ -355,6 +393,79 @@ public final class GroupCall {
         }
     }
 
+    /**
+     *
+     * Sets the name shared with the other devices in the call so they
+     * can label this one, even if they don't know the user.
+     *
+     * @param displayName  the name to share, or null to stop sharing one
+     *
+     * @throws CallException for native code failures
+     *
+     */
+    public void setLocalDisplayName(@Nullable String displayName)
+        throws CallException
+    {
+        Log.i(TAG, "setLocalDisplayName():");
+
+        ringrtcSetLocalDisplayName(nativeCallManager, this.clientId, displayName);
+    }
+
+    /**
+     *
+     * Sets the hash of the avatar shared with the other devices in the call.
+     *
+     * @param avatarHash  the hash to share, or null to stop sharing one
+     *
+     * @throws CallException for native code failures
+     *
+     */
+    public void setLocalAvatarHash(@Nullable byte[] avatarHash)
+        throws CallException
+    {
+        Log.i(TAG, "setLocalAvatarHash():");
+
+        ringrtcSetLocalAvatarHash(nativeCallManager, this.clientId, avatarHash);
+    }
+
+    /**
+     *
+     * Changes how loud a remote device is heard. This only affects
+     * what the user hears; the remote device isn't told.
+     *
+     * @param demuxId  the demux ID of the remote device
+     * @param volume   0.0 is silent, 1.0 is unchanged, and up to 10.0 amplifies
+     *
+     * @throws CallException for native code failures
+     *
+     */
+    public void setRemoteAudioVolume(long demuxId, double volume)
+        throws CallException
+    {
+        Log.i(TAG, "setRemoteAudioVolume():");
+
+        ringrtcSetRemoteAudioVolume(nativeCallManager, this.clientId, demuxId, volume);
+    }
+
+    /**
+     *
+     * Silences a remote device without forgetting its volume. This only
+     * affects what the user hears; the remote device isn't told.
+     *
+     * @param demuxId  the demux ID of the remote device
+     * @param muted    whether to silence it
+     *
+     * @throws CallException for native code failures
+     *
+     */
+    public void setRemoteAudioMuted(long demuxId, boolean muted)
+        throws CallException
+    {
+        Log.i(TAG, "setRemoteAudioMuted():");
+
+        ringrtcSetRemoteAudioMuted(nativeCallManager, this.clientId, demuxId, muted);
+    }
+
     /**
      *
      * Sends a ring request to everyone in the group.
@@ -405,6 +516,43 @@ public final class GroupCall {
         ringrtcSetBandwidthMode(nativeCallManager, this.clientId, bandwidthMode.ordinal());
     }
 
+    /**
+     *
+     * Like setBandwidthMode, but with arbitrary limits.
+     *
+     * @param maxSendKbps     the most to send, for all media
+     * @param maxReceiveKbps  the most to ask the SFU to send, for all media
+     *
+     * @throws CallException for native code failures
+     *
+     */
+    public void setBandwidthPolicy(long maxSendKbps, long maxReceiveKbps)
+        throws CallException
+    {
+        Log.i(TAG, "setBandwidthPolicy():");
+
+        ringrtcSetBandwidthPolicy(nativeCallManager, this.clientId, maxSendKbps, maxReceiveKbps);
+    }
+
+    /**
+     *
+     * Tells the group call about the state of the device so it can
+     * send less when the device needs to conserve power.
+     *
+     * @param lowBattery         true if the battery is low or power saving is on
+     * @param thermalThrottling  true if the device is (or soon will be) throttled for heat
+     *
+     * @throws CallException for native code failures
+     *
+     */
+    public void setDeviceConditions(boolean lowBattery, boolean thermalThrottling)
+        throws CallException
+    {
+        Log.i(TAG, "setDeviceConditions():");
+
+        ringrtcSetDeviceConditions(nativeCallManager, this.clientId, lowBattery, thermalThrottling);
+    }
+
     /**
      *
      * Provides a collection of VideoRequest objects to the group call
@@ -429,6 +577,33 @@ public final class GroupCall {
                             activeSpeakerHeight);
     }
 
+    /**
+     *
+     * Describes the layout the remote videos are shown in, so that the
+     * group call object can decide which videos to request from the SFU
+     * as devices come and go and speakers change, instead of the
+     * application calling requestVideo each time. Each device's place in
+     * the layout is given by RemoteDeviceState.getVideoSlot().
+     *
+     * Calling requestVideo replaces the policy.
+     *
+     * @param slots          the parts of the layout, filled in order, or null to clear the policy
+     * @param pinnedDemuxId  the device to always put in the first slot, if any
+     *
+     * @throws CallException for native code failures
+     *
+     */
+    public void setVideoRequestPolicy(@Nullable Collection<VideoTileSlot> slots, @Nullable Long pinnedDemuxId)
+        throws CallException
+    {
+        Log.i(TAG, "setVideoRequestPolicy():");
+
+        ringrtcSetVideoRequestPolicy(nativeCallManager,
+                                     this.clientId,
+                                     slots == null ? null : new ArrayList<>(slots),
+                                     pinnedDemuxId);
+    }
+
     /**
      *
      * Provides a collection of GroupMemberInfo objects representing all
@@ -511,6 +686,17 @@ public final class GroupCall {
      * the CallManager.
      *
      */
+    void handleSpotlightChanged(@Nullable Long demuxId) {
+        Log.i(TAG, "handleSpotlightChanged():");
+
+        LocalDeviceState localDeviceState = new LocalDeviceState(this.localDeviceState);
+        localDeviceState.spotlightDemuxId = demuxId;
+
+        this.localDeviceState = localDeviceState;
+
+        this.observer.onLocalDeviceStateChanged(this);
+    }
+
     void handleJoinStateChanged(JoinState joinState) {
         Log.i(TAG, "handleJoinStateChanged():");
 
@@ -555,6 +741,17 @@ public final class GroupCall {
         this.observer.onAudioLevels(this);
     }
 
+    /**
+     *
+     * Callback from RingRTC when the user seems to be talking while muted.
+     *
+     */
+    void handleTalkingWhileMuted() {
+        Log.i(TAG, "handleTalkingWhileMuted():");
+
+        this.observer.onTalkingWhileMuted(this);
+    }
+
     /**
      *
      * Callback from RingRTC when the remote device states have changed.
@@ -762,6 +959,8 @@ public final class GroupCall {
         boolean         videoMuted;
         NetworkRoute    networkRoute;
         int             audioLevel;
+        // The device in the spotlight for everyone, which may be this one.
+        @Nullable Long  spotlightDemuxId;
 
         public LocalDeviceState() {
             this.connectionState = ConnectionState.NOT_CONNECTED;
@@ -779,6 +978,7 @@ public final class GroupCall {
             this.videoMuted = localDeviceState.videoMuted;
             this.networkRoute = localDeviceState.networkRoute;
             this.audioLevel = localDeviceState.audioLevel;
+            this.spotlightDemuxId = localDeviceState.spotlightDemuxId;
         }
 
         public ConnectionState getConnectionState() {
@@ -805,6 +1005,10 @@ public final class GroupCall {
         public int getAudioLevel() {
             return audioLevel;
         }
+
+        public @Nullable Long getSpotlightDemuxId() {
+            return spotlightDemuxId;
+        }
     }
 
     /**
@@ -829,6 +1033,14 @@ public final class GroupCall {
         long                 speakerTime; // unix millis; 0 if was never the speaker
         @Nullable Boolean    forwardingVideo;
                   boolean    isHigherResolutionPending;
+                  boolean    spotlighted;
+        // Sent by the remote device, so these are available even for users
+        // the application doesn't know.
+        @Nullable String     displayName;
+        @Nullable byte[]     avatarHash;
+        // Which tile of the video request policy this device is shown in,
+        // or null if it's offscreen or there is no policy.
+        @Nullable Long       videoSlot;
 
         @Nullable VideoTrack videoTrack;
         @NonNull  int        audioLevel;
@@ -843,7 +1055,11 @@ public final class GroupCall {
                                            long    addedTime,
                                            long    speakerTime,
                                  @Nullable Boolean forwardingVideo,
-                                           boolean isHigherResolutionPending) {
+                                           boolean isHigherResolutionPending,
+                                           boolean spotlighted,
+                                 @Nullable String  displayName,
+                                 @Nullable byte[]  avatarHash,
+                                 @Nullable Long    videoSlot) {
             this.demuxId = demuxId;
             this.userIdByteArray = userIdByteArray;
             this.mediaKeysReceived = mediaKeysReceived;
@@ -856,6 +1072,10 @@ public final class GroupCall {
             this.speakerTime = speakerTime;
             this.forwardingVideo = forwardingVideo;
             this.isHigherResolutionPending = isHigherResolutionPending;
+            this.spotlighted = spotlighted;
+            this.displayName = displayName;
+            this.avatarHash = avatarHash;
+            this.videoSlot = videoSlot;
             this.audioLevel = 0;
         }
 
@@ -905,6 +1125,25 @@ public final class GroupCall {
             return isHigherResolutionPending;
         }
 
+        // Whether some device in the call put this one in the spotlight for everyone.
+        public boolean isSpotlighted() {
+            return spotlighted;
+        }
+
+        public @Nullable String getDisplayName() {
+            return displayName;
+        }
+
+        public @Nullable byte[] getAvatarHash() {
+            return avatarHash;
+        }
+
+        // Tiles are counted across the policy's slots in order. Outside the
+        // spotlight, a device keeps its tile for as long as it is shown.
+        public @Nullable Long getVideoSlot() {
+            return videoSlot;
+        }
+
         public @Nullable VideoTrack getVideoTrack() {
             return videoTrack;
         }
@@ -964,6 +1203,43 @@ public final class GroupCall {
         }
     }
 
+    /**
+     *
+     * A part of the layout described to setVideoRequestPolicy.
+     *
+     */
+    public static class VideoTileSlot {
+        public enum Kind {
+            // One big tile, for the pinned device, else the spotlighted device,
+            // else the latest speaker.
+            SPOTLIGHT,
+            // A grid of count tiles of the same size.
+            GRID,
+            // A strip of count small tiles.
+            THUMBNAILS,
+            // Shown nowhere; slots after this one are never filled.
+            OFFSCREEN
+        }
+
+                  int     kind;
+                  int     count;     // UInt16
+                  int     width;     // UInt16
+                  int     height;    // UInt16
+        @Nullable Integer framerate; // UInt16
+
+        public VideoTileSlot(@NonNull  Kind    kind,
+                                       int     count,
+                                       int     width,
+                                       int     height,
+                             @Nullable Integer framerate) {
+            this.kind = kind.ordinal();
+            this.count = count;
+            this.width = width;
+            this.height = height;
+            this.framerate = framerate;
+        }
+    }
+
     /**
      * The client must provide an observer for each group call object
      * which is used to convey callbacks and notifications from
@@ -991,6 +1267,11 @@ public final class GroupCall {
          */
         void onAudioLevels(GroupCall groupCall);
 
+        /**
+         * Notification that the user seems to be talking while muted.
+         */
+        void onTalkingWhileMuted(GroupCall groupCall);
+
         /**
          * Notification that the remote device states have changed.
          */
@@ -1057,6 +1338,38 @@ public final class GroupCall {
                                           boolean muted)
         throws CallException;
 
+    private native
+        void ringrtcSetAudioOnly(long nativeCallManager,
+                                 long clientId,
+                                 boolean audioOnly)
+        throws CallException;
+
+    private native
+        void ringrtcSetLocalDisplayName(          long   nativeCallManager,
+                                                  long   clientId,
+                                        @Nullable String displayName)
+        throws CallException;
+
+    private native
+        void ringrtcSetLocalAvatarHash(          long   nativeCallManager,
+                                                 long   clientId,
+                                       @Nullable byte[] avatarHash)
+        throws CallException;
+
+    private native
+        void ringrtcSetRemoteAudioVolume(long   nativeCallManager,
+                                         long   clientId,
+                                         long   demuxId,
+                                         double volume)
+        throws CallException;
+
+    private native
+        void ringrtcSetRemoteAudioMuted(long    nativeCallManager,
+                                        long    clientId,
+                                        long    demuxId,
+                                        boolean muted)
+        throws CallException;
+
     private native
         void ringrtcRing(          long   nativeCallManager,
                                    long   clientId,
@@ -1074,6 +1387,20 @@ public final class GroupCall {
                                      int bandwidthMode)
         throws CallException;
 
+    private native
+        void ringrtcSetBandwidthPolicy(long nativeCallManager,
+                                       long clientId,
+                                       long maxSendKbps,
+                                       long maxReceiveKbps)
+        throws CallException;
+
+    private native
+        void ringrtcSetDeviceConditions(long nativeCallManager,
+                                        long clientId,
+                                        boolean lowBattery,
+                                        boolean thermalThrottling)
+        throws CallException;
+
     private native
         void ringrtcRequestVideo(long nativeCallManager,
                                  long clientId,
@@ -1081,6 +1408,19 @@ public final class GroupCall {
                                  int activeSpeakerHeight)
         throws CallException;
 
+    private native
+        void ringrtcSetSpotlight(long nativeCallManager,
+                                 long clientId,
+                                 Long demuxId)
+        throws CallException;
+
+    private native
+        void ringrtcSetVideoRequestPolicy(long nativeCallManager,
+                                          long clientId,
+                                          List<VideoTileSlot> slots,
+                                          Long pinnedDemuxId)
+        throws CallException;
+
     private native
         void ringrtcSetGroupMembers(long nativeCallManager,
                                     long clientId,
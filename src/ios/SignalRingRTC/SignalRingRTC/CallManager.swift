@@ -134,6 +134,8 @@ public enum BandwidthMode: Int32 {
     /// (Default) No specific constraints, but keep a relatively
     /// high bitrate to ensure good quality.
     case normal = 2
+    /// Intended for 1080p video on good networks.
+    case high = 3
 }
 
 /// Type of hangup message.
@@ -225,6 +227,12 @@ public protocol CallManagerDelegate: AnyObject {
      */
     func callManager(_ callManager: CallManager<CallManagerDelegateCallType, Self>, onAudioLevelsFor call: CallManagerDelegateCallType, capturedLevel: UInt16, receivedLevel: UInt16)
 
+    /**
+     * onTalkingWhileMutedFor will be invoked when the user seems to be talking while muted.
+     * Invoked on the main thread, asynchronously.
+     */
+    func callManager(_ callManager: CallManager<CallManagerDelegateCallType, Self>, onTalkingWhileMutedFor call: CallManagerDelegateCallType)
+
     /**
      * An Offer message should be sent to the given remote.
      * Invoked on the main thread, asynchronously.
@@ -543,6 +551,10 @@ public class CallManager<CallType, CallManagerDelegateType>: CallManagerInterfac
         let appCallContext: CallContext = Unmanaged.fromOpaque(callContext).takeUnretainedValue()
 
         appCallContext.setAudioEnabled(enabled: enabled)
+
+        if ringrtcSetAudioEnable(ringRtcCallManager, enabled) == nil {
+            owsFailDebug("ringrtcSetAudioEnable() function failure")
+        }
     }
 
     public func setLocalVideoEnabled(enabled: Bool, call: CallType) {
@@ -583,6 +595,62 @@ public class CallManager<CallType, CallManagerDelegateType>: CallManagerInterfac
         }
     }
 
+    /// Configures when the user is considered to be talking while muted,
+    /// for the active call, every group call, and those created later.
+    ///
+    /// - parameter minLevel: a captured audio level (0-32767) at or above this counts as talking
+    /// - parameter minDurationMillis: how long talking must go on before notifying
+    /// - parameter maxPauseMillis: quiet shorter than this (such as between words) doesn't end talking
+    /// - parameter minIntervalMillis: how long to wait before notifying again if talking goes on
+    public func setTalkingWhileMutedConfig(minLevel: UInt16, minDurationMillis: UInt64, maxPauseMillis: UInt64, minIntervalMillis: UInt64) {
+        AssertIsOnMainThread()
+        Logger.debug("setTalkingWhileMutedConfig")
+
+        if ringrtcSetTalkingWhileMutedConfig(ringRtcCallManager, minLevel, minDurationMillis, maxPauseMillis, minIntervalMillis) == nil {
+            owsFailDebug("ringrtcSetTalkingWhileMutedConfig() function failure")
+        }
+    }
+
+    /// Adds a step to the processing of video captured for group calls,
+    /// current and future: cropping around the center to an aspect ratio.
+    /// Direct calls aren't processed.
+    ///
+    /// - parameter width: width of the aspect ratio, such as 16 for 16:9
+    /// - parameter height: height of the aspect ratio, such as 9 for 16:9
+    public func addVideoFrameCropToAspectRatio(width: UInt32, height: UInt32) {
+        AssertIsOnMainThread()
+        Logger.debug("addVideoFrameCropToAspectRatio(\(width):\(height))")
+
+        if ringrtcAddVideoFrameCropToAspectRatio(ringRtcCallManager, width, height) == nil {
+            owsFailDebug("ringrtcAddVideoFrameCropToAspectRatio() function failure")
+        }
+    }
+
+    /// Adds a step to the processing of video captured for group calls,
+    /// current and future: scaling to a fixed size.
+    /// Direct calls aren't processed.
+    ///
+    /// - parameter width: width to scale to, in pixels
+    /// - parameter height: height to scale to, in pixels
+    public func addVideoFrameScale(width: UInt32, height: UInt32) {
+        AssertIsOnMainThread()
+        Logger.debug("addVideoFrameScale(\(width)x\(height))")
+
+        if ringrtcAddVideoFrameScale(ringRtcCallManager, width, height) == nil {
+            owsFailDebug("ringrtcAddVideoFrameScale() function failure")
+        }
+    }
+
+    /// Removes every step added to the processing of video captured for group calls.
+    public func clearVideoFrameProcessors() {
+        AssertIsOnMainThread()
+        Logger.debug("clearVideoFrameProcessors")
+
+        if ringrtcClearVideoFrameProcessors(ringRtcCallManager) == nil {
+            owsFailDebug("ringrtcClearVideoFrameProcessors() function failure")
+        }
+    }
+
     public func udpateBandwidthMode(bandwidthMode: BandwidthMode) {
         AssertIsOnMainThread()
         Logger.debug("udpateBandwidthMode(\(bandwidthMode))")
@@ -590,6 +658,17 @@ public class CallManager<CallType, CallManagerDelegateType>: CallManagerInterfac
         ringrtcUpdateBandwidthMode(ringRtcCallManager, bandwidthMode.rawValue)
     }
 
+    /// Like udpateBandwidthMode, but with arbitrary limits.
+    ///
+    /// - parameter maxSendKbps: the most to send, for all media
+    /// - parameter maxReceiveKbps: the most to ask the other side to send, for all media
+    public func updateBandwidthPolicy(maxSendKbps: UInt64, maxReceiveKbps: UInt64) {
+        AssertIsOnMainThread()
+        Logger.debug("updateBandwidthPolicy(\(maxSendKbps), \(maxReceiveKbps))")
+
+        ringrtcUpdateBandwidthPolicy(ringRtcCallManager, maxSendKbps, maxReceiveKbps)
+    }
+
     // MARK: - Signaling API
     public func receivedOffer<CallType: CallManagerCallReference>(call: CallType, sourceDevice: UInt32, callId: UInt64, opaque: Data, messageAgeSec: UInt64, callMediaType: CallMediaType, localDevice: UInt32, isLocalDevicePrimary: Bool, senderIdentityKey: Data, receiverIdentityKey: Data) throws {
         AssertIsOnMainThread()
@@ -793,6 +872,19 @@ public class CallManager<CallType, CallManagerDelegateType>: CallManagerInterfac
         delegate.callManager(self, onAudioLevelsFor: callReference, capturedLevel: capturedLevel, receivedLevel: receivedLevel)
     }
 
+    func onTalkingWhileMutedFor(remote: UnsafeRawPointer) {
+        Logger.debug("onTalkingWhileMuted")
+
+        DispatchQueue.main.async {
+            Logger.debug("onTalkingWhileMuted - main.async")
+
+            guard let delegate = self.delegate else { return }
+
+            let callReference: CallType = Unmanaged.fromOpaque(remote).takeUnretainedValue()
+            delegate.callManager(self, onTalkingWhileMutedFor: callReference)
+        }
+    }
+
     // MARK: - Signaling Observers
 
     func onSendOffer(callId: UInt64, remote: UnsafeRawPointer, destinationDeviceId: UInt32?, opaque: Data, callMediaType: CallMediaType) {
@@ -1057,6 +1149,30 @@ public class CallManager<CallType, CallManagerDelegateType>: CallManagerInterfac
         }
     }
 
+    func handleTalkingWhileMuted(clientId: UInt32) {
+        Logger.debug("handleTalkingWhileMuted")
+
+        DispatchQueue.main.async {
+            guard let groupCall = self.groupCallByClientId[clientId] else {
+                return
+            }
+
+            groupCall.handleTalkingWhileMuted()
+        }
+    }
+
+    func handleSpotlightChanged(clientId: UInt32, demuxId: UInt32?) {
+        Logger.debug("handleSpotlightChanged")
+
+        DispatchQueue.main.async {
+            guard let groupCall = self.groupCallByClientId[clientId] else {
+                return
+            }
+
+            groupCall.handleSpotlightChanged(demuxId: demuxId)
+        }
+    }
+
     func handleJoinStateChanged(clientId: UInt32, joinState: JoinState) {
         Logger.debug("handleJoinStateChanged")
 
@@ -13,6 +13,7 @@ protocol CallManagerInterfaceDelegate: AnyObject {
     func onEvent(remote: UnsafeRawPointer, event: CallManagerEvent)
     func onNetworkRouteChangedFor(remote: UnsafeRawPointer, networkRoute: NetworkRoute)
     func onAudioLevelsFor(remote: UnsafeRawPointer, capturedLevel: UInt16, receivedLevel: UInt16)
+    func onTalkingWhileMutedFor(remote: UnsafeRawPointer)
     func onSendOffer(callId: UInt64, remote: UnsafeRawPointer, destinationDeviceId: UInt32?, opaque: Data, callMediaType: CallMediaType)
     func onSendAnswer(callId: UInt64, remote: UnsafeRawPointer, destinationDeviceId: UInt32?, opaque: Data)
     func onSendIceCandidates(callId: UInt64, remote: UnsafeRawPointer, destinationDeviceId: UInt32?, candidates: [Data])
@@ -34,6 +35,8 @@ protocol CallManagerInterfaceDelegate: AnyObject {
     func handleConnectionStateChanged(clientId: UInt32, connectionState: ConnectionState)
     func handleNetworkRouteChanged(clientId: UInt32, networkRoute: NetworkRoute)
     func handleAudioLevels(clientId: UInt32, capturedLevel: UInt16, receivedLevels: [ReceivedAudioLevel])
+    func handleTalkingWhileMuted(clientId: UInt32)
+    func handleSpotlightChanged(clientId: UInt32, demuxId: UInt32?)
     func handleJoinStateChanged(clientId: UInt32, joinState: JoinState)
     func handleRemoteDevicesChanged(clientId: UInt32, remoteDeviceStates: [RemoteDeviceState])
     func handleIncomingVideoTrack(clientId: UInt32, remoteDemuxId: UInt32, nativeVideoTrackBorrowedRc: UnsafeMutableRawPointer?)
@@ -66,6 +69,7 @@ class CallManagerInterface {
             onEvent: callManagerInterfaceOnCallEvent,
             onNetworkRouteChanged: callManagerInterfaceOnNetworkRouteChanged,
             onAudioLevels: callManagerInterfaceOnAudioLevels,
+            onTalkingWhileMuted: callManagerInterfaceOnTalkingWhileMuted,
             onSendOffer: callManagerInterfaceOnSendOffer,
             onSendAnswer: callManagerInterfaceOnSendAnswer,
             onSendIceCandidates: callManagerInterfaceOnSendIceCandidates,
@@ -88,6 +92,8 @@ class CallManagerInterface {
             handleConnectionStateChanged: callManagerInterfaceHandleConnectionStateChanged,
             handleNetworkRouteChanged: callManagerInterfaceHandleNetworkRouteChanged,
             handleAudioLevels: callManagerInterfaceHandleAudioLevels,
+            handleTalkingWhileMuted: callManagerInterfaceHandleTalkingWhileMuted,
+            handleSpotlightChanged: callManagerInterfaceHandleSpotlightChanged,
             handleJoinStateChanged: callManagerInterfaceHandleJoinStateChanged,
             handleRemoteDevicesChanged: callManagerInterfaceHandleRemoteDevicesChanged,
             handleIncomingVideoTrack: callManagerInterfaceHandleIncomingVideoTrack,
@@ -140,6 +146,14 @@ class CallManagerInterface {
         delegate.onAudioLevelsFor(remote: remote, capturedLevel: capturedLevel, receivedLevel: receivedLevel)
     }
 
+    func onTalkingWhileMutedFor(remote: UnsafeRawPointer) {
+        guard let delegate = self.callManagerObserverDelegate else {
+            return
+        }
+
+        delegate.onTalkingWhileMutedFor(remote: remote)
+    }
+
     func onSendOffer(callId: UInt64, remote: UnsafeRawPointer, destinationDeviceId: UInt32?, opaque: Data, callMediaType: CallMediaType) {
         guard let delegate = self.callManagerObserverDelegate else {
             return
@@ -278,6 +292,22 @@ class CallManagerInterface {
         delegate.handleAudioLevels(clientId: clientId, capturedLevel: capturedLevel, receivedLevels: receivedLevels)
     }
 
+    func handleTalkingWhileMuted(clientId: UInt32) {
+        guard let delegate = self.callManagerObserverDelegate else {
+            return
+        }
+
+        delegate.handleTalkingWhileMuted(clientId: clientId)
+    }
+
+    func handleSpotlightChanged(clientId: UInt32, demuxId: UInt32?) {
+        guard let delegate = self.callManagerObserverDelegate else {
+            return
+        }
+
+        delegate.handleSpotlightChanged(clientId: clientId, demuxId: demuxId)
+    }
+
     func handleJoinStateChanged(clientId: UInt32, joinState: JoinState) {
         guard let delegate = self.callManagerObserverDelegate else {
             return
@@ -403,6 +433,22 @@ func callManagerInterfaceOnAudioLevels(object: UnsafeMutableRawPointer?, remote:
     obj.onAudioLevelsFor(remote: remote, capturedLevel: capturedLevel, receivedLevel: receivedLevel)
 }
 
+@available(iOSApplicationExtension, unavailable)
+func callManagerInterfaceOnTalkingWhileMuted(object: UnsafeMutableRawPointer?, remote: UnsafeRawPointer?) {
+    guard let object = object else {
+        owsFailDebug("object was unexpectedly nil")
+        return
+    }
+    let obj: CallManagerInterface = Unmanaged.fromOpaque(object).takeUnretainedValue()
+
+    guard let remote = remote else {
+        owsFailDebug("remote was unexpectedly nil")
+        return
+    }
+
+    obj.onTalkingWhileMutedFor(remote: remote)
+}
+
 @available(iOSApplicationExtension, unavailable)
 func callManagerInterfaceOnSendOffer(object: UnsafeMutableRawPointer?, callId: UInt64, remote: UnsafeRawPointer?, destinationDeviceId: UInt32, broadcast: Bool, opaque: AppByteSlice, mediaType: Int32) {
     guard let object = object else {
@@ -830,6 +876,28 @@ func callManagerInterfaceHandleAudioLevels(object: UnsafeMutableRawPointer?, cli
     obj.handleAudioLevels(clientId: clientId, capturedLevel: capturedLevel, receivedLevels: finalReceivedLevels)
 }
 
+@available(iOSApplicationExtension, unavailable)
+func callManagerInterfaceHandleTalkingWhileMuted(object: UnsafeMutableRawPointer?, clientId: UInt32) {
+    guard let object = object else {
+        owsFailDebug("object was unexpectedly nil")
+        return
+    }
+    let obj: CallManagerInterface = Unmanaged.fromOpaque(object).takeUnretainedValue()
+
+    obj.handleTalkingWhileMuted(clientId: clientId)
+}
+
+@available(iOSApplicationExtension, unavailable)
+func callManagerInterfaceHandleSpotlightChanged(object: UnsafeMutableRawPointer?, clientId: UInt32, demuxId: AppOptionalUInt32) {
+    guard let object = object else {
+        owsFailDebug("object was unexpectedly nil")
+        return
+    }
+    let obj: CallManagerInterface = Unmanaged.fromOpaque(object).takeUnretainedValue()
+
+    obj.handleSpotlightChanged(clientId: clientId, demuxId: demuxId.valid ? demuxId.value : nil)
+}
+
 @available(iOSApplicationExtension, unavailable)
 func callManagerInterfaceHandleJoinStateChanged(object: UnsafeMutableRawPointer?, clientId: UInt32, joinState: Int32) {
     guard let object = object else {
@@ -876,6 +944,8 @@ func callManagerInterfaceHandleRemoteDevicesChanged(object: UnsafeMutableRawPoin
             isHigherResolutionPending: remoteDeviceState.isHigherResolutionPending
         )
 
+        deviceState.spotlighted = remoteDeviceState.spotlighted
+
         if remoteDeviceState.audioMuted.valid {
             deviceState.audioMuted = remoteDeviceState.audioMuted.value
         }
@@ -896,6 +966,13 @@ func callManagerInterfaceHandleRemoteDevicesChanged(object: UnsafeMutableRawPoin
             deviceState.forwardingVideo = remoteDeviceState.forwardingVideo.value
         }
 
+        if remoteDeviceState.videoSlot.valid {
+            deviceState.videoSlot = remoteDeviceState.videoSlot.value
+        }
+
+        deviceState.displayName = remoteDeviceState.displayName.asString()
+        deviceState.avatarHash = remoteDeviceState.avatarHash.asData()
+
         finalRemoteDeviceStates.append(deviceState)
     }
 
@@ -53,6 +53,8 @@ public class LocalDeviceState {
     public internal(set) var networkRoute: NetworkRoute
     public internal(set) var joinState: JoinState
     public internal(set) var audioLevel: UInt16
+    /// The device in the spotlight for everyone, which may be this one.
+    public internal(set) var spotlightDemuxId: UInt32?
 
     init() {
         self.connectionState = .notConnected
@@ -88,7 +90,16 @@ public class RemoteDeviceState: Hashable {
     public internal(set) var speakerTime: UInt64  // unix millis; 0 if they've never spoken
     public internal(set) var forwardingVideo: Bool?
     public internal(set) var isHigherResolutionPending: Bool
+    // Whether some device in the call put this one in the spotlight for everyone.
+    public internal(set) var spotlighted = false
     public internal(set) var audioLevel: UInt16
+    // Sent by the remote device, so these are available even for users the app doesn't know.
+    public internal(set) var displayName: String?
+    public internal(set) var avatarHash: Data?
+    // Which tile of the VideoRequestPolicy this device is shown in, counting the tiles of its
+    // slots in order.  Outside the spotlight, a device keeps its tile for as long as it is shown.
+    // Nil if it's offscreen or there is no policy.
+    public internal(set) var videoSlot: UInt32?
 
     public internal(set) var videoTrack: RTCVideoTrack?
 
@@ -129,6 +140,55 @@ public struct VideoRequest {
     }
 }
 
+/// A part of the layout described by a VideoRequestPolicy.
+@available(iOSApplicationExtension, unavailable)
+public enum VideoTileSlot {
+    /// One big tile, for the pinned device, else the spotlighted device, else the latest speaker.
+    case spotlight(width: UInt16, height: UInt16, framerate: UInt16?)
+    /// A grid of `count` tiles of the same size.
+    case grid(count: UInt16, width: UInt16, height: UInt16, framerate: UInt16?)
+    /// A strip of `count` small tiles.
+    case thumbnails(count: UInt16, width: UInt16, height: UInt16, framerate: UInt16?)
+    /// Shown nowhere; slots after this one are never filled.
+    case offscreen
+
+    func toApp() -> AppVideoTileSlot {
+        let appFramerate = { (framerate: UInt16?) -> AppOptionalUInt16 in
+            if let framerate = framerate {
+                return AppOptionalUInt16(value: framerate, valid: true)
+            } else {
+                return AppOptionalUInt16(value: 0, valid: false)
+            }
+        }
+
+        switch self {
+        case let .spotlight(width, height, framerate):
+            return AppVideoTileSlot(kind: 0, count: 1, width: width, height: height, framerate: appFramerate(framerate))
+        case let .grid(count, width, height, framerate):
+            return AppVideoTileSlot(kind: 1, count: count, width: width, height: height, framerate: appFramerate(framerate))
+        case let .thumbnails(count, width, height, framerate):
+            return AppVideoTileSlot(kind: 2, count: count, width: width, height: height, framerate: appFramerate(framerate))
+        case .offscreen:
+            return AppVideoTileSlot(kind: 3, count: 0, width: 0, height: 0, framerate: appFramerate(nil))
+        }
+    }
+}
+
+/// Describes the layout the remote videos are shown in, so the group call
+/// can decide which videos to request as devices come and go and speakers change.
+@available(iOSApplicationExtension, unavailable)
+public struct VideoRequestPolicy {
+    /// Filled in order.
+    let slots: [VideoTileSlot]
+    /// Goes in the first slot regardless of who is speaking or in the spotlight.
+    let pinnedDemuxId: UInt32?
+
+    public init(slots: [VideoTileSlot], pinnedDemuxId: UInt32?) {
+        self.slots = slots
+        self.pinnedDemuxId = pinnedDemuxId
+    }
+}
+
 /// The group call observer.
 @available(iOSApplicationExtension, unavailable)
 public protocol GroupCallDelegate: AnyObject {
@@ -161,6 +221,11 @@ public protocol GroupCallDelegate: AnyObject {
      */
     func groupCall(onAudioLevels groupCall: GroupCall)
 
+    /**
+     * Indication that the user seems to be talking while muted.
+     */
+    func groupCall(onTalkingWhileMuted groupCall: GroupCall)
+
     /**
      * Indication that the application can retrieve an updated PeekInfo which
      * includes a list of users that are actively in the group call.
@@ -391,6 +456,122 @@ public class GroupCall {
         }
     }
 
+    /// Switches to (or back from) audio-only. Unlike BandwidthMode.veryLow, this
+    /// stops receiving and decoding video, stops sending video, and tells the other devices.
+    ///
+    /// - parameter audioOnly: true to switch to audio-only
+    public func setAudioOnly(_ audioOnly: Bool) {
+        AssertIsOnMainThread()
+        Logger.debug("setAudioOnly")
+
+        guard let clientId = self.clientId else {
+            Logger.warn("no clientId defined for groupCall")
+            return
+        }
+
+        ringrtcSetAudioOnly(self.ringRtcCallManager, clientId, audioOnly)
+    }
+
+    /// Puts a device (which may be this one) in the spotlight for everyone in the call,
+    /// or takes it out. RingRTC doesn't check who is allowed to do this; that's up to the app.
+    ///
+    /// - parameter demuxId: the device to put in the spotlight, or nil to clear it
+    public func setSpotlight(demuxId: UInt32?) {
+        AssertIsOnMainThread()
+        Logger.debug("setSpotlight")
+
+        guard let clientId = self.clientId else {
+            Logger.warn("no clientId defined for groupCall")
+            return
+        }
+
+        ringrtcSetSpotlight(self.ringRtcCallManager, clientId, AppOptionalUInt32(value: demuxId ?? 0, valid: demuxId != nil))
+    }
+
+    /// Sets the name shared with the other devices in the call so they
+    /// can label this one, even if they don't know the user.
+    ///
+    /// - parameter displayName: the name to share, or nil to stop sharing one
+    public func setLocalDisplayName(displayName: String?) {
+        AssertIsOnMainThread()
+        Logger.debug("setLocalDisplayName")
+
+        guard let clientId = self.clientId else {
+            Logger.warn("no clientId defined for groupCall")
+            return
+        }
+
+        let displayNameSlice = allocatedAppByteSliceFromString(maybe_string: displayName)
+
+        // Make sure to release the allocated memory when the function exists,
+        // to ensure that the pointers are still valid when used in the RingRTC
+        // API function.
+        defer {
+            if displayNameSlice.bytes != nil {
+                displayNameSlice.bytes.deallocate()
+            }
+        }
+
+        ringrtcSetLocalDisplayName(self.ringRtcCallManager, clientId, displayNameSlice)
+    }
+
+    /// Sets the hash of the avatar shared with the other devices in the call.
+    ///
+    /// - parameter avatarHash: the hash to share, or nil to stop sharing one
+    public func setLocalAvatarHash(avatarHash: Data?) {
+        AssertIsOnMainThread()
+        Logger.debug("setLocalAvatarHash")
+
+        guard let clientId = self.clientId else {
+            Logger.warn("no clientId defined for groupCall")
+            return
+        }
+
+        let avatarHashSlice = allocatedAppByteSliceFromData(maybe_data: avatarHash)
+
+        // Make sure to release the allocated memory when the function exists,
+        // to ensure that the pointers are still valid when used in the RingRTC
+        // API function.
+        defer {
+            if avatarHashSlice.bytes != nil {
+                avatarHashSlice.bytes.deallocate()
+            }
+        }
+
+        ringrtcSetLocalAvatarHash(self.ringRtcCallManager, clientId, avatarHashSlice)
+    }
+
+    /// Changes how loud a remote device is heard. This only affects
+    /// what the user hears; the remote device isn't told.
+    ///
+    /// - parameter demuxId: the demux ID of the remote device
+    /// - parameter volume: 0.0 is silent, 1.0 is unchanged, and up to 10.0 amplifies
+    public func setRemoteAudioVolume(demuxId: UInt32, volume: Double) {
+        AssertIsOnMainThread()
+        Logger.debug("setRemoteAudioVolume")
+
+        guard let clientId = self.clientId else {
+            Logger.warn("no clientId defined for groupCall")
+            return
+        }
+
+        ringrtcSetRemoteAudioVolume(self.ringRtcCallManager, clientId, demuxId, volume)
+    }
+
+    /// Silences a remote device without forgetting its volume. This only
+    /// affects what the user hears; the remote device isn't told.
+    public func setRemoteAudioMuted(demuxId: UInt32, muted: Bool) {
+        AssertIsOnMainThread()
+        Logger.debug("setRemoteAudioMuted")
+
+        guard let clientId = self.clientId else {
+            Logger.warn("no clientId defined for groupCall")
+            return
+        }
+
+        ringrtcSetRemoteAudioMuted(self.ringRtcCallManager, clientId, demuxId, muted)
+    }
+
     public func ringAll() {
         AssertIsOnMainThread()
         Logger.debug("ring")
@@ -427,6 +608,39 @@ public class GroupCall {
         ringrtcSetBandwidthMode(self.ringRtcCallManager, clientId, bandwidthMode.rawValue)
     }
 
+    /// Like updateBandwidthMode, but with arbitrary limits.
+    ///
+    /// - parameter maxSendKbps: the most to send, for all media
+    /// - parameter maxReceiveKbps: the most to ask the SFU to send, for all media
+    public func updateBandwidthPolicy(maxSendKbps: UInt64, maxReceiveKbps: UInt64) {
+        AssertIsOnMainThread()
+        Logger.debug("updateBandwidthPolicy")
+
+        guard let clientId = self.clientId else {
+            Logger.warn("no clientId defined for groupCall")
+            return
+        }
+
+        ringrtcSetBandwidthPolicy(self.ringRtcCallManager, clientId, maxSendKbps, maxReceiveKbps)
+    }
+
+    /// Tells the group call about the state of the device so it can send
+    /// less when the device needs to conserve power.
+    ///
+    /// - parameter lowBattery: true if the battery is low or Low Power Mode is on
+    /// - parameter thermalThrottling: true if the thermal state is serious or critical
+    public func updateDeviceConditions(lowBattery: Bool, thermalThrottling: Bool) {
+        AssertIsOnMainThread()
+        Logger.debug("updateDeviceConditions")
+
+        guard let clientId = self.clientId else {
+            Logger.warn("no clientId defined for groupCall")
+            return
+        }
+
+        ringrtcSetDeviceConditions(self.ringRtcCallManager, clientId, lowBattery, thermalThrottling)
+    }
+
     /// Provides a collection of VideoRequest objects to the group call
     /// object which are sent to the SFU. This allows the appropriate
     /// video resolution to be sent from the SFU to efficiently fit in
@@ -464,6 +678,38 @@ public class GroupCall {
         ringrtcRequestVideo(self.ringRtcCallManager, clientId, &appResolutionArray, activeSpeakerHeight)
     }
 
+    /// Provides a description of the layout so the group call object can
+    /// send the video requests to the SFU itself as devices come and go and
+    /// speakers change. Each device's place is given by RemoteDeviceState.videoSlot.
+    /// Calling updateVideoRequests replaces the policy.
+    ///
+    /// - parameter policy: the layout, or nil to clear the policy
+    public func updateVideoRequestPolicy(_ policy: VideoRequestPolicy?) {
+        AssertIsOnMainThread()
+        Logger.debug("updateVideoRequestPolicy")
+
+        guard let clientId = self.clientId else {
+            Logger.warn("no clientId defined for groupCall")
+            return
+        }
+
+        guard let policy = policy else {
+            ringrtcSetVideoRequestPolicy(self.ringRtcCallManager, clientId, nil, AppOptionalUInt32(value: 0, valid: false))
+            return
+        }
+
+        let appSlots = policy.slots.map { $0.toApp() }
+        let appPinnedDemuxId = AppOptionalUInt32(value: policy.pinnedDemuxId ?? 0, valid: policy.pinnedDemuxId != nil)
+
+        appSlots.withUnsafeBufferPointer { appSlotsBytes in
+            var appSlotArray = AppVideoTileSlotArray(
+                slots: appSlotsBytes.baseAddress,
+                count: appSlots.count
+            )
+            ringrtcSetVideoRequestPolicy(self.ringRtcCallManager, clientId, &appSlotArray, appPinnedDemuxId)
+        }
+    }
+
     public func updateGroupMembers(members: [GroupMember]) {
         AssertIsOnMainThread()
         Logger.debug("updateGroupMembers")
@@ -571,6 +817,20 @@ public class GroupCall {
         self.delegate?.groupCall(onAudioLevels: self)
     }
 
+    func handleTalkingWhileMuted() {
+        AssertIsOnMainThread()
+
+        self.delegate?.groupCall(onTalkingWhileMuted: self)
+    }
+
+    func handleSpotlightChanged(demuxId: UInt32?) {
+        AssertIsOnMainThread()
+
+        self.localDeviceState.spotlightDemuxId = demuxId
+
+        self.delegate?.groupCall(onLocalDeviceStateChanged: self)
+    }
+
     func handleJoinStateChanged(joinState: JoinState) {
        AssertIsOnMainThread()
 
@@ -338,6 +338,10 @@ final class TestDelegate: CallManagerDelegate & HTTPDelegate {
         Logger.debug("TestDelegate:onAudioLevelsFor - \(capturedLevel) \(receivedLevel)")
     }
 
+    func callManager(_ callManager: CallManager<OpaqueCallData, TestDelegate>, onTalkingWhileMutedFor call: OpaqueCallData) {
+        Logger.debug("TestDelegate:onTalkingWhileMutedFor")
+    }
+
     func callManager(_ callManager: CallManager<OpaqueCallData, TestDelegate>, shouldSendOffer callId: UInt64, call: OpaqueCallData, destinationDeviceId: UInt32?, opaque: Data, callMediaType: CallMediaType) {
         Logger.debug("TestDelegate:shouldSendOffer")
         generalInvocationDetected = true
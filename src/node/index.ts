@@ -32,10 +32,14 @@ export {
   RingCancelReason,
   RingRTCType,
   RingUpdate,
+  TalkingWhileMutedConfig,
   UserId,
   VideoCapturer,
   VideoRenderer,
   VideoRequest,
+  VideoRequestPolicy,
+  VideoTileSlot,
+  VideoTileSlotKind,
 } from './ringrtc/Service';
 
 export {
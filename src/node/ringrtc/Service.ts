@@ -63,6 +63,8 @@ class NativeCallManager {
   Native.cm_signalingMessageSendFailed;
 (NativeCallManager.prototype as any).updateBandwidthMode =
   Native.cm_updateBandwidthMode;
+(NativeCallManager.prototype as any).updateBandwidthPolicy =
+  Native.cm_updateBandwidthPolicy;
 (NativeCallManager.prototype as any).receivedOffer = Native.cm_receivedOffer;
 (NativeCallManager.prototype as any).receivedAnswer = Native.cm_receivedAnswer;
 (NativeCallManager.prototype as any).receivedIceCandidates =
@@ -79,6 +81,14 @@ class NativeCallManager {
   Native.cm_setOutgoingAudioEnabled;
 (NativeCallManager.prototype as any).setOutgoingVideoEnabled =
   Native.cm_setOutgoingVideoEnabled;
+(NativeCallManager.prototype as any).setTalkingWhileMutedConfig =
+  Native.cm_setTalkingWhileMutedConfig;
+(NativeCallManager.prototype as any).addVideoFrameCropToAspectRatio =
+  Native.cm_addVideoFrameCropToAspectRatio;
+(NativeCallManager.prototype as any).addVideoFrameScale =
+  Native.cm_addVideoFrameScale;
+(NativeCallManager.prototype as any).clearVideoFrameProcessors =
+  Native.cm_clearVideoFrameProcessors;
 (NativeCallManager.prototype as any).setOutgoingVideoIsScreenShare =
   Native.cm_setOutgoingVideoIsScreenShare;
 (NativeCallManager.prototype as any).sendVideoFrame = Native.cm_sendVideoFrame;
@@ -99,14 +109,34 @@ class NativeCallManager {
   Native.cm_setOutgoingAudioMuted;
 (NativeCallManager.prototype as any).setOutgoingVideoMuted =
   Native.cm_setOutgoingVideoMuted;
+(NativeCallManager.prototype as any).setAudioOnly = Native.cm_setAudioOnly;
+(NativeCallManager.prototype as any).setIncomingAudioEnabled =
+  Native.cm_setIncomingAudioEnabled;
+(NativeCallManager.prototype as any).receiveGroupCallAudio =
+  Native.cm_receiveGroupCallAudio;
 (NativeCallManager.prototype as any).setOutgoingGroupCallVideoIsScreenShare =
   Native.cm_setOutgoingGroupCallVideoIsScreenShare;
 (NativeCallManager.prototype as any).setPresenting = Native.cm_setPresenting;
+(NativeCallManager.prototype as any).setSpotlight = Native.cm_setSpotlight;
+(NativeCallManager.prototype as any).setLocalDisplayName =
+  Native.cm_setLocalDisplayName;
+(NativeCallManager.prototype as any).setLocalAvatarHash =
+  Native.cm_setLocalAvatarHash;
+(NativeCallManager.prototype as any).setRemoteAudioVolume =
+  Native.cm_setRemoteAudioVolume;
+(NativeCallManager.prototype as any).setRemoteAudioMuted =
+  Native.cm_setRemoteAudioMuted;
 (NativeCallManager.prototype as any).resendMediaKeys =
   Native.cm_resendMediaKeys;
 (NativeCallManager.prototype as any).setBandwidthMode =
   Native.cm_setBandwidthMode;
+(NativeCallManager.prototype as any).setBandwidthPolicy =
+  Native.cm_setBandwidthPolicy;
+(NativeCallManager.prototype as any).setDeviceConditions =
+  Native.cm_setDeviceConditions;
 (NativeCallManager.prototype as any).requestVideo = Native.cm_requestVideo;
+(NativeCallManager.prototype as any).setVideoRequestPolicy =
+  Native.cm_setVideoRequestPolicy;
 (NativeCallManager.prototype as any).setGroupMembers =
   Native.cm_setGroupMembers;
 (NativeCallManager.prototype as any).setMembershipProof =
@@ -573,6 +603,18 @@ export class RingRTCType {
     }
   }
 
+  // Called by Rust
+  onTalkingWhileMuted(remoteUserId: UserId): void {
+    const call = this._call;
+    if (!call || call.remoteUserId !== remoteUserId) {
+      return;
+    }
+
+    if (call.handleTalkingWhileMuted) {
+      call.handleTalkingWhileMuted();
+    }
+  }
+
   renderVideoFrame(width: number, height: number, buffer: Buffer): void {
     const call = this._call;
     if (!call) {
@@ -887,6 +929,29 @@ export class RingRTCType {
     });
   }
 
+  // Called by Rust
+  handleTalkingWhileMuted(clientId: GroupCallClientId): void {
+    silly_deadlock_protection(() => {
+      let groupCall = this._groupCallByClientId.get(clientId);
+      if (!!groupCall) {
+        groupCall.handleTalkingWhileMuted();
+      }
+    });
+  }
+
+  // Called by Rust
+  handleSpotlightChanged(
+    clientId: GroupCallClientId,
+    demuxId: number | undefined
+  ): void {
+    silly_deadlock_protection(() => {
+      let groupCall = this._groupCallByClientId.get(clientId);
+      if (!!groupCall) {
+        groupCall.handleSpotlightChanged(demuxId);
+      }
+    });
+  }
+
   // Called by Rust
   handleRemoteDevicesChanged(
     clientId: GroupCallClientId,
@@ -1309,6 +1374,47 @@ export class RingRTCType {
   setAudioOutput(index: number): void {
     this.callManager.setAudioOutput(index);
   }
+
+  // Called by UX
+  // Applies to the current call, every group call, and those created later.
+  setTalkingWhileMutedConfig(config: TalkingWhileMutedConfig): void {
+    this.callManager.setTalkingWhileMutedConfig(
+      config.minLevel,
+      config.minDurationMillis,
+      config.maxPauseMillis,
+      config.minIntervalMillis
+    );
+  }
+
+  // Called by UX
+  // Adds a step to the processing of sent video frames: cropping around
+  // the center to an aspect ratio such as 16:9.
+  addVideoFrameCropToAspectRatio(width: number, height: number): void {
+    this.callManager.addVideoFrameCropToAspectRatio(width, height);
+  }
+
+  // Called by UX
+  // Adds a step to the processing of sent video frames: scaling to a fixed size.
+  addVideoFrameScale(width: number, height: number): void {
+    this.callManager.addVideoFrameScale(width, height);
+  }
+
+  // Called by UX
+  clearVideoFrameProcessors(): void {
+    this.callManager.clearVideoFrameProcessors();
+  }
+}
+
+// When to tell the user they seem to be talking while muted.
+export interface TalkingWhileMutedConfig {
+  // A captured audio level at or above this counts as talking.
+  minLevel: RawAudioLevel;
+  // How long talking must go on before notifying.
+  minDurationMillis: number;
+  // Quiet shorter than this (such as between words) doesn't end talking.
+  maxPauseMillis: number;
+  // How long to wait before notifying again if talking goes on.
+  minIntervalMillis: number;
 }
 
 export interface CallSettings {
@@ -1378,6 +1484,7 @@ export class Call {
   handleRemoteSharingScreen?: () => void;
   handleNetworkRouteChanged?: () => void;
   handleAudioLevels?: () => void;
+  handleTalkingWhileMuted?: () => void;
 
   // This callback should be set by the VideoCapturer,
   // But could also be set by the UX.
@@ -1520,9 +1627,12 @@ export class Call {
   }
 
   // With this method, a Call is a VideoFrameSource
-  receiveVideoFrame(buffer: Buffer): [number, number] | undefined {
+  receiveVideoFrame(
+    buffer: Buffer,
+    format?: VideoPixelFormatEnum
+  ): [number, number] | undefined {
     // This assumes we only have one active all.
-    return this._callManager.receiveVideoFrame(buffer);
+    return this._callManager.receiveVideoFrame(buffer, format);
   }
 
   private enableOrDisableCapturer(): void {
@@ -1582,6 +1692,18 @@ export class Call {
     });
   }
 
+  // Like updateBandwidthMode, but with arbitrary limits.
+  updateBandwidthPolicy(maxSendKbps: number, maxReceiveKbps: number) {
+    silly_deadlock_protection(() => {
+      try {
+        this._callManager.updateBandwidthPolicy(maxSendKbps, maxReceiveKbps);
+      } catch {
+        // We may not have an active connection any more.
+        // In which case it doesn't matter
+      }
+    });
+  }
+
   private enableOrDisableRenderer(): void {
     if (!this._videoRenderer) {
       return;
@@ -1689,6 +1811,8 @@ export class LocalDeviceState {
   presenting: boolean;
   sharingScreen: boolean;
   networkRoute: NetworkRoute;
+  // The device in the spotlight for everyone, which may be this one (see demuxId).
+  spotlightDemuxId: number | undefined;
 
   constructor() {
     this.connectionState = ConnectionState.NotConnected;
@@ -1719,6 +1843,15 @@ export class RemoteDeviceState {
   speakerTime: string | undefined; // unix millis; 0 if they've never spoken (to be converted to a numeric type)
   forwardingVideo: boolean | undefined;
   isHigherResolutionPending: boolean;
+  // Whether some device in the call put this one in the spotlight for everyone.
+  spotlighted: boolean;
+  // Sent by the remote device, so it's available even for users the app doesn't know.
+  displayName: string | undefined;
+  avatarHash: Buffer | undefined;
+  // Which tile of the VideoRequestPolicy this device is shown in, counting the tiles
+  // of its slots in order. Outside the spotlight, a device keeps its tile for as long
+  // as it is shown. Undefined if it's offscreen or there is no policy.
+  videoSlot: number | undefined;
 
   constructor(demuxId: number, userId: Buffer, mediaKeysReceived: boolean) {
     this.demuxId = demuxId;
@@ -1726,6 +1859,7 @@ export class RemoteDeviceState {
     this.mediaKeysReceived = mediaKeysReceived;
     this.audioLevel = 0;
     this.isHigherResolutionPending = false;
+    this.spotlighted = false;
   }
 }
 
@@ -1761,12 +1895,42 @@ export class VideoRequest {
   }
 }
 
+export enum VideoTileSlotKind {
+  // One big tile, for the pinned device, else the spotlighted device, else the latest speaker.
+  Spotlight = 0,
+  // A grid of `count` tiles of the same size.
+  Grid = 1,
+  // A strip of `count` small tiles.
+  Thumbnails = 2,
+  // Shown nowhere; slots after this one are never filled.
+  Offscreen = 3,
+}
+
+// A part of the layout described by a VideoRequestPolicy.
+export interface VideoTileSlot {
+  kind: VideoTileSlotKind;
+  count: number; // UInt16; ignored for Spotlight and Offscreen
+  width: number; // UInt16
+  height: number; // UInt16
+  framerate: number | undefined; // UInt16
+}
+
+// Describes the layout the remote videos are shown in, so the group call can
+// decide which videos to request as devices come and go and speakers change.
+export interface VideoRequestPolicy {
+  // Filled in order.
+  slots: Array<VideoTileSlot>;
+  // Goes in the first slot regardless of who is speaking or in the spotlight.
+  pinnedDemuxId: number | undefined; // UInt32
+}
+
 export interface GroupCallObserver {
   requestMembershipProof(groupCall: GroupCall): void;
   requestGroupMembers(groupCall: GroupCall): void;
   onLocalDeviceStateChanged(groupCall: GroupCall): void;
   onRemoteDeviceStatesChanged(groupCall: GroupCall): void;
   onAudioLevels(groupCall: GroupCall): void;
+  onTalkingWhileMuted(groupCall: GroupCall): void;
   onPeekChanged(groupCall: GroupCall): void;
   onEnded(groupCall: GroupCall, reason: GroupCallEndReason): void;
 }
@@ -1856,6 +2020,35 @@ export class GroupCall {
     this._observer.onLocalDeviceStateChanged(this);
   }
 
+  // Called by UI
+  // Unlike BandwidthMode.VeryLow, this stops receiving and decoding video,
+  // stops sending video, and tells the other devices.
+  setAudioOnly(audioOnly: boolean): void {
+    this._callManager.setAudioOnly(this._clientId, audioOnly);
+  }
+
+  // Called by UI
+  // While enabled, the decoded audio of each remote device is kept (up to a second of it)
+  // for receiveAudio, such as for live captions.  It's still played out as well.
+  setIncomingAudioEnabled(enabled: boolean): void {
+    this._callManager.setIncomingAudioEnabled(this._clientId, enabled);
+  }
+
+  // Called by UI
+  // Copies the audio received from the remote device since the last call into the buffer,
+  // as interleaved 16-bit little-endian samples.  Returns [sampleRate, channels, sampleCount],
+  // or undefined if there's nothing new.
+  receiveAudio(
+    remoteDemuxId: number, // Uint32
+    buffer: Buffer
+  ): [number, number, number] | undefined {
+    return this._callManager.receiveGroupCallAudio(
+      this._clientId,
+      remoteDemuxId,
+      buffer
+    );
+  }
+
   // Called by UI
   setPresenting(presenting: boolean): void {
     this._localDeviceState.presenting = presenting;
@@ -1863,6 +2056,37 @@ export class GroupCall {
     this._observer.onLocalDeviceStateChanged(this);
   }
 
+  // Called by UI
+  // Puts a device (which may be this one) in the spotlight for everyone in the call,
+  // or takes it out with undefined.  RingRTC doesn't check who is allowed to do this.
+  setSpotlight(demuxId: number | undefined): void {
+    this._callManager.setSpotlight(this._clientId, demuxId);
+  }
+
+  // Called by UI
+  // Shared with the other devices in the call so they can label this one.
+  setLocalDisplayName(displayName: string | undefined): void {
+    this._callManager.setLocalDisplayName(this._clientId, displayName);
+  }
+
+  // Called by UI
+  setLocalAvatarHash(avatarHash: Buffer | undefined): void {
+    this._callManager.setLocalAvatarHash(this._clientId, avatarHash);
+  }
+
+  // Called by UI
+  // A volume of 0 is silent, 1 is unchanged, and up to 10 amplifies the remote device.
+  // Only affects what we hear; the remote device isn't told.
+  setRemoteAudioVolume(demuxId: number, volume: number): void {
+    this._callManager.setRemoteAudioVolume(this._clientId, demuxId, volume);
+  }
+
+  // Called by UI
+  // Silences the remote device without forgetting its volume.
+  setRemoteAudioMuted(demuxId: number, muted: boolean): void {
+    this._callManager.setRemoteAudioMuted(this._clientId, demuxId, muted);
+  }
+
   // Called by UI
   setOutgoingVideoIsScreenShare(isScreenShare: boolean): void {
     this._localDeviceState.sharingScreen = isScreenShare;
@@ -1888,11 +2112,40 @@ export class GroupCall {
     this._callManager.setBandwidthMode(this._clientId, bandwidthMode);
   }
 
+  // Called by UI
+  // Like setBandwidthMode, but with arbitrary limits.
+  setBandwidthPolicy(maxSendKbps: number, maxReceiveKbps: number): void {
+    this._callManager.setBandwidthPolicy(
+      this._clientId,
+      maxSendKbps,
+      maxReceiveKbps
+    );
+  }
+
+  // Called by UI
+  setDeviceConditions(lowBattery: boolean, thermalThrottling: boolean): void {
+    this._callManager.setDeviceConditions(
+      this._clientId,
+      lowBattery,
+      thermalThrottling
+    );
+  }
+
   // Called by UI
   requestVideo(resolutions: Array<VideoRequest>, activeSpeakerHeight: number): void {
     this._callManager.requestVideo(this._clientId, resolutions, activeSpeakerHeight);
   }
 
+  // Called by UI
+  // Replaced by requestVideo; pass undefined to clear it.
+  setVideoRequestPolicy(policy: VideoRequestPolicy | undefined): void {
+    this._callManager.setVideoRequestPolicy(
+      this._clientId,
+      policy?.slots,
+      policy?.pinnedDemuxId
+    );
+  }
+
   // Called by UI
   setGroupMembers(members: Array<GroupMemberInfo>): void {
     this._callManager.setGroupMembers(this._clientId, members);
@@ -1961,6 +2214,17 @@ export class GroupCall {
     this._observer.onAudioLevels(this);
   }
 
+  // Called by Rust via RingRTC object
+  handleTalkingWhileMuted(): void {
+    this._observer.onTalkingWhileMuted(this);
+  }
+
+  // Called by Rust via RingRTC object
+  handleSpotlightChanged(demuxId: number | undefined): void {
+    this._localDeviceState.spotlightDemuxId = demuxId;
+    this._observer.onLocalDeviceStateChanged(this);
+  }
+
   // Called by Rust via RingRTC object
   handleRemoteDevicesChanged(
     remoteDeviceStates: Array<RemoteDeviceState>
@@ -2040,12 +2304,16 @@ class GroupCallVideoFrameSource {
     this._remoteDemuxId = remoteDemuxId;
   }
 
-  receiveVideoFrame(buffer: Buffer): [number, number] | undefined {
+  receiveVideoFrame(
+    buffer: Buffer,
+    format?: VideoPixelFormatEnum
+  ): [number, number] | undefined {
     // This assumes we only have one active all.
     const frame = this._callManager.receiveGroupCallVideoFrame(
       this._groupCall.clientId,
       this._remoteDemuxId,
-      buffer
+      buffer,
+      format
     );
     if (!!frame) {
       const [width, height] = frame;
@@ -2139,6 +2407,7 @@ export enum BandwidthMode {
   VeryLow = 0,
   Low = 1,
   Normal = 2,
+  High = 3,
 }
 
 /// Describes why a ring was cancelled.
@@ -2178,15 +2447,28 @@ export interface CallManager {
   signalingMessageSendFailed(callId: CallId): void;
   setOutgoingAudioEnabled(enabled: boolean): void;
   setOutgoingVideoEnabled(enabled: boolean): void;
+  setTalkingWhileMutedConfig(
+    minLevel: RawAudioLevel,
+    minDurationMillis: number,
+    maxPauseMillis: number,
+    minIntervalMillis: number
+  ): void;
+  addVideoFrameCropToAspectRatio(width: number, height: number): void;
+  addVideoFrameScale(width: number, height: number): void;
+  clearVideoFrameProcessors(): void;
   setOutgoingVideoIsScreenShare(enabled: boolean): void;
   updateBandwidthMode(bandwidthMode: BandwidthMode): void;
+  updateBandwidthPolicy(maxSendKbps: number, maxReceiveKbps: number): void;
   sendVideoFrame(
     width: number,
     height: number,
     format: VideoPixelFormatEnum,
     buffer: Buffer
   ): void;
-  receiveVideoFrame(buffer: Buffer): [number, number] | undefined;
+  receiveVideoFrame(
+    buffer: Buffer,
+    format?: VideoPixelFormatEnum
+  ): [number, number] | undefined;
   receivedOffer(
     remoteUserId: UserId,
     remoteDeviceId: DeviceId,
@@ -2250,7 +2532,33 @@ export interface CallManager {
   disconnect(clientId: GroupCallClientId): void;
   setOutgoingAudioMuted(clientId: GroupCallClientId, muted: boolean): void;
   setOutgoingVideoMuted(clientId: GroupCallClientId, muted: boolean): void;
+  setAudioOnly(clientId: GroupCallClientId, audioOnly: boolean): void;
+  setIncomingAudioEnabled(clientId: GroupCallClientId, enabled: boolean): void;
+  receiveGroupCallAudio(
+    clientId: GroupCallClientId,
+    remoteDemuxId: number,
+    buffer: Buffer
+  ): [number, number, number] | undefined;
   setPresenting(clientId: GroupCallClientId, presenting: boolean): void;
+  setSpotlight(clientId: GroupCallClientId, demuxId: number | undefined): void;
+  setLocalDisplayName(
+    clientId: GroupCallClientId,
+    displayName: string | undefined
+  ): void;
+  setLocalAvatarHash(
+    clientId: GroupCallClientId,
+    avatarHash: Buffer | undefined
+  ): void;
+  setRemoteAudioVolume(
+    clientId: GroupCallClientId,
+    demuxId: number,
+    volume: number
+  ): void;
+  setRemoteAudioMuted(
+    clientId: GroupCallClientId,
+    demuxId: number,
+    muted: boolean
+  ): void;
   setOutgoingGroupCallVideoIsScreenShare(
     clientId: GroupCallClientId,
     isScreenShare: boolean
@@ -2261,11 +2569,26 @@ export interface CallManager {
     clientId: GroupCallClientId,
     bandwidthMode: BandwidthMode
   ): void;
+  setBandwidthPolicy(
+    clientId: GroupCallClientId,
+    maxSendKbps: number,
+    maxReceiveKbps: number
+  ): void;
+  setDeviceConditions(
+    clientId: GroupCallClientId,
+    lowBattery: boolean,
+    thermalThrottling: boolean
+  ): void;
   requestVideo(
     clientId: GroupCallClientId,
     resolutions: Array<VideoRequest>,
     activeSpeakerHeight: number
   ): void;
+  setVideoRequestPolicy(
+    clientId: GroupCallClientId,
+    slots: Array<VideoTileSlot> | undefined,
+    pinnedDemuxId: number | undefined
+  ): void;
   setGroupMembers(
     clientId: GroupCallClientId,
     members: Array<GroupMemberInfo>
@@ -2275,7 +2598,8 @@ export interface CallManager {
   receiveGroupCallVideoFrame(
     clientId: GroupCallClientId,
     remoteDemuxId: number,
-    buffer: Buffer
+    buffer: Buffer,
+    format?: VideoPixelFormatEnum
   ): [number, number] | undefined;
   // Response comes back via handlePeekResponse
   peekGroupCall(
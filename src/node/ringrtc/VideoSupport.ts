@@ -16,11 +16,15 @@ export enum VideoPixelFormatEnum {
   I420 = 0,
   Nv12 = 1,
   Rgba = 2,
+  Bgra = 3,
+  Argb = 4,
+  I444 = 5,
+  P010 = 6,
 }
 
 function videoPixelFormatFromEnum(
   format: VideoPixelFormatEnum
-): VideoPixelFormat {
+): VideoPixelFormat | undefined {
   switch (format) {
     case VideoPixelFormatEnum.I420: {
       return 'I420';
@@ -31,6 +35,17 @@ function videoPixelFormatFromEnum(
     case VideoPixelFormatEnum.Rgba: {
       return 'RGBA';
     }
+    case VideoPixelFormatEnum.Bgra: {
+      return 'BGRA';
+    }
+    case VideoPixelFormatEnum.I444: {
+      return 'I444';
+    }
+    // WebCodecs doesn't have these.
+    case VideoPixelFormatEnum.Argb:
+    case VideoPixelFormatEnum.P010: {
+      return undefined;
+    }
   }
 }
 
@@ -47,6 +62,12 @@ function videoPixelFormatToEnum(
     case 'RGBA': {
       return VideoPixelFormatEnum.Rgba;
     }
+    case 'BGRA': {
+      return VideoPixelFormatEnum.Bgra;
+    }
+    case 'I444': {
+      return VideoPixelFormatEnum.I444;
+    }
   }
 }
 
@@ -55,7 +76,11 @@ export interface VideoFrameSource {
   // Fills in the given buffer and returns the width x height
   // or returns undefined if nothing was filled in because no
   // video frame was available.
-  receiveVideoFrame(buffer: Buffer): [number, number] | undefined;
+  // The buffer is filled in as RGBA unless another format is given.
+  receiveVideoFrame(
+    buffer: Buffer,
+    format?: VideoPixelFormatEnum
+  ): [number, number] | undefined;
 }
 
 // Sends frames (after getting them from something like GumVideoCapturer, for example).
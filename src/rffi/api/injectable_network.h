@@ -18,6 +18,8 @@ namespace rffi {
 typedef struct {
   void* object_owned;
   int (*SendUdp)(void* object_borrowed, IpPort source, IpPort dest, const uint8_t* data_borrowed, size_t);
+  // Each call is one whole message written to the TCP connection.
+  int (*SendTcp)(void* object_borrowed, IpPort source, IpPort dest, const uint8_t* data_borrowed, size_t);
   int (*Delete)(void* object_owned);
 } InjectableNetworkSender;
 
@@ -40,6 +42,8 @@ class InjectableNetwork {
   virtual void RemoveInterface(const char* name) = 0;
   virtual void ReceiveUdp(
     IpPort source, IpPort dest, const uint8_t* data, size_t size) = 0;
+  virtual void ReceiveTcp(
+    IpPort source, IpPort dest, const uint8_t* data, size_t size) = 0;
 
   // These are more for internal use, not external, which is why the types
   // aren't the external types.
@@ -49,6 +53,12 @@ class InjectableNetwork {
     const uint8_t* data,
     size_t size) = 0;
   virtual void ForgetUdp(const rtc::SocketAddress& local_address) = 0;
+  virtual int SendTcp(
+    const rtc::SocketAddress& local_address,
+    const rtc::SocketAddress& remote_address,
+    const uint8_t* data,
+    size_t size) = 0;
+  virtual void ForgetTcp(const rtc::SocketAddress& local_address) = 0;
 };
 
 std::unique_ptr<InjectableNetwork> CreateInjectableNetwork(rtc::Thread* network_thread);
@@ -75,6 +85,13 @@ RUSTEXPORT void Rust_InjectableNetwork_ReceiveUdp(
   const uint8_t* data_borrowed,
   size_t size);
 
+RUSTEXPORT void Rust_InjectableNetwork_ReceiveTcp(
+  InjectableNetwork* network_borrowed,
+  IpPort source,
+  IpPort dest,
+  const uint8_t* data_borrowed,
+  size_t size);
+
 }  // namespace rffi
 
 }  // namespace webrtc
@@ -17,6 +17,18 @@ typedef struct {
   webrtc::VideoRotation rotation;
 } RffiVideoFrameMetadata;
 
+// Passed to Rust_createProcessedVideoSource.
+typedef struct {
+  // Returns an owned RC of the buffer to send in place of the given one,
+  // or nullptr to drop the frame.  Returning the given buffer sends the
+  // frame as it was, rotation and all.
+  webrtc::VideoFrameBuffer* (*processFrame)(void* processor_borrowed,
+                                            RffiVideoFrameMetadata metadata,
+                                            webrtc::VideoFrameBuffer* buffer_borrowed_rc);
+  // Called once the processor won't be used again.
+  void (*deleteProcessor)(void* processor_owned);
+} RffiVideoFrameProcessorCallbacks;
+
 namespace webrtc {
 namespace rffi {
 
@@ -38,6 +50,24 @@ class VideoSource : public VideoTrackSource {
   rtc::VideoBroadcaster broadcaster_;
 };
 
+// A VideoSource whose frames come from another track (such as one fed by
+// a platform's camera capturer), each passed through a processor first.
+class ProcessedVideoSource : public VideoSource, public rtc::VideoSinkInterface<webrtc::VideoFrame> {
+ public:
+  ProcessedVideoSource(rtc::scoped_refptr<VideoTrackInterface> input,
+                       void* processor_owned,
+                       RffiVideoFrameProcessorCallbacks callbacks);
+  ~ProcessedVideoSource() override;
+
+  // Called by the input track.
+  void OnFrame(const webrtc::VideoFrame& frame) override;
+
+ private:
+  rtc::scoped_refptr<VideoTrackInterface> input_;
+  void* processor_;
+  RffiVideoFrameProcessorCallbacks callbacks_;
+};
+
 } // namespace rffi
 } // namespace webrtc
 
@@ -80,6 +110,10 @@ RUSTEXPORT webrtc::VideoFrameBuffer* Rust_copyVideoFrameBufferFromRgba(
 RUSTEXPORT void Rust_convertVideoFrameBufferToRgba(
   const webrtc::VideoFrameBuffer* buffer, uint8_t* rgba_out);
 
+// Any => I420, packed without padding (Y, then U, then V)
+RUSTEXPORT void Rust_convertVideoFrameBufferToI420(
+  const webrtc::VideoFrameBuffer* buffer, uint8_t* i420_out);
+
 // RGBA => I420
 RUSTEXPORT webrtc::VideoFrameBuffer* Rust_copyAndRotateVideoFrameBuffer(
     const webrtc::VideoFrameBuffer* buffer_borrowed_rc, webrtc::VideoRotation rotation);
@@ -9,6 +9,7 @@
 #include "rffi/api/peer_connection_intf.h"
 
 #include "rffi/api/injectable_network.h"
+#include "rffi/api/media.h"
 #include "rtc_base/ref_count.h"
 
 namespace rtc {
@@ -52,6 +53,9 @@ namespace webrtc {
     virtual bool SetAudioRecordingDevice(uint16_t index) {
       return false;
     }
+    // Sent instead of what the recording device captures, for as long as samples keep being pushed.
+    virtual void PushOutgoingAudioSamples(uint32_t sample_rate, size_t channels, const int16_t* samples, size_t samples_size) {
+    }
   };
 
   namespace rffi {
@@ -69,9 +73,11 @@ typedef struct {
 // Returns an owned RC.
 // You can create more than one, but you should probably only have one unless
 // you want to test separate endpoints that are as independent as possible.
+// With use_injectable_audio, Rust_pushOutgoingAudioSamples works; otherwise it drops samples.
 RUSTEXPORT webrtc::PeerConnectionFactoryOwner* Rust_createPeerConnectionFactory(
   bool use_new_audio_device_module, 
-  bool use_injectable_network);
+  bool use_injectable_network,
+  bool use_injectable_audio);
 
 // Returns an owned RC.
 RUSTEXPORT webrtc::PeerConnectionFactoryOwner* Rust_createPeerConnectionFactoryWrapper(
@@ -94,9 +100,24 @@ RUSTEXPORT webrtc::PeerConnectionInterface* Rust_createPeerConnection(
 RUSTEXPORT webrtc::AudioTrackInterface* Rust_createAudioTrack(
   webrtc::PeerConnectionFactoryOwner* factory_owner_borrowed_rc);
 
+// Samples are interleaved if there is more than one channel.
+RUSTEXPORT void Rust_pushOutgoingAudioSamples(
+  webrtc::PeerConnectionFactoryOwner* factory_owner_borrowed_rc,
+  uint32_t sample_rate,
+  size_t channels,
+  const int16_t* samples_borrowed,
+  size_t samples_size);
+
 // Returns an owned RC.
 RUSTEXPORT webrtc::VideoTrackSourceInterface* Rust_createVideoSource();
 
+// Takes ownership of the processor, which is deleted with callbacks_borrowed->deleteProcessor.
+// Returns an owned RC.
+RUSTEXPORT webrtc::VideoTrackSourceInterface* Rust_createProcessedVideoSource(
+  webrtc::VideoTrackInterface* input_track_borrowed_rc,
+  void* processor_owned,
+  const RffiVideoFrameProcessorCallbacks* callbacks_borrowed);
+
 // Returns an owned RC.
 RUSTEXPORT webrtc::VideoTrackInterface* Rust_createVideoTrack(
   webrtc::PeerConnectionFactoryOwner* factory_owner_borrowed_rc,
@@ -91,6 +91,13 @@ typedef struct {
   size_t salt_len;
 } RffiSrtpKey;
 
+// Applied to an outgoing video sender as RtpEncodingParameters.
+typedef struct {
+  double scale_resolution_down_by;
+  uint32_t max_bitrate_bps;
+  bool active;
+} RffiVideoEncodingLayer;
+
 // Returns an owned pointer.
 RUSTEXPORT RffiConnectionParametersV4*
 Rust_sessionDescriptionToV4(const webrtc::SessionDescriptionInterface* session_description_borrowed);
@@ -118,6 +125,22 @@ RUSTEXPORT bool
 Rust_setIncomingMediaEnabled(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc,
                              bool                             enabled);
 
+RUSTEXPORT void
+Rust_setOutgoingVideoEnabled(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc,
+                             bool                             enabled);
+
+RUSTEXPORT void
+Rust_setIncomingVideoEnabled(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc,
+                             bool                             enabled);
+
+RUSTEXPORT void
+Rust_setIncomingAudioFramesEnabled(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc,
+                                   bool                             enabled);
+
+RUSTEXPORT void
+Rust_setIncomingVideoFrameContentEnabled(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc,
+                                         bool                             enabled);
+
 RUSTEXPORT void
 Rust_setAudioPlayoutEnabled(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc,
                             bool                             enabled);
@@ -180,4 +203,19 @@ Rust_getAudioLevels(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc
                     size_t received_out_size,
                     size_t* received_size_out);
 
+// Returns false if there is no audio receiver for the demux ID.
+RUSTEXPORT bool
+Rust_setRemoteAudioVolume(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc,
+                          uint32_t demux_id,
+                          double volume);
+
+// An empty list of layers goes back to the default encodings.
+// Returns false if the layers couldn't be applied to every video sender
+// or if there are more than 3 layers.
+RUSTEXPORT bool
+Rust_setVideoEncodingLayers(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc,
+                            bool svc,
+                            const RffiVideoEncodingLayer* layers_borrowed,
+                            size_t layers_len);
+
 #endif /* RFFI_API_PEER_CONNECTION_INTF_H__ */
@@ -46,6 +46,9 @@ typedef struct {
   void (*onAddAudioRtpReceiver)(void* observer_borrowed, webrtc::MediaStreamTrackInterface* track_owned_rc);
   void (*onAddVideoRtpReceiver)(void* observer_borrowed, webrtc::MediaStreamTrackInterface* track_owned_rc);
   void (*onVideoFrame)(void* observer_borrowed, uint32_t track_id, RffiVideoFrameMetadata metadata, webrtc::VideoFrameBuffer* frame_buffer_borrowed);
+  // Only called while enabled with Rust_setIncomingAudioFramesEnabled.
+  // Warning: this runs on the WebRTC audio thread, so it must not block.
+  void (*onAudioFrame)(void* observer_borrowed, uint32_t track_id, uint32_t sample_rate, size_t channels, const int16_t* samples_borrowed, size_t samples_len);
 
   // RTP data events
   // Warning: this runs on the WebRTC network thread, so doing anything that
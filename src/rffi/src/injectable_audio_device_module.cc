@@ -0,0 +1,385 @@
+/*
+ * Copyright 2022 Signal Messenger, LLC
+ * SPDX-License-Identifier: AGPL-3.0-only
+ */
+
+#include "rffi/src/injectable_audio_device_module.h"
+
+#include "rtc_base/logging.h"
+#include "rtc_base/time_utils.h"
+
+namespace webrtc {
+namespace rffi {
+
+// How long after the last pushed samples the recording device is used again.
+const int64_t kPushedSamplesTimeoutMs = 100;
+
+InjectableAudioDeviceModule::InjectableAudioDeviceModule(rtc::scoped_refptr<AudioDeviceModule> device_adm)
+  : device_adm_(std::move(device_adm)) {
+}
+
+void InjectableAudioDeviceModule::PushRecordedSamples(uint32_t sample_rate,
+                                                      size_t channels,
+                                                      const int16_t* samples,
+                                                      size_t samples_size) {
+  if (channels == 0 || samples_size % channels != 0) {
+    RTC_LOG(LS_WARNING) << "Dropping pushed audio samples with " << channels << " channels and " << samples_size << " samples";
+    return;
+  }
+
+  AudioTransport* transport;
+  {
+    MutexLock lock(&mutex_);
+    last_pushed_ms_ = rtc::TimeMillis();
+    transport = transport_;
+  }
+  if (!transport) {
+    return;
+  }
+  uint32_t new_mic_level = 0;
+  transport->RecordedDataIsAvailable(samples,
+                                     samples_size / channels,
+                                     sizeof(int16_t) * channels,
+                                     channels,
+                                     sample_rate,
+                                     /* total_delay_ms */ 0,
+                                     /* clock_drift */ 0,
+                                     /* current_mic_level */ 0,
+                                     /* key_pressed */ false,
+                                     new_mic_level);
+}
+
+AudioTransport* InjectableAudioDeviceModule::CurrentTransport() {
+  MutexLock lock(&mutex_);
+  return transport_;
+}
+
+int32_t InjectableAudioDeviceModule::RegisterAudioCallback(AudioTransport* transport) {
+  {
+    MutexLock lock(&mutex_);
+    transport_ = transport;
+  }
+  // The device gives us what it records so that we can ignore it while samples are being pushed.
+  return device_adm_->RegisterAudioCallback(transport ? this : nullptr);
+}
+
+int32_t InjectableAudioDeviceModule::ActiveAudioLayer(AudioLayer* audio_layer) const {
+  return device_adm_->ActiveAudioLayer(audio_layer);
+}
+
+int32_t InjectableAudioDeviceModule::Init() {
+  return device_adm_->Init();
+}
+
+int32_t InjectableAudioDeviceModule::Terminate() {
+  return device_adm_->Terminate();
+}
+
+bool InjectableAudioDeviceModule::Initialized() const {
+  return device_adm_->Initialized();
+}
+
+int16_t InjectableAudioDeviceModule::PlayoutDevices() {
+  return device_adm_->PlayoutDevices();
+}
+
+int16_t InjectableAudioDeviceModule::RecordingDevices() {
+  return device_adm_->RecordingDevices();
+}
+
+int32_t InjectableAudioDeviceModule::PlayoutDeviceName(uint16_t index, char name[kAdmMaxDeviceNameSize], char guid[kAdmMaxGuidSize]) {
+  return device_adm_->PlayoutDeviceName(index, name, guid);
+}
+
+int32_t InjectableAudioDeviceModule::RecordingDeviceName(uint16_t index, char name[kAdmMaxDeviceNameSize], char guid[kAdmMaxGuidSize]) {
+  return device_adm_->RecordingDeviceName(index, name, guid);
+}
+
+int32_t InjectableAudioDeviceModule::SetPlayoutDevice(uint16_t index) {
+  return device_adm_->SetPlayoutDevice(index);
+}
+
+int32_t InjectableAudioDeviceModule::SetPlayoutDevice(WindowsDeviceType device) {
+  return device_adm_->SetPlayoutDevice(device);
+}
+
+int32_t InjectableAudioDeviceModule::SetRecordingDevice(uint16_t index) {
+  return device_adm_->SetRecordingDevice(index);
+}
+
+int32_t InjectableAudioDeviceModule::SetRecordingDevice(WindowsDeviceType device) {
+  return device_adm_->SetRecordingDevice(device);
+}
+
+int32_t InjectableAudioDeviceModule::PlayoutIsAvailable(bool* available) {
+  return device_adm_->PlayoutIsAvailable(available);
+}
+
+int32_t InjectableAudioDeviceModule::InitPlayout() {
+  return device_adm_->InitPlayout();
+}
+
+bool InjectableAudioDeviceModule::PlayoutIsInitialized() const {
+  return device_adm_->PlayoutIsInitialized();
+}
+
+int32_t InjectableAudioDeviceModule::RecordingIsAvailable(bool* available) {
+  return device_adm_->RecordingIsAvailable(available);
+}
+
+int32_t InjectableAudioDeviceModule::InitRecording() {
+  return device_adm_->InitRecording();
+}
+
+bool InjectableAudioDeviceModule::RecordingIsInitialized() const {
+  return device_adm_->RecordingIsInitialized();
+}
+
+int32_t InjectableAudioDeviceModule::StartPlayout() {
+  return device_adm_->StartPlayout();
+}
+
+int32_t InjectableAudioDeviceModule::StopPlayout() {
+  return device_adm_->StopPlayout();
+}
+
+bool InjectableAudioDeviceModule::Playing() const {
+  return device_adm_->Playing();
+}
+
+int32_t InjectableAudioDeviceModule::StartRecording() {
+  return device_adm_->StartRecording();
+}
+
+int32_t InjectableAudioDeviceModule::StopRecording() {
+  return device_adm_->StopRecording();
+}
+
+bool InjectableAudioDeviceModule::Recording() const {
+  return device_adm_->Recording();
+}
+
+int32_t InjectableAudioDeviceModule::InitSpeaker() {
+  return device_adm_->InitSpeaker();
+}
+
+bool InjectableAudioDeviceModule::SpeakerIsInitialized() const {
+  return device_adm_->SpeakerIsInitialized();
+}
+
+int32_t InjectableAudioDeviceModule::InitMicrophone() {
+  return device_adm_->InitMicrophone();
+}
+
+bool InjectableAudioDeviceModule::MicrophoneIsInitialized() const {
+  return device_adm_->MicrophoneIsInitialized();
+}
+
+int32_t InjectableAudioDeviceModule::SpeakerVolumeIsAvailable(bool* available) {
+  return device_adm_->SpeakerVolumeIsAvailable(available);
+}
+
+int32_t InjectableAudioDeviceModule::SetSpeakerVolume(uint32_t volume) {
+  return device_adm_->SetSpeakerVolume(volume);
+}
+
+int32_t InjectableAudioDeviceModule::SpeakerVolume(uint32_t* volume) const {
+  return device_adm_->SpeakerVolume(volume);
+}
+
+int32_t InjectableAudioDeviceModule::MaxSpeakerVolume(uint32_t* max_volume) const {
+  return device_adm_->MaxSpeakerVolume(max_volume);
+}
+
+int32_t InjectableAudioDeviceModule::MinSpeakerVolume(uint32_t* min_volume) const {
+  return device_adm_->MinSpeakerVolume(min_volume);
+}
+
+int32_t InjectableAudioDeviceModule::MicrophoneVolumeIsAvailable(bool* available) {
+  return device_adm_->MicrophoneVolumeIsAvailable(available);
+}
+
+int32_t InjectableAudioDeviceModule::SetMicrophoneVolume(uint32_t volume) {
+  return device_adm_->SetMicrophoneVolume(volume);
+}
+
+int32_t InjectableAudioDeviceModule::MicrophoneVolume(uint32_t* volume) const {
+  return device_adm_->MicrophoneVolume(volume);
+}
+
+int32_t InjectableAudioDeviceModule::MaxMicrophoneVolume(uint32_t* max_volume) const {
+  return device_adm_->MaxMicrophoneVolume(max_volume);
+}
+
+int32_t InjectableAudioDeviceModule::MinMicrophoneVolume(uint32_t* min_volume) const {
+  return device_adm_->MinMicrophoneVolume(min_volume);
+}
+
+int32_t InjectableAudioDeviceModule::SpeakerMuteIsAvailable(bool* available) {
+  return device_adm_->SpeakerMuteIsAvailable(available);
+}
+
+int32_t InjectableAudioDeviceModule::SetSpeakerMute(bool enable) {
+  return device_adm_->SetSpeakerMute(enable);
+}
+
+int32_t InjectableAudioDeviceModule::SpeakerMute(bool* enabled) const {
+  return device_adm_->SpeakerMute(enabled);
+}
+
+int32_t InjectableAudioDeviceModule::MicrophoneMuteIsAvailable(bool* available) {
+  return device_adm_->MicrophoneMuteIsAvailable(available);
+}
+
+int32_t InjectableAudioDeviceModule::SetMicrophoneMute(bool enable) {
+  return device_adm_->SetMicrophoneMute(enable);
+}
+
+int32_t InjectableAudioDeviceModule::MicrophoneMute(bool* enabled) const {
+  return device_adm_->MicrophoneMute(enabled);
+}
+
+int32_t InjectableAudioDeviceModule::StereoPlayoutIsAvailable(bool* available) const {
+  return device_adm_->StereoPlayoutIsAvailable(available);
+}
+
+int32_t InjectableAudioDeviceModule::SetStereoPlayout(bool enable) {
+  return device_adm_->SetStereoPlayout(enable);
+}
+
+int32_t InjectableAudioDeviceModule::StereoPlayout(bool* enabled) const {
+  return device_adm_->StereoPlayout(enabled);
+}
+
+int32_t InjectableAudioDeviceModule::StereoRecordingIsAvailable(bool* available) const {
+  return device_adm_->StereoRecordingIsAvailable(available);
+}
+
+int32_t InjectableAudioDeviceModule::SetStereoRecording(bool enable) {
+  return device_adm_->SetStereoRecording(enable);
+}
+
+int32_t InjectableAudioDeviceModule::StereoRecording(bool* enabled) const {
+  return device_adm_->StereoRecording(enabled);
+}
+
+int32_t InjectableAudioDeviceModule::PlayoutDelay(uint16_t* delay_ms) const {
+  return device_adm_->PlayoutDelay(delay_ms);
+}
+
+bool InjectableAudioDeviceModule::BuiltInAECIsAvailable() const {
+  return device_adm_->BuiltInAECIsAvailable();
+}
+
+bool InjectableAudioDeviceModule::BuiltInAGCIsAvailable() const {
+  return device_adm_->BuiltInAGCIsAvailable();
+}
+
+bool InjectableAudioDeviceModule::BuiltInNSIsAvailable() const {
+  return device_adm_->BuiltInNSIsAvailable();
+}
+
+int32_t InjectableAudioDeviceModule::EnableBuiltInAEC(bool enable) {
+  return device_adm_->EnableBuiltInAEC(enable);
+}
+
+int32_t InjectableAudioDeviceModule::EnableBuiltInAGC(bool enable) {
+  return device_adm_->EnableBuiltInAGC(enable);
+}
+
+int32_t InjectableAudioDeviceModule::EnableBuiltInNS(bool enable) {
+  return device_adm_->EnableBuiltInNS(enable);
+}
+
+int32_t InjectableAudioDeviceModule::GetPlayoutUnderrunCount() const {
+  return device_adm_->GetPlayoutUnderrunCount();
+}
+
+#if defined(WEBRTC_IOS)
+int InjectableAudioDeviceModule::GetPlayoutAudioParameters(AudioParameters* params) const {
+  return device_adm_->GetPlayoutAudioParameters(params);
+}
+
+int InjectableAudioDeviceModule::GetRecordAudioParameters(AudioParameters* params) const {
+  return device_adm_->GetRecordAudioParameters(params);
+}
+#endif
+
+int32_t InjectableAudioDeviceModule::RecordedDataIsAvailable(const void* audio_samples,
+                                                             size_t samples_per_channel,
+                                                             size_t bytes_per_sample,
+                                                             size_t channels,
+                                                             uint32_t samples_per_sec,
+                                                             uint32_t total_delay_ms,
+                                                             int32_t clock_drift,
+                                                             uint32_t current_mic_level,
+                                                             bool key_pressed,
+                                                             uint32_t& new_mic_level) {
+  AudioTransport* transport;
+  bool pushing;
+  {
+    MutexLock lock(&mutex_);
+    transport = transport_;
+    pushing = last_pushed_ms_ >= 0 && rtc::TimeMillis() - last_pushed_ms_ < kPushedSamplesTimeoutMs;
+  }
+  if (pushing || !transport) {
+    // Leave the mic level alone.
+    new_mic_level = current_mic_level;
+    return 0;
+  }
+  return transport->RecordedDataIsAvailable(audio_samples,
+                                            samples_per_channel,
+                                            bytes_per_sample,
+                                            channels,
+                                            samples_per_sec,
+                                            total_delay_ms,
+                                            clock_drift,
+                                            current_mic_level,
+                                            key_pressed,
+                                            new_mic_level);
+}
+
+int32_t InjectableAudioDeviceModule::NeedMorePlayData(size_t samples_per_channel,
+                                                      size_t bytes_per_sample,
+                                                      size_t channels,
+                                                      uint32_t samples_per_sec,
+                                                      void* audio_samples,
+                                                      size_t& samples_out,
+                                                      int64_t* elapsed_time_ms,
+                                                      int64_t* ntp_time_ms) {
+  AudioTransport* transport = CurrentTransport();
+  if (!transport) {
+    samples_out = 0;
+    return 0;
+  }
+  return transport->NeedMorePlayData(samples_per_channel,
+                                     bytes_per_sample,
+                                     channels,
+                                     samples_per_sec,
+                                     audio_samples,
+                                     samples_out,
+                                     elapsed_time_ms,
+                                     ntp_time_ms);
+}
+
+void InjectableAudioDeviceModule::PullRenderData(int bits_per_sample,
+                                                 int sample_rate,
+                                                 size_t channels,
+                                                 size_t frames,
+                                                 void* audio_data,
+                                                 int64_t* elapsed_time_ms,
+                                                 int64_t* ntp_time_ms) {
+  AudioTransport* transport = CurrentTransport();
+  if (transport) {
+    transport->PullRenderData(bits_per_sample,
+                              sample_rate,
+                              channels,
+                              frames,
+                              audio_data,
+                              elapsed_time_ms,
+                              ntp_time_ms);
+  }
+}
+
+} // namespace rffi
+} // namespace webrtc
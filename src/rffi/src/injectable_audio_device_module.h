@@ -0,0 +1,144 @@
+/*
+ * Copyright 2022 Signal Messenger, LLC
+ * SPDX-License-Identifier: AGPL-3.0-only
+ */
+
+#ifndef RFFI_INJECTABLE_AUDIO_DEVICE_MODULE_H__
+#define RFFI_INJECTABLE_AUDIO_DEVICE_MODULE_H__
+
+#include "modules/audio_device/include/audio_device.h"
+#include "rtc_base/synchronization/mutex.h"
+
+namespace webrtc {
+namespace rffi {
+
+// Wraps the AudioDeviceModule of a PeerConnectionFactory and forwards everything to it,
+// except that while samples are being pushed with PushRecordedSamples, those are sent
+// instead of what the recording device captures.
+// Only factories created with use_injectable_audio (tests and the CLI) use it.
+class InjectableAudioDeviceModule : public AudioDeviceModule, public AudioTransport {
+ public:
+  explicit InjectableAudioDeviceModule(rtc::scoped_refptr<AudioDeviceModule> device_adm);
+
+  // Samples are interleaved if there is more than one channel.
+  // They should be pushed in real time, 10ms at a time.
+  void PushRecordedSamples(uint32_t sample_rate,
+                           size_t channels,
+                           const int16_t* samples,
+                           size_t samples_size);
+
+  // As AudioDeviceModule
+  int32_t ActiveAudioLayer(AudioLayer* audio_layer) const override;
+  int32_t RegisterAudioCallback(AudioTransport* transport) override;
+  int32_t Init() override;
+  int32_t Terminate() override;
+  bool Initialized() const override;
+  int16_t PlayoutDevices() override;
+  int16_t RecordingDevices() override;
+  int32_t PlayoutDeviceName(uint16_t index,
+                            char name[kAdmMaxDeviceNameSize],
+                            char guid[kAdmMaxGuidSize]) override;
+  int32_t RecordingDeviceName(uint16_t index,
+                              char name[kAdmMaxDeviceNameSize],
+                              char guid[kAdmMaxGuidSize]) override;
+  int32_t SetPlayoutDevice(uint16_t index) override;
+  int32_t SetPlayoutDevice(WindowsDeviceType device) override;
+  int32_t SetRecordingDevice(uint16_t index) override;
+  int32_t SetRecordingDevice(WindowsDeviceType device) override;
+  int32_t PlayoutIsAvailable(bool* available) override;
+  int32_t InitPlayout() override;
+  bool PlayoutIsInitialized() const override;
+  int32_t RecordingIsAvailable(bool* available) override;
+  int32_t InitRecording() override;
+  bool RecordingIsInitialized() const override;
+  int32_t StartPlayout() override;
+  int32_t StopPlayout() override;
+  bool Playing() const override;
+  int32_t StartRecording() override;
+  int32_t StopRecording() override;
+  bool Recording() const override;
+  int32_t InitSpeaker() override;
+  bool SpeakerIsInitialized() const override;
+  int32_t InitMicrophone() override;
+  bool MicrophoneIsInitialized() const override;
+  int32_t SpeakerVolumeIsAvailable(bool* available) override;
+  int32_t SetSpeakerVolume(uint32_t volume) override;
+  int32_t SpeakerVolume(uint32_t* volume) const override;
+  int32_t MaxSpeakerVolume(uint32_t* max_volume) const override;
+  int32_t MinSpeakerVolume(uint32_t* min_volume) const override;
+  int32_t MicrophoneVolumeIsAvailable(bool* available) override;
+  int32_t SetMicrophoneVolume(uint32_t volume) override;
+  int32_t MicrophoneVolume(uint32_t* volume) const override;
+  int32_t MaxMicrophoneVolume(uint32_t* max_volume) const override;
+  int32_t MinMicrophoneVolume(uint32_t* min_volume) const override;
+  int32_t SpeakerMuteIsAvailable(bool* available) override;
+  int32_t SetSpeakerMute(bool enable) override;
+  int32_t SpeakerMute(bool* enabled) const override;
+  int32_t MicrophoneMuteIsAvailable(bool* available) override;
+  int32_t SetMicrophoneMute(bool enable) override;
+  int32_t MicrophoneMute(bool* enabled) const override;
+  int32_t StereoPlayoutIsAvailable(bool* available) const override;
+  int32_t SetStereoPlayout(bool enable) override;
+  int32_t StereoPlayout(bool* enabled) const override;
+  int32_t StereoRecordingIsAvailable(bool* available) const override;
+  int32_t SetStereoRecording(bool enable) override;
+  int32_t StereoRecording(bool* enabled) const override;
+  int32_t PlayoutDelay(uint16_t* delay_ms) const override;
+  bool BuiltInAECIsAvailable() const override;
+  bool BuiltInAGCIsAvailable() const override;
+  bool BuiltInNSIsAvailable() const override;
+  int32_t EnableBuiltInAEC(bool enable) override;
+  int32_t EnableBuiltInAGC(bool enable) override;
+  int32_t EnableBuiltInNS(bool enable) override;
+  int32_t GetPlayoutUnderrunCount() const override;
+#if defined(WEBRTC_IOS)
+  int GetPlayoutAudioParameters(AudioParameters* params) const override;
+  int GetRecordAudioParameters(AudioParameters* params) const override;
+#endif
+
+  // As AudioTransport, registered with the device ADM in place of the real transport.
+  int32_t RecordedDataIsAvailable(const void* audio_samples,
+                                  size_t samples_per_channel,
+                                  size_t bytes_per_sample,
+                                  size_t channels,
+                                  uint32_t samples_per_sec,
+                                  uint32_t total_delay_ms,
+                                  int32_t clock_drift,
+                                  uint32_t current_mic_level,
+                                  bool key_pressed,
+                                  uint32_t& new_mic_level) override;
+  int32_t NeedMorePlayData(size_t samples_per_channel,
+                           size_t bytes_per_sample,
+                           size_t channels,
+                           uint32_t samples_per_sec,
+                           void* audio_samples,
+                           size_t& samples_out,
+                           int64_t* elapsed_time_ms,
+                           int64_t* ntp_time_ms) override;
+  void PullRenderData(int bits_per_sample,
+                      int sample_rate,
+                      size_t channels,
+                      size_t frames,
+                      void* audio_data,
+                      int64_t* elapsed_time_ms,
+                      int64_t* ntp_time_ms) override;
+
+ private:
+  // The transport is called without holding mutex_, so that the audio threads
+  // don't wait on each other (or on PushRecordedSamples) while it runs.
+  // Like any ADM, this relies on a transport staying alive until it's unregistered
+  // and the device has stopped calling it.
+  AudioTransport* CurrentTransport();
+
+  const rtc::scoped_refptr<AudioDeviceModule> device_adm_;
+
+  Mutex mutex_;
+  AudioTransport* transport_ RTC_GUARDED_BY(mutex_) = nullptr;
+  // When PushRecordedSamples was last called, or -1 if never.
+  int64_t last_pushed_ms_ RTC_GUARDED_BY(mutex_) = -1;
+};
+
+} // namespace rffi
+} // namespace webrtc
+
+#endif /* RFFI_INJECTABLE_AUDIO_DEVICE_MODULE_H__ */
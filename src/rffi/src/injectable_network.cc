@@ -5,6 +5,8 @@
 
 #include "rffi/api/injectable_network.h"
 
+#include <errno.h>
+
 #include "api/packet_socket_factory.h"
 #include "p2p/client/basic_port_allocator.h"
 #include "rffi/api/network.h"
@@ -127,6 +129,114 @@ class InjectableUdpSocket : public rtc::AsyncPacketSocket {
   int last_error_ = 0;
 };
 
+// A TCP connection to a TURN server.  Each Send() is passed along as one
+// message (TurnPort sends whole STUN and ChannelData messages), so there is
+// no need for framing like AsyncStunTCPSocket does.
+class InjectableTcpSocket : public rtc::AsyncPacketSocket {
+ public:
+  InjectableTcpSocket(InjectableNetwork* network,
+                      const rtc::SocketAddress& local_address,
+                      const rtc::SocketAddress& remote_address)
+      : network_(network), local_address_(local_address), remote_address_(remote_address) {
+  }
+  ~InjectableTcpSocket() override {
+    network_->ForgetTcp(local_address_);
+  }
+
+  // As rtc::AsyncPacketSocket
+  rtc::SocketAddress GetLocalAddress() const override {
+    return local_address_;
+  }
+
+  // As rtc::AsyncPacketSocket
+  rtc::SocketAddress GetRemoteAddress() const override {
+    return remote_address_;
+  }
+
+  // As rtc::AsyncPacketSocket
+  int Send(const void* data,
+           size_t data_size,
+           const rtc::PacketOptions& options) override {
+    if (state_ != rtc::AsyncPacketSocket::STATE_CONNECTED) {
+      last_error_ = ENOTCONN;
+      return -1;
+    }
+    int result = network_->SendTcp(local_address_, remote_address_, static_cast<const uint8_t*>(data), data_size);
+    if (result < 0) {
+      last_error_ = result;
+      return result;
+    }
+
+    SignalSentPacket(this, rtc::SentPacket(options.packet_id, rtc::TimeMillis()));
+    return result;
+  }
+
+  // As rtc::AsyncPacketSocket
+  int SendTo(const void* data,
+             size_t data_size,
+             const rtc::SocketAddress& remote_address,
+             const rtc::PacketOptions& options) override {
+    // Like a real TCP socket, the address is ignored.
+    return Send(data, data_size, options);
+  }
+
+  void ReceiveFrom(const uint8_t* data, size_t data_size) {
+    RTC_LOG(LS_VERBOSE) << "InjectableTcpSocket::ReceiveFrom()"
+                        << " from " << remote_address_.ToString()
+                        << " to " << local_address_.ToString();
+    auto now = rtc::TimeMicros();
+    SignalReadPacket(this, reinterpret_cast<const char*>(data), data_size, remote_address_, now);
+  }
+
+  // TODO: Simulate the time a TCP handshake takes (and handshakes that fail).
+  void Connect() {
+    if (state_ != rtc::AsyncPacketSocket::STATE_CONNECTING) {
+      return;
+    }
+    state_ = rtc::AsyncPacketSocket::STATE_CONNECTED;
+    SignalConnect(this);
+  }
+
+  // As rtc::AsyncPacketSocket
+  int Close() override {
+    state_ = rtc::AsyncPacketSocket::STATE_CLOSED;
+    return 0;
+  }
+
+  // As rtc::AsyncPacketSocket
+  State GetState() const override {
+    return state_;
+  }
+
+  // As rtc::AsyncPacketSocket
+  int GetOption(rtc::Socket::Option option, int* value) override {
+    return -1;
+  }
+
+  // As rtc::AsyncPacketSocket
+  int SetOption(rtc::Socket::Option option, int value) override {
+    // See InjectableUdpSocket::SetOption.
+    return 1;
+  }
+
+  // As rtc::AsyncPacketSocket
+  int GetError() const override {
+    return last_error_;
+  }
+
+  // As rtc::AsyncPacketSocket
+  void SetError(int error) override {
+    last_error_ = error;
+  }
+
+ private:
+  InjectableNetwork* network_;
+  rtc::SocketAddress local_address_;
+  rtc::SocketAddress remote_address_;
+  State state_ = rtc::AsyncPacketSocket::STATE_CONNECTING;
+  int last_error_ = 0;
+};
+
 class InjectableNetworkImpl : public InjectableNetwork, public rtc::NetworkManager, public rtc::PacketSocketFactory {
  public:
   InjectableNetworkImpl(rtc::Thread* network_thread) : network_thread_(network_thread) {
@@ -230,6 +340,49 @@ class InjectableNetworkImpl : public InjectableNetwork, public rtc::NetworkManag
     });
   }
 
+  void ReceiveTcp(IpPort source,
+                  IpPort dest,
+                  const uint8_t* data,
+                  size_t size) override {
+    // The network stack expects everything to happen on the network thread.
+    // Make sure to copy the data!
+    network_thread_->PostTask(
+        [this, source, dest, data{std::vector<uint8_t>(data, data+size)}] {
+      auto local_address = IpPortToRtcSocketAddress(dest);
+      auto tcp_socket = tcp_socket_by_local_address_.find(local_address);
+      if (tcp_socket == tcp_socket_by_local_address_.end()) {
+        RTC_LOG(LS_WARNING) << "Received TCP packet for unknown local address.";
+        return;
+      }
+      if (tcp_socket->second->GetRemoteAddress() != IpPortToRtcSocketAddress(source)) {
+        RTC_LOG(LS_WARNING) << "Received TCP packet from the wrong remote address.";
+        return;
+      }
+      tcp_socket->second->ReceiveFrom(data.data(), data.size());
+    });
+  }
+
+  int SendTcp(const rtc::SocketAddress& local_address,
+              const rtc::SocketAddress& remote_address,
+              const uint8_t* data,
+              size_t size) override {
+    if (!sender_.object_owned) {
+      RTC_LOG(LS_WARNING) << "Dropping TCP packet because no sender set.";
+      return -1;
+    }
+    IpPort local = RtcSocketAddressToIpPort(local_address);
+    IpPort remote = RtcSocketAddressToIpPort(remote_address);
+    sender_.SendTcp(sender_.object_owned, local, remote, data, size);
+    return size;
+  }
+
+  void ForgetTcp(const rtc::SocketAddress& local_address) override {
+    // We need to access tcp_socket_by_local_address_ on the network_thread_.
+    network_thread_->PostTask([this, local_address] {
+      tcp_socket_by_local_address_.erase(local_address);
+    });
+  }
+
   // As NetworkManager
   void StartUpdating() override {
     RTC_DCHECK(network_thread_->IsCurrent());
@@ -321,8 +474,25 @@ class InjectableNetworkImpl : public InjectableNetwork, public rtc::NetworkManag
       const rtc::ProxyInfo& proxy_info,
       const std::string& user_agent,
       const rtc::PacketSocketTcpOptions& tcp_options) override {
-    // TODO: Support TCP for TURN
-    return nullptr;
+    // This is only used for TURN over TCP (and TLS, which we don't support).
+    RTC_DCHECK(network_thread_->IsCurrent());
+    RTC_LOG(LS_INFO) << "InjectableNetworkImpl::CreateClientTcpSocket() ip: " << local_address.ip()
+                     << " remote: " << remote_address.ToString();
+    if (tcp_options.opts & rtc::PacketSocketFactory::OPT_TLS) {
+      RTC_LOG(LS_WARNING) << "TLS is not supported by the InjectableNetwork.";
+      return nullptr;
+    }
+    rtc::SocketAddress local_address_with_port(local_address.ipaddr(), next_tcp_port_++);
+    auto tcp_socket = new InjectableTcpSocket(this, local_address_with_port, remote_address);
+    tcp_socket_by_local_address_.insert({local_address_with_port, tcp_socket});
+    // The caller hooks up SignalConnect after this returns.
+    network_thread_->PostTask([this, local_address_with_port] {
+      auto tcp_socket = tcp_socket_by_local_address_.find(local_address_with_port);
+      if (tcp_socket != tcp_socket_by_local_address_.end()) {
+        tcp_socket->second->Connect();
+      }
+    });
+    return tcp_socket;
   }
 
   // As PacketSocketFactory
@@ -336,9 +506,11 @@ class InjectableNetworkImpl : public InjectableNetwork, public rtc::NetworkManag
   rtc::Thread* network_thread_;
   std::map<std::string, std::unique_ptr<rtc::Network>> interface_by_name_;
   std::map<rtc::SocketAddress, InjectableUdpSocket*> udp_socket_by_local_address_;
+  std::map<rtc::SocketAddress, InjectableTcpSocket*> tcp_socket_by_local_address_;
   // The ICE stack does not like ports below 1024.
   // Give it a nice even number to count up from.
   uint16_t next_udp_port_ = 2001;
+  uint16_t next_tcp_port_ = 3001;
   InjectableNetworkSender sender_ = {};
 };
 
@@ -378,6 +550,15 @@ RUSTEXPORT void Rust_InjectableNetwork_ReceiveUdp(
   network_borrowed->ReceiveUdp(local, remote, data_borrowed, size);
 }
 
+RUSTEXPORT void Rust_InjectableNetwork_ReceiveTcp(
+    InjectableNetwork* network_borrowed,
+    IpPort local,
+    IpPort remote,
+    const uint8_t* data_borrowed,
+    size_t size) {
+  network_borrowed->ReceiveTcp(local, remote, data_borrowed, size);
+}
+
 }  // namespace rffi
 
 }  // namespace webrtc
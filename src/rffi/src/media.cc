@@ -26,6 +26,44 @@ void VideoSource::PushVideoFrame(const webrtc::VideoFrame& frame) {
   broadcaster_.OnFrame(frame);
 }
 
+ProcessedVideoSource::ProcessedVideoSource(rtc::scoped_refptr<VideoTrackInterface> input,
+                                           void* processor_owned,
+                                           RffiVideoFrameProcessorCallbacks callbacks)
+  : input_(std::move(input)), processor_(processor_owned), callbacks_(callbacks) {
+  input_->AddOrUpdateSink(this, rtc::VideoSinkWants());
+}
+
+ProcessedVideoSource::~ProcessedVideoSource() {
+  // After this, OnFrame won't be called again.
+  input_->RemoveSink(this);
+  callbacks_.deleteProcessor(processor_);
+}
+
+void ProcessedVideoSource::OnFrame(const webrtc::VideoFrame& frame) {
+  RffiVideoFrameMetadata metadata = {};
+  metadata.width = frame.width();
+  metadata.height = frame.height();
+  metadata.rotation = frame.rotation();
+  auto* buffer_owned_rc = callbacks_.processFrame(processor_, metadata, frame.video_frame_buffer().get());
+  if (!buffer_owned_rc) {
+    return;
+  }
+  auto buffer = inc_rc(buffer_owned_rc);
+  // Now that the scoped_refptr has its own, give up the one we were given.
+  buffer_owned_rc->Release();
+  if (buffer == frame.video_frame_buffer()) {
+    PushVideoFrame(frame);
+    return;
+  }
+  // The processors apply the rotation.
+  auto processed_frame = webrtc::VideoFrame::Builder()
+      .set_video_frame_buffer(buffer)
+      .set_timestamp_us(frame.timestamp_us())
+      .set_rotation(kVideoRotation_0)
+      .build();
+  PushVideoFrame(processed_frame);
+}
+
 // Returns 0 upon failure
 RUSTEXPORT uint32_t Rust_getTrackIdAsUint32(webrtc::MediaStreamTrackInterface* track_borrowed_rc) {
   uint32_t id = 0;
@@ -133,6 +171,33 @@ RUSTEXPORT void Rust_convertVideoFrameBufferToRgba(const VideoFrameBuffer* buffe
       i420->width(), i420->height());
 }
 
+RUSTEXPORT void Rust_convertVideoFrameBufferToI420(const VideoFrameBuffer* buffer_borrowed_rc, uint8_t* i420_out) {
+  // Decoded frames aren't always I420 (such as from hardware decoders).
+  // ToI420 isn't const, but it doesn't modify the buffer.
+  rtc::scoped_refptr<I420BufferInterface> i420 =
+      const_cast<VideoFrameBuffer*>(buffer_borrowed_rc)->ToI420();
+  if (!i420) {
+    RTC_LOG(LS_WARNING) << "Rust_convertVideoFrameBufferToI420: failed to convert buffer";
+    return;
+  }
+  int width_y = i420->width();
+  int height_y = i420->height();
+  int width_u = (width_y + 1) / 2;
+  int height_u = (height_y + 1) / 2;
+
+  uint8_t* dst_y = i420_out;
+  uint8_t* dst_u = dst_y + (width_y * height_y);
+  uint8_t* dst_v = dst_u + (width_u * height_u);
+  libyuv::I420Copy(
+      i420->DataY(), i420->StrideY(),
+      i420->DataU(), i420->StrideU(),
+      i420->DataV(), i420->StrideV(),
+      dst_y, width_y,
+      dst_u, width_u,
+      dst_v, width_u,
+      width_y, height_y);
+}
+
 // Returns an owned RC.
 RUSTEXPORT VideoFrameBuffer* Rust_copyAndRotateVideoFrameBuffer(
     const VideoFrameBuffer* buffer_borrowed_rc, VideoRotation rotation) {
@@ -15,7 +15,10 @@
 #include "pc/sdp_utils.h"
 #include "pc/session_description.h"
 #include "sdk/media_constraints.h"
+#include "rffi/api/media.h"
 #include "rffi/api/peer_connection_intf.h"
+#include "rffi/api/peer_connection_observer_intf.h"
+#include "rffi/src/peer_connection_observer.h"
 #include "rffi/src/ptr.h"
 #include "rffi/src/sdp_observer.h"
 #include "rffi/src/stats_observer.h"
@@ -501,7 +504,8 @@ CreateSessionDescriptionForGroupCall(bool local,
                                      const std::string& ice_ufrag,
                                      const std::string& ice_pwd,
                                      RffiSrtpKey srtp_key,
-                                     std::vector<uint32_t> rtp_demux_ids) {
+                                     std::vector<uint32_t> rtp_demux_ids,
+                                     bool receive_video) {
   // Major changes from the default WebRTC behavior:
   // 1. We remove all codecs except Opus and VP8.
   // 2. We remove all header extensions except for transport-cc, video orientation,
@@ -665,7 +669,12 @@ CreateSessionDescriptionForGroupCall(bool local,
     }
 
     audio->AddStream(audio_stream);
-    video->AddStream(video_stream);
+    // We use Plan B, so there are no transceivers whose direction we could change.
+    // Leaving out the remote video streams removes their receivers (and decoders)
+    // instead, and adding them back recreates them.
+    if (local || receive_video) {
+      video->AddStream(video_stream);
+    }
   }
 
   // TODO: Why is this only for video by default in WebRTC? Should we enable it for all of them?
@@ -711,7 +720,7 @@ Rust_localDescriptionForGroupCall(const char* ice_ufrag_borrowed,
     rtp_demux_ids.push_back(rtp_demux_id);
   }
   return CreateSessionDescriptionForGroupCall(
-    true /* local */, std::string(ice_ufrag_borrowed), std::string(ice_pwd_borrowed), client_srtp_key, rtp_demux_ids);
+    true /* local */, std::string(ice_ufrag_borrowed), std::string(ice_pwd_borrowed), client_srtp_key, rtp_demux_ids, true /* receive_video */);
 }
 
 // Returns an owned pointer.
@@ -720,11 +729,12 @@ Rust_remoteDescriptionForGroupCall(const char* ice_ufrag_borrowed,
                                    const char* ice_pwd_borrowed,
                                    RffiSrtpKey server_srtp_key,
                                    uint32_t* rtp_demux_ids_borrowed,
-                                   size_t rtp_demux_ids_len) {
+                                   size_t rtp_demux_ids_len,
+                                   bool receive_video) {
   std::vector<uint32_t> rtp_demux_ids;
   rtp_demux_ids.assign(rtp_demux_ids_borrowed, rtp_demux_ids_borrowed + rtp_demux_ids_len);
   return CreateSessionDescriptionForGroupCall(
-    false /* local */, std::string(ice_ufrag_borrowed), std::string(ice_pwd_borrowed), server_srtp_key, rtp_demux_ids);
+    false /* local */, std::string(ice_ufrag_borrowed), std::string(ice_pwd_borrowed), server_srtp_key, rtp_demux_ids, receive_video);
 }
 
 RUSTEXPORT void
@@ -775,6 +785,71 @@ Rust_setIncomingMediaEnabled(PeerConnectionInterface* peer_connection_borrowed_r
   return peer_connection_borrowed_rc->SetIncomingRtpEnabled(enabled);
 }
 
+// Like Rust_setOutgoingMediaEnabled, but only for video senders.
+RUSTEXPORT void
+Rust_setOutgoingVideoEnabled(PeerConnectionInterface* peer_connection_borrowed_rc,
+                             bool                     enabled) {
+  int encodings_changed = 0;
+  for (auto& sender : peer_connection_borrowed_rc->GetSenders()) {
+    if (sender->media_type() != cricket::MEDIA_TYPE_VIDEO) {
+      continue;
+    }
+    RtpParameters parameters = sender->GetParameters();
+    for (auto& encoding: parameters.encodings) {
+      encoding.active = enabled;
+      encodings_changed++;
+    }
+    sender->SetParameters(parameters);
+  }
+  RTC_LOG(LS_INFO) << "Rust_setOutgoingVideoEnabled(" << enabled << ") for " << encodings_changed << " encodings.";
+}
+
+// Disabled remote video tracks pass on black frames instead of what's decoded.
+// Receivers added by a later remote description start out enabled.
+// The receivers keep decoding; with Plan B there are no transceivers to stop.
+// To stop decoding, group calls leave the video streams out of the remote
+// description (see Rust_remoteDescriptionForGroupCall).
+RUSTEXPORT void
+Rust_setIncomingVideoEnabled(PeerConnectionInterface* peer_connection_borrowed_rc,
+                             bool                     enabled) {
+  int tracks_changed = 0;
+  for (const auto& receiver : peer_connection_borrowed_rc->GetReceivers()) {
+    if (receiver->media_type() != cricket::MEDIA_TYPE_VIDEO) {
+      continue;
+    }
+    auto track = receiver->track();
+    if (track) {
+      track->set_enabled(enabled);
+      tracks_changed++;
+    }
+  }
+  RTC_LOG(LS_INFO) << "Rust_setIncomingVideoEnabled(" << enabled << ") for " << tracks_changed << " tracks.";
+}
+
+RUSTEXPORT void
+Rust_setIncomingAudioFramesEnabled(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc,
+                                   bool                             enabled) {
+  auto* observer = PeerConnectionObserverRffi::ForPeerConnection(peer_connection_borrowed_rc);
+  if (!observer) {
+    RTC_LOG(LS_WARNING) << "Rust_setIncomingAudioFramesEnabled(" << enabled << ") called for a PeerConnection without a known observer";
+    return;
+  }
+  RTC_LOG(LS_INFO) << "Rust_setIncomingAudioFramesEnabled(" << enabled << ")";
+  observer->SetIncomingAudioFramesEnabled(enabled);
+}
+
+RUSTEXPORT void
+Rust_setIncomingVideoFrameContentEnabled(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc,
+                                         bool                             enabled) {
+  auto* observer = PeerConnectionObserverRffi::ForPeerConnection(peer_connection_borrowed_rc);
+  if (!observer) {
+    RTC_LOG(LS_WARNING) << "Rust_setIncomingVideoFrameContentEnabled(" << enabled << ") called for a PeerConnection without a known observer";
+    return;
+  }
+  RTC_LOG(LS_INFO) << "Rust_setIncomingVideoFrameContentEnabled(" << enabled << ")";
+  observer->SetIncomingVideoFrameContentEnabled(enabled);
+}
+
 RUSTEXPORT void
 Rust_setAudioPlayoutEnabled(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc,
                             bool                             enabled) {
@@ -951,6 +1026,113 @@ Rust_getAudioLevels(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc
   peer_connection_borrowed_rc->GetAudioLevels(captured_out, received_out, received_out_size, received_size_out);
 }
 
+// Remote audio tracks have the demux ID as their ID.
+// Returns false if there is no audio receiver for the demux ID.
+RUSTEXPORT bool
+Rust_setRemoteAudioVolume(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc,
+                          uint32_t demux_id,
+                          double volume) {
+  for (const auto& receiver : peer_connection_borrowed_rc->GetReceivers()) {
+    if (receiver->media_type() != cricket::MEDIA_TYPE_AUDIO) {
+      continue;
+    }
+    auto track = receiver->track();
+    if (!track || Rust_getTrackIdAsUint32(track.get()) != demux_id) {
+      continue;
+    }
+    auto* source = static_cast<AudioTrackInterface*>(track.get())->GetSource();
+    if (!source) {
+      RTC_LOG(LS_WARNING) << "Rust_setRemoteAudioVolume(" << demux_id << ") found no audio source";
+      return false;
+    }
+    source->SetVolume(volume);
+    return true;
+  }
+  RTC_LOG(LS_WARNING) << "Rust_setRemoteAudioVolume(" << demux_id << ") found no audio receiver";
+  return false;
+}
+
+// With simulcast, each layer is applied to the encoding at the same index.
+// With SVC, the layers are spatial layers of the first encoding.
+RUSTEXPORT bool
+Rust_setVideoEncodingLayers(webrtc::PeerConnectionInterface* peer_connection_borrowed_rc,
+                            bool svc,
+                            const RffiVideoEncodingLayer* layers_borrowed,
+                            size_t layers_len) {
+  RTC_LOG(LS_INFO) << "Rust_setVideoEncodingLayers(svc=" << svc << ", layers=" << layers_len << ")";
+  // Local video is negotiated with 3 simulcast SSRCs (see CreateSessionDescriptionForGroupCall),
+  // and we use at most 3 spatial layers (L3T3) for SVC.
+  // Keep in sync with MAX_VIDEO_ENCODING_LAYERS in media.rs.
+  if (layers_len > 3) {
+    RTC_LOG(LS_WARNING) << "Rust_setVideoEncodingLayers: " << layers_len
+                        << " layers but at most 3 are supported";
+    return false;
+  }
+  bool all_applied = true;
+  for (auto& sender : peer_connection_borrowed_rc->GetSenders()) {
+    if (sender->media_type() != cricket::MEDIA_TYPE_VIDEO) {
+      continue;
+    }
+    RtpParameters parameters = sender->GetParameters();
+    auto& encodings = parameters.encodings;
+    if (layers_len == 0) {
+      for (auto& encoding : encodings) {
+        encoding.active = true;
+        encoding.scale_resolution_down_by = absl::nullopt;
+        encoding.max_bitrate_bps = absl::nullopt;
+        encoding.scalability_mode = absl::nullopt;
+      }
+    } else if (svc) {
+      if (encodings.empty()) {
+        all_applied = false;
+        continue;
+      }
+      size_t spatial_layers = 0;
+      uint32_t max_bitrate_bps = 0;
+      for (size_t i = 0; i < layers_len; i++) {
+        if (layers_borrowed[i].active) {
+          spatial_layers = i + 1;
+          max_bitrate_bps += layers_borrowed[i].max_bitrate_bps;
+        }
+      }
+      for (size_t i = 1; i < encodings.size(); i++) {
+        encodings[i].active = false;
+      }
+      auto& encoding = encodings[0];
+      encoding.active = spatial_layers > 0;
+      encoding.scale_resolution_down_by = absl::nullopt;
+      encoding.max_bitrate_bps = max_bitrate_bps;
+      if (spatial_layers > 0) {
+        encoding.scalability_mode = "L" + std::to_string(spatial_layers) + "T3";
+      }
+    } else {
+      if (layers_len > encodings.size()) {
+        RTC_LOG(LS_WARNING) << "Rust_setVideoEncodingLayers: " << layers_len
+                            << " layers but only " << encodings.size() << " encodings";
+        all_applied = false;
+        continue;
+      }
+      for (size_t i = 0; i < encodings.size(); i++) {
+        auto& encoding = encodings[i];
+        encoding.scalability_mode = absl::nullopt;
+        if (i < layers_len) {
+          encoding.active = layers_borrowed[i].active;
+          encoding.scale_resolution_down_by = layers_borrowed[i].scale_resolution_down_by;
+          encoding.max_bitrate_bps = layers_borrowed[i].max_bitrate_bps;
+        } else {
+          encoding.active = false;
+        }
+      }
+    }
+    auto result = sender->SetParameters(parameters);
+    if (!result.ok()) {
+      RTC_LOG(LS_WARNING) << "Rust_setVideoEncodingLayers failed: " << result.message();
+      all_applied = false;
+    }
+  }
+  return all_applied;
+}
+
 RUSTEXPORT void
 Rust_closePeerConnection(PeerConnectionInterface* peer_connection_borrowed_rc) {
     peer_connection_borrowed_rc->Close();
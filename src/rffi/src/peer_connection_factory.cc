@@ -19,6 +19,7 @@
 #include "rffi/api/peer_connection_factory.h"
 #include "rffi/api/peer_connection_observer_intf.h"
 #include "rffi/api/injectable_network.h"
+#include "rffi/src/injectable_audio_device_module.h"
 #include "rffi/src/peer_connection_observer.h"
 #include "rffi/src/ptr.h"
 #include "rtc_base/logging.h"
@@ -43,7 +44,8 @@ class PeerConnectionFactoryWithOwnedThreads
  public:
   static rtc::scoped_refptr<PeerConnectionFactoryWithOwnedThreads> Create(
       bool use_new_audio_device_module,
-      bool use_injectable_network) {
+      bool use_injectable_network,
+      bool use_injectable_audio) {
     // Creating a PeerConnectionFactory is a little complex.  To make sure we're doing it right, we read several examples:
     // Android SDK:
     //  https://cs.chromium.org/chromium/src/third_party/webrtc/sdk/android/src/jni/pc/peer_connection_factory.cc
@@ -87,7 +89,7 @@ class PeerConnectionFactoryWithOwnedThreads
 
     // The audio device module must be created (and destroyed) on the _worker_ thread.
     // It is safe to release the reference on this thread, however, because the PeerConnectionFactory keeps its own reference.
-    auto adm = worker_thread->Invoke<rtc::scoped_refptr<AudioDeviceModule>>(RTC_FROM_HERE, [&]() {
+    auto device_adm = worker_thread->Invoke<rtc::scoped_refptr<AudioDeviceModule>>(RTC_FROM_HERE, [&]() {
       if (use_new_audio_device_module) {
 #if defined(WEBRTC_WIN)
         com_initializer = std::make_unique<ScopedCOMInitializer>(ScopedCOMInitializer::kMTA);
@@ -101,6 +103,13 @@ class PeerConnectionFactoryWithOwnedThreads
       return AudioDeviceModule::Create(
         AudioDeviceModule::kPlatformDefaultAudio, dependencies.task_queue_factory.get());
     });
+    rtc::scoped_refptr<InjectableAudioDeviceModule> injectable_adm;
+    rtc::scoped_refptr<AudioDeviceModule> adm = device_adm;
+    if (use_injectable_audio) {
+      // The wrapper only forwards to the device ADM, so it doesn't matter which thread creates it.
+      injectable_adm = rtc::make_ref_counted<InjectableAudioDeviceModule>(std::move(device_adm));
+      adm = injectable_adm;
+    }
     media_dependencies.adm = adm;
     media_dependencies.audio_encoder_factory = CreateBuiltinAudioEncoderFactory();
     media_dependencies.audio_decoder_factory = CreateBuiltinAudioDecoderFactory();
@@ -120,7 +129,8 @@ class PeerConnectionFactoryWithOwnedThreads
 #if defined(WEBRTC_WIN)
         std::move(com_initializer),
 #endif
-        adm);
+        adm.get(),
+        injectable_adm.get());
   }
 
   ~PeerConnectionFactoryWithOwnedThreads() override {
@@ -213,6 +223,14 @@ class PeerConnectionFactoryWithOwnedThreads
     });
   }
 
+  void PushOutgoingAudioSamples(uint32_t sample_rate, size_t channels, const int16_t* samples, size_t samples_size) override {
+    if (!injectable_audio_device_module_) {
+      RTC_LOG(LS_WARNING) << "Dropping pushed audio samples because the factory wasn't created with use_injectable_audio";
+      return;
+    }
+    injectable_audio_device_module_->PushRecordedSamples(sample_rate, channels, samples, samples_size);
+  }
+
  protected:
   PeerConnectionFactoryWithOwnedThreads(
       rtc::scoped_refptr<PeerConnectionFactoryInterface> factory,
@@ -223,7 +241,8 @@ class PeerConnectionFactoryWithOwnedThreads
 #if defined(WEBRTC_WIN)
       std::unique_ptr<ScopedCOMInitializer> com_initializer,
 #endif
-      AudioDeviceModule* audio_device_module) :
+      AudioDeviceModule* audio_device_module,
+      InjectableAudioDeviceModule* injectable_audio_device_module) :
     owned_network_thread_(std::move(owned_network_thread)),
     owned_worker_thread_(std::move(owned_worker_thread)),
     owned_signaling_thread_(std::move(owned_signaling_thread)),
@@ -232,6 +251,7 @@ class PeerConnectionFactoryWithOwnedThreads
     com_initializer_(std::move(com_initializer)),
 #endif
     audio_device_module_(audio_device_module),
+    injectable_audio_device_module_(injectable_audio_device_module),
     factory_(std::move(factory)) {
   }
 
@@ -258,16 +278,20 @@ class PeerConnectionFactoryWithOwnedThreads
   std::unique_ptr<ScopedCOMInitializer> com_initializer_;
 #endif
   webrtc::AudioDeviceModule* audio_device_module_;
+  // Null unless created with use_injectable_audio.  Otherwise, the same as audio_device_module_.
+  InjectableAudioDeviceModule* injectable_audio_device_module_;
   const rtc::scoped_refptr<PeerConnectionFactoryInterface> factory_;
 };
 
 // Returns an owned RC.
 RUSTEXPORT PeerConnectionFactoryOwner* Rust_createPeerConnectionFactory(
     bool use_new_audio_device_module,
-    bool use_injectable_network) {
+    bool use_injectable_network,
+    bool use_injectable_audio) {
   auto factory_owner = PeerConnectionFactoryWithOwnedThreads::Create(
     use_new_audio_device_module,
-    use_injectable_network);
+    use_injectable_network,
+    use_injectable_audio);
   return take_rc(std::move(factory_owner));
 }
 
@@ -339,6 +363,7 @@ RUSTEXPORT PeerConnectionInterface* Rust_createPeerConnection(
     return nullptr;
   }
   rtc::scoped_refptr<PeerConnectionInterface> pc = result.MoveValue();
+  PeerConnectionObserverRffi::Associate(pc.get(), observer_borrowed);
 
   // We use an arbitrary stream_id because existing apps want a MediaStream to pop out.
   auto stream_id = "s";
@@ -389,11 +414,29 @@ RUSTEXPORT AudioTrackInterface* Rust_createAudioTrack(
   return take_rc(factory->CreateAudioTrack("audio1", std::move(source)));
 }
 
+RUSTEXPORT void Rust_pushOutgoingAudioSamples(
+    PeerConnectionFactoryOwner* factory_owner_borrowed_rc,
+    uint32_t sample_rate,
+    size_t channels,
+    const int16_t* samples_borrowed,
+    size_t samples_size) {
+  factory_owner_borrowed_rc->PushOutgoingAudioSamples(sample_rate, channels, samples_borrowed, samples_size);
+}
+
 // Returns an owned RC.
 RUSTEXPORT VideoTrackSourceInterface* Rust_createVideoSource() {
   return take_rc(rtc::make_ref_counted<webrtc::rffi::VideoSource>());
 }
 
+// Returns an owned RC.
+RUSTEXPORT VideoTrackSourceInterface* Rust_createProcessedVideoSource(
+    VideoTrackInterface* input_track_borrowed_rc,
+    void* processor_owned,
+    const RffiVideoFrameProcessorCallbacks* callbacks_borrowed) {
+  return take_rc(rtc::make_ref_counted<webrtc::rffi::ProcessedVideoSource>(
+      inc_rc(input_track_borrowed_rc), processor_owned, *callbacks_borrowed));
+}
+
 // Returns an owned RC.
 RUSTEXPORT VideoTrackInterface* Rust_createVideoTrack(
     PeerConnectionFactoryOwner* factory_owner_borrowed_rc,
@@ -8,10 +8,28 @@
 #include "rffi/api/media.h"
 #include "rffi/src/peer_connection_observer.h"
 #include "rffi/src/ptr.h"
+#include "rtc_base/synchronization/mutex.h"
+
+#include <map>
 
 namespace webrtc {
 namespace rffi {
 
+namespace {
+
+// Only the PeerConnections created by Rust_createPeerConnection are in here.
+Mutex& ObserversLock() {
+  static Mutex* lock = new Mutex();
+  return *lock;
+}
+
+std::map<PeerConnectionInterface*, PeerConnectionObserverRffi*>& ObserversByPeerConnection() {
+  static auto* observers = new std::map<PeerConnectionInterface*, PeerConnectionObserverRffi*>();
+  return *observers;
+}
+
+}  // namespace
+
 PeerConnectionObserverRffi::PeerConnectionObserverRffi(void* observer,
                                                        const PeerConnectionObserverCallbacks* callbacks,
                                                        bool enable_frame_encryption,
@@ -24,6 +42,29 @@ PeerConnectionObserverRffi::PeerConnectionObserverRffi(void* observer,
 
 PeerConnectionObserverRffi::~PeerConnectionObserverRffi() {
   RTC_LOG(LS_INFO) << "PeerConnectionObserverRffi:dtor(): " << this->observer_;
+
+  MutexLock lock(&ObserversLock());
+  auto& observers = ObserversByPeerConnection();
+  for (auto it = observers.begin(); it != observers.end();) {
+    if (it->second == this) {
+      it = observers.erase(it);
+    } else {
+      ++it;
+    }
+  }
+}
+
+void PeerConnectionObserverRffi::Associate(PeerConnectionInterface* peer_connection,
+                                           PeerConnectionObserverRffi* observer) {
+  MutexLock lock(&ObserversLock());
+  ObserversByPeerConnection()[peer_connection] = observer;
+}
+
+PeerConnectionObserverRffi* PeerConnectionObserverRffi::ForPeerConnection(PeerConnectionInterface* peer_connection) {
+  MutexLock lock(&ObserversLock());
+  auto& observers = ObserversByPeerConnection();
+  auto it = observers.find(peer_connection);
+  return it == observers.end() ? nullptr : it->second;
 }
 
 void PeerConnectionObserverRffi::OnIceCandidate(const IceCandidateInterface* candidate) {
@@ -155,11 +196,13 @@ void PeerConnectionObserverRffi::OnAddTrack(
       uint32_t id = Rust_getTrackIdAsUint32(receiver->track());
       if (id != 0) {
         receiver->SetFrameDecryptor(CreateDecryptor(id));
+        AddAudioSink(static_cast<webrtc::AudioTrackInterface*>(receiver->track().get()));
         callbacks_.onAddAudioRtpReceiver(observer_, take_rc(receiver->track()));
       } else {
         RTC_LOG(LS_WARNING) << "Not sending decryptor for RtpReceiver with strange ID: " << receiver->track()->id();
       }
     } else {
+      AddAudioSink(static_cast<webrtc::AudioTrackInterface*>(receiver->track().get()));
       callbacks_.onAddAudioRtpReceiver(observer_, take_rc(receiver->track()));
     }
   } else if (receiver->media_type() == cricket::MEDIA_TYPE_VIDEO) {
@@ -284,6 +327,63 @@ void PeerConnectionObserverRffi::OnVideoFrame(uint32_t track_id, const webrtc::V
   callbacks_.onVideoFrame(observer_, track_id, metadata, buffer_owned_rc);
 }
 
+void PeerConnectionObserverRffi::AddAudioSink(AudioTrackInterface* track) {
+  if (!track) {
+    return;
+  }
+
+  // Unlike the VideoSinks, these are always attached so that enabling
+  // audio frames later also covers the tracks that already exist.
+  // OnAudioFrame drops the frames until then.
+  uint32_t track_id = Rust_getTrackIdAsUint32(track);
+  auto sink = std::make_unique<AudioSink>(track_id, this);
+
+  // The sink gets stored in the track, but never destroys it.
+  // The sink must live as long as the track, which is why we
+  // stored it in the PeerConnectionObserverRffi.
+  track->AddSink(sink.get());
+  audio_sinks_.push_back(std::move(sink));
+}
+
+void PeerConnectionObserverRffi::SetIncomingAudioFramesEnabled(bool enabled) {
+  enable_audio_frame_event_ = enabled;
+}
+
+void PeerConnectionObserverRffi::SetIncomingVideoFrameContentEnabled(bool enabled) {
+  enable_video_frame_content_ = enabled;
+}
+
+AudioSink::AudioSink(uint32_t track_id, PeerConnectionObserverRffi* pc_observer)
+  : track_id_(track_id), pc_observer_(pc_observer) {
+}
+
+void AudioSink::OnData(const void* audio_data,
+                       int bits_per_sample,
+                       int sample_rate,
+                       size_t number_of_channels,
+                       size_t number_of_frames) {
+  pc_observer_->OnAudioFrame(track_id_, audio_data, bits_per_sample, sample_rate, number_of_channels, number_of_frames);
+}
+
+void PeerConnectionObserverRffi::OnAudioFrame(uint32_t track_id,
+                                              const void* audio_data,
+                                              int bits_per_sample,
+                                              int sample_rate,
+                                              size_t number_of_channels,
+                                              size_t number_of_frames) {
+  if (!enable_audio_frame_event_) {
+    return;
+  }
+  if (bits_per_sample != 16) {
+    RTC_LOG(LS_WARNING) << "Dropping incoming audio frame with " << bits_per_sample << " bits per sample";
+    return;
+  }
+
+  // The samples are interleaved, so there's one per channel per frame.
+  callbacks_.onAudioFrame(observer_, track_id, sample_rate, number_of_channels,
+                          static_cast<const int16_t*>(audio_data), number_of_channels * number_of_frames);
+}
+
 class Decryptor : public webrtc::FrameDecryptorInterface {
  public:
   // Passed-in observer must live at least as long as the Decryptor,
@@ -10,6 +10,8 @@
 #include "api/media_stream_interface.h"
 #include "api/peer_connection_interface.h"
 
+#include <atomic>
+
 /**
  * Adapter between the C++ PeerConnectionObserver interface and the
  * Rust PeerConnection.Observer interface.  Wraps an instance of the
@@ -19,6 +21,7 @@
 namespace webrtc {
 namespace rffi {
 
+class AudioSink;
 class VideoSink;
 
 class PeerConnectionObserverRffi : public PeerConnectionObserver {
@@ -77,17 +80,46 @@ class PeerConnectionObserverRffi : public PeerConnectionObserver {
   // Called by the VideoSinks in video_sinks_.
   void OnVideoFrame(uint32_t track_id, const webrtc::VideoFrame& frame);
 
+  // Called by the AudioSinks in audio_sinks_.
+  void OnAudioFrame(uint32_t track_id,
+                    const void* audio_data,
+                    int bits_per_sample,
+                    int sample_rate,
+                    size_t number_of_channels,
+                    size_t number_of_frames);
+
+  // Applies to the remote audio tracks that already exist and those added later.
+  void SetIncomingAudioFramesEnabled(bool enabled);
+
+  // Applies to the frames of every remote video track from now on.
+  void SetIncomingVideoFrameContentEnabled(bool enabled);
+
+  // Records which PeerConnection this observer was created for so that it can be
+  // found again from functions that are only given the PeerConnection.
+  static void Associate(PeerConnectionInterface* peer_connection, PeerConnectionObserverRffi* observer);
+  // Returns nullptr if the PeerConnection wasn't created with Rust_createPeerConnection.
+  static PeerConnectionObserverRffi* ForPeerConnection(PeerConnectionInterface* peer_connection);
+
  private:
   // Add a VideoSink to the video_sinks_ for ownership and pass
   // a borrowed pointer to the track.
   void AddVideoSink(VideoTrackInterface* track);
+  // Add an AudioSink to the audio_sinks_ for ownership and pass
+  // a borrowed pointer to the track.
+  void AddAudioSink(AudioTrackInterface* track);
 
   void* observer_;
   PeerConnectionObserverCallbacks callbacks_;
   bool enable_frame_encryption_ = false;
   bool enable_video_frame_event_ = false;
-  bool enable_video_frame_content_ = false;
+  // Read on the decoder threads and written on whichever thread calls
+  // Rust_setIncomingVideoFrameContentEnabled.
+  std::atomic<bool> enable_video_frame_content_{false};
   std::vector<std::unique_ptr<VideoSink>> video_sinks_;
+  // Read on the audio thread and written on whichever thread calls
+  // Rust_setIncomingAudioFramesEnabled.
+  std::atomic<bool> enable_audio_frame_event_{false};
+  std::vector<std::unique_ptr<AudioSink>> audio_sinks_;
 };
 
 // A simple implementation of a VideoSinkInterface which passes video frames
@@ -104,7 +136,22 @@ class VideoSink : public rtc::VideoSinkInterface<webrtc::VideoFrame> {
   PeerConnectionObserverRffi* pc_observer_;
 };
 
+// Like VideoSink, but for decoded audio from a remote AudioTrack.
+class AudioSink : public webrtc::AudioTrackSinkInterface {
+ public:
+  AudioSink(uint32_t track_id, PeerConnectionObserverRffi*);
+  ~AudioSink() override = default;
+
+  void OnData(const void* audio_data,
+              int bits_per_sample,
+              int sample_rate,
+              size_t number_of_channels,
+              size_t number_of_frames) override;
 
+ private:
+  uint32_t track_id_;
+  PeerConnectionObserverRffi* pc_observer_;
+};
 
 } // namespace rffi
 } // namespace webrtc
@@ -18,7 +18,7 @@ use crate::android::error::AndroidError;
 use crate::android::jni_util::*;
 use crate::android::webrtc_java_media_stream::JavaMediaStream;
 use crate::common::{ApplicationEvent, CallDirection, CallId, CallMediaType, DeviceId, Result};
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::core::bandwidth_mode::BandwidthPolicy;
 use crate::core::call::Call;
 use crate::core::connection::{Connection, ConnectionType};
 use crate::core::platform::{Platform, PlatformItem};
@@ -265,15 +265,15 @@ impl Platform for AndroidPlatform {
         remote_device_id: DeviceId,
         connection_type: ConnectionType,
         signaling_version: signaling::Version,
-        bandwidth_mode: BandwidthMode,
+        bandwidth_policy: BandwidthPolicy,
         audio_levels_interval: Option<Duration>,
     ) -> Result<Connection<Self>> {
         info!(
-            "create_connection(): call_id: {} remote_device_id: {} signaling_version: {:?}, bandwidth_mode: {}, audio_levels_interval: {:?}",
+            "create_connection(): call_id: {} remote_device_id: {} signaling_version: {:?}, bandwidth_policy: {:?}, audio_levels_interval: {:?}",
             call.call_id(),
             remote_device_id,
             signaling_version,
-            bandwidth_mode,
+            bandwidth_policy,
             audio_levels_interval,
         );
 
@@ -284,7 +284,7 @@ impl Platform for AndroidPlatform {
             call.clone(),
             remote_device_id,
             connection_type,
-            bandwidth_mode,
+            bandwidth_policy,
             audio_levels_interval,
             None, // The app adds sinks to VideoTracks.
         )?;
@@ -451,6 +451,22 @@ impl Platform for AndroidPlatform {
         Ok(())
     }
 
+    fn on_talking_while_muted(&self, remote_peer: &Self::AppRemotePeer) -> Result<()> {
+        info!("on_talking_while_muted():");
+
+        let env = self.java_env()?;
+
+        jni_call_method(
+            &env,
+            self.jni_call_manager.as_obj(),
+            "onTalkingWhileMuted",
+            jni_args!((
+                remote_peer.as_obj() => org.signal.ringrtc.Remote,
+            ) -> void),
+        )?;
+        Ok(())
+    }
+
     fn on_send_offer(
         &self,
         remote_peer: &Self::AppRemotePeer,
@@ -1062,6 +1078,73 @@ impl Platform for AndroidPlatform {
         }
     }
 
+    fn handle_talking_while_muted(&self, client_id: group_call::ClientId) {
+        info!("handle_talking_while_muted():");
+
+        let env = match self.java_env() {
+            Ok(v) => v,
+            Err(error) => {
+                error!("{:?}", error);
+                return;
+            }
+        };
+
+        let result = jni_call_method(
+            &env,
+            self.jni_call_manager.as_obj(),
+            "handleTalkingWhileMuted",
+            jni_args!((
+                client_id as jlong => long,
+            ) -> void),
+        );
+        if result.is_err() {
+            error!("jni_call_method: {:?}", result.err());
+        }
+    }
+
+    fn handle_spotlight_changed(
+        &self,
+        client_id: group_call::ClientId,
+        spotlight_demux_id: Option<DemuxId>,
+    ) {
+        info!("handle_spotlight_changed(): {:?}", spotlight_demux_id);
+
+        let env = match self.java_env() {
+            Ok(v) => v,
+            Err(error) => {
+                error!("{:?}", error);
+                return;
+            }
+        };
+
+        // Set a frame capacity of min (5) + objects (1).
+        let capacity = 6;
+        let _ = env.with_local_frame(capacity, || {
+            let jni_demux_id = match self.get_optional_u32_long_object(&env, spotlight_demux_id) {
+                Ok(v) => v,
+                Err(error) => {
+                    error!("jni_demux_id: {:?}", error);
+                    return Ok(JObject::null());
+                }
+            };
+
+            let result = jni_call_method(
+                &env,
+                self.jni_call_manager.as_obj(),
+                "handleSpotlightChanged",
+                jni_args!((
+                    client_id as jlong => long,
+                    jni_demux_id => java.lang.Long,
+                ) -> void),
+            );
+            if result.is_err() {
+                error!("jni_call_method: {:?}", result.err());
+            }
+
+            Ok(JObject::null())
+        });
+    }
+
     fn handle_join_state_changed(
         &self,
         client_id: group_call::ClientId,
@@ -1103,8 +1186,8 @@ impl Platform for AndroidPlatform {
         };
         let jni_call_manager = self.jni_call_manager.as_obj();
 
-        // Set a frame capacity of min (5) + objects (2) + elements (N * 2 object per element).
-        let capacity = (7 + remote_device_states.len() * 2) as i32;
+        // Set a frame capacity of min (5) + objects (2) + elements (N * 5 object per element).
+        let capacity = (7 + remote_device_states.len() * 5) as i32;
         let _ = env.with_local_frame(capacity, || {
             let jni_client_id = client_id as jlong;
 
@@ -1188,6 +1271,38 @@ impl Platform for AndroidPlatform {
                     }
                 };
 
+                let jni_video_slot = match self.get_optional_u32_long_object(
+                    &env,
+                    remote_device_state.video_slot.map(|slot| slot as u32),
+                ) {
+                    Ok(v) => v,
+                    Err(error) => {
+                        error!("jni_video_slot: {:?}", error);
+                        continue;
+                    }
+                };
+
+                let jni_display_name = match &remote_device_state.profile.display_name {
+                    None => JObject::null(),
+                    Some(display_name) => match env.new_string(display_name) {
+                        Ok(v) => JObject::from(v),
+                        Err(error) => {
+                            error!("jni_display_name: {:?}", error);
+                            continue;
+                        }
+                    },
+                };
+                let jni_avatar_hash = match &remote_device_state.profile.avatar_hash {
+                    None => JObject::null(),
+                    Some(avatar_hash) => match env.byte_array_from_slice(avatar_hash) {
+                        Ok(v) => JObject::from(v),
+                        Err(error) => {
+                            error!("jni_avatar_hash: {:?}", error);
+                            continue;
+                        }
+                    },
+                };
+
                 let args = jni_args!((
                     jni_demux_id => long,
                     jni_user_id_byte_array => [byte],
@@ -1200,6 +1315,10 @@ impl Platform for AndroidPlatform {
                     jni_speaker_time => long,
                     jni_forwarding_video => java.lang.Boolean,
                     remote_device_state.is_higher_resolution_pending => boolean,
+                    remote_device_state.spotlighted => boolean,
+                    jni_display_name => java.lang.String,
+                    jni_avatar_hash => [byte],
+                    jni_video_slot => java.lang.Long,
                 ) -> void);
 
                 let remote_device_state_obj =
@@ -9,17 +9,19 @@
 //! org.signal.ringrtc.CallManager objects.
 
 use jni::objects::{JClass, JObject, JString};
-use jni::sys::{jboolean, jbyteArray, jint, jlong, jobject};
+use jni::sys::{jboolean, jbyteArray, jdouble, jint, jlong, jobject};
 use jni::JNIEnv;
 
 use crate::android::android_platform::AndroidPlatform;
 use crate::android::call_manager;
 use crate::android::call_manager::AndroidCallManager;
 use crate::android::error;
-use crate::common::{CallMediaType, DeviceId};
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::common::{units::DataRate, CallMediaType, DeviceId};
+use crate::core::bandwidth_mode::{BandwidthMode, BandwidthPolicy};
 use crate::core::connection::Connection;
+use crate::core::send_rate_policy::DeviceConditions;
 use crate::core::{group_call, signaling};
+use crate::lite::sfu::DemuxId;
 use crate::webrtc;
 use std::time::Duration;
 
@@ -487,6 +489,104 @@ pub unsafe extern "C" fn Java_org_signal_ringrtc_CallManager_ringrtcGetActiveCal
     }
 }
 
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn Java_org_signal_ringrtc_CallManager_ringrtcSetAudioEnable(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+    enable: jboolean,
+) {
+    match call_manager::set_audio_enable(call_manager as *mut AndroidCallManager, enable != 0) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn Java_org_signal_ringrtc_CallManager_ringrtcSetTalkingWhileMutedConfig(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+    min_level: jint,
+    min_duration_millis: jlong,
+    max_pause_millis: jlong,
+    min_interval_millis: jlong,
+) {
+    match call_manager::set_talking_while_muted_config(
+        call_manager as *mut AndroidCallManager,
+        min_level,
+        min_duration_millis,
+        max_pause_millis,
+        min_interval_millis,
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn Java_org_signal_ringrtc_CallManager_ringrtcAddVideoFrameCropToAspectRatio(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+    width: jint,
+    height: jint,
+) {
+    match call_manager::add_video_frame_crop_to_aspect_ratio(
+        call_manager as *mut AndroidCallManager,
+        width,
+        height,
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn Java_org_signal_ringrtc_CallManager_ringrtcAddVideoFrameScale(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+    width: jint,
+    height: jint,
+) {
+    match call_manager::add_video_frame_scale(
+        call_manager as *mut AndroidCallManager,
+        width,
+        height,
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn Java_org_signal_ringrtc_CallManager_ringrtcClearVideoFrameProcessors(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+) {
+    match call_manager::clear_video_frame_processors(call_manager as *mut AndroidCallManager) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
 #[no_mangle]
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn Java_org_signal_ringrtc_CallManager_ringrtcSetVideoEnable(
@@ -522,6 +622,29 @@ pub extern "C" fn Java_org_signal_ringrtc_CallManager_ringrtcUpdateBandwidthMode
     }
 }
 
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn Java_org_signal_ringrtc_CallManager_ringrtcUpdateBandwidthPolicy(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+    max_send_kbps: jlong,
+    max_receive_kbps: jlong,
+) {
+    match call_manager::update_bandwidth_policy(
+        call_manager as *mut AndroidCallManager,
+        BandwidthPolicy::new(
+            DataRate::from_kbps(max_send_kbps.max(0) as u64),
+            DataRate::from_kbps(max_receive_kbps.max(0) as u64),
+        ),
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
 #[no_mangle]
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn Java_org_signal_ringrtc_CallManager_ringrtcDrop(
@@ -766,6 +889,117 @@ pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcSetOutgoingVid
     }
 }
 
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcSetAudioOnly(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+    client_id: jlong,
+    audio_only: bool,
+) {
+    match call_manager::set_audio_only(
+        call_manager as *mut AndroidCallManager,
+        client_id as group_call::ClientId,
+        audio_only,
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcSetLocalDisplayName(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+    client_id: jlong,
+    display_name: JString,
+) {
+    match call_manager::set_local_display_name(
+        &env,
+        call_manager as *mut AndroidCallManager,
+        client_id as group_call::ClientId,
+        display_name,
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcSetLocalAvatarHash(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+    client_id: jlong,
+    avatar_hash: jbyteArray,
+) {
+    match call_manager::set_local_avatar_hash(
+        &env,
+        call_manager as *mut AndroidCallManager,
+        client_id as group_call::ClientId,
+        avatar_hash,
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcSetRemoteAudioVolume(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+    client_id: jlong,
+    demux_id: jlong,
+    volume: jdouble,
+) {
+    match call_manager::set_remote_audio_volume(
+        call_manager as *mut AndroidCallManager,
+        client_id as group_call::ClientId,
+        demux_id as DemuxId,
+        volume,
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcSetRemoteAudioMuted(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+    client_id: jlong,
+    demux_id: jlong,
+    muted: bool,
+) {
+    match call_manager::set_remote_audio_muted(
+        call_manager as *mut AndroidCallManager,
+        client_id as group_call::ClientId,
+        demux_id as DemuxId,
+        muted,
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
 #[no_mangle]
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcRing(
@@ -828,6 +1062,56 @@ pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcSetBandwidthMo
     }
 }
 
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcSetBandwidthPolicy(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+    client_id: jlong,
+    max_send_kbps: jlong,
+    max_receive_kbps: jlong,
+) {
+    match call_manager::set_bandwidth_policy(
+        call_manager as *mut AndroidCallManager,
+        client_id as group_call::ClientId,
+        BandwidthPolicy::new(
+            DataRate::from_kbps(max_send_kbps.max(0) as u64),
+            DataRate::from_kbps(max_receive_kbps.max(0) as u64),
+        ),
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcSetDeviceConditions(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+    client_id: jlong,
+    low_battery: jboolean,
+    thermal_throttling: jboolean,
+) {
+    match call_manager::set_device_conditions(
+        call_manager as *mut AndroidCallManager,
+        client_id as group_call::ClientId,
+        DeviceConditions {
+            low_battery: low_battery == jni::sys::JNI_TRUE,
+            thermal_throttling: thermal_throttling == jni::sys::JNI_TRUE,
+        },
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
 #[no_mangle]
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcRequestVideo(
@@ -852,6 +1136,52 @@ pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcRequestVideo(
     }
 }
 
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcSetSpotlight(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+    client_id: jlong,
+    jni_demux_id: JObject,
+) {
+    match call_manager::set_spotlight(
+        &env,
+        call_manager as *mut AndroidCallManager,
+        client_id as group_call::ClientId,
+        jni_demux_id,
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcSetVideoRequestPolicy(
+    env: JNIEnv<'static>,
+    _object: JObject,
+    call_manager: jlong,
+    client_id: jlong,
+    jni_slots: JObject,
+    jni_pinned_demux_id: JObject,
+) {
+    match call_manager::set_video_request_policy(
+        &env,
+        call_manager as *mut AndroidCallManager,
+        client_id as group_call::ClientId,
+        jni_slots,
+        jni_pinned_demux_id,
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            error::throw_error(&env, e);
+        }
+    }
+}
+
 #[no_mangle]
 #[allow(non_snake_case)]
 pub unsafe extern "C" fn Java_org_signal_ringrtc_GroupCall_ringrtcSetGroupMembers(
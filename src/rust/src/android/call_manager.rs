@@ -21,18 +21,24 @@ use crate::android::logging::init_logging;
 use crate::android::webrtc_peer_connection_factory::*;
 
 use crate::common::{CallId, CallMediaType, DeviceId, Result};
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::core::bandwidth_mode::{BandwidthMode, BandwidthPolicy};
 use crate::core::call_manager::CallManager;
 use crate::core::connection::Connection;
+use crate::core::send_rate_policy::DeviceConditions;
+use crate::core::speaker_detector::TalkingWhileMutedConfig;
 use crate::core::util::{ptr_as_box, ptr_as_mut};
 use crate::core::{group_call, signaling};
 use crate::error::RingRtcError;
-use crate::lite::{http, sfu::GroupMember};
+use crate::lite::{
+    http,
+    sfu::{DemuxId, GroupMember},
+};
 use crate::webrtc;
 use crate::webrtc::media;
-use crate::webrtc::peer_connection::PeerConnection;
+use crate::webrtc::peer_connection::{AudioLevel, PeerConnection};
 use crate::webrtc::peer_connection_factory::{self as pcf, PeerConnectionFactory};
 use crate::webrtc::peer_connection_observer::PeerConnectionObserver;
+use crate::webrtc::video_frame_processor::{checked_frame_size, CropProcessor, ScaleProcessor};
 
 /// Public type for Android CallManager
 pub type AndroidCallManager = CallManager<AndroidPlatform>;
@@ -531,6 +537,74 @@ pub fn get_active_call_context(call_manager: *mut AndroidCallManager) -> Result<
     Ok(android_call_context.to_jni().as_obj().into_inner())
 }
 
+/// CMI request to set the audio status
+pub fn set_audio_enable(call_manager: *mut AndroidCallManager, enable: bool) -> Result<()> {
+    info!("set_audio_enable():");
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_direct_outgoing_audio_muted(!enable)
+}
+
+/// CMI request to configure when to notify that the user is talking while muted
+pub fn set_talking_while_muted_config(
+    call_manager: *mut AndroidCallManager,
+    min_level: jint,
+    min_duration_millis: jlong,
+    max_pause_millis: jlong,
+    min_interval_millis: jlong,
+) -> Result<()> {
+    info!("set_talking_while_muted_config():");
+
+    let config = TalkingWhileMutedConfig {
+        min_level: min_level as AudioLevel,
+        min_duration: Duration::from_millis(min_duration_millis as u64),
+        max_pause: Duration::from_millis(max_pause_millis as u64),
+        min_interval: Duration::from_millis(min_interval_millis as u64),
+    };
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_talking_while_muted_config(config)
+}
+
+pub fn add_video_frame_crop_to_aspect_ratio(
+    call_manager: *mut AndroidCallManager,
+    width: jint,
+    height: jint,
+) -> Result<()> {
+    info!(
+        "add_video_frame_crop_to_aspect_ratio(): {}:{}",
+        width, height
+    );
+
+    let (width, height) = checked_frame_size(width.into(), height.into())?;
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.add_video_frame_processor(Box::new(CropProcessor::center_to_aspect_ratio(
+        width, height,
+    )));
+    Ok(())
+}
+
+pub fn add_video_frame_scale(
+    call_manager: *mut AndroidCallManager,
+    width: jint,
+    height: jint,
+) -> Result<()> {
+    info!("add_video_frame_scale(): {}x{}", width, height);
+
+    let (width, height) = checked_frame_size(width.into(), height.into())?;
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.add_video_frame_processor(Box::new(ScaleProcessor::new(width, height)));
+    Ok(())
+}
+
+pub fn clear_video_frame_processors(call_manager: *mut AndroidCallManager) -> Result<()> {
+    info!("clear_video_frame_processors():");
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.clear_video_frame_processors();
+    Ok(())
+}
+
 /// CMI request to set the video status
 pub fn set_video_enable(call_manager: *mut AndroidCallManager, enable: bool) -> Result<()> {
     info!("set_video_enable():");
@@ -559,6 +633,17 @@ pub fn update_bandwidth_mode(
     active_connection.inject_update_bandwidth_mode(bandwidth_mode)
 }
 
+/// Request to update the bandwidth policy on the direct connection
+pub fn update_bandwidth_policy(
+    call_manager: *mut AndroidCallManager,
+    bandwidth_policy: BandwidthPolicy,
+) -> Result<()> {
+    info!("update_bandwidth_policy():");
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.update_bandwidth_policy(bandwidth_policy)
+}
+
 /// CMI request to drop the active call
 pub fn drop_call(call_manager: *mut AndroidCallManager, call_id: jlong) -> Result<()> {
     let call_id = CallId::from(call_id);
@@ -675,7 +760,7 @@ pub fn create_group_call_client(
     );
 
     // This is safe because the track given to us should still be alive.
-    let outgoing_video_track = media::VideoTrack::new(
+    let platform_video_track = media::VideoTrack::new(
         unsafe {
             webrtc::Arc::from_borrowed(webrtc::ptr::BorrowedRc::from_ptr(
                 native_video_track_borrowed_rc as *const media::RffiVideoTrack,
@@ -691,7 +776,16 @@ pub fn create_group_call_client(
     };
 
     let call_manager = unsafe { ptr_as_mut(call_manager)? };
-    call_manager.create_group_call_client(
+
+    // Send the captured video through the call manager's frame processors.
+    let outgoing_video_source = peer_connection_factory.create_processed_video_source(
+        &platform_video_track,
+        call_manager.video_frame_processors(),
+    )?;
+    let outgoing_video_track =
+        peer_connection_factory.create_outgoing_video_track(&outgoing_video_source)?;
+
+    let client_id = call_manager.create_group_call_client(
         group_id,
         sfu_url,
         hkdf_extra_info,
@@ -700,7 +794,10 @@ pub fn create_group_call_client(
         outgoing_audio_track,
         outgoing_video_track,
         None,
-    )
+    )?;
+    // Only this client, so the source stops processing when the client is deleted.
+    call_manager.set_group_call_outgoing_video_source(client_id, outgoing_video_source);
+    Ok(client_id)
 }
 
 pub fn delete_group_call_client(
@@ -776,6 +873,106 @@ pub fn set_outgoing_video_muted(
     Ok(())
 }
 
+pub fn set_audio_only(
+    call_manager: *mut AndroidCallManager,
+    client_id: group_call::ClientId,
+    audio_only: bool,
+) -> Result<()> {
+    info!("set_audio_only(): id: {}", client_id);
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_audio_only(client_id, audio_only);
+    Ok(())
+}
+
+pub fn set_spotlight(
+    env: &JNIEnv,
+    call_manager: *mut AndroidCallManager,
+    client_id: group_call::ClientId,
+    jni_demux_id: JObject,
+) -> Result<()> {
+    info!("set_spotlight(): id: {}", client_id);
+
+    let demux_id = if jni_demux_id.is_null() {
+        None
+    } else {
+        // We have java.lang.Long, so we need to invoke the function to get the actual
+        // long value that is attached to it.
+        Some(
+            env.call_method(jni_demux_id, "longValue", jni_signature!(() -> long), &[])?
+                .j()? as DemuxId,
+        )
+    };
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_spotlight(client_id, demux_id);
+    Ok(())
+}
+
+pub fn set_local_display_name(
+    env: &JNIEnv,
+    call_manager: *mut AndroidCallManager,
+    client_id: group_call::ClientId,
+    display_name: JString,
+) -> Result<()> {
+    info!("set_local_display_name(): id: {}", client_id);
+
+    let display_name = if display_name.is_null() {
+        None
+    } else {
+        Some(env.get_string(display_name)?.into())
+    };
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_local_display_name(client_id, display_name);
+    Ok(())
+}
+
+pub fn set_local_avatar_hash(
+    env: &JNIEnv,
+    call_manager: *mut AndroidCallManager,
+    client_id: group_call::ClientId,
+    avatar_hash: jbyteArray,
+) -> Result<()> {
+    info!("set_local_avatar_hash(): id: {}", client_id);
+
+    let avatar_hash = if avatar_hash.is_null() {
+        None
+    } else {
+        Some(env.convert_byte_array(avatar_hash)?)
+    };
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_local_avatar_hash(client_id, avatar_hash);
+    Ok(())
+}
+
+pub fn set_remote_audio_volume(
+    call_manager: *mut AndroidCallManager,
+    client_id: group_call::ClientId,
+    demux_id: DemuxId,
+    volume: f64,
+) -> Result<()> {
+    info!("set_remote_audio_volume(): id: {}", client_id);
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_remote_audio_volume(client_id, demux_id, volume);
+    Ok(())
+}
+
+pub fn set_remote_audio_muted(
+    call_manager: *mut AndroidCallManager,
+    client_id: group_call::ClientId,
+    demux_id: DemuxId,
+    muted: bool,
+) -> Result<()> {
+    info!("set_remote_audio_muted(): id: {}", client_id);
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_remote_audio_muted(client_id, demux_id, muted);
+    Ok(())
+}
+
 pub fn group_ring(
     env: &JNIEnv,
     call_manager: *mut AndroidCallManager,
@@ -818,6 +1015,30 @@ pub fn set_bandwidth_mode(
     Ok(())
 }
 
+pub fn set_bandwidth_policy(
+    call_manager: *mut AndroidCallManager,
+    client_id: group_call::ClientId,
+    bandwidth_policy: BandwidthPolicy,
+) -> Result<()> {
+    info!("set_bandwidth_policy(): id: {}", client_id);
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_bandwidth_policy(client_id, bandwidth_policy);
+    Ok(())
+}
+
+pub fn set_device_conditions(
+    call_manager: *mut AndroidCallManager,
+    client_id: group_call::ClientId,
+    device_conditions: DeviceConditions,
+) -> Result<()> {
+    info!("set_device_conditions(): id: {}", client_id);
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_device_conditions(client_id, device_conditions);
+    Ok(())
+}
+
 pub fn request_video(
     env: &JNIEnv,
     call_manager: *mut AndroidCallManager,
@@ -897,6 +1118,74 @@ pub fn request_video(
     Ok(())
 }
 
+pub fn set_video_request_policy(
+    env: &JNIEnv,
+    call_manager: *mut AndroidCallManager,
+    client_id: group_call::ClientId,
+    jni_slots: JObject,
+    jni_pinned_demux_id: JObject,
+) -> Result<()> {
+    info!("set_video_request_policy(): id: {}", client_id);
+
+    let policy = if jni_slots.is_null() {
+        None
+    } else {
+        // Convert Java list of VideoTileSlot into Rust Vec<group_call::VideoTileSlot>.
+        let jni_slot_list = env.get_list(jni_slots)?;
+        let mut slots: Vec<group_call::VideoTileSlot> = Vec::new();
+        for jni_slot in jni_slot_list.iter()? {
+            const INT_TYPE: &str = jni_signature!(int);
+            const NULLABLE_INT_TYPE: &str = jni_signature!(java.lang.Integer);
+
+            let kind = jni_get_field(env, jni_slot, "kind", INT_TYPE)?.i()?;
+            let count = jni_get_field(env, jni_slot, "count", INT_TYPE)?.i()? as u16;
+            let width = jni_get_field(env, jni_slot, "width", INT_TYPE)?.i()? as u16;
+            let height = jni_get_field(env, jni_slot, "height", INT_TYPE)?.i()? as u16;
+
+            let framerate = jni_get_field(env, jni_slot, "framerate", NULLABLE_INT_TYPE)?.l()?;
+            let framerate = if framerate.is_null() {
+                None
+            } else {
+                // We have java.lang.Integer, so we need to invoke the function to get the actual
+                // int value that is attached to it.
+                Some(
+                    env.call_method(framerate, "intValue", jni_signature!(() -> int), &[])?
+                        .i()? as u16,
+                )
+            };
+
+            if let Some(slot) =
+                group_call::VideoTileSlot::from_kind(kind, count, width, height, framerate)
+            {
+                slots.push(slot);
+            }
+        }
+
+        let pinned_demux_id = if jni_pinned_demux_id.is_null() {
+            None
+        } else {
+            Some(
+                env.call_method(
+                    jni_pinned_demux_id,
+                    "longValue",
+                    jni_signature!(() -> long),
+                    &[],
+                )?
+                .j()? as DemuxId,
+            )
+        };
+
+        Some(group_call::VideoRequestPolicy {
+            slots,
+            pinned_demux_id,
+        })
+    };
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_video_request_policy(client_id, policy);
+    Ok(())
+}
+
 pub fn set_group_members(
     env: &JNIEnv,
     call_manager: *mut AndroidCallManager,
@@ -3,12 +3,11 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
-use log::{debug, info};
+use log::{debug, info, warn};
 
 use ringrtc::{
     common::{
         actor::{Actor, Stopper},
-        units::DataRate,
         CallId, CallMediaType, DeviceId, Result,
     },
     core::{bandwidth_mode::BandwidthMode, call_manager::CallManager, group_call, signaling},
@@ -18,22 +17,46 @@ use ringrtc::{
         NativePlatform, PeerId, SignalingSender,
     },
     simnet::{
+        pcap::PacketCapture,
         router,
-        router::{LinkConfig, Router},
+        router::{LinkConfig, Protocol, Router},
+        scenario::{LinkChange, Scenario, ScenarioInterface, ScenarioTarget},
+        stun::StunServer,
+        turn::TurnServer,
     },
     webrtc::{
         injectable_network,
         injectable_network::InjectableNetwork,
-        media::{VideoFrame, VideoPixelFormat, VideoSink, VideoSource},
+        media::{AudioFrame, AudioSink, VideoFrame, VideoSink, VideoSource},
         network::NetworkInterfaceType,
         peer_connection::AudioLevel,
         peer_connection_factory::{self as pcf, IceServer, PeerConnectionFactory},
         peer_connection_observer::NetworkRoute,
+        test_media::{
+            MediaQualityMeter, TestAudioAnalyzer, TestAudioGenerator, TestTone, TestVideoAnalyzer,
+            TestVideoConfig, TestVideoGenerator,
+        },
     },
 };
-use std::{collections::HashMap, thread, time::Duration};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+const MEASUREMENT_USAGE: &str =
+    "usage: cli measure <link profile | network scenario .json> <seconds> [output.json | -] [capture.pcap]";
+const MEASUREMENT_REPORT_INTERVAL: Duration = Duration::from_secs(1);
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let measuring = args.get(1).map(String::as_str) == Some("measure");
+    // The measurement may be written to stdout, so keep the logs out of it.
+    LOG.to_stderr.store(measuring, Ordering::Relaxed);
     log::set_logger(&LOG).expect("set logger");
     log::set_max_level(log::LevelFilter::Debug);
 
@@ -44,28 +67,26 @@ fn main() {
     #[cfg(not(debug_assertions))]
     ringrtc::webrtc::logging::set_logger(log::LevelFilter::Warn);
 
+    if measuring {
+        measure(&args[2..]);
+        return;
+    }
+
     let hide_ip = false;
-    // TODO: Real STUN/TURN servers.
-    let ice_server = IceServer::none();
     let stopper = Stopper::new();
     let signaling_server = SignalingServer::start(&stopper).expect("Start signaling server");
     let router = Router::start(&stopper).expect("Start router");
-    let good_link = LinkConfig {
-        delay_min: Duration::from_millis(10),
-        delay_max: Duration::from_millis(20),
-        loss_probabilty: 0.00,
-        repeated_loss_probability: 0.00,
-        rate: DataRate::from_mbps(5),
-        queue_size: DataRate::from_mbps(5) * Duration::from_millis(500),
-    };
-    let bad_link = LinkConfig {
-        delay_min: Duration::from_millis(100),
-        delay_max: Duration::from_millis(200),
-        loss_probabilty: 0.005,
-        repeated_loss_probability: 0.70,
-        rate: DataRate::from_kbps(256),
-        queue_size: DataRate::from_kbps(256) * Duration::from_secs(500),
-    };
+    let good_link = LinkConfig::profile("good").expect("good link");
+    let bad_link = LinkConfig::profile("bad").expect("bad link");
+    let turn_server = TurnServer::start(
+        "3.0.0.1".parse().expect("parse IP address"),
+        good_link.clone(),
+        good_link.clone(),
+        &router,
+        &stopper,
+    )
+    .expect("Start TURN server");
+    let ice_server = simnet_ice_server(turn_server.urls(&[Protocol::Udp, Protocol::Tcp]));
 
     let caller = CallEndpoint::start(
         "caller",
@@ -179,12 +200,275 @@ fn main() {
     stopper.stop_all_and_join();
 }
 
+// Makes a call from caller to callee over the given link profile, sending test
+// video and audio, and reports latency, A/V skew and freezes as JSON.
+fn measure(args: &[String]) {
+    let network = args.first().expect(MEASUREMENT_USAGE);
+    let scenario = if network.ends_with(".json") {
+        let json = std::fs::read_to_string(network).expect("read network scenario");
+        Scenario::from_json(&json).expect("parse network scenario")
+    } else {
+        link_profile_scenario(network)
+    };
+    let duration = Duration::from_secs(
+        args.get(1)
+            .and_then(|seconds| seconds.parse().ok())
+            .expect(MEASUREMENT_USAGE),
+    );
+    // "-" means stdout, so a capture can be given without an output file.
+    let output = args.get(2).filter(|output| *output != "-");
+    let capture = args
+        .get(3)
+        .map(|path| PacketCapture::create(path).expect("create packet capture"));
+
+    let stopper = Stopper::new();
+    let signaling_server = SignalingServer::start(&stopper).expect("Start signaling server");
+    let router = Router::start(&stopper).expect("Start router");
+    // What made it through the network, as each endpoint received it.
+    router.set_delivered_capture(capture.clone());
+    for nat in &scenario.nats {
+        router.add_nat(
+            nat.public_ip,
+            scenario.private_ips(nat),
+            nat.config().expect("NAT config"),
+        );
+    }
+    let mut ice_urls = Vec::new();
+    if let Some(ip) = scenario.stun_server {
+        let link = LinkConfig::profile("good").expect("good link");
+        let stun_server =
+            StunServer::start(ip, link.clone(), link, &router).expect("Start STUN server");
+        ice_urls.push(stun_server.url());
+    }
+    let mut hide_ip = false;
+    if let Some(relay) = &scenario.relay {
+        let link = relay.link_config().expect("relay link");
+        let turn_server = TurnServer::start(relay.ip, link.clone(), link, &router, &stopper)
+            .expect("Start TURN server");
+        ice_urls.extend(turn_server.urls(&relay.transports));
+        hide_ip = relay.relay_only;
+    }
+    let ice_server = simnet_ice_server(ice_urls);
+
+    let caller = CallEndpoint::start(
+        "caller",
+        1 as DeviceId,
+        hide_ip,
+        &ice_server,
+        &signaling_server,
+        &router,
+        &stopper,
+    )
+    .expect("Start caller");
+    let callee = CallEndpoint::start(
+        "callee",
+        1 as DeviceId,
+        hide_ip,
+        &ice_server,
+        &signaling_server,
+        &router,
+        &stopper,
+    )
+    .expect("Start callee");
+    let endpoints = ScenarioEndpoints(
+        vec![caller.clone(), callee.clone()]
+            .into_iter()
+            .map(|endpoint| (endpoint.peer_id.clone(), endpoint))
+            .collect(),
+    );
+    if let Some(interface) = scenario
+        .interfaces
+        .iter()
+        .find(|interface| !endpoints.0.contains_key(&interface.endpoint))
+    {
+        panic!(
+            "Unknown endpoint {:?}; expected caller or callee",
+            interface.endpoint
+        );
+    }
+    // The scenario's times start now, a few seconds before media is sent.
+    scenario
+        .start(Box::new(endpoints), &stopper)
+        .expect("Start network scenario");
+
+    let call_id = CallId::new(0xCA111D);
+    caller.create_outgoing_call(
+        &callee.peer_id,
+        call_id,
+        CallMediaType::Video,
+        caller.device_id,
+    );
+    thread::sleep(Duration::from_secs(2));
+    callee.accept_incoming_call(call_id);
+    // Let it connect before looking at what's received.
+    thread::sleep(Duration::from_secs(2));
+
+    let meter = Arc::new(Mutex::new(MediaQualityMeter::new()));
+    let audio_analyzer = TestAudioAnalyzer::new();
+    callee.set_incoming_audio_sink(Box::new(audio_analyzer.clone()));
+    caller.send_generated_video(640, 480, Duration::from_millis(33));
+    caller.send_generated_audio(meter.clone());
+
+    let mut reports = Vec::new();
+    let end = Instant::now() + duration;
+    while Instant::now() < end {
+        thread::sleep(MEASUREMENT_REPORT_INTERVAL);
+        let mut meter = meter.lock().expect("lock meter");
+        for frame in callee.video_analyzer.take_received() {
+            meter.video_frame_received(&frame);
+        }
+        for detected in audio_analyzer.take_detected() {
+            meter.audio_marker_received(&detected);
+        }
+        let report = meter.take_report(Instant::now());
+        info!(
+            "Measured: {}",
+            serde_json::to_string(&report).expect("serialize report")
+        );
+        reports.push(report);
+    }
+
+    caller.hangup();
+    callee.hangup();
+    thread::sleep(Duration::from_secs(1));
+    stopper.stop_all_and_join();
+
+    let mut measurement = serde_json::json!({
+        "network": network,
+        "duration_secs": duration.as_secs(),
+        "report_interval_ms": MEASUREMENT_REPORT_INTERVAL.as_millis() as u64,
+        "reports": reports,
+    });
+    // With hide_ip, only relayed candidates should be signaled.
+    let candidates = signaling_server.take_signaled_candidates();
+    let hide_ip_leaks: Vec<&SignaledCandidate> = candidates
+        .iter()
+        .filter(|candidate| hide_ip && candidate.typ != "relay")
+        .collect();
+    for leak in &hide_ip_leaks {
+        warn!("Signaled a non-relayed candidate with hide_ip: {:?}", leak);
+    }
+    let candidate_json = |candidate: &SignaledCandidate| {
+        serde_json::json!({
+            "sender": candidate.sender,
+            "type": candidate.typ,
+            "address": candidate.address,
+        })
+    };
+    measurement["hide_ip"] = serde_json::json!(hide_ip);
+    measurement["signaled_candidates"] = candidates
+        .iter()
+        .map(candidate_json)
+        .collect::<serde_json::Value>();
+    measurement["hide_ip_leaks"] = hide_ip_leaks
+        .into_iter()
+        .map(candidate_json)
+        .collect::<serde_json::Value>();
+    if let Some(capture) = capture {
+        capture.flush().expect("flush packet capture");
+        measurement["capture"] = serde_json::json!(capture.summary());
+    }
+    let measurement = serde_json::to_string_pretty(&measurement).expect("serialize measurement");
+    match output {
+        Some(path) => std::fs::write(path, measurement).expect("write measurement"),
+        None => println!("{}", measurement),
+    }
+}
+
+// For the simulated STUN and TURN servers.  Only the TURN server uses the credentials.
+fn simnet_ice_server(urls: Vec<String>) -> IceServer {
+    IceServer::new(
+        TurnServer::USERNAME.to_string(),
+        TurnServer::PASSWORD.to_string(),
+        urls,
+    )
+}
+
+// A caller and a callee, each with one Wi-Fi interface using the link profile.
+fn link_profile_scenario(profile: &str) -> Scenario {
+    if LinkConfig::profile(profile).is_none() {
+        panic!(
+            "Unknown link profile {:?}; expected one of {:?} or a scenario .json file",
+            profile,
+            LinkConfig::PROFILE_NAMES
+        );
+    }
+    let interface = |endpoint: &str, ip: &str| ScenarioInterface {
+        endpoint: endpoint.to_string(),
+        name: "wifi".to_string(),
+        typ: NetworkInterfaceType::Wifi,
+        ip: ip.parse().expect("parse IP address"),
+        preference: 1,
+        behind_nat: None,
+        link: LinkChange {
+            profile: Some(profile.to_string()),
+            ..Default::default()
+        },
+    };
+    Scenario {
+        interfaces: vec![
+            interface("caller", "1.1.0.1"),
+            interface("callee", "2.1.0.1"),
+        ],
+        events: vec![],
+        relay: None,
+        nats: vec![],
+        stun_server: None,
+    }
+}
+
+// Applies a network scenario to the endpoints with the same PeerId.
+struct ScenarioEndpoints(HashMap<PeerId, CallEndpoint>);
+
+impl ScenarioTarget for ScenarioEndpoints {
+    fn add_interface(
+        &self,
+        interface: &ScenarioInterface,
+        send_config: &LinkConfig,
+        receive_config: &LinkConfig,
+    ) {
+        if let Some(endpoint) = self.0.get(&interface.endpoint) {
+            endpoint.add_network_interface(
+                &interface.name,
+                interface.typ,
+                &interface.ip.to_string(),
+                interface.preference,
+                send_config,
+                receive_config,
+            );
+        }
+    }
+
+    fn remove_interface(&self, interface: &ScenarioInterface) {
+        if let Some(endpoint) = self.0.get(&interface.endpoint) {
+            endpoint.remove_network_interface(&interface.name, &interface.ip.to_string());
+        }
+    }
+
+    fn set_link_configs(
+        &self,
+        interface: &ScenarioInterface,
+        send_config: &LinkConfig,
+        receive_config: &LinkConfig,
+    ) {
+        if let Some(endpoint) = self.0.get(&interface.endpoint) {
+            endpoint.set_network_interface_links(
+                &interface.ip.to_string(),
+                send_config,
+                receive_config,
+            );
+        }
+    }
+}
+
 #[derive(Clone)]
 struct CallEndpoint {
     // We keep a copy of these outside of the actor state
     // so we can know them in any thread.
     peer_id: PeerId,
     device_id: DeviceId,
+    // What the incoming video sink saw, if it was test video.
+    video_analyzer: TestVideoAnalyzer,
     // There is probably a way to have a CallEndpoint without a thread,
     // but this is the easiest way to get around the nasty dependency cycle
     // of CallEndpoint -> CallManger -> NativePlatform -> CallEndpoint.
@@ -211,6 +495,8 @@ struct CallEndpointState {
     actor: Actor<Self>,
     // Keep a copy around to be able to push out video frames
     outgoing_video_source: VideoSource,
+    // Keep a copy around to be able to push out audio samples
+    pcf: PeerConnectionFactory,
 }
 
 impl CallEndpoint {
@@ -229,20 +515,29 @@ impl CallEndpoint {
         let ice_server = ice_server.clone();
         let signaling_server: SignalingServer = signaling_server.clone();
         let router = router.clone();
+        let video_analyzer = TestVideoAnalyzer::new();
+        let video_analyzer_for_actor = video_analyzer.clone();
 
         Ok(Self::from_actor(
             peer_id.clone(),
             device_id,
+            video_analyzer,
             Actor::start(stopper.clone(), move |actor| {
                 // Constructing this is a funny way of getting a clone of the CallEndpoint
                 // on the actor's thread so we can have it in the actor's state so we can
                 // pass it to the NativePlatform/CallManager.
                 // This is a little weird, but it seems nicer than doing some kind of
                 // Option<CallManager> thing that we have to set later.
-                let endpoint = Self::from_actor(peer_id.clone(), device_id, actor.clone());
+                let endpoint = Self::from_actor(
+                    peer_id.clone(),
+                    device_id,
+                    video_analyzer_for_actor.clone(),
+                    actor.clone(),
+                );
 
                 let mut pcf = PeerConnectionFactory::new(pcf::Config {
                     use_injectable_network: true, // Set up packet flow
+                    use_injectable_audio: true,   // For generated audio
                     ..Default::default()
                 })?;
                 info!(
@@ -261,6 +556,7 @@ impl CallEndpoint {
                         source: packet.source,
                         dest: packet.dest,
                         data: packet.data,
+                        protocol: packet.protocol,
                     });
                 }));
 
@@ -271,6 +567,7 @@ impl CallEndpoint {
                 let state_handler = Box::new(endpoint.clone());
                 let incoming_video_sink = Box::new(LoggingVideoSink {
                     peer_id: peer_id.clone(),
+                    analyzer: video_analyzer_for_actor,
                 });
 
                 // Fill in fake group call things
@@ -284,11 +581,12 @@ impl CallEndpoint {
                     state_handler,
                     group_handler,
                 );
-                let call_manager = CallManager::new(platform, http_client)?;
+                let mut call_manager = CallManager::new(platform, http_client)?;
 
                 // And a CallContext.  We'll use the same context for each call.
                 let outgoing_audio_track = pcf.create_outgoing_audio_track()?;
                 let outgoing_video_source = pcf.create_outgoing_video_source()?;
+                call_manager.set_outgoing_video_source(Some(outgoing_video_source.clone()))?;
                 let outgoing_video_track =
                     pcf.create_outgoing_video_track(&outgoing_video_source)?;
                 let call_context = NativeCallContext::new(
@@ -311,22 +609,29 @@ impl CallEndpoint {
 
                     actor,
                     outgoing_video_source,
+                    pcf,
                 })
             })?,
         ))
     }
 
-    fn from_actor(peer_id: PeerId, device_id: DeviceId, actor: Actor<CallEndpointState>) -> Self {
+    fn from_actor(
+        peer_id: PeerId,
+        device_id: DeviceId,
+        video_analyzer: TestVideoAnalyzer,
+        actor: Actor<CallEndpointState>,
+    ) -> Self {
         Self {
             peer_id,
             device_id,
+            video_analyzer,
             actor,
         }
     }
 
     pub fn add_network_interface(
         &self,
-        name: &'static str,
+        name: &str,
         typ: NetworkInterfaceType,
         ip: &str,
         preference: u16,
@@ -336,13 +641,14 @@ impl CallEndpoint {
         let ip = ip.parse().expect("parse IP address");
 
         // To send across threads
+        let name = name.to_string();
         let send_config = send_config.clone();
         let receive_config = receive_config.clone();
 
         self.actor.send(move |state| {
             // Adding it to the network causes the PeerConnections to learn about it through
             // the NetworkMonitor.
-            state.network.add_interface(name, typ, ip, preference);
+            state.network.add_interface(&name, typ, ip, preference);
             // Adding it to the router applies the config to the up and down links
             // and allow routing packets to and from other endpoints.alloc
             // Passing in network.get_receiver() causes packets from the PeerConnections
@@ -355,10 +661,11 @@ impl CallEndpoint {
                     send_config,
                     receive_config,
                     Box::new(move |packet: router::Packet| {
-                        network_as_receiver.receive_udp(injectable_network::Packet {
+                        network_as_receiver.receive_packet(injectable_network::Packet {
                             source: packet.source,
                             dest: packet.dest,
                             data: packet.data,
+                            protocol: packet.protocol,
                         });
                     }),
                 )
@@ -371,6 +678,41 @@ impl CallEndpoint {
         });
     }
 
+    pub fn remove_network_interface(&self, name: &str, ip: &str) {
+        let ip = ip.parse().expect("parse IP address");
+
+        // To send across threads
+        let name = name.to_string();
+
+        self.actor.send(move |state| {
+            state.network.remove_interface(&name);
+            state.router.remove_interface(ip);
+            debug!(
+                "Removed the interface for {:?} from {:?}.{:?}",
+                ip, state.peer_id, state.device_id
+            );
+        });
+    }
+
+    pub fn set_network_interface_links(
+        &self,
+        ip: &str,
+        send_config: &LinkConfig,
+        receive_config: &LinkConfig,
+    ) {
+        let ip = ip.parse().expect("parse IP address");
+
+        // To send across threads
+        let send_config = send_config.clone();
+        let receive_config = receive_config.clone();
+
+        self.actor.send(move |state| {
+            state
+                .router
+                .set_link_configs(ip, send_config, receive_config);
+        });
+    }
+
     fn create_outgoing_call(
         &self,
         callee_id: &PeerId,
@@ -404,6 +746,17 @@ impl CallEndpoint {
         });
     }
 
+    pub fn set_incoming_audio_sink(&self, sink: Box<dyn AudioSink>) {
+        self.actor.send(move |state| {
+            state
+                .call_manager
+                .active_connection()
+                .expect("active connection")
+                .set_incoming_audio_sink(Some(sink))
+                .expect("set incoming audio sink");
+        });
+    }
+
     // A callback from SignalingServer.
     fn receive_signaling(
         &self,
@@ -479,25 +832,91 @@ impl CallEndpoint {
     fn send_generated_video(&self, width: u32, height: u32, duration: Duration) {
         fn send_one_frame_and_schedule_another(
             state: &mut CallEndpointState,
-            width: u32,
-            height: u32,
+            mut generator: TestVideoGenerator,
             duration: Duration,
         ) {
-            let rgba_data: Vec<u8> = (0..(width * height * 4)).map(|i: u32| i as u8).collect();
             state
                 .outgoing_video_source
-                .push_frame(VideoFrame::copy_from_slice(
-                    width,
-                    height,
-                    VideoPixelFormat::Rgba,
-                    &rgba_data,
-                ));
+                .push_frame(generator.next_frame());
             state.actor.send_delayed(duration, move |state| {
-                send_one_frame_and_schedule_another(state, width, height, duration);
+                send_one_frame_and_schedule_another(state, generator, duration);
             });
         }
+        let generator = TestVideoGenerator::new(TestVideoConfig {
+            width,
+            height,
+            ..Default::default()
+        });
+        self.actor.send(move |state| {
+            send_one_frame_and_schedule_another(state, generator, duration);
+        });
+    }
+
+    // Sends a DTMF digit every second as a marker and tells the meter when each
+    // one starts.
+    fn send_generated_audio(&self, meter: Arc<Mutex<MediaQualityMeter>>) {
+        const SAMPLE_RATE: u32 = 48000;
+        const CHUNK_DURATION: Duration = Duration::from_millis(10);
+
+        struct GeneratedAudio {
+            generator: TestAudioGenerator,
+            started: Instant,
+            chunks_sent: u32,
+            playing: Option<char>,
+            meter: Arc<Mutex<MediaQualityMeter>>,
+        }
+
+        fn send_due_audio_and_schedule_more(
+            state: &mut CallEndpointState,
+            mut audio: GeneratedAudio,
+        ) {
+            // Catch up on whatever is due so that scheduling delays don't add up.
+            while CHUNK_DURATION * audio.chunks_sent <= audio.started.elapsed() {
+                let digit = audio.generator.current_dtmf_digit();
+                if digit != audio.playing {
+                    if let Some(digit) = digit {
+                        audio
+                            .meter
+                            .lock()
+                            .expect("lock meter")
+                            .audio_marker_sent(digit, Instant::now());
+                    }
+                    audio.playing = digit;
+                }
+                let samples = audio.generator.next_10ms();
+                state.pcf.push_outgoing_audio_samples(AudioFrame {
+                    sample_rate: SAMPLE_RATE,
+                    channels: 1,
+                    samples: &samples,
+                });
+                audio.chunks_sent += 1;
+            }
+            let next_due = audio.started + CHUNK_DURATION * audio.chunks_sent;
+            state.actor.send_delayed(
+                next_due.saturating_duration_since(Instant::now()),
+                move |state| {
+                    send_due_audio_and_schedule_more(state, audio);
+                },
+            );
+        }
+
+        let audio = GeneratedAudio {
+            generator: TestAudioGenerator::new(
+                TestTone::Dtmf {
+                    digits: "0123456789ABCD*#".to_string(),
+                    tone_duration: Duration::from_millis(100),
+                    gap_duration: Duration::from_millis(900),
+                },
+                SAMPLE_RATE,
+                0.5,
+            ),
+            started: Instant::now(),
+            chunks_sent: 0,
+            playing: None,
+            meter,
+        };
         self.actor.send(move |state| {
-            send_one_frame_and_schedule_another(state, width, height, duration);
+            send_due_audio_and_schedule_more(state, audio);
         });
     }
 }
@@ -605,6 +1024,14 @@ impl CallStateHandler for CallEndpoint {
         Ok(())
     }
 
+    fn handle_talking_while_muted(&self, remote_peer_id: &str) -> Result<()> {
+        info!(
+            "Talking while muted for {} => {}",
+            self.peer_id, remote_peer_id
+        );
+        Ok(())
+    }
+
     fn handle_remote_video_state(&self, remote_peer_id: &str, enabled: bool) -> Result<()> {
         info!(
             "Video State for {} => {}: {}",
@@ -638,6 +1065,7 @@ impl http::Delegate for CallEndpoint {
 #[derive(Clone)]
 struct LoggingVideoSink {
     peer_id: PeerId,
+    analyzer: TestVideoAnalyzer,
 }
 
 impl VideoSink for LoggingVideoSink {
@@ -649,6 +1077,7 @@ impl VideoSink for LoggingVideoSink {
             frame.width(),
             frame.height(),
         );
+        self.analyzer.on_video_frame(track_id, frame);
     }
 
     fn box_clone(&self) -> Box<dyn VideoSink> {
@@ -656,9 +1085,33 @@ impl VideoSink for LoggingVideoSink {
     }
 }
 
+// An ICE candidate as it was signaled, to see what it revealed.
+#[derive(Clone, Debug)]
+struct SignaledCandidate {
+    sender: PeerId,
+    // Such as "host", "srflx" or "relay"
+    typ: String,
+    address: String,
+}
+
+impl SignaledCandidate {
+    // The SDP is like "candidate:1 1 udp 2122260223 1.1.0.1 2001 typ host generation 0".
+    fn from_sdp(sender: &PeerId, sdp: &str) -> Option<Self> {
+        let fields: Vec<&str> = sdp.split_whitespace().collect();
+        let typ_index = fields.iter().position(|field| *field == "typ")?;
+        Some(Self {
+            sender: sender.clone(),
+            typ: fields.get(typ_index + 1)?.to_string(),
+            address: format!("{}:{}", fields.get(4)?, fields.get(5)?),
+        })
+    }
+}
+
 #[derive(Clone)]
 struct SignalingServer {
     actor: Actor<SignalingServerState>,
+    // Shared by all the copies, so it can be read from any thread.
+    signaled_candidates: Arc<Mutex<Vec<SignaledCandidate>>>,
 }
 
 struct SignalingServerState {
@@ -673,9 +1126,19 @@ impl SignalingServer {
                     endpoints_by_peer_id: HashMap::new(),
                 })
             })?,
+            signaled_candidates: Arc::new(Mutex::new(Vec::new())),
         })
     }
 
+    fn take_signaled_candidates(&self) -> Vec<SignaledCandidate> {
+        std::mem::take(
+            &mut *self
+                .signaled_candidates
+                .lock()
+                .expect("lock signaled candidates"),
+        )
+    }
+
     fn add_endpoint(&self, endpoint: &CallEndpoint) {
         // To send across threads
         let peer_id = endpoint.peer_id.clone();
@@ -698,6 +1161,21 @@ impl SignalingServer {
         call_id: CallId,
         msg: signaling::Message,
     ) {
+        if let signaling::Message::Ice(ice) = &msg {
+            let mut signaled_candidates = self
+                .signaled_candidates
+                .lock()
+                .expect("lock signaled candidates");
+            for candidate in &ice.candidates {
+                if let Some(candidate) = candidate
+                    .v3_sdp()
+                    .and_then(|sdp| SignaledCandidate::from_sdp(sender_id, &sdp))
+                {
+                    signaled_candidates.push(candidate);
+                }
+            }
+        }
+
         // To send across threads
         let sender_id = sender_id.clone();
         let recipient_id = recipient_id.clone();
@@ -718,9 +1196,13 @@ impl SignalingServer {
     }
 }
 
-struct Log;
+struct Log {
+    to_stderr: AtomicBool,
+}
 
-static LOG: Log = Log;
+static LOG: Log = Log {
+    to_stderr: AtomicBool::new(false),
+};
 
 impl log::Log for Log {
     fn enabled(&self, metadata: &log::Metadata) -> bool {
@@ -729,7 +1211,11 @@ impl log::Log for Log {
 
     fn log(&self, record: &log::Record) {
         if self.enabled(record.metadata()) {
-            println!("{} - {}", record.level(), record.args());
+            if self.to_stderr.load(Ordering::Relaxed) {
+                eprintln!("{} - {}", record.level(), record.args());
+            } else {
+                println!("{} - {}", record.level(), record.args());
+            }
         }
     }
 
@@ -0,0 +1,254 @@
+//
+// Copyright 2022 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Make outgoing audio more robust when the network is lossy.
+//!
+//! The remote side's reports of the audio we send (from the stats
+//! observer) give us the loss and jitter they see.  When those get
+//! worse, we step up the protection on top of whatever audio config
+//! the bandwidth settings picked: first Opus in-band FEC, then 40ms
+//! packets without DTX, then 60ms packets.  When they get better for
+//! long enough, we step back down.
+//!
+//! RED (RFC 2198) isn't one of the steps: the encoder config we pass
+//! over FFI mirrors webrtc::AudioEncoder::Config, which has no RED
+//! setting, and RED for Opus can only be turned on by negotiating the
+//! red codec, which the group call SDP and the SFU don't do.
+
+use crate::webrtc::{media::AudioEncoderConfig, stats_observer::AudioSenderStatistics};
+
+// Loss is a fraction of the packets sent since the last report.
+const MODERATE_LOSS: f64 = 0.02;
+const HIGH_LOSS: f64 = 0.08;
+const SEVERE_LOSS: f64 = 0.20;
+// In seconds, like the stats.
+const MODERATE_JITTER: f64 = 0.030;
+const SEVERE_JITTER: f64 = 0.100;
+
+// How many reports in a row must be below a level before stepping down from it.
+// Stepping up happens immediately.
+const REPORTS_BEFORE_STEPPING_DOWN: u32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AudioProtectionLevel {
+    /// Whatever the bandwidth settings picked.
+    None,
+    /// Opus in-band FEC.
+    Moderate,
+    /// Opus in-band FEC, with 40ms packets and no DTX.
+    High,
+    /// Opus in-band FEC, with 60ms packets and no DTX.
+    Severe,
+}
+
+#[derive(Debug)]
+pub struct AudioAdaptation {
+    level: AudioProtectionLevel,
+    last_stats: Option<AudioSenderStatistics>,
+    reports_below_level: u32,
+}
+
+impl Default for AudioAdaptation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioAdaptation {
+    pub fn new() -> Self {
+        Self {
+            level: AudioProtectionLevel::None,
+            last_stats: None,
+            reports_below_level: 0,
+        }
+    }
+
+    pub fn level(&self) -> AudioProtectionLevel {
+        self.level
+    }
+
+    /// Takes the (cumulative) stats of the audio sender.
+    /// Returns true if the level changed, in which case the audio
+    /// encoders should be reconfigured using adapt().
+    pub fn update(&mut self, stats: &AudioSenderStatistics) -> bool {
+        let last_stats = self.last_stats.replace(*stats);
+        let last_stats = match last_stats {
+            Some(last_stats) => last_stats,
+            None => return false,
+        };
+        let packets_sent = stats.packets_sent.saturating_sub(last_stats.packets_sent);
+        if packets_sent == 0 {
+            // Nothing to learn from; maybe we're muted or using DTX.
+            return false;
+        }
+        // The remote count can go down when duplicates arrive.
+        let packets_lost = stats
+            .remote_packets_lost
+            .saturating_sub(last_stats.remote_packets_lost)
+            .max(0) as f64;
+        let loss = packets_lost / (packets_sent as f64);
+        let observed_level = Self::level_for(loss, stats.remote_jitter);
+
+        let previous_level = self.level;
+        if observed_level >= self.level {
+            self.level = observed_level;
+            self.reports_below_level = 0;
+        } else {
+            self.reports_below_level += 1;
+            if self.reports_below_level >= REPORTS_BEFORE_STEPPING_DOWN {
+                self.level = Self::step_down(self.level);
+                self.reports_below_level = 0;
+            }
+        }
+        if self.level != previous_level {
+            info!(
+                "Audio protection level changed from {:?} to {:?} (loss: {:.3}, jitter: {:.3})",
+                previous_level, self.level, loss, stats.remote_jitter
+            );
+        }
+        self.level != previous_level
+    }
+
+    fn level_for(loss: f64, jitter: f64) -> AudioProtectionLevel {
+        if loss >= SEVERE_LOSS || jitter >= SEVERE_JITTER {
+            AudioProtectionLevel::Severe
+        } else if loss >= HIGH_LOSS {
+            AudioProtectionLevel::High
+        } else if loss >= MODERATE_LOSS || jitter >= MODERATE_JITTER {
+            AudioProtectionLevel::Moderate
+        } else {
+            AudioProtectionLevel::None
+        }
+    }
+
+    fn step_down(level: AudioProtectionLevel) -> AudioProtectionLevel {
+        match level {
+            AudioProtectionLevel::None | AudioProtectionLevel::Moderate => {
+                AudioProtectionLevel::None
+            }
+            AudioProtectionLevel::High => AudioProtectionLevel::Moderate,
+            AudioProtectionLevel::Severe => AudioProtectionLevel::High,
+        }
+    }
+
+    /// Adds the current protection to the config picked by the bandwidth settings.
+    pub fn adapt(&self, config: &AudioEncoderConfig) -> AudioEncoderConfig {
+        let mut config = config.clone();
+        if self.level >= AudioProtectionLevel::Moderate {
+            config.enable_fec = true;
+        }
+        // The first packet after a DTX gap has no FEC for what came before,
+        // so losing it cuts off the start of what was said.
+        if self.level >= AudioProtectionLevel::High {
+            config.enable_dtx = false;
+        }
+        // Larger packets have less overhead and hold more FEC.
+        if self.level >= AudioProtectionLevel::Severe {
+            config.packet_size_ms = config.packet_size_ms.max(60);
+        } else if self.level >= AudioProtectionLevel::High {
+            config.packet_size_ms = config.packet_size_ms.max(40);
+        }
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(
+        packets_sent: u32,
+        remote_packets_lost: i32,
+        remote_jitter: f64,
+    ) -> AudioSenderStatistics {
+        AudioSenderStatistics {
+            ssrc: 1,
+            packets_sent,
+            bytes_sent: 0,
+            remote_packets_lost,
+            remote_jitter,
+            remote_round_trip_time: 0.0,
+            audio_level: 0.0,
+            total_audio_energy: 0.0,
+            echo_likelihood: 0.0,
+        }
+    }
+
+    #[test]
+    fn steps_up_immediately_and_down_slowly() {
+        let mut adaptation = AudioAdaptation::new();
+        assert!(!adaptation.update(&stats(0, 0, 0.0)));
+        assert!(!adaptation.update(&stats(500, 0, 0.0)));
+        assert_eq!(AudioProtectionLevel::None, adaptation.level());
+
+        // 25% loss
+        assert!(adaptation.update(&stats(1000, 125, 0.0)));
+        assert_eq!(AudioProtectionLevel::Severe, adaptation.level());
+
+        // 10% loss
+        assert!(!adaptation.update(&stats(1500, 175, 0.0)));
+        assert!(!adaptation.update(&stats(2000, 225, 0.0)));
+        assert!(adaptation.update(&stats(2500, 275, 0.0)));
+        assert_eq!(AudioProtectionLevel::High, adaptation.level());
+
+        // No loss
+        assert!(!adaptation.update(&stats(3000, 275, 0.0)));
+        assert!(!adaptation.update(&stats(3500, 275, 0.0)));
+        assert!(adaptation.update(&stats(4000, 275, 0.0)));
+        assert_eq!(AudioProtectionLevel::Moderate, adaptation.level());
+
+        // A spike resets the count.
+        assert!(!adaptation.update(&stats(4500, 275, 0.0)));
+        assert!(!adaptation.update(&stats(5000, 290, 0.0)));
+        assert!(!adaptation.update(&stats(5500, 290, 0.0)));
+        assert!(!adaptation.update(&stats(6000, 290, 0.0)));
+        assert!(adaptation.update(&stats(6500, 290, 0.0)));
+        assert_eq!(AudioProtectionLevel::None, adaptation.level());
+
+        // Jitter counts too.
+        assert!(adaptation.update(&stats(7000, 290, 0.040)));
+        assert_eq!(AudioProtectionLevel::Moderate, adaptation.level());
+
+        // Nothing sent
+        assert!(!adaptation.update(&stats(7000, 290, 0.0)));
+        assert_eq!(AudioProtectionLevel::Moderate, adaptation.level());
+    }
+
+    #[test]
+    fn adapt() {
+        let base = AudioEncoderConfig {
+            enable_fec: false,
+            enable_dtx: true,
+            ..Default::default()
+        };
+        let mut adaptation = AudioAdaptation::new();
+        assert_eq!(base, adaptation.adapt(&base));
+
+        adaptation.level = AudioProtectionLevel::Moderate;
+        let config = adaptation.adapt(&base);
+        assert!(config.enable_fec);
+        assert!(config.enable_dtx);
+        assert_eq!(20, config.packet_size_ms);
+
+        adaptation.level = AudioProtectionLevel::High;
+        let config = adaptation.adapt(&base);
+        assert!(config.enable_fec);
+        assert!(!config.enable_dtx);
+        assert_eq!(40, config.packet_size_ms);
+
+        adaptation.level = AudioProtectionLevel::Severe;
+        let config = adaptation.adapt(&base);
+        assert!(config.enable_fec);
+        assert!(!config.enable_dtx);
+        assert_eq!(60, config.packet_size_ms);
+
+        // Larger packets stay larger.
+        let base = AudioEncoderConfig {
+            packet_size_ms: 120,
+            ..Default::default()
+        };
+        assert_eq!(120, adaptation.adapt(&base).packet_size_ms);
+    }
+}
@@ -10,7 +10,6 @@ use std::fmt;
 use crate::common::units;
 
 pub const MINIMUM_BITRATE_BPS: u64 = 30_000;
-pub const MAXIMUM_BITRATE_BPS: u64 = 2_000_001;
 
 #[repr(i32)]
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
@@ -24,6 +23,8 @@ pub enum BandwidthMode {
     /// (Default) No specific constraints, but keep a relatively
     /// high bitrate to ensure good quality.
     Normal,
+    /// Intended for 1080p video on good networks.
+    High,
 }
 
 impl fmt::Display for BandwidthMode {
@@ -38,6 +39,7 @@ impl BandwidthMode {
             0 => BandwidthMode::VeryLow,
             1 => BandwidthMode::Low,
             2 => BandwidthMode::Normal,
+            3 => BandwidthMode::High,
             _ => {
                 // Log but otherwise assume normal if not valid.
                 warn!("Invalid bandwidth_mode: {}", value);
@@ -52,6 +54,7 @@ impl BandwidthMode {
             BandwidthMode::VeryLow => units::DataRate::from_kbps(125),
             BandwidthMode::Low => units::DataRate::from_kbps(300),
             BandwidthMode::Normal => units::DataRate::from_kbps(2_000),
+            BandwidthMode::High => units::DataRate::from_kbps(4_000),
         }
     }
 
@@ -60,6 +63,7 @@ impl BandwidthMode {
             BandwidthMode::VeryLow => (60, 16_000, 16_000, 16_000),
             BandwidthMode::Low => (40, 28_000, 16_000, 28_000),
             BandwidthMode::Normal => (20, 40_000, 20_000, 40_000),
+            BandwidthMode::High => (20, 64_000, 20_000, 64_000),
         };
         crate::webrtc::media::AudioEncoderConfig {
             packet_size_ms,
@@ -70,3 +74,86 @@ impl BandwidthMode {
         }
     }
 }
+
+/// Like a BandwidthMode, but with arbitrary limits instead of fixed tiers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BandwidthPolicy {
+    /// The most we will send, for all media.
+    pub max_send_rate: units::DataRate,
+    /// The most we ask the other side(s) to send us, for all media.
+    pub max_receive_rate: units::DataRate,
+    pub audio_encoder_config: crate::webrtc::media::AudioEncoderConfig,
+}
+
+impl From<BandwidthMode> for BandwidthPolicy {
+    fn from(mode: BandwidthMode) -> Self {
+        Self {
+            max_send_rate: mode.max_bitrate(),
+            max_receive_rate: mode.max_bitrate(),
+            audio_encoder_config: mode.audio_encoder_config(),
+        }
+    }
+}
+
+impl BandwidthPolicy {
+    /// Sends at most max_send_rate and asks for at most max_receive_rate,
+    /// with the audio configuration of the highest BandwidthMode that fits
+    /// in max_send_rate.
+    pub fn new(max_send_rate: units::DataRate, max_receive_rate: units::DataRate) -> Self {
+        let mode = [
+            BandwidthMode::High,
+            BandwidthMode::Normal,
+            BandwidthMode::Low,
+        ]
+        .iter()
+        .copied()
+        .find(|mode| mode.max_bitrate() <= max_send_rate)
+        .unwrap_or(BandwidthMode::VeryLow);
+        Self {
+            max_send_rate,
+            max_receive_rate,
+            audio_encoder_config: mode.audio_encoder_config(),
+        }
+    }
+
+    /// Sends and receives at most max_rate.
+    pub fn with_max_rate(max_rate: units::DataRate) -> Self {
+        Self::new(max_rate, max_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bandwidth_policy_with_max_rate() {
+        let policy = BandwidthPolicy::with_max_rate(units::DataRate::from_kbps(3_000));
+        assert_eq!(units::DataRate::from_kbps(3_000), policy.max_send_rate);
+        assert_eq!(units::DataRate::from_kbps(3_000), policy.max_receive_rate);
+        assert_eq!(
+            BandwidthMode::Normal.audio_encoder_config(),
+            policy.audio_encoder_config
+        );
+
+        assert_eq!(
+            BandwidthMode::VeryLow.audio_encoder_config(),
+            BandwidthPolicy::with_max_rate(units::DataRate::from_kbps(100)).audio_encoder_config
+        );
+        assert_eq!(
+            BandwidthPolicy::from(BandwidthMode::High),
+            BandwidthPolicy::with_max_rate(units::DataRate::from_kbps(4_000))
+        );
+
+        // The audio configuration follows what we send.
+        let policy = BandwidthPolicy::new(
+            units::DataRate::from_kbps(300),
+            units::DataRate::from_kbps(10_000),
+        );
+        assert_eq!(units::DataRate::from_kbps(10_000), policy.max_receive_rate);
+        assert_eq!(
+            BandwidthMode::Low.audio_encoder_config(),
+            policy.audio_encoder_config
+        );
+    }
+}
@@ -18,17 +18,18 @@ use x25519_dalek::StaticSecret;
 use crate::common::{
     ApplicationEvent, CallDirection, CallId, CallMediaType, CallState, DeviceId, Result,
 };
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::core::bandwidth_mode::BandwidthPolicy;
 use crate::core::call_fsm::{CallEvent, CallStateMachine};
 use crate::core::call_manager::CallManager;
 use crate::core::call_mutex::CallMutex;
 use crate::core::connection::{Connection, ConnectionObserverEvent, ConnectionType};
 use crate::core::platform::Platform;
 use crate::core::signaling;
+use crate::core::speaker_detector::TalkingWhileMutedConfig;
 use crate::core::util::TaskQueueRuntime;
 use crate::error::RingRtcError;
 use crate::webrtc::ice_gatherer::IceGatherer;
-use crate::webrtc::media::MediaStream;
+use crate::webrtc::media::{MediaStream, VideoSource};
 use crate::webrtc::peer_connection::AudioLevel;
 use crate::webrtc::peer_connection_observer::NetworkRoute;
 
@@ -126,6 +127,9 @@ where
     /// ICE candidates and signaling alive.
     /// And we also need to keep around that parent's offer that it created.
     forking: Arc<CallMutex<Option<ForkingState<T>>>>,
+    /// Whether the application has muted the outgoing audio, so that
+    /// each Connection can notice talking while muted.
+    outgoing_audio_muted: Arc<AtomicBool>,
 }
 
 impl<T> fmt::Display for Call<T>
@@ -207,6 +211,7 @@ where
                 &self.did_notify_application_of_remote_ringing,
             ),
             forking: Arc::clone(&self.forking),
+            outgoing_audio_muted: Arc::clone(&self.outgoing_audio_muted),
         }
     }
 }
@@ -252,6 +257,7 @@ where
             did_send_offer: Arc::new(AtomicBool::new(false)),
             did_notify_application_of_remote_ringing: Arc::new(AtomicBool::new(false)),
             forking: Arc::new(CallMutex::new(None, "forking")),
+            outgoing_audio_muted: Arc::new(AtomicBool::new(false)),
         };
 
         Ok(call)
@@ -325,6 +331,40 @@ where
         )
     }
 
+    /// Whether the application has muted the outgoing audio.
+    pub fn outgoing_audio_muted(&self) -> bool {
+        self.outgoing_audio_muted.load(Ordering::Acquire)
+    }
+
+    /// Lets every Connection, including those created later, notice
+    /// when the user talks while muted.
+    pub fn set_outgoing_audio_muted(&self, muted: bool) -> Result<()> {
+        self.outgoing_audio_muted.store(muted, Ordering::Release);
+        let connection_map = self.connection_map.lock()?;
+        for connection in connection_map.values() {
+            connection.set_outgoing_audio_muted(muted)?;
+        }
+        Ok(())
+    }
+
+    /// Applies the config to every Connection of the call.
+    pub fn set_talking_while_muted_config(&self, config: &TalkingWhileMutedConfig) -> Result<()> {
+        let connection_map = self.connection_map.lock()?;
+        for connection in connection_map.values() {
+            connection.set_talking_while_muted_config(config.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Applies the source to every Connection of the call.
+    pub fn set_outgoing_video_source(&self, source: Option<VideoSource>) -> Result<()> {
+        let connection_map = self.connection_map.lock()?;
+        for connection in connection_map.values() {
+            connection.set_outgoing_video_source(source.clone())?;
+        }
+        Ok(())
+    }
+
     /// Return the active Connection this call is associated with.
     pub fn active_connection(&self) -> Result<Connection<T>> {
         let connection_map = self.connection_map.lock()?;
@@ -485,6 +525,16 @@ where
         call_manager.notify_audio_levels(&*remote_peer, captured_level, received_level)
     }
 
+    /// Notify application that the user is talking while muted
+    ///
+    /// This is a pass through to the CallManager.
+    pub fn notify_talking_while_muted(&self) -> Result<()> {
+        let call_manager = self.call_manager()?;
+        let remote_peer = self.remote_peer()?;
+
+        call_manager.notify_talking_while_muted(&*remote_peer)
+    }
+
     /// Notify call manager of an internal error.
     ///
     pub fn internal_error(&self, error: anyhow::Error) -> Result<()> {
@@ -558,7 +608,7 @@ where
     /// - handle the previously stored pending Offer and ICE Candidates
     pub fn proceed(
         &mut self,
-        bandwidth_mode: BandwidthMode,
+        bandwidth_policy: BandwidthPolicy,
         audio_levels_interval: Option<Duration>,
     ) -> Result<()> {
         info!("proceed():");
@@ -578,7 +628,7 @@ where
                         remote_device_id,
                         ConnectionType::Incoming,
                         pending_call.received.offer.latest_version(),
-                        bandwidth_mode,
+                        bandwidth_policy,
                         audio_levels_interval,
                     )?;
                     let answer = connection
@@ -611,11 +661,11 @@ where
                     0,
                     ConnectionType::OutgoingParent,
                     signaling::Version::V4,
-                    bandwidth_mode,
+                    bandwidth_policy,
                     audio_levels_interval,
                 )?;
                 let (local_secret, ice_gatherer, offer) =
-                    parent_connection.start_outgoing_parent(self.media_type, bandwidth_mode)?;
+                    parent_connection.start_outgoing_parent(self.media_type)?;
 
                 // Keep around so that it's not closed until all the connections are closed.
                 *(self.forking.lock()?) = Some(ForkingState {
@@ -651,14 +701,14 @@ where
             if let Some(forking) = maybe_forking.as_mut() {
                 info!("received_answer from device {}; forking enabled, so inject into connection_map", sender_device_id);
                 let call_manager = self.call_manager()?;
-                let bandwidth_mode = forking.parent_connection.local_bandwidth_mode()?;
+                let bandwidth_policy = forking.parent_connection.local_bandwidth_policy()?;
                 let audio_levels_interval = forking.parent_connection.audio_levels_interval();
                 let mut child_connection = call_manager.create_connection(
                     self,
                     sender_device_id,
                     ConnectionType::OutgoingChild,
                     received.answer.latest_version(),
-                    bandwidth_mode,
+                    bandwidth_policy,
                     audio_levels_interval,
                 )?;
                 child_connection.start_outgoing_child(
@@ -986,11 +1036,11 @@ where
     /// Inject a call Proceed event into the FSM.
     pub fn inject_proceed(
         &mut self,
-        bandwidth_mode: BandwidthMode,
+        bandwidth_policy: BandwidthPolicy,
         audio_levels_interval: Option<Duration>,
     ) -> Result<()> {
         let event = CallEvent::Proceed {
-            bandwidth_mode,
+            bandwidth_policy,
             audio_levels_interval,
         };
         self.inject_event(event)
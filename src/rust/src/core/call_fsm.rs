@@ -33,6 +33,7 @@
 //! - IceFailed
 //! - Timeout
 //! - Reconnecting
+//! - TalkingWhileMuted
 //!
 //! ## Signaling events from client application
 //! - ReceivedAnswer
@@ -59,7 +60,7 @@ use crate::error::RingRtcError;
 use crate::common::{
     ApplicationEvent, CallDirection, CallState, ConnectionState, DeviceId, Result,
 };
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::core::bandwidth_mode::BandwidthPolicy;
 use crate::core::call::{Call, EventStream};
 use crate::core::connection::ConnectionObserverEvent;
 use crate::core::platform::Platform;
@@ -82,7 +83,7 @@ pub enum CallEvent {
     // Flow events from client application
     /// OK to proceed with call setup including user options.
     Proceed {
-        bandwidth_mode: BandwidthMode,
+        bandwidth_policy: BandwidthPolicy,
         audio_levels_interval: Option<Duration>,
     },
 
@@ -118,12 +119,12 @@ impl fmt::Display for CallEvent {
                 format!("SendHangupViaRtpDataToAll, hangup: {}", hangup)
             }
             CallEvent::Proceed {
-                bandwidth_mode,
+                bandwidth_policy,
                 audio_levels_interval,
             } => {
                 format!(
-                    "Proceed, bandwidth_mode: {}, audio_levels_interval: {:?}",
-                    bandwidth_mode, audio_levels_interval
+                    "Proceed, bandwidth_policy: {:?}, audio_levels_interval: {:?}",
+                    bandwidth_policy, audio_levels_interval
                 )
             }
             CallEvent::ReceivedAnswer(received) => {
@@ -406,9 +407,9 @@ where
         match event {
             CallEvent::StartCall => self.handle_start_call(call, state),
             CallEvent::Proceed {
-                bandwidth_mode,
+                bandwidth_policy,
                 audio_levels_interval,
-            } => self.handle_proceed(call, state, bandwidth_mode, audio_levels_interval),
+            } => self.handle_proceed(call, state, bandwidth_policy, audio_levels_interval),
             CallEvent::AcceptCall => self.handle_accept_call(call, state),
             CallEvent::ReceivedAnswer(received) => {
                 self.handle_received_answer(call, state, received)
@@ -482,6 +483,21 @@ where
         self.notify_spawn(notify_app_future);
     }
 
+    fn notify_talking_while_muted(&mut self, call: Call<T>) {
+        let mut err_call = call.clone();
+        let notify_app_future = async move {
+            if call.terminating()? {
+                return Ok(());
+            }
+            call.notify_talking_while_muted()
+        }
+        .unwrap_or_else(move |err| {
+            err_call.inject_internal_error(err, "Notify Talking While Muted Future failed");
+        });
+
+        self.notify_spawn(notify_app_future);
+    }
+
     fn handle_start_call(&mut self, call: Call<T>, state: CallState) -> Result<()> {
         info!("handle_start_call():");
 
@@ -499,7 +515,7 @@ where
         &mut self,
         call: Call<T>,
         state: CallState,
-        bandwidth_mode: BandwidthMode,
+        bandwidth_policy: BandwidthPolicy,
         audio_levels_interval: Option<Duration>,
     ) -> Result<()> {
         info!("handle_proceed():");
@@ -507,7 +523,7 @@ where
         if state == CallState::WaitingToProceed {
             call.set_state(CallState::ConnectingBeforeAccepted)?;
             self.schedule_work_until_terminating(call, "Proceed failed", move |mut call| {
-                call.proceed(bandwidth_mode, audio_levels_interval)
+                call.proceed(bandwidth_policy, audio_levels_interval)
             });
         } else {
             self.unexpected_state(state, "Proceed");
@@ -991,6 +1007,18 @@ where
                 self.notify_audio_levels(call, captured_level, received_level);
                 Ok(())
             }
+            ConnectionObserverEvent::TalkingWhileMuted => {
+                if matches!(call.active_device_id(), Ok(active_device_id) if active_device_id == remote_device_id)
+                {
+                    self.notify_talking_while_muted(call);
+                } else {
+                    info!(
+                        "call_id: {} remote_device_id: {} Ignoring event: {}, from inactive connection.",
+                        call_id, remote_device_id, event
+                    );
+                }
+                Ok(())
+            }
         }
     }
 
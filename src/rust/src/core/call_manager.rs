@@ -23,25 +23,32 @@ use prost::Message;
 use crate::common::{
     ApplicationEvent, CallDirection, CallId, CallMediaType, CallState, DeviceId, Result, RingBench,
 };
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::core::bandwidth_mode::{BandwidthMode, BandwidthPolicy};
 use crate::core::call::Call;
 use crate::core::call_mutex::CallMutex;
 use crate::core::connection::{Connection, ConnectionType};
-use crate::core::group_call::{HttpSfuClient, Observer};
+use crate::core::group_call::{CallLinkSfuClient, HttpSfuClient, Observer};
 use crate::core::platform::Platform;
+use crate::core::recorder::Recorder;
+use crate::core::send_rate_policy::{DeviceConditions, SendRatePolicy};
 use crate::core::signaling::ReceivedOffer;
+use crate::core::speaker_detector::TalkingWhileMutedConfig;
 use crate::core::util::{uuid_to_string, TaskQueueRuntime};
 use crate::core::{group_call, signaling};
 use crate::error::RingRtcError;
 use crate::lite::{
+    call_links::{self, CallLinkAuthCredentialPresentation, CallLinkRootKey},
     http, sfu,
     sfu::{DemuxId, GroupMember, MembershipProof, PeekInfo, UserId},
 };
 use crate::protobuf;
-use crate::webrtc::media::{AudioTrack, MediaStream, VideoSink, VideoTrack};
+use crate::webrtc::media::{
+    AudioSink, AudioTrack, MediaStream, VideoEncodingConfig, VideoSink, VideoSource, VideoTrack,
+};
 use crate::webrtc::peer_connection::{AudioLevel, ReceivedAudioLevel};
 use crate::webrtc::peer_connection_factory::PeerConnectionFactory;
 use crate::webrtc::peer_connection_observer::NetworkRoute;
+use crate::webrtc::video_frame_processor::{VideoFrameProcessor, VideoFrameProcessorChain};
 
 pub const MAX_MESSAGE_AGE: Duration = Duration::from_secs(60);
 const TIME_OUT_PERIOD: Duration = Duration::from_secs(60);
@@ -313,6 +320,13 @@ where
     message_queue: Arc<CallMutex<SignalingMessageQueue<T>>>,
     /// How to make HTTP requests to the SFU for group calls.
     http_client: http::DelegatingClient,
+    /// How to notice talking while muted, in direct and group calls.
+    talking_while_muted_config: Arc<CallMutex<TalkingWhileMutedConfig>>,
+    /// Whose frame processor stats are logged with the stats of every call.
+    outgoing_video_source: Arc<CallMutex<Option<VideoSource>>>,
+    /// Applied to the platform's captured video in group calls (see
+    /// PeerConnectionFactory::create_processed_video_source).
+    video_frame_processors: Arc<VideoFrameProcessorChain>,
 }
 
 impl<T> fmt::Display for CallManager<T>
@@ -378,6 +392,9 @@ where
             worker_runtime: Arc::clone(&self.worker_runtime),
             message_queue: Arc::clone(&self.message_queue),
             http_client: self.http_client.clone(),
+            talking_while_muted_config: Arc::clone(&self.talking_while_muted_config),
+            outgoing_video_source: Arc::clone(&self.outgoing_video_source),
+            video_frame_processors: Arc::clone(&self.video_frame_processors),
         }
     }
 }
@@ -426,6 +443,12 @@ where
                 "message_queue",
             )),
             http_client,
+            talking_while_muted_config: Arc::new(CallMutex::new(
+                TalkingWhileMutedConfig::default(),
+                "talking_while_muted_config",
+            )),
+            outgoing_video_source: Arc::new(CallMutex::new(None, "outgoing_video_source")),
+            video_frame_processors: Default::default(),
         })
     }
 
@@ -488,13 +511,29 @@ where
         app_call_context: <T as Platform>::AppCallContext,
         bandwidth_mode: BandwidthMode,
         audio_levels_interval: Option<Duration>,
+    ) -> Result<()> {
+        self.proceed_with_bandwidth_policy(
+            call_id,
+            app_call_context,
+            bandwidth_mode.into(),
+            audio_levels_interval,
+        )
+    }
+
+    /// Like proceed, but with arbitrary limits instead of a BandwidthMode.
+    pub fn proceed_with_bandwidth_policy(
+        &mut self,
+        call_id: CallId,
+        app_call_context: <T as Platform>::AppCallContext,
+        bandwidth_policy: BandwidthPolicy,
+        audio_levels_interval: Option<Duration>,
     ) -> Result<()> {
         handle_active_call_api!(
             self,
             CallManager::handle_proceed,
             call_id,
             app_call_context,
-            bandwidth_mode,
+            bandwidth_policy,
             audio_levels_interval
         )
     }
@@ -715,6 +754,69 @@ where
         active_call.active_connection()
     }
 
+    /// Lets the active direct call notice when the user talks while muted.
+    /// We don't modify the outgoing audio track.  We expect the app to handle that.
+    pub fn set_direct_outgoing_audio_muted(&mut self, muted: bool) -> Result<()> {
+        info!("set_direct_outgoing_audio_muted(): muted: {}", muted);
+        self.active_call()?.set_outgoing_audio_muted(muted)
+    }
+
+    /// Updates the limits of the active direct call, like the platforms'
+    /// update_bandwidth_mode, but with arbitrary limits.
+    pub fn update_bandwidth_policy(&mut self, bandwidth_policy: BandwidthPolicy) -> Result<()> {
+        info!(
+            "update_bandwidth_policy(): bandwidth_policy: {:?}",
+            bandwidth_policy
+        );
+        let mut active_connection = self.active_connection()?;
+        active_connection.inject_update_bandwidth_policy(bandwidth_policy)
+    }
+
+    /// Applies to the active direct call, every group call, and those created later.
+    pub fn set_talking_while_muted_config(
+        &mut self,
+        config: TalkingWhileMutedConfig,
+    ) -> Result<()> {
+        info!("set_talking_while_muted_config(): config: {:?}", config);
+        *self.talking_while_muted_config.lock()? = config.clone();
+        if let Ok(active_call) = self.active_call() {
+            active_call.set_talking_while_muted_config(&config)?;
+        }
+        for client in self.group_call_by_client_id.lock()?.values() {
+            client.set_talking_while_muted_config(config.clone());
+        }
+        Ok(())
+    }
+
+    /// The timing of the source's frame processors is logged with the stats
+    /// of the active direct call, every group call, and those created later.
+    pub fn set_outgoing_video_source(&mut self, source: Option<VideoSource>) -> Result<()> {
+        info!("set_outgoing_video_source(): {}", source.is_some());
+        *self.outgoing_video_source.lock()? = source.clone();
+        if let Ok(active_call) = self.active_call() {
+            active_call.set_outgoing_video_source(source.clone())?;
+        }
+        for client in self.group_call_by_client_id.lock()?.values() {
+            client.set_outgoing_video_source(source.clone());
+        }
+        Ok(())
+    }
+
+    pub fn video_frame_processors(&self) -> Arc<VideoFrameProcessorChain> {
+        self.video_frame_processors.clone()
+    }
+
+    /// Adds a processor to the end of the chain applied to the platform's captured video.
+    pub fn add_video_frame_processor(&mut self, processor: Box<dyn VideoFrameProcessor>) {
+        info!("add_video_frame_processor(): {}", processor.name());
+        self.video_frame_processors.add(processor);
+    }
+
+    pub fn clear_video_frame_processors(&mut self) {
+        info!("clear_video_frame_processors():");
+        self.video_frame_processors.clear();
+    }
+
     /// Checks if a call is active.
     pub fn call_active(&self) -> Result<bool> {
         Ok(self.active_call_id.lock()?.is_some())
@@ -1113,7 +1215,7 @@ where
         &mut self,
         call_id: CallId,
         app_call_context: <T as Platform>::AppCallContext,
-        bandwidth_mode: BandwidthMode,
+        bandwidth_policy: BandwidthPolicy,
         audio_levels_interval: Option<Duration>,
     ) -> Result<()> {
         ringbench!(
@@ -1128,7 +1230,7 @@ where
             Ok(())
         } else {
             active_call.set_call_context(app_call_context)?;
-            active_call.inject_proceed(bandwidth_mode, audio_levels_interval)
+            active_call.inject_proceed(bandwidth_policy, audio_levels_interval)
         }
     }
 
@@ -2134,6 +2236,15 @@ where
         platform.on_audio_levels(remote_peer, captured_level, received_level)
     }
 
+    /// Notify application that the user is talking while muted
+    pub(super) fn notify_talking_while_muted(
+        &self,
+        remote_peer: &<T as Platform>::AppRemotePeer,
+    ) -> Result<()> {
+        let platform = self.platform.lock()?;
+        platform.on_talking_while_muted(remote_peer)
+    }
+
     /// Create a new connection to a remote device
     pub(super) fn create_connection(
         &self,
@@ -2141,18 +2252,25 @@ where
         device_id: DeviceId,
         connection_type: ConnectionType,
         signaling_version: signaling::Version,
-        bandwidth_mode: BandwidthMode,
+        bandwidth_policy: BandwidthPolicy,
         audio_levels_interval: Option<Duration>,
     ) -> Result<Connection<T>> {
-        let mut platform = self.platform.lock()?;
-        platform.create_connection(
-            call,
-            device_id,
-            connection_type,
-            signaling_version,
-            bandwidth_mode,
-            audio_levels_interval,
-        )
+        let connection = {
+            let mut platform = self.platform.lock()?;
+            platform.create_connection(
+                call,
+                device_id,
+                connection_type,
+                signaling_version,
+                bandwidth_policy,
+                audio_levels_interval,
+            )?
+        };
+        connection.set_outgoing_audio_muted(call.outgoing_audio_muted())?;
+        connection
+            .set_talking_while_muted_config(self.talking_while_muted_config.lock()?.clone())?;
+        connection.set_outgoing_video_source(self.outgoing_video_source.lock()?.clone())?;
+        Ok(connection)
     }
 
     /// Create a new application specific media stream
@@ -2541,6 +2659,25 @@ where
         );
     }
 
+    fn handle_talking_while_muted(&self, client_id: group_call::ClientId) {
+        info!("handle_talking_while_muted():");
+        platform_handler!(self, handle_talking_while_muted, client_id);
+    }
+
+    fn handle_spotlight_changed(
+        &self,
+        client_id: group_call::ClientId,
+        spotlight_demux_id: Option<DemuxId>,
+    ) {
+        info!("handle_spotlight_changed({:?}):", spotlight_demux_id);
+        platform_handler!(
+            self,
+            handle_spotlight_changed,
+            client_id,
+            spotlight_demux_id
+        );
+    }
+
     fn handle_ended(&self, client_id: group_call::ClientId, reason: group_call::EndReason) {
         info!("handle_ended({:?}):", reason);
         platform_handler!(self, handle_ended, client_id, reason);
@@ -2631,6 +2768,31 @@ where
         }
     }
 
+    // Unlike peek_group_call, there are no group members to map user IDs through.
+    // The user IDs of the result are decrypted with the root key instead.
+    pub fn peek_call_link(
+        &self,
+        request_id: u32,
+        sfu_url: String,
+        auth_credential_presentation: CallLinkAuthCredentialPresentation,
+        root_key: CallLinkRootKey,
+    ) {
+        let auth_header = call_links::auth_header_from_auth_credential_presentation(
+            &auth_credential_presentation,
+        );
+        let call_manager = self.clone();
+        sfu::peek_call_link(
+            &self.http_client,
+            &sfu_url,
+            &root_key,
+            auth_header,
+            Box::new(move |peek_result| {
+                info!("handle_peek_response");
+                platform_handler!(call_manager, handle_peek_result, request_id, peek_result);
+            }),
+        );
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn create_group_call_client(
         &mut self,
@@ -2681,6 +2843,9 @@ where
             audio_levels_interval,
         )?;
 
+        client.set_talking_while_muted_config(self.talking_while_muted_config.lock()?.clone());
+        client.set_outgoing_video_source(self.outgoing_video_source.lock()?.clone());
+
         let mut client_by_id = self.group_call_by_client_id.lock()?;
         client_by_id.insert(client_id, client);
 
@@ -2689,6 +2854,67 @@ where
         Ok(client_id)
     }
 
+    // The auth credential presentation is used in place of a membership proof;
+    // fresh ones should be provided via set_membership_proof when requested.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_call_link_call_client(
+        &mut self,
+        sfu_url: String,
+        root_key: CallLinkRootKey,
+        auth_credential_presentation: CallLinkAuthCredentialPresentation,
+        hkdf_extra_info: Vec<u8>,
+        audio_levels_interval: Option<Duration>,
+        peer_connection_factory: Option<PeerConnectionFactory>,
+        outgoing_audio_track: AudioTrack,
+        outgoing_video_track: VideoTrack,
+        incoming_video_sink: Option<Box<dyn VideoSink>>,
+    ) -> Result<group_call::ClientId> {
+        info!("create_call_link_call_client():");
+        debug!("  sfu_url: {}", sfu_url);
+
+        let mut next_group_call_client_id = self.next_group_call_client_id.lock()?;
+        if *next_group_call_client_id == group_call::INVALID_CLIENT_ID {
+            *next_group_call_client_id += 1;
+        }
+        let client_id = *next_group_call_client_id;
+        *next_group_call_client_id += 1;
+
+        let sfu_client = CallLinkSfuClient::new(
+            Box::new(self.http_client.clone()),
+            sfu_url,
+            &root_key,
+            hkdf_extra_info,
+        );
+        // The room ID takes the place of the group ID.
+        // Nobody rings for a call link, so there's no ring ID.
+        let room_id = sfu_client.room_id().to_vec();
+        let client = group_call::Client::start(
+            room_id,
+            client_id,
+            Box::new(sfu_client),
+            Box::new(self.clone()),
+            self.busy.clone(),
+            self.self_uuid.clone(),
+            peer_connection_factory,
+            outgoing_audio_track,
+            Some(outgoing_video_track),
+            incoming_video_sink,
+            None,
+            audio_levels_interval,
+        )?;
+        client.set_membership_proof(auth_credential_presentation);
+
+        client.set_talking_while_muted_config(self.talking_while_muted_config.lock()?.clone());
+        client.set_outgoing_video_source(self.outgoing_video_source.lock()?.clone());
+
+        let mut client_by_id = self.group_call_by_client_id.lock()?;
+        client_by_id.insert(client_id, client);
+
+        info!("Call link Client created with id: {}", client_id);
+
+        Ok(client_id)
+    }
+
     pub fn delete_group_call_client(&mut self, client_id: group_call::ClientId) {
         info!("delete_group_call_client(): id: {}", client_id);
 
@@ -2785,11 +3011,76 @@ where
         group_call_api_handler!(self, client_id, set_presenting, presenting);
     }
 
+    pub fn set_incoming_audio_sink(
+        &mut self,
+        client_id: group_call::ClientId,
+        incoming_audio_sink: Option<Box<dyn AudioSink>>,
+    ) {
+        info!(
+            "set_incoming_audio_sink(): id: {} set: {}",
+            client_id,
+            incoming_audio_sink.is_some()
+        );
+        group_call_api_handler!(
+            self,
+            client_id,
+            set_incoming_audio_sink,
+            incoming_audio_sink
+        );
+    }
+
+    pub fn set_recorder(&mut self, client_id: group_call::ClientId, recorder: Option<Recorder>) {
+        info!(
+            "set_recorder(): id: {} recording: {}",
+            client_id,
+            recorder.is_some()
+        );
+        group_call_api_handler!(self, client_id, set_recorder, recorder);
+    }
+
     pub fn set_sharing_screen(&mut self, client_id: group_call::ClientId, sharing_screen: bool) {
         info!("set_sharing_screen(): id: {}", client_id);
         group_call_api_handler!(self, client_id, set_sharing_screen, sharing_screen);
     }
 
+    pub fn set_local_display_name(
+        &mut self,
+        client_id: group_call::ClientId,
+        display_name: Option<String>,
+    ) {
+        info!("set_local_display_name(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, set_local_display_name, display_name);
+    }
+
+    pub fn set_local_avatar_hash(
+        &mut self,
+        client_id: group_call::ClientId,
+        avatar_hash: Option<Vec<u8>>,
+    ) {
+        info!("set_local_avatar_hash(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, set_local_avatar_hash, avatar_hash);
+    }
+
+    pub fn set_remote_audio_volume(
+        &mut self,
+        client_id: group_call::ClientId,
+        demux_id: DemuxId,
+        volume: f64,
+    ) {
+        info!("set_remote_audio_volume(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, set_remote_audio_volume, demux_id, volume);
+    }
+
+    pub fn set_remote_audio_muted(
+        &mut self,
+        client_id: group_call::ClientId,
+        demux_id: DemuxId,
+        muted: bool,
+    ) {
+        info!("set_remote_audio_muted(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, set_remote_audio_muted, demux_id, muted);
+    }
+
     pub fn resend_media_keys(&mut self, client_id: group_call::ClientId) {
         info!("resend_media_keys(): id: {}", client_id);
         group_call_api_handler!(self, client_id, resend_media_keys);
@@ -2820,6 +3111,95 @@ where
         );
     }
 
+    pub fn set_video_request_policy(
+        &mut self,
+        client_id: group_call::ClientId,
+        policy: Option<group_call::VideoRequestPolicy>,
+    ) {
+        info!("set_video_request_policy(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, set_video_request_policy, policy);
+    }
+
+    pub fn set_audio_only(&mut self, client_id: group_call::ClientId, audio_only: bool) {
+        info!(
+            "set_audio_only(): id: {} audio_only: {}",
+            client_id, audio_only
+        );
+        group_call_api_handler!(self, client_id, set_audio_only, audio_only);
+    }
+
+    pub fn set_bandwidth_policy(
+        &mut self,
+        client_id: group_call::ClientId,
+        bandwidth_policy: BandwidthPolicy,
+    ) {
+        info!(
+            "set_bandwidth_policy(): id: {} bandwidth_policy: {:?}",
+            client_id, bandwidth_policy
+        );
+        group_call_api_handler!(self, client_id, set_bandwidth_policy, bandwidth_policy);
+    }
+
+    pub fn set_device_conditions(
+        &mut self,
+        client_id: group_call::ClientId,
+        device_conditions: DeviceConditions,
+    ) {
+        info!(
+            "set_device_conditions(): id: {} device_conditions: {:?}",
+            client_id, device_conditions
+        );
+        group_call_api_handler!(self, client_id, set_device_conditions, device_conditions);
+    }
+
+    /// Replaces the source whose frame processor stats are logged with the
+    /// stats of this group call, and only this one.
+    pub fn set_group_call_outgoing_video_source(
+        &mut self,
+        client_id: group_call::ClientId,
+        source: VideoSource,
+    ) {
+        info!("set_group_call_outgoing_video_source(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, set_outgoing_video_source, Some(source));
+    }
+
+    pub fn set_send_rate_policy(
+        &mut self,
+        client_id: group_call::ClientId,
+        send_rate_policy: Box<dyn SendRatePolicy + Send>,
+    ) {
+        info!("set_send_rate_policy(): id: {}", client_id);
+        group_call_api_handler!(self, client_id, set_send_rate_policy, send_rate_policy);
+    }
+
+    /// Fails if there are more than MAX_VIDEO_ENCODING_LAYERS layers.
+    pub fn set_video_encoding_config(
+        &mut self,
+        client_id: group_call::ClientId,
+        config: Option<VideoEncodingConfig>,
+        full_height: u16,
+    ) -> Result<()> {
+        info!(
+            "set_video_encoding_config(): id: {} config: {:?} full_height: {}",
+            client_id, config, full_height
+        );
+        match self.group_call_by_client_id.lock()?.get(&client_id) {
+            Some(group_call) => group_call.set_video_encoding_config(config, full_height),
+            None => {
+                warn!("Group Client not found for id: {}", client_id);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn set_spotlight(&mut self, client_id: group_call::ClientId, demux_id: Option<DemuxId>) {
+        info!(
+            "set_spotlight(): id: {} demux_id: {:?}",
+            client_id, demux_id
+        );
+        group_call_api_handler!(self, client_id, set_spotlight, demux_id);
+    }
+
     pub fn set_group_members(
         &mut self,
         client_id: group_call::ClientId,
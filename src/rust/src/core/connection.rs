@@ -9,7 +9,7 @@ use std::fmt;
 use std::net::SocketAddr;
 use std::sync::{Arc, Condvar, Mutex, MutexGuard};
 use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 use bytes::{BufMut, BytesMut};
 
@@ -28,12 +28,14 @@ use crate::common::{
     units::DataRate, CallDirection, CallId, CallMediaType, ConnectionState, DeviceId, Result,
     RingBench,
 };
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::core::audio_adaptation::AudioAdaptation;
+use crate::core::bandwidth_mode::{BandwidthMode, BandwidthPolicy};
 use crate::core::call::Call;
 use crate::core::call_mutex::CallMutex;
 use crate::core::connection_fsm::{ConnectionEvent, ConnectionStateMachine};
 use crate::core::platform::Platform;
 use crate::core::signaling;
+use crate::core::speaker_detector::{TalkingWhileMutedConfig, TalkingWhileMutedDetector};
 use crate::core::util::{ptr_as_box, redact_string, TaskQueueRuntime};
 use crate::error::RingRtcError;
 use crate::protobuf;
@@ -41,7 +43,8 @@ use crate::protobuf;
 use crate::webrtc;
 use crate::webrtc::ice_gatherer::IceGatherer;
 use crate::webrtc::media::{
-    AudioEncoderConfig, MediaStream, VideoFrame, VideoFrameMetadata, VideoSink,
+    AudioEncoderConfig, AudioFrame, AudioSink, MediaStream, VideoFrame, VideoFrameMetadata,
+    VideoSink, VideoSource,
 };
 use crate::webrtc::peer_connection::{AudioLevel, PeerConnection, SendRates};
 use crate::webrtc::peer_connection_observer::{
@@ -94,6 +97,10 @@ pub enum ConnectionObserverEvent {
         captured_level: AudioLevel,
         received_level: AudioLevel,
     },
+
+    /// The captured audio level shows sustained talking while
+    /// the app has set the outgoing audio as muted.
+    TalkingWhileMuted,
 }
 
 impl ConnectionObserverEvent {
@@ -267,12 +274,14 @@ const RELAYED_MAX_SEND_RATE: DataRate = DataRate::from_mbps(1);
 /// and the network route (in particular, if it's relayed or not).
 #[derive(Debug)]
 pub struct BandwidthController {
-    /// The current bandwidth mode being used for the local endpoint.
-    pub local_mode: BandwidthMode,
+    /// The current bandwidth policy being used for the local endpoint.
+    pub local_policy: BandwidthPolicy,
     /// The max rate sent from the remote endpoint.
     pub remote_max: Option<DataRate>,
     // The current network route
     pub network_route: NetworkRoute,
+    /// Protection added to the audio when the remote side reports loss.
+    pub audio_adaptation: AudioAdaptation,
 }
 
 impl BandwidthController {
@@ -286,13 +295,19 @@ impl BandwidthController {
 
     pub fn audio_encoder_config(&self) -> AudioEncoderConfig {
         // Min of local and inferred remote
-        self.local_mode
-            .min(self.inferred_remote_mode())
-            .audio_encoder_config()
+        let remote_config = self.inferred_remote_mode().audio_encoder_config();
+        let config = if remote_config.max_bitrate_bps
+            < self.local_policy.audio_encoder_config.max_bitrate_bps
+        {
+            remote_config
+        } else {
+            self.local_policy.audio_encoder_config.clone()
+        };
+        self.audio_adaptation.adapt(&config)
     }
 
     fn local_max(&self) -> DataRate {
-        self.local_mode.max_bitrate()
+        self.local_policy.max_send_rate
     }
 
     fn relay_max(&self) -> Option<DataRate> {
@@ -313,7 +328,12 @@ impl BandwidthController {
             Some(remote_max) if remote_max < BandwidthMode::Normal.max_bitrate() => {
                 BandwidthMode::Low
             }
-            _ => BandwidthMode::Normal,
+            Some(remote_max) if remote_max < BandwidthMode::High.max_bitrate() => {
+                BandwidthMode::Normal
+            }
+            Some(_) => BandwidthMode::High,
+            // Older clients don't send it.
+            None => BandwidthMode::Normal,
         }
     }
 }
@@ -349,6 +369,10 @@ where
     bandwidth_controller: Arc<CallMutex<BandwidthController>>,
     /// The interval for audio level polling
     audio_levels_interval: Option<Duration>,
+    /// Fed by the audio level polling to notice talking while muted.
+    talking_while_muted_detector: Arc<CallMutex<TalkingWhileMutedDetector>>,
+    /// Whose frame processor stats are logged with the other stats.
+    outgoing_video_source: Arc<CallMutex<Option<VideoSource>>>,
     /// Local ICE candidates waiting to be sent over signaling.
     buffered_local_ice_candidates: Arc<CallMutex<Vec<signaling::IceCandidate>>>,
     /// Condition variable used at termination to quiesce and synchronize the FSM.
@@ -364,6 +388,8 @@ where
     // If set, all of the video frames will go here.
     // This is separate from the observer so it can bypass a thread hop.
     incoming_video_sink: Option<Box<dyn VideoSink>>,
+    // If set, all of the decoded audio will go here (as well as to the playout device).
+    incoming_audio_sink: Arc<CallMutex<Option<Box<dyn AudioSink>>>>,
 }
 
 impl<T> fmt::Display for Connection<T>
@@ -440,6 +466,8 @@ where
             webrtc: Arc::clone(&self.webrtc),
             bandwidth_controller: Arc::clone(&self.bandwidth_controller),
             audio_levels_interval: self.audio_levels_interval,
+            talking_while_muted_detector: Arc::clone(&self.talking_while_muted_detector),
+            outgoing_video_source: Arc::clone(&self.outgoing_video_source),
             buffered_local_ice_candidates: Arc::clone(&self.buffered_local_ice_candidates),
             terminate_condvar: Arc::clone(&self.terminate_condvar),
             connection_type: self.connection_type,
@@ -447,6 +475,7 @@ where
             accumulated_rtp_data_message: Arc::clone(&self.accumulated_rtp_data_message),
             last_received_rtp_data_timestamp: Arc::clone(&self.last_received_rtp_data_timestamp),
             incoming_video_sink: self.incoming_video_sink.clone(),
+            incoming_audio_sink: Arc::clone(&self.incoming_audio_sink),
         }
     }
 }
@@ -461,7 +490,7 @@ where
         call: Call<T>,
         remote_device: DeviceId,
         connection_type: ConnectionType,
-        bandwidth_mode: BandwidthMode,
+        bandwidth_policy: BandwidthPolicy,
         audio_levels_interval: Option<Duration>,
         incoming_video_sink: Option<Box<dyn VideoSink>>,
     ) -> Result<Self> {
@@ -493,7 +522,7 @@ where
             webrtc: Arc::new(CallMutex::new(webrtc, "webrtc")),
             bandwidth_controller: Arc::new(CallMutex::new(
                 BandwidthController {
-                    local_mode: bandwidth_mode,
+                    local_policy: bandwidth_policy,
                     remote_max: None,
                     network_route: NetworkRoute {
                         local_adapter_type: NetworkAdapterType::Unknown,
@@ -502,10 +531,16 @@ where
                         local_relay_protocol: TransportProtocol::Unknown,
                         remote_relayed: false,
                     },
+                    audio_adaptation: AudioAdaptation::new(),
                 },
                 "webrtc",
             )),
             audio_levels_interval,
+            talking_while_muted_detector: Arc::new(CallMutex::new(
+                TalkingWhileMutedDetector::default(),
+                "talking_while_muted_detector",
+            )),
+            outgoing_video_source: Arc::new(CallMutex::new(None, "outgoing_video_source")),
             buffered_local_ice_candidates: Arc::new(CallMutex::new(
                 Vec::new(),
                 "buffered_local_ice_candidates",
@@ -522,6 +557,7 @@ where
                 "last_received_rtp_data_timestamp",
             )),
             incoming_video_sink,
+            incoming_audio_sink: Arc::new(CallMutex::new(None, "incoming_audio_sink")),
         };
 
         connection.init_connection_ptr()?;
@@ -553,11 +589,15 @@ where
     pub fn start_outgoing_parent(
         &mut self,
         call_media_type: CallMediaType,
-        bandwidth_mode: BandwidthMode,
     ) -> Result<(StaticSecret, IceGatherer, signaling::Offer)> {
         let result = (|| {
             self.set_state(ConnectionState::Starting)?;
 
+            let max_receive_rate = self
+                .bandwidth_controller
+                .lock()?
+                .local_policy
+                .max_receive_rate;
             let webrtc = self.webrtc.lock()?;
             let peer_connection = webrtc.peer_connection()?;
 
@@ -573,7 +613,7 @@ where
 
             // We have to do this before we pass ownership of offer_sdi into set_local_description.
             let (local_secret, local_public_key) = generate_local_secret_and_public_key()?;
-            let v4_offer = offer.to_v4(local_public_key.as_bytes().to_vec(), bandwidth_mode)?;
+            let v4_offer = offer.to_v4(local_public_key.as_bytes().to_vec(), max_receive_rate)?;
 
             info!(
                 "Outgoing offer codecs: {:?}, max_bitrate: {:?}",
@@ -788,7 +828,7 @@ where
             let answer_to_send = if v4_offer.is_some() {
                 let v4_answer = answer.to_v4(
                     local_public_key.as_bytes().to_vec(),
-                    bandwidth_controller.local_mode,
+                    bandwidth_controller.local_policy.max_receive_rate,
                 )?;
 
                 info!(
@@ -924,14 +964,29 @@ where
     /// Update the PeerConnection.
     pub fn set_peer_connection(&self, peer_connection: PeerConnection) -> Result<()> {
         let mut webrtc = self.webrtc.lock()?;
+        if self.incoming_audio_sink.lock()?.is_some() {
+            peer_connection.set_incoming_audio_frames_enabled(true);
+        }
         webrtc.peer_connection = Some(peer_connection);
         Ok(())
     }
 
-    /// Return the current local bandwidth mode used for this connection.
-    pub fn local_bandwidth_mode(&self) -> Result<BandwidthMode> {
+    /// Decoded audio from the remote peer will go to the sink (as well as
+    /// to the playout device) until it's replaced or cleared.
+    pub fn set_incoming_audio_sink(&self, sink: Option<Box<dyn AudioSink>>) -> Result<()> {
+        let enabled = sink.is_some();
+        *self.incoming_audio_sink.lock()? = sink;
+        let webrtc = self.webrtc.lock()?;
+        if let Some(peer_connection) = webrtc.peer_connection.as_ref() {
+            peer_connection.set_incoming_audio_frames_enabled(enabled);
+        }
+        Ok(())
+    }
+
+    /// Return the current local bandwidth policy used for this connection.
+    pub fn local_bandwidth_policy(&self) -> Result<BandwidthPolicy> {
         let bandwidth_controller = self.bandwidth_controller.lock()?;
-        Ok(bandwidth_controller.local_mode)
+        Ok(bandwidth_controller.local_policy.clone())
     }
 
     /// Needed for ICE forking (we must copy this value from the parent connection
@@ -1048,14 +1103,20 @@ where
     /// The local user is updating the bandwidth mode via the API. Update locally and
     /// send an updated bitrate to the remote.
     pub fn update_bandwidth_mode(&self, local_mode: BandwidthMode) -> Result<()> {
+        self.update_bandwidth_policy(local_mode.into())
+    }
+
+    /// Like update_bandwidth_mode, but with arbitrary limits.
+    pub fn update_bandwidth_policy(&self, local_policy: BandwidthPolicy) -> Result<()> {
+        let max_receive_rate = local_policy.max_receive_rate;
         let changed = self.update_bandwidth_controller(|bandwidth_controller| {
-            if bandwidth_controller.local_mode == local_mode {
+            if bandwidth_controller.local_policy == local_policy {
                 // Nothing changed
                 return false;
             }
-            bandwidth_controller.local_mode = local_mode;
+            bandwidth_controller.local_policy = local_policy;
             info!(
-                "update_bandwidth_mode(): bandwidth_controller: {:?}",
+                "update_bandwidth_policy(): bandwidth_controller: {:?}",
                 bandwidth_controller
             );
             true
@@ -1064,7 +1125,7 @@ where
         if changed {
             let mut receiver_status = protobuf::rtp_data::ReceiverStatus {
                 id: Some(u64::from(self.call_id)),
-                max_bitrate_bps: Some(local_mode.max_bitrate().as_bps()),
+                max_bitrate_bps: Some(max_receive_rate.as_bps()),
             };
             receiver_status.id = Some(u64::from(self.call_id));
 
@@ -1122,9 +1183,15 @@ where
             self.send_latest_rtp_data_message(&mut webrtc)?;
         }
 
+        let mut audio_sender_statistics = None;
         if ticks_elapsed % POLL_STATS_INTERVAL_TICKS == 0 {
             if let Some(observer) = webrtc.stats_observer.as_ref() {
+                // Stats arrive asynchronously, so these are from the previous poll.
+                audio_sender_statistics = observer.take_audio_sender_statistics();
                 let _ = webrtc.peer_connection()?.get_stats(observer);
+                if let Some(source) = self.outgoing_video_source.lock()?.as_ref() {
+                    observer.log_video_frame_processor_stats(&source.frame_processor_stats());
+                }
             } else {
                 warn!("tick(): No stats_observer found");
             }
@@ -1148,9 +1215,20 @@ where
                 if let Err(err) = self.notify_observer(event) {
                     warn!("tick(): failed to notify of audio levels: {:?}", err);
                 }
+                self.detect_talking_while_muted(Instant::now(), captured_level)?;
             }
         }
 
+        if let Some(audio_sender_statistics) = audio_sender_statistics {
+            // We need to always take the locks in the order of (bandwidth_controller, webrtc).
+            drop(webrtc);
+            self.update_bandwidth_controller(|bandwidth_controller| {
+                bandwidth_controller
+                    .audio_adaptation
+                    .update(&audio_sender_statistics)
+            })?;
+        }
+
         Ok(())
     }
 
@@ -1163,6 +1241,42 @@ where
         )
     }
 
+    /// Fed by the tick with the captured audio level.
+    pub fn detect_talking_while_muted(
+        &self,
+        now: Instant,
+        captured_level: AudioLevel,
+    ) -> Result<()> {
+        if self
+            .talking_while_muted_detector
+            .lock()?
+            .update(now, captured_level)
+        {
+            let event = ConnectionObserverEvent::TalkingWhileMuted;
+            if let Err(err) = self.notify_observer(event) {
+                warn!("failed to notify of talking while muted: {:?}", err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Lets the Connection notice when the user talks while muted.
+    /// We don't modify the outgoing audio track.  We expect the app to handle that.
+    pub fn set_outgoing_audio_muted(&self, muted: bool) -> Result<()> {
+        self.talking_while_muted_detector.lock()?.set_muted(muted);
+        Ok(())
+    }
+
+    pub fn set_talking_while_muted_config(&self, config: TalkingWhileMutedConfig) -> Result<()> {
+        self.talking_while_muted_detector.lock()?.set_config(config);
+        Ok(())
+    }
+
+    pub fn set_outgoing_video_source(&self, source: Option<VideoSource>) -> Result<()> {
+        *self.outgoing_video_source.lock()? = source;
+        Ok(())
+    }
+
     pub fn set_outgoing_media_enabled(&self, enabled: bool) -> Result<()> {
         let webrtc = self.webrtc.lock()?;
         webrtc
@@ -1902,7 +2016,19 @@ where
     ///
     /// * `mode` - The bandwidth mode that should be used
     pub fn inject_update_bandwidth_mode(&mut self, bandwidth_mode: BandwidthMode) -> Result<()> {
-        self.inject_event(ConnectionEvent::UpdateBandwidthMode(bandwidth_mode))
+        self.inject_update_bandwidth_policy(bandwidth_mode.into())
+    }
+
+    /// Inject a `UpdateBandwidthPolicy` event into the FSM.
+    ///
+    /// `Called By:` Local application.
+    ///
+    /// * `policy` - The bandwidth policy that should be used
+    pub fn inject_update_bandwidth_policy(
+        &mut self,
+        bandwidth_policy: BandwidthPolicy,
+    ) -> Result<()> {
+        self.inject_event(ConnectionEvent::UpdateBandwidthPolicy(bandwidth_policy))
     }
 
     /// Inject a `ReceivedIce` event into the FSM.
@@ -2069,6 +2195,13 @@ where
         Ok(())
     }
 
+    fn handle_incoming_audio_frame(&mut self, track_id: u32, frame: AudioFrame) -> Result<()> {
+        if let Some(incoming_audio_sink) = self.incoming_audio_sink.lock()?.as_ref() {
+            incoming_audio_sink.on_audio_frame(track_id, frame)
+        }
+        Ok(())
+    }
+
     fn handle_rtp_received(&mut self, header: rtp::Header, payload: &[u8]) {
         let data = match (header.pt, header.ssrc) {
             // Old clients send with 4 bytes of reserved data.
@@ -2189,7 +2322,7 @@ mod tests {
         relayed: bool,
     ) -> (DataRate, AudioEncoderConfig) {
         let controller = BandwidthController {
-            local_mode,
+            local_policy: local_mode.into(),
             remote_max: Some(DataRate::from_bps(remote_max_bps)),
             network_route: NetworkRoute {
                 local_adapter_type: NetworkAdapterType::Unknown,
@@ -2198,6 +2331,7 @@ mod tests {
                 local_relay_protocol: TransportProtocol::Unknown,
                 remote_relayed: false,
             },
+            audio_adaptation: AudioAdaptation::new(),
         };
         (
             controller.max_send_rate(),
@@ -2217,6 +2351,11 @@ mod tests {
         assert_eq!(expect(299_000, VeryLow), compute(Normal, 299_000, false));
         assert_eq!(expect(30_000, VeryLow), compute(Normal, 1_000, false));
 
+        // High needs both sides to allow it.
+        assert_eq!(expect(4_000_000, High), compute(High, 5_000_000, false));
+        assert_eq!(expect(2_000_000, Normal), compute(High, 2_000_000, false));
+        assert_eq!(expect(2_000_000, Normal), compute(Normal, 5_000_000, false));
+
         // Local mode can also push it down
         assert_eq!(expect(300_000, Low), compute(Low, 3_000_000, false));
         assert_eq!(expect(300_000, Low), compute(Low, 2_000_000, false));
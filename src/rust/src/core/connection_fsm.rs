@@ -56,7 +56,7 @@ use futures::task::Poll;
 use futures::{Future, Stream};
 
 use crate::common::{units::DataRate, CallDirection, CallId, ConnectionState, Result, RingBench};
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::core::bandwidth_mode::BandwidthPolicy;
 use crate::core::connection::{Connection, ConnectionObserverEvent, EventStream};
 use crate::core::platform::Platform;
 use crate::core::signaling;
@@ -99,10 +99,10 @@ pub enum ConnectionEvent {
     /// Source: app (user action)
     /// Action: Accumulate and send a sender status message via RTP data.
     UpdateSenderStatus(signaling::SenderStatus),
-    /// Set bandwidth mode
+    /// Set bandwidth policy (or mode, converted to a policy)
     /// Source: app (user setting)
     /// Action: Update and send bitrate via a receiver status message via RTP data.
-    UpdateBandwidthMode(BandwidthMode),
+    UpdateBandwidthPolicy(BandwidthPolicy),
     /// Local ICE candidates added or removed from PeerConnection
     /// Source: PeerConnection
     /// Action: Send ICE candidate (addition or removal) over signaling.
@@ -170,8 +170,8 @@ impl fmt::Display for ConnectionEvent {
             ConnectionEvent::UpdateSenderStatus(status) => {
                 format!("UpdateSenderStatus, status: {:?}", status)
             }
-            ConnectionEvent::UpdateBandwidthMode(mode) => {
-                format!("UpdateBandwidthMode, mode: {:?}", mode)
+            ConnectionEvent::UpdateBandwidthPolicy(policy) => {
+                format!("UpdateBandwidthPolicy, policy: {:?}", policy)
             }
             ConnectionEvent::LocalIceCandidates(_) => "LocalIceCandidates".to_string(),
             ConnectionEvent::IceConnected => "IceConnected".to_string(),
@@ -403,8 +403,8 @@ where
             ConnectionEvent::UpdateSenderStatus(status) => {
                 self.handle_update_sender_status(connection, state, status)
             }
-            ConnectionEvent::UpdateBandwidthMode(mode) => {
-                self.handle_update_bandwidth_mode(connection, state, mode)
+            ConnectionEvent::UpdateBandwidthPolicy(policy) => {
+                self.handle_update_bandwidth_policy(connection, state, policy)
             }
             ConnectionEvent::LocalIceCandidates(candidates) => {
                 self.handle_local_ice_candidates(connection, state, candidates)
@@ -773,26 +773,26 @@ where
         Ok(())
     }
 
-    fn handle_update_bandwidth_mode(
+    fn handle_update_bandwidth_policy(
         &mut self,
         connection: Connection<T>,
         state: ConnectionState,
-        bandwidth_mode: BandwidthMode,
+        bandwidth_policy: BandwidthPolicy,
     ) -> Result<()> {
         if state.connecting_or_connected() {
             let mut err_connection = connection.clone();
-            let update_bandwidth_mode_future = lazy(move |_| {
+            let update_bandwidth_policy_future = lazy(move |_| {
                 if connection.terminating()? {
                     return Ok(());
                 }
 
-                connection.update_bandwidth_mode(bandwidth_mode)
+                connection.update_bandwidth_policy(bandwidth_policy)
             })
             .unwrap_or_else(move |err| {
-                err_connection.inject_internal_error(err, "Updating bandwidth mode failed");
+                err_connection.inject_internal_error(err, "Updating bandwidth policy failed");
             });
 
-            self.worker_spawn(update_bandwidth_mode_future);
+            self.worker_spawn(update_bandwidth_policy_future);
         };
         Ok(())
     }
@@ -30,10 +30,20 @@ use crate::{
         Result,
     },
     core::{
-        bandwidth_mode::BandwidthMode, call_mutex::CallMutex, crypto as frame_crypto, signaling,
+        audio_adaptation::{AudioAdaptation, AudioProtectionLevel},
+        bandwidth_mode::{BandwidthMode, BandwidthPolicy},
+        call_mutex::CallMutex,
+        crypto as frame_crypto,
+        recorder::Recorder,
+        send_rate_policy::{
+            DefaultSendRatePolicy, DeviceConditions, SendRateInputs, SendRatePolicy,
+        },
+        signaling,
+        speaker_detector::{SpeakerDetector, TalkingWhileMutedConfig, TalkingWhileMutedDetector},
     },
     error::RingRtcError,
     lite::{
+        call_links::{self, CallLinkRootKey, RoomId},
         http, sfu,
         sfu::{
             DemuxId, GroupMember, MembershipProof, OpaqueUserIdMapping, PeekDeviceInfo, PeekInfo,
@@ -43,8 +53,13 @@ use crate::{
     protobuf,
     webrtc::{
         self,
-        media::{AudioTrack, VideoFrame, VideoFrameMetadata, VideoSink, VideoTrack},
-        peer_connection::{AudioLevel, PeerConnection, ReceivedAudioLevel, SendRates},
+        media::{
+            AudioEncoderConfig, AudioFrame, AudioSink, AudioTrack, VideoEncodingConfig, VideoFrame,
+            VideoFrameMetadata, VideoSink, VideoSource, VideoTrack,
+        },
+        peer_connection::{
+            AudioLevel, PeerConnection, ReceivedAudioLevel, SendRates, MAX_REMOTE_AUDIO_VOLUME,
+        },
         peer_connection_factory::{self as pcf, IceServer, PeerConnectionFactory},
         peer_connection_observer::{
             IceConnectionState, NetworkRoute, PeerConnectionObserver, PeerConnectionObserverTrait,
@@ -159,9 +174,13 @@ pub enum RemoteDevicesChangedReason {
     DemuxIdsChanged,
     MediaKeyReceived(DemuxId),
     SpeakerTimeChanged(DemuxId),
+    SpeakingChanged,
     HeartbeatStateChanged(DemuxId),
+    ProfileChanged(DemuxId),
+    SpotlightChanged,
     ForwardedVideosChanged,
     HigherResolutionPendingChanged,
+    VideoSlotsChanged,
 }
 
 // The callbacks from the Call to the Observer of the call.
@@ -234,6 +253,15 @@ pub trait Observer {
         received_levels: Vec<ReceivedAudioLevel>,
     );
 
+    // Called when the captured audio level shows sustained talking
+    // while set_outgoing_audio_muted(true), so the app can nudge the user.
+    // Only called when audio levels are polled.
+    fn handle_talking_while_muted(&self, client_id: ClientId);
+
+    // Called when a device is put in the spotlight for everyone, or taken out
+    // of it (None).  The device may be the local device.
+    fn handle_spotlight_changed(&self, client_id: ClientId, spotlight_demux_id: Option<DemuxId>);
+
     // This will be the last callback.
     // The observer can assume the Call is completely shut down and can be deleted.
     fn handle_ended(&self, client_id: ClientId, reason: EndReason);
@@ -419,6 +447,35 @@ pub struct Joined {
     pub hkdf_extra_info: Vec<u8>,
 }
 
+/// Maps the SFU's response to a join request, shared by all SFU clients
+/// so they report the same errors for the same response codes.
+fn joined_from_join_result(
+    join_result: sfu::JoinResult,
+    hkdf_extra_info: Vec<u8>,
+) -> Result<Joined> {
+    match join_result {
+        Ok(join_response) => Ok(Joined {
+            sfu_info: SfuInfo {
+                udp_addresses: join_response.server_addresses,
+                ice_ufrag: join_response.server_ice_ufrag,
+                ice_pwd: join_response.server_ice_pwd,
+            },
+            local_demux_id: join_response.client_demux_id,
+            server_dhe_pub_key: join_response.server_dhe_pub_key,
+            hkdf_extra_info,
+        }),
+        Err(http_status) if http_status == sfu::ResponseCode::RequestFailed => {
+            Err(RingRtcError::SfuClientRequestFailed.into())
+        }
+        Err(http_status) if http_status == sfu::ResponseCode::GroupCallFull => {
+            Err(RingRtcError::GroupCallFull.into())
+        }
+        Err(http_status) => {
+            Err(RingRtcError::UnexpectedResponseCodeFromSFu(http_status.code).into())
+        }
+    }
+}
+
 /// Communicates with the SFU using HTTP.
 pub struct HttpSfuClient {
     url: String,
@@ -461,29 +518,8 @@ impl HttpSfuClient {
             ice_ufrag,
             dhe_pub_key,
             &self.hkdf_extra_info,
-            Box::new(move |join_response| {
-                let join_result: Result<Joined> = match join_response {
-                    Ok(join_response) => Ok(Joined {
-                        sfu_info: SfuInfo {
-                            udp_addresses: join_response.server_addresses,
-                            ice_ufrag: join_response.server_ice_ufrag,
-                            ice_pwd: join_response.server_ice_pwd,
-                        },
-                        local_demux_id: join_response.client_demux_id,
-                        server_dhe_pub_key: join_response.server_dhe_pub_key,
-                        hkdf_extra_info,
-                    }),
-                    Err(http_status) if http_status == sfu::ResponseCode::RequestFailed => {
-                        Err(RingRtcError::SfuClientRequestFailed.into())
-                    }
-                    Err(http_status) if http_status == sfu::ResponseCode::GroupCallFull => {
-                        Err(RingRtcError::GroupCallFull.into())
-                    }
-                    Err(http_status) => {
-                        Err(RingRtcError::UnexpectedResponseCodeFromSFu(http_status.code).into())
-                    }
-                };
-                client.on_sfu_client_joined(join_result);
+            Box::new(move |join_result| {
+                client.on_sfu_client_joined(joined_from_join_result(join_result, hkdf_extra_info));
             }),
         );
     }
@@ -536,12 +572,120 @@ impl SfuClient for HttpSfuClient {
     }
 }
 
+/// Communicates with the SFU using HTTP to join the room of a call link.
+///
+/// Participants of a call link aren't necessarily members of a group,
+/// so an auth credential presentation takes the place of the membership proof
+/// and the user IDs of remote devices aren't mapped through GroupMembers.
+/// Instead, they are decrypted with the root key, and devices exchange
+/// display names over encrypted RTP data.
+pub struct CallLinkSfuClient {
+    url: String,
+    root_key: CallLinkRootKey,
+    room_id: RoomId,
+    // For use post-DHE
+    hkdf_extra_info: Vec<u8>,
+    http_client: Box<dyn http::Client + Send>,
+    auth_header: Option<String>,
+    deferred_join: Option<(String, [u8; 32], Client)>,
+}
+
+impl CallLinkSfuClient {
+    pub fn new(
+        http_client: Box<dyn http::Client + Send>,
+        url: String,
+        root_key: &CallLinkRootKey,
+        hkdf_extra_info: Vec<u8>,
+    ) -> Self {
+        Self {
+            url,
+            root_key: *root_key,
+            room_id: root_key.derive_room_id(),
+            hkdf_extra_info,
+            http_client,
+            auth_header: None,
+            deferred_join: None,
+        }
+    }
+
+    pub fn room_id(&self) -> &[u8] {
+        &self.room_id
+    }
+
+    fn join_with_header(
+        &self,
+        auth_header: String,
+        ice_ufrag: &str,
+        dhe_pub_key: &[u8],
+        client: Client,
+    ) {
+        let hkdf_extra_info = self.hkdf_extra_info.clone();
+        sfu::join_call_link(
+            self.http_client.as_ref(),
+            &self.url,
+            &self.room_id,
+            auth_header,
+            ice_ufrag,
+            dhe_pub_key,
+            &self.hkdf_extra_info,
+            Box::new(move |join_result| {
+                client.on_sfu_client_joined(joined_from_join_result(join_result, hkdf_extra_info));
+            }),
+        );
+    }
+}
+
+impl SfuClient for CallLinkSfuClient {
+    // For a call link, the "proof" is a CallLinkAuthCredentialPresentation.
+    fn set_membership_proof(&mut self, proof: MembershipProof) {
+        let auth_header = call_links::auth_header_from_auth_credential_presentation(&proof);
+        self.auth_header = Some(auth_header.clone());
+        // Release any tasks that were blocked on getting the credential.
+        if let Some((ice_ufrag, dhe_pub_key, client)) = self.deferred_join.take() {
+            info!("call link credential received, proceeding with deferred join");
+            self.join_with_header(auth_header, &ice_ufrag, &dhe_pub_key[..], client);
+        }
+    }
+
+    fn join(&mut self, ice_ufrag: &str, dhe_pub_key: [u8; 32], client: Client) {
+        match self.auth_header.as_ref() {
+            Some(h) => self.join_with_header(h.clone(), ice_ufrag, &dhe_pub_key[..], client),
+            None => {
+                info!("join requested without call link credential - deferring");
+                let ice_ufrag = ice_ufrag.to_string();
+                self.deferred_join = Some((ice_ufrag, dhe_pub_key, client));
+            }
+        }
+    }
+
+    fn peek(&mut self, result_callback: PeekResultCallback) {
+        match self.auth_header.clone() {
+            Some(auth_header) => sfu::peek_call_link(
+                self.http_client.as_ref(),
+                &self.url,
+                &self.root_key,
+                auth_header,
+                result_callback,
+            ),
+            None => {
+                result_callback(Err(sfu::ResponseCode::InvalidClientAuth.into()));
+            }
+        }
+    }
+
+    fn set_group_members(&mut self, _members: Vec<GroupMember>) {
+        // There are no group members for a call link.
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
 pub struct HeartbeatState {
     pub audio_muted: Option<bool>,
     pub video_muted: Option<bool>,
     pub presenting: Option<bool>,
     pub sharing_screen: Option<bool>,
+    pub audio_only: Option<bool>,
+    pub recording: Option<bool>,
 }
 
 impl From<protobuf::group_call::device_to_device::Heartbeat> for HeartbeatState {
@@ -551,10 +695,94 @@ impl From<protobuf::group_call::device_to_device::Heartbeat> for HeartbeatState
             video_muted: proto.video_muted,
             presenting: proto.presenting,
             sharing_screen: proto.sharing_screen,
+            audio_only: proto.audio_only,
+            recording: proto.recording,
+        }
+    }
+}
+
+/// What a device tells others about its user so they can label it,
+/// even when their app doesn't know the user (such as in a call link).
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Profile {
+    pub display_name: Option<String>,
+    pub avatar_hash: Option<Vec<u8>>,
+}
+
+// Profiles come from remote devices, so don't let them be arbitrarily big.
+const MAX_DISPLAY_NAME_LEN: usize = 256;
+const MAX_AVATAR_HASH_LEN: usize = 64;
+// Profiles can arrive before the SFU tells us about the device that sent them,
+// but don't hold on to an unbounded number of them.
+const MAX_PENDING_REMOTE_PROFILES: usize = 64;
+
+impl Profile {
+    fn to_proto(&self) -> protobuf::group_call::device_to_device::Profile {
+        protobuf::group_call::device_to_device::Profile {
+            display_name: self.display_name.clone(),
+            avatar_hash: self.avatar_hash.clone(),
+        }
+    }
+}
+
+impl From<protobuf::group_call::device_to_device::Profile> for Profile {
+    fn from(proto: protobuf::group_call::device_to_device::Profile) -> Self {
+        Self {
+            display_name: proto.display_name.map(truncate_display_name),
+            avatar_hash: proto
+                .avatar_hash
+                .filter(|avatar_hash| avatar_hash.len() <= MAX_AVATAR_HASH_LEN),
+        }
+    }
+}
+
+fn truncate_display_name(mut display_name: String) -> String {
+    if display_name.len() > MAX_DISPLAY_NAME_LEN {
+        let mut len = MAX_DISPLAY_NAME_LEN;
+        while !display_name.is_char_boundary(len) {
+            len -= 1;
+        }
+        display_name.truncate(len);
+    }
+    display_name
+}
+
+// How the audio of a remote device is played locally.
+// Not sent to anyone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RemoteAudioSettings {
+    volume: f64,
+    muted: bool,
+}
+
+impl Default for RemoteAudioSettings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            muted: false,
+        }
+    }
+}
+
+impl RemoteAudioSettings {
+    fn effective_volume(&self) -> f64 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume
         }
     }
 }
 
+// Orders changes to the spotlight so every device ends up with the newest one:
+// a higher sequence number wins, and the demux ID of the device that made the
+// change breaks ties between changes made at the same time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct SpotlightVersion {
+    sequence_number: u32,
+    set_by_demux_id: DemuxId,
+}
+
 // The info about remote devices received from the SFU
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct RemoteDeviceState {
@@ -562,6 +790,9 @@ pub struct RemoteDeviceState {
     pub user_id: UserId,
     pub media_keys_received: bool,
     pub heartbeat_state: HeartbeatState,
+    // Sent by the remote device itself.  Useful when the app doesn't know the user
+    // (such as in a call link).
+    pub profile: Profile,
     // The latest timestamp we received from an update to
     // heartbeat_state.
     heartbeat_rtp_timestamp: Option<rtp::Timestamp>,
@@ -572,9 +803,30 @@ pub struct RemoteDeviceState {
     // The most recent time at which this device became the primary speaker
     // Sorting using this value will give a history of who spoke.
     pub speaker_time: Option<SystemTime>,
+    // Whether this device is speaking right now, detected locally from
+    // the level of its audio.  Unlike the speaker from the SFU, many
+    // devices can be speaking at the same time.
+    pub speaking: bool,
+    // The most recent time at which this device started or stopped speaking,
+    // detected locally.  Sorting by (speaking, last_speaking_time) ranks
+    // the recent speakers even if the SFU doesn't send speaker changes.
+    pub last_speaking_time: Option<SystemTime>,
+    // Where this device is in the recent speakers detected locally:
+    // devices speaking now come first (most recently started first), then
+    // those that stopped (most recent first).  None if it hasn't spoken.
+    pub recent_speaker_rank: Option<usize>,
+    // Whether some device in the call put this device in the spotlight for everyone.
+    pub spotlighted: bool,
+    // Which tile of the VideoRequestPolicy this device is shown in, counting the
+    // tiles of its slots in order.  Outside the spotlight, this only changes when the
+    // device stops being shown, so the app can keep tiles from jumping around.
+    // None if it's offscreen or there is no policy.
+    pub video_slot: Option<usize>,
     pub leaving_received: bool,
     pub forwarding_video: Option<bool>,
     pub server_allocated_height: u16,
+    // None if the SFU isn't limiting the framerate (or isn't forwarding video).
+    pub server_allocated_framerate: Option<u16>,
     pub client_decoded_height: Option<u32>,
     pub is_higher_resolution_pending: bool,
 }
@@ -598,13 +850,20 @@ impl RemoteDeviceState {
             user_id,
             media_keys_received: false,
             heartbeat_state: Default::default(),
+            profile: Profile::default(),
             heartbeat_rtp_timestamp: None,
 
             added_time,
             speaker_time: None,
+            speaking: false,
+            last_speaking_time: None,
+            recent_speaker_rank: None,
+            spotlighted: false,
+            video_slot: None,
             leaving_received: false,
             forwarding_video: None,
             server_allocated_height: 0,
+            server_allocated_framerate: None,
             client_decoded_height: None,
             is_higher_resolution_pending: false,
         }
@@ -614,6 +873,10 @@ impl RemoteDeviceState {
         as_unix_millis(self.speaker_time)
     }
 
+    pub fn last_speaking_time_as_unix_millis(&self) -> u64 {
+        as_unix_millis(self.last_speaking_time)
+    }
+
     pub fn added_time_as_unix_millis(&self) -> u64 {
         as_unix_millis(Some(self.added_time))
     }
@@ -643,6 +906,226 @@ pub struct VideoRequest {
     pub framerate: Option<u16>,
 }
 
+/// A place in the app's layout where remote video is shown.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VideoTileSlot {
+    /// One big tile, given to the pinned device, else the spotlighted device,
+    /// else the latest speaker.
+    Spotlight {
+        width: u16,
+        height: u16,
+        framerate: Option<u16>,
+    },
+    /// A grid of `count` tiles of the same size.
+    Grid {
+        count: u16,
+        width: u16,
+        height: u16,
+        framerate: Option<u16>,
+    },
+    /// A strip of `count` small tiles.  A low framerate (such as 15)
+    /// saves bandwidth without being noticeable at this size.
+    Thumbnails {
+        count: u16,
+        width: u16,
+        height: u16,
+        framerate: Option<u16>,
+    },
+    /// Shown nowhere.  Any device left over after filling the other slots
+    /// is also offscreen, so this is only needed to make that explicit.
+    Offscreen,
+}
+
+impl VideoTileSlot {
+    /// Builds a slot from the kind the platforms pass: 0 = Spotlight, 1 = Grid,
+    /// 2 = Thumbnails and 3 = Offscreen.  `count` is ignored unless it's a Grid
+    /// or Thumbnails.
+    pub fn from_kind(
+        kind: i32,
+        count: u16,
+        width: u16,
+        height: u16,
+        framerate: Option<u16>,
+    ) -> Option<Self> {
+        match kind {
+            0 => Some(VideoTileSlot::Spotlight {
+                width,
+                height,
+                framerate,
+            }),
+            1 => Some(VideoTileSlot::Grid {
+                count,
+                width,
+                height,
+                framerate,
+            }),
+            2 => Some(VideoTileSlot::Thumbnails {
+                count,
+                width,
+                height,
+                framerate,
+            }),
+            3 => Some(VideoTileSlot::Offscreen),
+            _ => {
+                warn!("Invalid video tile slot kind: {}", kind);
+                None
+            }
+        }
+    }
+}
+
+/// Lets the Client build the video requests from a description of the layout
+/// instead of the app calling request_video whenever anything changes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VideoRequestPolicy {
+    /// Filled in order, so slots after an Offscreen slot are never filled.
+    pub slots: Vec<VideoTileSlot>,
+    /// Goes in the first slot regardless of who is speaking, or who is in the spotlight.
+    pub pinned_demux_id: Option<DemuxId>,
+}
+
+impl VideoRequestPolicy {
+    /// Assigns devices to slots: the pinned device first, then the spotlighted device,
+    /// then by most recent speaker, then by when they were added.  Returns the requests and the
+    /// active speaker height to send to the SFU.
+    ///
+    /// Also sets each device's video_slot.  Spotlight tiles always go to the devices ranked
+    /// first, but a device in any other tile keeps it for as long as it is shown at all, so
+    /// those tiles are only reassigned when a device leaves them.
+    fn video_requests(&self, remote_devices: &mut [RemoteDeviceState]) -> (Vec<VideoRequest>, u16) {
+        let mut ranked: Vec<usize> = (0..remote_devices.len()).collect();
+        ranked.sort_by_key(|index| {
+            let device = &remote_devices[*index];
+            (
+                Some(device.demux_id) != self.pinned_demux_id,
+                !device.spotlighted,
+                std::cmp::Reverse(device.speaker_time),
+                device.added_time,
+                device.demux_id,
+            )
+        });
+
+        // (width, height, framerate, is_spotlight) for each tile, in layout order.
+        let mut tiles = Vec::new();
+        for slot in &self.slots {
+            match *slot {
+                VideoTileSlot::Spotlight {
+                    width,
+                    height,
+                    framerate,
+                } => tiles.push((width, height, framerate, true)),
+                VideoTileSlot::Grid {
+                    count,
+                    width,
+                    height,
+                    framerate,
+                }
+                | VideoTileSlot::Thumbnails {
+                    count,
+                    width,
+                    height,
+                    framerate,
+                } => tiles.extend((0..count).map(|_| (width, height, framerate, false))),
+                VideoTileSlot::Offscreen => break,
+            }
+        }
+        let shown = &ranked[..std::cmp::min(tiles.len(), ranked.len())];
+
+        let mut assigned_tiles: Vec<Option<usize>> = vec![None; remote_devices.len()];
+        let mut tile_taken = vec![false; tiles.len()];
+        let spotlight_tiles = tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, _, _, is_spotlight))| *is_spotlight)
+            .map(|(tile, _)| tile);
+        for (tile, index) in spotlight_tiles.zip(shown.iter()) {
+            assigned_tiles[*index] = Some(tile);
+            tile_taken[tile] = true;
+        }
+        // Keep the tiles of devices that are still shown, then fill the vacated ones.
+        for index in shown {
+            if assigned_tiles[*index].is_some() {
+                continue;
+            }
+            if let Some(tile) = remote_devices[*index].video_slot {
+                if tile < tiles.len() && !tiles[tile].3 && !tile_taken[tile] {
+                    assigned_tiles[*index] = Some(tile);
+                    tile_taken[tile] = true;
+                }
+            }
+        }
+        for index in shown {
+            if assigned_tiles[*index].is_none() {
+                if let Some(tile) = (0..tiles.len()).find(|tile| !tile_taken[*tile]) {
+                    assigned_tiles[*index] = Some(tile);
+                    tile_taken[tile] = true;
+                }
+            }
+        }
+
+        let mut active_speaker_height = 0;
+        let requests = ranked
+            .iter()
+            .map(|index| {
+                let device = &mut remote_devices[*index];
+                device.video_slot = assigned_tiles[*index];
+                let (width, height, framerate) = match device.video_slot {
+                    Some(tile) => {
+                        let (width, height, framerate, is_spotlight) = tiles[tile];
+                        // If the pinned or spotlighted device gets this tile, the SFU's
+                        // speaker shouldn't get it too.
+                        let featured =
+                            Some(device.demux_id) == self.pinned_demux_id || device.spotlighted;
+                        if is_spotlight && !featured && active_speaker_height == 0 {
+                            active_speaker_height = height;
+                        }
+                        (width, height, framerate)
+                    }
+                    None => (0, 0, None),
+                };
+                VideoRequest {
+                    demux_id: device.demux_id,
+                    width,
+                    height,
+                    framerate,
+                }
+            })
+            .collect();
+        (requests, active_speaker_height)
+    }
+}
+
+// Pauses the layers that no remote device needs: those taller than the shortest layer
+// that satisfies the height the SFU requested.  If the SFU hasn't said yet, all are sent.
+fn pause_unrequested_layers(
+    config: &VideoEncodingConfig,
+    full_height: u16,
+    requested_height: Option<u16>,
+) -> VideoEncodingConfig {
+    let mut config = config.clone();
+    let requested_height = match requested_height {
+        None => return config,
+        Some(requested_height) => requested_height,
+    };
+    let needed_height = if requested_height == 0 {
+        None
+    } else {
+        let heights = config.layers.iter().map(|layer| layer.height(full_height));
+        heights
+            .clone()
+            .filter(|&height| height >= requested_height)
+            .min()
+            .or_else(|| heights.max())
+    };
+    for layer in &mut config.layers {
+        if !matches!(needed_height, Some(needed_height) if layer.height(full_height) <= needed_height)
+        {
+            layer.active = false;
+        }
+    }
+    config
+}
+
 // This must stay in sync with the data PT in SfuClient.
 const RTP_DATA_PAYLOAD_TYPE: rtp::PayloadType = 101;
 // This must stay in sync with the data SSRC offset in SfuClient.
@@ -656,22 +1139,15 @@ const RTP_DATA_TO_SFU_SSRC: rtp::Ssrc = 1;
 // ramp all the way up, though.
 const ALL_ALONE_MAX_SEND_RATE: DataRate = DataRate::from_kbps(1);
 
-const SMALL_CALL_MAX_SEND_RATE: DataRate = DataRate::from_kbps(1000);
-
-// This is the smallest rate at which WebRTC seems to still send VGA.
-const LARGE_CALL_MAX_SEND_RATE: DataRate = DataRate::from_kbps(671);
-
-// Use a higher bitrate for screen sharing
-const SCREENSHARE_MIN_SEND_RATE: DataRate = DataRate::from_mbps(2);
-const SCREENSHARE_START_SEND_RATE: DataRate = DataRate::from_mbps(2);
-const SCREENSHARE_MAX_SEND_RATE: DataRate = DataRate::from_mbps(5);
-
 const AUDIO_ONLY_MAX_RECEIVE_RATE: DataRate = DataRate::from_kbps(1);
 
 const LOW_MAX_RECEIVE_RATE: DataRate = DataRate::from_kbps(500);
 
 const NORMAL_MAX_RECEIVE_RATE: DataRate = DataRate::from_mbps(20);
 
+// Enough for 1080p from several devices at once.
+const HIGH_MAX_RECEIVE_RATE: DataRate = DataRate::from_mbps(40);
+
 // The time between when a sender generates a new media send key
 // and applies it.  It needs to be big enough that there is
 // a high probability that receivers will receive the
@@ -795,6 +1271,16 @@ struct State {
     // These are unset until the app sets them.
     // But we err on the side of caution and don't send anything when they are unset.
     outgoing_heartbeat_state: HeartbeatState,
+    // Sent to other clients with heartbeats and media keys.
+    // Unset until the app sets it.  Once set, it's sent even when empty
+    // so that clearing the name or avatar reaches other devices.
+    local_profile: Option<Profile>,
+    // The device in the spotlight for everyone, which may be the local device.
+    spotlight_demux_id: Option<DemuxId>,
+    // Which change to the spotlight we last applied, or None if no one has set it.
+    // Once set, we repeat the spotlight with each heartbeat so it isn't lost and
+    // devices that join later learn about it, even after the device that set it leaves.
+    spotlight_version: Option<SpotlightVersion>,
 
     // Things for controlling the PeerConnection
     local_ice_ufrag: String,
@@ -814,6 +1300,13 @@ struct State {
     audio_levels_interval: Option<Duration>,
     // Things for getting audio levels from the PeerConnection
     next_audio_levels_time: Option<Instant>,
+    // Set by the app to turn remote devices up, down, or off locally.
+    remote_audio_settings: HashMap<DemuxId, RemoteAudioSettings>,
+    // Fed by the audio levels, so only used when audio_levels_interval is set.
+    speaker_detector: SpeakerDetector,
+    talking_while_muted_detector: TalkingWhileMutedDetector,
+    // Whose frame processor stats are logged with the other stats.
+    outgoing_video_source: Option<VideoSource>,
 
     next_membership_proof_request_time: Option<Instant>,
 
@@ -821,6 +1314,11 @@ struct State {
     // we change the keys from within the actor.
     frame_crypto_context: Arc<CallMutex<frame_crypto::Context>>,
 
+    // Shared with the PeerConnectionObserverImpl, which gives it the decoded video frames.
+    recorder: Arc<CallMutex<Option<Recorder>>>,
+    // Also shared with the PeerConnectionObserverImpl, which gives it the decoded audio.
+    incoming_audio_sink: Arc<CallMutex<Option<Box<dyn AudioSink>>>>,
+
     // If we receive a media key before we know about the remote device,
     // we store it here until we do know about the remote device.
     pending_media_receive_keys: Vec<(
@@ -829,6 +1327,8 @@ struct State {
         frame_crypto::RatchetCounter,
         frame_crypto::Secret,
     )>,
+    // Likewise for profiles, which are sent alongside the media keys.
+    pending_remote_profiles: Vec<(UserId, DemuxId, Profile)>,
     // If we generate a new media send key when a user leaves the call,
     // during the time between when we generate it and apply it, we need
     // to make sure that user that joined in that window gets that key
@@ -845,6 +1345,8 @@ struct State {
     // once per second, you get an "on demand" one.  Any more than that and you
     // wait for the next tick.
     video_requests: Option<Vec<VideoRequest>>,
+    // If set, video_requests and active_speaker_height are derived from it.
+    video_request_policy: Option<VideoRequestPolicy>,
     active_speaker_height: Option<u16>,
     on_demand_video_request_sent_since_last_heartbeat: bool,
     speaker_rtp_timestamp: Option<rtp::Timestamp>,
@@ -852,9 +1354,29 @@ struct State {
     send_rates: SendRates,
     // If set, will always overide the send_rates.  Intended for testing.
     send_rates_override: Option<SendRates>,
+    send_rate_policy: Box<dyn SendRatePolicy + Send>,
+    // Set by the app with a BandwidthMode or BandwidthPolicy.  None means unlimited.
+    max_send_rate: Option<DataRate>,
+    // Only set by a BandwidthPolicy; otherwise WebRTC's defaults are used.
+    audio_encoder_config: Option<AudioEncoderConfig>,
+    // What was last given to WebRTC.  None means its defaults.
+    applied_audio_encoder_config: Option<AudioEncoderConfig>,
+    // Protection added to the audio when the SFU reports loss.
+    audio_adaptation: AudioAdaptation,
+    device_conditions: DeviceConditions,
+    // What the SFU tells us the other devices want and what it thinks we can send.
+    sfu_requested_height: Option<u16>,
+    sfu_target_send_rate: Option<DataRate>,
+    // Set by the app, along with the height of the full resolution video.
+    // Layers taller than what the SFU requested are paused.
+    video_encoding_config: Option<(VideoEncodingConfig, u16)>,
+    applied_video_encoding_config: Option<VideoEncodingConfig>,
     max_receive_rate: Option<DataRate>,
-    // Demux IDs where video is being forward from, mapped to the server allocated height.
-    forwarding_videos: HashMap<DemuxId, u16>,
+    // No video is sent or received, regardless of the video requests.
+    audio_only: bool,
+    // Demux IDs where video is being forward from, mapped to the server allocated height
+    // and framerate.
+    forwarding_videos: HashMap<DemuxId, (u16, Option<u16>)>,
 
     /// A ring sent to the whole group when the call was created.
     ///
@@ -951,8 +1473,14 @@ impl Client {
                     Some(v) => v,
                 };
 
+                let recorder = Arc::new(CallMutex::new(None, "recorder"));
+                let incoming_audio_sink = Arc::new(CallMutex::new(None, "incoming_audio_sink"));
                 let (peer_connection_observer_impl, peer_connection_observer) =
-                    PeerConnectionObserverImpl::uninitialized(incoming_video_sink)?;
+                    PeerConnectionObserverImpl::uninitialized(
+                        incoming_video_sink,
+                        recorder.clone(),
+                        incoming_audio_sink.clone(),
+                    )?;
                 // WebRTC uses alphanumeric plus + and /, which is just barely a superset of this,
                 // but we can't uses dashes due to the sfu.
                 let local_ice_ufrag = random_alphanumeric(4);
@@ -995,6 +1523,9 @@ impl Client {
                     joined_members: HashSet::new(),
 
                     outgoing_heartbeat_state: Default::default(),
+                    local_profile: None,
+                    spotlight_demux_id: None,
+                    spotlight_version: None,
 
                     sfu_info: None,
                     peer_connection_observer_impl,
@@ -1009,22 +1540,41 @@ impl Client {
 
                     audio_levels_interval,
                     next_audio_levels_time: None,
+                    remote_audio_settings: HashMap::new(),
+                    speaker_detector: SpeakerDetector::default(),
+                    talking_while_muted_detector: TalkingWhileMutedDetector::default(),
+                    outgoing_video_source: None,
 
                     next_membership_proof_request_time: None,
 
                     frame_crypto_context,
+                    recorder,
+                    incoming_audio_sink,
                     pending_media_receive_keys: Vec::new(),
+                    pending_remote_profiles: Vec::new(),
                     media_send_key_rotation_state: KeyRotationState::Applied,
 
                     video_requests: None,
+                    video_request_policy: None,
                     active_speaker_height: None,
                     on_demand_video_request_sent_since_last_heartbeat: false,
                     speaker_rtp_timestamp: None,
 
                     send_rates: SendRates::default(),
                     send_rates_override: None,
+                    send_rate_policy: Box::new(DefaultSendRatePolicy),
+                    max_send_rate: None,
+                    audio_encoder_config: None,
+                    applied_audio_encoder_config: None,
+                    audio_adaptation: AudioAdaptation::new(),
+                    device_conditions: DeviceConditions::default(),
+                    sfu_requested_height: None,
+                    sfu_target_send_rate: None,
+                    video_encoding_config: None,
+                    applied_video_encoding_config: None,
                     // If the client never calls set_bandwidth_mode, use the normal max receive rate.
                     max_receive_rate: Some(NORMAL_MAX_RECEIVE_RATE),
+                    audio_only: false,
                     forwarding_videos: HashMap::default(),
 
                     cancellable_initial_ring: None,
@@ -1079,9 +1629,22 @@ impl Client {
 
         if let Some(next_stats_time) = state.next_stats_time {
             if now >= next_stats_time {
+                // Stats arrive asynchronously, so these are from the previous request.
+                if let Some(audio_sender_statistics) =
+                    state.stats_observer.take_audio_sender_statistics()
+                {
+                    if state.audio_adaptation.update(&audio_sender_statistics) {
+                        Self::apply_audio_encoder_config(state);
+                    }
+                }
                 let _ = state
                     .peer_connection
                     .get_stats(state.stats_observer.as_ref());
+                if let Some(source) = &state.outgoing_video_source {
+                    state
+                        .stats_observer
+                        .log_video_frame_processor_stats(&source.frame_processor_stats());
+                }
                 state.next_stats_time = Some(now + STATS_INTERVAL);
             }
         }
@@ -1091,6 +1654,8 @@ impl Client {
         {
             if now >= next_audio_levels_time {
                 let (captured_level, received_levels) = state.peer_connection.get_audio_levels();
+                Self::detect_speaking(state, now, &received_levels);
+                Self::detect_talking_while_muted(state, now, captured_level);
                 state.observer.handle_audio_levels(
                     state.client_id,
                     captured_level,
@@ -1111,6 +1676,43 @@ impl Client {
         state.actor.send_delayed(TICK_INTERVAL, Self::tick);
     }
 
+    fn detect_speaking(state: &mut State, now: Instant, received_levels: &[ReceivedAudioLevel]) {
+        let changed_demux_ids = state.speaker_detector.update(now, received_levels);
+        let recent_speakers = state.speaker_detector.recent_speakers();
+        let mut any_changed = false;
+        for device in state.remote_devices.iter_mut() {
+            if changed_demux_ids.contains(&device.demux_id) {
+                device.last_speaking_time = Some(SystemTime::now());
+                any_changed = true;
+            }
+            let speaking = state.speaker_detector.is_speaking(device.demux_id);
+            let recent_speaker_rank = recent_speakers
+                .iter()
+                .position(|demux_id| *demux_id == device.demux_id);
+            if device.speaking != speaking || device.recent_speaker_rank != recent_speaker_rank {
+                device.speaking = speaking;
+                device.recent_speaker_rank = recent_speaker_rank;
+                any_changed = true;
+            }
+        }
+        if any_changed {
+            state.observer.handle_remote_devices_changed(
+                state.client_id,
+                &state.remote_devices,
+                RemoteDevicesChangedReason::SpeakingChanged,
+            );
+        }
+    }
+
+    fn detect_talking_while_muted(state: &mut State, now: Instant, captured_level: AudioLevel) {
+        if state
+            .talking_while_muted_detector
+            .update(now, captured_level)
+        {
+            state.observer.handle_talking_while_muted(state.client_id);
+        }
+    }
+
     fn request_remote_devices_as_soon_as_possible(state: &mut State) {
         debug!(
             "group_call::Client::request_remote_devices_as_soon_as_possible(client_id: {})",
@@ -1387,6 +1989,7 @@ impl Client {
                 state.peer_connection.set_outgoing_media_enabled(false);
                 state.peer_connection.set_incoming_media_enabled(false);
                 Self::release_busy(state);
+                Self::stop_recording(state);
 
                 if let JoinState::Joined(local_demux_id) = state.join_state {
                     Self::send_leaving_through_sfu_and_over_signaling(state, local_demux_id);
@@ -1476,6 +2079,7 @@ impl Client {
             );
             // We don't modify the outgoing audio track.  We expect the app to handle that.
             state.outgoing_heartbeat_state.audio_muted = Some(muted);
+            state.talking_while_muted_detector.set_muted(muted);
             if let Err(err) = Self::send_heartbeat(state) {
                 warn!(
                     "Failed to send heartbeat after updating audio mute state: {:?}",
@@ -1485,334 +2089,983 @@ impl Client {
         });
     }
 
-    pub fn set_outgoing_video_muted(&self, muted: bool) {
+    pub fn set_talking_while_muted_config(&self, config: TalkingWhileMutedConfig) {
         debug!(
-            "group_call::Client(outer)::set_video_muted(client_id: {}, muted: {})",
-            self.client_id, muted
+            "group_call::Client(outer)::set_talking_while_muted_config(client_id: {}, config: {:?})",
+            self.client_id, config
         );
         self.actor.send(move |state| {
             debug!(
-                "group_call::Client(inner)::set_video_muted(client_id: {}, muted: {})",
-                state.client_id, muted
+                "group_call::Client(inner)::set_talking_while_muted_config(client_id: {})",
+                state.client_id
             );
-            // We don't modify the outgoing video track.  We expect the app to handle that.
-            state.outgoing_heartbeat_state.video_muted = Some(muted);
-            if let Err(err) = Self::send_heartbeat(state) {
-                warn!(
-                    "Failed to send heartbeat after updating video mute state: {:?}",
-                    err
-                );
-            }
+            state.talking_while_muted_detector.set_config(config);
         });
     }
 
-    pub fn set_presenting(&self, presenting: bool) {
+    pub fn set_outgoing_video_source(&self, source: Option<VideoSource>) {
         debug!(
-            "group_call::Client(outer)::set_presenting(client_id: {}, presenting: {})",
-            self.client_id, presenting
+            "group_call::Client(outer)::set_outgoing_video_source(client_id: {})",
+            self.client_id
         );
         self.actor.send(move |state| {
             debug!(
-                "group_call::Client(inner)::set_presenting(client_id: {}, presenting: {})",
-                state.client_id, presenting
+                "group_call::Client(inner)::set_outgoing_video_source(client_id: {})",
+                state.client_id
             );
-            state.outgoing_heartbeat_state.presenting = Some(presenting);
-            if let Err(err) = Self::send_heartbeat(state) {
-                warn!(
-                    "Failed to send heartbeat after updating presenting state: {:?}",
-                    err
-                );
-            }
+            state.outgoing_video_source = source;
         });
     }
 
-    pub fn set_sharing_screen(&self, sharing_screen: bool) {
+    // A volume of 0.0 is silent, 1.0 is unchanged, and up to
+    // MAX_REMOTE_AUDIO_VOLUME amplifies the remote device.
+    // Only affects what we hear; the remote device isn't told.
+    pub fn set_remote_audio_volume(&self, demux_id: DemuxId, volume: f64) {
         debug!(
-            "group_call::Client(outer)::set_sharing_screen(client_id: {}, sharing_screen: {})",
-            self.client_id, sharing_screen
+            "group_call::Client(outer)::set_remote_audio_volume(client_id: {}, demux_id: {}, volume: {})",
+            self.client_id, demux_id, volume
         );
         self.actor.send(move |state| {
             debug!(
-                "group_call::Client(inner)::set_sharing_screen(client_id: {}, sharing_screen: {})",
-                state.client_id, sharing_screen
+                "group_call::Client(inner)::set_remote_audio_volume(client_id: {}, demux_id: {}, volume: {})",
+                state.client_id, demux_id, volume
             );
-            state.outgoing_heartbeat_state.sharing_screen = Some(sharing_screen);
-            if let Err(err) = Self::send_heartbeat(state) {
-                warn!(
-                    "Failed to send heartbeat after updating sharing screen state: {:?}",
-                    err
-                );
-            }
-            let send_rates = Self::compute_send_rates(state.joined_members.len(), sharing_screen);
-            Self::set_send_rates_inner(state, send_rates);
+            Self::update_remote_audio_settings(state, demux_id, |settings| {
+                settings.volume = volume.clamp(0.0, MAX_REMOTE_AUDIO_VOLUME);
+            });
         });
     }
 
-    pub fn resend_media_keys(&self) {
+    // Silences the remote device without forgetting its volume.
+    // Only affects what we hear; the remote device isn't told.
+    pub fn set_remote_audio_muted(&self, demux_id: DemuxId, muted: bool) {
         debug!(
-            "group_call::Client(outer)::resend_media_keys(client_id: {})",
-            self.client_id
+            "group_call::Client(outer)::set_remote_audio_muted(client_id: {}, demux_id: {}, muted: {})",
+            self.client_id, demux_id, muted
         );
         self.actor.send(move |state| {
             debug!(
-                "group_call::Client(inner)::resend_media_keys(client_id: {})",
-                state.client_id
+                "group_call::Client(inner)::set_remote_audio_muted(client_id: {}, demux_id: {}, muted: {})",
+                state.client_id, demux_id, muted
             );
+            Self::update_remote_audio_settings(state, demux_id, |settings| {
+                settings.muted = muted;
+            });
+        });
+    }
 
-            if let JoinState::Joined(local_demux_id) = state.join_state {
-                let user_ids: HashSet<UserId> = state
-                    .remote_devices
-                    .iter()
-                    .map(|rd| rd.user_id.clone())
-                    .collect();
+    fn update_remote_audio_settings(
+        state: &mut State,
+        demux_id: DemuxId,
+        update: impl FnOnce(&mut RemoteAudioSettings),
+    ) {
+        if state.remote_devices.find_by_demux_id(demux_id).is_none() {
+            warn!(
+                "Ignoring remote audio settings for unknown demux_id {}",
+                demux_id
+            );
+            return;
+        }
+        let settings = state.remote_audio_settings.entry(demux_id).or_default();
+        update(settings);
+        let volume = settings.effective_volume();
+        if let Err(err) = state
+            .peer_connection
+            .set_remote_audio_volume(demux_id, volume)
+        {
+            warn!("Failed to set remote audio volume: {:?}", err);
+        }
+    }
 
-                let (ratchet_counter, secret) = {
-                    let frame_crypto_context = state
-                        .frame_crypto_context
-                        .lock()
-                        .expect("Get lock for frame encryption context to advance media send key");
-                    frame_crypto_context.send_state()
-                };
+    fn apply_remote_audio_settings(state: &State) {
+        for (demux_id, settings) in &state.remote_audio_settings {
+            if let Err(err) = state
+                .peer_connection
+                .set_remote_audio_volume(*demux_id, settings.effective_volume())
+            {
+                warn!("Failed to set remote audio volume: {:?}", err);
+            }
+        }
+    }
 
-                info!(
-                    "Resending media keys to everyone (number of users: {})",
-                    user_ids.len()
+    pub fn set_outgoing_video_muted(&self, muted: bool) {
+        debug!(
+            "group_call::Client(outer)::set_video_muted(client_id: {}, muted: {})",
+            self.client_id, muted
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_video_muted(client_id: {}, muted: {})",
+                state.client_id, muted
+            );
+            // We don't modify the outgoing video track.  We expect the app to handle that.
+            state.outgoing_heartbeat_state.video_muted = Some(muted);
+            if let Err(err) = Self::send_heartbeat(state) {
+                warn!(
+                    "Failed to send heartbeat after updating video mute state: {:?}",
+                    err
                 );
-                for user_id in user_ids {
-                    Self::send_media_send_key_to_user_over_signaling(
-                        state,
-                        user_id,
-                        local_demux_id,
-                        ratchet_counter,
-                        secret,
-                    );
-                }
             }
         });
     }
 
-    pub fn set_bandwidth_mode(&self, bandwidth_mode: BandwidthMode) {
+    // Recording starts when given a recorder and stops (finishing the recorder)
+    // when given None or when leaving.  Everyone else in the call is told
+    // through the heartbeat.
+    pub fn set_recorder(&self, recorder: Option<Recorder>) {
         debug!(
-            "group_call::Client(outer)::set_bandwidth_mode(client_id: {}, bandwidth_mode: {:?})",
-            self.client_id, bandwidth_mode
+            "group_call::Client(outer)::set_recorder(client_id: {}, recording: {})",
+            self.client_id,
+            recorder.is_some()
         );
         self.actor.send(move |state| {
             debug!(
-                "group_call::Client(inner)::set_bandwidth_mode(client_id: {}), bandwidth_mode: {:?}",
+                "group_call::Client(inner)::set_recorder(client_id: {}, recording: {})",
                 state.client_id,
-                bandwidth_mode,
+                recorder.is_some()
             );
+            let recording = recorder.is_some();
+            let previous_recorder = match state.recorder.lock() {
+                Ok(mut current_recorder) => std::mem::replace(&mut *current_recorder, recorder),
+                Err(err) => {
+                    warn!("Failed to set recorder: {:?}", err);
+                    return;
+                }
+            };
+            if let Some(previous_recorder) = previous_recorder {
+                if let Err(err) = previous_recorder.finish() {
+                    warn!("Failed to finish recording: {:?}", err);
+                }
+            }
+            Self::update_incoming_audio_frames_enabled(state);
+            Self::update_incoming_video_frame_content_enabled(state);
+            Self::set_recording_and_send_heartbeat(state, recording);
+        });
+    }
 
-            state.max_receive_rate = Some(match bandwidth_mode {
-                BandwidthMode::VeryLow => AUDIO_ONLY_MAX_RECEIVE_RATE,
-                BandwidthMode::Low => LOW_MAX_RECEIVE_RATE,
-                BandwidthMode::Normal => NORMAL_MAX_RECEIVE_RATE,
-            });
-            if !state.on_demand_video_request_sent_since_last_heartbeat {
-                Self::send_video_requests_to_sfu(state);
-                state.on_demand_video_request_sent_since_last_heartbeat = true;
+    // Decoded audio from each remote device goes to the sink (keyed by demux ID),
+    // as well as to the playout device, until it's replaced or cleared.
+    pub fn set_incoming_audio_sink(&self, incoming_audio_sink: Option<Box<dyn AudioSink>>) {
+        debug!(
+            "group_call::Client(outer)::set_incoming_audio_sink(client_id: {}, set: {})",
+            self.client_id,
+            incoming_audio_sink.is_some()
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_incoming_audio_sink(client_id: {}, set: {})",
+                state.client_id,
+                incoming_audio_sink.is_some()
+            );
+            match state.incoming_audio_sink.lock() {
+                Ok(mut current_sink) => *current_sink = incoming_audio_sink,
+                Err(err) => {
+                    warn!("Failed to set incoming audio sink: {:?}", err);
+                    return;
+                }
             }
+            Self::update_incoming_audio_frames_enabled(state);
         });
     }
 
-    fn set_send_rates_inner(state: &mut State, mut send_rates: SendRates) {
-        if let Some(send_rates_override) = &state.send_rates_override {
-            send_rates = send_rates_override.clone();
-        }
-        if state.send_rates != send_rates {
-            if send_rates.max == Some(ALL_ALONE_MAX_SEND_RATE) {
-                info!("Disable audio and outgoing media because there are no other devices.");
-                state.peer_connection.set_audio_recording_enabled(false);
-                state.peer_connection.set_audio_playout_enabled(false);
-                state.peer_connection.set_outgoing_media_enabled(false);
-            } else {
-                info!("Enable audio and outgoing media because there are other devices.");
-                state.peer_connection.set_audio_recording_enabled(true);
-                state.peer_connection.set_audio_playout_enabled(true);
-                state.peer_connection.set_outgoing_media_enabled(true);
+    // Both the recorder and the incoming audio sink need decoded audio.
+    fn update_incoming_audio_frames_enabled(state: &mut State) {
+        let has_recorder = matches!(state.recorder.lock(), Ok(recorder) if recorder.is_some());
+        let has_sink = matches!(state.incoming_audio_sink.lock(), Ok(sink) if sink.is_some());
+        state
+            .peer_connection
+            .set_incoming_audio_frames_enabled(has_recorder || has_sink);
+    }
+
+    // The recorder gets decoded video whether or not there's an incoming video sink.
+    fn update_incoming_video_frame_content_enabled(state: &mut State) {
+        let has_recorder = matches!(state.recorder.lock(), Ok(recorder) if recorder.is_some());
+        let has_sink = state
+            .peer_connection_observer_impl
+            .incoming_video_sink
+            .is_some();
+        state
+            .peer_connection
+            .set_incoming_video_frame_content_enabled(has_recorder || has_sink);
+    }
+
+    fn stop_recording(state: &mut State) {
+        let recorder = match state.recorder.lock() {
+            Ok(mut recorder) => recorder.take(),
+            Err(err) => {
+                warn!("Failed to stop recording: {:?}", err);
+                return;
             }
-            if let Err(e) = state.peer_connection.set_send_rates(send_rates.clone()) {
-                warn!("Could not set send rates to {:?}: {}", send_rates, e);
-            } else {
-                info!("Setting send rates to {:?}", send_rates);
-                state
-                    .observer
-                    .handle_send_rates_changed(state.client_id, send_rates);
+        };
+        if let Some(recorder) = recorder {
+            if let Err(err) = recorder.finish() {
+                warn!("Failed to finish recording: {:?}", err);
             }
+            Self::update_incoming_audio_frames_enabled(state);
+            Self::update_incoming_video_frame_content_enabled(state);
+            Self::set_recording_and_send_heartbeat(state, false);
         }
     }
 
-    pub fn request_video(&self, requests: Vec<VideoRequest>, active_speaker_height: u16) {
+    fn set_recording_and_send_heartbeat(state: &mut State, recording: bool) {
+        if state.outgoing_heartbeat_state.recording == Some(recording) {
+            return;
+        }
+        state.outgoing_heartbeat_state.recording = Some(recording);
+        if let Err(err) = Self::send_heartbeat(state) {
+            warn!(
+                "Failed to send heartbeat after updating recording state: {:?}",
+                err
+            );
+        }
+    }
+
+    pub fn set_presenting(&self, presenting: bool) {
         debug!(
-            "group_call::Client(outer)::request_video(client_id: {}, requests: {:?}, active_speaker_height: {})",
-            self.client_id, requests, active_speaker_height,
+            "group_call::Client(outer)::set_presenting(client_id: {}, presenting: {})",
+            self.client_id, presenting
         );
         self.actor.send(move |state| {
             debug!(
-                "group_call::Client(inner)::request_video(client_id: {})",
-                state.client_id
+                "group_call::Client(inner)::set_presenting(client_id: {}, presenting: {})",
+                state.client_id, presenting
             );
-            state.video_requests = Some(requests);
-            state.active_speaker_height = Some(active_speaker_height);
-            if !state.on_demand_video_request_sent_since_last_heartbeat {
-                Self::send_video_requests_to_sfu(state);
-                state.on_demand_video_request_sent_since_last_heartbeat = true;
+            state.outgoing_heartbeat_state.presenting = Some(presenting);
+            if let Err(err) = Self::send_heartbeat(state) {
+                warn!(
+                    "Failed to send heartbeat after updating presenting state: {:?}",
+                    err
+                );
             }
         });
     }
 
-    fn send_video_requests_to_sfu(state: &mut State) {
-        use protobuf::group_call::{
-            device_to_sfu::{
-                video_request_message::VideoRequest as VideoRequestProto, VideoRequestMessage,
-            },
-            DeviceToSfu,
-        };
-        use std::cmp::min;
-
-        if let Some(video_requests) = &state.video_requests {
-            let requests: Vec<_> = video_requests
-                .iter()
-                .filter_map(|request| {
-                    state
-                        .remote_devices
-                        .find_by_demux_id(request.demux_id)
-                        .map(|device| {
-                            VideoRequestProto {
-                                demux_id: Some(device.demux_id),
-                                // We use the min because the SFU does not understand the concept of video rotation
-                                // so all requests must be in terms of non-rotated video even though the apps
-                                // will request in terms of rotated video.  We assume that all video is sent over the
-                                // wire in landscape format with rotation metadata.
-                                // If it's not, we'll have a problem.
-                                height: Some(min(request.height, request.width) as u32),
-                            }
-                        })
-                })
-                .collect();
-            match encode_proto(DeviceToSfu {
-                video_request: Some(VideoRequestMessage {
-                    // TODO: Update the server to handle this as expected or remove this altogether.
-                    // The client needs the server to sort by resolution and then cap the number after that sort.
-                    // Currently, the server is sorting by audio activity and then capping the number.
-                    // Two possible fixes on the server:
-                    // A. Sort by resolution and then cap.
-                    //    After that, the client could re-add the lines below.
-                    // B. Treat the list of resolution requests as "complete" and don't use "lastN" at all.
-                    //    After that, the client could remove the lines below.
-                    // Note: the server can't handle a None value here, so we have to pass
-                    // in a value larger than a group call would ever be.
-                    // The only problem with this mechanism is that the server will send video for
-                    // new remote devices that the local device hasn't yet learned about.
-                    // max: Some(
-                    //     requests
-                    //         .iter()
-                    //         .filter(|request| request.height.unwrap() > 0)
-                    //         .count() as u32,
-                    // ),
-                    max_kbps: state.max_receive_rate.map(|rate| rate.as_kbps() as u32),
-                    requests,
-                    active_speaker_height: state.active_speaker_height.map(|height| height.into()),
-                }),
-                ..Default::default()
-            }) {
-                Err(e) => {
-                    warn!("Failed to encode video request: {:?}", e);
-                }
-                Ok(msg) => {
-                    if let Err(e) = Self::send_data_to_sfu(state, &msg) {
-                        warn!("Failed to send video request: {:?}", e);
-                    }
-                }
+    pub fn set_sharing_screen(&self, sharing_screen: bool) {
+        debug!(
+            "group_call::Client(outer)::set_sharing_screen(client_id: {}, sharing_screen: {})",
+            self.client_id, sharing_screen
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_sharing_screen(client_id: {}, sharing_screen: {})",
+                state.client_id, sharing_screen
+            );
+            state.outgoing_heartbeat_state.sharing_screen = Some(sharing_screen);
+            if let Err(err) = Self::send_heartbeat(state) {
+                warn!(
+                    "Failed to send heartbeat after updating sharing screen state: {:?}",
+                    err
+                );
             }
-        }
+            Self::update_send_rates(state);
+        });
     }
 
-    pub fn set_group_members(&self, group_members: Vec<GroupMember>) {
+    // None clears the display name.
+    pub fn set_local_display_name(&self, display_name: Option<String>) {
         debug!(
-            "group_call::Client(outer)::set_group_members(client_id: {})",
+            "group_call::Client(outer)::set_local_display_name(client_id: {})",
             self.client_id
         );
         self.actor.send(move |state| {
             debug!(
-                "group_call::Client(inner)::set_group_members(client_id: {})",
+                "group_call::Client(inner)::set_local_display_name(client_id: {})",
                 state.client_id
             );
-            let new_members: HashSet<UserId> =
-                group_members.iter().map(|i| i.user_id.clone()).collect();
-            if new_members != state.known_members {
-                info!("known group members changed");
-                state.known_members = new_members;
-                state.sfu_client.set_group_members(group_members);
-                Self::request_remote_devices_as_soon_as_possible(state);
-            }
-        })
+            let profile = Profile {
+                display_name: display_name.map(truncate_display_name),
+                ..state.local_profile.clone().unwrap_or_default()
+            };
+            Self::set_local_profile_inner(state, profile);
+        });
     }
 
-    pub fn set_membership_proof(&self, proof: MembershipProof) {
+    // The hash is sent to other devices so they can look up the avatar image.
+    pub fn set_local_avatar_hash(&self, avatar_hash: Option<Vec<u8>>) {
         debug!(
-            "group_call::Client(outer)::set_membership_proof(client_id: {})",
+            "group_call::Client(outer)::set_local_avatar_hash(client_id: {})",
             self.client_id
         );
         self.actor.send(move |state| {
             debug!(
-                "group_call::Client(inner)::set_membership_proof(client_id: {})",
+                "group_call::Client(inner)::set_local_avatar_hash(client_id: {})",
                 state.client_id
             );
-            state.sfu_client.set_membership_proof(proof);
-            if matches!(
-                state.remote_devices_request_state,
-                RemoteDevicesRequestState::WaitingForMembershipProof
-            ) {
-                state.remote_devices_request_state = RemoteDevicesRequestState::NeverRequested;
-                Self::request_remote_devices_as_soon_as_possible(state);
+            if matches!(&avatar_hash, Some(avatar_hash) if avatar_hash.len() > MAX_AVATAR_HASH_LEN)
+            {
+                warn!("Ignoring avatar hash that is too long");
+                return;
             }
-        })
+            let profile = Profile {
+                avatar_hash,
+                ..state.local_profile.clone().unwrap_or_default()
+            };
+            Self::set_local_profile_inner(state, profile);
+        });
     }
 
-    // Pulled into a named private method because it can be called in many places.
-    #[allow(clippy::collapsible_if)]
-    fn end(state: &mut State, reason: EndReason) {
+    // Puts a device in the spotlight (or takes it out with None) for everyone in the call.
+    // The device may be the local device.  RingRTC doesn't check who is allowed
+    // to do this; that's up to the app.
+    pub fn set_spotlight(&self, demux_id: Option<DemuxId>) {
         debug!(
-            "group_call::Client(inner)::end(client_id: {})",
-            state.client_id
+            "group_call::Client(outer)::set_spotlight(client_id: {}, demux_id: {:?})",
+            self.client_id, demux_id
         );
-
-        let joining_or_joined = match state.join_state {
-            JoinState::Joined(_) | JoinState::Joining => true,
-            JoinState::NotJoined(_) => false,
-        };
-        if joining_or_joined {
-            // This will send an update after changing the join state.
-            Self::leave_inner(state);
-        }
-        match state.connection_state {
-            ConnectionState::NotConnected => {
-                warn!("Can't disconnect when not connected.");
-            }
-            ConnectionState::Connecting
-            | ConnectionState::Connected
-            | ConnectionState::Reconnecting => {
-                state.peer_connection.close();
-                Self::set_connection_state_and_notify_observer(
-                    state,
-                    ConnectionState::NotConnected,
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_spotlight(client_id: {}, demux_id: {:?})",
+                state.client_id, demux_id
+            );
+            let set_by_demux_id = match state.join_state {
+                JoinState::Joined(local_demux_id) => local_demux_id,
+                _ => 0,
+            };
+            let version = SpotlightVersion {
+                sequence_number: state
+                    .spotlight_version
+                    .map_or(0, |version| version.sequence_number)
+                    .wrapping_add(1),
+                set_by_demux_id,
+            };
+            Self::set_spotlight_inner(state, demux_id, version);
+            if let Err(err) = Self::send_heartbeat(state) {
+                warn!(
+                    "Failed to send heartbeat after setting spotlight: {:?}",
+                    err
                 );
-                let _join_handles = state.actor.stopper().stop_all_without_joining();
-                state.observer.handle_ended(state.client_id, reason);
             }
-        }
+        });
     }
 
-    // This should be called by the SfuClient after it has joined.
-    pub fn on_sfu_client_joined(&self, joined: Result<Joined>) {
-        debug!(
-            "group_call::Client(outer)::on_sfu_client_joined(client_id: {})",
-            self.client_id
-        );
-        self.actor.send(move |state| {
-            debug!(
-                "group_call::Client(inner)::on_sfu_client_joined(client_id: {})",
+    fn set_spotlight_inner(
+        state: &mut State,
+        demux_id: Option<DemuxId>,
+        version: SpotlightVersion,
+    ) {
+        state.spotlight_version = Some(version);
+        if state.spotlight_demux_id == demux_id {
+            return;
+        }
+        info!(
+            "Spotlight changed to demux_id {:?} ({:?})",
+            demux_id, version
+        );
+        state.spotlight_demux_id = demux_id;
+        for remote_device in state.remote_devices.iter_mut() {
+            remote_device.spotlighted = Some(remote_device.demux_id) == demux_id;
+        }
+        state.observer.handle_remote_devices_changed(
+            state.client_id,
+            &state.remote_devices,
+            RemoteDevicesChangedReason::SpotlightChanged,
+        );
+        state
+            .observer
+            .handle_spotlight_changed(state.client_id, demux_id);
+        Self::apply_video_request_policy(state);
+    }
+
+    fn set_local_profile_inner(state: &mut State, profile: Profile) {
+        if state.local_profile.as_ref() != Some(&profile) {
+            state.local_profile = Some(profile);
+            if let Err(err) = Self::send_heartbeat(state) {
+                warn!("Failed to send heartbeat after updating profile: {:?}", err);
+            }
+        }
+    }
+
+    pub fn resend_media_keys(&self) {
+        debug!(
+            "group_call::Client(outer)::resend_media_keys(client_id: {})",
+            self.client_id
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::resend_media_keys(client_id: {})",
+                state.client_id
+            );
+
+            if let JoinState::Joined(local_demux_id) = state.join_state {
+                let user_ids: HashSet<UserId> = state
+                    .remote_devices
+                    .iter()
+                    .map(|rd| rd.user_id.clone())
+                    .collect();
+
+                let (ratchet_counter, secret) = {
+                    let frame_crypto_context = state
+                        .frame_crypto_context
+                        .lock()
+                        .expect("Get lock for frame encryption context to advance media send key");
+                    frame_crypto_context.send_state()
+                };
+
+                info!(
+                    "Resending media keys to everyone (number of users: {})",
+                    user_ids.len()
+                );
+                for user_id in user_ids {
+                    Self::send_media_send_key_to_user_over_signaling(
+                        state,
+                        user_id,
+                        local_demux_id,
+                        ratchet_counter,
+                        secret,
+                    );
+                }
+            }
+        });
+    }
+
+    pub fn set_bandwidth_mode(&self, bandwidth_mode: BandwidthMode) {
+        debug!(
+            "group_call::Client(outer)::set_bandwidth_mode(client_id: {}, bandwidth_mode: {:?})",
+            self.client_id, bandwidth_mode
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_bandwidth_mode(client_id: {}), bandwidth_mode: {:?}",
+                state.client_id,
+                bandwidth_mode,
+            );
+
+            state.max_receive_rate = Some(match bandwidth_mode {
+                BandwidthMode::VeryLow => AUDIO_ONLY_MAX_RECEIVE_RATE,
+                BandwidthMode::Low => LOW_MAX_RECEIVE_RATE,
+                BandwidthMode::Normal => NORMAL_MAX_RECEIVE_RATE,
+                BandwidthMode::High => HIGH_MAX_RECEIVE_RATE,
+            });
+            // The send rate policy decides how much to send unless the mode limits it.
+            state.max_send_rate = match bandwidth_mode {
+                BandwidthMode::VeryLow | BandwidthMode::Low => Some(bandwidth_mode.max_bitrate()),
+                BandwidthMode::Normal | BandwidthMode::High => None,
+            };
+            // Undo any audio configuration from an earlier BandwidthPolicy.
+            state.audio_encoder_config = None;
+            Self::apply_audio_encoder_config(state);
+            Self::apply_bandwidth_limits(state);
+        });
+    }
+
+    // Like set_bandwidth_mode, but with arbitrary limits and audio configuration.
+    pub fn set_bandwidth_policy(&self, bandwidth_policy: BandwidthPolicy) {
+        debug!(
+            "group_call::Client(outer)::set_bandwidth_policy(client_id: {}, bandwidth_policy: {:?})",
+            self.client_id, bandwidth_policy
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_bandwidth_policy(client_id: {}), bandwidth_policy: {:?}",
+                state.client_id,
+                bandwidth_policy,
+            );
+
+            state.max_receive_rate = Some(bandwidth_policy.max_receive_rate);
+            state.max_send_rate = Some(bandwidth_policy.max_send_rate);
+            state.audio_encoder_config = Some(bandwidth_policy.audio_encoder_config);
+            Self::apply_audio_encoder_config(state);
+            Self::apply_bandwidth_limits(state);
+        });
+    }
+
+    fn apply_audio_encoder_config(state: &mut State) {
+        if state.sfu_info.is_none() {
+            // We haven't started the PeerConnection yet; joining will apply it.
+            return;
+        }
+        let config = if state.audio_encoder_config.is_none()
+            && state.audio_adaptation.level() == AudioProtectionLevel::None
+        {
+            // WebRTC's defaults
+            None
+        } else {
+            Some(
+                state
+                    .audio_adaptation
+                    .adapt(&state.audio_encoder_config.clone().unwrap_or_default()),
+            )
+        };
+        if state.applied_audio_encoder_config == config {
+            return;
+        }
+        // Going back to the defaults has to be explicit because WebRTC keeps
+        // whatever it was given last.
+        state
+            .peer_connection
+            .configure_audio_encoders(&config.clone().unwrap_or_default());
+        state.applied_audio_encoder_config = config;
+    }
+
+    fn apply_bandwidth_limits(state: &mut State) {
+        Self::update_send_rates(state);
+        if state.video_request_policy.is_some() {
+            Self::apply_video_request_policy(state);
+        } else if !state.on_demand_video_request_sent_since_last_heartbeat {
+            Self::send_video_requests_to_sfu(state);
+            state.on_demand_video_request_sent_since_last_heartbeat = true;
+        }
+    }
+
+    // Unlike BandwidthMode::VeryLow, which only asks the SFU to send very little,
+    // this sends empty video requests, disables incoming video, stops the
+    // outgoing video encoder, and tells the other devices.
+    pub fn set_audio_only(&self, audio_only: bool) {
+        debug!(
+            "group_call::Client(outer)::set_audio_only(client_id: {}, audio_only: {})",
+            self.client_id, audio_only
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_audio_only(client_id: {}, audio_only: {})",
+                state.client_id, audio_only
+            );
+            if state.audio_only == audio_only {
+                return;
+            }
+            state.audio_only = audio_only;
+            state.outgoing_heartbeat_state.audio_only = Some(audio_only);
+            // Remove or recreate the remote video receivers.
+            if let (
+                Some(sfu_info),
+                JoinState::Joined(local_demux_id),
+                DheState::Negotiated { srtp_keys },
+            ) = (&state.sfu_info, &state.join_state, &state.dhe_state)
+            {
+                let remote_demux_ids: Vec<DemuxId> =
+                    state.remote_devices.demux_id_set().into_iter().collect();
+                let result = Self::set_peer_connection_descriptions(
+                    state,
+                    sfu_info,
+                    *local_demux_id,
+                    &remote_demux_ids,
+                    srtp_keys,
+                );
+                if result.is_err() {
+                    Self::end(state, EndReason::FailedToUpdatePeerConnection);
+                    return;
+                }
+                // Updating the remote description may have recreated the receivers.
+                Self::apply_remote_audio_settings(state);
+            }
+            Self::apply_audio_only(state);
+            if let Err(err) = Self::send_heartbeat(state) {
+                warn!(
+                    "Failed to send heartbeat after updating audio only state: {:?}",
+                    err
+                );
+            }
+            if state.video_request_policy.is_some() {
+                Self::apply_video_request_policy(state);
+            } else if !state.on_demand_video_request_sent_since_last_heartbeat {
+                Self::send_video_requests_to_sfu(state);
+                state.on_demand_video_request_sent_since_last_heartbeat = true;
+            }
+        });
+    }
+
+    fn apply_audio_only(state: &mut State) {
+        if state.sfu_info.is_none() {
+            // We haven't started the PeerConnection yet; joining will apply it.
+            return;
+        }
+        state
+            .peer_connection
+            .set_incoming_video_enabled(!state.audio_only);
+        if state.send_rates.max == Some(ALL_ALONE_MAX_SEND_RATE) {
+            // Outgoing media stays off until someone else joins.
+            return;
+        }
+        state
+            .peer_connection
+            .set_outgoing_video_enabled(!state.audio_only);
+        if !state.audio_only {
+            // That activates every encoding, including paused layers.
+            state.applied_video_encoding_config = None;
+            Self::apply_video_encoding_config(state);
+        }
+    }
+
+    // Replaces the DefaultSendRatePolicy.
+    pub fn set_send_rate_policy(&self, send_rate_policy: Box<dyn SendRatePolicy + Send>) {
+        debug!(
+            "group_call::Client(outer)::set_send_rate_policy(client_id: {})",
+            self.client_id
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_send_rate_policy(client_id: {})",
+                state.client_id
+            );
+            state.send_rate_policy = send_rate_policy;
+            Self::update_send_rates(state);
+        });
+    }
+
+    pub fn set_device_conditions(&self, device_conditions: DeviceConditions) {
+        debug!(
+            "group_call::Client(outer)::set_device_conditions(client_id: {}, device_conditions: {:?})",
+            self.client_id, device_conditions
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_device_conditions(client_id: {}, device_conditions: {:?})",
+                state.client_id, device_conditions
+            );
+            state.device_conditions = device_conditions;
+            Self::update_send_rates(state);
+        });
+    }
+
+    // Encodes outgoing video in the given layers (simulcast or SVC),
+    // or goes back to the default layers with None.  full_height is the height of the
+    // outgoing video at full resolution, in terms of non-rotated video.
+    // Fails if there are more than MAX_VIDEO_ENCODING_LAYERS layers.
+    pub fn set_video_encoding_config(
+        &self,
+        config: Option<VideoEncodingConfig>,
+        full_height: u16,
+    ) -> Result<()> {
+        debug!(
+            "group_call::Client(outer)::set_video_encoding_config(client_id: {}, config: {:?}, full_height: {})",
+            self.client_id, config, full_height
+        );
+        if let Some(config) = &config {
+            config.check_layer_count()?;
+        }
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_video_encoding_config(client_id: {}, config: {:?}, full_height: {})",
+                state.client_id, config, full_height
+            );
+            state.video_encoding_config = config.map(|config| (config, full_height));
+            Self::apply_video_encoding_config(state);
+        });
+        Ok(())
+    }
+
+    fn apply_video_encoding_config(state: &mut State) {
+        if state.sfu_info.is_none() {
+            // We haven't started the PeerConnection yet; joining will apply it.
+            return;
+        }
+        let config = state
+            .video_encoding_config
+            .as_ref()
+            .map(|(config, full_height)| {
+                pause_unrequested_layers(config, *full_height, state.sfu_requested_height)
+            });
+        if state.applied_video_encoding_config == config {
+            return;
+        }
+        if let Err(e) = state
+            .peer_connection
+            .set_video_encoding_layers(config.as_ref())
+        {
+            warn!("Could not set video encoding layers to {:?}: {}", config, e);
+        } else {
+            state.applied_video_encoding_config = config;
+        }
+    }
+
+    fn update_send_rates(state: &mut State) {
+        if state.sfu_info.is_none() {
+            // We haven't started the PeerConnection yet; joining will set the send rates.
+            return;
+        }
+        let send_rates = if state.remote_devices.is_empty() {
+            // This isn't up to the policy because it also disables outgoing media.
+            SendRates {
+                max: Some(ALL_ALONE_MAX_SEND_RATE),
+                ..SendRates::default()
+            }
+        } else {
+            state.send_rate_policy.compute_send_rates(&SendRateInputs {
+                joined_member_count: state.remote_devices.len(),
+                sharing_screen: state
+                    .outgoing_heartbeat_state
+                    .sharing_screen
+                    .unwrap_or(false),
+                requested_height: state.sfu_requested_height,
+                target_send_rate: state.sfu_target_send_rate,
+                max_send_rate: state.max_send_rate,
+                device_conditions: state.device_conditions,
+                current_send_rates: state.send_rates.clone(),
+            })
+        };
+        Self::set_send_rates_inner(state, send_rates);
+    }
+
+    fn set_send_rates_inner(state: &mut State, mut send_rates: SendRates) {
+        if let Some(send_rates_override) = &state.send_rates_override {
+            send_rates = send_rates_override.clone();
+        }
+        if state.send_rates != send_rates {
+            if send_rates.max == Some(ALL_ALONE_MAX_SEND_RATE) {
+                info!("Disable audio and outgoing media because there are no other devices.");
+                state.peer_connection.set_audio_recording_enabled(false);
+                state.peer_connection.set_audio_playout_enabled(false);
+                state.peer_connection.set_outgoing_media_enabled(false);
+            } else {
+                info!("Enable audio and outgoing media because there are other devices.");
+                state.peer_connection.set_audio_recording_enabled(true);
+                state.peer_connection.set_audio_playout_enabled(true);
+                state.peer_connection.set_outgoing_media_enabled(true);
+                // That activates every encoding, including paused layers.
+                state.applied_video_encoding_config = None;
+                Self::apply_video_encoding_config(state);
+                if state.audio_only {
+                    state.peer_connection.set_outgoing_video_enabled(false);
+                }
+            }
+            if let Err(e) = state.peer_connection.set_send_rates(send_rates.clone()) {
+                warn!("Could not set send rates to {:?}: {}", send_rates, e);
+            } else {
+                info!("Setting send rates to {:?}", send_rates);
+                state.send_rates = send_rates.clone();
+                state
+                    .observer
+                    .handle_send_rates_changed(state.client_id, send_rates);
+            }
+        }
+    }
+
+    pub fn request_video(&self, requests: Vec<VideoRequest>, active_speaker_height: u16) {
+        debug!(
+            "group_call::Client(outer)::request_video(client_id: {}, requests: {:?}, active_speaker_height: {})",
+            self.client_id, requests, active_speaker_height,
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::request_video(client_id: {})",
+                state.client_id
+            );
+            // Explicit requests replace any policy.
+            state.video_request_policy = None;
+            Self::apply_video_request_policy(state);
+            state.video_requests = Some(requests);
+            state.active_speaker_height = Some(active_speaker_height);
+            if !state.on_demand_video_request_sent_since_last_heartbeat {
+                Self::send_video_requests_to_sfu(state);
+                state.on_demand_video_request_sent_since_last_heartbeat = true;
+            }
+        });
+    }
+
+    // The video requests will follow the policy as devices come and go and
+    // speakers change, until request_video is called or the policy is cleared.
+    pub fn set_video_request_policy(&self, policy: Option<VideoRequestPolicy>) {
+        debug!(
+            "group_call::Client(outer)::set_video_request_policy(client_id: {}, policy: {:?})",
+            self.client_id, policy,
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_video_request_policy(client_id: {})",
+                state.client_id
+            );
+            state.video_request_policy = policy;
+            Self::apply_video_request_policy(state);
+        });
+    }
+
+    fn apply_video_request_policy(state: &mut State) {
+        let video_slots_before: Vec<Option<usize>> = state
+            .remote_devices
+            .iter()
+            .map(|device| device.video_slot)
+            .collect();
+        if let Some(policy) = &state.video_request_policy {
+            let (requests, active_speaker_height) =
+                policy.video_requests(&mut state.remote_devices);
+            state.video_requests = Some(requests);
+            state.active_speaker_height = Some(active_speaker_height);
+            if !state.on_demand_video_request_sent_since_last_heartbeat {
+                Self::send_video_requests_to_sfu(state);
+                state.on_demand_video_request_sent_since_last_heartbeat = true;
+            }
+        } else {
+            for device in state.remote_devices.iter_mut() {
+                device.video_slot = None;
+            }
+        }
+        if state
+            .remote_devices
+            .iter()
+            .map(|device| device.video_slot)
+            .ne(video_slots_before)
+        {
+            state.observer.handle_remote_devices_changed(
+                state.client_id,
+                &state.remote_devices,
+                RemoteDevicesChangedReason::VideoSlotsChanged,
+            );
+        }
+    }
+
+    fn send_video_requests_to_sfu(state: &mut State) {
+        use protobuf::group_call::{
+            device_to_sfu::{
+                video_request_message::VideoRequest as VideoRequestProto, VideoRequestMessage,
+            },
+            DeviceToSfu,
+        };
+        use std::cmp::min;
+
+        let (video_requests, active_speaker_height, max_receive_rate) = if state.audio_only {
+            (&[][..], Some(0), Some(AUDIO_ONLY_MAX_RECEIVE_RATE))
+        } else if let Some(video_requests) = &state.video_requests {
+            (
+                &video_requests[..],
+                state.active_speaker_height,
+                state.max_receive_rate,
+            )
+        } else {
+            return;
+        };
+
+        let requests: Vec<_> = video_requests
+            .iter()
+            .filter_map(|request| {
+                state
+                    .remote_devices
+                    .find_by_demux_id(request.demux_id)
+                    .map(|device| {
+                        VideoRequestProto {
+                            demux_id: Some(device.demux_id),
+                            // We use the min because the SFU does not understand the concept of video rotation
+                            // so all requests must be in terms of non-rotated video even though the apps
+                            // will request in terms of rotated video.  We assume that all video is sent over the
+                            // wire in landscape format with rotation metadata.
+                            // If it's not, we'll have a problem.
+                            height: Some(min(request.height, request.width) as u32),
+                            framerate: request.framerate.map(u32::from),
+                        }
+                    })
+            })
+            .collect();
+        match encode_proto(DeviceToSfu {
+            video_request: Some(VideoRequestMessage {
+                // TODO: Update the server to handle this as expected or remove this altogether.
+                // The client needs the server to sort by resolution and then cap the number after that sort.
+                // Currently, the server is sorting by audio activity and then capping the number.
+                // Two possible fixes on the server:
+                // A. Sort by resolution and then cap.
+                //    After that, the client could re-add the lines below.
+                // B. Treat the list of resolution requests as "complete" and don't use "lastN" at all.
+                //    After that, the client could remove the lines below.
+                // Note: the server can't handle a None value here, so we have to pass
+                // in a value larger than a group call would ever be.
+                // The only problem with this mechanism is that the server will send video for
+                // new remote devices that the local device hasn't yet learned about.
+                // max: Some(
+                //     requests
+                //         .iter()
+                //         .filter(|request| request.height.unwrap() > 0)
+                //         .count() as u32,
+                // ),
+                max_kbps: max_receive_rate.map(|rate| rate.as_kbps() as u32),
+                requests,
+                active_speaker_height: active_speaker_height.map(|height| height.into()),
+            }),
+            ..Default::default()
+        }) {
+            Err(e) => {
+                warn!("Failed to encode video request: {:?}", e);
+            }
+            Ok(msg) => {
+                if let Err(e) = Self::send_data_to_sfu(state, &msg) {
+                    warn!("Failed to send video request: {:?}", e);
+                }
+            }
+        }
+    }
+
+    pub fn set_group_members(&self, group_members: Vec<GroupMember>) {
+        debug!(
+            "group_call::Client(outer)::set_group_members(client_id: {})",
+            self.client_id
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_group_members(client_id: {})",
+                state.client_id
+            );
+            let new_members: HashSet<UserId> =
+                group_members.iter().map(|i| i.user_id.clone()).collect();
+            if new_members != state.known_members {
+                info!("known group members changed");
+                state.known_members = new_members;
+                state.sfu_client.set_group_members(group_members);
+                Self::request_remote_devices_as_soon_as_possible(state);
+            }
+        })
+    }
+
+    pub fn set_membership_proof(&self, proof: MembershipProof) {
+        debug!(
+            "group_call::Client(outer)::set_membership_proof(client_id: {})",
+            self.client_id
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::set_membership_proof(client_id: {})",
+                state.client_id
+            );
+            state.sfu_client.set_membership_proof(proof);
+            if matches!(
+                state.remote_devices_request_state,
+                RemoteDevicesRequestState::WaitingForMembershipProof
+            ) {
+                state.remote_devices_request_state = RemoteDevicesRequestState::NeverRequested;
+                Self::request_remote_devices_as_soon_as_possible(state);
+            }
+        })
+    }
+
+    // Pulled into a named private method because it can be called in many places.
+    #[allow(clippy::collapsible_if)]
+    fn end(state: &mut State, reason: EndReason) {
+        debug!(
+            "group_call::Client(inner)::end(client_id: {})",
+            state.client_id
+        );
+
+        let joining_or_joined = match state.join_state {
+            JoinState::Joined(_) | JoinState::Joining => true,
+            JoinState::NotJoined(_) => false,
+        };
+        if joining_or_joined {
+            // This will send an update after changing the join state.
+            Self::leave_inner(state);
+        }
+        match state.connection_state {
+            ConnectionState::NotConnected => {
+                warn!("Can't disconnect when not connected.");
+            }
+            ConnectionState::Connecting
+            | ConnectionState::Connected
+            | ConnectionState::Reconnecting => {
+                state.peer_connection.close();
+                Self::set_connection_state_and_notify_observer(
+                    state,
+                    ConnectionState::NotConnected,
+                );
+                let _join_handles = state.actor.stopper().stop_all_without_joining();
+                state.observer.handle_ended(state.client_id, reason);
+            }
+        }
+    }
+
+    // This should be called by the SfuClient after it has joined.
+    pub fn on_sfu_client_joined(&self, joined: Result<Joined>) {
+        debug!(
+            "group_call::Client(outer)::on_sfu_client_joined(client_id: {})",
+            self.client_id
+        );
+        self.actor.send(move |state| {
+            debug!(
+                "group_call::Client(inner)::on_sfu_client_joined(client_id: {})",
                 state.client_id
             );
 
@@ -1857,6 +3110,9 @@ impl Client {
                         );
 
                         state.sfu_info = Some(sfu_info);
+                        Self::apply_video_encoding_config(state);
+                        Self::apply_audio_encoder_config(state);
+                        Self::apply_audio_only(state);
                     }
                     ConnectionState::Connected | ConnectionState::Reconnecting => {
                         warn!("The SFU completed joining after already being connected.");
@@ -1930,12 +3186,21 @@ impl Client {
                             secret: Some(secret_vec),
                             ..
                         }),
+                    profile,
                     ..
                 } => {
                     if secret_vec.len() != size_of::<frame_crypto::Secret>() {
                         warn!("on_signaling_message_received(): ignoring media receive key with wrong length");
                         return;
                     }
+                    if let Some(profile) = profile {
+                        Self::set_remote_profile_or_store_for_later(
+                            state,
+                            sender_user_id.clone(),
+                            sender_demux_id,
+                            profile.into(),
+                        );
+                    }
                     if let Ok(ratchet_counter) = ratchet_counter.try_into() {
                         let mut secret = frame_crypto::Secret::default();
                         secret.copy_from_slice(&secret_vec);
@@ -2120,6 +3385,10 @@ impl Client {
                     }
                 })
                 .collect();
+            for remote_device in state.remote_devices.iter_mut() {
+                remote_device.spotlighted =
+                    Some(remote_device.demux_id) == state.spotlight_demux_id;
+            }
 
             // Recalculate to see the differences
             let new_demux_ids: HashSet<DemuxId> = state.remote_devices.demux_id_set();
@@ -2145,6 +3414,12 @@ impl Client {
                         return;
                     }
                 }
+                state
+                    .remote_audio_settings
+                    .retain(|demux_id, _| new_demux_ids.contains(demux_id));
+                // Updating the remote description may have recreated the receivers.
+                Self::apply_remote_audio_settings(state);
+                Self::apply_audio_only(state);
             }
 
             if demux_ids_changed {
@@ -2153,6 +3428,7 @@ impl Client {
                     &state.remote_devices,
                     RemoteDevicesChangedReason::DemuxIdsChanged,
                 );
+                Self::apply_video_request_policy(state);
             }
 
             if new_user_ids != old_user_ids {
@@ -2201,15 +3477,12 @@ impl Client {
                     secret,
                 );
             }
+            let pending_remote_profiles = std::mem::take(&mut state.pending_remote_profiles);
+            for (user_id, demux_id, profile) in pending_remote_profiles {
+                Self::set_remote_profile_or_store_for_later(state, user_id, demux_id, profile);
+            }
             if new_demux_ids.len() != old_demux_ids.len() {
-                let send_rates = Self::compute_send_rates(
-                    new_demux_ids.len(),
-                    state
-                        .outgoing_heartbeat_state
-                        .sharing_screen
-                        .unwrap_or(false),
-                );
-                Self::set_send_rates_inner(state, send_rates);
+                Self::update_send_rates(state);
             }
 
             // If anyone has joined besides us, we won't cancel the ring on leave.
@@ -2231,29 +3504,6 @@ impl Client {
         }
     }
 
-    // Returns (min, start, max)
-    fn compute_send_rates(joined_member_count: usize, sharing_screen: bool) -> SendRates {
-        match (joined_member_count, sharing_screen) {
-            (0, _) => SendRates {
-                max: Some(ALL_ALONE_MAX_SEND_RATE),
-                ..SendRates::default()
-            },
-            (_, true) => SendRates {
-                min: Some(SCREENSHARE_MIN_SEND_RATE),
-                start: Some(SCREENSHARE_START_SEND_RATE),
-                max: Some(SCREENSHARE_MAX_SEND_RATE),
-            },
-            (1..=7, _) => SendRates {
-                max: Some(SMALL_CALL_MAX_SEND_RATE),
-                ..SendRates::default()
-            },
-            _ => SendRates {
-                max: Some(LARGE_CALL_MAX_SEND_RATE),
-                ..SendRates::default()
-            },
-        }
-    }
-
     // Pulled into a named private method because it might be called by set_peek_result
     fn set_peer_connection_descriptions(
         state: &State,
@@ -2279,6 +3529,8 @@ impl Client {
             &sfu_info.ice_pwd,
             &srtp_keys.server,
             remote_demux_ids,
+            // Audio-only calls don't have video receivers (or decoders) at all.
+            !state.audio_only,
         )?;
         let observer = create_ssd_observer();
         state
@@ -2451,6 +3703,9 @@ impl Client {
         let message = protobuf::group_call::DeviceToDevice {
             group_id: Some(state.group_id.clone()),
             media_key: Some(media_key),
+            // Sent along with the media key so that new devices can label us
+            // as soon as they can decrypt our media.
+            profile: state.local_profile.as_ref().map(Profile::to_proto),
             ..Default::default()
         };
         let call_message = protobuf::signaling::CallMessage {
@@ -2465,6 +3720,53 @@ impl Client {
         );
     }
 
+    fn set_remote_profile_or_store_for_later(
+        state: &mut State,
+        user_id: UserId,
+        demux_id: DemuxId,
+        profile: Profile,
+    ) {
+        match state.remote_devices.find_by_demux_id(demux_id) {
+            Some(device) if device.user_id == user_id => {
+                Self::set_remote_profile(state, demux_id, profile);
+            }
+            Some(_) => {
+                warn!("Ignoring received profile from user because the demux ID {} doesn't make sense", demux_id);
+            }
+            None => {
+                // Only the latest profile from a device matters.
+                state
+                    .pending_remote_profiles
+                    .retain(|(_, pending_demux_id, _)| *pending_demux_id != demux_id);
+                if state.pending_remote_profiles.len() >= MAX_PENDING_REMOTE_PROFILES {
+                    warn!("Dropping the oldest pending remote profile because there are too many");
+                    state.pending_remote_profiles.remove(0);
+                }
+                state
+                    .pending_remote_profiles
+                    .push((user_id, demux_id, profile));
+            }
+        }
+    }
+
+    fn set_remote_profile(state: &mut State, demux_id: DemuxId, profile: Profile) {
+        if let Some(remote_device) = state.remote_devices.find_by_demux_id_mut(demux_id) {
+            if remote_device.profile != profile {
+                remote_device.profile = profile;
+                state.observer.handle_remote_devices_changed(
+                    state.client_id,
+                    &state.remote_devices,
+                    RemoteDevicesChangedReason::ProfileChanged(demux_id),
+                );
+            }
+        } else {
+            warn!(
+                "Ignoring received profile for unknown demux_id {}",
+                demux_id
+            );
+        }
+    }
+
     fn send_pending_media_send_key_to_users_with_added_devices(
         state: &mut State,
         users_with_added_devices: &[UserId],
@@ -2677,8 +3979,20 @@ impl Client {
                         video_muted: state.outgoing_heartbeat_state.video_muted,
                         presenting: state.outgoing_heartbeat_state.presenting,
                         sharing_screen: state.outgoing_heartbeat_state.sharing_screen,
+                        audio_only: state.outgoing_heartbeat_state.audio_only,
+                        recording: state.outgoing_heartbeat_state.recording,
                     })
                 },
+                // Sent with every heartbeat as well in case a media key
+                // (and the profile alongside it) was dropped.
+                profile: state.local_profile.as_ref().map(Profile::to_proto),
+                spotlight: state.spotlight_version.map(|version| {
+                    protobuf::group_call::device_to_device::Spotlight {
+                        demux_id: state.spotlight_demux_id,
+                        sequence_number: Some(version.sequence_number),
+                        set_by_demux_id: Some(version.set_by_demux_id),
+                    }
+                }),
                 ..Default::default()
             }
         })?;
@@ -2827,21 +4141,26 @@ impl Client {
 
     fn handle_rtp_received(&self, header: rtp::Header, payload: &[u8]) {
         use protobuf::group_call::{
-            sfu_to_device::{CurrentDevices, DeviceJoinedOrLeft, Speaker},
+            sfu_to_device::{CurrentDevices, DeviceJoinedOrLeft, Speaker, VideoRequest},
             DeviceToDevice, SfuToDevice,
         };
 
         if header.pt == RTP_DATA_PAYLOAD_TYPE {
             if header.ssrc == RTP_DATA_TO_SFU_SSRC {
-                // TODO: Use video_request to throttle down how much we send when it's not needed.
                 if let Ok(SfuToDevice {
                     speaker,
                     device_joined_or_left,
                     current_devices,
                     stats,
-                    video_request: _,
+                    video_request,
                 }) = SfuToDevice::decode(payload)
                 {
+                    if let Some(VideoRequest {
+                        height: Some(height),
+                    }) = video_request
+                    {
+                        self.handle_requested_height_received(height as u16);
+                    }
                     if let Some(Speaker {
                         demux_id: speaker_demux_id,
                     }) = speaker
@@ -2860,11 +4179,13 @@ impl Client {
                         demux_ids_with_video,
                         all_demux_ids: _,
                         allocated_heights,
+                        allocated_framerates,
                     }) = current_devices
                     {
                         self.handle_forwarding_video_received(
                             demux_ids_with_video,
                             allocated_heights,
+                            allocated_framerates,
                         );
                     }
                     if let Some(stats) = stats {
@@ -2874,6 +4195,11 @@ impl Client {
                             stats.ideal_send_rate_kbps.unwrap_or(0),
                             stats.allocated_send_rate_kbps.unwrap_or(0)
                         );
+                        if let Some(target_send_rate_kbps) = stats.target_send_rate_kbps {
+                            self.handle_target_send_rate_received(DataRate::from_kbps(
+                                target_send_rate_kbps as u64,
+                            ));
+                        }
                     }
                 }
                 debug!("Received RTP data from SFU: {:?}.", payload);
@@ -2884,6 +4210,12 @@ impl Client {
                         if let Some(heartbeat) = msg.heartbeat {
                             self.handle_heartbeat_received(demux_id, header.timestamp, heartbeat);
                         }
+                        if let Some(profile) = msg.profile {
+                            self.handle_profile_received(demux_id, profile);
+                        }
+                        if let Some(spotlight) = msg.spotlight {
+                            self.handle_spotlight_received(demux_id, spotlight);
+                        }
                         if let Some(_leaving) = msg.leaving {
                             self.actor.send(move |state| {
                                 Self::handle_leaving_received(state, demux_id);
@@ -2957,6 +4289,7 @@ impl Client {
                     &state.remote_devices,
                     RemoteDevicesChangedReason::SpeakerTimeChanged(demux_id),
                 );
+                Self::apply_video_request_policy(state);
             } else {
                 debug!(
                     "Ignoring speaker change because it isn't a known remote devices: {}",
@@ -2974,16 +4307,47 @@ impl Client {
         })
     }
 
+    fn handle_requested_height_received(&self, height: u16) {
+        self.actor.send(move |state| {
+            if state.sfu_requested_height != Some(height) {
+                debug!("SFU requested height changed to {}", height);
+                state.sfu_requested_height = Some(height);
+                Self::update_send_rates(state);
+                Self::apply_video_encoding_config(state);
+            }
+        });
+    }
+
+    fn handle_target_send_rate_received(&self, target_send_rate: DataRate) {
+        self.actor.send(move |state| {
+            if state.sfu_target_send_rate != Some(target_send_rate) {
+                state.sfu_target_send_rate = Some(target_send_rate);
+                // The policy decides what matters, and set_send_rates_inner ignores
+                // send rates that didn't change.
+                Self::update_send_rates(state);
+            }
+        });
+    }
+
     fn handle_forwarding_video_received(
         &self,
         mut demux_ids_with_video: Vec<DemuxId>,
         allocated_heights: Vec<u32>,
+        allocated_framerates: Vec<u32>,
     ) {
         self.actor.send(move |state| {
-            let forwarding_videos: HashMap<DemuxId, u16> = demux_ids_with_video
+            // Older SFUs don't send framerates, and 0 means unrestrained.
+            let forwarding_videos: HashMap<DemuxId, (u16, Option<u16>)> = demux_ids_with_video
                 .iter()
                 .zip(allocated_heights.iter())
-                .map(|(&demux_id, &height)| (demux_id, height as u16))
+                .enumerate()
+                .map(|(i, (&demux_id, &height))| {
+                    let framerate = allocated_framerates
+                        .get(i)
+                        .filter(|&&framerate| framerate > 0)
+                        .map(|&framerate| framerate as u16);
+                    (demux_id, (height as u16, framerate))
+                })
                 .collect();
             if state.forwarding_videos != forwarding_videos {
                 demux_ids_with_video.sort_unstable();
@@ -2992,10 +4356,13 @@ impl Client {
                     demux_ids_with_video
                 );
                 for remote_device in state.remote_devices.iter_mut() {
-                    let server_allocated_height = forwarding_videos.get(&remote_device.demux_id);
-                    let is_forwarding = server_allocated_height.is_some();
+                    let allocated = forwarding_videos.get(&remote_device.demux_id);
+                    let is_forwarding = allocated.is_some();
                     remote_device.forwarding_video = Some(is_forwarding);
-                    remote_device.server_allocated_height = server_allocated_height.copied().unwrap_or(0);
+                    let (server_allocated_height, server_allocated_framerate) =
+                        allocated.copied().unwrap_or((0, None));
+                    remote_device.server_allocated_height = server_allocated_height;
+                    remote_device.server_allocated_framerate = server_allocated_framerate;
 
                     if !is_forwarding {
                         remote_device.client_decoded_height = None;
@@ -3050,6 +4417,43 @@ impl Client {
         });
     }
 
+    fn handle_profile_received(
+        &self,
+        demux_id: DemuxId,
+        profile: protobuf::group_call::device_to_device::Profile,
+    ) {
+        self.actor.send(move |state| {
+            // Decrypting it with the key of the demux ID is enough to know who sent it.
+            Self::set_remote_profile(state, demux_id, profile.into());
+        });
+    }
+
+    fn handle_spotlight_received(
+        &self,
+        demux_id: DemuxId,
+        spotlight: protobuf::group_call::device_to_device::Spotlight,
+    ) {
+        self.actor.send(move |state| {
+            if state.remote_devices.find_by_demux_id(demux_id).is_none() {
+                warn!(
+                    "Ignoring received spotlight from unknown demux_id {}",
+                    demux_id
+                );
+                return;
+            }
+            // Devices that don't send a version only send their own changes.
+            let version = SpotlightVersion {
+                sequence_number: spotlight.sequence_number.unwrap_or(0),
+                set_by_demux_id: spotlight.set_by_demux_id.unwrap_or(demux_id),
+            };
+            if Some(version) <= state.spotlight_version {
+                // We already have this change or a newer one.
+                return;
+            }
+            Self::set_spotlight_inner(state, spotlight.demux_id, version);
+        });
+    }
+
     fn handle_leaving_received(state: &mut State, demux_id: DemuxId) {
         // It's likely we haven't received an update from the SFU about this demux_id leaving.
         debug!(
@@ -3101,17 +4505,24 @@ fn encode_proto(msg: impl prost::Message) -> Result<BytesMut> {
 struct PeerConnectionObserverImpl {
     client: Option<Client>,
     incoming_video_sink: Option<Box<dyn VideoSink>>,
+    recorder: Arc<CallMutex<Option<Recorder>>>,
+    incoming_audio_sink: Arc<CallMutex<Option<Box<dyn AudioSink>>>>,
     last_height_by_track_id: HashMap<u32, u32>,
 }
 
 impl PeerConnectionObserverImpl {
     fn uninitialized(
         incoming_video_sink: Option<Box<dyn VideoSink>>,
+        recorder: Arc<CallMutex<Option<Recorder>>>,
+        incoming_audio_sink: Arc<CallMutex<Option<Box<dyn AudioSink>>>>,
     ) -> Result<(Box<Self>, PeerConnectionObserver<Self>)> {
+        // Until a recorder is set, the frames are only needed by the incoming video sink.
         let enable_video_frame_content = incoming_video_sink.is_some();
         let boxed_observer_impl = Box::new(Self {
             client: None,
             incoming_video_sink,
+            recorder,
+            incoming_audio_sink,
             last_height_by_track_id: HashMap::new(),
         });
         let observer = PeerConnectionObserver::new(
@@ -3257,6 +4668,22 @@ impl PeerConnectionObserverTrait for PeerConnectionObserverImpl {
         Ok(())
     }
 
+    // The track ID is the demux ID of the remote device.
+    fn handle_incoming_audio_frame(&mut self, track_id: u32, frame: AudioFrame) -> Result<()> {
+        if let Some(recorder) = self.recorder.lock()?.as_ref() {
+            recorder.record_audio_samples(
+                track_id,
+                frame.sample_rate,
+                frame.channels,
+                frame.samples,
+            );
+        }
+        if let Some(incoming_audio_sink) = self.incoming_audio_sink.lock()?.as_ref() {
+            incoming_audio_sink.on_audio_frame(track_id, frame);
+        }
+        Ok(())
+    }
+
     fn handle_incoming_video_frame(
         &mut self,
         track_id: u32,
@@ -3264,6 +4691,10 @@ impl PeerConnectionObserverTrait for PeerConnectionObserverImpl {
         video_frame: Option<VideoFrame>,
     ) -> Result<()> {
         let height = video_frame_metadata.height;
+        if let (Some(recorder), Some(video_frame)) = (self.recorder.lock()?.as_ref(), &video_frame)
+        {
+            recorder.record_video_frame(track_id, video_frame);
+        }
         if let (Some(incoming_video_sink), Some(video_frame)) =
             (self.incoming_video_sink.as_ref(), video_frame)
         {
@@ -3295,6 +4726,7 @@ impl PeerConnectionObserverTrait for PeerConnectionObserverImpl {
                                     &state.remote_devices,
                                     RemoteDevicesChangedReason::HigherResolutionPendingChanged,
                                 );
+                                Client::apply_video_request_policy(state);
                             }
                         }
                     }
@@ -3587,12 +5019,14 @@ mod tests {
         remote_devices_at_join_time: Arc<CallMutex<Vec<RemoteDeviceState>>>,
         peek_state: Arc<CallMutex<FakeObserverPeekState>>,
         send_rates: Arc<CallMutex<Option<SendRates>>>,
+        spotlight_demux_id: Arc<CallMutex<Option<DemuxId>>>,
         ended: Waitable<EndReason>,
         era_id: Option<String>,
 
         request_membership_proof_invocation_count: Arc<AtomicU64>,
         handle_remote_devices_changed_invocation_count: Arc<AtomicU64>,
         handle_audio_levels_invocation_count: Arc<AtomicU64>,
+        handle_talking_while_muted_invocation_count: Arc<AtomicU64>,
     }
 
     impl FakeObserver {
@@ -3622,11 +5056,13 @@ mod tests {
                     "FakeObserver peek state",
                 )),
                 send_rates: Arc::new(CallMutex::new(None, "FakeObserver send rates")),
+                spotlight_demux_id: Arc::new(CallMutex::new(None, "FakeObserver spotlight")),
                 ended: Waitable::default(),
                 era_id: None,
                 request_membership_proof_invocation_count: Default::default(),
                 handle_remote_devices_changed_invocation_count: Default::default(),
                 handle_audio_levels_invocation_count: Default::default(),
+                handle_talking_while_muted_invocation_count: Default::default(),
             }
         }
 
@@ -3685,6 +5121,13 @@ mod tests {
             send_rates.clone()
         }
 
+        fn spotlight_demux_id(&self) -> Option<DemuxId> {
+            *self
+                .spotlight_demux_id
+                .lock()
+                .expect("Lock spotlight to read it")
+        }
+
         /// Gets the number of `request_membership_proof` since last checked.
         fn request_membership_proof_invocation_count(&self) -> u64 {
             self.request_membership_proof_invocation_count
@@ -3702,6 +5145,11 @@ mod tests {
             self.handle_audio_levels_invocation_count
                 .swap(0, Ordering::Relaxed)
         }
+
+        fn handle_talking_while_muted_invocation_count(&self) -> u64 {
+            self.handle_talking_while_muted_invocation_count
+                .swap(0, Ordering::Relaxed)
+        }
     }
 
     impl Observer for FakeObserver {
@@ -3762,6 +5210,22 @@ mod tests {
                 .fetch_add(1, Ordering::Relaxed);
         }
 
+        fn handle_talking_while_muted(&self, _client_id: ClientId) {
+            self.handle_talking_while_muted_invocation_count
+                .fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn handle_spotlight_changed(
+            &self,
+            _client_id: ClientId,
+            spotlight_demux_id: Option<DemuxId>,
+        ) {
+            *self
+                .spotlight_demux_id
+                .lock()
+                .expect("Lock spotlight to set it") = spotlight_demux_id;
+        }
+
         fn handle_peek_changed(
             &self,
             _client_id: ClientId,
@@ -4045,376 +5509,948 @@ mod tests {
             .init();
     }
 
-    fn set_group_and_wait_until_applied(clients: &[&TestClient]) {
-        for client in clients {
-            // We're going to be lazy and not remove ourselves.  It shouldn't matter.
-            client.set_remotes_and_wait_until_applied(clients);
-        }
-        for client in clients {
-            client.wait_for_client_to_process();
-        }
+    fn set_group_and_wait_until_applied(clients: &[&TestClient]) {
+        for client in clients {
+            // We're going to be lazy and not remove ourselves.  It shouldn't matter.
+            client.set_remotes_and_wait_until_applied(clients);
+        }
+        for client in clients {
+            client.wait_for_client_to_process();
+        }
+    }
+
+    #[test]
+    fn frame_encryption_normal() {
+        let mut client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
+
+        let mut client2 = TestClient::new(vec![2], 2, None);
+        client2.connect_join_and_wait_until_joined();
+
+        client2.set_remotes_and_wait_until_applied(&[&client1]);
+
+        // At this point, client2 knows about client1, so can receive encrypted media.
+        // But client1 does not know about client1, so has not yet shared its encryption key
+        // with it, so client2 cannot decrypt media from client1.
+        // And while client2 has shared the key with client1, client1 has not yet learned
+        // about client2 so can't decrypt either.
+
+        let is_audio = true;
+        let plaintext = &b"Fake Audio"[..];
+        let ciphertext1 = client1.encrypt_media(is_audio, plaintext).unwrap();
+        let ciphertext2 = client2.encrypt_media(is_audio, plaintext).unwrap();
+
+        // Check that the first byte for audio is left unencrypted
+        // and the rest has changed
+        assert_eq!(plaintext[0], ciphertext1[0]);
+        assert_ne!(plaintext, &ciphertext1[..plaintext.len()]);
+
+        assert!(client1
+            .decrypt_media(client2.demux_id, is_audio, &ciphertext2)
+            .is_err());
+        assert!(client2
+            .decrypt_media(client1.demux_id, is_audio, &ciphertext1)
+            .is_err());
+
+        client1.set_remotes_and_wait_until_applied(&[&client2]);
+        // We wait until client2 has processed the key from client1
+        client2.wait_for_client_to_process();
+
+        // At this point, both clients know about each other and have shared keys
+        // and should be able to decrypt.
+
+        // Because client1 just learned about client2, it advanced its key
+        // and so we need to re-encrypt with that key.
+        let mut ciphertext1 = client1.encrypt_media(is_audio, plaintext).unwrap();
+
+        assert_eq!(
+            plaintext,
+            client2
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext1)
+                .unwrap()
+        );
+        assert_eq!(
+            plaintext,
+            client1
+                .decrypt_media(client2.demux_id, is_audio, &ciphertext2)
+                .unwrap()
+        );
+
+        // But if the footer is too small, decryption should fail
+        assert!(client1
+            .decrypt_media(client2.demux_id, is_audio, b"small")
+            .is_err());
+
+        // And if the unencrypted media header has been modified, it should fail (bad mac)
+        ciphertext1[0] = ciphertext1[0].wrapping_add(1);
+        assert!(client2
+            .decrypt_media(client1.demux_id, is_audio, &ciphertext1)
+            .is_err());
+
+        // Finally, let's make sure video works as well
+
+        let is_audio = false;
+        let plaintext = &b"Fake Video Needs To Be Bigger"[..];
+        let ciphertext1 = client1.encrypt_media(is_audio, plaintext).unwrap();
+
+        // Check that the first 10 bytes of video is left unencrypted
+        // and the rest has changed
+        assert_eq!(plaintext[..10], ciphertext1[..10]);
+        assert_ne!(plaintext, &ciphertext1[..plaintext.len()]);
+
+        assert_eq!(
+            plaintext,
+            client2
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext1)
+                .unwrap()
+        );
+
+        client1.disconnect_and_wait_until_ended();
+        client2.disconnect_and_wait_until_ended();
+    }
+
+    #[test]
+    #[ignore] // Because it's too slow
+    fn frame_encryption_rotation_is_delayed() {
+        let mut client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
+
+        let mut client2 = TestClient::new(vec![2], 2, None);
+        client2.connect_join_and_wait_until_joined();
+
+        let mut client3 = TestClient::new(vec![3], 3, None);
+        client3.connect_join_and_wait_until_joined();
+
+        let mut client4 = TestClient::new(vec![4], 4, None);
+        client4.connect_join_and_wait_until_joined();
+
+        let mut client5 = TestClient::new(vec![5], 5, None);
+        client5.connect_join_and_wait_until_joined();
+
+        set_group_and_wait_until_applied(&[&client1, &client2, &client3]);
+
+        // client2 and client3 can decrypt client1
+        // client4 can't yet
+        let is_audio = true;
+        let plaintext = &b"Fake Audio"[..];
+        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
+        assert_eq!(
+            plaintext,
+            client2
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+        assert_eq!(
+            plaintext,
+            client3
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+        assert!(client4
+            .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+            .is_err());
+
+        // Add client4 and remove client3
+        set_group_and_wait_until_applied(&[&client1, &client2, &client4]);
+
+        // client2 and client4 can decrypt client1
+        // client3 can as well, at least for a little while
+        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
+        assert_eq!(
+            plaintext,
+            client2
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+        assert_eq!(
+            plaintext,
+            client3
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+        assert_eq!(
+            plaintext,
+            client4
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+
+        // TODO: Make Actors use tokio so we can use fake time
+        std::thread::sleep(std::time::Duration::from_millis(2000));
+
+        // client5 joins during the period between when the new key is generated
+        // and when it is applied.  client 5 should receive this key and decrypt
+        // both before and after the key is applied.
+        // meanwhile, client2 leaves, which will cause another rotation after this
+        // one.
+        set_group_and_wait_until_applied(&[&client1, &client4, &client5]);
+
+        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
+        assert_eq!(
+            plaintext,
+            client2
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+        assert_eq!(
+            plaintext,
+            client3
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+        assert_eq!(
+            plaintext,
+            client4
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+        assert_eq!(
+            plaintext,
+            client5
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+
+        std::thread::sleep(std::time::Duration::from_millis(2000));
+
+        // client4 and client5 can still decrypt from client1
+        // but client3 no longer can
+        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
+        assert_eq!(
+            plaintext,
+            client2
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+        assert!(client3
+            .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+            .is_err());
+        assert_eq!(
+            plaintext,
+            client4
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+        assert_eq!(
+            plaintext,
+            client5
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+
+        std::thread::sleep(std::time::Duration::from_millis(3000));
+
+        // After the next key rotation is applied, now client2 cannot decrypt,
+        // but client4 and client5 can.
+        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
+        assert!(client2
+            .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+            .is_err());
+        assert!(client3
+            .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+            .is_err());
+        assert_eq!(
+            plaintext,
+            client4
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+        assert_eq!(
+            plaintext,
+            client5
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .unwrap()
+        );
+
+        client1.disconnect_and_wait_until_ended();
+        client2.disconnect_and_wait_until_ended();
+        client3.disconnect_and_wait_until_ended();
+        client4.disconnect_and_wait_until_ended();
+        client5.disconnect_and_wait_until_ended();
     }
 
     #[test]
-    fn frame_encryption_normal() {
+    fn frame_encryption_resend_keys() {
         let mut client1 = TestClient::new(vec![1], 1, None);
         client1.connect_join_and_wait_until_joined();
 
         let mut client2 = TestClient::new(vec![2], 2, None);
         client2.connect_join_and_wait_until_joined();
 
-        client2.set_remotes_and_wait_until_applied(&[&client1]);
-
-        // At this point, client2 knows about client1, so can receive encrypted media.
-        // But client1 does not know about client1, so has not yet shared its encryption key
-        // with it, so client2 cannot decrypt media from client1.
-        // And while client2 has shared the key with client1, client1 has not yet learned
-        // about client2 so can't decrypt either.
-
-        let is_audio = true;
-        let plaintext = &b"Fake Audio"[..];
-        let ciphertext1 = client1.encrypt_media(is_audio, plaintext).unwrap();
-        let ciphertext2 = client2.encrypt_media(is_audio, plaintext).unwrap();
+        // Prevent client1 from sharing keys with client2
+        client1.observer.set_outgoing_signaling_blocked(true);
+        set_group_and_wait_until_applied(&[&client1, &client2]);
 
-        // Check that the first byte for audio is left unencrypted
-        // and the rest has changed
-        assert_eq!(plaintext[0], ciphertext1[0]);
-        assert_ne!(plaintext, &ciphertext1[..plaintext.len()]);
+        let remote_devices = client2.observer.remote_devices();
+        assert_eq!(1, remote_devices.len());
+        assert!(!remote_devices[0].media_keys_received);
 
-        assert!(client1
-            .decrypt_media(client2.demux_id, is_audio, &ciphertext2)
-            .is_err());
+        let is_audio = false;
+        let plaintext = &b"Fake Video is big"[..];
+        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
+        // We can't decrypt because the keys got dropped
         assert!(client2
-            .decrypt_media(client1.demux_id, is_audio, &ciphertext1)
+            .decrypt_media(client1.demux_id, is_audio, &ciphertext)
             .is_err());
 
-        client1.set_remotes_and_wait_until_applied(&[&client2]);
-        // We wait until client2 has processed the key from client1
+        client1.observer.set_outgoing_signaling_blocked(false);
+        client1.client.resend_media_keys();
+        client1.wait_for_client_to_process();
         client2.wait_for_client_to_process();
 
-        // At this point, both clients know about each other and have shared keys
-        // and should be able to decrypt.
-
-        // Because client1 just learned about client2, it advanced its key
-        // and so we need to re-encrypt with that key.
-        let mut ciphertext1 = client1.encrypt_media(is_audio, plaintext).unwrap();
+        let remote_devices = client2.observer.remote_devices();
+        assert_eq!(1, remote_devices.len());
+        assert!(remote_devices[0].media_keys_received);
 
         assert_eq!(
             plaintext,
             client2
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext1)
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
                 .unwrap()
         );
+    }
+
+    #[test]
+    fn frame_encryption_send_advanced_key_to_same_user() {
+        let mut client1a = TestClient::new(vec![1], 11, None);
+        let mut client2a = TestClient::new(vec![2], 21, None);
+        let mut client2b = TestClient::new(vec![2], 22, None);
+
+        client1a.connect_join_and_wait_until_joined();
+        client2a.connect_join_and_wait_until_joined();
+        set_group_and_wait_until_applied(&[&client1a, &client2a]);
+
+        let is_audio = true;
+        let plaintext = &b"Fake Audio"[..];
+        let ciphertext1a = client1a.encrypt_media(is_audio, plaintext).unwrap();
         assert_eq!(
             plaintext,
-            client1
-                .decrypt_media(client2.demux_id, is_audio, &ciphertext2)
+            client2a
+                .decrypt_media(client1a.demux_id, is_audio, &ciphertext1a)
                 .unwrap()
         );
 
-        // But if the footer is too small, decryption should fail
-        assert!(client1
-            .decrypt_media(client2.demux_id, is_audio, b"small")
-            .is_err());
-
-        // And if the unencrypted media header has been modified, it should fail (bad mac)
-        ciphertext1[0] = ciphertext1[0].wrapping_add(1);
-        assert!(client2
-            .decrypt_media(client1.demux_id, is_audio, &ciphertext1)
-            .is_err());
-
-        // Finally, let's make sure video works as well
-
-        let is_audio = false;
-        let plaintext = &b"Fake Video Needs To Be Bigger"[..];
-        let ciphertext1 = client1.encrypt_media(is_audio, plaintext).unwrap();
-
-        // Check that the first 10 bytes of video is left unencrypted
-        // and the rest has changed
-        assert_eq!(plaintext[..10], ciphertext1[..10]);
-        assert_ne!(plaintext, &ciphertext1[..plaintext.len()]);
-
+        // Make sure the advanced key gets sent to client2b even though it's the same user as 2a.
+        client2b.connect_join_and_wait_until_joined();
+        set_group_and_wait_until_applied(&[&client1a, &client2a, &client2b]);
+        let ciphertext1a = client1a.encrypt_media(is_audio, plaintext).unwrap();
         assert_eq!(
             plaintext,
-            client2
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext1)
+            client2b
+                .decrypt_media(client1a.demux_id, is_audio, &ciphertext1a)
                 .unwrap()
         );
-
-        client1.disconnect_and_wait_until_ended();
-        client2.disconnect_and_wait_until_ended();
     }
 
     #[test]
-    #[ignore] // Because it's too slow
-    fn frame_encryption_rotation_is_delayed() {
+    fn frame_encryption_someone_forging_demux_id() {
         let mut client1 = TestClient::new(vec![1], 1, None);
         client1.connect_join_and_wait_until_joined();
 
         let mut client2 = TestClient::new(vec![2], 2, None);
         client2.connect_join_and_wait_until_joined();
 
-        let mut client3 = TestClient::new(vec![3], 3, None);
+        // Client3 is pretending to have demux ID 1 when sending media keys
+        let mut client3 = TestClient::new(vec![3], 3, Some(1));
         client3.connect_join_and_wait_until_joined();
 
-        let mut client4 = TestClient::new(vec![4], 4, None);
-        client4.connect_join_and_wait_until_joined();
-
-        let mut client5 = TestClient::new(vec![5], 5, None);
-        client5.connect_join_and_wait_until_joined();
-
         set_group_and_wait_until_applied(&[&client1, &client2, &client3]);
 
-        // client2 and client3 can decrypt client1
-        // client4 can't yet
         let is_audio = true;
         let plaintext = &b"Fake Audio"[..];
-        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
+        let ciphertext1 = client1.encrypt_media(is_audio, plaintext).unwrap();
+        let ciphertext3 = client3.encrypt_media(is_audio, plaintext).unwrap();
+        // The forger doesn't mess anything up for the others
         assert_eq!(
             plaintext,
             client2
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-                .unwrap()
-        );
-        assert_eq!(
-            plaintext,
-            client3
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+                .decrypt_media(client1.demux_id, is_audio, &ciphertext1)
                 .unwrap()
         );
-        assert!(client4
-            .decrypt_media(client1.demux_id, is_audio, &ciphertext)
+        // And you can't decrypt from the forger.
+        assert!(client2
+            .decrypt_media(client3.demux_id, is_audio, &ciphertext3)
             .is_err());
 
-        // Add client4 and remove client3
-        set_group_and_wait_until_applied(&[&client1, &client2, &client4]);
+        client1.disconnect_and_wait_until_ended();
+        client2.disconnect_and_wait_until_ended();
+        client3.disconnect_and_wait_until_ended();
+    }
 
-        // client2 and client4 can decrypt client1
-        // client3 can as well, at least for a little while
-        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
-        assert_eq!(
-            plaintext,
-            client2
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-                .unwrap()
-        );
-        assert_eq!(
-            plaintext,
-            client3
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-                .unwrap()
-        );
+    #[test]
+    fn remote_heartbeat_state() {
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
+
+        let client2 = TestClient::new(vec![2], 2, None);
+        client2.connect_join_and_wait_until_joined();
+
+        set_group_and_wait_until_applied(&[&client1, &client2]);
+
+        let remote_devices2 = client2.observer.remote_devices();
+        assert_eq!(1, remote_devices2.len());
+        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.audio_muted);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.video_muted);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.presenting);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
+
+        client1.client.set_outgoing_audio_muted(true);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+
+        let remote_devices2 = client2.observer.remote_devices();
+        assert_eq!(1, remote_devices2.len());
+        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
+        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.video_muted);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.presenting);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
+
+        client1.client.set_outgoing_video_muted(false);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+
+        let remote_devices2 = client2.observer.remote_devices();
+        assert_eq!(1, remote_devices2.len());
+        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
+        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
+        assert_eq!(Some(false), remote_devices2[0].heartbeat_state.video_muted);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.presenting);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
+
+        client1.client.set_presenting(true);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+
+        let remote_devices2 = client2.observer.remote_devices();
+        assert_eq!(1, remote_devices2.len());
+        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
+        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
+        assert_eq!(Some(false), remote_devices2[0].heartbeat_state.video_muted);
+        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.presenting);
+        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
+
+        client1.client.set_sharing_screen(true);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+
+        let remote_devices2 = client2.observer.remote_devices();
+        assert_eq!(1, remote_devices2.len());
+        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
+        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
+        assert_eq!(Some(false), remote_devices2[0].heartbeat_state.video_muted);
+        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.presenting);
         assert_eq!(
-            plaintext,
-            client4
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-                .unwrap()
+            Some(true),
+            remote_devices2[0].heartbeat_state.sharing_screen
         );
+    }
 
-        // TODO: Make Actors use tokio so we can use fake time
-        std::thread::sleep(std::time::Duration::from_millis(2000));
+    #[test]
+    fn audio_only() {
+        use protobuf::group_call::DeviceToSfu;
+
+        let mut client1 = TestClient::new(vec![1], 1, None);
+        let client2 = TestClient::new(vec![2], 2, None);
+
+        let (sender, receiver) = mpsc::channel();
+        client1.sfu_rtp_packet_sender = Some(sender);
+        client1.connect_join_and_wait_until_joined();
+        client2.connect_join_and_wait_until_joined();
+        set_group_and_wait_until_applied(&[&client1, &client2]);
 
-        // client5 joins during the period between when the new key is generated
-        // and when it is applied.  client 5 should receive this key and decrypt
-        // both before and after the key is applied.
-        // meanwhile, client2 leaves, which will cause another rotation after this
-        // one.
-        set_group_and_wait_until_applied(&[&client1, &client4, &client5]);
+        let video_enabled = |client: &TestClient| {
+            let (sender, receiver) = mpsc::channel();
+            client.client.actor.send(move |state| {
+                sender
+                    .send((
+                        state
+                            .peer_connection
+                            .incoming_video_receivers_enabled()
+                            .iter()
+                            .any(|enabled| *enabled),
+                        state.peer_connection.outgoing_video_enabled(),
+                    ))
+                    .unwrap();
+            });
+            receiver.recv().unwrap()
+        };
+        let next_video_request = || loop {
+            let (_header, payload) = receiver
+                .recv_timeout(Duration::from_secs(2))
+                .expect("Get RTP packet to SFU");
+            let device_to_sfu = DeviceToSfu::decode(&payload[..]).unwrap();
+            if let Some(video_request) = device_to_sfu.video_request {
+                break video_request;
+            }
+        };
 
-        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
-        assert_eq!(
-            plaintext,
-            client2
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-                .unwrap()
+        client1.client.request_video(
+            vec![VideoRequest {
+                demux_id: 2,
+                width: 1280,
+                height: 720,
+                framerate: None,
+            }],
+            480,
         );
+        client1.wait_for_client_to_process();
+        assert_eq!((true, true), video_enabled(&client1));
+
+        let remote_video_demux_ids = |client: &TestClient| {
+            let (sender, receiver) = mpsc::channel();
+            client.client.actor.send(move |state| {
+                let mut demux_ids = state.peer_connection.remote_video_demux_ids();
+                demux_ids.sort_unstable();
+                sender.send(demux_ids).unwrap();
+            });
+            receiver.recv().unwrap()
+        };
+        assert_eq!(vec![2], remote_video_demux_ids(&client1));
+
+        client1.client.set_audio_only(true);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+        assert_eq!((false, false), video_enabled(&client1));
+        // The video receivers are gone, not just disabled.
+        assert_eq!(Vec::<DemuxId>::new(), remote_video_demux_ids(&client1));
         assert_eq!(
-            plaintext,
-            client3
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-                .unwrap()
+            Some(true),
+            client2.observer.remote_devices()[0]
+                .heartbeat_state
+                .audio_only
         );
+        let video_request = loop {
+            let video_request = next_video_request();
+            if video_request.requests.is_empty() {
+                break video_request;
+            }
+        };
+        assert_eq!(Some(0), video_request.active_speaker_height);
+        assert_eq!(Some(1), video_request.max_kbps);
+
+        // Joining someone else (which re-enables outgoing media and adds a
+        // video receiver) doesn't resume video.
+        let client3 = TestClient::new(vec![3], 3, None);
+        client1.set_remotes_and_wait_until_applied(&[&client2, &client3]);
+        assert_eq!((false, false), video_enabled(&client1));
+        assert_eq!(Vec::<DemuxId>::new(), remote_video_demux_ids(&client1));
+
+        client1.client.set_audio_only(false);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+        assert_eq!((true, true), video_enabled(&client1));
+        assert_eq!(vec![2, 3], remote_video_demux_ids(&client1));
         assert_eq!(
-            plaintext,
-            client4
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-                .unwrap()
+            Some(false),
+            client2.observer.remote_devices()[0]
+                .heartbeat_state
+                .audio_only
         );
+        let video_request = loop {
+            let video_request = next_video_request();
+            if !video_request.requests.is_empty() {
+                break video_request;
+            }
+        };
+        assert_eq!(Some(720), video_request.requests[0].height);
+        assert_eq!(Some(480), video_request.active_speaker_height);
+
+        client1.disconnect_and_wait_until_ended();
+        client2.disconnect_and_wait_until_ended();
+    }
+
+    #[test]
+    fn recording() {
+        use crate::core::recorder::{RawMuxer, RecordingLayout};
+
+        let client1 = TestClient::new(vec![1], 1, None);
+        let client2 = TestClient::new(vec![2], 2, None);
+        client1.connect_join_and_wait_until_joined();
+        client2.connect_join_and_wait_until_joined();
+        set_group_and_wait_until_applied(&[&client1, &client2]);
         assert_eq!(
-            plaintext,
-            client5
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-                .unwrap()
+            None,
+            client2.observer.remote_devices()[0]
+                .heartbeat_state
+                .recording
         );
 
-        std::thread::sleep(std::time::Duration::from_millis(2000));
+        let directory =
+            std::env::temp_dir().join(format!("ringrtc-group-call-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let recorder = Recorder::new(
+            Box::new(RawMuxer::new(&directory)),
+            RecordingLayout::PerParticipant,
+        );
+        recorder.record_audio_samples(2, 48000, 1, &[1, 2, 3]);
 
-        // client4 and client5 can still decrypt from client1
-        // but client3 no longer can
-        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
+        let video_frame_content_enabled = |client: &TestClient| {
+            let (sender, receiver) = mpsc::channel();
+            client.client.actor.send(move |state| {
+                sender
+                    .send(state.peer_connection.incoming_video_frame_content_enabled())
+                    .unwrap();
+            });
+            receiver.recv().unwrap()
+        };
+        // There's no incoming video sink, but the recorder still needs the frames.
+        assert!(!video_frame_content_enabled(&client1));
+        client1.client.set_recorder(Some(recorder.clone()));
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+        assert!(video_frame_content_enabled(&client1));
         assert_eq!(
-            plaintext,
-            client2
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-                .unwrap()
+            Some(true),
+            client2.observer.remote_devices()[0]
+                .heartbeat_state
+                .recording
         );
-        assert!(client3
-            .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-            .is_err());
+        assert!(!directory.join("index.json").exists());
+
+        client1.client.set_recorder(None);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+        assert!(!video_frame_content_enabled(&client1));
         assert_eq!(
-            plaintext,
-            client4
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-                .unwrap()
+            Some(false),
+            client2.observer.remote_devices()[0]
+                .heartbeat_state
+                .recording
         );
+        assert!(directory.join("index.json").exists());
         assert_eq!(
-            plaintext,
-            client5
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-                .unwrap()
+            vec![1, 0, 2, 0, 3, 0],
+            std::fs::read(directory.join("audio-2.pcm")).unwrap()
         );
 
-        std::thread::sleep(std::time::Duration::from_millis(3000));
-
-        // After the next key rotation is applied, now client2 cannot decrypt,
-        // but client4 and client5 can.
-        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
-        assert!(client2
-            .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-            .is_err());
-        assert!(client3
-            .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-            .is_err());
+        // Leaving stops recording too.
+        client1.client.set_recorder(Some(Recorder::new(
+            Box::new(RawMuxer::new(&directory)),
+            RecordingLayout::Mixed,
+        )));
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
         assert_eq!(
-            plaintext,
-            client4
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-                .unwrap()
+            Some(true),
+            client2.observer.remote_devices()[0]
+                .heartbeat_state
+                .recording
         );
+        client1.client.leave();
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
         assert_eq!(
-            plaintext,
-            client5
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-                .unwrap()
+            Some(false),
+            client2.observer.remote_devices()[0]
+                .heartbeat_state
+                .recording
         );
+        std::fs::remove_dir_all(&directory).unwrap();
 
         client1.disconnect_and_wait_until_ended();
         client2.disconnect_and_wait_until_ended();
-        client3.disconnect_and_wait_until_ended();
-        client4.disconnect_and_wait_until_ended();
-        client5.disconnect_and_wait_until_ended();
     }
 
-    #[test]
-    fn frame_encryption_resend_keys() {
-        let mut client1 = TestClient::new(vec![1], 1, None);
-        client1.connect_join_and_wait_until_joined();
+    #[derive(Clone, Default)]
+    struct FakeAudioSink {
+        frames: Arc<Mutex<Vec<(u32, u32, u16, Vec<i16>)>>>,
+    }
+
+    impl AudioSink for FakeAudioSink {
+        fn on_audio_frame(&self, track_id: u32, frame: AudioFrame) {
+            self.frames.lock().unwrap().push((
+                track_id,
+                frame.sample_rate,
+                frame.channels,
+                frame.samples.to_vec(),
+            ));
+        }
 
-        let mut client2 = TestClient::new(vec![2], 2, None);
-        client2.connect_join_and_wait_until_joined();
+        fn box_clone(&self) -> Box<dyn AudioSink> {
+            Box::new(self.clone())
+        }
+    }
 
-        // Prevent client1 from sharing keys with client2
-        client1.observer.set_outgoing_signaling_blocked(true);
-        set_group_and_wait_until_applied(&[&client1, &client2]);
+    #[test]
+    fn incoming_audio_sink() {
+        use crate::core::recorder::{Muxer, RecordingLayout};
 
-        let remote_devices = client2.observer.remote_devices();
-        assert_eq!(1, remote_devices.len());
-        assert!(!remote_devices[0].media_keys_received);
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
 
-        let is_audio = false;
-        let plaintext = &b"Fake Video is big"[..];
-        let ciphertext = client1.encrypt_media(is_audio, plaintext).unwrap();
-        // We can't decrypt because the keys got dropped
-        assert!(client2
-            .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-            .is_err());
+        let audio_frames_enabled = |client: &TestClient| {
+            let (sender, receiver) = mpsc::channel();
+            client.client.actor.send(move |state| {
+                sender
+                    .send(state.peer_connection.incoming_audio_frames_enabled())
+                    .unwrap();
+            });
+            receiver.recv().unwrap()
+        };
+        let receive_audio_frame = |client: &TestClient, demux_id: DemuxId, samples: &[i16]| {
+            let samples = samples.to_vec();
+            client.client.actor.send(move |state| {
+                state
+                    .peer_connection_observer_impl
+                    .handle_incoming_audio_frame(
+                        demux_id,
+                        AudioFrame {
+                            sample_rate: 48000,
+                            channels: 1,
+                            samples: &samples,
+                        },
+                    )
+                    .unwrap();
+            });
+            client.wait_for_client_to_process();
+        };
+        assert!(!audio_frames_enabled(&client1));
 
-        client1.observer.set_outgoing_signaling_blocked(false);
-        client1.client.resend_media_keys();
+        let sink = FakeAudioSink::default();
+        client1
+            .client
+            .set_incoming_audio_sink(Some(sink.box_clone()));
         client1.wait_for_client_to_process();
-        client2.wait_for_client_to_process();
-
-        let remote_devices = client2.observer.remote_devices();
-        assert_eq!(1, remote_devices.len());
-        assert!(remote_devices[0].media_keys_received);
+        assert!(audio_frames_enabled(&client1));
 
+        receive_audio_frame(&client1, 2, &[1, 2]);
+        receive_audio_frame(&client1, 3, &[3]);
         assert_eq!(
-            plaintext,
-            client2
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext)
-                .unwrap()
+            vec![(2, 48000, 1, vec![1, 2]), (3, 48000, 1, vec![3])],
+            *sink.frames.lock().unwrap()
         );
+
+        client1.client.set_incoming_audio_sink(None);
+        client1.wait_for_client_to_process();
+        assert!(!audio_frames_enabled(&client1));
+        receive_audio_frame(&client1, 2, &[4]);
+        assert_eq!(2, sink.frames.lock().unwrap().len());
+
+        // A recorder needs the audio too.
+        struct NullMuxer;
+        impl Muxer for NullMuxer {
+            fn write_video_frame(
+                &mut self,
+                _stream: &str,
+                _timestamp: Duration,
+                _width: u32,
+                _height: u32,
+                _rotation: crate::webrtc::media::VideoRotation,
+                _i420: &[u8],
+            ) -> std::io::Result<()> {
+                Ok(())
+            }
+            fn write_audio_samples(
+                &mut self,
+                _stream: &str,
+                _timestamp: Duration,
+                _sample_rate: u32,
+                _channels: u16,
+                _samples: &[i16],
+            ) -> std::io::Result<()> {
+                Ok(())
+            }
+            fn finish(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        client1.client.set_recorder(Some(Recorder::new(
+            Box::new(NullMuxer),
+            RecordingLayout::PerParticipant,
+        )));
+        client1.wait_for_client_to_process();
+        assert!(audio_frames_enabled(&client1));
+        client1.client.set_recorder(None);
+        client1.wait_for_client_to_process();
+        assert!(!audio_frames_enabled(&client1));
+
+        client1.disconnect_and_wait_until_ended();
     }
 
     #[test]
-    fn frame_encryption_send_advanced_key_to_same_user() {
-        let mut client1a = TestClient::new(vec![1], 11, None);
-        let mut client2a = TestClient::new(vec![2], 21, None);
-        let mut client2b = TestClient::new(vec![2], 22, None);
+    fn speaking_detected_from_audio_levels() {
+        let client1 = TestClient::new(vec![1], 1, None);
+        let client2 = TestClient::new(vec![2], 2, None);
+        let client3 = TestClient::new(vec![3], 3, None);
+        client1.connect_join_and_wait_until_joined();
+        client1.set_remotes_and_wait_until_applied(&[&client2, &client3]);
 
-        client1a.connect_join_and_wait_until_joined();
-        client2a.connect_join_and_wait_until_joined();
-        set_group_and_wait_until_applied(&[&client1a, &client2a]);
+        // Feed the levels with our own timestamps rather than from the tick.
+        client1.client.actor.send(|state| {
+            state.audio_levels_interval = None;
+        });
+        let start = Instant::now();
+        let detect = |millis: u64, levels: &[(DemuxId, AudioLevel)]| {
+            let received_levels: Vec<ReceivedAudioLevel> = levels
+                .iter()
+                .map(|(demux_id, level)| ReceivedAudioLevel {
+                    demux_id: *demux_id,
+                    level: *level,
+                })
+                .collect();
+            client1.client.actor.send(move |state| {
+                Client::detect_speaking(
+                    state,
+                    start + Duration::from_millis(millis),
+                    &received_levels,
+                );
+            });
+            client1.wait_for_client_to_process();
+        };
+        let speakers = || {
+            let mut speakers: Vec<(DemuxId, bool, Option<usize>)> = client1
+                .observer
+                .remote_devices()
+                .iter()
+                .map(|device| (device.demux_id, device.speaking, device.recent_speaker_rank))
+                .collect();
+            speakers.sort_unstable();
+            speakers
+        };
 
-        let is_audio = true;
-        let plaintext = &b"Fake Audio"[..];
-        let ciphertext1a = client1a.encrypt_media(is_audio, plaintext).unwrap();
-        assert_eq!(
-            plaintext,
-            client2a
-                .decrypt_media(client1a.demux_id, is_audio, &ciphertext1a)
-                .unwrap()
-        );
+        // Both at once, which the SFU can't tell us.
+        detect(0, &[(2, 2000), (3, 5000)]);
+        assert_eq!(vec![(2, false, None), (3, false, None)], speakers());
+        detect(400, &[(2, 2000), (3, 5000)]);
+        assert_eq!(vec![(2, true, Some(0)), (3, true, Some(1))], speakers());
+        client1
+            .observer
+            .handle_remote_devices_changed_invocation_count();
 
-        // Make sure the advanced key gets sent to client2b even though it's the same user as 2a.
-        client2b.connect_join_and_wait_until_joined();
-        set_group_and_wait_until_applied(&[&client1a, &client2a, &client2b]);
-        let ciphertext1a = client1a.encrypt_media(is_audio, plaintext).unwrap();
+        // Levels changing while both keep speaking don't reorder them or notify.
+        detect(600, &[(2, 5000), (3, 0)]);
+        detect(800, &[(2, 1000), (3, 5000)]);
+        assert_eq!(vec![(2, true, Some(0)), (3, true, Some(1))], speakers());
         assert_eq!(
-            plaintext,
-            client2b
-                .decrypt_media(client1a.demux_id, is_audio, &ciphertext1a)
-                .unwrap()
+            0,
+            client1
+                .observer
+                .handle_remote_devices_changed_invocation_count()
         );
+
+        detect(900, &[(2, 5000), (3, 0)]);
+
+        // Silence must last a while before a device stops speaking.
+        detect(1000, &[(2, 5000), (3, 0)]);
+        assert_eq!(vec![(2, true, Some(0)), (3, true, Some(1))], speakers());
+        detect(1200, &[(2, 5000), (3, 0)]);
+        assert_eq!(vec![(2, true, Some(0)), (3, true, Some(1))], speakers());
+        detect(2000, &[(2, 5000), (3, 0)]);
+        assert_eq!(vec![(2, true, Some(0)), (3, false, Some(1))], speakers());
+        assert!(client1
+            .observer
+            .remote_devices()
+            .iter()
+            .all(|device| device.last_speaking_time.is_some()));
+
+        client1.disconnect_and_wait_until_ended();
+    }
+
+    #[test]
+    fn talking_while_muted() {
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
+        client1
+            .client
+            .set_talking_while_muted_config(TalkingWhileMutedConfig {
+                min_duration: Duration::from_millis(400),
+                ..TalkingWhileMutedConfig::default()
+            });
+
+        // Feed the levels with our own timestamps rather than from the tick.
+        client1.client.actor.send(|state| {
+            state.audio_levels_interval = None;
+        });
+        let start = Instant::now();
+        let detect = |millis: u64| {
+            client1.client.actor.send(move |state| {
+                Client::detect_talking_while_muted(
+                    state,
+                    start + Duration::from_millis(millis),
+                    5000,
+                );
+            });
+            client1.wait_for_client_to_process();
+            client1
+                .observer
+                .handle_talking_while_muted_invocation_count()
+        };
+
+        // Not muted
+        assert_eq!(0, detect(0));
+        assert_eq!(0, detect(1000));
+
+        client1.client.set_outgoing_audio_muted(true);
+        assert_eq!(0, detect(1200));
+        assert_eq!(0, detect(1400));
+        assert_eq!(1, detect(1600));
+        // Not again right away.
+        assert_eq!(0, detect(1800));
+
+        client1.disconnect_and_wait_until_ended();
     }
 
     #[test]
-    fn frame_encryption_someone_forging_demux_id() {
-        let mut client1 = TestClient::new(vec![1], 1, None);
+    fn remote_audio_volume_and_mute() {
+        let client1 = TestClient::new(vec![1], 1, None);
+        let client2 = TestClient::new(vec![2], 2, None);
         client1.connect_join_and_wait_until_joined();
-
-        let mut client2 = TestClient::new(vec![2], 2, None);
         client2.connect_join_and_wait_until_joined();
+        client1.set_remotes_and_wait_until_applied(&[&client2]);
 
-        // Client3 is pretending to have demux ID 1 when sending media keys
-        let mut client3 = TestClient::new(vec![3], 3, Some(1));
-        client3.connect_join_and_wait_until_joined();
+        let remote_audio_volume = |demux_id: DemuxId| {
+            let (sender, receiver) = mpsc::channel();
+            client1.client.actor.send(move |state| {
+                sender
+                    .send(state.peer_connection.remote_audio_volume(demux_id))
+                    .unwrap();
+            });
+            receiver.recv().unwrap()
+        };
 
-        set_group_and_wait_until_applied(&[&client1, &client2, &client3]);
+        assert_eq!(None, remote_audio_volume(2));
 
-        let is_audio = true;
-        let plaintext = &b"Fake Audio"[..];
-        let ciphertext1 = client1.encrypt_media(is_audio, plaintext).unwrap();
-        let ciphertext3 = client3.encrypt_media(is_audio, plaintext).unwrap();
-        // The forger doesn't mess anything up for the others
-        assert_eq!(
-            plaintext,
-            client2
-                .decrypt_media(client1.demux_id, is_audio, &ciphertext1)
-                .unwrap()
-        );
-        // And you can't decrypt from the forger.
-        assert!(client2
-            .decrypt_media(client3.demux_id, is_audio, &ciphertext3)
-            .is_err());
+        client1.client.set_remote_audio_volume(2, 0.5);
+        assert_eq!(Some(0.5), remote_audio_volume(2));
+
+        client1.client.set_remote_audio_muted(2, true);
+        assert_eq!(Some(0.0), remote_audio_volume(2));
+
+        // The volume is remembered while muted.
+        client1.client.set_remote_audio_volume(2, 2.0);
+        assert_eq!(Some(0.0), remote_audio_volume(2));
+        client1.client.set_remote_audio_muted(2, false);
+        assert_eq!(Some(2.0), remote_audio_volume(2));
+
+        client1.client.set_remote_audio_volume(2, 100.0);
+        assert_eq!(Some(MAX_REMOTE_AUDIO_VOLUME), remote_audio_volume(2));
+
+        // Unknown devices are ignored.
+        client1.client.set_remote_audio_muted(3, true);
+        assert_eq!(None, remote_audio_volume(3));
+
+        // And the PeerConnection refuses devices it has no audio receiver for.
+        let (sender, receiver) = mpsc::channel();
+        client1.client.actor.send(move |state| {
+            sender
+                .send(
+                    state
+                        .peer_connection
+                        .set_remote_audio_volume(3, 1.0)
+                        .is_err(),
+                )
+                .unwrap();
+        });
+        assert!(receiver.recv().unwrap());
 
         client1.disconnect_and_wait_until_ended();
         client2.disconnect_and_wait_until_ended();
-        client3.disconnect_and_wait_until_ended();
     }
 
     #[test]
-    fn remote_heartbeat_state() {
+    fn remote_profile() {
         let client1 = TestClient::new(vec![1], 1, None);
         client1.connect_join_and_wait_until_joined();
 
@@ -4425,62 +6461,274 @@ mod tests {
 
         let remote_devices2 = client2.observer.remote_devices();
         assert_eq!(1, remote_devices2.len());
-        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.audio_muted);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.video_muted);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.presenting);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
+        assert_eq!(Profile::default(), remote_devices2[0].profile);
 
-        client1.client.set_outgoing_audio_muted(true);
+        client1
+            .client
+            .set_local_display_name(Some("Alice".to_string()));
         client1.wait_for_client_to_process();
         client2.wait_for_client_to_process();
 
         let remote_devices2 = client2.observer.remote_devices();
         assert_eq!(1, remote_devices2.len());
         assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
-        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.video_muted);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.presenting);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
+        assert_eq!(
+            Some("Alice".to_string()),
+            remote_devices2[0].profile.display_name
+        );
 
-        client1.client.set_outgoing_video_muted(false);
+        client1.client.set_local_avatar_hash(Some(vec![0xAB; 32]));
         client1.wait_for_client_to_process();
         client2.wait_for_client_to_process();
 
         let remote_devices2 = client2.observer.remote_devices();
-        assert_eq!(1, remote_devices2.len());
-        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
-        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
-        assert_eq!(Some(false), remote_devices2[0].heartbeat_state.video_muted);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.presenting);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
+        assert_eq!(
+            Profile {
+                display_name: Some("Alice".to_string()),
+                avatar_hash: Some(vec![0xAB; 32]),
+            },
+            remote_devices2[0].profile
+        );
 
-        client1.client.set_presenting(true);
+        // Too long to be a hash; ignored.
+        client1
+            .client
+            .set_local_avatar_hash(Some(vec![0xAB; MAX_AVATAR_HASH_LEN + 1]));
         client1.wait_for_client_to_process();
         client2.wait_for_client_to_process();
 
         let remote_devices2 = client2.observer.remote_devices();
-        assert_eq!(1, remote_devices2.len());
-        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
-        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
-        assert_eq!(Some(false), remote_devices2[0].heartbeat_state.video_muted);
-        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.presenting);
-        assert_eq!(None, remote_devices2[0].heartbeat_state.sharing_screen);
+        assert_eq!(Some(vec![0xAB; 32]), remote_devices2[0].profile.avatar_hash);
 
-        client1.client.set_sharing_screen(true);
+        let long_name = "\u{00e9}".repeat(MAX_DISPLAY_NAME_LEN);
+        client1.client.set_local_display_name(Some(long_name));
         client1.wait_for_client_to_process();
         client2.wait_for_client_to_process();
 
         let remote_devices2 = client2.observer.remote_devices();
-        assert_eq!(1, remote_devices2.len());
-        assert_eq!(client1.demux_id, remote_devices2[0].demux_id);
-        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.audio_muted);
-        assert_eq!(Some(false), remote_devices2[0].heartbeat_state.video_muted);
-        assert_eq!(Some(true), remote_devices2[0].heartbeat_state.presenting);
+        let display_name = remote_devices2[0].profile.display_name.clone().unwrap();
+        assert_eq!(MAX_DISPLAY_NAME_LEN, display_name.len());
+        assert!(display_name.chars().all(|c| c == '\u{00e9}'));
+
+        // Clearing the profile reaches the other device too.
+        client1.client.set_local_display_name(None);
+        client1.client.set_local_avatar_hash(None);
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+
+        let remote_devices2 = client2.observer.remote_devices();
+        assert_eq!(Profile::default(), remote_devices2[0].profile);
+    }
+
+    #[test]
+    fn pending_remote_profiles_are_limited() {
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
+
+        let profile = |name: &str| Profile {
+            display_name: Some(name.to_string()),
+            avatar_hash: None,
+        };
+        let last_demux_id = (MAX_PENDING_REMOTE_PROFILES as DemuxId + 10) * 16;
+        let (sender, receiver) = mpsc::channel();
+        client1.client.actor.send(move |state| {
+            // From devices the SFU hasn't told us about yet.
+            for demux_id in (16..last_demux_id).step_by(16) {
+                Client::set_remote_profile_or_store_for_later(
+                    state,
+                    vec![2],
+                    demux_id,
+                    profile("old"),
+                );
+            }
+            Client::set_remote_profile_or_store_for_later(
+                state,
+                vec![2],
+                last_demux_id,
+                profile("old"),
+            );
+            Client::set_remote_profile_or_store_for_later(
+                state,
+                vec![2],
+                last_demux_id,
+                profile("new"),
+            );
+            sender
+                .send(state.pending_remote_profiles.clone())
+                .expect("send pending profiles");
+        });
+        let pending_remote_profiles = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("receive pending profiles");
+
+        assert_eq!(MAX_PENDING_REMOTE_PROFILES, pending_remote_profiles.len());
         assert_eq!(
-            Some(true),
-            remote_devices2[0].heartbeat_state.sharing_screen
+            Some(&(vec![2], last_demux_id, profile("new"))),
+            pending_remote_profiles.last()
+        );
+        // The oldest were dropped.
+        assert_eq!(11 * 16, pending_remote_profiles[0].1);
+
+        client1.disconnect_and_wait_until_ended();
+    }
+
+    #[test]
+    fn spotlight() {
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
+        let client2 = TestClient::new(vec![2], 2, None);
+        client2.connect_join_and_wait_until_joined();
+        let client3 = TestClient::new(vec![3], 3, None);
+        client3.connect_join_and_wait_until_joined();
+
+        set_group_and_wait_until_applied(&[&client1, &client2, &client3]);
+
+        let spotlighted = |client: &TestClient| -> Vec<DemuxId> {
+            client
+                .observer
+                .remote_devices()
+                .iter()
+                .filter(|remote| remote.spotlighted)
+                .map(|remote| remote.demux_id)
+                .collect()
+        };
+        assert_eq!(Vec::<DemuxId>::new(), spotlighted(&client1));
+        assert_eq!(Vec::<DemuxId>::new(), spotlighted(&client2));
+
+        client1.client.set_spotlight(Some(client3.demux_id));
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+        client3.wait_for_client_to_process();
+
+        assert_eq!(vec![client3.demux_id], spotlighted(&client1));
+        assert_eq!(vec![client3.demux_id], spotlighted(&client2));
+        // client3 is in the spotlight itself, which isn't a remote device.
+        assert_eq!(Vec::<DemuxId>::new(), spotlighted(&client3));
+
+        // Someone else can change it.
+        client2.client.set_spotlight(Some(client1.demux_id));
+        client2.wait_for_client_to_process();
+        client1.wait_for_client_to_process();
+        client3.wait_for_client_to_process();
+
+        assert_eq!(Vec::<DemuxId>::new(), spotlighted(&client1));
+        assert_eq!(vec![client1.demux_id], spotlighted(&client2));
+        assert_eq!(vec![client1.demux_id], spotlighted(&client3));
+
+        // client1 stopped repeating its old spotlight, so it doesn't come back
+        // with the next heartbeat.
+        std::thread::sleep(Duration::from_millis(1100));
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+        client3.wait_for_client_to_process();
+        assert_eq!(vec![client1.demux_id], spotlighted(&client3));
+
+        client3.client.set_spotlight(None);
+        client3.wait_for_client_to_process();
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+
+        assert_eq!(Vec::<DemuxId>::new(), spotlighted(&client2));
+        assert_eq!(Vec::<DemuxId>::new(), spotlighted(&client3));
+
+        client1.disconnect_and_wait_until_ended();
+        client2.disconnect_and_wait_until_ended();
+        client3.disconnect_and_wait_until_ended();
+    }
+
+    #[test]
+    fn spotlight_ordering_and_late_joiners() {
+        use protobuf::group_call::device_to_device::Spotlight;
+
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
+        let client2 = TestClient::new(vec![2], 2, None);
+        client2.connect_join_and_wait_until_joined();
+        let client3 = TestClient::new(vec![3], 3, None);
+        client3.connect_join_and_wait_until_joined();
+
+        set_group_and_wait_until_applied(&[&client1, &client2, &client3]);
+
+        client1.client.set_spotlight(Some(client3.demux_id));
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+        client3.wait_for_client_to_process();
+        assert_eq!(
+            Some(client3.demux_id),
+            client1.observer.spotlight_demux_id()
+        );
+        assert_eq!(
+            Some(client3.demux_id),
+            client2.observer.spotlight_demux_id()
+        );
+        // The app learns that the local device is in the spotlight.
+        assert_eq!(
+            Some(client3.demux_id),
+            client3.observer.spotlight_demux_id()
+        );
+
+        // An older change that arrives late doesn't undo a newer one.
+        client2.client.handle_spotlight_received(
+            client1.demux_id,
+            Spotlight {
+                demux_id: Some(client1.demux_id),
+                sequence_number: Some(0),
+                set_by_demux_id: Some(client1.demux_id),
+            },
+        );
+        client2.wait_for_client_to_process();
+        assert_eq!(
+            Some(client3.demux_id),
+            client2.observer.spotlight_demux_id()
+        );
+
+        // Changes made at the same time are broken by who made them, whatever order
+        // they arrive in.
+        let concurrent = |set_by_demux_id: DemuxId, demux_id: DemuxId| Spotlight {
+            demux_id: Some(demux_id),
+            sequence_number: Some(2),
+            set_by_demux_id: Some(set_by_demux_id),
+        };
+        client1
+            .client
+            .handle_spotlight_received(client2.demux_id, concurrent(2, 1));
+        client1
+            .client
+            .handle_spotlight_received(client2.demux_id, concurrent(1, 2));
+        client3
+            .client
+            .handle_spotlight_received(client2.demux_id, concurrent(1, 2));
+        client3
+            .client
+            .handle_spotlight_received(client2.demux_id, concurrent(2, 1));
+        client1.wait_for_client_to_process();
+        client3.wait_for_client_to_process();
+        assert_eq!(Some(1), client1.observer.spotlight_demux_id());
+        assert_eq!(Some(1), client3.observer.spotlight_demux_id());
+
+        // A device that joins later learns the spotlight from the heartbeats of
+        // those still in the call, even after the device that set it left.
+        client1.client.set_spotlight(Some(client3.demux_id));
+        client1.wait_for_client_to_process();
+        client2.wait_for_client_to_process();
+        client3.wait_for_client_to_process();
+        client1.disconnect_and_wait_until_ended();
+        let client4 = TestClient::new(vec![4], 4, None);
+        client4.connect_join_and_wait_until_joined();
+        set_group_and_wait_until_applied(&[&client2, &client3, &client4]);
+        assert_eq!(None, client4.observer.spotlight_demux_id());
+        std::thread::sleep(Duration::from_millis(1100));
+        client2.wait_for_client_to_process();
+        client3.wait_for_client_to_process();
+        client4.wait_for_client_to_process();
+        assert_eq!(
+            Some(client3.demux_id),
+            client4.observer.spotlight_demux_id()
         );
+
+        client2.disconnect_and_wait_until_ended();
+        client3.disconnect_and_wait_until_ended();
+        client4.disconnect_and_wait_until_ended();
     }
 
     fn hash_set<T: std::hash::Hash + Eq + Clone>(vals: impl IntoIterator<Item = T>) -> HashSet<T> {
@@ -4793,14 +7041,17 @@ mod tests {
                         VideoRequestProto {
                             demux_id: Some(2),
                             height: Some(1080),
+                            framerate: None,
                         },
                         VideoRequestProto {
                             demux_id: Some(3),
                             height: Some(80),
+                            framerate: Some(5),
                         },
                         VideoRequestProto {
                             demux_id: Some(4),
                             height: Some(0),
+                            framerate: None,
                         },
                     ],
                     max_kbps: Some(NORMAL_MAX_RECEIVE_RATE.as_kbps() as u32),
@@ -4855,14 +7106,17 @@ mod tests {
                         VideoRequestProto {
                             demux_id: Some(2),
                             height: Some(1080),
+                            framerate: None,
                         },
                         VideoRequestProto {
                             demux_id: Some(3),
                             height: Some(80),
+                            framerate: Some(5),
                         },
                         VideoRequestProto {
                             demux_id: Some(4),
                             height: Some(0),
+                            framerate: None,
                         },
                     ],
                     max_kbps: Some(1),
@@ -4885,14 +7139,17 @@ mod tests {
                         VideoRequestProto {
                             demux_id: Some(2),
                             height: Some(1080),
+                            framerate: None,
                         },
                         VideoRequestProto {
                             demux_id: Some(3),
                             height: Some(80),
+                            framerate: Some(5),
                         },
                         VideoRequestProto {
                             demux_id: Some(4),
                             height: Some(0),
+                            framerate: None,
                         },
                     ],
                     max_kbps: Some(500),
@@ -4903,37 +7160,187 @@ mod tests {
             DeviceToSfu::decode(&payload[..]).unwrap()
         );
 
-        client1.client.set_bandwidth_mode(BandwidthMode::Normal);
-        let (header, payload) = receiver
-            .recv_timeout(Duration::from_secs(2))
-            .expect("Get RTP packet to SFU");
-        assert_eq!(1, header.ssrc);
+        client1.client.set_bandwidth_mode(BandwidthMode::Normal);
+        let (header, payload) = receiver
+            .recv_timeout(Duration::from_secs(2))
+            .expect("Get RTP packet to SFU");
+        assert_eq!(1, header.ssrc);
+        assert_eq!(
+            DeviceToSfu {
+                video_request: Some(VideoRequestMessage {
+                    requests: vec![
+                        VideoRequestProto {
+                            demux_id: Some(2),
+                            height: Some(1080),
+                            framerate: None,
+                        },
+                        VideoRequestProto {
+                            demux_id: Some(3),
+                            height: Some(80),
+                            framerate: Some(5),
+                        },
+                        VideoRequestProto {
+                            demux_id: Some(4),
+                            height: Some(0),
+                            framerate: None,
+                        },
+                    ],
+                    max_kbps: Some(20_000_000),
+                    active_speaker_height: Some(1080),
+                }),
+                ..Default::default()
+            },
+            DeviceToSfu::decode(&payload[..]).unwrap()
+        );
+
+        client1.disconnect_and_wait_until_ended();
+    }
+
+    #[test]
+    fn video_request_policy() {
+        let time = |millis| SystemTime::UNIX_EPOCH + Duration::from_millis(millis);
+        let mut devices: Vec<RemoteDeviceState> = (1..=5)
+            .map(|demux_id| {
+                RemoteDeviceState::new(demux_id, vec![demux_id as u8], time(demux_id as u64))
+            })
+            .collect();
+        devices[3].speaker_time = Some(time(100));
+        devices[2].speaker_time = Some(time(50));
+
+        let heights = |(requests, active_speaker_height): (Vec<VideoRequest>, u16)| {
+            (
+                requests
+                    .iter()
+                    .map(|request| (request.demux_id, request.height))
+                    .collect::<Vec<_>>(),
+                active_speaker_height,
+            )
+        };
+
+        let mut policy = VideoRequestPolicy {
+            slots: vec![
+                VideoTileSlot::Spotlight {
+                    width: 1280,
+                    height: 720,
+                    framerate: None,
+                },
+                VideoTileSlot::Thumbnails {
+                    count: 2,
+                    width: 160,
+                    height: 90,
+                    framerate: Some(15),
+                },
+            ],
+            pinned_demux_id: None,
+        };
+        // Latest speaker in the spotlight, then by speaker time, then by added time,
+        // and the rest offscreen.
+        assert_eq!(
+            (vec![(4, 720), (3, 90), (1, 90), (2, 0), (5, 0)], 720),
+            heights(policy.video_requests(&mut devices))
+        );
+        assert_eq!(
+            vec![
+                (4, None),
+                (3, Some(15)),
+                (1, Some(15)),
+                (2, None),
+                (5, None)
+            ],
+            policy
+                .video_requests(&mut devices)
+                .0
+                .iter()
+                .map(|request| (request.demux_id, request.framerate))
+                .collect::<Vec<_>>()
+        );
+
+        let video_slots = |devices: &[RemoteDeviceState]| {
+            devices
+                .iter()
+                .map(|device| (device.demux_id, device.video_slot))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![
+                (1, Some(2)),
+                (2, None),
+                (3, Some(1)),
+                (4, Some(0)),
+                (5, None)
+            ],
+            video_slots(&devices)
+        );
+
+        // A new speaker takes the spotlight, and the old one takes the thumbnail it left
+        // rather than shifting the other thumbnails.
+        devices[0].speaker_time = Some(time(200));
+        assert_eq!(
+            (vec![(1, 720), (4, 90), (3, 90), (2, 0), (5, 0)], 720),
+            heights(policy.video_requests(&mut devices))
+        );
+        assert_eq!(
+            vec![
+                (1, Some(0)),
+                (2, None),
+                (3, Some(1)),
+                (4, Some(2)),
+                (5, None)
+            ],
+            video_slots(&devices)
+        );
+        devices[0].speaker_time = None;
+
+        // Pinning takes the spotlight away from the speaker.
+        policy.pinned_demux_id = Some(5);
         assert_eq!(
-            DeviceToSfu {
-                video_request: Some(VideoRequestMessage {
-                    requests: vec![
-                        VideoRequestProto {
-                            demux_id: Some(2),
-                            height: Some(1080),
-                        },
-                        VideoRequestProto {
-                            demux_id: Some(3),
-                            height: Some(80),
-                        },
-                        VideoRequestProto {
-                            demux_id: Some(4),
-                            height: Some(0),
-                        },
-                    ],
-                    max_kbps: Some(20_000_000),
-                    active_speaker_height: Some(1080),
-                }),
-                ..Default::default()
-            },
-            DeviceToSfu::decode(&payload[..]).unwrap()
+            (vec![(5, 720), (4, 90), (3, 90), (1, 0), (2, 0)], 0),
+            heights(policy.video_requests(&mut devices))
         );
 
-        client1.disconnect_and_wait_until_ended();
+        // Pinning a device that isn't there does nothing.
+        policy.pinned_demux_id = Some(6);
+        assert_eq!(
+            (vec![(4, 720), (3, 90), (1, 90), (2, 0), (5, 0)], 720),
+            heights(policy.video_requests(&mut devices))
+        );
+
+        // The spotlight takes it away from the speaker too, but not from the pinned device.
+        devices[1].spotlighted = true;
+        assert_eq!(
+            (vec![(2, 720), (4, 90), (3, 90), (1, 0), (5, 0)], 0),
+            heights(policy.video_requests(&mut devices))
+        );
+        policy.pinned_demux_id = Some(5);
+        assert_eq!(
+            (vec![(5, 720), (2, 90), (4, 90), (3, 0), (1, 0)], 0),
+            heights(policy.video_requests(&mut devices))
+        );
+        devices[1].spotlighted = false;
+        policy.pinned_demux_id = None;
+
+        let policy = VideoRequestPolicy {
+            slots: vec![
+                VideoTileSlot::Grid {
+                    count: 2,
+                    width: 640,
+                    height: 360,
+                    framerate: None,
+                },
+                VideoTileSlot::Offscreen,
+                VideoTileSlot::Thumbnails {
+                    count: 2,
+                    width: 160,
+                    height: 90,
+                    framerate: None,
+                },
+            ],
+            pinned_demux_id: None,
+        };
+        assert_eq!(
+            (vec![(4, 360), (3, 360), (1, 0), (2, 0), (5, 0)], 0),
+            heights(policy.video_requests(&mut devices))
+        );
     }
 
     #[test]
@@ -5144,6 +7551,116 @@ mod tests {
         );
     }
 
+    // Responds to joins like an SFU would, and to peeks with the given participants.
+    #[derive(Clone)]
+    struct FakeSfuHttpClient {
+        peek_body: String,
+        requests: Arc<Mutex<Vec<http::Request>>>,
+    }
+
+    impl http::Client for FakeSfuHttpClient {
+        fn send_request(&self, request: http::Request, callback: http::ResponseCallback) {
+            let body = match request.method {
+                http::Method::Put => serde_json::json!({
+                    "demuxId": 16,
+                    "ip": "192.0.2.1",
+                    "port": 10000,
+                    "iceUfrag": "server ufrag",
+                    "icePwd": "server pwd",
+                    "dhePublicKey": hex::encode([1u8; 32]),
+                })
+                .to_string(),
+                _ => self.peek_body.clone(),
+            };
+            self.requests.lock().unwrap().push(request);
+            callback(Some(http::Response {
+                status: 200.into(),
+                body: body.into_bytes(),
+            }))
+        }
+    }
+
+    #[test]
+    fn call_link_join_and_peek() {
+        let root_key = CallLinkRootKey::try_from(&[0x43; 16]).unwrap();
+        let local_user_id = b"alice".to_vec();
+        let remote_user_id = b"bob".to_vec();
+        let http_client = FakeSfuHttpClient {
+            peek_body: serde_json::json!({
+                "participants": [
+                    {"opaqueUserId": hex::encode(root_key.encrypt_user_id(&local_user_id)), "demuxId": 16},
+                    {"opaqueUserId": hex::encode(root_key.encrypt_user_id(&remote_user_id)), "demuxId": 32},
+                ],
+            })
+            .to_string(),
+            requests: Default::default(),
+        };
+        let sfu_client = CallLinkSfuClient::new(
+            Box::new(http_client.clone()),
+            "https://sfu.example".to_string(),
+            &root_key,
+            vec![],
+        );
+        let observer = FakeObserver::new(local_user_id.clone());
+        let client = Client::start(
+            sfu_client.room_id().to_vec(),
+            1,
+            Box::new(sfu_client),
+            Box::new(observer.clone()),
+            Arc::new(CallMutex::new(false, "fake_busy")),
+            Arc::new(CallMutex::new(Some(local_user_id), "fake_self_uuid")),
+            None,
+            AudioTrack::new(
+                webrtc::Arc::from_owned(unsafe {
+                    webrtc::ptr::OwnedRc::from_ptr(&FAKE_AUDIO_TRACK as *const u32)
+                }),
+                None,
+            ),
+            None,
+            None,
+            None,
+            None,
+        )
+        .expect("Start Client");
+
+        // Without a credential, the join waits.
+        client.connect();
+        client.join();
+        client.synchronize();
+        assert!(!observer.joined.wait(Duration::ZERO));
+        assert!(http_client.requests.lock().unwrap().is_empty());
+
+        client.set_membership_proof(b"presentation".to_vec());
+        assert!(observer.joined.wait(Duration::from_secs(5)));
+        client.synchronize();
+
+        let requests = http_client.requests.lock().unwrap().clone();
+        let join_request = requests
+            .iter()
+            .find(|request| request.method == http::Method::Put)
+            .expect("join request");
+        assert_eq!(
+            Some(&format!("Bearer auth.{}", base64::encode(b"presentation"))),
+            join_request.headers.get("Authorization")
+        );
+        assert_eq!(
+            Some(&hex::encode(root_key.derive_room_id())),
+            join_request.headers.get("X-Room-Id")
+        );
+        assert!(requests.iter().all(|request| {
+            request.headers.get("X-Room-Id") == join_request.headers.get("X-Room-Id")
+        }));
+
+        // User IDs are decrypted, so they can be matched with the senders of media keys.
+        let remote_devices = observer.remote_devices();
+        assert_eq!(1, remote_devices.len());
+        assert_eq!(32, remote_devices[0].demux_id);
+        assert_eq!(remote_user_id, remote_devices[0].user_id);
+
+        client.disconnect();
+        observer.ended.wait(Duration::from_secs(5));
+    }
+
     #[test]
     fn speakers() {
         let client1 = TestClient::new(vec![1], 1, None);
@@ -5314,7 +7831,7 @@ mod tests {
 
         client1
             .client
-            .handle_forwarding_video_received(vec![2, 3], vec![240, 120]);
+            .handle_forwarding_video_received(vec![2, 3], vec![240, 120], vec![]);
         client1.wait_for_client_to_process();
 
         assert_eq!(
@@ -5324,7 +7841,7 @@ mod tests {
 
         client1
             .client
-            .handle_forwarding_video_received(vec![2], vec![120]);
+            .handle_forwarding_video_received(vec![2], vec![120], vec![]);
         client1.wait_for_client_to_process();
 
         assert_eq!(
@@ -5360,7 +7877,7 @@ mod tests {
 
         client1
             .client
-            .handle_forwarding_video_received(vec![2], vec![480]);
+            .handle_forwarding_video_received(vec![2], vec![480], vec![]);
         client1.wait_for_client_to_process();
 
         set_client_decoded_height(&client1, 480);
@@ -5368,7 +7885,7 @@ mod tests {
         // There is no video when forwarding stops, so the height is None
         client1
             .client
-            .handle_forwarding_video_received(vec![], vec![]);
+            .handle_forwarding_video_received(vec![], vec![], vec![]);
         client1.wait_for_client_to_process();
 
         assert_eq!(None, get_client_decoded_height(&client1));
@@ -5376,6 +7893,137 @@ mod tests {
         client1.disconnect_and_wait_until_ended();
     }
 
+    // Allocates to each device what was requested for it, the way the SFU does
+    // when there's enough bandwidth.
+    fn fake_sfu_current_devices(
+        device_to_sfu: &protobuf::group_call::DeviceToSfu,
+    ) -> protobuf::group_call::SfuToDevice {
+        use protobuf::group_call::{sfu_to_device::CurrentDevices, SfuToDevice};
+
+        let requests = device_to_sfu
+            .video_request
+            .as_ref()
+            .map(|video_request| video_request.requests.as_slice())
+            .unwrap_or_default();
+        let forwarded: Vec<_> = requests
+            .iter()
+            .filter(|request| request.height.unwrap_or(0) > 0)
+            .collect();
+        SfuToDevice {
+            current_devices: Some(CurrentDevices {
+                demux_ids_with_video: forwarded
+                    .iter()
+                    .map(|request| request.demux_id.unwrap_or(0))
+                    .collect(),
+                all_demux_ids: requests
+                    .iter()
+                    .map(|request| request.demux_id.unwrap_or(0))
+                    .collect(),
+                allocated_heights: forwarded
+                    .iter()
+                    .map(|request| request.height.unwrap_or(0))
+                    .collect(),
+                allocated_framerates: forwarded
+                    .iter()
+                    .map(|request| request.framerate.unwrap_or(0))
+                    .collect(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn video_request_framerate() {
+        use protobuf::group_call::DeviceToSfu;
+
+        let mut client1 = TestClient::new(vec![1], 1, None);
+        let client2 = TestClient::new(vec![2], 2, None);
+        let client3 = TestClient::new(vec![3], 3, None);
+        let client4 = TestClient::new(vec![4], 4, None);
+
+        let (sender, receiver) = mpsc::channel();
+        client1.sfu_rtp_packet_sender = Some(sender);
+        client1.connect_join_and_wait_until_joined();
+        client1.set_remotes_and_wait_until_applied(&[&client2, &client3, &client4]);
+
+        client1.client.request_video(
+            vec![
+                VideoRequest {
+                    demux_id: 2,
+                    width: 1280,
+                    height: 720,
+                    framerate: None,
+                },
+                VideoRequest {
+                    demux_id: 3,
+                    width: 160,
+                    height: 90,
+                    framerate: Some(15),
+                },
+                VideoRequest {
+                    demux_id: 4,
+                    width: 0,
+                    height: 0,
+                    framerate: Some(15),
+                },
+            ],
+            0,
+        );
+        let device_to_sfu = loop {
+            let (_header, payload) = receiver
+                .recv_timeout(Duration::from_secs(1))
+                .expect("Get RTP packet to SFU");
+            let device_to_sfu = DeviceToSfu::decode(&payload[..]).unwrap();
+            if device_to_sfu.video_request.is_some() {
+                break device_to_sfu;
+            }
+        };
+        assert_eq!(
+            vec![(Some(2), None), (Some(3), Some(15)), (Some(4), Some(15))],
+            device_to_sfu
+                .video_request
+                .as_ref()
+                .unwrap()
+                .requests
+                .iter()
+                .map(|request| (request.demux_id, request.framerate))
+                .collect::<Vec<_>>()
+        );
+
+        let sfu_to_device = fake_sfu_current_devices(&device_to_sfu);
+        client1.client.handle_rtp_received(
+            rtp::Header {
+                pt: RTP_DATA_PAYLOAD_TYPE,
+                ssrc: RTP_DATA_TO_SFU_SSRC,
+                seqnum: 1,
+                timestamp: 1,
+            },
+            &encode_proto(sfu_to_device).unwrap(),
+        );
+        client1.wait_for_client_to_process();
+
+        assert_eq!(
+            vec![
+                (2, Some(true), 720, None),
+                (3, Some(true), 90, Some(15)),
+                (4, Some(false), 0, None)
+            ],
+            client1
+                .observer
+                .remote_devices()
+                .iter()
+                .map(|remote| (
+                    remote.demux_id,
+                    remote.forwarding_video,
+                    remote.server_allocated_height,
+                    remote.server_allocated_framerate
+                ))
+                .collect::<Vec<_>>()
+        );
+
+        client1.disconnect_and_wait_until_ended();
+    }
+
     #[test]
     fn is_higher_resolution_pending() {
         let get_forwarding_videos = |client: &TestClient| -> Vec<(DemuxId, u16)> {
@@ -5410,7 +8058,7 @@ mod tests {
 
         client1
             .client
-            .handle_forwarding_video_received(vec![2], vec![240]);
+            .handle_forwarding_video_received(vec![2], vec![240], vec![]);
         client1.wait_for_client_to_process();
 
         assert_eq!(vec![(2, 240)], get_forwarding_videos(&client1));
@@ -5605,6 +8253,302 @@ mod tests {
         client1.disconnect_and_wait_until_ended();
     }
 
+    #[test]
+    fn audio_adaptation_returns_to_defaults() {
+        use crate::webrtc::stats_observer::AudioSenderStatistics;
+
+        let client1 = TestClient::new(vec![1], 1, None);
+        let client2 = TestClient::new(vec![2], 2, None);
+        client1.connect_join_and_wait_until_joined();
+        client1.set_remotes_and_wait_until_applied(&[&client2]);
+
+        // Returns what was last given to WebRTC.
+        let report = |client: &TestClient, packets_sent: u32, remote_packets_lost: i32| {
+            let (sender, receiver) = mpsc::channel();
+            client.client.actor.send(move |state| {
+                let stats = AudioSenderStatistics {
+                    ssrc: 1,
+                    packets_sent,
+                    bytes_sent: 0,
+                    remote_packets_lost,
+                    remote_jitter: 0.0,
+                    remote_round_trip_time: 0.0,
+                    audio_level: 0.0,
+                    total_audio_energy: 0.0,
+                    echo_likelihood: 0.0,
+                };
+                if state.audio_adaptation.update(&stats) {
+                    Client::apply_audio_encoder_config(state);
+                }
+                sender
+                    .send(state.applied_audio_encoder_config.clone())
+                    .unwrap();
+            });
+            receiver.recv().unwrap()
+        };
+
+        assert_eq!(None, report(&client1, 0, 0));
+        // 5% loss
+        let protected = report(&client1, 1000, 50).expect("protected config");
+        assert!(protected.enable_fec);
+        // It takes a few reports without loss to step down.
+        assert_eq!(Some(protected.clone()), report(&client1, 2000, 50));
+        assert_eq!(Some(protected), report(&client1, 3000, 50));
+        assert_eq!(None, report(&client1, 4000, 50));
+
+        client1.disconnect_and_wait_until_ended();
+    }
+
+    #[test]
+    fn send_rate_policy() {
+        use protobuf::group_call::{sfu_to_device::VideoRequest, SfuToDevice};
+
+        let client1 = TestClient::new(vec![1], 1, None);
+        client1.connect_join_and_wait_until_joined();
+        let client2 = TestClient::new(vec![2], 2, None);
+        let client3 = TestClient::new(vec![3], 3, None);
+        client1.set_remotes_and_wait_until_applied(&[&client2, &client3]);
+        let max_kbps = |client: &TestClient| -> Option<u64> {
+            client
+                .observer
+                .send_rates()
+                .and_then(|send_rates| send_rates.max)
+                .map(DataRate::as_kbps)
+        };
+        let receive_requested_height = |client: &TestClient, height: u32| {
+            client.client.handle_rtp_received(
+                rtp::Header {
+                    pt: RTP_DATA_PAYLOAD_TYPE,
+                    ssrc: RTP_DATA_TO_SFU_SSRC,
+                    seqnum: 1,
+                    timestamp: 1,
+                },
+                &encode_proto(SfuToDevice {
+                    video_request: Some(VideoRequest {
+                        height: Some(height),
+                    }),
+                    ..Default::default()
+                })
+                .unwrap(),
+            );
+            client.wait_for_client_to_process();
+        };
+        assert_eq!(Some(1000), max_kbps(&client1));
+
+        // Everyone only wants thumbnails.
+        receive_requested_height(&client1, 90);
+        assert_eq!(Some(250), max_kbps(&client1));
+        receive_requested_height(&client1, 720);
+        assert_eq!(Some(1000), max_kbps(&client1));
+
+        client1.client.set_device_conditions(DeviceConditions {
+            low_battery: true,
+            thermal_throttling: false,
+        });
+        client1.wait_for_client_to_process();
+        assert_eq!(Some(671), max_kbps(&client1));
+
+        client1.client.set_bandwidth_mode(BandwidthMode::Low);
+        client1.wait_for_client_to_process();
+        assert_eq!(Some(300), max_kbps(&client1));
+
+        client1
+            .client
+            .set_bandwidth_policy(BandwidthPolicy::with_max_rate(DataRate::from_kbps(200)));
+        client1.wait_for_client_to_process();
+        assert_eq!(Some(200), max_kbps(&client1));
+        let applied_audio_encoder_config = |client: &TestClient| {
+            let (sender, receiver) = mpsc::channel();
+            client.client.actor.send(move |state| {
+                sender
+                    .send(state.applied_audio_encoder_config.clone())
+                    .unwrap();
+            });
+            receiver.recv().unwrap()
+        };
+        assert_eq!(
+            Some(BandwidthMode::VeryLow.audio_encoder_config()),
+            applied_audio_encoder_config(&client1)
+        );
+
+        // High doesn't limit what the send rate policy allows.
+        client1.client.set_bandwidth_mode(BandwidthMode::High);
+        client1.wait_for_client_to_process();
+        assert_eq!(Some(671), max_kbps(&client1));
+        // But it asks the SFU for more than Normal.
+        let max_receive_rate = |client: &TestClient| {
+            let (sender, receiver) = mpsc::channel();
+            client.client.actor.send(move |state| {
+                sender.send(state.max_receive_rate).unwrap();
+            });
+            receiver.recv().unwrap()
+        };
+        assert_eq!(Some(HIGH_MAX_RECEIVE_RATE), max_receive_rate(&client1));
+        // And the policy's audio configuration is gone.
+        assert_eq!(None, applied_audio_encoder_config(&client1));
+
+        // Screen sharing only insists on its minimum if the SFU thinks we can send it,
+        // and once given up, only insists again when there's room to spare.
+        let min_kbps = |client: &TestClient| -> Option<u64> {
+            client
+                .observer
+                .send_rates()
+                .and_then(|send_rates| send_rates.min)
+                .map(DataRate::as_kbps)
+        };
+        let receive_target_send_rate = |client: &TestClient, kbps: u64| {
+            client
+                .client
+                .handle_target_send_rate_received(DataRate::from_kbps(kbps));
+            client.wait_for_client_to_process();
+        };
+        client1.client.set_sharing_screen(true);
+        client1.wait_for_client_to_process();
+        assert_eq!(Some(2000), min_kbps(&client1));
+        receive_target_send_rate(&client1, 800);
+        assert_eq!(None, min_kbps(&client1));
+        receive_target_send_rate(&client1, 2200);
+        assert_eq!(None, min_kbps(&client1));
+        receive_target_send_rate(&client1, 3000);
+        assert_eq!(Some(2000), min_kbps(&client1));
+        receive_target_send_rate(&client1, 2200);
+        assert_eq!(Some(2000), min_kbps(&client1));
+        client1.client.set_sharing_screen(false);
+        client1.wait_for_client_to_process();
+
+        struct FixedSendRatePolicy;
+        impl SendRatePolicy for FixedSendRatePolicy {
+            fn compute_send_rates(&self, _inputs: &SendRateInputs) -> SendRates {
+                SendRates {
+                    max: Some(DataRate::from_kbps(42)),
+                    ..SendRates::default()
+                }
+            }
+        }
+        client1
+            .client
+            .set_send_rate_policy(Box::new(FixedSendRatePolicy));
+        client1.wait_for_client_to_process();
+        assert_eq!(Some(42), max_kbps(&client1));
+
+        // Being alone isn't up to the policy.
+        client1.set_remotes_and_wait_until_applied(&[]);
+        assert_eq!(Some(1), max_kbps(&client1));
+
+        client1.disconnect_and_wait_until_ended();
+    }
+
+    #[test]
+    fn pause_unrequested_video_layers() {
+        use crate::webrtc::media::{VideoEncodingLayer, VideoLayering};
+
+        let layer = |scale_resolution_down_by| VideoEncodingLayer {
+            scale_resolution_down_by,
+            max_bitrate_bps: 100_000,
+            active: true,
+        };
+        let config = VideoEncodingConfig {
+            layering: VideoLayering::Simulcast,
+            layers: vec![layer(4.0), layer(2.0), layer(1.0)],
+        };
+        let active = |requested_height| -> Vec<bool> {
+            pause_unrequested_layers(&config, 720, requested_height)
+                .layers
+                .iter()
+                .map(|layer| layer.active)
+                .collect()
+        };
+
+        assert_eq!(vec![true, true, true], active(None));
+        assert_eq!(vec![false, false, false], active(Some(0)));
+        assert_eq!(vec![true, false, false], active(Some(90)));
+        assert_eq!(vec![true, false, false], active(Some(180)));
+        assert_eq!(vec![true, true, false], active(Some(240)));
+        assert_eq!(vec![true, true, true], active(Some(720)));
+        // More than we can send; send everything we have.
+        assert_eq!(vec![true, true, true], active(Some(1080)));
+    }
+
+    #[test]
+    fn video_encoding_layers() {
+        use crate::webrtc::media::{VideoEncodingLayer, VideoLayering};
+        use protobuf::group_call::{sfu_to_device::VideoRequest, SfuToDevice};
+
+        let client1 = TestClient::new(vec![1], 1, None);
+        let client2 = TestClient::new(vec![2], 2, None);
+        let layer = |scale_resolution_down_by| VideoEncodingLayer {
+            scale_resolution_down_by,
+            max_bitrate_bps: 100_000,
+            active: true,
+        };
+        // Set before joining, applied once the PeerConnection is started.
+        client1
+            .client
+            .set_video_encoding_config(
+                Some(VideoEncodingConfig {
+                    layering: VideoLayering::Svc,
+                    layers: vec![layer(4.0), layer(2.0), layer(1.0)],
+                }),
+                720,
+            )
+            .unwrap();
+        client1.connect_join_and_wait_until_joined();
+        client1.set_remotes_and_wait_until_applied(&[&client2]);
+
+        let active_layers = |client: &TestClient| -> (bool, Vec<bool>) {
+            let (sender, receiver) = mpsc::channel();
+            client.client.actor.send(move |state| {
+                let (svc, layers) = state.peer_connection.video_encoding_layers();
+                sender
+                    .send((svc, layers.iter().map(|layer| layer.active).collect()))
+                    .unwrap();
+            });
+            receiver.recv().unwrap()
+        };
+        assert_eq!((true, vec![true, true, true]), active_layers(&client1));
+
+        client1.client.handle_rtp_received(
+            rtp::Header {
+                pt: RTP_DATA_PAYLOAD_TYPE,
+                ssrc: RTP_DATA_TO_SFU_SSRC,
+                seqnum: 1,
+                timestamp: 1,
+            },
+            &encode_proto(SfuToDevice {
+                video_request: Some(VideoRequest { height: Some(180) }),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        client1.wait_for_client_to_process();
+        assert_eq!((true, vec![true, false, false]), active_layers(&client1));
+
+        // Re-enabling outgoing media must not resume the paused layers.
+        client1.set_remotes_and_wait_until_applied(&[]);
+        client1.set_remotes_and_wait_until_applied(&[&client2]);
+        assert_eq!((true, vec![true, false, false]), active_layers(&client1));
+
+        // There are only 3 simulcast streams to put layers in.
+        assert!(client1
+            .client
+            .set_video_encoding_config(
+                Some(VideoEncodingConfig {
+                    layering: VideoLayering::Simulcast,
+                    layers: vec![layer(8.0), layer(4.0), layer(2.0), layer(1.0)],
+                }),
+                720,
+            )
+            .is_err());
+        client1.wait_for_client_to_process();
+        assert_eq!((true, vec![true, false, false]), active_layers(&client1));
+
+        client1.client.set_video_encoding_config(None, 720).unwrap();
+        client1.wait_for_client_to_process();
+        assert_eq!((false, vec![]), active_layers(&client1));
+
+        client1.disconnect_and_wait_until_ended();
+    }
+
     #[test]
     fn group_ring() {
         let client1 = TestClient::new(vec![1], 1, None);
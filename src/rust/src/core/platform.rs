@@ -10,7 +10,7 @@ use std::fmt;
 use std::time::Duration;
 
 use crate::common::{ApplicationEvent, CallDirection, CallId, CallMediaType, DeviceId, Result};
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::core::bandwidth_mode::BandwidthPolicy;
 use crate::core::call::Call;
 use crate::core::connection::{Connection, ConnectionType};
 use crate::core::{group_call, signaling};
@@ -48,7 +48,7 @@ pub trait Platform: sfu::Delegate + fmt::Debug + fmt::Display + Send + Sized + '
         remote_device: DeviceId,
         connection_type: ConnectionType,
         signaling_version: signaling::Version,
-        bandwidth_mode: BandwidthMode,
+        bandwidth_policy: BandwidthPolicy,
         audio_levels_interval: Option<Duration>,
     ) -> Result<Connection<Self>>;
 
@@ -84,6 +84,10 @@ pub trait Platform: sfu::Delegate + fmt::Debug + fmt::Display + Send + Sized + '
         received_level: AudioLevel,
     ) -> Result<()>;
 
+    /// Notify the client application that the user seems to be
+    /// talking while muted (1:1 calls)
+    fn on_talking_while_muted(&self, remote_peer: &Self::AppRemotePeer) -> Result<()>;
+
     /// Send an offer to a remote peer using the signaling
     /// channel.  Offers are always broadcast to all devices.
     fn on_send_offer(
@@ -250,5 +254,17 @@ pub trait Platform: sfu::Delegate + fmt::Debug + fmt::Display + Send + Sized + '
     ) {
     }
 
+    /// Notify the client application that the user seems to be
+    /// talking while muted (group calls)
+    fn handle_talking_while_muted(&self, client_id: group_call::ClientId);
+
+    /// Notify the client application that a device (which may be the local
+    /// device) was put in the spotlight or taken out of it (group calls)
+    fn handle_spotlight_changed(
+        &self,
+        client_id: group_call::ClientId,
+        spotlight_demux_id: Option<DemuxId>,
+    );
+
     fn handle_ended(&self, client_id: group_call::ClientId, reason: group_call::EndReason);
 }
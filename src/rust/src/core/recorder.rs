@@ -0,0 +1,842 @@
+//
+// Copyright 2022 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Record the decoded media of a call.
+//!
+//! A Recorder takes decoded video frames (it is a VideoSink, and a group
+//! call can be given one with set_recorder) and decoded audio samples and
+//! hands them to a Muxer on a thread of its own, so that the threads
+//! giving it media (the WebRTC decoding and audio threads) never wait on a
+//! lock or a write.  The RawMuxer writes raw I420 and 16-bit PCM files
+//! along with a JSON index of where each chunk is and when it was
+//! received.  Other containers can be added by implementing Muxer.
+//!
+//! Only group calls can be recorded (see group_call::Client::set_recorder).
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::webrtc::media::{VideoFrame, VideoRotation, VideoSink};
+
+/// Mixed audio is written this long after it's received so that every
+/// participant's samples for the same moment have arrived.  A track whose
+/// samples stray further than this from when they arrive (such as after
+/// it stopped for a while) starts over at the arrival time.
+const MIX_DELAY: Duration = Duration::from_millis(100);
+
+/// How many chunks of media can wait for the writer thread before more
+/// are dropped.  Audio comes in chunks of 10ms, so this is a couple of
+/// seconds of audio for one participant.
+const MAX_PENDING_CHUNKS: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordingLayout {
+    /// One video and one audio stream for each participant (track).
+    PerParticipant,
+    /// All audio mixed into one stream.  Video is still one stream for
+    /// each participant; laying it out is up to whoever plays it back.
+    Mixed,
+}
+
+/// Where recorded media goes.  Streams are identified by name
+/// (such as "video-1" or "audio-mixed").
+pub trait Muxer: Send {
+    fn write_video_frame(
+        &mut self,
+        stream: &str,
+        timestamp: Duration,
+        width: u32,
+        height: u32,
+        rotation: VideoRotation,
+        i420: &[u8],
+    ) -> io::Result<()>;
+
+    fn write_audio_samples(
+        &mut self,
+        stream: &str,
+        timestamp: Duration,
+        sample_rate: u32,
+        channels: u16,
+        samples: &[i16],
+    ) -> io::Result<()>;
+
+    /// Called once when recording stops.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// Cloning a Recorder gives another handle to the same recording.
+#[derive(Clone)]
+pub struct Recorder {
+    start: Instant,
+    sender: mpsc::SyncSender<Chunk>,
+    dropped_chunks: Arc<AtomicU64>,
+    // Taken by finish().
+    writer_thread: Arc<Mutex<Option<thread::JoinHandle<io::Result<()>>>>>,
+}
+
+enum Chunk {
+    Video {
+        track_id: u32,
+        timestamp: Duration,
+        width: u32,
+        height: u32,
+        rotation: VideoRotation,
+        i420: Vec<u8>,
+    },
+    Audio {
+        track_id: u32,
+        timestamp: Duration,
+        sample_rate: u32,
+        channels: u16,
+        samples: Vec<i16>,
+    },
+    Finish,
+}
+
+// Owned by the writer thread.
+struct RecorderInner {
+    muxer: Box<dyn Muxer>,
+    layout: RecordingLayout,
+    // Only used for RecordingLayout::Mixed; created by the first samples.
+    mix: Option<AudioMix>,
+}
+
+// Samples are placed by how many came before them on the same track
+// rather than by when they arrived, so that jitter in delivery
+// doesn't cut gaps into (or overlap) a participant's audio.
+struct AudioMix {
+    sample_rate: u32,
+    channels: u16,
+    // Counted in frames (one sample for each channel) since the recording started.
+    written_frames: u64,
+    // Interleaved, starting at written_frames.
+    pending: VecDeque<i32>,
+    // Where the next samples of each track go.
+    next_frame_by_track_id: HashMap<u32, u64>,
+}
+
+impl AudioMix {
+    fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            written_frames: 0,
+            pending: VecDeque::new(),
+            next_frame_by_track_id: HashMap::new(),
+        }
+    }
+
+    fn frames_at(&self, timestamp: Duration) -> u64 {
+        (timestamp.as_micros() * u128::from(self.sample_rate) / 1_000_000) as u64
+    }
+
+    fn timestamp_of(&self, frames: u64) -> Duration {
+        Duration::from_micros(
+            (u128::from(frames) * 1_000_000 / u128::from(self.sample_rate)) as u64,
+        )
+    }
+
+    fn add(&mut self, track_id: u32, timestamp: Duration, samples: &[i16]) {
+        let channels = usize::from(self.channels);
+        let arrival_frame = self.frames_at(timestamp);
+        let max_drift = self.frames_at(MIX_DELAY);
+        let frame = match self.next_frame_by_track_id.get(&track_id) {
+            Some(&next_frame)
+                if next_frame + max_drift >= arrival_frame
+                    && next_frame <= arrival_frame + max_drift =>
+            {
+                next_frame
+            }
+            _ => arrival_frame,
+        };
+        self.next_frame_by_track_id
+            .insert(track_id, frame + (samples.len() / channels) as u64);
+
+        // Anything before written_frames arrived too late to be mixed.
+        let skipped_frames = self.written_frames.saturating_sub(frame);
+        let samples = samples
+            .get((skipped_frames as usize) * channels..)
+            .unwrap_or_default();
+        let offset = (frame.saturating_sub(self.written_frames) as usize) * channels;
+        if self.pending.len() < offset + samples.len() {
+            self.pending.resize(offset + samples.len(), 0);
+        }
+        for (mixed, sample) in self.pending.iter_mut().skip(offset).zip(samples) {
+            *mixed += i32::from(*sample);
+        }
+    }
+
+    // Takes the mixed samples before the given timestamp (or all of them, if None),
+    // along with the timestamp of the first one.
+    fn take(&mut self, before: Option<Duration>) -> Option<(Duration, Vec<i16>)> {
+        let channels = usize::from(self.channels);
+        let pending_frames = self.pending.len() / channels;
+        let frames = match before {
+            Some(before) => (self.frames_at(before).saturating_sub(self.written_frames) as usize)
+                .min(pending_frames),
+            None => pending_frames,
+        };
+        if frames == 0 {
+            return None;
+        }
+        let timestamp = self.timestamp_of(self.written_frames);
+        let samples = self
+            .pending
+            .drain(..frames * channels)
+            .map(|sample| sample.clamp(i16::MIN.into(), i16::MAX.into()) as i16)
+            .collect();
+        self.written_frames += frames as u64;
+        Some((timestamp, samples))
+    }
+}
+
+impl Recorder {
+    pub fn new(muxer: Box<dyn Muxer>, layout: RecordingLayout) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(MAX_PENDING_CHUNKS);
+        let mut inner = RecorderInner {
+            muxer,
+            layout,
+            mix: None,
+        };
+        let writer_thread = thread::spawn(move || inner.write_until_finished(receiver));
+        Self {
+            start: Instant::now(),
+            sender,
+            dropped_chunks: Arc::new(AtomicU64::new(0)),
+            writer_thread: Arc::new(Mutex::new(Some(writer_thread))),
+        }
+    }
+
+    // Never blocks.  If the writer thread has fallen behind, the chunk is dropped.
+    fn send(&self, chunk: Chunk) {
+        if let Err(mpsc::TrySendError::Full(_)) = self.sender.try_send(chunk) {
+            self.dropped_chunks.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The frame isn't rotated; the rotation is recorded instead.
+    /// The frame is copied before returning, so it can be dropped right away.
+    pub fn record_video_frame(&self, track_id: u32, frame: &VideoFrame) {
+        let timestamp = self.start.elapsed();
+        let width = frame.width();
+        let height = frame.height();
+        let mut i420 = vec![0u8; i420_size(width, height)];
+        frame.to_i420(&mut i420);
+        self.send(Chunk::Video {
+            track_id,
+            timestamp,
+            width,
+            height,
+            rotation: frame.metadata().rotation(),
+            i420,
+        });
+    }
+
+    /// Samples are interleaved if there is more than one channel.
+    pub fn record_audio_samples(
+        &self,
+        track_id: u32,
+        sample_rate: u32,
+        channels: u16,
+        samples: &[i16],
+    ) {
+        self.send(Chunk::Audio {
+            track_id,
+            timestamp: self.start.elapsed(),
+            sample_rate,
+            channels,
+            samples: samples.to_vec(),
+        });
+    }
+
+    /// Waits for everything recorded so far to be written and finishes the muxer.
+    /// Anything recorded afterward is dropped.
+    pub fn finish(&self) -> io::Result<()> {
+        let writer_thread = match self.writer_thread.lock() {
+            Ok(mut writer_thread) => writer_thread.take(),
+            Err(_) => return Err(io::ErrorKind::Other.into()),
+        };
+        let writer_thread = match writer_thread {
+            Some(writer_thread) => writer_thread,
+            None => return Ok(()),
+        };
+        // Unlike the media, this waits for room.
+        let _ = self.sender.send(Chunk::Finish);
+        let result = writer_thread
+            .join()
+            .unwrap_or_else(|_| Err(io::ErrorKind::Other.into()));
+        let dropped_chunks = self.dropped_chunks.load(Ordering::Relaxed);
+        if dropped_chunks > 0 {
+            warn!(
+                "Dropped {} chunks of recorded media because writing fell behind",
+                dropped_chunks
+            );
+        }
+        result
+    }
+}
+
+impl RecorderInner {
+    // Also finishes if every Recorder is dropped without calling finish().
+    fn write_until_finished(&mut self, receiver: mpsc::Receiver<Chunk>) -> io::Result<()> {
+        for chunk in receiver {
+            match chunk {
+                Chunk::Video {
+                    track_id,
+                    timestamp,
+                    width,
+                    height,
+                    rotation,
+                    i420,
+                } => {
+                    if let Err(err) = self.muxer.write_video_frame(
+                        &format!("video-{}", track_id),
+                        timestamp,
+                        width,
+                        height,
+                        rotation,
+                        &i420,
+                    ) {
+                        warn!("Failed to record video frame: {:?}", err);
+                    }
+                }
+                Chunk::Audio {
+                    track_id,
+                    timestamp,
+                    sample_rate,
+                    channels,
+                    samples,
+                } => {
+                    if let Err(err) = self.record_audio_samples(
+                        track_id,
+                        timestamp,
+                        sample_rate,
+                        channels,
+                        &samples,
+                    ) {
+                        warn!("Failed to record audio samples: {:?}", err);
+                    }
+                }
+                Chunk::Finish => break,
+            }
+        }
+        self.flush_mix(None)?;
+        self.muxer.finish()
+    }
+
+    fn record_audio_samples(
+        &mut self,
+        track_id: u32,
+        timestamp: Duration,
+        sample_rate: u32,
+        channels: u16,
+        samples: &[i16],
+    ) -> io::Result<()> {
+        match self.layout {
+            RecordingLayout::PerParticipant => self.muxer.write_audio_samples(
+                &format!("audio-{}", track_id),
+                timestamp,
+                sample_rate,
+                channels,
+                samples,
+            ),
+            RecordingLayout::Mixed => {
+                if channels == 0 || sample_rate == 0 {
+                    return Ok(());
+                }
+                let mix = self
+                    .mix
+                    .get_or_insert_with(|| AudioMix::new(sample_rate, channels));
+                if (mix.sample_rate, mix.channels) != (sample_rate, channels) {
+                    warn!(
+                        "Not mixing audio from track {} with a different format: {:?}",
+                        track_id,
+                        (sample_rate, channels)
+                    );
+                    return Ok(());
+                }
+                mix.add(track_id, timestamp, samples);
+                self.flush_mix(Some(timestamp))
+            }
+        }
+    }
+
+    // Writes the mixed samples older than MIX_DELAY (or all of them, if now is None).
+    fn flush_mix(&mut self, now: Option<Duration>) -> io::Result<()> {
+        let mix = match self.mix.as_mut() {
+            Some(mix) => mix,
+            None => return Ok(()),
+        };
+        let (sample_rate, channels) = (mix.sample_rate, mix.channels);
+        if let Some((timestamp, samples)) = mix.take(now.map(|now| now.saturating_sub(MIX_DELAY))) {
+            self.muxer.write_audio_samples(
+                "audio-mixed",
+                timestamp,
+                sample_rate,
+                channels,
+                &samples,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl VideoSink for Recorder {
+    fn on_video_frame(&self, track_id: u32, frame: VideoFrame) {
+        self.record_video_frame(track_id, &frame);
+    }
+
+    fn box_clone(&self) -> Box<dyn VideoSink> {
+        Box::new(self.clone())
+    }
+}
+
+pub fn i420_size(width: u32, height: u32) -> usize {
+    let (width, height) = (width as usize, height as usize);
+    // Chroma planes are half the size, rounded up.
+    let chroma_width = width / 2 + width % 2;
+    let chroma_height = height / 2 + height % 2;
+    (width * height) + (2 * chroma_width * chroma_height)
+}
+
+#[derive(Serialize, Debug)]
+struct RawIndex {
+    streams: Vec<RawStreamIndex>,
+}
+
+#[derive(Serialize, Debug)]
+struct RawStreamIndex {
+    name: String,
+    file: String,
+    #[serde(flatten)]
+    kind: RawStreamKind,
+    chunks: Vec<RawChunkIndex>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum RawStreamKind {
+    Video,
+    Audio { sample_rate: u32, channels: u16 },
+}
+
+#[derive(Serialize, Debug)]
+struct RawChunkIndex {
+    timestamp_us: u64,
+    offset: u64,
+    length: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rotation: Option<u32>,
+}
+
+struct RawStream {
+    writer: BufWriter<File>,
+    offset: u64,
+    index: RawStreamIndex,
+}
+
+/// Writes each stream to its own file of raw I420 (video) or
+/// interleaved 16-bit little-endian PCM (audio), plus index.json.
+pub struct RawMuxer {
+    directory: PathBuf,
+    streams: HashMap<String, RawStream>,
+}
+
+impl RawMuxer {
+    /// The directory must already exist.
+    pub fn new(directory: impl AsRef<Path>) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+            streams: HashMap::new(),
+        }
+    }
+
+    fn write_chunk(
+        &mut self,
+        stream: &str,
+        kind: RawStreamKind,
+        timestamp: Duration,
+        data: &[u8],
+        video: Option<(u32, u32, VideoRotation)>,
+    ) -> io::Result<()> {
+        if !self.streams.contains_key(stream) {
+            let file = match kind {
+                RawStreamKind::Video => format!("{}.i420", stream),
+                RawStreamKind::Audio { .. } => format!("{}.pcm", stream),
+            };
+            let writer = BufWriter::new(File::create(self.directory.join(&file))?);
+            self.streams.insert(
+                stream.to_string(),
+                RawStream {
+                    writer,
+                    offset: 0,
+                    index: RawStreamIndex {
+                        name: stream.to_string(),
+                        file,
+                        kind,
+                        chunks: Vec::new(),
+                    },
+                },
+            );
+        }
+        let raw_stream = self.streams.get_mut(stream).unwrap();
+        raw_stream.writer.write_all(data)?;
+        raw_stream.index.chunks.push(RawChunkIndex {
+            timestamp_us: timestamp.as_micros() as u64,
+            offset: raw_stream.offset,
+            length: data.len() as u64,
+            width: video.map(|(width, _, _)| width),
+            height: video.map(|(_, height, _)| height),
+            rotation: video.map(|(_, _, rotation)| rotation as u32),
+        });
+        raw_stream.offset += data.len() as u64;
+        Ok(())
+    }
+}
+
+impl Muxer for RawMuxer {
+    fn write_video_frame(
+        &mut self,
+        stream: &str,
+        timestamp: Duration,
+        width: u32,
+        height: u32,
+        rotation: VideoRotation,
+        i420: &[u8],
+    ) -> io::Result<()> {
+        self.write_chunk(
+            stream,
+            RawStreamKind::Video,
+            timestamp,
+            i420,
+            Some((width, height, rotation)),
+        )
+    }
+
+    fn write_audio_samples(
+        &mut self,
+        stream: &str,
+        timestamp: Duration,
+        sample_rate: u32,
+        channels: u16,
+        samples: &[i16],
+    ) -> io::Result<()> {
+        let data: Vec<u8> = samples
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        self.write_chunk(
+            stream,
+            RawStreamKind::Audio {
+                sample_rate,
+                channels,
+            },
+            timestamp,
+            &data,
+            None,
+        )
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let mut streams: Vec<RawStream> = self.streams.drain().map(|(_, stream)| stream).collect();
+        streams.sort_by(|a, b| a.index.name.cmp(&b.index.name));
+        let mut index = RawIndex {
+            streams: Vec::with_capacity(streams.len()),
+        };
+        for mut stream in streams {
+            stream.writer.flush()?;
+            index.streams.push(stream.index);
+        }
+        let file = File::create(self.directory.join("index.json"))?;
+        serde_json::to_writer_pretty(file, &index)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct FakeMuxer {
+        audio: Arc<Mutex<Vec<(String, Duration, Vec<i16>)>>>,
+    }
+
+    impl Muxer for FakeMuxer {
+        fn write_video_frame(
+            &mut self,
+            _stream: &str,
+            _timestamp: Duration,
+            _width: u32,
+            _height: u32,
+            _rotation: VideoRotation,
+            _i420: &[u8],
+        ) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn write_audio_samples(
+            &mut self,
+            stream: &str,
+            timestamp: Duration,
+            _sample_rate: u32,
+            _channels: u16,
+            samples: &[i16],
+        ) -> io::Result<()> {
+            self.audio
+                .lock()
+                .unwrap()
+                .push((stream.to_string(), timestamp, samples.to_vec()));
+            Ok(())
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn inner(muxer: FakeMuxer, layout: RecordingLayout) -> RecorderInner {
+        RecorderInner {
+            muxer: Box::new(muxer),
+            layout,
+            mix: None,
+        }
+    }
+
+    #[test]
+    fn mixes_audio() {
+        let muxer = FakeMuxer::default();
+        let audio = muxer.audio.clone();
+        let mut inner = inner(muxer, RecordingLayout::Mixed);
+        let ms = Duration::from_millis;
+        // One sample per millisecond keeps the positions easy to follow.
+        let rate = 1000;
+
+        inner
+            .record_audio_samples(1, ms(0), rate, 1, &[1, 2, i16::MAX])
+            .unwrap();
+        inner
+            .record_audio_samples(2, ms(1), rate, 1, &[10, 20, 1])
+            .unwrap();
+        // Not mixed because of the format
+        inner
+            .record_audio_samples(3, ms(1), 16000, 1, &[100, 100, 100, 100])
+            .unwrap();
+        // Arrived late, but follows what track 1 sent before.
+        inner.record_audio_samples(1, ms(8), rate, 1, &[7]).unwrap();
+        assert!(audio.lock().unwrap().is_empty());
+
+        // Track 1 stopped for a while, so it starts over when it arrives,
+        // and everything more than MIX_DELAY before that is written.
+        inner
+            .record_audio_samples(1, ms(150), rate, 1, &[9])
+            .unwrap();
+        let mut expected_start = vec![1, 12, i16::MAX, 8];
+        expected_start.resize(50, 0);
+        assert_eq!(
+            vec![("audio-mixed".to_string(), ms(0), expected_start.clone())],
+            *audio.lock().unwrap()
+        );
+
+        // Too late to be mixed
+        inner
+            .record_audio_samples(4, ms(30), rate, 1, &[3, 3])
+            .unwrap();
+
+        inner.flush_mix(None).unwrap();
+        let mut expected_rest = vec![0; 101];
+        expected_rest[100] = 9;
+        assert_eq!(
+            vec![
+                ("audio-mixed".to_string(), ms(0), expected_start),
+                ("audio-mixed".to_string(), ms(50), expected_rest),
+            ],
+            *audio.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn per_participant_audio() {
+        let muxer = FakeMuxer::default();
+        let audio = muxer.audio.clone();
+        let mut inner = inner(muxer, RecordingLayout::PerParticipant);
+        let ms = Duration::from_millis;
+
+        inner
+            .record_audio_samples(1, ms(0), 48000, 1, &[1, 2])
+            .unwrap();
+        inner
+            .record_audio_samples(2, ms(5), 48000, 1, &[3])
+            .unwrap();
+        assert_eq!(
+            vec![
+                ("audio-1".to_string(), ms(0), vec![1, 2]),
+                ("audio-2".to_string(), ms(5), vec![3]),
+            ],
+            *audio.lock().unwrap()
+        );
+    }
+
+    // Stops at the first write until unblock is dropped.
+    struct BlockingMuxer {
+        muxer: FakeMuxer,
+        writing: mpsc::Sender<()>,
+        unblock: mpsc::Receiver<()>,
+    }
+
+    impl Muxer for BlockingMuxer {
+        fn write_video_frame(
+            &mut self,
+            stream: &str,
+            timestamp: Duration,
+            width: u32,
+            height: u32,
+            rotation: VideoRotation,
+            i420: &[u8],
+        ) -> io::Result<()> {
+            self.muxer
+                .write_video_frame(stream, timestamp, width, height, rotation, i420)
+        }
+
+        fn write_audio_samples(
+            &mut self,
+            stream: &str,
+            timestamp: Duration,
+            sample_rate: u32,
+            channels: u16,
+            samples: &[i16],
+        ) -> io::Result<()> {
+            let _ = self.writing.send(());
+            let _ = self.unblock.recv();
+            self.muxer
+                .write_audio_samples(stream, timestamp, sample_rate, channels, samples)
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            self.muxer.finish()
+        }
+    }
+
+    #[test]
+    fn drops_media_while_writing_falls_behind() {
+        let muxer = FakeMuxer::default();
+        let audio = muxer.audio.clone();
+        let (writing_sender, writing) = mpsc::channel();
+        let (unblock, unblock_receiver) = mpsc::channel();
+        let recorder = Recorder::new(
+            Box::new(BlockingMuxer {
+                muxer,
+                writing: writing_sender,
+                unblock: unblock_receiver,
+            }),
+            RecordingLayout::PerParticipant,
+        );
+
+        recorder.record_audio_samples(1, 48000, 1, &[1]);
+        writing.recv().unwrap();
+        // The writer thread is stuck on the first chunk, so only this many can wait.
+        for _ in 0..MAX_PENDING_CHUNKS + 5 {
+            recorder.record_audio_samples(1, 48000, 1, &[2]);
+        }
+        assert_eq!(5, recorder.dropped_chunks.load(Ordering::Relaxed));
+
+        drop(unblock);
+        recorder.finish().unwrap();
+        assert_eq!(MAX_PENDING_CHUNKS + 1, audio.lock().unwrap().len());
+
+        // Dropped after finishing
+        recorder.record_audio_samples(1, 48000, 1, &[3]);
+        recorder.finish().unwrap();
+        assert_eq!(MAX_PENDING_CHUNKS + 1, audio.lock().unwrap().len());
+    }
+
+    #[test]
+    fn raw_muxer() {
+        let directory =
+            std::env::temp_dir().join(format!("ringrtc-recorder-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let mut muxer = RawMuxer::new(&directory);
+        muxer
+            .write_video_frame(
+                "video-1",
+                Duration::from_millis(33),
+                2,
+                2,
+                VideoRotation::Clockwise90,
+                &[1, 2, 3, 4, 5, 6],
+            )
+            .unwrap();
+        muxer
+            .write_audio_samples("audio-1", Duration::from_millis(10), 48000, 1, &[1, -1])
+            .unwrap();
+        muxer
+            .write_audio_samples("audio-1", Duration::from_millis(20), 48000, 1, &[2])
+            .unwrap();
+        muxer.finish().unwrap();
+
+        assert_eq!(
+            vec![1, 2, 3, 4, 5, 6],
+            std::fs::read(directory.join("video-1.i420")).unwrap()
+        );
+        assert_eq!(
+            vec![1, 0, 0xff, 0xff, 2, 0],
+            std::fs::read(directory.join("audio-1.pcm")).unwrap()
+        );
+        let index: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(directory.join("index.json")).unwrap()).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "streams": [
+                    {
+                        "name": "audio-1",
+                        "file": "audio-1.pcm",
+                        "kind": "audio",
+                        "sample_rate": 48000,
+                        "channels": 1,
+                        "chunks": [
+                            {"timestamp_us": 10000, "offset": 0, "length": 4},
+                            {"timestamp_us": 20000, "offset": 4, "length": 2},
+                        ],
+                    },
+                    {
+                        "name": "video-1",
+                        "file": "video-1.i420",
+                        "kind": "video",
+                        "chunks": [
+                            {
+                                "timestamp_us": 33000,
+                                "offset": 0,
+                                "length": 6,
+                                "width": 2,
+                                "height": 2,
+                                "rotation": 90,
+                            },
+                        ],
+                    },
+                ],
+            }),
+            index
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
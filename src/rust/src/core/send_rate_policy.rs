@@ -0,0 +1,260 @@
+//
+// Copyright 2022 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Decide how much to send in a group call.
+//!
+//! A SendRatePolicy turns what the group call knows (how many others
+//! are in the call, what they asked the SFU for, what the SFU thinks
+//! the network can carry, and hints from the app) into the SendRates
+//! given to WebRTC.  The DefaultSendRatePolicy is used unless the app
+//! provides its own.
+
+use std::cmp::min;
+
+use crate::{common::units::DataRate, webrtc::peer_connection::SendRates};
+
+const SMALL_CALL_MAX_SEND_RATE: DataRate = DataRate::from_kbps(1000);
+
+// This is the smallest rate at which WebRTC seems to still send VGA.
+const LARGE_CALL_MAX_SEND_RATE: DataRate = DataRate::from_kbps(671);
+
+// Use a higher bitrate for screen sharing
+const SCREENSHARE_MIN_SEND_RATE: DataRate = DataRate::from_mbps(2);
+const SCREENSHARE_START_SEND_RATE: DataRate = DataRate::from_mbps(2);
+const SCREENSHARE_MAX_SEND_RATE: DataRate = DataRate::from_mbps(5);
+// Once the minimum has been given up, the SFU's target has to clear it by
+// this much before it is forced again, so a target hovering around the
+// minimum doesn't toggle it on every stats message.
+const SCREENSHARE_MIN_RESTORE_SEND_RATE: DataRate = DataRate::from_kbps(2500);
+
+// When nobody wants our video, leave enough for audio and a little video
+// so that sending resumes quickly once someone asks for it.
+const NO_VIDEO_REQUESTED_MAX_SEND_RATE: DataRate = DataRate::from_kbps(100);
+const THUMBNAIL_REQUESTED_MAX_SEND_RATE: DataRate = DataRate::from_kbps(250);
+const THUMBNAIL_MAX_HEIGHT: u16 = 180;
+const MEDIUM_REQUESTED_MAX_SEND_RATE: DataRate = DataRate::from_kbps(500);
+const MEDIUM_MAX_HEIGHT: u16 = 360;
+
+/// Hints from the app about the state of the device.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeviceConditions {
+    pub low_battery: bool,
+    /// The device is hot enough that the OS is (or soon will be) throttling it.
+    pub thermal_throttling: bool,
+}
+
+impl DeviceConditions {
+    fn should_conserve(&self) -> bool {
+        self.low_battery || self.thermal_throttling
+    }
+}
+
+/// Everything the group call knows that may affect how much to send.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SendRateInputs {
+    /// Never 0; when the local device is alone, the group call sends as
+    /// little as possible without asking the policy.
+    pub joined_member_count: usize,
+    pub sharing_screen: bool,
+    /// The height the SFU wants us to send to satisfy the requests of all
+    /// the other devices.  None until the SFU tells us.
+    pub requested_height: Option<u16>,
+    /// The rate the SFU's congestion control thinks we can send at.
+    /// None until the SFU tells us.
+    pub target_send_rate: Option<DataRate>,
+    /// Set by the app with a BandwidthMode or BandwidthPolicy.  None means unlimited.
+    pub max_send_rate: Option<DataRate>,
+    pub device_conditions: DeviceConditions,
+    /// What was last given to WebRTC, so a policy can avoid flapping.
+    pub current_send_rates: SendRates,
+}
+
+pub trait SendRatePolicy {
+    fn compute_send_rates(&self, inputs: &SendRateInputs) -> SendRates;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultSendRatePolicy;
+
+impl SendRatePolicy for DefaultSendRatePolicy {
+    fn compute_send_rates(&self, inputs: &SendRateInputs) -> SendRates {
+        let mut send_rates = if inputs.sharing_screen {
+            SendRates {
+                min: Some(SCREENSHARE_MIN_SEND_RATE),
+                start: Some(SCREENSHARE_START_SEND_RATE),
+                max: Some(SCREENSHARE_MAX_SEND_RATE),
+            }
+        } else {
+            let mut max = if inputs.joined_member_count <= 7 {
+                SMALL_CALL_MAX_SEND_RATE
+            } else {
+                LARGE_CALL_MAX_SEND_RATE
+            };
+            // Don't send more than anyone asked for.
+            // Screen sharing is exempt because the SFU may not have caught up yet.
+            match inputs.requested_height {
+                Some(0) => max = min(max, NO_VIDEO_REQUESTED_MAX_SEND_RATE),
+                Some(height) if height <= THUMBNAIL_MAX_HEIGHT => {
+                    max = min(max, THUMBNAIL_REQUESTED_MAX_SEND_RATE)
+                }
+                Some(height) if height <= MEDIUM_MAX_HEIGHT => {
+                    max = min(max, MEDIUM_REQUESTED_MAX_SEND_RATE)
+                }
+                _ => {}
+            }
+            SendRates {
+                max: Some(max),
+                ..SendRates::default()
+            }
+        };
+
+        // If the SFU says the network can't carry the screen share minimum,
+        // forcing it would only cause congestion.
+        if let Some(target) = inputs.target_send_rate {
+            let min_given_up = inputs.current_send_rates.min.is_none()
+                && target < SCREENSHARE_MIN_RESTORE_SEND_RATE;
+            if target < SCREENSHARE_MIN_SEND_RATE || min_given_up {
+                send_rates.min = None;
+                send_rates.start = None;
+            }
+        }
+
+        if inputs.device_conditions.should_conserve() {
+            let cap = if inputs.sharing_screen {
+                SCREENSHARE_MIN_SEND_RATE
+            } else {
+                LARGE_CALL_MAX_SEND_RATE
+            };
+            send_rates.max = send_rates.max.map(|max| min(max, cap));
+        }
+
+        if let Some(cap) = inputs.max_send_rate {
+            if matches!(send_rates.min, Some(min) if min > cap) {
+                send_rates.min = None;
+            }
+            if matches!(send_rates.start, Some(start) if start > cap) {
+                send_rates.start = None;
+            }
+            send_rates.max = send_rates.max.map(|max| min(max, cap));
+        }
+
+        send_rates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(joined_member_count: usize) -> SendRateInputs {
+        SendRateInputs {
+            joined_member_count,
+            sharing_screen: false,
+            requested_height: None,
+            target_send_rate: None,
+            max_send_rate: None,
+            device_conditions: DeviceConditions::default(),
+            current_send_rates: SendRates::default(),
+        }
+    }
+
+    fn max_kbps(inputs: &SendRateInputs) -> Option<u64> {
+        DefaultSendRatePolicy
+            .compute_send_rates(inputs)
+            .max
+            .map(DataRate::as_kbps)
+    }
+
+    #[test]
+    fn member_count() {
+        assert_eq!(Some(1000), max_kbps(&inputs(1)));
+        assert_eq!(Some(1000), max_kbps(&inputs(7)));
+        assert_eq!(Some(671), max_kbps(&inputs(8)));
+    }
+
+    #[test]
+    fn requested_height() {
+        let mut inputs = inputs(3);
+        inputs.requested_height = Some(720);
+        assert_eq!(Some(1000), max_kbps(&inputs));
+        inputs.requested_height = Some(360);
+        assert_eq!(Some(500), max_kbps(&inputs));
+        inputs.requested_height = Some(90);
+        assert_eq!(Some(250), max_kbps(&inputs));
+        inputs.requested_height = Some(0);
+        assert_eq!(Some(100), max_kbps(&inputs));
+
+        // Screen sharing ignores it.
+        inputs.sharing_screen = true;
+        assert_eq!(Some(5000), max_kbps(&inputs));
+    }
+
+    #[test]
+    fn screen_sharing() {
+        let mut inputs = inputs(3);
+        inputs.sharing_screen = true;
+        assert_eq!(
+            SendRates {
+                min: Some(DataRate::from_mbps(2)),
+                start: Some(DataRate::from_mbps(2)),
+                max: Some(DataRate::from_mbps(5)),
+            },
+            DefaultSendRatePolicy.compute_send_rates(&inputs)
+        );
+
+        inputs.target_send_rate = Some(DataRate::from_kbps(800));
+        assert_eq!(
+            SendRates {
+                min: None,
+                start: None,
+                max: Some(DataRate::from_mbps(5)),
+            },
+            DefaultSendRatePolicy.compute_send_rates(&inputs)
+        );
+
+        let min_kbps = |inputs: &SendRateInputs| {
+            DefaultSendRatePolicy
+                .compute_send_rates(inputs)
+                .min
+                .map(DataRate::as_kbps)
+        };
+        // Given up, the minimum needs some headroom to come back...
+        inputs.target_send_rate = Some(DataRate::from_kbps(2200));
+        assert_eq!(None, min_kbps(&inputs));
+        inputs.target_send_rate = Some(DataRate::from_kbps(2500));
+        assert_eq!(Some(2000), min_kbps(&inputs));
+        // ...but while forced, it stays until the target drops below it.
+        inputs.current_send_rates = DefaultSendRatePolicy.compute_send_rates(&inputs);
+        inputs.target_send_rate = Some(DataRate::from_kbps(2200));
+        assert_eq!(Some(2000), min_kbps(&inputs));
+        inputs.target_send_rate = Some(DataRate::from_kbps(1900));
+        assert_eq!(None, min_kbps(&inputs));
+        inputs.target_send_rate = Some(DataRate::from_kbps(800));
+
+        inputs.device_conditions.thermal_throttling = true;
+        assert_eq!(Some(2000), max_kbps(&inputs));
+    }
+
+    #[test]
+    fn max_send_rate_and_device_conditions() {
+        let mut inputs = inputs(3);
+        inputs.device_conditions.low_battery = true;
+        assert_eq!(Some(671), max_kbps(&inputs));
+        inputs.max_send_rate = Some(DataRate::from_kbps(300));
+        assert_eq!(Some(300), max_kbps(&inputs));
+        inputs.max_send_rate = Some(DataRate::from_kbps(125));
+        assert_eq!(Some(125), max_kbps(&inputs));
+
+        inputs.sharing_screen = true;
+        assert_eq!(
+            SendRates {
+                min: None,
+                start: None,
+                max: Some(DataRate::from_kbps(125)),
+            },
+            DefaultSendRatePolicy.compute_send_rates(&inputs)
+        );
+    }
+}
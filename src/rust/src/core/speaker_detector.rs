@@ -0,0 +1,423 @@
+//
+// Copyright 2022 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Detect who is speaking from audio levels.
+//!
+//! The SpeakerDetector uses the levels of received audio.  Unlike the
+//! speaker sent by the SFU, it can report several devices speaking at
+//! the same time, and works without the SFU.
+//!
+//! The TalkingWhileMutedDetector uses the level of captured audio to
+//! notice when the user is talking while muted.
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use crate::webrtc::peer_connection::{AudioLevel, ReceivedAudioLevel};
+
+/// Tuning for the SpeakerDetector.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpeakerDetectorConfig {
+    /// A smoothed level at or above this starts speech.
+    pub start_level: AudioLevel,
+    /// A smoothed level below this ends speech.
+    /// Lower than start_level so that a level hovering around
+    /// the threshold doesn't flap.
+    pub stop_level: AudioLevel,
+    /// How much of each new level goes into the smoothed level, from
+    /// 0.0 (ignore new levels) to 1.0 (no smoothing).
+    pub smoothing_factor: f32,
+    /// How long the level must stay high before a device is speaking.
+    pub min_speech_duration: Duration,
+    /// How long the level must stay low before a device stops speaking.
+    pub min_silence_duration: Duration,
+}
+
+impl Default for SpeakerDetectorConfig {
+    fn default() -> Self {
+        Self {
+            // Audio levels go from 0 to 32767.
+            start_level: 1000,
+            stop_level: 500,
+            smoothing_factor: 0.5,
+            min_speech_duration: Duration::from_millis(300),
+            min_silence_duration: Duration::from_millis(800),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct DeviceState {
+    smoothed_level: f32,
+    speaking: bool,
+    // When the level crossed the threshold that would change `speaking`.
+    crossed_since: Option<Instant>,
+    // When the device last started or stopped speaking.
+    last_speaking_time: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+pub struct SpeakerDetector {
+    config: SpeakerDetectorConfig,
+    devices: HashMap<u32, DeviceState>,
+}
+
+impl SpeakerDetector {
+    pub fn new(config: SpeakerDetectorConfig) -> Self {
+        Self {
+            config,
+            devices: HashMap::new(),
+        }
+    }
+
+    /// Feeds in the latest levels of all received audio and returns
+    /// the demux IDs that started or stopped speaking.
+    /// Devices without a level are forgotten.
+    pub fn update(&mut self, now: Instant, received_levels: &[ReceivedAudioLevel]) -> Vec<u32> {
+        let mut changed = Vec::new();
+
+        let demux_ids: HashSet<u32> = received_levels
+            .iter()
+            .map(|received| received.demux_id)
+            .collect();
+        self.devices.retain(|demux_id, device| {
+            let keep = demux_ids.contains(demux_id);
+            if !keep && device.speaking {
+                changed.push(*demux_id);
+            }
+            keep
+        });
+
+        for received in received_levels {
+            let device = self.devices.entry(received.demux_id).or_default();
+            device.smoothed_level +=
+                self.config.smoothing_factor * (received.level as f32 - device.smoothed_level);
+
+            let (crossed, min_duration) = if device.speaking {
+                (
+                    device.smoothed_level < self.config.stop_level as f32,
+                    self.config.min_silence_duration,
+                )
+            } else {
+                (
+                    device.smoothed_level >= self.config.start_level as f32,
+                    self.config.min_speech_duration,
+                )
+            };
+            if !crossed {
+                device.crossed_since = None;
+                continue;
+            }
+            let crossed_since = *device.crossed_since.get_or_insert(now);
+            if now.saturating_duration_since(crossed_since) >= min_duration {
+                device.speaking = !device.speaking;
+                device.crossed_since = None;
+                device.last_speaking_time = Some(now);
+                changed.push(received.demux_id);
+            }
+        }
+        changed
+    }
+
+    pub fn is_speaking(&self, demux_id: u32) -> bool {
+        matches!(self.devices.get(&demux_id), Some(device) if device.speaking)
+    }
+
+    /// Who is talking right now.
+    pub fn speaking(&self) -> HashSet<u32> {
+        self.devices
+            .iter()
+            .filter(|(_, device)| device.speaking)
+            .map(|(demux_id, _)| *demux_id)
+            .collect()
+    }
+
+    /// When the device last started or stopped speaking.
+    pub fn last_speaking_time(&self, demux_id: u32) -> Option<Instant> {
+        self.devices.get(&demux_id)?.last_speaking_time
+    }
+
+    /// Devices that have spoken, those speaking now first (most recently
+    /// started first), then the rest by how recently they stopped.
+    /// Only changes when a device starts or stops speaking (or goes away),
+    /// so it doesn't churn with every level update.
+    pub fn recent_speakers(&self) -> Vec<u32> {
+        let mut speakers: Vec<(&u32, &DeviceState)> = self
+            .devices
+            .iter()
+            .filter(|(_, device)| device.last_speaking_time.is_some())
+            .collect();
+        speakers.sort_by(|(demux_id1, device1), (demux_id2, device2)| {
+            device2
+                .speaking
+                .cmp(&device1.speaking)
+                .then_with(|| device2.last_speaking_time.cmp(&device1.last_speaking_time))
+                .then_with(|| demux_id1.cmp(demux_id2))
+        });
+        speakers
+            .into_iter()
+            .map(|(demux_id, _)| *demux_id)
+            .collect()
+    }
+}
+
+/// Tuning for the TalkingWhileMutedDetector.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TalkingWhileMutedConfig {
+    /// A captured level at or above this counts as talking.
+    pub min_level: AudioLevel,
+    /// How long talking must go on before notifying.
+    pub min_duration: Duration,
+    /// Quiet shorter than this (such as between words) doesn't end talking.
+    pub max_pause: Duration,
+    /// How long to wait before notifying again if talking goes on.
+    pub min_interval: Duration,
+}
+
+impl Default for TalkingWhileMutedConfig {
+    fn default() -> Self {
+        Self {
+            min_level: 1500,
+            min_duration: Duration::from_millis(1500),
+            max_pause: Duration::from_millis(500),
+            min_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TalkingWhileMutedDetector {
+    config: TalkingWhileMutedConfig,
+    muted: bool,
+    talking_since: Option<Instant>,
+    last_talking: Option<Instant>,
+    last_notified: Option<Instant>,
+}
+
+impl TalkingWhileMutedDetector {
+    pub fn new(config: TalkingWhileMutedConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn set_config(&mut self, config: TalkingWhileMutedConfig) {
+        self.config = config;
+    }
+
+    /// Talking only counts while muted.  Changing it starts over.
+    pub fn set_muted(&mut self, muted: bool) {
+        if self.muted != muted {
+            self.muted = muted;
+            self.talking_since = None;
+            self.last_talking = None;
+            self.last_notified = None;
+        }
+    }
+
+    /// Feeds in the latest captured level and returns true if the user
+    /// should be told that they are talking while muted.
+    pub fn update(&mut self, now: Instant, captured_level: AudioLevel) -> bool {
+        if !self.muted {
+            return false;
+        }
+
+        let paused_too_long = matches!(
+            self.last_talking,
+            Some(last_talking) if now.saturating_duration_since(last_talking) > self.config.max_pause
+        );
+        if paused_too_long {
+            self.talking_since = None;
+            self.last_talking = None;
+        }
+        if captured_level >= self.config.min_level {
+            self.talking_since.get_or_insert(now);
+            self.last_talking = Some(now);
+        }
+
+        let talked_long_enough = matches!(
+            self.talking_since,
+            Some(talking_since) if now.saturating_duration_since(talking_since) >= self.config.min_duration
+        );
+        let notified_recently = matches!(
+            self.last_notified,
+            Some(last_notified) if now.saturating_duration_since(last_notified) < self.config.min_interval
+        );
+        if talked_long_enough && !notified_recently {
+            self.last_notified = Some(now);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(levels: &[(u32, AudioLevel)]) -> Vec<ReceivedAudioLevel> {
+        levels
+            .iter()
+            .map(|(demux_id, level)| ReceivedAudioLevel {
+                demux_id: *demux_id,
+                level: *level,
+            })
+            .collect()
+    }
+
+    fn unsmoothed() -> SpeakerDetector {
+        SpeakerDetector::new(SpeakerDetectorConfig {
+            smoothing_factor: 1.0,
+            ..SpeakerDetectorConfig::default()
+        })
+    }
+
+    #[test]
+    fn speech_must_last_min_duration() {
+        let mut detector = unsmoothed();
+        let start = Instant::now();
+        let ms = |millis| start + Duration::from_millis(millis);
+
+        assert!(detector.update(ms(0), &levels(&[(1, 5000)])).is_empty());
+        assert!(detector.update(ms(200), &levels(&[(1, 5000)])).is_empty());
+        assert!(!detector.is_speaking(1));
+        assert_eq!(vec![1], detector.update(ms(400), &levels(&[(1, 5000)])));
+        assert!(detector.is_speaking(1));
+
+        // A short blip doesn't start speech.
+        let mut detector = unsmoothed();
+        assert!(detector.update(ms(0), &levels(&[(2, 5000)])).is_empty());
+        assert!(detector.update(ms(200), &levels(&[(2, 0)])).is_empty());
+        assert!(detector.update(ms(400), &levels(&[(2, 5000)])).is_empty());
+        assert!(!detector.is_speaking(2));
+    }
+
+    #[test]
+    fn hysteresis() {
+        let mut detector = unsmoothed();
+        let start = Instant::now();
+        let ms = |millis| start + Duration::from_millis(millis);
+
+        detector.update(ms(0), &levels(&[(1, 5000)]));
+        detector.update(ms(300), &levels(&[(1, 5000)]));
+        assert!(detector.is_speaking(1));
+
+        // Between stop_level and start_level keeps speaking.
+        assert!(detector.update(ms(2000), &levels(&[(1, 700)])).is_empty());
+        assert!(detector.is_speaking(1));
+
+        // Silence must last a while.
+        assert!(detector.update(ms(3000), &levels(&[(1, 0)])).is_empty());
+        assert!(detector.update(ms(3500), &levels(&[(1, 0)])).is_empty());
+        assert_eq!(vec![1], detector.update(ms(3800), &levels(&[(1, 0)])));
+        assert!(!detector.is_speaking(1));
+    }
+
+    #[test]
+    fn smoothing() {
+        let mut detector = SpeakerDetector::default();
+        let start = Instant::now();
+        let ms = |millis| start + Duration::from_millis(millis);
+
+        // One loud sample is halved by smoothing.
+        detector.update(ms(0), &levels(&[(1, 1500)]));
+        detector.update(ms(400), &levels(&[(1, 0)]));
+        assert!(!detector.is_speaking(1));
+    }
+
+    #[test]
+    fn multiple_speakers_and_ranking() {
+        let mut detector = unsmoothed();
+        let start = Instant::now();
+        let ms = |millis| start + Duration::from_millis(millis);
+
+        detector.update(ms(0), &levels(&[(1, 2000), (2, 0), (3, 0)]));
+        detector.update(ms(300), &levels(&[(1, 2000), (2, 0), (3, 0)]));
+        detector.update(ms(400), &levels(&[(1, 0), (2, 4000), (3, 0)]));
+        detector.update(ms(700), &levels(&[(1, 0), (2, 4000), (3, 0)]));
+        detector.update(ms(800), &levels(&[(1, 2000), (2, 4000), (3, 0)]));
+        assert_eq!(
+            vec![1, 2].into_iter().collect::<HashSet<_>>(),
+            detector.speaking()
+        );
+        // The one that started most recently first, however loud.
+        assert_eq!(vec![2, 1], detector.recent_speakers());
+        detector.update(ms(900), &levels(&[(1, 8000), (2, 1000), (3, 0)]));
+        assert_eq!(vec![2, 1], detector.recent_speakers());
+
+        detector.update(ms(1000), &levels(&[(1, 2000), (2, 0), (3, 0)]));
+        detector.update(ms(2000), &levels(&[(1, 2000), (2, 0), (3, 0)]));
+        assert_eq!(
+            vec![1].into_iter().collect::<HashSet<_>>(),
+            detector.speaking()
+        );
+        assert_eq!(vec![1, 2], detector.recent_speakers());
+
+        detector.update(ms(3000), &levels(&[(1, 0), (3, 0)]));
+        assert_eq!(
+            vec![1],
+            detector.update(ms(4000), &levels(&[(1, 0), (3, 0)]))
+        );
+        // 2 is gone; 1 stopped most recently.
+        assert_eq!(vec![1], detector.recent_speakers());
+        assert!(detector.speaking().is_empty());
+
+        // Removing a speaking device is a change.
+        detector.update(ms(5000), &levels(&[(3, 2000)]));
+        detector.update(ms(5500), &levels(&[(3, 2000)]));
+        assert!(detector.is_speaking(3));
+        assert_eq!(vec![3], detector.update(ms(6000), &levels(&[])));
+    }
+
+    #[test]
+    fn talking_while_muted() {
+        let mut detector = TalkingWhileMutedDetector::default();
+        let start = Instant::now();
+        // Feeds a level every 200ms and returns when it notified.
+        let feed = |detector: &mut TalkingWhileMutedDetector, from: u64, to: u64, level| {
+            (from..to)
+                .step_by(200)
+                .filter(|millis| detector.update(start + Duration::from_millis(*millis), level))
+                .collect::<Vec<u64>>()
+        };
+
+        // Not muted
+        assert!(feed(&mut detector, 0, 3000, 5000).is_empty());
+
+        detector.set_muted(true);
+        assert_eq!(vec![4600], feed(&mut detector, 3000, 5000, 5000));
+        // A pause between words is fine, and notifying again waits a while.
+        assert!(feed(&mut detector, 5000, 5400, 0).is_empty());
+        assert_eq!(vec![14600], feed(&mut detector, 5400, 16000, 5000));
+
+        // A long pause starts over.
+        assert!(feed(&mut detector, 16000, 17000, 0).is_empty());
+        assert!(feed(&mut detector, 17000, 18400, 5000).is_empty());
+
+        // Unmuting starts over.
+        detector.set_muted(false);
+        detector.set_muted(true);
+        assert_eq!(vec![20000], feed(&mut detector, 18400, 21000, 5000));
+    }
+
+    #[test]
+    fn talking_while_muted_config() {
+        let mut detector = TalkingWhileMutedDetector::new(TalkingWhileMutedConfig {
+            min_level: 100,
+            min_duration: Duration::from_millis(200),
+            ..TalkingWhileMutedConfig::default()
+        });
+        let start = Instant::now();
+        let ms = |millis| start + Duration::from_millis(millis);
+
+        detector.set_muted(true);
+        assert!(!detector.update(ms(0), 200));
+        assert!(detector.update(ms(200), 200));
+    }
+}
@@ -5,19 +5,21 @@
 
 use lazy_static::lazy_static;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::convert::TryFrom;
 use std::sync::atomic::AtomicBool;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use crate::common::{CallId, CallMediaType, DeviceId, Result};
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::common::{units::DataRate, CallId, CallMediaType, DeviceId, Result};
+use crate::core::bandwidth_mode::{BandwidthMode, BandwidthPolicy};
 use crate::core::call_manager::CallManager;
 use crate::core::group_call;
 use crate::core::group_call::{GroupId, SignalingMessageUrgency};
+use crate::core::send_rate_policy::DeviceConditions;
 use crate::core::signaling;
+use crate::core::speaker_detector::TalkingWhileMutedConfig;
 use crate::lite::{
     http,
     sfu::{DemuxId, GroupMember, PeekInfo, UserId},
@@ -27,13 +29,15 @@ use crate::native::{
     NativePlatform, PeerId, SignalingSender,
 };
 use crate::webrtc::media::{
-    AudioTrack, VideoFrame, VideoPixelFormat, VideoSink, VideoSource, VideoTrack,
+    AudioFrame, AudioSink, AudioTrack, VideoFrame, VideoPixelFormat, VideoSink, VideoSource,
+    VideoTrack,
 };
 use crate::webrtc::peer_connection::AudioLevel;
 use crate::webrtc::peer_connection_factory::{
     self as pcf, AudioDevice, IceServer, PeerConnectionFactory,
 };
 use crate::webrtc::peer_connection_observer::NetworkRoute;
+use crate::webrtc::video_frame_processor::{checked_frame_size, CropProcessor, ScaleProcessor};
 
 use neon::prelude::*;
 
@@ -143,6 +147,8 @@ pub enum Event {
         captured_level: AudioLevel,
         received_level: AudioLevel,
     },
+    // The user seems to be talking while muted in a 1:1 call.
+    TalkingWhileMuted(PeerId),
 }
 
 /// Wraps a [`std::sync::mpsc::Sender`] with a callback to report new events.
@@ -273,6 +279,11 @@ impl CallStateHandler for EventReporter {
         })?;
         Ok(())
     }
+
+    fn handle_talking_while_muted(&self, remote_peer_id: &str) -> Result<()> {
+        self.send(Event::TalkingWhileMuted(remote_peer_id.to_string()))?;
+        Ok(())
+    }
 }
 
 impl http::Delegate for EventReporter {
@@ -305,6 +316,8 @@ pub struct CallEndpoint {
     outgoing_video_track: VideoTrack,
     // Boxed so we can pass it as a Box<dyn VideoSink>
     incoming_video_sink: Box<LastFramesVideoSink>,
+    // Only given to a group call while setIncomingAudioEnabled(true).
+    incoming_audio_sink: BufferedAudioSink,
 
     peer_connection_factory: PeerConnectionFactory,
 
@@ -396,7 +409,8 @@ impl CallEndpoint {
             state_handler,
             group_handler,
         );
-        let call_manager = CallManager::new(platform, http_client)?;
+        let mut call_manager = CallManager::new(platform, http_client)?;
+        call_manager.set_outgoing_video_source(Some(outgoing_video_source.clone()))?;
 
         Ok(Self {
             call_manager,
@@ -405,6 +419,7 @@ impl CallEndpoint {
             outgoing_video_source,
             outgoing_video_track,
             incoming_video_sink,
+            incoming_audio_sink: BufferedAudioSink::default(),
             peer_connection_factory,
             js_object,
         })
@@ -442,6 +457,73 @@ impl LastFramesVideoSink {
     }
 }
 
+// One second of 48kHz stereo.
+const MAX_BUFFERED_AUDIO_SAMPLES: usize = 96_000;
+
+struct BufferedAudio {
+    sample_rate: u32,
+    channels: u16,
+    // Interleaved
+    samples: VecDeque<i16>,
+}
+
+/// Keeps the most recent decoded audio of each track until it's popped.
+#[derive(Clone, Default)]
+struct BufferedAudioSink {
+    audio_by_track_id: Arc<Mutex<HashMap<u32, BufferedAudio>>>,
+}
+
+impl AudioSink for BufferedAudioSink {
+    fn on_audio_frame(&self, track_id: u32, frame: AudioFrame) {
+        let mut audio_by_track_id = self.audio_by_track_id.lock().unwrap();
+        let audio = audio_by_track_id
+            .entry(track_id)
+            .or_insert_with(|| BufferedAudio {
+                sample_rate: frame.sample_rate,
+                channels: frame.channels,
+                samples: VecDeque::new(),
+            });
+        if (audio.sample_rate, audio.channels) != (frame.sample_rate, frame.channels) {
+            // What's buffered can't be returned along with samples of a different format.
+            audio.sample_rate = frame.sample_rate;
+            audio.channels = frame.channels;
+            audio.samples.clear();
+        }
+        audio.samples.extend(frame.samples);
+        let excess = audio
+            .samples
+            .len()
+            .saturating_sub(MAX_BUFFERED_AUDIO_SAMPLES);
+        audio.samples.drain(..excess);
+    }
+
+    fn box_clone(&self) -> Box<dyn AudioSink> {
+        Box::new(self.clone())
+    }
+}
+
+impl BufferedAudioSink {
+    // Returns the sample rate, channels, and as many whole frames of samples as fit in max_samples.
+    fn pop(&self, track_id: u32, max_samples: usize) -> Option<(u32, u16, Vec<i16>)> {
+        let mut audio_by_track_id = self.audio_by_track_id.lock().unwrap();
+        let audio = audio_by_track_id.get_mut(&track_id)?;
+        let channels = usize::from(audio.channels.max(1));
+        let count = audio.samples.len().min(max_samples) / channels * channels;
+        if count == 0 {
+            return None;
+        }
+        Some((
+            audio.sample_rate,
+            audio.channels,
+            audio.samples.drain(..count).collect(),
+        ))
+    }
+
+    fn clear(&self) {
+        self.audio_by_track_id.lock().unwrap().clear();
+    }
+}
+
 fn js_num_to_u64(num: f64) -> u64 {
     // Convert safely from signed.
     num as i32 as u32 as u64
@@ -758,6 +840,25 @@ fn updateBandwidthMode(mut cx: FunctionContext) -> JsResult<JsValue> {
     Ok(cx.undefined().upcast())
 }
 
+#[allow(non_snake_case)]
+fn updateBandwidthPolicy(mut cx: FunctionContext) -> JsResult<JsValue> {
+    debug!("JsCallManager.updateBandwidthPolicy()");
+    let max_send_kbps = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+    let max_receive_kbps = cx.argument::<JsNumber>(1)?.value(&mut cx) as u64;
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .call_manager
+            .update_bandwidth_policy(BandwidthPolicy::new(
+                DataRate::from_kbps(max_send_kbps),
+                DataRate::from_kbps(max_receive_kbps),
+            ))?;
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
 #[allow(non_snake_case)]
 fn receivedOffer(mut cx: FunctionContext) -> JsResult<JsValue> {
     let peer_id = cx.argument::<JsString>(0)?.value(&mut cx) as PeerId;
@@ -1006,6 +1107,82 @@ fn setOutgoingAudioEnabled(mut cx: FunctionContext) -> JsResult<JsValue> {
 
     with_call_endpoint(&mut cx, |endpoint| {
         endpoint.outgoing_audio_track.set_enabled(enabled);
+        // This only fails if there's no direct call, which is fine.
+        let _ = endpoint
+            .call_manager
+            .set_direct_outgoing_audio_muted(!enabled);
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn setTalkingWhileMutedConfig(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let min_level = cx.argument::<JsNumber>(0)?.value(&mut cx) as AudioLevel;
+    let min_duration_millis = cx.argument::<JsNumber>(1)?.value(&mut cx) as u64;
+    let max_pause_millis = cx.argument::<JsNumber>(2)?.value(&mut cx) as u64;
+    let min_interval_millis = cx.argument::<JsNumber>(3)?.value(&mut cx) as u64;
+    let config = TalkingWhileMutedConfig {
+        min_level,
+        min_duration: Duration::from_millis(min_duration_millis),
+        max_pause: Duration::from_millis(max_pause_millis),
+        min_interval: Duration::from_millis(min_interval_millis),
+    };
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .call_manager
+            .set_talking_while_muted_config(config)?;
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn addVideoFrameCropToAspectRatio(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let width = cx.argument::<JsNumber>(0)?.value(&mut cx) as i64;
+    let height = cx.argument::<JsNumber>(1)?.value(&mut cx) as i64;
+    debug!(
+        "JsCallManager.addVideoFrameCropToAspectRatio({}:{})",
+        width, height
+    );
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        let (width, height) = checked_frame_size(width, height)?;
+        endpoint.outgoing_video_source.add_frame_processor(Box::new(
+            CropProcessor::center_to_aspect_ratio(width, height),
+        ));
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn addVideoFrameScale(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let width = cx.argument::<JsNumber>(0)?.value(&mut cx) as i64;
+    let height = cx.argument::<JsNumber>(1)?.value(&mut cx) as i64;
+    debug!("JsCallManager.addVideoFrameScale({}x{})", width, height);
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        let (width, height) = checked_frame_size(width, height)?;
+        endpoint
+            .outgoing_video_source
+            .add_frame_processor(Box::new(ScaleProcessor::new(width, height)));
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn clearVideoFrameProcessors(mut cx: FunctionContext) -> JsResult<JsValue> {
+    debug!("JsCallManager.clearVideoFrameProcessors()");
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.outgoing_video_source.clear_frame_processors();
         Ok(())
     })
     .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
@@ -1065,6 +1242,10 @@ fn sendVideoFrame(mut cx: FunctionContext) -> JsResult<JsValue> {
         return cx.throw_error("Invalid pixel format");
     }
     let pixel_format = pixel_format.unwrap();
+    let buffer_size = cx.borrow(&buffer, |buffer| buffer.as_slice::<u8>().len());
+    if buffer_size < pixel_format.size(width, height) {
+        return cx.throw_error("Video frame buffer is too small");
+    }
 
     let frame = cx.borrow(&buffer, |buffer| {
         VideoFrame::copy_from_slice(width, height, pixel_format, buffer.as_slice())
@@ -1077,15 +1258,47 @@ fn sendVideoFrame(mut cx: FunctionContext) -> JsResult<JsValue> {
     Ok(cx.undefined().upcast())
 }
 
+// Received frames are RGBA unless the app asks for something else.
+fn optional_pixel_format_argument(
+    cx: &mut FunctionContext,
+    i: i32,
+) -> NeonResult<VideoPixelFormat> {
+    match cx.argument_opt(i) {
+        Some(value) if value.is_a::<JsNumber, _>(cx) => {
+            let pixel_format = value.downcast::<JsNumber, _>(cx).unwrap().value(cx) as i32;
+            match VideoPixelFormat::from_i32(pixel_format) {
+                Some(pixel_format) => Ok(pixel_format),
+                None => cx.throw_error("Invalid pixel format"),
+            }
+        }
+        _ => Ok(VideoPixelFormat::Rgba),
+    }
+}
+
+fn copy_out_video_frame(
+    cx: &mut FunctionContext,
+    frame: &VideoFrame,
+    pixel_format: VideoPixelFormat,
+    frame_buffer: Handle<JsBuffer>,
+) -> NeonResult<()> {
+    let buffer_size = cx.borrow(&frame_buffer, |handle| handle.as_slice::<u8>().len());
+    if buffer_size < pixel_format.size(frame.width(), frame.height()) {
+        return cx.throw_error("Video frame buffer is too small");
+    }
+    cx.borrow(&frame_buffer, |handle| {
+        frame.to_format(pixel_format, handle.as_mut_slice());
+    });
+    Ok(())
+}
+
 #[allow(non_snake_case)]
 fn receiveVideoFrame(mut cx: FunctionContext) -> JsResult<JsValue> {
-    let rgba_buffer = cx.argument::<JsBuffer>(0)?;
+    let frame_buffer = cx.argument::<JsBuffer>(0)?;
+    let pixel_format = optional_pixel_format_argument(&mut cx, 1)?;
     let frame = with_call_endpoint(&mut cx, |endpoint| endpoint.incoming_video_sink.pop(0));
     if let Some(frame) = frame {
         let frame = frame.apply_rotation();
-        cx.borrow(&rgba_buffer, |handle| {
-            frame.to_rgba(handle.as_mut_slice());
-        });
+        copy_out_video_frame(&mut cx, &frame, pixel_format, frame_buffer)?;
         let js_width = cx.number(frame.width());
         let js_height = cx.number(frame.height());
         let result = JsArray::new(&mut cx, 2);
@@ -1103,7 +1316,8 @@ fn receiveVideoFrame(mut cx: FunctionContext) -> JsResult<JsValue> {
 fn receiveGroupCallVideoFrame(mut cx: FunctionContext) -> JsResult<JsValue> {
     let _client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
     let remote_demux_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as DemuxId;
-    let rgba_buffer = cx.argument::<JsBuffer>(2)?;
+    let frame_buffer = cx.argument::<JsBuffer>(2)?;
+    let pixel_format = optional_pixel_format_argument(&mut cx, 3)?;
 
     let frame = with_call_endpoint(&mut cx, |endpoint| {
         endpoint.incoming_video_sink.pop(remote_demux_id)
@@ -1111,9 +1325,7 @@ fn receiveGroupCallVideoFrame(mut cx: FunctionContext) -> JsResult<JsValue> {
 
     if let Some(frame) = frame {
         let frame = frame.apply_rotation();
-        cx.borrow(&rgba_buffer, |handle| {
-            frame.to_rgba(handle.as_mut_slice());
-        });
+        copy_out_video_frame(&mut cx, &frame, pixel_format, frame_buffer)?;
         let js_width = cx.number(frame.width());
         let js_height = cx.number(frame.height());
         let result = JsArray::new(&mut cx, 2);
@@ -1280,6 +1492,77 @@ fn setOutgoingVideoMuted(mut cx: FunctionContext) -> JsResult<JsValue> {
     Ok(cx.undefined().upcast())
 }
 
+#[allow(non_snake_case)]
+fn setAudioOnly(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let audio_only = cx.argument::<JsBoolean>(1)?.value(&mut cx);
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.call_manager.set_audio_only(client_id, audio_only);
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn setIncomingAudioEnabled(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let enabled = cx.argument::<JsBoolean>(1)?.value(&mut cx);
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.incoming_audio_sink.clear();
+        let incoming_audio_sink = if enabled {
+            Some(endpoint.incoming_audio_sink.box_clone())
+        } else {
+            None
+        };
+        endpoint
+            .call_manager
+            .set_incoming_audio_sink(client_id, incoming_audio_sink);
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn receiveGroupCallAudio(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let _client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let remote_demux_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as DemuxId;
+    let audio_buffer = cx.argument::<JsBuffer>(2)?;
+
+    // Each sample is 2 bytes.
+    let max_samples = cx.borrow(&audio_buffer, |handle| handle.as_slice::<u8>().len()) / 2;
+    let audio = with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .incoming_audio_sink
+            .pop(remote_demux_id, max_samples)
+    });
+
+    if let Some((sample_rate, channels, samples)) = audio {
+        cx.borrow(&audio_buffer, |handle| {
+            for (bytes, sample) in handle
+                .as_mut_slice::<u8>()
+                .chunks_exact_mut(2)
+                .zip(&samples)
+            {
+                bytes.copy_from_slice(&sample.to_le_bytes());
+            }
+        });
+        let js_sample_rate = cx.number(sample_rate);
+        let js_channels = cx.number(channels);
+        let js_sample_count = cx.number(samples.len() as u32);
+        let result = JsArray::new(&mut cx, 3);
+        result.set(&mut cx, 0, js_sample_rate)?;
+        result.set(&mut cx, 1, js_channels)?;
+        result.set(&mut cx, 2, js_sample_count)?;
+        Ok(result.upcast())
+    } else {
+        Ok(cx.undefined().upcast())
+    }
+}
+
 #[allow(non_snake_case)]
 fn setPresenting(mut cx: FunctionContext) -> JsResult<JsValue> {
     let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
@@ -1293,6 +1576,105 @@ fn setPresenting(mut cx: FunctionContext) -> JsResult<JsValue> {
     Ok(cx.undefined().upcast())
 }
 
+#[allow(non_snake_case)]
+fn setSpotlight(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let demux_id_or_undef = cx.argument::<JsValue>(1)?;
+    let demux_id = match demux_id_or_undef.downcast::<JsUndefined, _>(&mut cx) {
+        Ok(_) => None,
+        Err(_) => {
+            // By checking 'undefined' first, we get an error message that mentions number.
+            let demux_id = demux_id_or_undef.downcast_or_throw::<JsNumber, _>(&mut cx)?;
+            Some(demux_id.value(&mut cx) as DemuxId)
+        }
+    };
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.call_manager.set_spotlight(client_id, demux_id);
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn setLocalDisplayName(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let display_name_or_undef = cx.argument::<JsValue>(1)?;
+    let display_name = match display_name_or_undef.downcast::<JsUndefined, _>(&mut cx) {
+        Ok(_) => None,
+        Err(_) => {
+            // By checking 'undefined' first, we get an error message that mentions string.
+            let display_name = display_name_or_undef.downcast_or_throw::<JsString, _>(&mut cx)?;
+            Some(display_name.value(&mut cx))
+        }
+    };
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .call_manager
+            .set_local_display_name(client_id, display_name);
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn setLocalAvatarHash(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let avatar_hash_or_undef = cx.argument::<JsValue>(1)?;
+    let avatar_hash = match avatar_hash_or_undef.downcast::<JsUndefined, _>(&mut cx) {
+        Ok(_) => None,
+        Err(_) => {
+            // By checking 'undefined' first, we get an error message that mentions Buffer.
+            let avatar_hash = avatar_hash_or_undef.downcast_or_throw::<JsBuffer, _>(&mut cx)?;
+            Some(cx.borrow(&avatar_hash, |handle| handle.as_slice().to_vec()))
+        }
+    };
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .call_manager
+            .set_local_avatar_hash(client_id, avatar_hash);
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn setRemoteAudioVolume(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let demux_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as DemuxId;
+    let volume = cx.argument::<JsNumber>(2)?.value(&mut cx);
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .call_manager
+            .set_remote_audio_volume(client_id, demux_id, volume);
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn setRemoteAudioMuted(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let demux_id = cx.argument::<JsNumber>(1)?.value(&mut cx) as DemuxId;
+    let muted = cx.argument::<JsBoolean>(2)?.value(&mut cx);
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .call_manager
+            .set_remote_audio_muted(client_id, demux_id, muted);
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
 #[allow(non_snake_case)]
 fn setOutgoingGroupCallVideoIsScreenShare(mut cx: FunctionContext) -> JsResult<JsValue> {
     let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
@@ -1359,6 +1741,118 @@ fn setBandwidthMode(mut cx: FunctionContext) -> JsResult<JsValue> {
     Ok(cx.undefined().upcast())
 }
 
+#[allow(non_snake_case)]
+fn setBandwidthPolicy(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let max_send_kbps = cx.argument::<JsNumber>(1)?.value(&mut cx) as u64;
+    let max_receive_kbps = cx.argument::<JsNumber>(2)?.value(&mut cx) as u64;
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.call_manager.set_bandwidth_policy(
+            client_id,
+            BandwidthPolicy::new(
+                DataRate::from_kbps(max_send_kbps),
+                DataRate::from_kbps(max_receive_kbps),
+            ),
+        );
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn setDeviceConditions(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let low_battery = cx.argument::<JsBoolean>(1)?.value(&mut cx);
+    let thermal_throttling = cx.argument::<JsBoolean>(2)?.value(&mut cx);
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint.call_manager.set_device_conditions(
+            client_id,
+            DeviceConditions {
+                low_battery,
+                thermal_throttling,
+            },
+        );
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
+#[allow(non_snake_case)]
+fn setVideoRequestPolicy(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
+    let slots_or_undef = cx.argument::<JsValue>(1)?;
+    let pinned_demux_id_or_undef = cx.argument::<JsValue>(2)?;
+
+    let policy = match slots_or_undef.downcast::<JsUndefined, _>(&mut cx) {
+        Ok(_) => None,
+        Err(_) => {
+            // By checking 'undefined' first, we get an error message that mentions array.
+            let js_slots = slots_or_undef.downcast_or_throw::<JsArray, _>(&mut cx)?;
+            let mut slots = Vec::with_capacity(js_slots.len(&mut cx) as usize);
+            for i in 0..js_slots.len(&mut cx) {
+                let js_slot = js_slots
+                    .get(&mut cx, i as u32)?
+                    .downcast::<JsObject, _>(&mut cx)
+                    .expect("VideoTileSlot");
+
+                let mut get_number = |name: &str| -> NeonResult<Option<f64>> {
+                    Ok(js_slot
+                        .get(&mut cx, name)?
+                        .downcast::<JsNumber, _>(&mut cx)
+                        .ok()
+                        .map(|handle| handle.value(&mut cx)))
+                };
+                let kind = get_number("kind")?;
+                let count = get_number("count")?.unwrap_or(0.0) as u16;
+                let width = get_number("width")?.unwrap_or(0.0) as u16;
+                let height = get_number("height")?.unwrap_or(0.0) as u16;
+                let framerate = get_number("framerate")?.map(|framerate| framerate as u16);
+
+                match kind.and_then(|kind| {
+                    group_call::VideoTileSlot::from_kind(
+                        kind as i32,
+                        count,
+                        width,
+                        height,
+                        framerate,
+                    )
+                }) {
+                    Some(slot) => slots.push(slot),
+                    None => warn!("Skipping video tile slot due to invalid kind"),
+                }
+            }
+
+            let pinned_demux_id = match pinned_demux_id_or_undef.downcast::<JsUndefined, _>(&mut cx)
+            {
+                Ok(_) => None,
+                Err(_) => Some(
+                    pinned_demux_id_or_undef
+                        .downcast_or_throw::<JsNumber, _>(&mut cx)?
+                        .value(&mut cx) as DemuxId,
+                ),
+            };
+
+            Some(group_call::VideoRequestPolicy {
+                slots,
+                pinned_demux_id,
+            })
+        }
+    };
+
+    with_call_endpoint(&mut cx, |endpoint| {
+        endpoint
+            .call_manager
+            .set_video_request_policy(client_id, policy);
+        Ok(())
+    })
+    .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
+    Ok(cx.undefined().upcast())
+}
+
 #[allow(non_snake_case)]
 fn requestVideo(mut cx: FunctionContext) -> JsResult<JsValue> {
     let client_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as group_call::ClientId;
@@ -1900,6 +2394,17 @@ fn processEvents(mut cx: FunctionContext) -> JsResult<JsValue> {
                 method.call(&mut cx, observer, args)?;
             }
 
+            Event::TalkingWhileMuted(peer_id) => {
+                let method_name = "onTalkingWhileMuted";
+                let args: Vec<Handle<JsValue>> = vec![cx.string(peer_id).upcast()];
+
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect("onTalkingWhileMuted is a function");
+                method.call(&mut cx, observer, args)?;
+            }
+
             Event::SendHttpRequest {
                 request_id,
                 request:
@@ -2110,6 +2615,22 @@ fn processEvents(mut cx: FunctionContext) -> JsResult<JsValue> {
                         };
                     let is_higher_resolution_pending =
                         cx.boolean(remote_device_state.is_higher_resolution_pending);
+                    let spotlighted = cx.boolean(remote_device_state.spotlighted);
+                    let display_name: neon::handle::Handle<JsValue> =
+                        match &remote_device_state.profile.display_name {
+                            None => cx.undefined().upcast(),
+                            Some(display_name) => cx.string(display_name).upcast(),
+                        };
+                    let avatar_hash: neon::handle::Handle<JsValue> =
+                        match &remote_device_state.profile.avatar_hash {
+                            None => cx.undefined().upcast(),
+                            Some(avatar_hash) => to_js_buffer(&mut cx, avatar_hash),
+                        };
+                    let video_slot: neon::handle::Handle<JsValue> =
+                        match remote_device_state.video_slot {
+                            None => cx.undefined().upcast(),
+                            Some(video_slot) => cx.number(video_slot as f64).upcast(),
+                        };
 
                     let js_remote_device_state = cx.empty_object();
                     js_remote_device_state.set(&mut cx, "demuxId", demux_id)?;
@@ -2131,6 +2652,10 @@ fn processEvents(mut cx: FunctionContext) -> JsResult<JsValue> {
                         "isHigherResolutionPending",
                         is_higher_resolution_pending,
                     )?;
+                    js_remote_device_state.set(&mut cx, "spotlighted", spotlighted)?;
+                    js_remote_device_state.set(&mut cx, "displayName", display_name)?;
+                    js_remote_device_state.set(&mut cx, "avatarHash", avatar_hash)?;
+                    js_remote_device_state.set(&mut cx, "videoSlot", video_slot)?;
 
                     js_remote_device_states.set(&mut cx, i as u32, js_remote_device_state)?;
                 }
@@ -2267,6 +2792,7 @@ fn processEvents(mut cx: FunctionContext) -> JsResult<JsValue> {
                 ];
                 with_call_endpoint(&mut cx, |endpoint| {
                     endpoint.incoming_video_sink.clear();
+                    endpoint.incoming_audio_sink.clear();
                     Ok(())
                 })
                 .or_else(|err: anyhow::Error| cx.throw_error(format!("{}", err)))?;
@@ -2328,6 +2854,32 @@ fn processEvents(mut cx: FunctionContext) -> JsResult<JsValue> {
                     .expect("handleAudioLevels is a function");
                 method.call(&mut cx, observer, args)?;
             }
+
+            Event::GroupUpdate(GroupUpdate::TalkingWhileMuted(client_id)) => {
+                let method_name = "handleTalkingWhileMuted";
+                let args: Vec<Handle<JsValue>> = vec![cx.number(client_id).upcast()];
+
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect("handleTalkingWhileMuted is a function");
+                method.call(&mut cx, observer, args)?;
+            }
+
+            Event::GroupUpdate(GroupUpdate::SpotlightChanged(client_id, demux_id)) => {
+                let method_name = "handleSpotlightChanged";
+                let js_demux_id: neon::handle::Handle<JsValue> = match demux_id {
+                    None => cx.undefined().upcast(),
+                    Some(demux_id) => cx.number(demux_id).upcast(),
+                };
+                let args: Vec<Handle<JsValue>> = vec![cx.number(client_id).upcast(), js_demux_id];
+
+                let method = *observer
+                    .get(&mut cx, method_name)?
+                    .downcast::<JsFunction, _>(&mut cx)
+                    .expect("handleSpotlightChanged is a function");
+                method.call(&mut cx, observer, args)?;
+            }
         }
     }
     Ok(cx.undefined().upcast())
@@ -2349,6 +2901,7 @@ fn register(mut cx: ModuleContext) -> NeonResult<()> {
     cx.export_function("cm_signalingMessageSent", signalingMessageSent)?;
     cx.export_function("cm_signalingMessageSendFailed", signalingMessageSendFailed)?;
     cx.export_function("cm_updateBandwidthMode", updateBandwidthMode)?;
+    cx.export_function("cm_updateBandwidthPolicy", updateBandwidthPolicy)?;
     cx.export_function("cm_receivedOffer", receivedOffer)?;
     cx.export_function("cm_receivedAnswer", receivedAnswer)?;
     cx.export_function("cm_receivedIceCandidates", receivedIceCandidates)?;
@@ -2359,6 +2912,13 @@ fn register(mut cx: ModuleContext) -> NeonResult<()> {
     cx.export_function("cm_httpRequestFailed", httpRequestFailed)?;
     cx.export_function("cm_setOutgoingAudioEnabled", setOutgoingAudioEnabled)?;
     cx.export_function("cm_setOutgoingVideoEnabled", setOutgoingVideoEnabled)?;
+    cx.export_function("cm_setTalkingWhileMutedConfig", setTalkingWhileMutedConfig)?;
+    cx.export_function(
+        "cm_addVideoFrameCropToAspectRatio",
+        addVideoFrameCropToAspectRatio,
+    )?;
+    cx.export_function("cm_addVideoFrameScale", addVideoFrameScale)?;
+    cx.export_function("cm_clearVideoFrameProcessors", clearVideoFrameProcessors)?;
     cx.export_function(
         "cm_setOutgoingVideoIsScreenShare",
         setOutgoingVideoIsScreenShare,
@@ -2374,7 +2934,15 @@ fn register(mut cx: ModuleContext) -> NeonResult<()> {
     cx.export_function("cm_disconnect", disconnect)?;
     cx.export_function("cm_setOutgoingAudioMuted", setOutgoingAudioMuted)?;
     cx.export_function("cm_setOutgoingVideoMuted", setOutgoingVideoMuted)?;
+    cx.export_function("cm_setAudioOnly", setAudioOnly)?;
+    cx.export_function("cm_setIncomingAudioEnabled", setIncomingAudioEnabled)?;
+    cx.export_function("cm_receiveGroupCallAudio", receiveGroupCallAudio)?;
     cx.export_function("cm_setPresenting", setPresenting)?;
+    cx.export_function("cm_setSpotlight", setSpotlight)?;
+    cx.export_function("cm_setLocalDisplayName", setLocalDisplayName)?;
+    cx.export_function("cm_setLocalAvatarHash", setLocalAvatarHash)?;
+    cx.export_function("cm_setRemoteAudioVolume", setRemoteAudioVolume)?;
+    cx.export_function("cm_setRemoteAudioMuted", setRemoteAudioMuted)?;
     cx.export_function(
         "cm_setOutgoingGroupCallVideoIsScreenShare",
         setOutgoingGroupCallVideoIsScreenShare,
@@ -2382,7 +2950,10 @@ fn register(mut cx: ModuleContext) -> NeonResult<()> {
     cx.export_function("cm_groupRing", groupRing)?;
     cx.export_function("cm_resendMediaKeys", resendMediaKeys)?;
     cx.export_function("cm_setBandwidthMode", setBandwidthMode)?;
+    cx.export_function("cm_setBandwidthPolicy", setBandwidthPolicy)?;
+    cx.export_function("cm_setDeviceConditions", setDeviceConditions)?;
     cx.export_function("cm_requestVideo", requestVideo)?;
+    cx.export_function("cm_setVideoRequestPolicy", setVideoRequestPolicy)?;
     cx.export_function("cm_setGroupMembers", setGroupMembers)?;
     cx.export_function("cm_setMembershipProof", setMembershipProof)?;
     cx.export_function("cm_peekGroupCall", peekGroupCall)?;
@@ -63,6 +63,14 @@ pub enum RingRtcError {
     #[allow(dead_code)]
     #[error("Unable to set Audio Device")]
     SetAudioDevice,
+    #[error("Unable to set remote audio volume for demux_id: {0}")]
+    SetRemoteAudioVolume(u32),
+    #[error("Unable to set video encoding layers")]
+    SetVideoEncodingLayers,
+    #[error("Too many video encoding layers: {0}")]
+    TooManyVideoEncodingLayers(usize),
+    #[error("Invalid video frame size: {0}x{1}")]
+    InvalidVideoFrameSize(i64, i64),
 
     // WebRTC / C++ session description error codes
     #[error("CreateSessionDescriptionObserver failure. error msg: {0}, type: {1}")]
@@ -123,4 +131,9 @@ pub enum RingRtcError {
     SrtpKeyNegotiationFailure,
     #[error("Buffer too small")]
     BufferTooSmall,
+
+    // Simulated network error codes
+    #[cfg(feature = "simnet")]
+    #[error("Invalid network scenario: {0}")]
+    InvalidNetworkScenario(String),
 }
@@ -30,9 +30,10 @@ use ringrtc::{
     },
     protobuf,
     webrtc::{
-        media::{VideoFrame, VideoFrameMetadata, VideoPixelFormat, VideoSink, VideoTrack},
+        media::{VideoFrame, VideoFrameMetadata, VideoSink, VideoTrack},
         peer_connection::{AudioLevel, ReceivedAudioLevel, SendRates},
         peer_connection_factory::{self, PeerConnectionFactory},
+        test_media::{TestPattern, TestVideoConfig, TestVideoGenerator},
     },
 };
 
@@ -223,6 +224,18 @@ impl group_call::Observer for Observer {
     ) {
         // ignore
     }
+
+    fn handle_talking_while_muted(&self, _client_id: group_call::ClientId) {
+        // ignore
+    }
+
+    fn handle_spotlight_changed(
+        &self,
+        _client_id: group_call::ClientId,
+        _spotlight_demux_id: Option<DemuxId>,
+    ) {
+        // ignore
+    }
 }
 
 impl VideoSink for Observer {
@@ -324,18 +337,14 @@ fn main() {
     outgoing_video_track.set_enabled(true);
 
     std::thread::spawn(move || {
-        for index in 0u64.. {
-            let width = 1280;
-            let height = 720;
-            let rgba_data: Vec<u8> = (0..(width * height * 4))
-                .map(|i: u32| i.wrapping_add(index as u32) as u8)
-                .collect();
-            outgoing_video_source.push_frame(VideoFrame::copy_from_slice(
-                width,
-                height,
-                VideoPixelFormat::Rgba,
-                &rgba_data,
-            ));
+        let mut generator = TestVideoGenerator::new(TestVideoConfig {
+            width: 1280,
+            height: 720,
+            pattern: TestPattern::MovingGradient,
+            ..Default::default()
+        });
+        loop {
+            outgoing_video_source.push_frame(generator.next_frame());
             std::thread::sleep(std::time::Duration::from_secs_f32(1.0 / 30.0));
         }
     });
@@ -15,9 +15,10 @@ use libc::size_t;
 use crate::ios::call_manager;
 use crate::ios::call_manager::IosCallManager;
 
-use crate::common::{CallMediaType, DeviceId};
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::common::{units::DataRate, CallMediaType, DeviceId};
+use crate::core::bandwidth_mode::{BandwidthMode, BandwidthPolicy};
 use crate::core::group_call;
+use crate::core::send_rate_policy::DeviceConditions;
 use crate::core::signaling;
 use crate::lite::{http, sfu, sfu::DemuxId};
 use crate::webrtc::peer_connection::AudioLevel;
@@ -232,6 +233,10 @@ pub struct AppRemoteDeviceState {
     pub speakerTime: u64, // unix millis; 0 if never was a speaker
     pub forwardingVideo: AppOptionalBool,
     pub isHigherResolutionPending: bool,
+    pub spotlighted: bool,
+    pub displayName: AppByteSlice,
+    pub avatarHash: AppByteSlice,
+    pub videoSlot: AppOptionalUInt32,
 }
 
 #[repr(C)]
@@ -300,6 +305,27 @@ pub struct AppVideoRequestArray {
     pub count: size_t,
 }
 
+/// A part of the layout for a video request policy.  The kind is
+/// 0 = Spotlight, 1 = Grid, 2 = Thumbnails or 3 = Offscreen.
+#[repr(C)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct AppVideoTileSlot {
+    pub kind: i32,
+    pub count: u16,
+    pub width: u16,
+    pub height: u16,
+    pub framerate: AppOptionalUInt16,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+#[allow(non_snake_case)]
+pub struct AppVideoTileSlotArray {
+    pub slots: *const AppVideoTileSlot,
+    pub count: size_t,
+}
+
 #[repr(C)]
 #[derive(Debug)]
 #[allow(non_snake_case)]
@@ -330,6 +356,8 @@ pub struct AppInterface {
         receivedLevel: u16,
     ),
     ///
+    pub onTalkingWhileMuted: extern "C" fn(object: *mut c_void, remote: *const c_void),
+    ///
     pub onSendOffer: extern "C" fn(
         object: *mut c_void,
         callId: u64,
@@ -439,6 +467,13 @@ pub struct AppInterface {
         capturedLevel: u16,
         receivedAudioLevels: AppReceivedAudioLevelArray,
     ),
+    pub handleTalkingWhileMuted: extern "C" fn(object: *mut c_void, clientId: group_call::ClientId),
+    ///
+    pub handleSpotlightChanged: extern "C" fn(
+        object: *mut c_void,
+        clientId: group_call::ClientId,
+        demuxId: AppOptionalUInt32,
+    ),
     ///
     pub handleJoinStateChanged:
         extern "C" fn(object: *mut c_void, clientId: group_call::ClientId, joinState: i32),
@@ -891,6 +926,90 @@ pub extern "C" fn ringrtcGetActiveCallContext(callManager: *mut c_void) -> *mut
     }
 }
 
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcSetAudioEnable(callManager: *mut c_void, enable: bool) -> *mut c_void {
+    match call_manager::set_audio_enable(callManager as *mut IosCallManager, enable) {
+        Ok(_v) => {
+            // Return the object reference back as indication of success.
+            callManager
+        }
+        Err(_e) => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcSetTalkingWhileMutedConfig(
+    callManager: *mut c_void,
+    minLevel: AudioLevel,
+    minDurationMillis: u64,
+    maxPauseMillis: u64,
+    minIntervalMillis: u64,
+) -> *mut c_void {
+    match call_manager::set_talking_while_muted_config(
+        callManager as *mut IosCallManager,
+        minLevel,
+        Duration::from_millis(minDurationMillis),
+        Duration::from_millis(maxPauseMillis),
+        Duration::from_millis(minIntervalMillis),
+    ) {
+        Ok(_v) => {
+            // Return the object reference back as indication of success.
+            callManager
+        }
+        Err(_e) => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcAddVideoFrameCropToAspectRatio(
+    callManager: *mut c_void,
+    width: u32,
+    height: u32,
+) -> *mut c_void {
+    match call_manager::add_video_frame_crop_to_aspect_ratio(
+        callManager as *mut IosCallManager,
+        width,
+        height,
+    ) {
+        Ok(_v) => {
+            // Return the object reference back as indication of success.
+            callManager
+        }
+        Err(_e) => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcAddVideoFrameScale(
+    callManager: *mut c_void,
+    width: u32,
+    height: u32,
+) -> *mut c_void {
+    match call_manager::add_video_frame_scale(callManager as *mut IosCallManager, width, height) {
+        Ok(_v) => {
+            // Return the object reference back as indication of success.
+            callManager
+        }
+        Err(_e) => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcClearVideoFrameProcessors(callManager: *mut c_void) -> *mut c_void {
+    match call_manager::clear_video_frame_processors(callManager as *mut IosCallManager) {
+        Ok(_v) => {
+            // Return the object reference back as indication of success.
+            callManager
+        }
+        Err(_e) => ptr::null_mut(),
+    }
+}
+
 #[no_mangle]
 #[allow(non_snake_case)]
 pub extern "C" fn ringrtcSetVideoEnable(callManager: *mut c_void, enable: bool) -> *mut c_void {
@@ -915,6 +1034,25 @@ pub extern "C" fn ringrtcUpdateBandwidthMode(callManager: *mut c_void, bandwidth
     }
 }
 
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcUpdateBandwidthPolicy(
+    callManager: *mut c_void,
+    maxSendKbps: u64,
+    maxReceiveKbps: u64,
+) {
+    let result = call_manager::update_bandwidth_policy(
+        callManager as *mut IosCallManager,
+        BandwidthPolicy::new(
+            DataRate::from_kbps(maxSendKbps),
+            DataRate::from_kbps(maxReceiveKbps),
+        ),
+    );
+    if result.is_err() {
+        error!("ringrtcUpdateBandwidthPolicy(): {:?}", result.err());
+    }
+}
+
 #[no_mangle]
 #[allow(non_snake_case)]
 pub extern "C" fn ringrtcDrop(callManager: *mut c_void, callId: u64) -> *mut c_void {
@@ -1091,6 +1229,88 @@ pub extern "C" fn ringrtcGroupRing(
     }
 }
 
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcSetLocalDisplayName(
+    callManager: *mut c_void,
+    clientId: group_call::ClientId,
+    displayName: AppByteSlice,
+) {
+    info!("ringrtcSetLocalDisplayName():");
+
+    let display_name = string_from_app_slice(&displayName);
+    let result = call_manager::set_local_display_name(
+        callManager as *mut IosCallManager,
+        clientId,
+        display_name,
+    );
+    if result.is_err() {
+        error!("{:?}", result.err());
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcSetLocalAvatarHash(
+    callManager: *mut c_void,
+    clientId: group_call::ClientId,
+    avatarHash: AppByteSlice,
+) {
+    info!("ringrtcSetLocalAvatarHash():");
+
+    let avatar_hash = byte_vec_from_app_slice(&avatarHash);
+    let result = call_manager::set_local_avatar_hash(
+        callManager as *mut IosCallManager,
+        clientId,
+        avatar_hash,
+    );
+    if result.is_err() {
+        error!("{:?}", result.err());
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcSetRemoteAudioVolume(
+    callManager: *mut c_void,
+    clientId: group_call::ClientId,
+    demuxId: DemuxId,
+    volume: f64,
+) {
+    info!("ringrtcSetRemoteAudioVolume():");
+
+    let result = call_manager::set_remote_audio_volume(
+        callManager as *mut IosCallManager,
+        clientId,
+        demuxId,
+        volume,
+    );
+    if result.is_err() {
+        error!("{:?}", result.err());
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcSetRemoteAudioMuted(
+    callManager: *mut c_void,
+    clientId: group_call::ClientId,
+    demuxId: DemuxId,
+    muted: bool,
+) {
+    info!("ringrtcSetRemoteAudioMuted():");
+
+    let result = call_manager::set_remote_audio_muted(
+        callManager as *mut IosCallManager,
+        clientId,
+        demuxId,
+        muted,
+    );
+    if result.is_err() {
+        error!("{:?}", result.err());
+    }
+}
+
 #[no_mangle]
 #[allow(non_snake_case)]
 pub extern "C" fn ringrtcSetOutgoingAudioMuted(
@@ -1123,6 +1343,43 @@ pub extern "C" fn ringrtcSetOutgoingVideoMuted(
     }
 }
 
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcSetAudioOnly(
+    callManager: *mut c_void,
+    clientId: group_call::ClientId,
+    audioOnly: bool,
+) {
+    info!("ringrtcSetAudioOnly():");
+
+    let result =
+        call_manager::set_audio_only(callManager as *mut IosCallManager, clientId, audioOnly);
+    if result.is_err() {
+        error!("{:?}", result.err());
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcSetSpotlight(
+    callManager: *mut c_void,
+    clientId: group_call::ClientId,
+    demuxId: AppOptionalUInt32,
+) {
+    info!("ringrtcSetSpotlight():");
+
+    let demux_id = if demuxId.valid {
+        Some(demuxId.value as DemuxId)
+    } else {
+        None
+    };
+    let result =
+        call_manager::set_spotlight(callManager as *mut IosCallManager, clientId, demux_id);
+    if result.is_err() {
+        error!("{:?}", result.err());
+    }
+}
+
 #[no_mangle]
 #[allow(non_snake_case)]
 pub extern "C" fn ringrtcResendMediaKeys(callManager: *mut c_void, clientId: group_call::ClientId) {
@@ -1153,6 +1410,52 @@ pub extern "C" fn ringrtcSetBandwidthMode(
     }
 }
 
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcSetBandwidthPolicy(
+    callManager: *mut c_void,
+    clientId: group_call::ClientId,
+    maxSendKbps: u64,
+    maxReceiveKbps: u64,
+) {
+    info!("ringrtcSetBandwidthPolicy():");
+
+    let result = call_manager::set_bandwidth_policy(
+        callManager as *mut IosCallManager,
+        clientId,
+        BandwidthPolicy::new(
+            DataRate::from_kbps(maxSendKbps),
+            DataRate::from_kbps(maxReceiveKbps),
+        ),
+    );
+    if result.is_err() {
+        error!("{:?}", result.err());
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcSetDeviceConditions(
+    callManager: *mut c_void,
+    clientId: group_call::ClientId,
+    lowBattery: bool,
+    thermalThrottling: bool,
+) {
+    info!("ringrtcSetDeviceConditions():");
+
+    let result = call_manager::set_device_conditions(
+        callManager as *mut IosCallManager,
+        clientId,
+        DeviceConditions {
+            low_battery: lowBattery,
+            thermal_throttling: thermalThrottling,
+        },
+    );
+    if result.is_err() {
+        error!("{:?}", result.err());
+    }
+}
+
 #[no_mangle]
 #[allow(non_snake_case)]
 pub extern "C" fn ringrtcRequestVideo(
@@ -1195,6 +1498,62 @@ pub extern "C" fn ringrtcRequestVideo(
     }
 }
 
+/// Passing a null slot array clears the policy.
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn ringrtcSetVideoRequestPolicy(
+    callManager: *mut c_void,
+    clientId: group_call::ClientId,
+    appVideoTileSlotArray: *const AppVideoTileSlotArray,
+    pinnedDemuxId: AppOptionalUInt32,
+) {
+    info!("ringrtcSetVideoRequestPolicy():");
+
+    let policy = if appVideoTileSlotArray.is_null() {
+        None
+    } else {
+        let count = unsafe { (*appVideoTileSlotArray).count };
+        let app_slots = unsafe { (*appVideoTileSlotArray).slots };
+        let app_slots = unsafe { slice::from_raw_parts(app_slots, count) };
+
+        let slots = app_slots
+            .iter()
+            .filter_map(|slot| {
+                let optional_framerate = if slot.framerate.valid {
+                    Some(slot.framerate.value)
+                } else {
+                    None
+                };
+                group_call::VideoTileSlot::from_kind(
+                    slot.kind,
+                    slot.count,
+                    slot.width,
+                    slot.height,
+                    optional_framerate,
+                )
+            })
+            .collect();
+
+        Some(group_call::VideoRequestPolicy {
+            slots,
+            pinned_demux_id: if pinnedDemuxId.valid {
+                Some(pinnedDemuxId.value as DemuxId)
+            } else {
+                None
+            },
+        })
+    };
+
+    let result = call_manager::set_video_request_policy(
+        callManager as *mut IosCallManager,
+        clientId,
+        policy,
+    );
+    if result.is_err() {
+        error!("{:?}", result.err());
+    }
+}
+
 #[no_mangle]
 #[allow(non_snake_case)]
 pub extern "C" fn ringrtcSetGroupMembers(
@@ -13,18 +13,22 @@ use crate::ios::api::call_manager_interface::{AppCallContext, AppInterface, AppO
 use crate::ios::ios_platform::IosPlatform;
 
 use crate::common::{CallId, CallMediaType, DeviceId, Result};
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::core::bandwidth_mode::{BandwidthMode, BandwidthPolicy};
 use crate::core::call_manager::CallManager;
+use crate::core::send_rate_policy::DeviceConditions;
+use crate::core::speaker_detector::TalkingWhileMutedConfig;
 use crate::core::util::{ptr_as_box, ptr_as_mut, uuid_to_string};
 use crate::core::{group_call, signaling};
 use crate::error::RingRtcError;
 use crate::lite::{
     http,
-    sfu::{GroupMember, UserId},
+    sfu::{DemuxId, GroupMember, UserId},
 };
 use crate::webrtc;
 use crate::webrtc::media;
+use crate::webrtc::peer_connection::AudioLevel;
 use crate::webrtc::peer_connection_factory::{self as pcf, PeerConnectionFactory};
+use crate::webrtc::video_frame_processor::{checked_frame_size, CropProcessor, ScaleProcessor};
 
 /// Public type for iOS CallManager
 pub type IosCallManager = CallManager<IosPlatform>;
@@ -377,6 +381,72 @@ pub fn get_active_call_context(call_manager: *mut IosCallManager) -> Result<*mut
     Ok(app_call_context.object)
 }
 
+/// CMI request to set the audio status
+pub fn set_audio_enable(call_manager: *mut IosCallManager, enable: bool) -> Result<()> {
+    info!("set_audio_enable():");
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_direct_outgoing_audio_muted(!enable)
+}
+
+/// CMI request to configure when to notify that the user is talking while muted
+pub fn set_talking_while_muted_config(
+    call_manager: *mut IosCallManager,
+    min_level: AudioLevel,
+    min_duration: Duration,
+    max_pause: Duration,
+    min_interval: Duration,
+) -> Result<()> {
+    info!("set_talking_while_muted_config():");
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_talking_while_muted_config(TalkingWhileMutedConfig {
+        min_level,
+        min_duration,
+        max_pause,
+        min_interval,
+    })
+}
+
+pub fn add_video_frame_crop_to_aspect_ratio(
+    call_manager: *mut IosCallManager,
+    width: u32,
+    height: u32,
+) -> Result<()> {
+    info!(
+        "add_video_frame_crop_to_aspect_ratio(): {}:{}",
+        width, height
+    );
+
+    let (width, height) = checked_frame_size(width.into(), height.into())?;
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.add_video_frame_processor(Box::new(CropProcessor::center_to_aspect_ratio(
+        width, height,
+    )));
+    Ok(())
+}
+
+pub fn add_video_frame_scale(
+    call_manager: *mut IosCallManager,
+    width: u32,
+    height: u32,
+) -> Result<()> {
+    info!("add_video_frame_scale(): {}x{}", width, height);
+
+    let (width, height) = checked_frame_size(width.into(), height.into())?;
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.add_video_frame_processor(Box::new(ScaleProcessor::new(width, height)));
+    Ok(())
+}
+
+pub fn clear_video_frame_processors(call_manager: *mut IosCallManager) -> Result<()> {
+    info!("clear_video_frame_processors():");
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.clear_video_frame_processors();
+    Ok(())
+}
+
 /// CMI request to set the video status
 pub fn set_video_enable(call_manager: *mut IosCallManager, enable: bool) -> Result<()> {
     info!("set_video_enable():");
@@ -401,6 +471,17 @@ pub fn update_bandwidth_mode(
     active_connection.inject_update_bandwidth_mode(bandwidth_mode)
 }
 
+/// Request to update the bandwidth policy on the direct connection
+pub fn update_bandwidth_policy(
+    call_manager: *mut IosCallManager,
+    bandwidth_policy: BandwidthPolicy,
+) -> Result<()> {
+    info!("update_bandwidth_policy():");
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.update_bandwidth_policy(bandwidth_policy)
+}
+
 /// CMI request to drop the active call
 pub fn drop_call(call_manager: *mut IosCallManager, call_id: u64) -> Result<()> {
     let call_id = CallId::from(call_id);
@@ -457,13 +538,22 @@ pub fn create_group_call_client(
         Some(peer_connection_factory.rffi().clone()),
     );
 
-    let outgoing_video_track = media::VideoTrack::new(
+    let platform_video_track = media::VideoTrack::new(
         webrtc::Arc::from_owned(native_video_track),
         Some(peer_connection_factory.rffi().clone()),
     );
 
     let call_manager = unsafe { ptr_as_mut(call_manager)? };
-    call_manager.create_group_call_client(
+
+    // Send the captured video through the call manager's frame processors.
+    let outgoing_video_source = peer_connection_factory.create_processed_video_source(
+        &platform_video_track,
+        call_manager.video_frame_processors(),
+    )?;
+    let outgoing_video_track =
+        peer_connection_factory.create_outgoing_video_track(&outgoing_video_source)?;
+
+    let client_id = call_manager.create_group_call_client(
         group_id,
         sfu_url,
         hkdf_extra_info,
@@ -472,7 +562,10 @@ pub fn create_group_call_client(
         outgoing_audio_track,
         outgoing_video_track,
         None,
-    )
+    )?;
+    // Only this client, so the source stops processing when the client is deleted.
+    call_manager.set_group_call_outgoing_video_source(client_id, outgoing_video_source);
+    Ok(client_id)
 }
 
 pub fn delete_group_call_client(
@@ -533,6 +626,56 @@ pub fn group_ring(
     Ok(())
 }
 
+pub fn set_local_display_name(
+    call_manager: *mut IosCallManager,
+    client_id: group_call::ClientId,
+    display_name: Option<String>,
+) -> Result<()> {
+    info!("set_local_display_name(): id: {}", client_id);
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_local_display_name(client_id, display_name);
+    Ok(())
+}
+
+pub fn set_local_avatar_hash(
+    call_manager: *mut IosCallManager,
+    client_id: group_call::ClientId,
+    avatar_hash: Option<Vec<u8>>,
+) -> Result<()> {
+    info!("set_local_avatar_hash(): id: {}", client_id);
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_local_avatar_hash(client_id, avatar_hash);
+    Ok(())
+}
+
+pub fn set_remote_audio_volume(
+    call_manager: *mut IosCallManager,
+    client_id: group_call::ClientId,
+    demux_id: DemuxId,
+    volume: f64,
+) -> Result<()> {
+    info!("set_remote_audio_volume(): id: {}", client_id);
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_remote_audio_volume(client_id, demux_id, volume);
+    Ok(())
+}
+
+pub fn set_remote_audio_muted(
+    call_manager: *mut IosCallManager,
+    client_id: group_call::ClientId,
+    demux_id: DemuxId,
+    muted: bool,
+) -> Result<()> {
+    info!("set_remote_audio_muted(): id: {}", client_id);
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_remote_audio_muted(client_id, demux_id, muted);
+    Ok(())
+}
+
 pub fn set_outgoing_audio_muted(
     call_manager: *mut IosCallManager,
     client_id: group_call::ClientId,
@@ -557,6 +700,18 @@ pub fn set_outgoing_video_muted(
     Ok(())
 }
 
+pub fn set_audio_only(
+    call_manager: *mut IosCallManager,
+    client_id: group_call::ClientId,
+    audio_only: bool,
+) -> Result<()> {
+    info!("set_audio_only(): id: {}", client_id);
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_audio_only(client_id, audio_only);
+    Ok(())
+}
+
 pub fn resend_media_keys(
     call_manager: *mut IosCallManager,
     client_id: group_call::ClientId,
@@ -580,6 +735,30 @@ pub fn set_bandwidth_mode(
     Ok(())
 }
 
+pub fn set_bandwidth_policy(
+    call_manager: *mut IosCallManager,
+    client_id: group_call::ClientId,
+    bandwidth_policy: BandwidthPolicy,
+) -> Result<()> {
+    info!("set_bandwidth_policy(): id: {}", client_id);
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_bandwidth_policy(client_id, bandwidth_policy);
+    Ok(())
+}
+
+pub fn set_device_conditions(
+    call_manager: *mut IosCallManager,
+    client_id: group_call::ClientId,
+    device_conditions: DeviceConditions,
+) -> Result<()> {
+    info!("set_device_conditions(): id: {}", client_id);
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_device_conditions(client_id, device_conditions);
+    Ok(())
+}
+
 pub fn request_video(
     call_manager: *mut IosCallManager,
     client_id: group_call::ClientId,
@@ -593,6 +772,30 @@ pub fn request_video(
     Ok(())
 }
 
+pub fn set_spotlight(
+    call_manager: *mut IosCallManager,
+    client_id: group_call::ClientId,
+    demux_id: Option<DemuxId>,
+) -> Result<()> {
+    info!("set_spotlight(): id: {}", client_id);
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_spotlight(client_id, demux_id);
+    Ok(())
+}
+
+pub fn set_video_request_policy(
+    call_manager: *mut IosCallManager,
+    client_id: group_call::ClientId,
+    policy: Option<group_call::VideoRequestPolicy>,
+) -> Result<()> {
+    info!("set_video_request_policy(): id: {}", client_id);
+
+    let call_manager = unsafe { ptr_as_mut(call_manager)? };
+    call_manager.set_video_request_policy(client_id, policy);
+    Ok(())
+}
+
 pub fn set_group_members(
     call_manager: *mut IosCallManager,
     client_id: group_call::ClientId,
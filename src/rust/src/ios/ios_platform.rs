@@ -11,7 +11,7 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use crate::common::{ApplicationEvent, CallDirection, CallId, CallMediaType, DeviceId, Result};
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::core::bandwidth_mode::BandwidthPolicy;
 use crate::core::call::Call;
 use crate::core::connection::{Connection, ConnectionType};
 use crate::core::platform::{Platform, PlatformItem};
@@ -95,15 +95,15 @@ impl Platform for IosPlatform {
         remote_device_id: DeviceId,
         connection_type: ConnectionType,
         signaling_version: signaling::Version,
-        bandwidth_mode: BandwidthMode,
+        bandwidth_policy: BandwidthPolicy,
         audio_levels_interval: Option<Duration>,
     ) -> Result<Connection<Self>> {
         info!(
-            "create_connection(): call_id: {} remote_device_id: {}, signaling_version: {:?}, bandwidth_mode: {}, audio_levels_interval: {:?}",
+            "create_connection(): call_id: {} remote_device_id: {}, signaling_version: {:?}, bandwidth_policy: {:?}, audio_levels_interval: {:?}",
             call.call_id(),
             remote_device_id,
             signaling_version,
-            bandwidth_mode,
+            bandwidth_policy,
             audio_levels_interval,
         );
 
@@ -111,7 +111,7 @@ impl Platform for IosPlatform {
             call.clone(),
             remote_device_id,
             connection_type,
-            bandwidth_mode,
+            bandwidth_policy,
             audio_levels_interval,
             None, // The app adds sinks to VideoTracks.
         )?;
@@ -235,6 +235,13 @@ impl Platform for IosPlatform {
         Ok(())
     }
 
+    fn on_talking_while_muted(&self, remote_peer: &Self::AppRemotePeer) -> Result<()> {
+        info!("on_talking_while_muted():");
+        (self.app_interface.onTalkingWhileMuted)(self.app_interface.object, remote_peer.ptr);
+
+        Ok(())
+    }
+
     fn on_send_offer(
         &self,
         remote_peer: &Self::AppRemotePeer,
@@ -578,6 +585,29 @@ impl Platform for IosPlatform {
         );
     }
 
+    fn handle_talking_while_muted(&self, client_id: group_call::ClientId) {
+        info!("handle_talking_while_muted(): client_id: {}", client_id);
+
+        (self.app_interface.handleTalkingWhileMuted)(self.app_interface.object, client_id);
+    }
+
+    fn handle_spotlight_changed(
+        &self,
+        client_id: group_call::ClientId,
+        spotlight_demux_id: Option<DemuxId>,
+    ) {
+        info!(
+            "handle_spotlight_changed(): client_id: {} demux_id: {:?}",
+            client_id, spotlight_demux_id
+        );
+
+        (self.app_interface.handleSpotlightChanged)(
+            self.app_interface.object,
+            client_id,
+            app_option_from_u32(spotlight_demux_id),
+        );
+    }
+
     fn handle_join_state_changed(
         &self,
         client_id: group_call::ClientId,
@@ -617,6 +647,12 @@ impl Platform for IosPlatform {
                 speakerTime: remote_device_state.speaker_time_as_unix_millis(),
                 forwardingVideo: app_option_from_bool(remote_device_state.forwarding_video),
                 isHigherResolutionPending: remote_device_state.is_higher_resolution_pending,
+                spotlighted: remote_device_state.spotlighted,
+                displayName: app_slice_from_str(remote_device_state.profile.display_name.as_ref()),
+                avatarHash: app_slice_from_bytes(remote_device_state.profile.avatar_hash.as_ref()),
+                videoSlot: app_option_from_u32(
+                    remote_device_state.video_slot.map(|slot| slot as u32),
+                ),
             };
 
             app_remote_device_states.push(app_remote_device_state);
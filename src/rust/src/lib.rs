@@ -30,6 +30,7 @@ mod error;
 
 // Doesn't depend on WebRTC
 pub mod lite {
+    pub mod call_links;
     pub mod ffi;
     pub mod http;
     pub mod logging;
@@ -38,6 +39,7 @@ pub mod lite {
 
 /// Core, platform independent functionality.
 pub mod core {
+    pub mod audio_adaptation;
     pub mod bandwidth_mode;
     pub mod call;
     pub mod call_fsm;
@@ -48,7 +50,10 @@ pub mod core {
     pub mod crypto;
     pub mod group_call;
     pub mod platform;
+    pub mod recorder;
+    pub mod send_rate_policy;
     pub mod signaling;
+    pub mod speaker_detector;
     pub mod util;
 }
 
@@ -110,6 +115,9 @@ pub mod webrtc {
     pub mod rtp;
     pub mod sdp_observer;
     pub mod stats_observer;
+    pub mod test_media;
+    pub mod video_frame_buffer;
+    pub mod video_frame_processor;
     #[cfg(not(feature = "sim"))]
     mod ffi {
         pub mod ice_gatherer;
@@ -143,5 +151,11 @@ pub mod sim {
 
 #[cfg(feature = "simnet")]
 pub mod simnet {
+    pub mod impairment;
+    pub mod nat;
+    pub mod pcap;
     pub mod router;
+    pub mod scenario;
+    pub mod stun;
+    pub mod turn;
 }
@@ -0,0 +1,153 @@
+//
+// Copyright 2022 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Define the types needed to join a call through a link rather than
+//! through membership in a group, like CallLinkRootKey and RoomId.
+
+use std::convert::TryInto;
+
+use aes::cipher::{NewCipher, StreamCipher};
+use aes::Aes256Ctr;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac, NewMac};
+use rand::{CryptoRng, Rng};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+use crate::lite::sfu::UserId;
+
+/// Identifies the "room" of a call link to the SFU.
+/// Derived from the CallLinkRootKey so that anyone with the link can find the room.
+pub type RoomId = Vec<u8>;
+
+/// Proof to the SFU that we have a credential for the call link.
+/// Used as authentication for peeking and joining in place of a MembershipProof.
+pub type CallLinkAuthCredentialPresentation = Vec<u8>;
+
+/// The secret shared by everyone who has the link.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CallLinkRootKey {
+    bytes: [u8; Self::LEN],
+}
+
+impl CallLinkRootKey {
+    pub const LEN: usize = 16;
+
+    pub fn generate(mut rng: impl Rng + CryptoRng) -> Self {
+        let mut bytes = [0u8; Self::LEN];
+        rng.fill(&mut bytes);
+        Self { bytes }
+    }
+
+    pub fn try_from(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            bytes: bytes.try_into().ok()?,
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Derives the RoomId with which the SFU knows the call.
+    ///
+    /// ```
+    /// use ringrtc::lite::call_links::CallLinkRootKey;
+    ///
+    /// let root_key = CallLinkRootKey::try_from(&[0x43; 16]).unwrap();
+    /// assert_eq!(root_key.derive_room_id().len(), 32);
+    /// assert_eq!(root_key.derive_room_id(), root_key.derive_room_id());
+    /// assert!(CallLinkRootKey::try_from(&[0x43; 15]).is_none());
+    /// ```
+    pub fn derive_room_id(&self) -> RoomId {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.bytes).expect("HMAC can take a key of any size");
+        mac.update(b"20220919-Signal-CallLinkRootKey-RoomId");
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    /// Encrypts a user ID the way it is presented to the SFU, which knows
+    /// participants of a call link only by these ciphertexts.
+    /// The encryption is deterministic so that the same user always gets the
+    /// same ciphertext within a call link.
+    ///
+    /// ```
+    /// use ringrtc::lite::call_links::CallLinkRootKey;
+    ///
+    /// let root_key = CallLinkRootKey::try_from(&[0x43; 16]).unwrap();
+    /// let ciphertext = root_key.encrypt_user_id(b"user");
+    /// assert_eq!(ciphertext, root_key.encrypt_user_id(b"user"));
+    /// assert_ne!(ciphertext, root_key.encrypt_user_id(b"other user"));
+    /// assert_eq!(Some(b"user".to_vec()), root_key.decrypt_user_id(&ciphertext));
+    ///
+    /// let other_root_key = CallLinkRootKey::try_from(&[0x44; 16]).unwrap();
+    /// assert_eq!(None, other_root_key.decrypt_user_id(&ciphertext));
+    /// assert_eq!(None, root_key.decrypt_user_id(&ciphertext[1..]));
+    /// ```
+    pub fn encrypt_user_id(&self, user_id: &[u8]) -> Vec<u8> {
+        let (aes_key, hmac_key) = self.derive_user_id_keys();
+        // The MAC doubles as the IV (as in AES-SIV).
+        let iv = user_id_mac(&hmac_key, user_id);
+        let mut ciphertext = user_id.to_vec();
+        Aes256Ctr::new(&aes_key.into(), &iv.into()).apply_keystream(&mut ciphertext);
+        [&iv[..], &ciphertext[..]].concat()
+    }
+
+    /// Decrypts a user ID the SFU knows a participant of a call link by.
+    /// Returns None if it wasn't encrypted with this root key.
+    pub fn decrypt_user_id(&self, ciphertext: &[u8]) -> Option<UserId> {
+        if ciphertext.len() < USER_ID_MAC_LEN {
+            return None;
+        }
+        let (iv, ciphertext) = ciphertext.split_at(USER_ID_MAC_LEN);
+        let (aes_key, hmac_key) = self.derive_user_id_keys();
+        let mut user_id = ciphertext.to_vec();
+        Aes256Ctr::new(&aes_key.into(), iv.into()).apply_keystream(&mut user_id);
+        if bool::from(user_id_mac(&hmac_key, &user_id).ct_eq(iv)) {
+            Some(user_id)
+        } else {
+            None
+        }
+    }
+
+    fn derive_user_id_keys(&self) -> ([u8; 32], [u8; 32]) {
+        let hkdf = Hkdf::<Sha256>::new(None, &self.bytes);
+        let mut aes_key = [0u8; 32];
+        let mut hmac_key = [0u8; 32];
+        hkdf.expand(
+            b"20220919-Signal-CallLinkRootKey-UserIdAesKey",
+            &mut aes_key,
+        )
+        .expect("HKDF can produce 32 bytes");
+        hkdf.expand(
+            b"20220919-Signal-CallLinkRootKey-UserIdHmacKey",
+            &mut hmac_key,
+        )
+        .expect("HKDF can produce 32 bytes");
+        (aes_key, hmac_key)
+    }
+}
+
+const USER_ID_MAC_LEN: usize = 16;
+
+fn user_id_mac(hmac_key: &[u8], user_id: &[u8]) -> [u8; USER_ID_MAC_LEN] {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(hmac_key).expect("HMAC can take a key of any size");
+    mac.update(user_id);
+    mac.finalize().into_bytes()[..USER_ID_MAC_LEN]
+        .try_into()
+        .expect("HMAC-SHA256 is longer than the MAC")
+}
+
+impl std::fmt::Debug for CallLinkRootKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Don't log the secret.
+        f.write_str("CallLinkRootKey(...)")
+    }
+}
+
+pub fn auth_header_from_auth_credential_presentation(presentation: &[u8]) -> String {
+    format!("Bearer auth.{}", base64::encode(presentation))
+}
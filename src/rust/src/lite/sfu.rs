@@ -5,6 +5,9 @@
 
 //! Make calls to an SFU to see who is in the call.
 //! and define common types like PeekInfo, MembershipProof, MemberInfo
+//!
+//! Calls can be identified by a group (authenticated with a MembershipProof)
+//! or by a call link (authenticated with a CallLinkAuthCredentialPresentation).
 
 use std::{
     collections::{HashMap, HashSet},
@@ -18,7 +21,7 @@ use serde::Deserialize;
 use serde_json::json;
 use sha2::{Digest, Sha256};
 
-use crate::lite::http;
+use crate::lite::{call_links::CallLinkRootKey, http};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[repr(u16)]
@@ -119,6 +122,32 @@ impl SerializedPeekInfo {
     }
 }
 
+impl SerializedPeekInfo {
+    // Participants of a call link aren't necessarily members of a group,
+    // so there is nothing to map their opaque user IDs through.
+    // Instead, the opaque user ID is the hex encoding of the user ID ciphertext,
+    // which can be decrypted with the CallLinkRootKey.
+    fn into_call_link_peek_info(self, root_key: &CallLinkRootKey) -> PeekInfo {
+        let decrypt_user_id =
+            |opaque_user_id: &str| root_key.decrypt_user_id(&hex::decode(opaque_user_id).ok()?);
+        let device_count = self.devices.len() as u32;
+        PeekInfo {
+            devices: self
+                .devices
+                .iter()
+                .map(|device| PeekDeviceInfo {
+                    demux_id: device.demux_id,
+                    user_id: decrypt_user_id(&device.opaque_user_id),
+                })
+                .collect(),
+            creator: self.creator.as_deref().and_then(decrypt_user_id),
+            era_id: self.era_id,
+            max_devices: self.max_devices,
+            device_count,
+        }
+    }
+}
+
 impl SerializedPeekDeviceInfo {
     fn deobfuscate(self, opaque_user_id_mappings: &[OpaqueUserIdMapping]) -> PeekDeviceInfo {
         PeekDeviceInfo {
@@ -299,6 +328,14 @@ fn parse_http_json_response<'a, D: Deserialize<'a>>(
     Ok(deserialized)
 }
 
+fn auth_headers(auth_header: String, room_id: Option<&[u8]>) -> HashMap<String, String> {
+    let mut headers = HashMap::from_iter([("Authorization".to_string(), auth_header)]);
+    if let Some(room_id) = room_id {
+        headers.insert("X-Room-Id".to_string(), room_id.encode_hex::<String>());
+    }
+    headers
+}
+
 pub type PeekResult = Result<PeekInfo, http::ResponseStatus>;
 pub type PeekResultCallback = Box<dyn FnOnce(PeekResult) + Send>;
 
@@ -308,12 +345,47 @@ pub fn peek(
     auth_header: String,
     opaque_user_id_mappings: Vec<OpaqueUserIdMapping>,
     result_callback: PeekResultCallback,
+) {
+    send_peek_request(
+        http_client,
+        sfu_url,
+        auth_headers(auth_header, None),
+        Box::new(move |deserialized| deserialized.deobfuscate(&opaque_user_id_mappings)),
+        result_callback,
+    )
+}
+
+/// Like peek(), but for the room of a call link rather than a group.
+/// The user IDs of the result are decrypted with the root key.
+pub fn peek_call_link(
+    http_client: &dyn http::Client,
+    sfu_url: &str,
+    root_key: &CallLinkRootKey,
+    auth_header: String,
+    result_callback: PeekResultCallback,
+) {
+    let root_key = *root_key;
+    send_peek_request(
+        http_client,
+        sfu_url,
+        auth_headers(auth_header, Some(&root_key.derive_room_id())),
+        Box::new(move |deserialized| deserialized.into_call_link_peek_info(&root_key)),
+        result_callback,
+    )
+}
+
+fn send_peek_request(
+    http_client: &dyn http::Client,
+    sfu_url: &str,
+    headers: HashMap<String, String>,
+    into_peek_info: Box<dyn FnOnce(SerializedPeekInfo) -> PeekInfo + Send>,
+    result_callback: PeekResultCallback,
 ) {
     http_client.send_request(
         http::Request {
             method: http::Method::Get,
             url: participants_url_from_sfu_url(sfu_url),
-            headers: HashMap::from_iter([("Authorization".to_string(), auth_header)]),
+            headers,
             body: None,
         },
         Box::new(move |http_response| {
@@ -324,7 +396,7 @@ pub fn peek(
                             "Got group call peek result with device count = {}",
                             deserialized.devices.len()
                         );
-                        Ok(deserialized.deobfuscate(&opaque_user_id_mappings))
+                        Ok(into_peek_info(deserialized))
                     }
                     Err(status) if status == ResponseCode::GroupCallNotStarted => {
                         info!("Got group call peek result with device count = 0 (status code 404)");
@@ -357,14 +429,57 @@ pub fn join(
 ) {
     info!("sfu::Join(): ");
 
+    send_join_request(
+        http_client,
+        sfu_url,
+        auth_headers(auth_header, None),
+        client_ice_ufrag,
+        client_dhe_pub_key,
+        hkdf_extra_info,
+        result_callback,
+    )
+}
+
+/// Like join(), but for the room of a call link rather than a group.
+#[allow(clippy::too_many_arguments)]
+pub fn join_call_link(
+    http_client: &dyn http::Client,
+    sfu_url: &str,
+    room_id: &[u8],
+    auth_header: String,
+    client_ice_ufrag: &str,
+    client_dhe_pub_key: &[u8],
+    hkdf_extra_info: &[u8],
+    result_callback: JoinResultCallback,
+) {
+    info!("sfu::join_call_link(): ");
+
+    send_join_request(
+        http_client,
+        sfu_url,
+        auth_headers(auth_header, Some(room_id)),
+        client_ice_ufrag,
+        client_dhe_pub_key,
+        hkdf_extra_info,
+        result_callback,
+    )
+}
+
+fn send_join_request(
+    http_client: &dyn http::Client,
+    sfu_url: &str,
+    mut headers: HashMap<String, String>,
+    client_ice_ufrag: &str,
+    client_dhe_pub_key: &[u8],
+    hkdf_extra_info: &[u8],
+    result_callback: JoinResultCallback,
+) {
+    headers.insert("Content-Type".to_string(), "application/json".to_string());
     http_client.send_request(
         http::Request {
             method: http::Method::Put,
             url: participants_url_from_sfu_url(sfu_url),
-            headers: HashMap::from_iter([
-                ("Authorization".to_string(), auth_header),
-                ("Content-Type".to_string(), "application/json".to_string()),
-            ]),
+            headers,
             body: Some(
                 json!({
                     "iceUfrag" : client_ice_ufrag,
@@ -558,3 +673,257 @@ pub mod ios {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    // Records requests and responds immediately with a canned response.
+    #[derive(Clone)]
+    struct FakeHttpClient {
+        response: http::Response,
+        requests: Arc<Mutex<Vec<http::Request>>>,
+    }
+
+    impl FakeHttpClient {
+        fn new(code: u16, body: &str) -> Self {
+            Self {
+                response: http::Response {
+                    status: code.into(),
+                    body: body.as_bytes().to_vec(),
+                },
+                requests: Default::default(),
+            }
+        }
+
+        fn requests(&self) -> Vec<http::Request> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    impl http::Client for FakeHttpClient {
+        fn send_request(&self, request: http::Request, callback: http::ResponseCallback) {
+            self.requests.lock().unwrap().push(request);
+            callback(Some(self.response.clone()))
+        }
+    }
+
+    fn peek_result(peek: impl FnOnce(PeekResultCallback)) -> PeekResult {
+        let result = Arc::new(Mutex::new(None));
+        let result_to_set = result.clone();
+        peek(Box::new(move |peek_result| {
+            *result_to_set.lock().unwrap() = Some(peek_result);
+        }));
+        let result = result.lock().unwrap().take();
+        result.expect("peek result")
+    }
+
+    fn user_ids(peek_info: &PeekInfo) -> Vec<Option<UserId>> {
+        peek_info
+            .devices
+            .iter()
+            .map(|device| device.user_id.clone())
+            .collect()
+    }
+
+    #[test]
+    fn peek_group() {
+        let members = [
+            GroupMember {
+                user_id: b"alice".to_vec(),
+                member_id: b"alice ciphertext".to_vec(),
+            },
+            GroupMember {
+                user_id: b"bob".to_vec(),
+                member_id: b"bob ciphertext".to_vec(),
+            },
+        ];
+        let body = json!({
+            "conferenceId": "era",
+            "maxDevices": 16,
+            "creator": sha256_as_hexstring(b"bob ciphertext"),
+            "participants": [
+                {"opaqueUserId": sha256_as_hexstring(b"alice ciphertext"), "demuxId": 16},
+                {"opaqueUserId": sha256_as_hexstring(b"bob ciphertext"), "demuxId": 32},
+                {"opaqueUserId": "unknown", "demuxId": 48},
+            ],
+        })
+        .to_string();
+        let http_client = FakeHttpClient::new(200, &body);
+
+        let peek_info = peek_result(|callback| {
+            peek(
+                &http_client,
+                "https://sfu.example/",
+                "auth".to_string(),
+                opaque_user_id_mappings_from_group_members(&members),
+                callback,
+            )
+        })
+        .expect("successful peek");
+        assert_eq!(
+            vec![Some(b"alice".to_vec()), Some(b"bob".to_vec()), None],
+            user_ids(&peek_info)
+        );
+        assert_eq!(
+            vec![16, 32, 48],
+            peek_info
+                .devices
+                .iter()
+                .map(|device| device.demux_id)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(b"bob".to_vec()), peek_info.creator);
+        assert_eq!(Some("era"), peek_info.era_id.as_deref());
+        assert_eq!(Some(16), peek_info.max_devices);
+        assert_eq!(3, peek_info.device_count);
+
+        let requests = http_client.requests();
+        assert_eq!(1, requests.len());
+        assert_eq!(http::Method::Get, requests[0].method);
+        assert_eq!(
+            "https://sfu.example/v2/conference/participants",
+            requests[0].url
+        );
+        assert_eq!(
+            HashMap::from_iter([("Authorization".to_string(), "auth".to_string())]),
+            requests[0].headers
+        );
+    }
+
+    #[test]
+    fn peek_call_link() {
+        let root_key = CallLinkRootKey::try_from(&[0x43; 16]).unwrap();
+        let other_root_key = CallLinkRootKey::try_from(&[0x44; 16]).unwrap();
+        let alice = hex::encode(root_key.encrypt_user_id(b"alice"));
+        let body = json!({
+            "creator": alice,
+            "participants": [
+                {"opaqueUserId": alice, "demuxId": 16},
+                {"opaqueUserId": hex::encode(other_root_key.encrypt_user_id(b"eve")), "demuxId": 32},
+                {"opaqueUserId": "not hex", "demuxId": 48},
+            ],
+        })
+        .to_string();
+        let http_client = FakeHttpClient::new(200, &body);
+
+        let peek_info = peek_result(|callback| {
+            super::peek_call_link(
+                &http_client,
+                "https://sfu.example",
+                &root_key,
+                "auth".to_string(),
+                callback,
+            )
+        })
+        .expect("successful peek");
+        assert_eq!(
+            vec![Some(b"alice".to_vec()), None, None],
+            user_ids(&peek_info)
+        );
+        assert_eq!(Some(b"alice".to_vec()), peek_info.creator);
+        assert_eq!(3, peek_info.device_count);
+
+        let requests = http_client.requests();
+        assert_eq!(1, requests.len());
+        assert_eq!(
+            Some(&root_key.derive_room_id().encode_hex::<String>()),
+            requests[0].headers.get("X-Room-Id")
+        );
+        assert_eq!(
+            Some(&"auth".to_string()),
+            requests[0].headers.get("Authorization")
+        );
+    }
+
+    #[test]
+    fn peek_errors() {
+        let root_key = CallLinkRootKey::try_from(&[0x43; 16]).unwrap();
+        let peek_with = |http_client: &FakeHttpClient| {
+            peek_result(|callback| {
+                super::peek_call_link(
+                    http_client,
+                    "https://sfu.example",
+                    &root_key,
+                    "auth".to_string(),
+                    callback,
+                )
+            })
+        };
+
+        let not_started = peek_with(&FakeHttpClient::new(404, "")).expect("empty peek");
+        assert_eq!(0, not_started.device_count);
+        assert!(not_started.devices.is_empty());
+
+        assert_eq!(
+            ResponseCode::InvalidResponseBodyJson as u16,
+            peek_with(&FakeHttpClient::new(200, "{")).unwrap_err().code
+        );
+        assert_eq!(
+            403,
+            peek_with(&FakeHttpClient::new(403, "")).unwrap_err().code
+        );
+    }
+
+    #[test]
+    fn join_call_link() {
+        let root_key = CallLinkRootKey::try_from(&[0x43; 16]).unwrap();
+        let body = json!({
+            "demuxId": 16,
+            "ip": "192.0.2.1",
+            "port": 10000,
+            "iceUfrag": "server ufrag",
+            "icePwd": "server pwd",
+            "dhePublicKey": hex::encode([1u8; 32]),
+        })
+        .to_string();
+        let http_client = FakeHttpClient::new(200, &body);
+
+        let result = Arc::new(Mutex::new(None));
+        let result_to_set = result.clone();
+        super::join_call_link(
+            &http_client,
+            "https://sfu.example",
+            &root_key.derive_room_id(),
+            "auth".to_string(),
+            "client ufrag",
+            &[2u8; 32],
+            b"extra info",
+            Box::new(move |join_result| {
+                *result_to_set.lock().unwrap() = Some(join_result);
+            }),
+        );
+        let join_response = result
+            .lock()
+            .unwrap()
+            .take()
+            .expect("join result")
+            .expect("successful join");
+        assert_eq!(16, join_response.client_demux_id);
+        assert_eq!(
+            vec!["192.0.2.1:10000".parse::<SocketAddr>().unwrap()],
+            join_response.server_addresses
+        );
+        assert_eq!([1u8; 32], join_response.server_dhe_pub_key);
+
+        let requests = http_client.requests();
+        assert_eq!(1, requests.len());
+        assert_eq!(http::Method::Put, requests[0].method);
+        assert_eq!(
+            HashMap::from_iter([
+                ("Authorization".to_string(), "auth".to_string()),
+                (
+                    "X-Room-Id".to_string(),
+                    root_key.derive_room_id().encode_hex::<String>()
+                ),
+                ("Content-Type".to_string(), "application/json".to_string()),
+            ]),
+            requests[0].headers
+        );
+        let body: serde_json::Value =
+            serde_json::from_slice(requests[0].body.as_ref().unwrap()).unwrap();
+        assert_eq!("client ufrag", body["iceUfrag"]);
+        assert_eq!(hex::encode([2u8; 32]), body["dhePublicKey"]);
+    }
+}
@@ -8,7 +8,7 @@ use std::fmt;
 use std::time::Duration;
 
 use crate::common::{ApplicationEvent, CallDirection, CallId, CallMediaType, DeviceId, Result};
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::core::bandwidth_mode::BandwidthPolicy;
 use crate::core::call::Call;
 use crate::core::connection::{Connection, ConnectionType};
 use crate::core::platform::{Platform, PlatformItem};
@@ -126,6 +126,7 @@ pub trait CallStateHandler {
         captured_level: AudioLevel,
         received_level: AudioLevel,
     ) -> Result<()>;
+    fn handle_talking_while_muted(&self, remote_peer_id: &str) -> Result<()>;
 }
 
 // These are the different states a call can be in.
@@ -247,6 +248,8 @@ pub enum GroupUpdate {
     },
     NetworkRouteChanged(group_call::ClientId, NetworkRoute),
     AudioLevels(group_call::ClientId, AudioLevel, Vec<ReceivedAudioLevel>),
+    TalkingWhileMuted(group_call::ClientId),
+    SpotlightChanged(group_call::ClientId, Option<DemuxId>),
 }
 
 impl fmt::Display for GroupUpdate {
@@ -267,6 +270,10 @@ impl fmt::Display for GroupUpdate {
             GroupUpdate::AudioLevels(_, captured_level, received_levels) => {
                 format!("AudioLevels({:?}, {:?})", captured_level, received_levels)
             }
+            GroupUpdate::TalkingWhileMuted(_) => "TalkingWhileMuted".to_string(),
+            GroupUpdate::SpotlightChanged(_, spotlight_demux_id) => {
+                format!("SpotlightChanged({:?})", spotlight_demux_id)
+            }
         };
         write!(f, "({})", display)
     }
@@ -395,7 +402,7 @@ impl Platform for NativePlatform {
         remote_device_id: DeviceId,
         connection_type: ConnectionType,
         signaling_version: signaling::Version,
-        bandwidth_mode: BandwidthMode,
+        bandwidth_policy: BandwidthPolicy,
         audio_levels_interval: Option<Duration>,
     ) -> Result<Connection<Self>> {
         info!(
@@ -409,7 +416,7 @@ impl Platform for NativePlatform {
             call.clone(),
             remote_device_id,
             connection_type,
-            bandwidth_mode,
+            bandwidth_policy,
             audio_levels_interval,
             Some(context.incoming_video_sink),
         )?;
@@ -623,6 +630,15 @@ impl Platform for NativePlatform {
         Ok(())
     }
 
+    fn on_talking_while_muted(&self, remote_peer: &Self::AppRemotePeer) -> Result<()> {
+        info!(
+            "NativePlatform::on_talking_while_muted(): remote_peer: {}",
+            remote_peer
+        );
+
+        self.state_handler.handle_talking_while_muted(remote_peer)
+    }
+
     fn on_offer_expired(
         &self,
         remote_peer: &Self::AppRemotePeer,
@@ -846,6 +862,35 @@ impl Platform for NativePlatform {
         }
     }
 
+    fn handle_talking_while_muted(&self, client_id: group_call::ClientId) {
+        info!(
+            "NativePlatform::handle_talking_while_muted(): id: {}",
+            client_id
+        );
+
+        let result = self.send_group_update(GroupUpdate::TalkingWhileMuted(client_id));
+        if result.is_err() {
+            error!("{:?}", result.err());
+        }
+    }
+
+    fn handle_spotlight_changed(
+        &self,
+        client_id: group_call::ClientId,
+        spotlight_demux_id: Option<DemuxId>,
+    ) {
+        info!(
+            "NativePlatform::handle_spotlight_changed(): id: {} demux_id: {:?}",
+            client_id, spotlight_demux_id
+        );
+
+        let result =
+            self.send_group_update(GroupUpdate::SpotlightChanged(client_id, spotlight_demux_id));
+        if result.is_err() {
+            error!("{:?}", result.err());
+        }
+    }
+
     fn handle_join_state_changed(
         &self,
         client_id: group_call::ClientId,
@@ -12,7 +12,7 @@ use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use crate::common::{ApplicationEvent, CallDirection, CallId, CallMediaType, DeviceId, Result};
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::core::bandwidth_mode::BandwidthPolicy;
 use crate::core::call::Call;
 use crate::core::call_manager::CallManager;
 use crate::core::connection::{Connection, ConnectionType};
@@ -63,6 +63,8 @@ struct SimStats {
     call_concluded: AtomicUsize,
     /// Track stream counts
     stream_count: AtomicUsize,
+    /// Number of talking while muted notifications
+    talking_while_muted: AtomicUsize,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -136,7 +138,7 @@ impl Platform for SimPlatform {
         remote_device_id: DeviceId,
         connection_type: ConnectionType,
         signaling_version: signaling::Version,
-        bandwidth_mode: BandwidthMode,
+        bandwidth_policy: BandwidthPolicy,
         audio_levels_interval: Option<Duration>,
     ) -> Result<Connection<Self>> {
         info!(
@@ -152,7 +154,7 @@ impl Platform for SimPlatform {
             call.clone(),
             remote_device_id,
             connection_type,
-            bandwidth_mode,
+            bandwidth_policy,
             audio_levels_interval,
             None,
         )
@@ -222,6 +224,15 @@ impl Platform for SimPlatform {
         Ok(())
     }
 
+    fn on_talking_while_muted(&self, _remote_peer: &Self::AppRemotePeer) -> Result<()> {
+        info!("on_talking_while_muted():");
+        let _ = self
+            .stats
+            .talking_while_muted
+            .fetch_add(1, Ordering::AcqRel);
+        Ok(())
+    }
+
     fn on_send_offer(
         &self,
         remote_peer: &Self::AppRemotePeer,
@@ -528,6 +539,22 @@ impl Platform for SimPlatform {
         );
     }
 
+    fn handle_talking_while_muted(&self, _client_id: group_call::ClientId) {
+        info!("handle_talking_while_muted():");
+        let _ = self
+            .stats
+            .talking_while_muted
+            .fetch_add(1, Ordering::AcqRel);
+    }
+
+    fn handle_spotlight_changed(
+        &self,
+        _client_id: group_call::ClientId,
+        spotlight_demux_id: Option<DemuxId>,
+    ) {
+        info!("handle_spotlight_changed(): {:?}", spotlight_demux_id);
+    }
+
     fn handle_join_state_changed(
         &self,
         _client_id: group_call::ClientId,
@@ -738,6 +765,10 @@ impl SimPlatform {
         self.stats.call_concluded.load(Ordering::Acquire)
     }
 
+    pub fn talking_while_muted_count(&self) -> usize {
+        self.stats.talking_while_muted.load(Ordering::Acquire)
+    }
+
     pub fn take_group_call_ring_updates(&self) -> Vec<GroupCallRingUpdate> {
         std::mem::take(&mut *self.group_call_ring_updates.lock().unwrap())
     }
@@ -0,0 +1,355 @@
+//
+// Copyright 2022 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Models of how real networks delay, lose, and limit packets, used by the
+//! links in the Router.
+
+use std::{f64::consts::PI, fs, path::Path, sync::Arc, time::Duration, time::Instant};
+
+use rand::{distributions, distributions::Distribution, Rng};
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    common::{
+        units::{DataRate, DataSize},
+        Result,
+    },
+    error::RingRtcError,
+};
+
+fn deserialize_ms<'de, D>(deserializer: D) -> std::result::Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let ms = f64::deserialize(deserializer)?;
+    if ms.is_finite() && ms >= 0.0 {
+        Ok(Duration::from_secs_f64(ms / 1000.0))
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "invalid milliseconds {}",
+            ms
+        )))
+    }
+}
+
+/// How the delay of each packet is chosen.  The delay is always kept between
+/// the link's delay_min and delay_max.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DelayDistribution {
+    /// Anywhere between delay_min and delay_max with equal probability.
+    #[default]
+    Uniform,
+    Normal {
+        #[serde(rename = "mean_ms", deserialize_with = "deserialize_ms")]
+        mean: Duration,
+        #[serde(rename = "std_dev_ms", deserialize_with = "deserialize_ms")]
+        std_dev: Duration,
+    },
+    /// delay_min plus a heavy tail: usually close to delay_min, but
+    /// occasionally much more.  A smaller shape means a heavier tail.
+    Pareto {
+        #[serde(rename = "scale_ms", deserialize_with = "deserialize_ms")]
+        scale: Duration,
+        shape: f64,
+    },
+}
+
+impl DelayDistribution {
+    pub fn sample(&self, min: Duration, max: Duration, rng: &mut impl Rng) -> Duration {
+        let delay = match self {
+            Self::Uniform => {
+                return Duration::from_millis(
+                    distributions::Uniform::new_inclusive(
+                        min.as_millis() as u64,
+                        max.as_millis() as u64,
+                    )
+                    .sample(rng),
+                );
+            }
+            Self::Normal { mean, std_dev } => {
+                // Box-Muller
+                let u1 = 1.0 - rng.gen::<f64>();
+                let u2 = rng.gen::<f64>();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+                mean.as_secs_f64() + z * std_dev.as_secs_f64()
+            }
+            Self::Pareto { scale, shape } => {
+                let u = 1.0 - rng.gen::<f64>();
+                let scale = scale.as_secs_f64();
+                min.as_secs_f64() + scale / u.powf(1.0 / shape) - scale
+            }
+        };
+        Duration::from_secs_f64(delay.clamp(min.as_secs_f64(), max.as_secs_f64()))
+    }
+}
+
+/// Burst loss with two states: a good state with little loss and a bad
+/// state with a lot.  The state can change after every packet.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct GilbertElliott {
+    pub good_to_bad_probability: f64,
+    pub bad_to_good_probability: f64,
+    pub good_loss_probability: f64,
+    pub bad_loss_probability: f64,
+}
+
+impl GilbertElliott {
+    pub fn probabilities(&self) -> [f64; 4] {
+        [
+            self.good_to_bad_probability,
+            self.bad_to_good_probability,
+            self.good_loss_probability,
+            self.bad_loss_probability,
+        ]
+    }
+
+    /// Whether the next packet is lost.  in_bad_state is updated for the
+    /// packet after.
+    pub fn is_lost(&self, in_bad_state: &mut bool, rng: &mut impl Rng) -> bool {
+        let lost = if *in_bad_state {
+            rng.gen_bool(self.bad_loss_probability)
+        } else {
+            rng.gen_bool(self.good_loss_probability)
+        };
+        let change_probability = if *in_bad_state {
+            self.bad_to_good_probability
+        } else {
+            self.good_to_bad_probability
+        };
+        if rng.gen_bool(change_probability) {
+            *in_bad_state = !*in_bad_state;
+        }
+        lost
+    }
+
+    /// The long-term average loss.
+    pub fn average_loss_probability(&self) -> f64 {
+        let transitions = self.good_to_bad_probability + self.bad_to_good_probability;
+        if transitions == 0.0 {
+            return self.good_loss_probability;
+        }
+        let bad_fraction = self.good_to_bad_probability / transitions;
+        (1.0 - bad_fraction) * self.good_loss_probability + bad_fraction * self.bad_loss_probability
+    }
+}
+
+/// A bandwidth that varies over time, as a list of delivery opportunities
+/// in the Mahimahi format: each line is the time (in ms) at which one
+/// MTU-sized packet can be delivered.  Opportunities that aren't used are
+/// lost.  The trace repeats after its last time.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RateTrace {
+    opportunities: Vec<Duration>,
+    period: Duration,
+}
+
+impl RateTrace {
+    pub const MTU: DataSize = DataSize::from_bytes(1500);
+
+    pub fn from_mahimahi(trace: &str) -> Result<Self> {
+        let mut opportunities = Vec::new();
+        for line in trace.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let ms: u64 = line.parse().map_err(|_| {
+                RingRtcError::InvalidNetworkScenario(format!("bad rate trace line {:?}", line))
+            })?;
+            let opportunity = Duration::from_millis(ms);
+            if matches!(opportunities.last(), Some(last) if opportunity < *last) {
+                return Err(RingRtcError::InvalidNetworkScenario(
+                    "rate trace times must not decrease".to_string(),
+                )
+                .into());
+            }
+            opportunities.push(opportunity);
+        }
+        let period = opportunities.last().copied().unwrap_or_default();
+        if period == Duration::ZERO {
+            return Err(RingRtcError::InvalidNetworkScenario(
+                "rate trace must last more than 0ms".to_string(),
+            )
+            .into());
+        }
+        Ok(Self {
+            opportunities,
+            period,
+        })
+    }
+
+    pub fn load_mahimahi(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let trace = fs::read_to_string(path).map_err(|e| {
+            RingRtcError::InvalidNetworkScenario(format!(
+                "can't read rate trace {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::from_mahimahi(&trace)
+    }
+
+    pub fn average_rate(&self) -> DataRate {
+        let size = DataSize::from_bytes(Self::MTU.as_bytes() * self.opportunities.len() as u64);
+        DataRate::from_bps((size.as_bits() as f64 / self.period.as_secs_f64()) as u64)
+    }
+
+    // From the start of the trace, repeating it as needed.
+    fn opportunity(&self, index: u64) -> Duration {
+        let count = self.opportunities.len() as u64;
+        self.period * (index / count) as u32 + self.opportunities[(index % count) as usize]
+    }
+}
+
+/// Where a link is in a RateTrace.
+pub struct RateTracePosition {
+    trace: Arc<RateTrace>,
+    started: Instant,
+    next_opportunity: u64,
+}
+
+impl RateTracePosition {
+    pub fn new(trace: Arc<RateTrace>, started: Instant) -> Self {
+        Self {
+            trace,
+            started,
+            next_opportunity: 0,
+        }
+    }
+
+    pub fn trace(&self) -> &Arc<RateTrace> {
+        &self.trace
+    }
+
+    /// Uses up the opportunities needed to deliver a packet of the given
+    /// size at or after now, and returns when the packet is delivered.
+    pub fn deliver(&mut self, size: DataSize, now: Instant) -> Instant {
+        let elapsed = now.saturating_duration_since(self.started);
+        let count = self.trace.opportunities.len() as u64;
+        // Skip whole periods that went by without any packets.
+        let behind = elapsed.saturating_sub(self.trace.opportunity(self.next_opportunity));
+        let periods_behind = (behind.as_nanos() / self.trace.period.as_nanos()) as u64;
+        self.next_opportunity += periods_behind * count;
+        while self.trace.opportunity(self.next_opportunity) < elapsed {
+            self.next_opportunity += 1;
+        }
+
+        let mtu = RateTrace::MTU.as_bytes();
+        let needed = (size.as_bytes() / mtu + (size.as_bytes() % mtu).min(1)).max(1);
+        self.next_opportunity += needed;
+        self.started + self.trace.opportunity(self.next_opportunity - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn delay_distributions() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let min = Duration::from_millis(20);
+        let max = Duration::from_millis(500);
+        let samples = |distribution: DelayDistribution, rng: &mut StdRng| {
+            (0..10000)
+                .map(|_| distribution.sample(min, max, rng).as_secs_f64() * 1000.0)
+                .collect::<Vec<_>>()
+        };
+        let mean = |samples: &[f64]| samples.iter().sum::<f64>() / samples.len() as f64;
+
+        let uniform = samples(DelayDistribution::Uniform, &mut rng);
+        assert!((mean(&uniform) - 260.0).abs() < 10.0);
+
+        let normal = samples(
+            DelayDistribution::Normal {
+                mean: Duration::from_millis(100),
+                std_dev: Duration::from_millis(10),
+            },
+            &mut rng,
+        );
+        assert!((mean(&normal) - 100.0).abs() < 1.0);
+        let within_one_std_dev = normal.iter().filter(|d| (*d - 100.0).abs() < 10.0).count();
+        assert!((6500..7200).contains(&within_one_std_dev));
+
+        let pareto = samples(
+            DelayDistribution::Pareto {
+                scale: Duration::from_millis(10),
+                shape: 1.5,
+            },
+            &mut rng,
+        );
+        assert!(pareto.iter().all(|d| (20.0..=500.0).contains(d)));
+        let median = {
+            let mut sorted = pareto.clone();
+            sorted.sort_by(f64::total_cmp);
+            sorted[sorted.len() / 2]
+        };
+        assert!(median < 30.0);
+        assert!(pareto.iter().any(|d| *d > 200.0));
+
+        // Always clamped
+        let fixed = DelayDistribution::Normal {
+            mean: Duration::from_millis(100),
+            std_dev: Duration::from_millis(100),
+        };
+        assert_eq!(
+            Duration::from_millis(50),
+            fixed.sample(
+                Duration::from_millis(50),
+                Duration::from_millis(50),
+                &mut rng
+            )
+        );
+    }
+
+    #[test]
+    fn gilbert_elliott() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let model = GilbertElliott {
+            good_to_bad_probability: 0.01,
+            bad_to_good_probability: 0.25,
+            good_loss_probability: 0.0,
+            bad_loss_probability: 0.8,
+        };
+        let expected = model.average_loss_probability();
+        assert!((expected - 0.8 / 26.0).abs() < 1e-9);
+
+        let mut in_bad_state = false;
+        let losses: Vec<bool> = (0..100_000)
+            .map(|_| model.is_lost(&mut in_bad_state, &mut rng))
+            .collect();
+        let loss = losses.iter().filter(|lost| **lost).count() as f64 / losses.len() as f64;
+        assert!((loss - expected).abs() < 0.005);
+        // Losses come in bursts.
+        let repeated = losses.windows(2).filter(|w| w[0] && w[1]).count() as f64;
+        let lost = losses.iter().filter(|lost| **lost).count() as f64;
+        assert!(repeated / lost > 0.5);
+    }
+
+    #[test]
+    fn rate_trace() {
+        assert!(RateTrace::from_mahimahi("").is_err());
+        assert!(RateTrace::from_mahimahi("0\n0\n").is_err());
+        assert!(RateTrace::from_mahimahi("5\n3\n").is_err());
+        assert!(RateTrace::from_mahimahi("1\nfast\n").is_err());
+
+        // 2 packets at 1ms and 1 at 4ms, every 4ms
+        let trace = Arc::new(RateTrace::from_mahimahi("1\n1\n\n4\n").unwrap());
+        assert_eq!(9_000_000, trace.average_rate().as_bps());
+
+        let started = Instant::now();
+        let at = |ms: u64| started + Duration::from_millis(ms);
+        let small = DataSize::from_bytes(1000);
+        let mut position = RateTracePosition::new(trace, started);
+        assert_eq!(at(1), position.deliver(small, at(0)));
+        assert_eq!(at(1), position.deliver(small, at(0)));
+        assert_eq!(at(4), position.deliver(small, at(0)));
+        assert_eq!(at(5), position.deliver(small, at(0)));
+        // Too big for one opportunity
+        assert_eq!(at(8), position.deliver(DataSize::from_bytes(2000), at(0)));
+        // Unused opportunities are lost.
+        assert_eq!(at(40), position.deliver(small, at(40)));
+        assert_eq!(at(44), position.deliver(small, at(42)));
+    }
+}
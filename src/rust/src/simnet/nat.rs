@@ -0,0 +1,302 @@
+//
+// Copyright 2022 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! NATs for the Router (see Router::add_nat), so that ICE has to discover
+//! server-reflexive candidates and sometimes fall back to a relay.  The
+//! behaviors are the classic ones, in terms of RFC 4787:
+//!
+//! | type                 | mapping                    | filtering                  |
+//! |----------------------|----------------------------|----------------------------|
+//! | full_cone            | endpoint-independent       | endpoint-independent       |
+//! | restricted_cone      | endpoint-independent       | address-dependent          |
+//! | port_restricted_cone | endpoint-independent       | address and port-dependent |
+//! | symmetric            | address and port-dependent | address and port-dependent |
+
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+
+use crate::simnet::router::{Packet, Protocol};
+
+// RFC 4787 says UDP mappings must last at least 2 minutes (REQ-5).
+const DEFAULT_MAPPING_TIMEOUT: Duration = Duration::from_secs(120);
+// Where mapped ports start (the start of the dynamic port range).
+const FIRST_MAPPED_PORT: u16 = 49152;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NatType {
+    FullCone,
+    RestrictedCone,
+    PortRestrictedCone,
+    Symmetric,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NatConfig {
+    pub typ: NatType,
+    /// How long a mapping lasts after the last packet sent through it.
+    pub mapping_timeout: Duration,
+}
+
+impl NatConfig {
+    pub fn new(typ: NatType) -> Self {
+        Self {
+            typ,
+            mapping_timeout: DEFAULT_MAPPING_TIMEOUT,
+        }
+    }
+}
+
+struct Mapping {
+    private_address: SocketAddr,
+    // Where packets have been sent, for filtering
+    remote_addresses: HashSet<SocketAddr>,
+    last_sent: Instant,
+}
+
+// The remote address is only part of the key for address-dependent mapping.
+type MappingKey = (Protocol, SocketAddr, Option<SocketAddr>);
+
+/// The mapping table of one NAT with one public IP.
+pub struct Nat {
+    public_ip: IpAddr,
+    config: NatConfig,
+    public_port_by_key: HashMap<MappingKey, u16>,
+    mapping_by_public_port: HashMap<(Protocol, u16), Mapping>,
+    next_public_port: u16,
+}
+
+impl Nat {
+    pub fn new(public_ip: IpAddr, config: NatConfig) -> Self {
+        Self {
+            public_ip,
+            config,
+            public_port_by_key: HashMap::new(),
+            mapping_by_public_port: HashMap::new(),
+            next_public_port: FIRST_MAPPED_PORT,
+        }
+    }
+
+    pub fn public_ip(&self) -> IpAddr {
+        self.public_ip
+    }
+
+    /// Number of mappings that haven't expired.
+    pub fn mapping_count(&self, now: Instant) -> usize {
+        self.mapping_by_public_port
+            .values()
+            .filter(|mapping| !self.is_expired(mapping, now))
+            .count()
+    }
+
+    fn is_expired(&self, mapping: &Mapping, now: Instant) -> bool {
+        now.saturating_duration_since(mapping.last_sent) >= self.config.mapping_timeout
+    }
+
+    fn remove_expired(&mut self, now: Instant) {
+        let timeout = self.config.mapping_timeout;
+        self.mapping_by_public_port
+            .retain(|_, mapping| now.saturating_duration_since(mapping.last_sent) < timeout);
+        let mapping_by_public_port = &self.mapping_by_public_port;
+        self.public_port_by_key
+            .retain(|(protocol, _, _), public_port| {
+                mapping_by_public_port.contains_key(&(*protocol, *public_port))
+            });
+    }
+
+    // Skips ports that are still mapped (after wrapping around).
+    fn allocate_public_port(&mut self, protocol: Protocol) -> Option<u16> {
+        for _ in FIRST_MAPPED_PORT..=u16::MAX {
+            let port = self.next_public_port;
+            self.next_public_port = port.checked_add(1).unwrap_or(FIRST_MAPPED_PORT);
+            if !self.mapping_by_public_port.contains_key(&(protocol, port)) {
+                return Some(port);
+            }
+        }
+        None
+    }
+
+    /// Changes the source of a packet from a private address to a public one,
+    /// creating or refreshing a mapping.  Returns false if it should be dropped
+    /// (because there are no ports left).
+    pub fn translate_outgoing(&mut self, packet: &mut Packet, now: Instant) -> bool {
+        self.remove_expired(now);
+        let remote = match self.config.typ {
+            NatType::Symmetric => Some(packet.dest),
+            _ => None,
+        };
+        let key = (packet.protocol, packet.source, remote);
+        let public_port = match self.public_port_by_key.get(&key) {
+            Some(public_port) => *public_port,
+            None => {
+                let public_port = match self.allocate_public_port(packet.protocol) {
+                    Some(public_port) => public_port,
+                    None => return false,
+                };
+                self.public_port_by_key.insert(key, public_port);
+                self.mapping_by_public_port.insert(
+                    (packet.protocol, public_port),
+                    Mapping {
+                        private_address: packet.source,
+                        remote_addresses: HashSet::new(),
+                        last_sent: now,
+                    },
+                );
+                debug!(
+                    "NAT {:?} mapped {:?} to port {} for {:?}",
+                    self.public_ip, packet.source, public_port, remote
+                );
+                public_port
+            }
+        };
+        if let Some(mapping) = self
+            .mapping_by_public_port
+            .get_mut(&(packet.protocol, public_port))
+        {
+            mapping.remote_addresses.insert(packet.dest);
+            mapping.last_sent = now;
+        }
+        packet.source = SocketAddr::new(self.public_ip, public_port);
+        true
+    }
+
+    /// Changes the dest of a packet from the public address to the private
+    /// one it's mapped to.  Returns false if it should be dropped (because
+    /// there's no mapping or it's filtered).
+    pub fn translate_incoming(&mut self, packet: &mut Packet, now: Instant) -> bool {
+        self.remove_expired(now);
+        let mapping = match self
+            .mapping_by_public_port
+            .get(&(packet.protocol, packet.dest.port()))
+        {
+            Some(mapping) => mapping,
+            None => return false,
+        };
+        let source = packet.source;
+        let allowed = match self.config.typ {
+            NatType::FullCone => true,
+            NatType::RestrictedCone => mapping
+                .remote_addresses
+                .iter()
+                .any(|remote| remote.ip() == source.ip()),
+            NatType::PortRestrictedCone | NatType::Symmetric => {
+                mapping.remote_addresses.contains(&source)
+            }
+        };
+        if allowed {
+            packet.dest = mapping.private_address;
+        }
+        allowed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(source: &str, dest: &str) -> Packet {
+        Packet {
+            source: source.parse().unwrap(),
+            dest: dest.parse().unwrap(),
+            data: vec![],
+            protocol: Protocol::Udp,
+        }
+    }
+
+    // Sends from 192.168.1.2:2001 to server1 and server2, returning the
+    // mapped addresses, and then which of server1, server1 (another port),
+    // server2 and server3 can send back to the first mapped address.
+    fn behavior(typ: NatType) -> (SocketAddr, SocketAddr, [bool; 4]) {
+        let now = Instant::now();
+        let mut nat = Nat::new("1.1.0.1".parse().unwrap(), NatConfig::new(typ));
+        let mut to_server1 = packet("192.168.1.2:2001", "3.0.0.1:3478");
+        let mut to_server2 = packet("192.168.1.2:2001", "3.0.0.2:3478");
+        assert!(nat.translate_outgoing(&mut to_server1, now));
+        assert!(nat.translate_outgoing(&mut to_server2, now));
+        let mapped = to_server1.source;
+        let allowed = [
+            "3.0.0.1:3478",
+            "3.0.0.1:3479",
+            "3.0.0.2:4000",
+            "3.0.0.3:3478",
+        ]
+        .map(|source| {
+            let mut incoming = packet(source, &mapped.to_string());
+            let allowed = nat.translate_incoming(&mut incoming, now);
+            if allowed {
+                assert_eq!(
+                    "192.168.1.2:2001".parse::<SocketAddr>().unwrap(),
+                    incoming.dest
+                );
+            }
+            allowed
+        });
+        (mapped, to_server2.source, allowed)
+    }
+
+    #[test]
+    fn behaviors() {
+        let public = |port: u16| SocketAddr::new("1.1.0.1".parse().unwrap(), port);
+        assert_eq!(
+            (public(49152), public(49152), [true, true, true, true]),
+            behavior(NatType::FullCone)
+        );
+        assert_eq!(
+            (public(49152), public(49152), [true, true, true, false]),
+            behavior(NatType::RestrictedCone)
+        );
+        assert_eq!(
+            (public(49152), public(49152), [true, false, false, false]),
+            behavior(NatType::PortRestrictedCone)
+        );
+        assert_eq!(
+            (public(49152), public(49153), [true, false, false, false]),
+            behavior(NatType::Symmetric)
+        );
+    }
+
+    #[test]
+    fn mappings() {
+        let start = Instant::now();
+        let mut nat = Nat::new(
+            "1.1.0.1".parse().unwrap(),
+            NatConfig {
+                typ: NatType::PortRestrictedCone,
+                mapping_timeout: Duration::from_secs(30),
+            },
+        );
+
+        // UDP and TCP get separate mappings, as do different private addresses.
+        let mut outgoing = packet("192.168.1.2:2001", "3.0.0.1:3478");
+        assert!(nat.translate_outgoing(&mut outgoing, start));
+        let mut tcp = packet("192.168.1.2:2001", "3.0.0.1:3478");
+        tcp.protocol = Protocol::Tcp;
+        assert!(nat.translate_outgoing(&mut tcp, start));
+        let mut other = packet("192.168.1.3:2001", "3.0.0.1:3478");
+        assert!(nat.translate_outgoing(&mut other, start));
+        assert_eq!(3, nat.mapping_count(start));
+        assert_ne!(outgoing.source, other.source);
+
+        // Incoming packets don't keep mappings alive, but outgoing ones do.
+        let mut incoming = packet("3.0.0.1:3478", &outgoing.source.to_string());
+        assert!(nat.translate_incoming(&mut incoming.clone(), start + Duration::from_secs(20)));
+        let mut refresh = packet("192.168.1.2:2001", "3.0.0.1:3478");
+        assert!(nat.translate_outgoing(&mut refresh, start + Duration::from_secs(20)));
+        assert_eq!(outgoing.source, refresh.source);
+        assert_eq!(1, nat.mapping_count(start + Duration::from_secs(40)));
+        assert!(nat.translate_incoming(&mut incoming.clone(), start + Duration::from_secs(40)));
+        assert!(!nat.translate_incoming(&mut incoming, start + Duration::from_secs(50)));
+        assert_eq!(0, nat.mapping_count(start + Duration::from_secs(50)));
+
+        // Nothing gets in without a mapping.
+        let mut unknown = packet("3.0.0.1:3478", "1.1.0.1:2001");
+        assert!(!nat.translate_incoming(&mut unknown, start));
+    }
+}
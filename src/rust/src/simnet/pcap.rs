@@ -0,0 +1,480 @@
+//
+// Copyright 2022 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Captures of simulated UDP packets as pcap files (with made-up IP and UDP
+//! headers), so simulated calls can be opened in Wireshark, and a summary of
+//! the RTP streams in them.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::File,
+    io::{BufWriter, Write},
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::common::Result;
+
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+const PCAP_VERSION: (u16, u16) = (2, 4);
+const PCAP_SNAPLEN: u32 = 65535;
+// The packets start with an IPv4 or IPv6 header.
+const LINKTYPE_RAW: u32 = 101;
+
+const IPV4_HEADER_SIZE: usize = 20;
+const IPV6_HEADER_SIZE: usize = 40;
+const UDP_HEADER_SIZE: usize = 8;
+const IP_PROTOCOL_UDP: u8 = 17;
+const TTL: u8 = 64;
+
+const STUN_MAGIC_COOKIE: [u8; 4] = [0x21, 0x12, 0xa4, 0x42];
+
+// The one's complement sum used by IP and UDP checksums.
+fn ones_complement_sum(sum: u32, data: &[u8]) -> u32 {
+    let mut sum = sum;
+    for chunk in data.chunks(2) {
+        let word = match chunk {
+            [high, low] => u16::from_be_bytes([*high, *low]),
+            [high] => u16::from_be_bytes([*high, 0]),
+            _ => 0,
+        };
+        sum += word as u32;
+    }
+    sum
+}
+
+fn finish_checksum(mut sum: u32) -> u16 {
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Puts IP and UDP headers in front of the data.  Returns None if the
+/// source and destination aren't the same IP version.
+pub fn ip_udp_packet(source: SocketAddr, dest: SocketAddr, data: &[u8]) -> Option<Vec<u8>> {
+    let udp_size = UDP_HEADER_SIZE + data.len();
+    let mut udp = Vec::with_capacity(udp_size);
+    udp.extend_from_slice(&source.port().to_be_bytes());
+    udp.extend_from_slice(&dest.port().to_be_bytes());
+    udp.extend_from_slice(&(udp_size as u16).to_be_bytes());
+    udp.extend_from_slice(&[0, 0]);
+    udp.extend_from_slice(data);
+
+    let (mut packet, pseudo_header_sum) = match (source.ip(), dest.ip()) {
+        (IpAddr::V4(source_ip), IpAddr::V4(dest_ip)) => {
+            let mut header = Vec::with_capacity(IPV4_HEADER_SIZE + udp_size);
+            header.extend_from_slice(&[0x45, 0]);
+            header.extend_from_slice(&((IPV4_HEADER_SIZE + udp_size) as u16).to_be_bytes());
+            // Identification, flags (don't fragment), and fragment offset
+            header.extend_from_slice(&[0, 0, 0x40, 0]);
+            header.extend_from_slice(&[TTL, IP_PROTOCOL_UDP, 0, 0]);
+            header.extend_from_slice(&source_ip.octets());
+            header.extend_from_slice(&dest_ip.octets());
+            let checksum = finish_checksum(ones_complement_sum(0, &header));
+            header[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+            let mut pseudo_header = Vec::with_capacity(12);
+            pseudo_header.extend_from_slice(&source_ip.octets());
+            pseudo_header.extend_from_slice(&dest_ip.octets());
+            pseudo_header.extend_from_slice(&[0, IP_PROTOCOL_UDP]);
+            pseudo_header.extend_from_slice(&(udp_size as u16).to_be_bytes());
+            (header, ones_complement_sum(0, &pseudo_header))
+        }
+        (IpAddr::V6(source_ip), IpAddr::V6(dest_ip)) => {
+            let mut header = Vec::with_capacity(IPV6_HEADER_SIZE + udp_size);
+            header.extend_from_slice(&[0x60, 0, 0, 0]);
+            header.extend_from_slice(&(udp_size as u16).to_be_bytes());
+            header.extend_from_slice(&[IP_PROTOCOL_UDP, TTL]);
+            header.extend_from_slice(&source_ip.octets());
+            header.extend_from_slice(&dest_ip.octets());
+
+            let mut pseudo_header = Vec::with_capacity(40);
+            pseudo_header.extend_from_slice(&source_ip.octets());
+            pseudo_header.extend_from_slice(&dest_ip.octets());
+            pseudo_header.extend_from_slice(&(udp_size as u32).to_be_bytes());
+            pseudo_header.extend_from_slice(&[0, 0, 0, IP_PROTOCOL_UDP]);
+            (header, ones_complement_sum(0, &pseudo_header))
+        }
+        _ => return None,
+    };
+
+    let checksum = match finish_checksum(ones_complement_sum(pseudo_header_sum, &udp)) {
+        // 0 means "no checksum", so it's sent as all ones instead.
+        0 => 0xffff,
+        checksum => checksum,
+    };
+    udp[6..8].copy_from_slice(&checksum.to_be_bytes());
+    packet.extend_from_slice(&udp);
+    Some(packet)
+}
+
+/// Writes packets to a pcap file.
+pub struct PcapWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PcapWriter<W> {
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(&PCAP_MAGIC.to_le_bytes())?;
+        writer.write_all(&PCAP_VERSION.0.to_le_bytes())?;
+        writer.write_all(&PCAP_VERSION.1.to_le_bytes())?;
+        // Time zone and timestamp accuracy
+        writer.write_all(&[0; 8])?;
+        writer.write_all(&PCAP_SNAPLEN.to_le_bytes())?;
+        writer.write_all(&LINKTYPE_RAW.to_le_bytes())?;
+        Ok(Self { writer })
+    }
+
+    pub fn write_packet(&mut self, time: SystemTime, packet: &[u8]) -> Result<()> {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let captured_size = packet.len().min(PCAP_SNAPLEN as usize);
+        self.writer
+            .write_all(&(since_epoch.as_secs() as u32).to_le_bytes())?;
+        self.writer
+            .write_all(&since_epoch.subsec_micros().to_le_bytes())?;
+        self.writer
+            .write_all(&(captured_size as u32).to_le_bytes())?;
+        self.writer
+            .write_all(&(packet.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&packet[..captured_size])?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// What a UDP packet in a call carries.  See RFC 7983.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketKind {
+    Stun,
+    Dtls,
+    Rtp,
+    Rtcp,
+    Other,
+}
+
+impl PacketKind {
+    pub fn of(data: &[u8]) -> Self {
+        match data.first() {
+            Some(0..=3) if data.len() >= 20 && data[4..8] == STUN_MAGIC_COOKIE => Self::Stun,
+            Some(20..=63) => Self::Dtls,
+            Some(128..=191) if data.len() >= 12 => {
+                // RTCP packet types 192-223 (RFC 5761)
+                if (192..=223).contains(&data[1]) {
+                    Self::Rtcp
+                } else {
+                    Self::Rtp
+                }
+            }
+            _ => Self::Other,
+        }
+    }
+}
+
+/// What was captured of one RTP stream (one SSRC from one address to another).
+/// The headers of SRTP packets aren't encrypted, so this works for them too.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RtpStreamSummary {
+    pub source: SocketAddr,
+    pub dest: SocketAddr,
+    pub ssrc: u32,
+    pub payload_types: BTreeSet<u8>,
+    pub packets: u64,
+    pub bytes: u64,
+    /// Expected (from the sequence numbers) but not captured.
+    pub lost: u64,
+    pub out_of_order: u64,
+    pub duration_ms: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct PacketCaptureSummary {
+    pub stun_packets: u64,
+    pub dtls_packets: u64,
+    pub rtcp_packets: u64,
+    pub other_packets: u64,
+    pub rtp_streams: Vec<RtpStreamSummary>,
+}
+
+struct RtpStream {
+    summary: RtpStreamSummary,
+    first_sequence_number: u64,
+    // Extended to count rollovers.
+    highest_sequence_number: u64,
+    first_captured: Instant,
+}
+
+impl RtpStream {
+    fn new(source: SocketAddr, dest: SocketAddr, ssrc: u32, sequence_number: u16) -> Self {
+        // Start in the second "cycle" so that earlier packets don't go negative.
+        let extended = (1 << 16) + sequence_number as u64;
+        Self {
+            summary: RtpStreamSummary {
+                source,
+                dest,
+                ssrc,
+                payload_types: BTreeSet::new(),
+                packets: 0,
+                bytes: 0,
+                lost: 0,
+                out_of_order: 0,
+                duration_ms: 0,
+            },
+            first_sequence_number: extended,
+            highest_sequence_number: extended,
+            first_captured: Instant::now(),
+        }
+    }
+
+    fn add(&mut self, data: &[u8], captured: Instant) {
+        let payload_type = data[1] & 0x7f;
+        let sequence_number = u16::from_be_bytes([data[2], data[3]]);
+
+        let highest = self.highest_sequence_number;
+        let delta = sequence_number.wrapping_sub(highest as u16) as i16;
+        let extended = (highest as i64 + delta as i64).max(0) as u64;
+        if extended > highest {
+            self.highest_sequence_number = extended;
+        } else if extended < highest {
+            self.summary.out_of_order += 1;
+        }
+        self.first_sequence_number = self.first_sequence_number.min(extended);
+
+        let summary = &mut self.summary;
+        summary.payload_types.insert(payload_type);
+        summary.packets += 1;
+        summary.bytes += data.len() as u64;
+        let expected = self.highest_sequence_number - self.first_sequence_number + 1;
+        summary.lost = expected.saturating_sub(summary.packets);
+        summary.duration_ms = captured
+            .saturating_duration_since(self.first_captured)
+            .as_millis() as u64;
+    }
+}
+
+#[derive(Default)]
+struct PacketSummarizer {
+    summary: PacketCaptureSummary,
+    rtp_streams: HashMap<(SocketAddr, SocketAddr, u32), RtpStream>,
+}
+
+impl PacketSummarizer {
+    fn add(&mut self, source: SocketAddr, dest: SocketAddr, data: &[u8], captured: Instant) {
+        match PacketKind::of(data) {
+            PacketKind::Stun => self.summary.stun_packets += 1,
+            PacketKind::Dtls => self.summary.dtls_packets += 1,
+            PacketKind::Rtcp => self.summary.rtcp_packets += 1,
+            PacketKind::Other => self.summary.other_packets += 1,
+            PacketKind::Rtp => {
+                let ssrc = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+                let sequence_number = u16::from_be_bytes([data[2], data[3]]);
+                self.rtp_streams
+                    .entry((source, dest, ssrc))
+                    .or_insert_with(|| RtpStream::new(source, dest, ssrc, sequence_number))
+                    .add(data, captured);
+            }
+        }
+    }
+
+    fn summary(&self) -> PacketCaptureSummary {
+        let mut rtp_streams: Vec<RtpStreamSummary> = self
+            .rtp_streams
+            .values()
+            .map(|stream| stream.summary.clone())
+            .collect();
+        rtp_streams.sort_by_key(|stream| (stream.source, stream.dest, stream.ssrc));
+        PacketCaptureSummary {
+            rtp_streams,
+            ..self.summary.clone()
+        }
+    }
+}
+
+struct PacketCaptureState {
+    writer: PcapWriter<Box<dyn Write + Send>>,
+    summarizer: PacketSummarizer,
+}
+
+/// A pcap file that packets from many threads can be added to, along with
+/// a running summary of them.  Clones add to the same file.
+#[derive(Clone)]
+pub struct PacketCapture {
+    state: Arc<Mutex<PacketCaptureState>>,
+}
+
+impl PacketCapture {
+    pub fn new(writer: Box<dyn Write + Send>) -> Result<Self> {
+        Ok(Self {
+            state: Arc::new(Mutex::new(PacketCaptureState {
+                writer: PcapWriter::new(writer)?,
+                summarizer: PacketSummarizer::default(),
+            })),
+        })
+    }
+
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Self::new(Box::new(BufWriter::new(File::create(path)?)))
+    }
+
+    pub fn capture(&self, source: SocketAddr, dest: SocketAddr, data: &[u8]) {
+        let packet = match ip_udp_packet(source, dest, data) {
+            Some(packet) => packet,
+            None => {
+                warn!(
+                    "Not capturing packet from {:?} to {:?} with different IP versions",
+                    source, dest
+                );
+                return;
+            }
+        };
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+        if let Err(e) = state.writer.write_packet(SystemTime::now(), &packet) {
+            warn!("Failed to write captured packet: {:?}", e);
+        }
+        state.summarizer.add(source, dest, data, Instant::now());
+    }
+
+    pub fn flush(&self) -> Result<()> {
+        match self.state.lock() {
+            Ok(mut state) => state.writer.flush(),
+            Err(_) => Ok(()),
+        }
+    }
+
+    pub fn summary(&self) -> PacketCaptureSummary {
+        match self.state.lock() {
+            Ok(state) => state.summarizer.summary(),
+            Err(_) => PacketCaptureSummary::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rtp(payload_type: u8, sequence_number: u16, ssrc: u32) -> Vec<u8> {
+        let mut packet = vec![0x80, payload_type];
+        packet.extend_from_slice(&sequence_number.to_be_bytes());
+        packet.extend_from_slice(&[0; 4]);
+        packet.extend_from_slice(&ssrc.to_be_bytes());
+        packet.extend_from_slice(&[0xaa; 100]);
+        packet
+    }
+
+    #[test]
+    fn ip_udp_headers() {
+        let source: SocketAddr = "1.1.0.1:5000".parse().unwrap();
+        let dest: SocketAddr = "2.1.0.1:6000".parse().unwrap();
+        let packet = ip_udp_packet(source, dest, b"hello").unwrap();
+        assert_eq!(20 + 8 + 5, packet.len());
+        assert_eq!(&[0x45, 0, 0, 33], &packet[0..4]);
+        assert_eq!(IP_PROTOCOL_UDP, packet[9]);
+        assert_eq!(&[1, 1, 0, 1, 2, 1, 0, 1], &packet[12..20]);
+        // A correct checksum sums to all ones.
+        assert_eq!(0, finish_checksum(ones_complement_sum(0, &packet[..20])));
+        assert_eq!(&[0x13, 0x88, 0x17, 0x70, 0, 13], &packet[20..26]);
+        assert_eq!(b"hello", &packet[28..]);
+
+        let v6_source: SocketAddr = "[2001:db8::1]:5000".parse().unwrap();
+        let v6_dest: SocketAddr = "[2001:db8::2]:6000".parse().unwrap();
+        let packet = ip_udp_packet(v6_source, v6_dest, b"hello").unwrap();
+        assert_eq!(40 + 8 + 5, packet.len());
+        assert_eq!(0x60, packet[0]);
+        assert_eq!(&[0, 13, IP_PROTOCOL_UDP], &packet[4..7]);
+
+        assert_eq!(None, ip_udp_packet(source, v6_dest, b"hello"));
+    }
+
+    #[test]
+    fn pcap_file() {
+        let mut writer = PcapWriter::new(Vec::new()).unwrap();
+        writer
+            .write_packet(
+                UNIX_EPOCH + std::time::Duration::from_micros(1_500_000),
+                &[1, 2, 3],
+            )
+            .unwrap();
+        let file = writer.into_inner();
+        assert_eq!(24 + 16 + 3, file.len());
+        assert_eq!(&[0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0], &file[0..8]);
+        assert_eq!(&LINKTYPE_RAW.to_le_bytes(), &file[20..24]);
+        assert_eq!(&[1, 0, 0, 0, 0x20, 0xa1, 0x07, 0], &file[24..32]);
+        assert_eq!(&[3, 0, 0, 0, 3, 0, 0, 0, 1, 2, 3], &file[32..]);
+    }
+
+    #[test]
+    fn packet_kinds() {
+        let mut stun = vec![0, 1, 0, 0];
+        stun.extend_from_slice(&STUN_MAGIC_COOKIE);
+        stun.extend_from_slice(&[0; 12]);
+        assert_eq!(PacketKind::Stun, PacketKind::of(&stun));
+        assert_eq!(PacketKind::Dtls, PacketKind::of(&[22, 0xfe, 0xfd]));
+        assert_eq!(PacketKind::Rtp, PacketKind::of(&rtp(96, 1, 1)));
+        // Marker bit
+        assert_eq!(PacketKind::Rtp, PacketKind::of(&rtp(0x80 | 96, 1, 1)));
+        assert_eq!(PacketKind::Rtcp, PacketKind::of(&rtp(200, 1, 1)));
+        assert_eq!(PacketKind::Other, PacketKind::of(&[0x80, 96]));
+        assert_eq!(PacketKind::Other, PacketKind::of(&[]));
+    }
+
+    #[test]
+    fn rtp_summary() {
+        let capture = PacketCapture::new(Box::new(std::io::sink())).unwrap();
+        let source: SocketAddr = "1.1.0.1:5000".parse().unwrap();
+        let dest: SocketAddr = "2.1.0.1:6000".parse().unwrap();
+        for sequence_number in [65534, 65535, 1, 0, 3] {
+            capture.capture(source, dest, &rtp(96, sequence_number, 0xabcd));
+        }
+        capture.capture(source, dest, &rtp(0x80 | 97, 7, 0x1234));
+        capture.capture(source, dest, &rtp(201, 7, 0x1234));
+        capture.capture(source, dest, &[22, 0xfe, 0xfd]);
+        capture.flush().unwrap();
+
+        let summary = capture.summary();
+        assert_eq!(1, summary.rtcp_packets);
+        assert_eq!(1, summary.dtls_packets);
+        assert_eq!(0, summary.stun_packets);
+        assert_eq!(2, summary.rtp_streams.len());
+        let stream = summary
+            .rtp_streams
+            .iter()
+            .find(|stream| stream.ssrc == 0xabcd)
+            .unwrap();
+        assert_eq!(5, stream.packets);
+        assert_eq!(5 * 112, stream.bytes);
+        // 2 is missing
+        assert_eq!(1, stream.lost);
+        assert_eq!(1, stream.out_of_order);
+        assert_eq!(
+            vec![96],
+            stream.payload_types.iter().copied().collect::<Vec<_>>()
+        );
+        let stream = summary
+            .rtp_streams
+            .iter()
+            .find(|stream| stream.ssrc == 0x1234)
+            .unwrap();
+        assert_eq!(
+            vec![97],
+            stream.payload_types.iter().copied().collect::<Vec<_>>()
+        );
+    }
+}
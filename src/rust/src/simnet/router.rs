@@ -3,35 +3,57 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
-use crate::common::{
-    actor::{Actor, Stopper},
-    units::{DataRate, DataSize},
-    Result,
+use crate::{
+    common::{
+        actor::{Actor, Stopper},
+        units::{DataRate, DataSize},
+        Result,
+    },
+    simnet::{
+        impairment::{DelayDistribution, GilbertElliott, RateTrace, RateTracePosition},
+        nat::{Nat, NatConfig},
+        pcap::PacketCapture,
+    },
 };
-use rand::{distributions, distributions::Distribution, rngs::ThreadRng, thread_rng, Rng};
+use rand::{rngs::ThreadRng, thread_rng, Rng};
+use serde::Deserialize;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     net::{IpAddr, SocketAddr},
-    sync::{atomic, atomic::AtomicU64, Arc},
+    sync::{atomic, atomic::AtomicU64, Arc, Mutex},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 const OVERHEAD_IN_BYTES_IPV4: u64 = 20;
 
 const OVERHEAD_IN_BYTES_IPV6: u64 = 40;
 const OVERHEAD_IN_BYTES_UDP: u64 = 8;
-#[allow(dead_code)]
 const OVERHEAD_IN_BYTES_TCP: u64 = 20;
 
-#[derive(Debug)]
+// Like Linux's minimum RTO.  Doubled for each retransmission of the same packet.
+const TCP_RETRANSMISSION_TIMEOUT: Duration = Duration::from_millis(200);
+// A real TCP connection would give up after this many; we just deliver the packet.
+const TCP_MAX_RETRANSMISSIONS: u32 = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    Udp,
+    // Each packet is one message written to (and read from) a TCP connection,
+    // which is how TURN over TCP looks to WebRTC.  There are no handshakes or
+    // ACKs, but losses cause retransmissions and head-of-line blocking.
+    Tcp,
+}
+
+#[derive(Clone, Debug)]
 pub struct Packet {
     pub source: SocketAddr,
     pub dest: SocketAddr,
     pub data: Vec<u8>,
+    pub protocol: Protocol,
 }
 
-// Just assume UDP for now.
 impl Packet {
     fn size(&self) -> DataSize {
         DataSize::from_bytes(self.data.len() as u64)
@@ -42,12 +64,22 @@ impl Packet {
             IpAddr::V4(_) => DataSize::from_bytes(OVERHEAD_IN_BYTES_IPV4),
             IpAddr::V6(_) => DataSize::from_bytes(OVERHEAD_IN_BYTES_IPV6),
         };
-        let udp_overhead = DataSize::from_bytes(OVERHEAD_IN_BYTES_UDP);
-        ip_overhead + udp_overhead
+        let transport_overhead = match self.protocol {
+            Protocol::Udp => DataSize::from_bytes(OVERHEAD_IN_BYTES_UDP),
+            Protocol::Tcp => DataSize::from_bytes(OVERHEAD_IN_BYTES_TCP),
+        };
+        ip_overhead + transport_overhead
     }
 
     fn reliable(&self) -> bool {
-        false
+        self.protocol == Protocol::Tcp
+    }
+
+    // The pcap captures only have UDP headers.
+    fn capture(&self, capture: &PacketCapture) {
+        if self.protocol == Protocol::Udp {
+            capture.capture(self.source, self.dest, &self.data);
+        }
     }
 }
 
@@ -69,21 +101,72 @@ pub struct LinkConfig {
     // pub delay_std_dev:             Duration,
     pub delay_min: Duration,
     pub delay_max: Duration,
+    pub delay_distribution: DelayDistribution,
     pub loss_probabilty: f64,
     // If a packet is lost, the probably of the next one being a loss
     pub repeated_loss_probability: f64,
+    // If set, used instead of loss_probabilty and repeated_loss_probability.
+    pub gilbert_elliott: Option<GilbertElliott>,
+    // Like netem, the probability of a packet skipping the delay, which
+    // gets it ahead of the packets sent before it.
+    pub reorder_probability: f64,
+    // The duplicate is delayed separately from the original.
+    pub duplicate_probability: f64,
     pub rate: DataRate,
+    // If set, used instead of the rate (but the queue_size stays the same).
+    pub rate_trace: Option<Arc<RateTrace>>,
     pub queue_size: DataSize,
 }
 
+impl LinkConfig {
+    /// The names accepted by profile().
+    pub const PROFILE_NAMES: &'static [&'static str] =
+        &["ideal", "good", "cellular", "congested", "lossy", "bad"];
+
+    /// Standard links, so that measurements can be compared across releases.
+    /// Don't change these; add new ones instead.
+    pub fn profile(name: &str) -> Option<Self> {
+        let (delay_min, delay_max, loss_probabilty, repeated_loss_probability, rate, queue_time) =
+            match name {
+                "ideal" => (5, 5, 0.0, 0.0, DataRate::from_mbps(100), 100),
+                "good" => (10, 20, 0.0, 0.0, DataRate::from_mbps(5), 500),
+                "cellular" => (40, 80, 0.01, 0.10, DataRate::from_mbps(2), 300),
+                "congested" => (20, 40, 0.0, 0.0, DataRate::from_kbps(512), 1000),
+                "lossy" => (10, 20, 0.05, 0.30, DataRate::from_mbps(5), 500),
+                "bad" => (100, 200, 0.005, 0.70, DataRate::from_kbps(256), 500_000),
+                _ => return None,
+            };
+        Some(Self {
+            delay_min: Duration::from_millis(delay_min),
+            delay_max: Duration::from_millis(delay_max),
+            delay_distribution: DelayDistribution::Uniform,
+            loss_probabilty,
+            repeated_loss_probability,
+            gilbert_elliott: None,
+            reorder_probability: 0.0,
+            duplicate_probability: 0.0,
+            rate,
+            rate_trace: None,
+            queue_size: rate * Duration::from_millis(queue_time),
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct Router {
     actor: Actor<RouterState>,
+    // Shared with the receivers passed to add_interface so that
+    // it can be changed after they are added.
+    delivered_capture: Arc<Mutex<Option<PacketCapture>>>,
 }
 
 struct RouterState {
     send_link_by_ip: HashMap<IpAddr, Link>,
     receive_link_by_ip: HashMap<IpAddr, Link>,
+    sent_capture: Option<PacketCapture>,
+    nat_by_public_ip: HashMap<IpAddr, Nat>,
+    // Private IPs are only reachable from behind the same NAT.
+    nat_public_ip_by_private_ip: HashMap<IpAddr, IpAddr>,
 }
 
 // Routes packets between the given interfaces with configurable links.
@@ -92,6 +175,7 @@ struct RouterState {
 // => Router.actor.state.send_link_by_ip[ip].send_packet
 // => Link.actor.state.leaky_bucket
 // => Router.receive_packet
+// => Router.actor.state.nat_by_public_ip[ip].translate_* (if behind a NAT)
 // => Router.actor.state.receive_link_by_ip[ip].receive_packet
 // => Link.actor.state.leaky_bucket
 // => callback passed to Router.add_interface
@@ -103,11 +187,31 @@ impl Router {
                 Ok(RouterState {
                     send_link_by_ip: HashMap::new(),
                     receive_link_by_ip: HashMap::new(),
+                    sent_capture: None,
+                    nat_by_public_ip: HashMap::new(),
+                    nat_public_ip_by_private_ip: HashMap::new(),
                 })
             })?,
+            delivered_capture: Arc::new(Mutex::new(None)),
         })
     }
 
+    // Packets will be captured as they are sent, before any are lost or delayed.
+    pub fn set_sent_capture(&self, capture: Option<PacketCapture>) {
+        self.actor.send(move |state| {
+            state.sent_capture = capture;
+        });
+    }
+
+    // Packets will be captured as they are delivered to the receivers passed
+    // to add_interface.
+    pub fn set_delivered_capture(&self, capture: Option<PacketCapture>) {
+        match self.delivered_capture.lock() {
+            Ok(mut delivered_capture) => *delivered_capture = capture,
+            Err(_) => warn!("Failed to set the delivered packet capture"),
+        }
+    }
+
     // Packets sent from the ip will behave according to send_config.
     // Packets received to the ip will behave according to the receive_config
     // and be routed to the given receiver.
@@ -123,6 +227,15 @@ impl Router {
             Box::new(self.clone()),
             self.actor.stopper().clone(),
         )?;
+        let delivered_capture = self.delivered_capture.clone();
+        let receiver = Box::new(move |packet: Packet| {
+            if let Ok(delivered_capture) = delivered_capture.lock() {
+                if let Some(capture) = delivered_capture.as_ref() {
+                    packet.capture(capture);
+                }
+            }
+            receiver.receive_packet(packet);
+        });
         let receive_link = Link::start(receive_config, receiver, self.actor.stopper().clone())?;
         self.actor.send(move |state| {
             state.send_link_by_ip.insert(ip, send_link);
@@ -131,8 +244,60 @@ impl Router {
         Ok(())
     }
 
+    // Packets sent from or received to the ip will be dropped until it's added again.
+    // The threads of the removed links keep running until the Router is stopped.
+    pub fn remove_interface(&self, ip: IpAddr) {
+        self.actor.send(move |state| {
+            state.send_link_by_ip.remove(&ip);
+            state.receive_link_by_ip.remove(&ip);
+        });
+    }
+
+    // Changes the configs given to add_interface.  Packets that have already
+    // been delayed are not affected.
+    pub fn set_link_configs(
+        &self,
+        ip: IpAddr,
+        send_config: LinkConfig,
+        receive_config: LinkConfig,
+    ) {
+        self.actor.send(move |state| {
+            match (
+                state.send_link_by_ip.get(&ip),
+                state.receive_link_by_ip.get(&ip),
+            ) {
+                (Some(send_link), Some(receive_link)) => {
+                    send_link.set_config(send_config);
+                    receive_link.set_config(receive_config);
+                }
+                _ => {
+                    warn!("Can't configure links for unknown IP ({:?}).", ip);
+                }
+            }
+        });
+    }
+
+    // Puts the private IPs behind a NAT with the public IP, which shouldn't be
+    // used for an interface.  The private IPs can still be added as interfaces
+    // later (or removed and added again).
+    pub fn add_nat(&self, public_ip: IpAddr, private_ips: Vec<IpAddr>, config: NatConfig) {
+        self.actor.send(move |state| {
+            for private_ip in private_ips {
+                state
+                    .nat_public_ip_by_private_ip
+                    .insert(private_ip, public_ip);
+            }
+            state
+                .nat_by_public_ip
+                .insert(public_ip, Nat::new(public_ip, config));
+        });
+    }
+
     pub fn send_packet(&self, packet: Packet) {
         self.actor.send(move |state| {
+            if let Some(capture) = &state.sent_capture {
+                packet.capture(capture);
+            }
             if let Some(send_link) = state.send_link_by_ip.get(&packet.source.ip()) {
                 send_link.send_packet(packet);
             } else {
@@ -145,9 +310,58 @@ impl Router {
     }
 }
 
+impl RouterState {
+    // Returns false if the packet should be dropped.
+    fn translate_addresses(&mut self, packet: &mut Packet) -> bool {
+        let now = Instant::now();
+        let source_nat = self
+            .nat_public_ip_by_private_ip
+            .get(&packet.source.ip())
+            .copied();
+        let dest_nat = self
+            .nat_public_ip_by_private_ip
+            .get(&packet.dest.ip())
+            .copied();
+        match (source_nat, dest_nat) {
+            // On the same private network, so no NAT is involved.
+            (Some(source_nat), Some(dest_nat)) if source_nat == dest_nat => return true,
+            (_, Some(_)) => {
+                debug!(
+                    "Dropped packet because the dest IP ({:?}) is behind another NAT.",
+                    packet.dest.ip()
+                );
+                return false;
+            }
+            (Some(source_nat), None) => {
+                if let Some(nat) = self.nat_by_public_ip.get_mut(&source_nat) {
+                    if !nat.translate_outgoing(packet, now) {
+                        debug!("Dropped packet because the NAT ({:?}) is full.", source_nat);
+                        return false;
+                    }
+                }
+            }
+            (None, None) => {}
+        }
+        // This also handles hairpinning (sending to your own NAT's public IP).
+        if let Some(nat) = self.nat_by_public_ip.get_mut(&packet.dest.ip()) {
+            if !nat.translate_incoming(packet, now) {
+                debug!(
+                    "Dropped packet from {:?} to {:?} because the NAT filtered it.",
+                    packet.source, packet.dest
+                );
+                return false;
+            }
+        }
+        true
+    }
+}
+
 impl PacketReceiver for Router {
-    fn receive_packet(&self, packet: Packet) {
+    fn receive_packet(&self, mut packet: Packet) {
         self.actor.send(move |state| {
+            if !state.translate_addresses(&mut packet) {
+                return;
+            }
             if let Some(receive_link) = state.receive_link_by_ip.get(&packet.dest.ip()) {
                 receive_link.send_packet(packet);
             } else {
@@ -174,15 +388,27 @@ struct LinkState {
     // goes out side of the actor because it affects
     // if and when we will send to the actor.
     previous_packet_dropped: bool,
-
-    #[allow(deprecated)]
-    delay_distribution: distributions::uniform::Uniform<u64>,
+    // For the GilbertElliott loss model
+    in_bad_state: bool,
 
     // We keep a clone of the actor in the link state
     // so we can schedule tasks based on the state.
     actor: Actor<LinkState>,
 
     leaky_bucket: LeakyBucket,
+
+    // For reliable packets, by source and dest.
+    tcp_streams: HashMap<(SocketAddr, SocketAddr), TcpStream>,
+}
+
+#[derive(Default)]
+struct TcpStream {
+    // Sequence numbers of the next packet to send and to deliver
+    next_sent: u64,
+    next_delivered: u64,
+    // Packets that got through but are waiting for an earlier one
+    // to be retransmitted (head-of-line blocking).
+    arrived: BTreeMap<u64, Packet>,
 }
 
 impl Link {
@@ -191,14 +417,6 @@ impl Link {
         receiver: Box<dyn PacketReceiver>,
         stopper: Stopper,
     ) -> Result<Self> {
-        // Could also be mean + std_dev
-        // let delay_distribution = distributions::Normal::new(
-        //     config.delay_mean.as_secs_f64(),
-        //     config.delay_std_dev.as_secs_f64(),
-        // );
-        let delay_distribution = distributions::Uniform::from(
-            (config.delay_min.as_millis() as u64)..(config.delay_max.as_millis() as u64),
-        );
         let leaky_bucket = LeakyBucket::start(config.clone(), receiver, stopper.clone())?;
         Ok(Self {
             actor: Actor::start(stopper, move |actor| {
@@ -207,40 +425,125 @@ impl Link {
                     config,
                     rng: thread_rng(),
                     previous_packet_dropped: false,
-                    delay_distribution,
+                    in_bad_state: false,
                     leaky_bucket,
+                    tcp_streams: HashMap::new(),
                 })
             })?,
         })
     }
 
+    fn set_config(&self, config: LinkConfig) {
+        self.actor.send(move |state| {
+            state.leaky_bucket.config = config.clone();
+            state.config = config;
+        });
+    }
+
     fn send_packet(&self, packet: Packet) {
         self.actor.send(move |state| {
-            let loss_probabilty = if state.previous_packet_dropped {
-                state.config.repeated_loss_probability
-            } else {
-                state.config.loss_probabilty
-            };
-            if !packet.reliable() && state.rng.gen_bool(loss_probabilty) {
+            if packet.reliable() {
+                state.send_reliable_packet(packet);
+                return;
+            }
+
+            let previous_packet_dropped = state.previous_packet_dropped;
+            if state.is_lost() {
                 println!(
                     "Dropped packet from {:?} to {:?} of size {} randomly (previous_packet_dropped={})",
-                    packet.source, packet.dest, packet.size().as_bytes(), state.previous_packet_dropped
+                    packet.source, packet.dest, packet.size().as_bytes(), previous_packet_dropped
                 );
-                state.previous_packet_dropped = true;
                 // Drop the packet
                 return;
             }
-            state.previous_packet_dropped = false;
 
-            // Delay the packet
-            let delay = Duration::from_millis(state.delay_distribution.sample(&mut state.rng));
-            state
-                .actor
-                .send_delayed(delay, move |state| state.leaky_bucket.send_packet(packet));
+            if state.rng.gen_bool(state.config.duplicate_probability) {
+                state.delay_packet(packet.clone());
+            }
+            state.delay_packet(packet);
         });
     }
 }
 
+impl LinkState {
+    fn is_lost(&mut self) -> bool {
+        let lost = match &self.config.gilbert_elliott {
+            Some(model) => model.is_lost(&mut self.in_bad_state, &mut self.rng),
+            None => {
+                let loss_probabilty = if self.previous_packet_dropped {
+                    self.config.repeated_loss_probability
+                } else {
+                    self.config.loss_probabilty
+                };
+                self.rng.gen_bool(loss_probabilty)
+            }
+        };
+        self.previous_packet_dropped = lost;
+        lost
+    }
+
+    // Reliable packets are never dropped, reordered or duplicated.  Instead,
+    // each loss delays the packet by a retransmission timeout, and packets
+    // sent after it aren't delivered until it is.
+    fn send_reliable_packet(&mut self, packet: Packet) {
+        let mut retransmissions = 0;
+        while retransmissions < TCP_MAX_RETRANSMISSIONS && self.is_lost() {
+            retransmissions += 1;
+        }
+        if retransmissions > 0 {
+            debug!(
+                "Retransmitted packet from {:?} to {:?} of size {} {} times",
+                packet.source,
+                packet.dest,
+                packet.size().as_bytes(),
+                retransmissions
+            );
+        }
+        let retransmission_delay = TCP_RETRANSMISSION_TIMEOUT * ((1 << retransmissions) - 1);
+        let delay = self.config.delay_distribution.sample(
+            self.config.delay_min,
+            self.config.delay_max,
+            &mut self.rng,
+        ) + retransmission_delay;
+
+        let key = (packet.source, packet.dest);
+        let stream = self.tcp_streams.entry(key).or_default();
+        let sequence_number = stream.next_sent;
+        stream.next_sent += 1;
+        self.actor.send_delayed(delay, move |state| {
+            state.reliable_packet_arrived(key, sequence_number, packet)
+        });
+    }
+
+    fn reliable_packet_arrived(
+        &mut self,
+        key: (SocketAddr, SocketAddr),
+        sequence_number: u64,
+        packet: Packet,
+    ) {
+        let stream = self.tcp_streams.entry(key).or_default();
+        stream.arrived.insert(sequence_number, packet);
+        while let Some(packet) = stream.arrived.remove(&stream.next_delivered) {
+            stream.next_delivered += 1;
+            self.leaky_bucket.send_packet(packet);
+        }
+    }
+
+    fn delay_packet(&mut self, packet: Packet) {
+        if self.rng.gen_bool(self.config.reorder_probability) {
+            self.leaky_bucket.send_packet(packet);
+            return;
+        }
+        let delay = self.config.delay_distribution.sample(
+            self.config.delay_min,
+            self.config.delay_max,
+            &mut self.rng,
+        );
+        self.actor
+            .send_delayed(delay, move |state| state.leaky_bucket.send_packet(packet));
+    }
+}
+
 struct LeakyBucket {
     config: LinkConfig,
     actor: Actor<LeakyBucketState>,
@@ -251,6 +554,7 @@ struct LeakyBucket {
 
 struct LeakyBucketState {
     queued_size: Arc<AtomicU64>,
+    rate_trace_position: Option<RateTracePosition>,
     receiver: Box<dyn PacketReceiver>,
 }
 
@@ -267,6 +571,7 @@ impl LeakyBucket {
             actor: Actor::start(stopper, move |_| {
                 Ok(LeakyBucketState {
                     queued_size: queued_size_clone,
+                    rate_trace_position: None,
                     receiver,
                 })
             })?,
@@ -286,7 +591,10 @@ impl LeakyBucket {
         let queued_size = DataSize::from_bytes(self.queued_size.load(ordering));
         let max_size = self.config.queue_size;
         let rate = self.config.rate;
-        if (queued_size + packet_size_with_overhead) > max_size {
+        let rate_trace = self.config.rate_trace.clone();
+        // A full TCP connection backs up in the sender instead of dropping.
+        // We don't simulate congestion control, so we let the queue grow.
+        if !packet.reliable() && (queued_size + packet_size_with_overhead) > max_size {
             println!(
                 "Dropped packet (size: {} overhead: {}) from full queue (queued_size={}/{})",
                 packet_size_without_overhead.as_bytes(),
@@ -306,9 +614,170 @@ impl LeakyBucket {
             // Simulates the time it takes to transmit a packet.
             // TODO: accumulate sleep amounts and only sleep when more
             // than some threshold for systems that have inprecise sleep.
-            thread::sleep(packet_size_with_overhead / rate);
+            match rate_trace {
+                Some(rate_trace) => {
+                    let now = Instant::now();
+                    let position = match &mut state.rate_trace_position {
+                        Some(position) if Arc::ptr_eq(position.trace(), &rate_trace) => position,
+                        position => position.insert(RateTracePosition::new(rate_trace, now)),
+                    };
+                    let delivered = position.deliver(packet_size_with_overhead, now);
+                    thread::sleep(delivered.saturating_duration_since(now));
+                }
+                None => thread::sleep(packet_size_with_overhead / rate),
+            }
 
             state.receiver.receive_packet(packet);
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simnet::{
+        nat::NatType,
+        stun::{self, MessageClass, StunMessage, StunServer},
+    };
+    use std::sync::mpsc;
+
+    #[test]
+    fn reliable_packets_are_delivered_in_order() {
+        let stopper = Stopper::new();
+        let router = Router::start(&stopper).unwrap();
+        let lossy = LinkConfig {
+            loss_probabilty: 0.1,
+            repeated_loss_probability: 0.1,
+            reorder_probability: 0.5,
+            duplicate_probability: 0.5,
+            ..LinkConfig::profile("ideal").unwrap()
+        };
+        let ideal = LinkConfig::profile("ideal").unwrap();
+        let (sender, received) = mpsc::channel();
+        router
+            .add_interface(
+                "1.1.0.1".parse().unwrap(),
+                lossy.clone(),
+                lossy,
+                Box::new(|_packet: Packet| {}),
+            )
+            .unwrap();
+        router
+            .add_interface(
+                "2.1.0.1".parse().unwrap(),
+                ideal.clone(),
+                ideal,
+                Box::new(move |packet: Packet| {
+                    let _ = sender.send(packet);
+                }),
+            )
+            .unwrap();
+
+        for i in 0..100u8 {
+            router.send_packet(Packet {
+                source: "1.1.0.1:2001".parse().unwrap(),
+                dest: "2.1.0.1:2001".parse().unwrap(),
+                data: vec![i],
+                protocol: Protocol::Tcp,
+            });
+        }
+        for i in 0..100u8 {
+            let packet = received.recv_timeout(Duration::from_secs(30)).unwrap();
+            assert_eq!(vec![i], packet.data);
+        }
+        // And no duplicates
+        assert!(received.recv_timeout(Duration::from_millis(100)).is_err());
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn nat() {
+        let stopper = Stopper::new();
+        let router = Router::start(&stopper).unwrap();
+        let link = LinkConfig::profile("ideal").unwrap();
+        let add_endpoint = |ip: &str| {
+            let (sender, received) = mpsc::channel();
+            router
+                .add_interface(
+                    ip.parse().unwrap(),
+                    link.clone(),
+                    link.clone(),
+                    Box::new(move |packet: Packet| {
+                        let _ = sender.send(packet);
+                    }),
+                )
+                .unwrap();
+            received
+        };
+        let send = |source: &str, dest: &str, data: Vec<u8>| {
+            router.send_packet(Packet {
+                source: source.parse().unwrap(),
+                dest: dest.parse().unwrap(),
+                data,
+                protocol: Protocol::Udp,
+            });
+        };
+        let timeout = Duration::from_secs(5);
+        let nothing = Duration::from_millis(100);
+
+        router.add_nat(
+            "1.1.0.1".parse().unwrap(),
+            vec![
+                "192.168.1.2".parse().unwrap(),
+                "192.168.1.3".parse().unwrap(),
+            ],
+            NatConfig::new(NatType::PortRestrictedCone),
+        );
+        let private = add_endpoint("192.168.1.2");
+        let neighbor = add_endpoint("192.168.1.3");
+        let peer = add_endpoint("2.1.0.1");
+        let stun_server = StunServer::start(
+            "4.0.0.1".parse().unwrap(),
+            link.clone(),
+            link.clone(),
+            &router,
+        )
+        .unwrap();
+
+        let request = StunMessage::new(stun::BINDING, MessageClass::Request, [1; 12]);
+        send(
+            "192.168.1.2:2001",
+            &stun_server.address().to_string(),
+            request.encode(None),
+        );
+        let response = private.recv_timeout(timeout).unwrap();
+        assert_eq!(stun_server.address(), response.source);
+        assert_eq!(
+            "192.168.1.2:2001".parse::<SocketAddr>().unwrap(),
+            response.dest
+        );
+        let mapped = StunMessage::parse(&response.data)
+            .unwrap()
+            .xor_addresses(stun::ATTR_XOR_MAPPED_ADDRESS)[0];
+        assert_eq!("1.1.0.1:49152".parse::<SocketAddr>().unwrap(), mapped);
+
+        // The peer can't get in until the private endpoint sends to it.
+        send("2.1.0.1:2001", &mapped.to_string(), vec![1]);
+        assert!(private.recv_timeout(nothing).is_err());
+        send("192.168.1.2:2001", "2.1.0.1:2001", vec![2]);
+        let packet = peer.recv_timeout(timeout).unwrap();
+        assert_eq!(mapped, packet.source);
+        send("2.1.0.1:2001", &mapped.to_string(), vec![3]);
+        assert_eq!(vec![3], private.recv_timeout(timeout).unwrap().data);
+
+        // Private IPs are reachable from the same private network (directly
+        // and through the NAT), but not from outside it.
+        send("192.168.1.3:2001", "192.168.1.2:2001", vec![4]);
+        let packet = private.recv_timeout(timeout).unwrap();
+        assert_eq!(
+            "192.168.1.3:2001".parse::<SocketAddr>().unwrap(),
+            packet.source
+        );
+        send("192.168.1.2:2001", "192.168.1.3:2001", vec![5]);
+        assert_eq!(vec![5], neighbor.recv_timeout(timeout).unwrap().data);
+        send("2.1.0.1:2001", "192.168.1.2:2001", vec![6]);
+        assert!(private.recv_timeout(nothing).is_err());
+
+        stopper.stop_all_and_join();
+    }
+}
@@ -0,0 +1,1147 @@
+//
+// Copyright 2022 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Changes to a simulated network over time, read from a JSON timeline, so
+//! that problems seen in the field (like "video froze after switching from
+//! Wi-Fi") can be reproduced deterministically.  For example:
+//!
+//! ```json
+//! {
+//!   "interfaces": [
+//!     {"endpoint": "caller", "name": "wifi", "type": "wifi", "ip": "1.1.0.2",
+//!      "preference": 2, "profile": "good"},
+//!     {"endpoint": "caller", "name": "cell", "type": "cellular", "ip": "1.1.0.1",
+//!      "preference": 1, "profile": "cellular"},
+//!     {"endpoint": "callee", "name": "wifi", "type": "wifi", "ip": "2.1.0.2",
+//!      "preference": 2, "profile": "good"}
+//!   ],
+//!   "events": [
+//!     {"at_secs": 10, "endpoint": "caller", "interface": "wifi",
+//!      "action": "change_link", "rate_kbps": 300},
+//!     {"at_secs": 20, "endpoint": "caller", "interface": "wifi",
+//!      "action": "change_link", "direction": "send", "loss_probability": 0.05, "for_secs": 2},
+//!     {"at_secs": 30, "endpoint": "caller", "interface": "wifi", "action": "down"},
+//!     {"at_secs": 40, "endpoint": "caller", "interface": "wifi", "action": "up"}
+//!   ]
+//! }
+//! ```
+//!
+//! Times are from when the scenario is started.  Interfaces start with the
+//! "good" profile, changed by any of the link fields given (see LinkChange).
+//!
+//! A scenario can also have a simulated TURN server for the endpoints to use
+//! (see ScenarioRelay), such as:
+//!
+//! ```json
+//! "relay": {"ip": "3.0.0.1", "transports": ["tcp"], "relay_only": true, "profile": "good"}
+//! ```
+//!
+//! And interfaces can be put behind NATs (see ScenarioNat) by giving them
+//! private IPs and a "behind_nat", with a STUN server to discover their
+//! public addresses:
+//!
+//! ```json
+//! "nats": [{"public_ip": "1.1.0.1", "type": "symmetric", "mapping_timeout_secs": 30}],
+//! "stun_server": "4.0.0.1"
+//! ```
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde::{de::Error as _, Deserialize, Deserializer};
+
+use crate::{
+    common::{
+        actor::{Actor, Stopper},
+        units::DataRate,
+        Result,
+    },
+    error::RingRtcError,
+    simnet::{
+        impairment::{DelayDistribution, GilbertElliott, RateTrace},
+        nat::{NatConfig, NatType},
+        router::{LinkConfig, Protocol},
+    },
+    webrtc::network::NetworkInterfaceType,
+};
+
+// What interfaces start with (before their own link changes).
+const DEFAULT_PROFILE: &str = "good";
+
+/// Changes to a LinkConfig.  The profile (if any) replaces the whole config,
+/// then each of the other fields that are set replace their part of it.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct LinkChange {
+    pub profile: Option<String>,
+    pub delay_min_ms: Option<u64>,
+    pub delay_max_ms: Option<u64>,
+    /// For example {"type": "normal", "mean_ms": 50, "std_dev_ms": 10}
+    pub delay_distribution: Option<DelayDistribution>,
+    pub loss_probability: Option<f64>,
+    pub repeated_loss_probability: Option<f64>,
+    pub gilbert_elliott: Option<GilbertElliott>,
+    pub reorder_probability: Option<f64>,
+    pub duplicate_probability: Option<f64>,
+    pub rate_kbps: Option<u64>,
+    /// The path of a Mahimahi trace file.
+    pub rate_trace: Option<String>,
+    /// The size of the queue, as how long it takes to drain at the (new) rate.
+    pub queue_ms: Option<u64>,
+}
+
+impl LinkChange {
+    pub fn apply(&self, config: &LinkConfig) -> Result<LinkConfig> {
+        let mut config = match &self.profile {
+            Some(profile) => LinkConfig::profile(profile).ok_or_else(|| {
+                RingRtcError::InvalidNetworkScenario(format!(
+                    "unknown link profile {:?}; expected one of {:?}",
+                    profile,
+                    LinkConfig::PROFILE_NAMES
+                ))
+            })?,
+            None => config.clone(),
+        };
+        if let Some(delay_min_ms) = self.delay_min_ms {
+            config.delay_min = Duration::from_millis(delay_min_ms);
+        }
+        if let Some(delay_max_ms) = self.delay_max_ms {
+            config.delay_max = Duration::from_millis(delay_max_ms);
+        }
+        if let Some(delay_distribution) = &self.delay_distribution {
+            config.delay_distribution = delay_distribution.clone();
+        }
+        if let Some(loss_probability) = self.loss_probability {
+            config.loss_probabilty = loss_probability;
+        }
+        if let Some(repeated_loss_probability) = self.repeated_loss_probability {
+            config.repeated_loss_probability = repeated_loss_probability;
+        }
+        if let Some(gilbert_elliott) = &self.gilbert_elliott {
+            config.gilbert_elliott = Some(gilbert_elliott.clone());
+        }
+        if let Some(reorder_probability) = self.reorder_probability {
+            config.reorder_probability = reorder_probability;
+        }
+        if let Some(duplicate_probability) = self.duplicate_probability {
+            config.duplicate_probability = duplicate_probability;
+        }
+        if let Some(rate_kbps) = self.rate_kbps {
+            config.rate = DataRate::from_kbps(rate_kbps);
+        }
+        if let Some(rate_trace) = &self.rate_trace {
+            config.rate_trace = Some(Arc::new(RateTrace::load_mahimahi(rate_trace)?));
+        }
+        if let Some(queue_ms) = self.queue_ms {
+            config.queue_size = config.rate * Duration::from_millis(queue_ms);
+        }
+
+        if config.delay_min > config.delay_max {
+            return Err(RingRtcError::InvalidNetworkScenario(format!(
+                "delay_min ({:?}) is more than delay_max ({:?})",
+                config.delay_min, config.delay_max
+            ))
+            .into());
+        }
+        let probabilities = [
+            config.loss_probabilty,
+            config.repeated_loss_probability,
+            config.reorder_probability,
+            config.duplicate_probability,
+        ];
+        let gilbert_elliott_probabilities = config
+            .gilbert_elliott
+            .as_ref()
+            .map(GilbertElliott::probabilities)
+            .unwrap_or_default();
+        for probability in probabilities
+            .iter()
+            .chain(gilbert_elliott_probabilities.iter())
+        {
+            if !(0.0..=1.0).contains(probability) {
+                return Err(RingRtcError::InvalidNetworkScenario(format!(
+                    "probability ({}) is not between 0 and 1",
+                    probability
+                ))
+                .into());
+            }
+        }
+        Ok(config)
+    }
+
+    /// Undoes a change made by apply: the parts of the link it set (all of
+    /// them for a profile) go back to how they were before, and the rest
+    /// keep their current values.
+    pub fn restore(&self, current: &LinkConfig, before: &LinkConfig) -> LinkConfig {
+        if self.profile.is_some() {
+            return before.clone();
+        }
+        let mut config = current.clone();
+        for part in self.parts() {
+            match part {
+                "delay" => {
+                    config.delay_min = before.delay_min;
+                    config.delay_max = before.delay_max;
+                }
+                "delay_distribution" => {
+                    config.delay_distribution = before.delay_distribution.clone();
+                }
+                "loss" => {
+                    config.loss_probabilty = before.loss_probabilty;
+                    config.repeated_loss_probability = before.repeated_loss_probability;
+                    config.gilbert_elliott = before.gilbert_elliott.clone();
+                }
+                "reorder" => config.reorder_probability = before.reorder_probability,
+                "duplicate" => config.duplicate_probability = before.duplicate_probability,
+                "rate" => {
+                    config.rate = before.rate;
+                    config.rate_trace = before.rate_trace.clone();
+                    config.queue_size = before.queue_size;
+                }
+                _ => unreachable!("unknown link part {}", part),
+            }
+        }
+        config
+    }
+
+    /// The parts of the link this sets.  Fields that only make sense
+    /// together (such as the rate and the queue size it drains at) are one part.
+    fn parts(&self) -> Vec<&'static str> {
+        let all = [
+            (
+                "delay",
+                self.delay_min_ms.is_some() || self.delay_max_ms.is_some(),
+            ),
+            ("delay_distribution", self.delay_distribution.is_some()),
+            (
+                "loss",
+                self.loss_probability.is_some()
+                    || self.repeated_loss_probability.is_some()
+                    || self.gilbert_elliott.is_some(),
+            ),
+            ("reorder", self.reorder_probability.is_some()),
+            ("duplicate", self.duplicate_probability.is_some()),
+            (
+                "rate",
+                self.rate_kbps.is_some() || self.rate_trace.is_some() || self.queue_ms.is_some(),
+            ),
+        ];
+        all.iter()
+            .filter(|(_, set)| self.profile.is_some() || *set)
+            .map(|(part, _)| *part)
+            .collect()
+    }
+}
+
+impl LinkDirection {
+    fn overlaps(self, other: Self) -> bool {
+        self == Self::Both || other == Self::Both || self == other
+    }
+}
+
+fn deserialize_interface_type<'de, D>(
+    deserializer: D,
+) -> std::result::Result<NetworkInterfaceType, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let typ = String::deserialize(deserializer)?;
+    match typ.as_str() {
+        "ethernet" => Ok(NetworkInterfaceType::Ethernet),
+        "wifi" => Ok(NetworkInterfaceType::Wifi),
+        "cellular" => Ok(NetworkInterfaceType::Cellular),
+        "vpn" => Ok(NetworkInterfaceType::Vpn),
+        _ => Err(D::Error::custom(format!(
+            "unknown interface type {:?}",
+            typ
+        ))),
+    }
+}
+
+/// A network interface of one of the simulated endpoints.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScenarioInterface {
+    pub endpoint: String,
+    pub name: String,
+    #[serde(rename = "type", deserialize_with = "deserialize_interface_type")]
+    pub typ: NetworkInterfaceType,
+    pub ip: IpAddr,
+    pub preference: u16,
+    /// The public IP of the ScenarioNat the (private) ip is behind.
+    #[serde(default)]
+    pub behind_nat: Option<IpAddr>,
+    /// Applied to both directions when the scenario starts.
+    #[serde(flatten)]
+    pub link: LinkChange,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkDirection {
+    #[default]
+    Both,
+    Send,
+    Receive,
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ScenarioAction {
+    /// If for_secs is set, the parts of the link this sets go back to how they
+    /// were after that long.  Other changes to those parts until then are rejected.
+    ChangeLink {
+        #[serde(default)]
+        direction: LinkDirection,
+        #[serde(flatten)]
+        change: LinkChange,
+        for_secs: Option<f64>,
+    },
+    /// Removes the interface, as if the device lost it.
+    Down,
+    /// Adds the interface back with the links it had before.
+    Up,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ScenarioEvent {
+    pub at_secs: f64,
+    pub endpoint: String,
+    pub interface: String,
+    #[serde(flatten)]
+    pub action: ScenarioAction,
+}
+
+/// Where a running Scenario makes its changes.  Implemented by whatever
+/// owns the endpoints (and their InjectableNetworks) and the Router.
+pub trait ScenarioTarget: Send {
+    /// Adds the interface to the endpoint's network and to the Router.
+    fn add_interface(
+        &self,
+        interface: &ScenarioInterface,
+        send_config: &LinkConfig,
+        receive_config: &LinkConfig,
+    );
+    /// Removes the interface from the endpoint's network and from the Router.
+    fn remove_interface(&self, interface: &ScenarioInterface);
+    /// See Router::set_link_configs.
+    fn set_link_configs(
+        &self,
+        interface: &ScenarioInterface,
+        send_config: &LinkConfig,
+        receive_config: &LinkConfig,
+    );
+}
+
+fn default_relay_transports() -> Vec<Protocol> {
+    vec![Protocol::Udp, Protocol::Tcp]
+}
+
+/// A simulated TURN server (see simnet::turn) that the endpoints are told about.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScenarioRelay {
+    pub ip: IpAddr,
+    /// How the endpoints reach the server ("udp" and/or "tcp").
+    #[serde(default = "default_relay_transports")]
+    pub transports: Vec<Protocol>,
+    /// If set, the endpoints only use relayed candidates (like hide_ip does).
+    #[serde(default)]
+    pub relay_only: bool,
+    /// Applied to both directions of the server's link.
+    #[serde(flatten)]
+    pub link: LinkChange,
+}
+
+impl ScenarioRelay {
+    pub fn link_config(&self) -> Result<LinkConfig> {
+        self.link
+            .apply(&LinkConfig::profile(DEFAULT_PROFILE).expect("default profile exists"))
+    }
+}
+
+/// A NAT (see simnet::nat) with the interfaces that are behind_nat it.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScenarioNat {
+    pub public_ip: IpAddr,
+    #[serde(rename = "type")]
+    pub typ: NatType,
+    pub mapping_timeout_secs: Option<f64>,
+}
+
+impl ScenarioNat {
+    pub fn config(&self) -> Result<NatConfig> {
+        let mut config = NatConfig::new(self.typ);
+        if let Some(mapping_timeout_secs) = self.mapping_timeout_secs {
+            config.mapping_timeout = secs_to_duration(mapping_timeout_secs)?;
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Scenario {
+    pub interfaces: Vec<ScenarioInterface>,
+    #[serde(default)]
+    pub events: Vec<ScenarioEvent>,
+    #[serde(default)]
+    pub relay: Option<ScenarioRelay>,
+    #[serde(default)]
+    pub nats: Vec<ScenarioNat>,
+    /// The IP of a simulated STUN server (see stun::StunServer).
+    #[serde(default)]
+    pub stun_server: Option<IpAddr>,
+}
+
+fn secs_to_duration(secs: f64) -> Result<Duration> {
+    if secs.is_finite() && secs >= 0.0 {
+        Ok(Duration::from_secs_f64(secs))
+    } else {
+        Err(RingRtcError::InvalidNetworkScenario(format!("invalid time {}", secs)).into())
+    }
+}
+
+impl Scenario {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let scenario: Self = serde_json::from_str(json)
+            .map_err(|e| RingRtcError::InvalidNetworkScenario(e.to_string()))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// The IPs of the interfaces behind the NAT.
+    pub fn private_ips(&self, nat: &ScenarioNat) -> Vec<IpAddr> {
+        self.interfaces
+            .iter()
+            .filter(|interface| interface.behind_nat == Some(nat.public_ip))
+            .map(|interface| interface.ip)
+            .collect()
+    }
+
+    fn validate(&self) -> Result<()> {
+        // Servers and NATs each need an IP of their own.
+        let server_ips: Vec<(&str, IpAddr)> = self
+            .relay
+            .iter()
+            .map(|relay| ("relay", relay.ip))
+            .chain(self.stun_server.iter().map(|ip| ("STUN server", *ip)))
+            .chain(self.nats.iter().map(|nat| ("NAT", nat.public_ip)))
+            .collect();
+        for (i, (name, ip)) in server_ips.iter().enumerate() {
+            if server_ips[..i].iter().any(|(_, other_ip)| other_ip == ip)
+                || self.interfaces.iter().any(|interface| interface.ip == *ip)
+            {
+                return Err(RingRtcError::InvalidNetworkScenario(format!(
+                    "the {} IP {} is used more than once",
+                    name, ip
+                ))
+                .into());
+            }
+        }
+        for nat in &self.nats {
+            nat.config()?;
+        }
+
+        let default_link = LinkConfig::profile(DEFAULT_PROFILE).expect("default profile exists");
+        let mut links = HashMap::new();
+        for interface in &self.interfaces {
+            if let Some(behind_nat) = interface.behind_nat {
+                if !self.nats.iter().any(|nat| nat.public_ip == behind_nat) {
+                    return Err(RingRtcError::InvalidNetworkScenario(format!(
+                        "interface {}/{} is behind unknown NAT {}",
+                        interface.endpoint, interface.name, behind_nat
+                    ))
+                    .into());
+                }
+            }
+            let link = interface.link.apply(&default_link)?;
+            if self
+                .interfaces
+                .iter()
+                .filter(|other| other.ip == interface.ip)
+                .count()
+                > 1
+            {
+                return Err(RingRtcError::InvalidNetworkScenario(format!(
+                    "more than one interface has IP {}",
+                    interface.ip
+                ))
+                .into());
+            }
+            let key = (interface.endpoint.as_str(), interface.name.as_str());
+            if links.insert(key, link).is_some() {
+                return Err(RingRtcError::InvalidNetworkScenario(format!(
+                    "more than one interface named {}/{}",
+                    interface.endpoint, interface.name
+                ))
+                .into());
+            }
+        }
+        if let Some(relay) = &self.relay {
+            relay.link_config()?;
+            if relay.transports.is_empty() {
+                return Err(RingRtcError::InvalidNetworkScenario(
+                    "the relay has no transports".to_string(),
+                )
+                .into());
+            }
+        }
+        for event in &self.events {
+            secs_to_duration(event.at_secs)?;
+            let link = links
+                .get(&(event.endpoint.as_str(), event.interface.as_str()))
+                .ok_or_else(|| {
+                    RingRtcError::InvalidNetworkScenario(format!(
+                        "event for unknown interface {}/{}",
+                        event.endpoint, event.interface
+                    ))
+                })?;
+            if let ScenarioAction::ChangeLink {
+                change, for_secs, ..
+            } = &event.action
+            {
+                // Catches bad profiles and probabilities.  Anything that depends on
+                // the earlier events is checked when the event happens.
+                change.apply(link)?;
+                if let Some(for_secs) = for_secs {
+                    secs_to_duration(*for_secs)?;
+                }
+            }
+        }
+        for (i, event) in self.events.iter().enumerate() {
+            self.validate_no_overlap(i, event)?;
+        }
+        Ok(())
+    }
+
+    /// A temporary link change only puts back the parts of the link it set,
+    /// so another change to the same part before then would be undone.
+    fn validate_no_overlap(&self, index: usize, event: &ScenarioEvent) -> Result<()> {
+        let (direction, change, for_secs) = match &event.action {
+            ScenarioAction::ChangeLink {
+                direction,
+                change,
+                for_secs: Some(for_secs),
+            } => (direction, change, for_secs),
+            _ => return Ok(()),
+        };
+        let parts = change.parts();
+        // The same arithmetic as when the scenario runs, so that it's the same at the boundary.
+        let at = secs_to_duration(event.at_secs)?;
+        let restored_at = at + secs_to_duration(*for_secs)?;
+        for (other_index, other) in self.events.iter().enumerate() {
+            let (other_direction, other_change) = match &other.action {
+                ScenarioAction::ChangeLink {
+                    direction, change, ..
+                } => (direction, change),
+                _ => continue,
+            };
+            // Changes at the same time are made in order, and the link is
+            // restored after the changes due at the same time.
+            let other_at = secs_to_duration(other.at_secs)?;
+            let during = if other_at == at {
+                other_index > index
+            } else {
+                other_at > at && other_at <= restored_at
+            };
+            if during
+                && other.endpoint == event.endpoint
+                && other.interface == event.interface
+                && direction.overlaps(*other_direction)
+            {
+                if let Some(part) = other_change
+                    .parts()
+                    .into_iter()
+                    .find(|part| parts.contains(part))
+                {
+                    return Err(RingRtcError::InvalidNetworkScenario(format!(
+                        "the {} of {}/{} changes at {}s, while the change at {}s is in effect",
+                        part, event.endpoint, event.interface, other.at_secs, event.at_secs
+                    ))
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// When the last event happens (including putting links back).
+    pub fn duration(&self) -> Duration {
+        self.events
+            .iter()
+            .map(|event| {
+                let for_secs = match event.action {
+                    ScenarioAction::ChangeLink {
+                        for_secs: Some(for_secs),
+                        ..
+                    } => for_secs,
+                    _ => 0.0,
+                };
+                secs_to_duration(event.at_secs + for_secs).unwrap_or_default()
+            })
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Adds the interfaces now, then makes each change when it's due.
+    pub fn start(self, target: Box<dyn ScenarioTarget>, stopper: &Stopper) -> Result<()> {
+        let started = Instant::now();
+        let default_link = LinkConfig::profile(DEFAULT_PROFILE).expect("default profile exists");
+        let mut interfaces = HashMap::new();
+        for interface in self.interfaces {
+            let link = interface.link.apply(&default_link)?;
+            target.add_interface(&interface, &link, &link);
+            interfaces.insert(
+                (interface.endpoint.clone(), interface.name.clone()),
+                InterfaceState {
+                    interface,
+                    send_config: link.clone(),
+                    receive_config: link,
+                    up: true,
+                },
+            );
+        }
+
+        let mut pending = PendingChanges::default();
+        for event in self.events {
+            pending.insert(
+                secs_to_duration(event.at_secs)?,
+                (event.endpoint, event.interface),
+                PendingChange::Action(event.action),
+            );
+        }
+
+        let actor = Actor::start(stopper.clone(), move |actor| {
+            Ok(ScenarioState {
+                actor,
+                started,
+                target,
+                interfaces,
+                pending,
+            })
+        })?;
+        actor.send(ScenarioState::make_due_changes);
+        Ok(())
+    }
+}
+
+struct InterfaceState {
+    interface: ScenarioInterface,
+    send_config: LinkConfig,
+    receive_config: LinkConfig,
+    up: bool,
+}
+
+type InterfaceKey = (String, String);
+
+#[allow(clippy::large_enum_variant)]
+enum PendingChange {
+    Action(ScenarioAction),
+    /// Undoes the change, which was made to these configs.
+    RestoreLink {
+        direction: LinkDirection,
+        change: LinkChange,
+        send_config: LinkConfig,
+        receive_config: LinkConfig,
+    },
+}
+
+// Sorted by when they are due.  Changes due at the same time stay in the
+// order they were added.
+#[derive(Default)]
+struct PendingChanges(Vec<(Duration, InterfaceKey, PendingChange)>);
+
+impl PendingChanges {
+    fn insert(&mut self, due: Duration, key: InterfaceKey, change: PendingChange) {
+        let index = self
+            .0
+            .partition_point(|(other_due, _, _)| *other_due <= due);
+        self.0.insert(index, (due, key, change));
+    }
+
+    fn next_due(&self) -> Option<Duration> {
+        self.0.first().map(|(due, _, _)| *due)
+    }
+
+    fn pop(&mut self) -> Option<(Duration, InterfaceKey, PendingChange)> {
+        if self.0.is_empty() {
+            None
+        } else {
+            Some(self.0.remove(0))
+        }
+    }
+}
+
+struct ScenarioState {
+    // Keep a copy around to be able to schedule changes.
+    actor: Actor<Self>,
+    started: Instant,
+    target: Box<dyn ScenarioTarget>,
+    interfaces: HashMap<InterfaceKey, InterfaceState>,
+    pending: PendingChanges,
+}
+
+impl ScenarioState {
+    fn make_due_changes(&mut self) {
+        while let Some(due) = self.pending.next_due() {
+            if due > self.started.elapsed() {
+                self.actor.send_delayed(
+                    due.saturating_sub(self.started.elapsed()),
+                    Self::make_due_changes,
+                );
+                return;
+            }
+            if let Some((due, key, change)) = self.pending.pop() {
+                self.make_change(due, key, change);
+            }
+        }
+        info!("Network scenario finished");
+    }
+
+    fn make_change(&mut self, due: Duration, key: InterfaceKey, change: PendingChange) {
+        let state = match self.interfaces.get_mut(&key) {
+            Some(state) => state,
+            None => {
+                warn!("Network scenario: unknown interface {}/{}", key.0, key.1);
+                return;
+            }
+        };
+        let (send_config, receive_config) = match change {
+            PendingChange::Action(ScenarioAction::Down) => {
+                info!("Network scenario: {}/{} down", key.0, key.1);
+                if state.up {
+                    self.target.remove_interface(&state.interface);
+                    state.up = false;
+                }
+                return;
+            }
+            PendingChange::Action(ScenarioAction::Up) => {
+                info!("Network scenario: {}/{} up", key.0, key.1);
+                if !state.up {
+                    self.target.add_interface(
+                        &state.interface,
+                        &state.send_config,
+                        &state.receive_config,
+                    );
+                    state.up = true;
+                }
+                return;
+            }
+            PendingChange::Action(ScenarioAction::ChangeLink {
+                direction,
+                change,
+                for_secs,
+            }) => {
+                info!(
+                    "Network scenario: {}/{} {:?} link change {:?}",
+                    key.0, key.1, direction, change
+                );
+                let changed = |config: &LinkConfig| match change.apply(config) {
+                    Ok(changed) => changed,
+                    Err(e) => {
+                        warn!("Network scenario: ignoring link change: {}", e);
+                        config.clone()
+                    }
+                };
+                let send_config = match direction {
+                    LinkDirection::Both | LinkDirection::Send => changed(&state.send_config),
+                    LinkDirection::Receive => state.send_config.clone(),
+                };
+                let receive_config = match direction {
+                    LinkDirection::Both | LinkDirection::Receive => changed(&state.receive_config),
+                    LinkDirection::Send => state.receive_config.clone(),
+                };
+                if let Some(for_secs) = for_secs {
+                    self.pending.insert(
+                        due + Duration::from_secs_f64(for_secs),
+                        key.clone(),
+                        PendingChange::RestoreLink {
+                            direction,
+                            change: change.clone(),
+                            send_config: state.send_config.clone(),
+                            receive_config: state.receive_config.clone(),
+                        },
+                    );
+                }
+                (send_config, receive_config)
+            }
+            PendingChange::RestoreLink {
+                direction,
+                change,
+                send_config,
+                receive_config,
+            } => {
+                info!("Network scenario: {}/{} link restored", key.0, key.1);
+                // Later changes to other parts of the link are kept.
+                let send_config = match direction {
+                    LinkDirection::Both | LinkDirection::Send => {
+                        change.restore(&state.send_config, &send_config)
+                    }
+                    LinkDirection::Receive => state.send_config.clone(),
+                };
+                let receive_config = match direction {
+                    LinkDirection::Both | LinkDirection::Receive => {
+                        change.restore(&state.receive_config, &receive_config)
+                    }
+                    LinkDirection::Send => state.receive_config.clone(),
+                };
+                (send_config, receive_config)
+            }
+        };
+        // A link that's down still changes; it comes back up with the new config.
+        if state.up {
+            self.target
+                .set_link_configs(&state.interface, &send_config, &receive_config);
+        }
+        state.send_config = send_config;
+        state.receive_config = receive_config;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        mpsc::{channel, Sender},
+        Mutex,
+    };
+
+    const SCENARIO: &str = r#"{
+        "interfaces": [
+            {"endpoint": "caller", "name": "wifi", "type": "wifi", "ip": "1.1.0.2",
+             "preference": 2},
+            {"endpoint": "caller", "name": "cell", "type": "cellular", "ip": "1.1.0.1",
+             "preference": 1, "profile": "cellular", "rate_kbps": 1000,
+             "behind_nat": "1.1.0.100"}
+        ],
+        "nats": [{"public_ip": "1.1.0.100", "type": "port_restricted_cone",
+                  "mapping_timeout_secs": 30}],
+        "stun_server": "4.0.0.1",
+        "relay": {"ip": "3.0.0.1", "transports": ["tcp"], "relay_only": true,
+                  "loss_probability": 0.01},
+        "events": [
+            {"at_secs": 0.1, "endpoint": "caller", "interface": "wifi",
+             "action": "change_link", "direction": "send", "loss_probability": 0.5,
+             "for_secs": 0.1},
+            {"at_secs": 0.15, "endpoint": "caller", "interface": "wifi", "action": "down"},
+            {"at_secs": 0.15, "endpoint": "caller", "interface": "wifi", "action": "up"}
+        ]
+    }"#;
+
+    #[derive(Debug, PartialEq)]
+    enum Change {
+        Add(String, f64, u64),
+        Remove(String),
+        // Send and receive loss, then send rate
+        Set(String, f64, f64, u64),
+    }
+
+    struct FakeTarget(Mutex<Sender<Change>>);
+
+    impl ScenarioTarget for FakeTarget {
+        fn add_interface(
+            &self,
+            interface: &ScenarioInterface,
+            send_config: &LinkConfig,
+            _receive_config: &LinkConfig,
+        ) {
+            let _ = self.0.lock().unwrap().send(Change::Add(
+                interface.name.clone(),
+                send_config.loss_probabilty,
+                send_config.rate.as_kbps(),
+            ));
+        }
+
+        fn remove_interface(&self, interface: &ScenarioInterface) {
+            let _ = self
+                .0
+                .lock()
+                .unwrap()
+                .send(Change::Remove(interface.name.clone()));
+        }
+
+        fn set_link_configs(
+            &self,
+            interface: &ScenarioInterface,
+            send_config: &LinkConfig,
+            receive_config: &LinkConfig,
+        ) {
+            let _ = self.0.lock().unwrap().send(Change::Set(
+                interface.name.clone(),
+                send_config.loss_probabilty,
+                receive_config.loss_probabilty,
+                send_config.rate.as_kbps(),
+            ));
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let scenario = Scenario::from_json(SCENARIO).unwrap();
+        assert_eq!(2, scenario.interfaces.len());
+        assert_eq!(Some(1000), scenario.interfaces[1].link.rate_kbps);
+        assert_eq!(
+            ScenarioAction::ChangeLink {
+                direction: LinkDirection::Send,
+                change: LinkChange {
+                    loss_probability: Some(0.5),
+                    ..Default::default()
+                },
+                for_secs: Some(0.1),
+            },
+            scenario.events[0].action
+        );
+        assert_eq!(ScenarioAction::Down, scenario.events[1].action);
+        assert_eq!(Duration::from_millis(200), scenario.duration());
+        let relay = scenario.relay.unwrap();
+        assert_eq!(vec![Protocol::Tcp], relay.transports);
+        assert!(relay.relay_only);
+        assert_eq!(0.01, relay.link_config().unwrap().loss_probabilty);
+        let relay = Scenario::from_json(&SCENARIO.replace("\"transports\": [\"tcp\"], ", ""))
+            .unwrap()
+            .relay
+            .unwrap();
+        assert_eq!(vec![Protocol::Udp, Protocol::Tcp], relay.transports);
+        let scenario = Scenario::from_json(SCENARIO).unwrap();
+        let nat = &scenario.nats[0];
+        assert_eq!(
+            NatConfig {
+                typ: NatType::PortRestrictedCone,
+                mapping_timeout: Duration::from_secs(30),
+            },
+            nat.config().unwrap()
+        );
+        assert_eq!(
+            vec!["1.1.0.1".parse::<IpAddr>().unwrap()],
+            scenario.private_ips(nat)
+        );
+
+        for bad in [
+            SCENARIO.replace("\"wifi\", \"type\"", "\"cell\", \"type\""),
+            SCENARIO.replace("1.1.0.2", "1.1.0.1"),
+            SCENARIO.replace("\"type\": \"wifi\"", "\"type\": \"wired\""),
+            SCENARIO.replace("\"cellular\", \"rate", "\"fast\", \"rate"),
+            SCENARIO.replace("0.5", "1.5"),
+            SCENARIO.replace("3.0.0.1", "1.1.0.1"),
+            SCENARIO.replace("[\"tcp\"]", "[\"tls\"]"),
+            SCENARIO.replace("[\"tcp\"]", "[]"),
+            SCENARIO.replace("0.01", "-0.01"),
+            SCENARIO.replace(
+                "\"behind_nat\": \"1.1.0.100\"",
+                "\"behind_nat\": \"1.1.0.101\"",
+            ),
+            SCENARIO.replace("\"port_restricted_cone\"", "\"cone\""),
+            SCENARIO.replace(
+                "\"mapping_timeout_secs\": 30",
+                "\"mapping_timeout_secs\": -30",
+            ),
+            SCENARIO.replace("4.0.0.1", "3.0.0.1"),
+            SCENARIO.replace("4.0.0.1", "1.1.0.2"),
+            SCENARIO.replace("\"at_secs\": 0.1", "\"at_secs\": -1"),
+            SCENARIO.replace(
+                "\"interface\": \"wifi\", \"action\": \"down\"",
+                "\"interface\": \"lte\", \"action\": \"down\"",
+            ),
+            // Changes the loss while the temporary loss is in effect.
+            SCENARIO.replace(
+                "\"action\": \"down\"",
+                "\"action\": \"change_link\", \"loss_probability\": 0.1",
+            ),
+        ] {
+            assert!(Scenario::from_json(&bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn link_change() {
+        let good = LinkConfig::profile("good").unwrap();
+        let changed = LinkChange {
+            rate_kbps: Some(300),
+            queue_ms: Some(1000),
+            delay_max_ms: Some(50),
+            ..Default::default()
+        }
+        .apply(&good)
+        .unwrap();
+        assert_eq!(300, changed.rate.as_kbps());
+        assert_eq!(300_000 / 8, changed.queue_size.as_bytes());
+        assert_eq!(good.delay_min, changed.delay_min);
+        assert_eq!(Duration::from_millis(50), changed.delay_max);
+
+        let bad = LinkChange {
+            profile: Some("bad".to_string()),
+            ..Default::default()
+        }
+        .apply(&good)
+        .unwrap();
+        assert_eq!(256, bad.rate.as_kbps());
+
+        assert!(LinkChange {
+            delay_min_ms: Some(100),
+            ..Default::default()
+        }
+        .apply(&good)
+        .is_err());
+
+        let change: LinkChange = serde_json::from_str(
+            r#"{
+                "delay_distribution": {"type": "pareto", "scale_ms": 5, "shape": 2.5},
+                "gilbert_elliott": {
+                    "good_to_bad_probability": 0.01,
+                    "bad_to_good_probability": 0.3,
+                    "good_loss_probability": 0.001,
+                    "bad_loss_probability": 0.5
+                },
+                "reorder_probability": 0.02,
+                "duplicate_probability": 0.01
+            }"#,
+        )
+        .unwrap();
+        let impaired = change.apply(&good).unwrap();
+        assert_eq!(
+            DelayDistribution::Pareto {
+                scale: Duration::from_millis(5),
+                shape: 2.5
+            },
+            impaired.delay_distribution
+        );
+        assert_eq!(
+            Some(0.5),
+            impaired
+                .gilbert_elliott
+                .as_ref()
+                .map(|model| model.bad_loss_probability)
+        );
+        assert_eq!(0.02, impaired.reorder_probability);
+        assert_eq!(0.01, impaired.duplicate_probability);
+
+        let mut bad_model = change;
+        if let Some(model) = &mut bad_model.gilbert_elliott {
+            model.bad_to_good_probability = 2.0;
+        }
+        assert!(bad_model.apply(&good).is_err());
+        assert!(LinkChange {
+            rate_trace: Some("/does/not/exist".to_string()),
+            ..Default::default()
+        }
+        .apply(&good)
+        .is_err());
+    }
+
+    #[test]
+    fn temporary_change_keeps_overlapping_changes() {
+        let scenario = Scenario::from_json(
+            r#"{
+            "interfaces": [
+                {"endpoint": "caller", "name": "wifi", "type": "wifi", "ip": "1.1.0.2",
+                 "preference": 2}
+            ],
+            "events": [
+                {"at_secs": 0.05, "endpoint": "caller", "interface": "wifi",
+                 "action": "change_link", "direction": "send", "loss_probability": 0.5,
+                 "for_secs": 0.1},
+                {"at_secs": 0.1, "endpoint": "caller", "interface": "wifi",
+                 "action": "change_link", "rate_kbps": 1000},
+                {"at_secs": 0.1, "endpoint": "caller", "interface": "wifi",
+                 "action": "change_link", "direction": "receive", "loss_probability": 0.2}
+            ]
+        }"#,
+        )
+        .unwrap();
+        let (sender, receiver) = channel();
+        let stopper = Stopper::new();
+        scenario
+            .start(Box::new(FakeTarget(Mutex::new(sender))), &stopper)
+            .unwrap();
+
+        let timeout = Duration::from_secs(5);
+        let mut changes = vec![];
+        for _ in 0..5 {
+            changes.push(receiver.recv_timeout(timeout).unwrap());
+        }
+        stopper.stop_all_and_join();
+
+        let wifi = || "wifi".to_string();
+        assert_eq!(
+            vec![
+                Change::Add(wifi(), 0.0, 5000),
+                Change::Set(wifi(), 0.5, 0.0, 5000),
+                Change::Set(wifi(), 0.5, 0.0, 1000),
+                Change::Set(wifi(), 0.5, 0.2, 1000),
+                // Only the send loss goes back.
+                Change::Set(wifi(), 0.0, 0.2, 1000),
+            ],
+            changes
+        );
+
+        let loss = |at_secs: f64, direction: &str| {
+            format!(
+                r#"{{"at_secs": {}, "endpoint": "caller", "interface": "wifi",
+                    "action": "change_link", "direction": "{}", "loss_probability": 0.1}}"#,
+                at_secs, direction
+            )
+        };
+        let with_events = |events: &[String]| {
+            format!(
+                r#"{{"interfaces": [{{"endpoint": "caller", "name": "wifi", "type": "wifi",
+                    "ip": "1.1.0.2", "preference": 2}}],
+                    "events": [{{"at_secs": 0.05, "endpoint": "caller", "interface": "wifi",
+                                "action": "change_link", "direction": "send",
+                                "loss_probability": 0.5, "for_secs": 0.1}}, {}]}}"#,
+                events.join(", ")
+            )
+        };
+        for ok in [
+            loss(0.1, "receive"),
+            loss(0.2, "send"),
+            loss(0.05, "receive"),
+        ] {
+            let json = with_events(&[ok]);
+            assert!(Scenario::from_json(&json).is_ok(), "{}", json);
+        }
+        for overlapping in [
+            loss(0.1, "send"),
+            loss(0.1, "both"),
+            loss(0.05, "send"),
+            loss(0.15, "send"),
+        ] {
+            let json = with_events(&[overlapping]);
+            assert!(Scenario::from_json(&json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn run() {
+        let (sender, receiver) = channel();
+        let stopper = Stopper::new();
+        Scenario::from_json(SCENARIO)
+            .unwrap()
+            .start(Box::new(FakeTarget(Mutex::new(sender))), &stopper)
+            .unwrap();
+
+        let timeout = Duration::from_secs(5);
+        let mut changes = vec![];
+        for _ in 0..6 {
+            changes.push(receiver.recv_timeout(timeout).unwrap());
+        }
+        stopper.stop_all_and_join();
+
+        let wifi = || "wifi".to_string();
+        assert_eq!(
+            vec![
+                Change::Add(wifi(), 0.0, 5000),
+                Change::Add("cell".to_string(), 0.01, 1000),
+                Change::Set(wifi(), 0.5, 0.0, 5000),
+                Change::Remove(wifi()),
+                // Back up with the changed link
+                Change::Add(wifi(), 0.5, 5000),
+                Change::Set(wifi(), 0.0, 0.0, 5000),
+            ],
+            changes
+        );
+    }
+}
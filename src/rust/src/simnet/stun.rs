@@ -0,0 +1,438 @@
+//
+// Copyright 2022 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Just enough of STUN (RFC 5389) for the simulated STUN and TURN servers:
+//! parsing and building messages, including MESSAGE-INTEGRITY with long-term
+//! credentials, which is why the simnet feature pulls in MD5 and SHA-1.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use hmac::{Hmac, Mac, NewMac};
+use md5::{Digest, Md5};
+use sha1::Sha1;
+
+use crate::{
+    common::Result,
+    simnet::router::{LinkConfig, Packet, Router},
+};
+
+pub const MAGIC_COOKIE: u32 = 0x2112_a442;
+const HEADER_SIZE: usize = 20;
+const MESSAGE_INTEGRITY_SIZE: usize = 20;
+
+// Methods (RFC 5389 and RFC 5766)
+pub const BINDING: u16 = 0x001;
+pub const ALLOCATE: u16 = 0x003;
+pub const REFRESH: u16 = 0x004;
+pub const SEND: u16 = 0x006;
+pub const DATA: u16 = 0x007;
+pub const CREATE_PERMISSION: u16 = 0x008;
+pub const CHANNEL_BIND: u16 = 0x009;
+
+// Attributes (RFC 5389 and RFC 5766)
+pub const ATTR_USERNAME: u16 = 0x0006;
+pub const ATTR_MESSAGE_INTEGRITY: u16 = 0x0008;
+pub const ATTR_ERROR_CODE: u16 = 0x0009;
+pub const ATTR_CHANNEL_NUMBER: u16 = 0x000c;
+pub const ATTR_LIFETIME: u16 = 0x000d;
+pub const ATTR_XOR_PEER_ADDRESS: u16 = 0x0012;
+pub const ATTR_DATA: u16 = 0x0013;
+pub const ATTR_REALM: u16 = 0x0014;
+pub const ATTR_NONCE: u16 = 0x0015;
+pub const ATTR_XOR_RELAYED_ADDRESS: u16 = 0x0016;
+pub const ATTR_REQUESTED_TRANSPORT: u16 = 0x0019;
+pub const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageClass {
+    Request,
+    Indication,
+    SuccessResponse,
+    ErrorResponse,
+}
+
+impl MessageClass {
+    fn bits(self) -> u16 {
+        match self {
+            Self::Request => 0b00,
+            Self::Indication => 0b01,
+            Self::SuccessResponse => 0b10,
+            Self::ErrorResponse => 0b11,
+        }
+    }
+
+    fn from_bits(bits: u16) -> Self {
+        match bits & 0b11 {
+            0b00 => Self::Request,
+            0b01 => Self::Indication,
+            0b10 => Self::SuccessResponse,
+            _ => Self::ErrorResponse,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StunMessage {
+    pub method: u16,
+    pub class: MessageClass,
+    pub transaction_id: [u8; 12],
+    /// Types and values, without padding, in order.
+    pub attributes: Vec<(u16, Vec<u8>)>,
+}
+
+fn padded_size(size: usize) -> usize {
+    (size + 3) & !3
+}
+
+impl StunMessage {
+    pub fn new(method: u16, class: MessageClass, transaction_id: [u8; 12]) -> Self {
+        Self {
+            method,
+            class,
+            transaction_id,
+            attributes: Vec::new(),
+        }
+    }
+
+    /// A response to this (request) message, with the same transaction ID.
+    pub fn response(&self, class: MessageClass) -> Self {
+        Self::new(self.method, class, self.transaction_id)
+    }
+
+    /// Returns None if the data isn't a STUN message.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < HEADER_SIZE {
+            return None;
+        }
+        let typ = u16::from_be_bytes([data[0], data[1]]);
+        let size = u16::from_be_bytes([data[2], data[3]]) as usize;
+        if typ & 0xc000 != 0
+            || data[4..8] != MAGIC_COOKIE.to_be_bytes()
+            || HEADER_SIZE + size != data.len()
+        {
+            return None;
+        }
+        let method = (typ & 0x000f) | ((typ & 0x00e0) >> 1) | ((typ & 0x3e00) >> 2);
+        let class = MessageClass::from_bits(((typ >> 7) & 0b10) | ((typ >> 4) & 0b01));
+        let mut transaction_id = [0; 12];
+        transaction_id.copy_from_slice(&data[8..HEADER_SIZE]);
+
+        let mut attributes = Vec::new();
+        let mut rest = &data[HEADER_SIZE..];
+        while !rest.is_empty() {
+            if rest.len() < 4 {
+                return None;
+            }
+            let attribute_type = u16::from_be_bytes([rest[0], rest[1]]);
+            let value_size = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+            let value = rest.get(4..4 + value_size)?;
+            attributes.push((attribute_type, value.to_vec()));
+            rest = rest.get(4 + padded_size(value_size)..).unwrap_or_default();
+        }
+        Some(Self {
+            method,
+            class,
+            transaction_id,
+            attributes,
+        })
+    }
+
+    /// Adds MESSAGE-INTEGRITY at the end if a key is given.
+    pub fn encode(&self, integrity_key: Option<&[u8]>) -> Vec<u8> {
+        let method = self.method;
+        let class = self.class.bits();
+        let typ = (method & 0x000f)
+            | ((method & 0x0070) << 1)
+            | ((method & 0x0f80) << 2)
+            | ((class & 0b10) << 7)
+            | ((class & 0b01) << 4);
+        let mut data = Vec::with_capacity(HEADER_SIZE);
+        data.extend_from_slice(&typ.to_be_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        data.extend_from_slice(&self.transaction_id);
+        for (attribute_type, value) in &self.attributes {
+            data.extend_from_slice(&attribute_type.to_be_bytes());
+            data.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            data.extend_from_slice(value);
+            data.resize(padded_size(data.len()), 0);
+        }
+        if let Some(key) = integrity_key {
+            // The length in the header includes the MESSAGE-INTEGRITY.
+            let size = data.len() - HEADER_SIZE + 4 + MESSAGE_INTEGRITY_SIZE;
+            data[2..4].copy_from_slice(&(size as u16).to_be_bytes());
+            let hmac = hmac_sha1(key, &data);
+            data.extend_from_slice(&ATTR_MESSAGE_INTEGRITY.to_be_bytes());
+            data.extend_from_slice(&(MESSAGE_INTEGRITY_SIZE as u16).to_be_bytes());
+            data.extend_from_slice(&hmac);
+        }
+        let size = data.len() - HEADER_SIZE;
+        data[2..4].copy_from_slice(&(size as u16).to_be_bytes());
+        data
+    }
+
+    pub fn attribute(&self, attribute_type: u16) -> Option<&[u8]> {
+        self.attributes
+            .iter()
+            .find(|(typ, _)| *typ == attribute_type)
+            .map(|(_, value)| &value[..])
+    }
+
+    pub fn add_attribute(&mut self, attribute_type: u16, value: Vec<u8>) {
+        self.attributes.push((attribute_type, value));
+    }
+
+    pub fn u32_attribute(&self, attribute_type: u16) -> Option<u32> {
+        match self.attribute(attribute_type)? {
+            [a, b, c, d] => Some(u32::from_be_bytes([*a, *b, *c, *d])),
+            _ => None,
+        }
+    }
+
+    pub fn add_u32_attribute(&mut self, attribute_type: u16, value: u32) {
+        self.add_attribute(attribute_type, value.to_be_bytes().to_vec());
+    }
+
+    /// All of them, since there can be more than one XOR-PEER-ADDRESS.
+    pub fn xor_addresses(&self, attribute_type: u16) -> Vec<SocketAddr> {
+        self.attributes
+            .iter()
+            .filter(|(typ, _)| *typ == attribute_type)
+            .filter_map(|(_, value)| self.unxor_address(value))
+            .collect()
+    }
+
+    pub fn add_xor_address(&mut self, attribute_type: u16, address: SocketAddr) {
+        let port = address.port() ^ (MAGIC_COOKIE >> 16) as u16;
+        let mut value = Vec::with_capacity(20);
+        match address.ip() {
+            IpAddr::V4(ip) => {
+                value.extend_from_slice(&[0, 0x01]);
+                value.extend_from_slice(&port.to_be_bytes());
+                value.extend_from_slice(&(u32::from(ip) ^ MAGIC_COOKIE).to_be_bytes());
+            }
+            IpAddr::V6(ip) => {
+                value.extend_from_slice(&[0, 0x02]);
+                value.extend_from_slice(&port.to_be_bytes());
+                let mask = self.ipv6_mask();
+                let octets = ip.octets();
+                value.extend(octets.iter().zip(mask.iter()).map(|(a, b)| a ^ b));
+            }
+        }
+        self.add_attribute(attribute_type, value);
+    }
+
+    fn unxor_address(&self, value: &[u8]) -> Option<SocketAddr> {
+        let port =
+            u16::from_be_bytes([*value.get(2)?, *value.get(3)?]) ^ (MAGIC_COOKIE >> 16) as u16;
+        let ip = match (value.get(1)?, value.len()) {
+            (0x01, 8) => {
+                let xored = u32::from_be_bytes([value[4], value[5], value[6], value[7]]);
+                IpAddr::V4(Ipv4Addr::from(xored ^ MAGIC_COOKIE))
+            }
+            (0x02, 20) => {
+                let mask = self.ipv6_mask();
+                let mut octets = [0; 16];
+                for (octet, (a, b)) in octets.iter_mut().zip(value[4..].iter().zip(mask.iter())) {
+                    *octet = a ^ b;
+                }
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            _ => return None,
+        };
+        Some(SocketAddr::new(ip, port))
+    }
+
+    fn ipv6_mask(&self) -> [u8; 16] {
+        let mut mask = [0; 16];
+        mask[..4].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        mask[4..].copy_from_slice(&self.transaction_id);
+        mask
+    }
+
+    pub fn add_error_code(&mut self, code: u16, reason: &str) {
+        let mut value = vec![0, 0, (code / 100) as u8, (code % 100) as u8];
+        value.extend_from_slice(reason.as_bytes());
+        self.add_attribute(ATTR_ERROR_CODE, value);
+    }
+
+    pub fn error_code(&self) -> Option<u16> {
+        match self.attribute(ATTR_ERROR_CODE)? {
+            [_, _, class, number, ..] => Some((*class as u16 & 0x7) * 100 + *number as u16),
+            _ => None,
+        }
+    }
+}
+
+/// Answers Binding requests with the address they came from, so that
+/// endpoints behind NATs can gather server-reflexive candidates.
+#[derive(Clone)]
+pub struct StunServer {
+    ip: IpAddr,
+}
+
+impl StunServer {
+    pub const PORT: u16 = 3478;
+
+    /// Adds an interface for the ip to the Router, with the given links
+    /// between the server and the rest of the simulated network.  It runs
+    /// until the Router is stopped.
+    pub fn start(
+        ip: IpAddr,
+        send_config: LinkConfig,
+        receive_config: LinkConfig,
+        router: &Router,
+    ) -> Result<Self> {
+        let router_as_sender = router.clone();
+        router.add_interface(
+            ip,
+            send_config,
+            receive_config,
+            Box::new(move |packet: Packet| {
+                let request = match StunMessage::parse(&packet.data) {
+                    Some(request)
+                        if request.class == MessageClass::Request
+                            && request.method == BINDING
+                            && packet.dest.port() == Self::PORT =>
+                    {
+                        request
+                    }
+                    _ => {
+                        debug!("Dropped a packet for the STUN server that isn't a Binding request");
+                        return;
+                    }
+                };
+                let mut response = request.response(MessageClass::SuccessResponse);
+                response.add_xor_address(ATTR_XOR_MAPPED_ADDRESS, packet.source);
+                router_as_sender.send_packet(Packet {
+                    source: packet.dest,
+                    dest: packet.source,
+                    data: response.encode(None),
+                    protocol: packet.protocol,
+                });
+            }),
+        )?;
+        Ok(Self { ip })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        SocketAddr::new(self.ip, Self::PORT)
+    }
+
+    /// For an IceServer
+    pub fn url(&self) -> String {
+        format!("stun:{}", self.address())
+    }
+}
+
+/// Checks the MESSAGE-INTEGRITY of an encoded message.  Anything after it
+/// (like a FINGERPRINT) isn't covered.
+pub fn has_valid_integrity(data: &[u8], key: &[u8]) -> bool {
+    let mut offset = HEADER_SIZE;
+    while offset + 4 <= data.len() {
+        let attribute_type = u16::from_be_bytes([data[offset], data[offset + 1]]);
+        let value_size = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        if attribute_type == ATTR_MESSAGE_INTEGRITY {
+            let expected = match data.get(offset + 4..offset + 4 + MESSAGE_INTEGRITY_SIZE) {
+                Some(expected) if value_size == MESSAGE_INTEGRITY_SIZE => expected,
+                _ => return false,
+            };
+            let mut covered = data[..offset].to_vec();
+            let size = offset - HEADER_SIZE + 4 + MESSAGE_INTEGRITY_SIZE;
+            covered[2..4].copy_from_slice(&(size as u16).to_be_bytes());
+            return hmac_sha1(key, &covered)[..] == *expected;
+        }
+        offset += 4 + padded_size(value_size);
+    }
+    false
+}
+
+/// The key for MESSAGE-INTEGRITY with long-term credentials (RFC 5389 section 15.4).
+pub fn long_term_key(username: &str, realm: &str, password: &str) -> [u8; 16] {
+    Md5::digest(format!("{}:{}:{}", username, realm, password).as_bytes()).into()
+}
+
+pub fn hmac_sha1(key: &[u8], data: &[u8]) -> [u8; 20] {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC can take a key of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_integrity_hmac() {
+        // RFC 2202
+        assert_eq!(
+            "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79",
+            hex::encode(hmac_sha1(b"Jefe", b"what do ya want for nothing?"))
+        );
+        assert_eq!(
+            "aa4ae5e15272d00e95705637ce8a3b55ed402112",
+            hex::encode(hmac_sha1(
+                &[0xaa; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ))
+        );
+    }
+
+    #[test]
+    fn messages() {
+        let transaction_id = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+        let mut message = StunMessage::new(ALLOCATE, MessageClass::SuccessResponse, transaction_id);
+        let v4: SocketAddr = "203.0.113.5:3478".parse().unwrap();
+        let v6: SocketAddr = "[2001:db8::1]:50000".parse().unwrap();
+        message.add_xor_address(ATTR_XOR_RELAYED_ADDRESS, v4);
+        message.add_xor_address(ATTR_XOR_PEER_ADDRESS, v4);
+        message.add_xor_address(ATTR_XOR_PEER_ADDRESS, v6);
+        message.add_u32_attribute(ATTR_LIFETIME, 600);
+        message.add_attribute(ATTR_REALM, b"realm".to_vec());
+
+        let key = long_term_key("user", "realm", "pass");
+        let data = message.encode(Some(&key));
+        assert_eq!(0, data.len() % 4);
+        // Allocate success response
+        assert_eq!([0x01, 0x03], data[..2]);
+        assert!(has_valid_integrity(&data, &key));
+        assert!(!has_valid_integrity(
+            &data,
+            &long_term_key("user", "realm", "wrong")
+        ));
+        assert!(!has_valid_integrity(&message.encode(None), &key));
+
+        let parsed = StunMessage::parse(&data).unwrap();
+        assert_eq!(ALLOCATE, parsed.method);
+        assert_eq!(MessageClass::SuccessResponse, parsed.class);
+        assert_eq!(transaction_id, parsed.transaction_id);
+        assert_eq!(vec![v4], parsed.xor_addresses(ATTR_XOR_RELAYED_ADDRESS));
+        assert_eq!(vec![v4, v6], parsed.xor_addresses(ATTR_XOR_PEER_ADDRESS));
+        assert_eq!(Some(600), parsed.u32_attribute(ATTR_LIFETIME));
+        assert_eq!(Some(&b"realm"[..]), parsed.attribute(ATTR_REALM));
+        assert!(parsed.attribute(ATTR_MESSAGE_INTEGRITY).is_some());
+
+        let mut error = parsed.response(MessageClass::ErrorResponse);
+        error.add_error_code(401, "Unauthorized");
+        let error = StunMessage::parse(&error.encode(None)).unwrap();
+        assert_eq!(MessageClass::ErrorResponse, error.class);
+        assert_eq!(Some(401), error.error_code());
+
+        let mut send = StunMessage::new(SEND, MessageClass::Indication, transaction_id);
+        send.add_attribute(ATTR_DATA, vec![1, 2, 3]);
+        let data = send.encode(None);
+        // Send indication
+        assert_eq!([0x00, 0x16], data[..2]);
+        assert_eq!(send, StunMessage::parse(&data).unwrap());
+
+        // RTP, ChannelData and truncated messages aren't STUN.
+        assert_eq!(None, StunMessage::parse(&[0x80; 40]));
+        assert_eq!(
+            None,
+            StunMessage::parse(&[0x40, 0x00, 0x00, 0x04, 1, 2, 3, 4])
+        );
+        assert_eq!(None, StunMessage::parse(&data[..data.len() - 1]));
+    }
+}
@@ -0,0 +1,682 @@
+//
+// Copyright 2022 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! A simulated TURN server (RFC 5766) connected to a Router, so that relayed
+//! calls (and falling back to them) can be tested deterministically.
+//!
+//! Clients can use UDP or TCP (see router::Protocol) to reach the server, and
+//! it relays to peers over UDP.  It uses long-term credentials with a fixed
+//! nonce, and supports permissions and channels.  Allocations expire, but
+//! permissions and channels last as long as the allocation.
+
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    common::{
+        actor::{Actor, Stopper},
+        Result,
+    },
+    simnet::{
+        router::{LinkConfig, Packet, Protocol, Router},
+        stun::{self, MessageClass, StunMessage},
+    },
+};
+
+const REALM: &str = "simnet";
+const NONCE: &str = "simnet-nonce";
+const DEFAULT_LIFETIME: Duration = Duration::from_secs(600);
+const MAX_LIFETIME: Duration = Duration::from_secs(3600);
+// Where relayed ports start (the start of the dynamic port range).
+const FIRST_RELAYED_PORT: u16 = 49152;
+const IP_PROTOCOL_UDP: u8 = 17;
+const CHANNEL_NUMBERS: std::ops::RangeInclusive<u16> = 0x4000..=0x7fff;
+const CHANNEL_DATA_HEADER_SIZE: usize = 4;
+
+/// Runs until the Stopper passed to start is stopped.
+#[derive(Clone)]
+pub struct TurnServer {
+    ip: IpAddr,
+}
+
+impl TurnServer {
+    pub const PORT: u16 = 3478;
+    pub const USERNAME: &'static str = "simnet";
+    pub const PASSWORD: &'static str = "simnet-password";
+
+    /// Adds an interface for the ip to the Router, with the given links
+    /// between the server and the rest of the simulated network.
+    pub fn start(
+        ip: IpAddr,
+        send_config: LinkConfig,
+        receive_config: LinkConfig,
+        router: &Router,
+        stopper: &Stopper,
+    ) -> Result<Self> {
+        let router_for_actor = router.clone();
+        let actor = Actor::start(stopper.clone(), move |_| {
+            Ok(TurnServerState {
+                ip,
+                router: router_for_actor,
+                key: stun::long_term_key(Self::USERNAME, REALM, Self::PASSWORD),
+                allocations: HashMap::new(),
+                client_by_relayed_port: HashMap::new(),
+                next_relayed_port: FIRST_RELAYED_PORT,
+            })
+        })?;
+        let actor_as_receiver = actor.clone();
+        router.add_interface(
+            ip,
+            send_config,
+            receive_config,
+            Box::new(move |packet: Packet| {
+                actor_as_receiver.send(move |state| state.receive_packet(packet));
+            }),
+        )?;
+        Ok(Self { ip })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        SocketAddr::new(self.ip, Self::PORT)
+    }
+
+    /// For an IceServer, along with USERNAME and PASSWORD.
+    pub fn urls(&self, protocols: &[Protocol]) -> Vec<String> {
+        protocols
+            .iter()
+            .map(|protocol| {
+                let transport = match protocol {
+                    Protocol::Udp => "udp",
+                    Protocol::Tcp => "tcp",
+                };
+                format!("turn:{}?transport={}", self.address(), transport)
+            })
+            .collect()
+    }
+}
+
+// Clients are identified by their address and the protocol they use to
+// reach the server (the "5-tuple" of RFC 5766).
+type Client = (SocketAddr, Protocol);
+
+struct Allocation {
+    relayed_address: SocketAddr,
+    // Of the Allocate request, so that a retransmission gets the same response.
+    transaction_id: [u8; 12],
+    expires: Instant,
+    permissions: HashSet<IpAddr>,
+    channel_by_peer: HashMap<SocketAddr, u16>,
+    peer_by_channel: HashMap<u16, SocketAddr>,
+}
+
+struct TurnServerState {
+    ip: IpAddr,
+    router: Router,
+    // For MESSAGE-INTEGRITY
+    key: [u8; 16],
+    allocations: HashMap<Client, Allocation>,
+    client_by_relayed_port: HashMap<u16, Client>,
+    next_relayed_port: u16,
+}
+
+// An error response code and reason
+type TurnError = (u16, &'static str);
+
+const BAD_REQUEST: TurnError = (400, "Bad Request");
+const ALLOCATION_MISMATCH: TurnError = (437, "Allocation Mismatch");
+const UNSUPPORTED_TRANSPORT_PROTOCOL: TurnError = (442, "Unsupported Transport Protocol");
+
+fn lifetime(request: &StunMessage) -> Duration {
+    request
+        .u32_attribute(stun::ATTR_LIFETIME)
+        .map(|secs| Duration::from_secs(secs as u64).min(MAX_LIFETIME))
+        .unwrap_or(DEFAULT_LIFETIME)
+}
+
+impl TurnServerState {
+    fn receive_packet(&mut self, packet: Packet) {
+        if packet.dest == SocketAddr::new(self.ip, TurnServer::PORT) {
+            self.receive_from_client(packet);
+        } else {
+            self.receive_from_peer(packet);
+        }
+    }
+
+    fn send(&self, source: SocketAddr, dest: SocketAddr, data: Vec<u8>, protocol: Protocol) {
+        self.router.send_packet(Packet {
+            source,
+            dest,
+            data,
+            protocol,
+        });
+    }
+
+    fn send_to_client(&self, client: Client, data: Vec<u8>) {
+        let (address, protocol) = client;
+        self.send(
+            SocketAddr::new(self.ip, TurnServer::PORT),
+            address,
+            data,
+            protocol,
+        );
+    }
+
+    fn relay_to_peer(&mut self, client: Client, peer: SocketAddr, data: Vec<u8>) {
+        match self.allocation(client) {
+            Some(allocation) if allocation.permissions.contains(&peer.ip()) => {
+                let relayed_address = allocation.relayed_address;
+                self.send(relayed_address, peer, data, Protocol::Udp);
+            }
+            _ => debug!(
+                "Dropped packet from {:?} to {:?} without an allocation and permission",
+                client, peer
+            ),
+        }
+    }
+
+    // Expired allocations are removed when they are looked up.
+    fn allocation(&mut self, client: Client) -> Option<&mut Allocation> {
+        if matches!(self.allocations.get(&client), Some(allocation) if allocation.expires <= Instant::now())
+        {
+            self.remove_allocation(client);
+        }
+        self.allocations.get_mut(&client)
+    }
+
+    fn remove_allocation(&mut self, client: Client) {
+        if let Some(allocation) = self.allocations.remove(&client) {
+            self.client_by_relayed_port
+                .remove(&allocation.relayed_address.port());
+        }
+    }
+
+    fn receive_from_client(&mut self, packet: Packet) {
+        let client = (packet.source, packet.protocol);
+        match packet.data.first() {
+            Some(0x40..=0x7f) => {
+                // ChannelData.  Over TCP, each packet is a whole message
+                // (see router::Protocol), so there's no padding.
+                if packet.data.len() < CHANNEL_DATA_HEADER_SIZE {
+                    return;
+                }
+                let channel = u16::from_be_bytes([packet.data[0], packet.data[1]]);
+                let size = u16::from_be_bytes([packet.data[2], packet.data[3]]) as usize;
+                let data = match packet
+                    .data
+                    .get(CHANNEL_DATA_HEADER_SIZE..CHANNEL_DATA_HEADER_SIZE + size)
+                {
+                    Some(data) => data.to_vec(),
+                    None => return,
+                };
+                let peer = self
+                    .allocation(client)
+                    .and_then(|allocation| allocation.peer_by_channel.get(&channel).copied());
+                match peer {
+                    Some(peer) => self.relay_to_peer(client, peer, data),
+                    None => debug!("Dropped ChannelData for unbound channel {:#x}", channel),
+                }
+            }
+            _ => match StunMessage::parse(&packet.data) {
+                Some(message) => self.receive_stun(client, &packet.data, message),
+                None => debug!("Dropped a packet from {:?} that isn't STUN", client),
+            },
+        }
+    }
+
+    fn receive_stun(&mut self, client: Client, data: &[u8], message: StunMessage) {
+        match (message.class, message.method) {
+            (MessageClass::Request, stun::BINDING) => {
+                let mut response = message.response(MessageClass::SuccessResponse);
+                response.add_xor_address(stun::ATTR_XOR_MAPPED_ADDRESS, client.0);
+                self.send_to_client(client, response.encode(None));
+            }
+            (MessageClass::Indication, stun::SEND) => {
+                let peer = message
+                    .xor_addresses(stun::ATTR_XOR_PEER_ADDRESS)
+                    .first()
+                    .copied();
+                match (peer, message.attribute(stun::ATTR_DATA)) {
+                    (Some(peer), Some(data)) => self.relay_to_peer(client, peer, data.to_vec()),
+                    _ => debug!("Dropped a Send indication without a peer or data"),
+                }
+            }
+            (
+                MessageClass::Request,
+                stun::ALLOCATE | stun::REFRESH | stun::CREATE_PERMISSION | stun::CHANNEL_BIND,
+            ) => {
+                if !self.is_authenticated(data, &message) {
+                    let mut response = message.response(MessageClass::ErrorResponse);
+                    response.add_error_code(401, "Unauthorized");
+                    response.add_attribute(stun::ATTR_REALM, REALM.as_bytes().to_vec());
+                    response.add_attribute(stun::ATTR_NONCE, NONCE.as_bytes().to_vec());
+                    self.send_to_client(client, response.encode(None));
+                    return;
+                }
+                let response = match message.method {
+                    stun::ALLOCATE => self.allocate(client, &message),
+                    stun::REFRESH => self.refresh(client, &message),
+                    stun::CREATE_PERMISSION => self.create_permission(client, &message),
+                    _ => self.channel_bind(client, &message),
+                };
+                let response = response.unwrap_or_else(|(code, reason)| {
+                    debug!(
+                        "TURN request (method {:#x}) from {:?} failed: {} {}",
+                        message.method, client, code, reason
+                    );
+                    let mut response = message.response(MessageClass::ErrorResponse);
+                    response.add_error_code(code, reason);
+                    response
+                });
+                let key = self.key;
+                self.send_to_client(client, response.encode(Some(&key)));
+            }
+            _ => debug!(
+                "Dropped unexpected STUN message (method {:#x}) from {:?}",
+                message.method, client
+            ),
+        }
+    }
+
+    fn is_authenticated(&self, data: &[u8], request: &StunMessage) -> bool {
+        request.attribute(stun::ATTR_USERNAME) == Some(TurnServer::USERNAME.as_bytes())
+            && request.attribute(stun::ATTR_REALM) == Some(REALM.as_bytes())
+            && request.attribute(stun::ATTR_NONCE) == Some(NONCE.as_bytes())
+            && stun::has_valid_integrity(data, &self.key)
+    }
+
+    fn allocate(
+        &mut self,
+        client: Client,
+        request: &StunMessage,
+    ) -> std::result::Result<StunMessage, TurnError> {
+        let relayed_address = match self.allocation(client) {
+            Some(allocation) if allocation.transaction_id == request.transaction_id => {
+                allocation.relayed_address
+            }
+            Some(_) => return Err(ALLOCATION_MISMATCH),
+            None => {
+                match request.attribute(stun::ATTR_REQUESTED_TRANSPORT) {
+                    Some([IP_PROTOCOL_UDP, ..]) => {}
+                    Some(_) => return Err(UNSUPPORTED_TRANSPORT_PROTOCOL),
+                    None => return Err(BAD_REQUEST),
+                }
+                let relayed_address = SocketAddr::new(self.ip, self.next_relayed_port);
+                self.next_relayed_port += 1;
+                self.allocations.insert(
+                    client,
+                    Allocation {
+                        relayed_address,
+                        transaction_id: request.transaction_id,
+                        expires: Instant::now() + lifetime(request),
+                        permissions: HashSet::new(),
+                        channel_by_peer: HashMap::new(),
+                        peer_by_channel: HashMap::new(),
+                    },
+                );
+                self.client_by_relayed_port
+                    .insert(relayed_address.port(), client);
+                debug!(
+                    "Allocated relayed address {:?} for {:?}",
+                    relayed_address, client
+                );
+                relayed_address
+            }
+        };
+        let mut response = request.response(MessageClass::SuccessResponse);
+        response.add_xor_address(stun::ATTR_XOR_RELAYED_ADDRESS, relayed_address);
+        response.add_u32_attribute(stun::ATTR_LIFETIME, lifetime(request).as_secs() as u32);
+        response.add_xor_address(stun::ATTR_XOR_MAPPED_ADDRESS, client.0);
+        Ok(response)
+    }
+
+    fn refresh(
+        &mut self,
+        client: Client,
+        request: &StunMessage,
+    ) -> std::result::Result<StunMessage, TurnError> {
+        let lifetime = lifetime(request);
+        let allocation = self.allocation(client).ok_or(ALLOCATION_MISMATCH)?;
+        if lifetime == Duration::ZERO {
+            self.remove_allocation(client);
+        } else {
+            allocation.expires = Instant::now() + lifetime;
+        }
+        let mut response = request.response(MessageClass::SuccessResponse);
+        response.add_u32_attribute(stun::ATTR_LIFETIME, lifetime.as_secs() as u32);
+        Ok(response)
+    }
+
+    fn create_permission(
+        &mut self,
+        client: Client,
+        request: &StunMessage,
+    ) -> std::result::Result<StunMessage, TurnError> {
+        let peers = request.xor_addresses(stun::ATTR_XOR_PEER_ADDRESS);
+        let allocation = self.allocation(client).ok_or(ALLOCATION_MISMATCH)?;
+        if peers.is_empty() {
+            return Err(BAD_REQUEST);
+        }
+        allocation
+            .permissions
+            .extend(peers.iter().map(|peer| peer.ip()));
+        Ok(request.response(MessageClass::SuccessResponse))
+    }
+
+    fn channel_bind(
+        &mut self,
+        client: Client,
+        request: &StunMessage,
+    ) -> std::result::Result<StunMessage, TurnError> {
+        let channel = request
+            .u32_attribute(stun::ATTR_CHANNEL_NUMBER)
+            .map(|value| (value >> 16) as u16);
+        let peer = request
+            .xor_addresses(stun::ATTR_XOR_PEER_ADDRESS)
+            .first()
+            .copied();
+        let allocation = self.allocation(client).ok_or(ALLOCATION_MISMATCH)?;
+        let (channel, peer) = match (channel, peer) {
+            (Some(channel), Some(peer)) if CHANNEL_NUMBERS.contains(&channel) => (channel, peer),
+            _ => return Err(BAD_REQUEST),
+        };
+        // A channel can be refreshed, but not moved to another peer (or vice versa).
+        let bound_peer = allocation.peer_by_channel.get(&channel);
+        let bound_channel = allocation.channel_by_peer.get(&peer);
+        if matches!(bound_peer, Some(bound_peer) if *bound_peer != peer)
+            || matches!(bound_channel, Some(bound_channel) if *bound_channel != channel)
+        {
+            return Err(BAD_REQUEST);
+        }
+        allocation.peer_by_channel.insert(channel, peer);
+        allocation.channel_by_peer.insert(peer, channel);
+        allocation.permissions.insert(peer.ip());
+        Ok(request.response(MessageClass::SuccessResponse))
+    }
+
+    fn receive_from_peer(&mut self, packet: Packet) {
+        let client = match self.client_by_relayed_port.get(&packet.dest.port()) {
+            Some(client) if packet.protocol == Protocol::Udp => *client,
+            _ => {
+                debug!("Dropped a packet for unknown address {:?}", packet.dest);
+                return;
+            }
+        };
+        let channel = match self.allocation(client) {
+            Some(allocation) if allocation.permissions.contains(&packet.source.ip()) => {
+                allocation.channel_by_peer.get(&packet.source).copied()
+            }
+            _ => {
+                debug!(
+                    "Dropped a packet from {:?} without an allocation and permission",
+                    packet.source
+                );
+                return;
+            }
+        };
+        let data = match channel {
+            Some(channel) => {
+                let mut data = Vec::with_capacity(CHANNEL_DATA_HEADER_SIZE + packet.data.len());
+                data.extend_from_slice(&channel.to_be_bytes());
+                data.extend_from_slice(&(packet.data.len() as u16).to_be_bytes());
+                data.extend_from_slice(&packet.data);
+                data
+            }
+            None => {
+                let mut indication =
+                    StunMessage::new(stun::DATA, MessageClass::Indication, rand::random());
+                indication.add_xor_address(stun::ATTR_XOR_PEER_ADDRESS, packet.source);
+                indication.add_attribute(stun::ATTR_DATA, packet.data);
+                indication.encode(None)
+            }
+        };
+        self.send_to_client(client, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    struct TestEndpoint {
+        address: SocketAddr,
+        protocol: Protocol,
+        router: Router,
+        received: mpsc::Receiver<Packet>,
+    }
+
+    impl TestEndpoint {
+        fn start(address: &str, protocol: Protocol, router: &Router) -> Self {
+            let address: SocketAddr = address.parse().unwrap();
+            let link = LinkConfig::profile("ideal").unwrap();
+            let (sender, received) = mpsc::channel();
+            router
+                .add_interface(
+                    address.ip(),
+                    link.clone(),
+                    link,
+                    Box::new(move |packet: Packet| {
+                        let _ = sender.send(packet);
+                    }),
+                )
+                .unwrap();
+            Self {
+                address,
+                protocol,
+                router: router.clone(),
+                received,
+            }
+        }
+
+        fn send(&self, dest: SocketAddr, data: Vec<u8>) {
+            self.router.send_packet(Packet {
+                source: self.address,
+                dest,
+                data,
+                protocol: self.protocol,
+            });
+        }
+
+        fn receive(&self) -> Packet {
+            self.received.recv_timeout(TIMEOUT).unwrap()
+        }
+
+        fn request(
+            &self,
+            server: SocketAddr,
+            request: StunMessage,
+            key: Option<&[u8]>,
+        ) -> StunMessage {
+            self.send(server, request.encode(key));
+            let packet = self.receive();
+            assert_eq!(server, packet.source);
+            assert_eq!(self.protocol, packet.protocol);
+            let response = StunMessage::parse(&packet.data).unwrap();
+            assert_eq!(request.transaction_id, response.transaction_id);
+            response
+        }
+    }
+
+    fn authenticated(method: u16, transaction_id: u8) -> StunMessage {
+        let mut request = StunMessage::new(method, MessageClass::Request, [transaction_id; 12]);
+        request.add_attribute(
+            stun::ATTR_USERNAME,
+            TurnServer::USERNAME.as_bytes().to_vec(),
+        );
+        request.add_attribute(stun::ATTR_REALM, REALM.as_bytes().to_vec());
+        request.add_attribute(stun::ATTR_NONCE, NONCE.as_bytes().to_vec());
+        request
+    }
+
+    fn relay(protocol: Protocol) {
+        let stopper = Stopper::new();
+        let router = Router::start(&stopper).unwrap();
+        let link = LinkConfig::profile("ideal").unwrap();
+        let server = TurnServer::start(
+            "3.0.0.1".parse().unwrap(),
+            link.clone(),
+            link,
+            &router,
+            &stopper,
+        )
+        .unwrap();
+        let server_address = server.address();
+        let client = TestEndpoint::start("1.1.0.1:2001", protocol, &router);
+        let peer = TestEndpoint::start("2.1.0.1:2001", Protocol::Udp, &router);
+        let key = stun::long_term_key(TurnServer::USERNAME, REALM, TurnServer::PASSWORD);
+
+        // Without credentials
+        let mut allocate = StunMessage::new(stun::ALLOCATE, MessageClass::Request, [1; 12]);
+        allocate.add_attribute(
+            stun::ATTR_REQUESTED_TRANSPORT,
+            vec![IP_PROTOCOL_UDP, 0, 0, 0],
+        );
+        let response = client.request(server_address, allocate, None);
+        assert_eq!(MessageClass::ErrorResponse, response.class);
+        assert_eq!(Some(401), response.error_code());
+        assert_eq!(Some(NONCE.as_bytes()), response.attribute(stun::ATTR_NONCE));
+
+        // With the wrong password
+        let mut allocate = authenticated(stun::ALLOCATE, 2);
+        allocate.add_attribute(
+            stun::ATTR_REQUESTED_TRANSPORT,
+            vec![IP_PROTOCOL_UDP, 0, 0, 0],
+        );
+        let wrong_key = stun::long_term_key(TurnServer::USERNAME, REALM, "wrong");
+        let response = client.request(server_address, allocate.clone(), Some(&wrong_key));
+        assert_eq!(Some(401), response.error_code());
+
+        let send_response = |request: StunMessage| {
+            client.send(server_address, request.encode(Some(&key)));
+            let packet = client.receive();
+            assert!(stun::has_valid_integrity(&packet.data, &key));
+            StunMessage::parse(&packet.data).unwrap()
+        };
+        let response = send_response(allocate.clone());
+        assert_eq!(MessageClass::SuccessResponse, response.class);
+        let relayed_address = response.xor_addresses(stun::ATTR_XOR_RELAYED_ADDRESS)[0];
+        assert_eq!(server_address.ip(), relayed_address.ip());
+        assert_eq!(
+            vec![client.address],
+            response.xor_addresses(stun::ATTR_XOR_MAPPED_ADDRESS)
+        );
+        // A retransmission gets the same allocation, but a new request doesn't.
+        assert_eq!(
+            vec![relayed_address],
+            send_response(allocate).xor_addresses(stun::ATTR_XOR_RELAYED_ADDRESS)
+        );
+        let mut allocate_again = authenticated(stun::ALLOCATE, 3);
+        allocate_again.add_attribute(
+            stun::ATTR_REQUESTED_TRANSPORT,
+            vec![IP_PROTOCOL_UDP, 0, 0, 0],
+        );
+        assert_eq!(Some(437), send_response(allocate_again).error_code());
+
+        // Nothing is relayed without a permission.
+        peer.send(relayed_address, vec![1]);
+        assert!(client
+            .received
+            .recv_timeout(Duration::from_millis(100))
+            .is_err());
+
+        let mut create_permission = authenticated(stun::CREATE_PERMISSION, 4);
+        create_permission.add_xor_address(stun::ATTR_XOR_PEER_ADDRESS, peer.address);
+        assert_eq!(
+            MessageClass::SuccessResponse,
+            send_response(create_permission).class
+        );
+
+        let mut send = StunMessage::new(stun::SEND, MessageClass::Indication, [5; 12]);
+        send.add_xor_address(stun::ATTR_XOR_PEER_ADDRESS, peer.address);
+        send.add_attribute(stun::ATTR_DATA, vec![2, 3]);
+        client.send(server_address, send.encode(None));
+        let packet = peer.receive();
+        assert_eq!(relayed_address, packet.source);
+        assert_eq!(vec![2, 3], packet.data);
+
+        peer.send(relayed_address, vec![4, 5]);
+        let packet = client.receive();
+        assert_eq!(protocol, packet.protocol);
+        let data_indication = StunMessage::parse(&packet.data).unwrap();
+        assert_eq!(stun::DATA, data_indication.method);
+        assert_eq!(
+            vec![peer.address],
+            data_indication.xor_addresses(stun::ATTR_XOR_PEER_ADDRESS)
+        );
+        assert_eq!(
+            Some(&[4, 5][..]),
+            data_indication.attribute(stun::ATTR_DATA)
+        );
+
+        let mut channel_bind = authenticated(stun::CHANNEL_BIND, 6);
+        channel_bind.add_u32_attribute(stun::ATTR_CHANNEL_NUMBER, 0x4001 << 16);
+        channel_bind.add_xor_address(stun::ATTR_XOR_PEER_ADDRESS, peer.address);
+        assert_eq!(
+            MessageClass::SuccessResponse,
+            send_response(channel_bind).class
+        );
+
+        client.send(server_address, vec![0x40, 0x01, 0x00, 0x02, 6, 7]);
+        let packet = peer.receive();
+        assert_eq!(relayed_address, packet.source);
+        assert_eq!(vec![6, 7], packet.data);
+
+        peer.send(relayed_address, vec![8, 9, 10]);
+        assert_eq!(
+            vec![0x40, 0x01, 0x00, 0x03, 8, 9, 10],
+            client.receive().data
+        );
+
+        // Deleting the allocation stops relaying.
+        let mut refresh = authenticated(stun::REFRESH, 7);
+        refresh.add_u32_attribute(stun::ATTR_LIFETIME, 0);
+        assert_eq!(MessageClass::SuccessResponse, send_response(refresh).class);
+        peer.send(relayed_address, vec![11]);
+        assert!(client
+            .received
+            .recv_timeout(Duration::from_millis(100))
+            .is_err());
+
+        stopper.stop_all_and_join();
+    }
+
+    #[test]
+    fn relay_udp() {
+        relay(Protocol::Udp);
+    }
+
+    #[test]
+    fn relay_tcp() {
+        relay(Protocol::Tcp);
+    }
+
+    #[test]
+    fn urls() {
+        let stopper = Stopper::new();
+        let router = Router::start(&stopper).unwrap();
+        let link = LinkConfig::profile("ideal").unwrap();
+        let server = TurnServer::start(
+            "3.0.0.1".parse().unwrap(),
+            link.clone(),
+            link,
+            &router,
+            &stopper,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                "turn:3.0.0.1:3478?transport=udp".to_string(),
+                "turn:3.0.0.1:3478?transport=tcp".to_string()
+            ],
+            server.urls(&[Protocol::Udp, Protocol::Tcp])
+        );
+        stopper.stop_all_and_join();
+    }
+}
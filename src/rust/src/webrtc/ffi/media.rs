@@ -93,6 +93,10 @@ extern "C" {
         buffer: webrtc::ptr::BorrowedRc<RffiVideoFrameBuffer>,
         rgba_out: *mut u8,
     );
+    pub fn Rust_convertVideoFrameBufferToI420(
+        buffer: webrtc::ptr::BorrowedRc<RffiVideoFrameBuffer>,
+        i420_out: *mut u8,
+    );
     pub fn Rust_copyAndRotateVideoFrameBuffer(
         buffer: webrtc::ptr::BorrowedRc<RffiVideoFrameBuffer>,
         rotation: VideoRotation,
@@ -9,7 +9,7 @@ use std::os::raw::c_char;
 
 use crate::webrtc;
 use crate::webrtc::ffi::ice_gatherer::RffiIceGatherer;
-use crate::webrtc::media::RffiAudioEncoderConfig;
+use crate::webrtc::media::{RffiAudioEncoderConfig, RffiVideoEncodingLayer};
 use crate::webrtc::network::{RffiIp, RffiIpPort};
 use crate::webrtc::peer_connection::{RffiAudioLevel, RffiReceivedAudioLevel};
 use crate::webrtc::rtp;
@@ -61,6 +61,33 @@ extern "C" {
         enabled: bool,
     ) -> bool;
 
+    // Stops (or resumes) the outgoing video encoder without removing the track.
+    pub fn Rust_setOutgoingVideoEnabled(
+        peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
+        enabled: bool,
+    );
+
+    // Disables (or re-enables) every incoming video track.  Tracks added by
+    // a later remote description start out enabled.
+    pub fn Rust_setIncomingVideoEnabled(
+        peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
+        enabled: bool,
+    );
+
+    // While enabled, decoded audio from every remote audio track is passed
+    // to the observer's onAudioFrame (in addition to being played out).
+    pub fn Rust_setIncomingAudioFramesEnabled(
+        peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
+        enabled: bool,
+    );
+
+    // Overrides the enable_video_frame_content the observer was created with.
+    // While disabled, the observer's onVideoFrame gets no frame buffer.
+    pub fn Rust_setIncomingVideoFrameContentEnabled(
+        peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
+        enabled: bool,
+    );
+
     pub fn Rust_setAudioPlayoutEnabled(
         peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
         enabled: bool,
@@ -139,5 +166,21 @@ extern "C" {
         received_size_out: webrtc::ptr::Borrowed<usize>,
     );
 
+    // Returns false if there is no audio receiver for the demux ID.
+    pub fn Rust_setRemoteAudioVolume(
+        peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
+        demux_id: u32,
+        volume: f64,
+    ) -> bool;
+
+    // An empty list of layers goes back to the default encodings.
+    // Returns false if the layers couldn't be applied to the outgoing video sender.
+    pub fn Rust_setVideoEncodingLayers(
+        peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
+        svc: bool,
+        layers_data: webrtc::ptr::Borrowed<RffiVideoEncodingLayer>,
+        layers_len: usize,
+    ) -> bool;
+
     pub fn Rust_closePeerConnection(peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>);
 }
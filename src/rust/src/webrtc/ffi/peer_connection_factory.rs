@@ -10,6 +10,7 @@ use crate::webrtc::ffi::peer_connection_observer::RffiPeerConnectionObserver;
 #[cfg(feature = "simnet")]
 use crate::webrtc::injectable_network::RffiInjectableNetwork;
 use crate::webrtc::peer_connection_factory::RffiIceServer;
+use libc::size_t;
 #[cfg(feature = "native")]
 use std::os::raw::c_char;
 
@@ -45,6 +46,7 @@ extern "C" {
     pub fn Rust_createPeerConnectionFactory(
         use_new_audio_device_module: bool,
         use_injectable_network: bool,
+        use_injectable_audio: bool,
     ) -> webrtc::ptr::OwnedRc<RffiPeerConnectionFactoryOwner>;
     pub fn Rust_createPeerConnectionFactoryWrapper(
         factory: webrtc::ptr::BorrowedRc<RffiPeerConnectionFactoryInterface>,
@@ -66,7 +68,19 @@ extern "C" {
     pub fn Rust_createAudioTrack(
         factory: webrtc::ptr::BorrowedRc<RffiPeerConnectionFactoryOwner>,
     ) -> webrtc::ptr::OwnedRc<RffiAudioTrack>;
+    pub fn Rust_pushOutgoingAudioSamples(
+        factory: webrtc::ptr::BorrowedRc<RffiPeerConnectionFactoryOwner>,
+        sample_rate: u32,
+        channels: size_t,
+        samples: webrtc::ptr::Borrowed<i16>,
+        samples_size: size_t,
+    );
     pub fn Rust_createVideoSource() -> webrtc::ptr::OwnedRc<RffiVideoSource>;
+    pub fn Rust_createProcessedVideoSource(
+        input_track: webrtc::ptr::BorrowedRc<RffiVideoTrack>,
+        processor: webrtc::ptr::Owned<std::ffi::c_void>,
+        callbacks: webrtc::ptr::Borrowed<std::ffi::c_void>,
+    ) -> webrtc::ptr::OwnedRc<RffiVideoSource>;
     pub fn Rust_createVideoTrack(
         factory: webrtc::ptr::BorrowedRc<RffiPeerConnectionFactoryOwner>,
         source: webrtc::ptr::BorrowedRc<RffiVideoSource>,
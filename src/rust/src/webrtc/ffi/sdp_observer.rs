@@ -100,6 +100,7 @@ extern "C" {
         server_srtp_key: RffiSrtpKey,
         demux_ids_data: webrtc::ptr::Borrowed<u32>,
         demux_ids_len: size_t,
+        receive_video: bool,
     ) -> webrtc::ptr::Owned<RffiSessionDescription>;
 
     pub fn Rust_deleteSessionDescription(sdi: webrtc::ptr::Owned<RffiSessionDescription>);
@@ -7,7 +7,9 @@ use std::ffi::CString;
 use std::fmt::Debug;
 use std::net::{IpAddr, SocketAddr};
 use std::os::raw::c_char;
+use std::sync::{Arc, Mutex};
 
+use crate::simnet::{pcap::PacketCapture, router::Protocol};
 use crate::webrtc;
 use crate::webrtc::network::{NetworkInterfaceType, RffiIp, RffiIpPort};
 use crate::webrtc::peer_connection_factory::RffiPeerConnectionFactoryOwner;
@@ -17,14 +19,15 @@ pub struct Packet {
     pub source: SocketAddr,
     pub dest: SocketAddr,
     pub data: Vec<u8>,
+    pub protocol: Protocol,
 }
 
 pub trait PacketSender {
-    fn send_udp(&self, packet: Packet);
+    fn send_packet(&self, packet: Packet);
 }
 
 impl<F: Fn(Packet)> PacketSender for F {
-    fn send_udp(&self, packet: Packet) {
+    fn send_packet(&self, packet: Packet) {
         self(packet)
     }
 }
@@ -35,6 +38,8 @@ pub struct InjectableNetwork {
     // We keep this around as an easy way to make sure the PeerConnectionFactory
     // outlives the InjectableNetwork.
     _owner: webrtc::Arc<RffiPeerConnectionFactoryOwner>,
+    // Shared with the sender so that it can be changed after the sender is set.
+    capture: Arc<Mutex<Option<PacketCapture>>>,
 }
 
 impl InjectableNetwork {
@@ -45,10 +50,37 @@ impl InjectableNetwork {
         Self {
             rffi,
             _owner: owner,
+            capture: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Packets sent and received through this InjectableNetwork (and its clones)
+    /// will be captured.
+    pub fn set_capture(&self, capture: Option<PacketCapture>) {
+        match self.capture.lock() {
+            Ok(mut current) => *current = capture,
+            Err(_) => warn!("Failed to set the injectable network packet capture"),
+        }
+    }
+
+    // The pcap captures only have UDP headers.
+    fn capture_packet(capture: &Mutex<Option<PacketCapture>>, packet: &Packet) {
+        if packet.protocol != Protocol::Udp {
+            return;
+        }
+        if let Ok(capture) = capture.lock() {
+            if let Some(capture) = capture.as_ref() {
+                capture.capture(packet.source, packet.dest, &packet.data);
+            }
         }
     }
 
     pub fn set_sender(&self, sender: Box<dyn PacketSender>) {
+        let capture = self.capture.clone();
+        let sender: Box<dyn PacketSender> = Box::new(move |packet: Packet| {
+            Self::capture_packet(&capture, &packet);
+            sender.send_packet(packet);
+        });
         let sender_ptr = &RffiInjectableNetworkSender {
             // Yes, it's a box in a box.  That's because we need
             // a non-dyn pointer to then point to the dyn pointer
@@ -56,6 +88,7 @@ impl InjectableNetwork {
             // are fat (double size) pointers.
             object: unsafe { webrtc::ptr::Owned::from_ptr(Box::into_raw(Box::new(sender))) },
             send_udp: Rust_InjectableNetworkSender_SendUdp,
+            send_tcp: Rust_InjectableNetworkSender_SendTcp,
             delete: Rust_InjectableNetworkSender_Delete,
         };
         unsafe {
@@ -95,10 +128,16 @@ impl InjectableNetwork {
         }
     }
 
-    pub fn receive_udp(&self, packet: Packet) {
+    pub fn receive_packet(&self, packet: Packet) {
+        Self::capture_packet(&self.capture, &packet);
+        let receive = match packet.protocol {
+            Protocol::Udp => Rust_InjectableNetwork_ReceiveUdp,
+            Protocol::Tcp => Rust_InjectableNetwork_ReceiveTcp,
+        };
         unsafe {
-            // Rust_receiveUdp is expected to copy it because it's going to get dropped.
-            Rust_InjectableNetwork_ReceiveUdp(
+            // Rust_InjectableNetwork_Receive* are expected to copy it
+            // because it's going to get dropped.
+            receive(
                 self.rffi,
                 packet.source.into(),
                 packet.dest.into(),
@@ -138,6 +177,14 @@ extern "C" {
         data: webrtc::ptr::Borrowed<u8>,
         size: usize,
     );
+    // The data is one whole message sent over a TCP connection.
+    pub fn Rust_InjectableNetwork_ReceiveTcp(
+        network: webrtc::ptr::Borrowed<RffiInjectableNetwork>,
+        source: RffiIpPort,
+        dest: RffiIpPort,
+        data: webrtc::ptr::Borrowed<u8>,
+        size: usize,
+    );
 }
 
 #[repr(C)]
@@ -150,6 +197,13 @@ struct RffiInjectableNetworkSender {
         data: webrtc::ptr::Borrowed<u8>,
         size: usize,
     ),
+    send_tcp: extern "C" fn(
+        object: webrtc::ptr::Borrowed<Box<dyn PacketSender>>,
+        source: RffiIpPort,
+        dest: RffiIpPort,
+        data: webrtc::ptr::Borrowed<u8>,
+        size: usize,
+    ),
     delete: extern "C" fn(object: webrtc::ptr::Owned<Box<dyn PacketSender>>),
 }
 
@@ -163,6 +217,30 @@ extern "C" fn Rust_InjectableNetworkSender_SendUdp(
 ) {
     debug!("Send UDP {:?} => {:?} of size {}", source, dest, size);
 
+    send_packet(sender, source, dest, data, size, Protocol::Udp);
+}
+
+#[allow(non_snake_case)]
+extern "C" fn Rust_InjectableNetworkSender_SendTcp(
+    sender: webrtc::ptr::Borrowed<Box<dyn PacketSender>>,
+    source: RffiIpPort,
+    dest: RffiIpPort,
+    data: webrtc::ptr::Borrowed<u8>,
+    size: usize,
+) {
+    debug!("Send TCP {:?} => {:?} of size {}", source, dest, size);
+
+    send_packet(sender, source, dest, data, size, Protocol::Tcp);
+}
+
+fn send_packet(
+    sender: webrtc::ptr::Borrowed<Box<dyn PacketSender>>,
+    source: RffiIpPort,
+    dest: RffiIpPort,
+    data: webrtc::ptr::Borrowed<u8>,
+    size: usize,
+    protocol: Protocol,
+) {
     // Safe because the sender should still be alive (it was just passed to us)
     if let Some(sender) = unsafe { sender.as_ref() } {
         // Copy the data because it won't be valid any more.
@@ -171,10 +249,11 @@ extern "C" fn Rust_InjectableNetworkSender_SendUdp(
             source: source.socket_addr(),
             dest: dest.socket_addr(),
             data,
+            protocol,
         };
-        sender.send_udp(packet);
+        sender.send_packet(packet);
     } else {
-        error!("Rust_InjectableNetworkSender_SendUdp called with null sender");
+        error!("InjectableNetworkSender called with null sender");
     }
 }
 
@@ -3,9 +3,15 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use crate::common::Result;
+use crate::error::RingRtcError;
 use crate::webrtc;
 
 pub use crate::webrtc::peer_connection_factory::RffiPeerConnectionFactoryOwner;
+use crate::webrtc::video_frame_buffer::I420Buffer;
+use crate::webrtc::video_frame_processor::{
+    VideoFrameProcessor, VideoFrameProcessorChain, VideoFrameProcessorStats,
+};
 
 #[cfg(not(feature = "sim"))]
 use crate::webrtc::ffi::media;
@@ -95,6 +101,10 @@ pub struct VideoFrameMetadata {
 }
 
 impl VideoFrameMetadata {
+    pub fn rotation(&self) -> VideoRotation {
+        self.rotation
+    }
+
     #[must_use]
     pub fn apply_rotation(&self) -> Self {
         match self.rotation {
@@ -118,6 +128,14 @@ pub enum VideoPixelFormat {
     I420,
     Nv12,
     Rgba,
+    /// Packed like Rgba, with blue first in memory.
+    Bgra,
+    /// Packed like Rgba, with alpha first in memory.
+    Argb,
+    /// Like I420, but the U and V planes are full size.
+    I444,
+    /// Like Nv12, but with 16-bit little-endian samples (10 significant bits at the top).
+    P010,
 }
 
 impl VideoPixelFormat {
@@ -126,9 +144,24 @@ impl VideoPixelFormat {
             0 => Some(VideoPixelFormat::I420),
             1 => Some(VideoPixelFormat::Nv12),
             2 => Some(VideoPixelFormat::Rgba),
+            3 => Some(VideoPixelFormat::Bgra),
+            4 => Some(VideoPixelFormat::Argb),
+            5 => Some(VideoPixelFormat::I444),
+            6 => Some(VideoPixelFormat::P010),
             _ => None,
         }
     }
+
+    /// The number of bytes in a frame of the given size.
+    pub fn size(self, width: u32, height: u32) -> usize {
+        let pixels = (width as usize) * (height as usize);
+        match self {
+            VideoPixelFormat::I420 | VideoPixelFormat::Nv12 => I420Buffer::size(width, height),
+            VideoPixelFormat::Rgba | VideoPixelFormat::Bgra | VideoPixelFormat::Argb => pixels * 4,
+            VideoPixelFormat::I444 => pixels * 3,
+            VideoPixelFormat::P010 => I420Buffer::size(width, height) * 2,
+        }
+    }
 }
 
 pub struct VideoFrame {
@@ -197,11 +230,26 @@ impl VideoFrame {
             VideoPixelFormat::Rgba => unsafe {
                 media::Rust_copyVideoFrameBufferFromRgba(width, height, rffi_source)
             },
+            // WebRTC doesn't take these directly, so convert them to I420 first.
+            VideoPixelFormat::Bgra
+            | VideoPixelFormat::Argb
+            | VideoPixelFormat::I444
+            | VideoPixelFormat::P010 => {
+                let i420 = I420Buffer::from_format(width, height, pixel_format, buffer);
+                let rffi_source = webrtc::ptr::Borrowed::from_ptr(i420.as_bytes().as_ptr());
+                unsafe { media::Rust_copyVideoFrameBufferFromI420(width, height, rffi_source) }
+            }
         });
         Self::from_buffer(metadata, rffi_buffer)
     }
 
     pub fn to_rgba(&self, rgba_buffer: &mut [u8]) {
+        assert!(
+            rgba_buffer.len() >= VideoPixelFormat::Rgba.size(self.width(), self.height()),
+            "RGBA buffer too small for a {}x{} frame",
+            self.width(),
+            self.height()
+        );
         unsafe {
             media::Rust_convertVideoFrameBufferToRgba(
                 self.rffi_buffer.as_borrowed(),
@@ -209,30 +257,164 @@ impl VideoFrame {
             )
         }
     }
+
+    // The buffer must be big enough for the Y plane followed by the U and V planes,
+    // each of which is half the width and height (rounded up).
+    pub fn to_i420(&self, i420_buffer: &mut [u8]) {
+        assert!(
+            i420_buffer.len() >= I420Buffer::size(self.width(), self.height()),
+            "I420 buffer too small for a {}x{} frame",
+            self.width(),
+            self.height()
+        );
+        unsafe {
+            media::Rust_convertVideoFrameBufferToI420(
+                self.rffi_buffer.as_borrowed(),
+                i420_buffer.as_mut_ptr(),
+            )
+        }
+    }
+
+    /// Copies the frame into the buffer in the given format.
+    /// The buffer must be at least pixel_format.size() bytes.
+    pub fn to_format(&self, pixel_format: VideoPixelFormat, buffer: &mut [u8]) {
+        match pixel_format {
+            VideoPixelFormat::I420 => self.to_i420(buffer),
+            VideoPixelFormat::Rgba => self.to_rgba(buffer),
+            _ => self.to_i420_buffer().to_format(pixel_format, buffer),
+        }
+    }
+
+    /// Copies the frame into a Rust buffer, for scaling, cropping, or converting
+    /// (e.g. in a VideoSink).  Rotation isn't applied; see apply_rotation().
+    pub fn to_i420_buffer(&self) -> I420Buffer {
+        let mut i420 = vec![0u8; I420Buffer::size(self.width(), self.height())];
+        self.to_i420(&mut i420);
+        I420Buffer::from_i420(self.width(), self.height(), &i420)
+    }
+
+    pub fn from_i420_buffer(buffer: &I420Buffer) -> Self {
+        Self::copy_from_slice(
+            buffer.width(),
+            buffer.height(),
+            VideoPixelFormat::I420,
+            buffer.as_bytes(),
+        )
+    }
 }
 
 /// Rust wrapper around WebRTC C++ VideoTrackSourceInterface object.
 #[derive(Clone, Debug)]
 pub struct VideoSource {
     rffi: webrtc::Arc<media::RffiVideoSource>,
+    // Shared by clones, so processors apply no matter which clone pushes.
+    processors: std::sync::Arc<VideoFrameProcessorChain>,
 }
 
 impl VideoSource {
     pub fn new(rffi: webrtc::Arc<media::RffiVideoSource>) -> Self {
-        Self { rffi }
+        Self {
+            rffi,
+            processors: Default::default(),
+        }
+    }
+
+    /// For a source created with the processors, such as by
+    /// PeerConnectionFactory::create_processed_video_source.
+    pub fn with_frame_processors(
+        rffi: webrtc::Arc<media::RffiVideoSource>,
+        processors: std::sync::Arc<VideoFrameProcessorChain>,
+    ) -> Self {
+        Self { rffi, processors }
     }
 
     pub fn rffi(&self) -> &webrtc::Arc<media::RffiVideoSource> {
         &self.rffi
     }
 
+    /// Adds a processor to the end of the chain that pushed frames go through.
+    pub fn add_frame_processor(&self, processor: Box<dyn VideoFrameProcessor>) {
+        self.processors.add(processor);
+    }
+
+    pub fn clear_frame_processors(&self) {
+        self.processors.clear();
+    }
+
+    pub fn frame_processor_stats(&self) -> Vec<VideoFrameProcessorStats> {
+        self.processors.stats()
+    }
+
     pub fn push_frame(&self, frame: VideoFrame) {
+        let frame = match self.processors.process(frame) {
+            Some(frame) => frame,
+            None => return,
+        };
         unsafe {
             media::Rust_pushVideoFrame(self.rffi.as_borrowed(), frame.rffi_buffer.as_borrowed());
         }
     }
 }
 
+/// Passed to Rust_createProcessedVideoSource, which calls processFrame
+/// for every frame of the input track and deleteProcessor when the
+/// source is destroyed.
+#[repr(C)]
+#[allow(non_snake_case)]
+pub struct VideoFrameProcessorCallbacks {
+    pub processFrame: extern "C" fn(
+        processors: webrtc::ptr::Borrowed<VideoFrameProcessorChain>,
+        metadata: VideoFrameMetadata,
+        buffer: webrtc::ptr::BorrowedRc<RffiVideoFrameBuffer>,
+    ) -> webrtc::ptr::OwnedRc<RffiVideoFrameBuffer>,
+    pub deleteProcessor: extern "C" fn(processors: webrtc::ptr::Owned<VideoFrameProcessorChain>),
+}
+
+pub const VIDEO_FRAME_PROCESSOR_CBS: VideoFrameProcessorCallbacks = VideoFrameProcessorCallbacks {
+    processFrame: video_frame_processor_ProcessFrame,
+    deleteProcessor: video_frame_processor_DeleteProcessor,
+};
+pub const VIDEO_FRAME_PROCESSOR_CBS_PTR: *const VideoFrameProcessorCallbacks =
+    &VIDEO_FRAME_PROCESSOR_CBS;
+
+/// Returns the buffer to send, or null to drop the frame.
+/// Without any processors, that's the given buffer.
+#[allow(non_snake_case)]
+extern "C" fn video_frame_processor_ProcessFrame(
+    processors: webrtc::ptr::Borrowed<VideoFrameProcessorChain>,
+    metadata: VideoFrameMetadata,
+    buffer: webrtc::ptr::BorrowedRc<RffiVideoFrameBuffer>,
+) -> webrtc::ptr::OwnedRc<RffiVideoFrameBuffer> {
+    // Safe because the source keeps the processors alive until deleteProcessor.
+    let processors = match unsafe { processors.as_ref() } {
+        Some(processors) => processors,
+        None => {
+            error!("video_frame_processor_ProcessFrame called with null processors");
+            return webrtc::ptr::OwnedRc::null();
+        }
+    };
+    // Safe because the buffer was just passed to us, so it's still alive.
+    let frame = VideoFrame::from_buffer(metadata, unsafe { webrtc::Arc::from_borrowed(buffer) });
+    match processors.process(frame) {
+        Some(frame) => frame.rffi_buffer.into_owned(),
+        None => webrtc::ptr::OwnedRc::null(),
+    }
+}
+
+#[allow(non_snake_case)]
+extern "C" fn video_frame_processor_DeleteProcessor(
+    processors: webrtc::ptr::Owned<VideoFrameProcessorChain>,
+) {
+    debug!("video_frame_processor_DeleteProcessor({:?})", processors);
+
+    if processors.is_null() {
+        error!("video_frame_processor_DeleteProcessor called with null processors");
+        return;
+    }
+    // Safe because the pointer came from Arc::into_raw in create_processed_video_source.
+    drop(unsafe { std::sync::Arc::from_raw(processors.as_ptr()) });
+}
+
 /// Rust wrapper around WebRTC C++ VideoTrackInterface object.
 #[derive(Clone, Debug)]
 pub struct VideoTrack {
@@ -284,6 +466,27 @@ impl VideoTrack {
     }
 }
 
+/// Decoded audio, 16-bit and interleaved if there is more than one channel.
+#[derive(Clone, Copy, Debug)]
+pub struct AudioFrame<'a> {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub samples: &'a [i16],
+}
+
+/// Like VideoSink, but for decoded audio.
+pub trait AudioSink: Sync + Send {
+    /// This runs on the WebRTC audio thread, so it must not block.
+    fn on_audio_frame(&self, track_id: u32, frame: AudioFrame);
+    fn box_clone(&self) -> Box<dyn AudioSink>;
+}
+
+impl Clone for Box<dyn AudioSink> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
 // You could have a non-Sync, non-Send VideoSink, but
 // it's more convenient put those traits here than anywhere else.
 pub trait VideoSink: Sync + Send {
@@ -379,6 +582,74 @@ impl From<&AudioEncoderConfig> for RffiAudioEncoderConfig {
     }
 }
 
+/// How the layers of outgoing video are encoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoLayering {
+    /// Each layer is a separate stream (works with any codec).
+    Simulcast,
+    /// The layers are spatial layers of one VP9 stream.
+    Svc,
+}
+
+/// One layer of outgoing video.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoEncodingLayer {
+    /// 1.0 is full resolution, 2.0 is half the width and height, and so on.
+    pub scale_resolution_down_by: f64,
+    pub max_bitrate_bps: u32,
+    /// An inactive layer isn't encoded or sent.
+    pub active: bool,
+}
+
+impl VideoEncodingLayer {
+    pub fn height(&self, full_height: u16) -> u16 {
+        (full_height as f64 / self.scale_resolution_down_by.max(1.0)) as u16
+    }
+}
+
+/// The most layers a VideoEncodingConfig can have.  Outgoing video is
+/// negotiated with 3 simulcast streams, and SVC uses at most 3 spatial layers.
+pub const MAX_VIDEO_ENCODING_LAYERS: usize = 3;
+
+/// The layers of outgoing video, set with PeerConnection::set_video_encoding_layers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoEncodingConfig {
+    pub layering: VideoLayering,
+    /// From the lowest resolution to the highest.
+    /// At most MAX_VIDEO_ENCODING_LAYERS.
+    pub layers: Vec<VideoEncodingLayer>,
+}
+
+impl VideoEncodingConfig {
+    /// Fails if there are more than MAX_VIDEO_ENCODING_LAYERS layers.
+    pub fn check_layer_count(&self) -> Result<()> {
+        if self.layers.len() > MAX_VIDEO_ENCODING_LAYERS {
+            return Err(RingRtcError::TooManyVideoEncodingLayers(self.layers.len()).into());
+        }
+        Ok(())
+    }
+}
+
+/// Same as RffiVideoEncodingLayer in rffi/api/peer_connection_intf.h,
+/// which is applied as RtpEncodingParameters.
+#[repr(C)]
+#[derive(Clone, Debug)]
+pub struct RffiVideoEncodingLayer {
+    pub scale_resolution_down_by: f64,
+    pub max_bitrate_bps: u32,
+    pub active: bool,
+}
+
+impl From<&VideoEncodingLayer> for RffiVideoEncodingLayer {
+    fn from(layer: &VideoEncodingLayer) -> Self {
+        Self {
+            scale_resolution_down_by: layer.scale_resolution_down_by,
+            max_bitrate_bps: layer.max_bitrate_bps,
+            active: layer.active,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(i32)]
 pub enum AudioBandwidth {
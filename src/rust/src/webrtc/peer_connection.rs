@@ -12,7 +12,10 @@ use crate::core::util::redact_string;
 use crate::error::RingRtcError;
 use crate::webrtc;
 use crate::webrtc::ice_gatherer::IceGatherer;
-use crate::webrtc::media::{AudioEncoderConfig, RffiAudioEncoderConfig};
+use crate::webrtc::media::{
+    AudioEncoderConfig, RffiAudioEncoderConfig, RffiVideoEncodingLayer, VideoEncodingConfig,
+    VideoLayering,
+};
 use crate::webrtc::network::RffiIpPort;
 use crate::webrtc::peer_connection_factory::RffiPeerConnectionFactoryOwner;
 use crate::webrtc::peer_connection_observer::RffiPeerConnectionObserver;
@@ -77,6 +80,9 @@ pub struct RffiReceivedAudioLevel {
 pub type AudioLevel = RffiAudioLevel;
 pub type ReceivedAudioLevel = RffiReceivedAudioLevel;
 
+// The upper bound of AudioSourceInterface::SetVolume().
+pub const MAX_REMOTE_AUDIO_VOLUME: f64 = 10.0;
+
 impl PeerConnection {
     pub fn new(
         rffi: webrtc::Arc<RffiPeerConnection>,
@@ -97,6 +103,66 @@ impl PeerConnection {
             .set_rtp_packet_sink(rtp_packet_sink)
     }
 
+    #[cfg(feature = "sim")]
+    pub fn set_audio_levels(
+        &self,
+        captured_level: AudioLevel,
+        received_levels: Vec<ReceivedAudioLevel>,
+    ) {
+        unsafe { self.rffi.as_borrowed().as_ref() }
+            .unwrap()
+            .set_audio_levels(captured_level, received_levels)
+    }
+
+    #[cfg(feature = "sim")]
+    pub fn remote_audio_volume(&self, demux_id: u32) -> Option<f64> {
+        unsafe { self.rffi.as_borrowed().as_ref() }
+            .unwrap()
+            .remote_audio_volume(demux_id)
+    }
+
+    #[cfg(feature = "sim")]
+    pub fn video_encoding_layers(&self) -> (bool, Vec<RffiVideoEncodingLayer>) {
+        unsafe { self.rffi.as_borrowed().as_ref() }
+            .unwrap()
+            .video_encoding_layers()
+    }
+
+    #[cfg(feature = "sim")]
+    pub fn incoming_video_receivers_enabled(&self) -> Vec<bool> {
+        unsafe { self.rffi.as_borrowed().as_ref() }
+            .unwrap()
+            .incoming_video_receivers_enabled()
+    }
+
+    #[cfg(feature = "sim")]
+    pub fn remote_video_demux_ids(&self) -> Vec<u32> {
+        unsafe { self.rffi.as_borrowed().as_ref() }
+            .unwrap()
+            .remote_video_demux_ids()
+    }
+
+    #[cfg(feature = "sim")]
+    pub fn incoming_audio_frames_enabled(&self) -> bool {
+        unsafe { self.rffi.as_borrowed().as_ref() }
+            .unwrap()
+            .incoming_audio_frames_enabled()
+    }
+
+    #[cfg(feature = "sim")]
+    pub fn incoming_video_frame_content_enabled(&self) -> bool {
+        unsafe { self.rffi.as_borrowed().as_ref() }
+            .unwrap()
+            .incoming_video_frame_content_enabled()
+    }
+
+    #[cfg(feature = "sim")]
+    pub fn outgoing_video_enabled(&self) -> bool {
+        unsafe { self.rffi.as_borrowed().as_ref() }
+            .unwrap()
+            .outgoing_video_enabled()
+    }
+
     /// Rust wrapper around C++ webrtc::CreateSessionDescription(kOffer).
     pub fn create_offer(&self, csd_observer: &CreateSessionDescriptionObserver) {
         unsafe { pc::Rust_createOffer(self.rffi.as_borrowed(), csd_observer.rffi().as_borrowed()) }
@@ -163,6 +229,26 @@ impl PeerConnection {
         }
     }
 
+    /// Unlike set_outgoing_media_enabled, this leaves audio alone.
+    pub fn set_outgoing_video_enabled(&self, enabled: bool) {
+        unsafe { pc::Rust_setOutgoingVideoEnabled(self.rffi.as_borrowed(), enabled) };
+    }
+
+    /// Unlike set_incoming_media_enabled, this leaves audio alone.
+    pub fn set_incoming_video_enabled(&self, enabled: bool) {
+        unsafe { pc::Rust_setIncomingVideoEnabled(self.rffi.as_borrowed(), enabled) };
+    }
+
+    /// See PeerConnectionObserverTrait::handle_incoming_audio_frame.
+    pub fn set_incoming_audio_frames_enabled(&self, enabled: bool) {
+        unsafe { pc::Rust_setIncomingAudioFramesEnabled(self.rffi.as_borrowed(), enabled) };
+    }
+
+    /// See PeerConnectionObserverTrait::handle_incoming_video_frame.
+    pub fn set_incoming_video_frame_content_enabled(&self, enabled: bool) {
+        unsafe { pc::Rust_setIncomingVideoFrameContentEnabled(self.rffi.as_borrowed(), enabled) };
+    }
+
     pub fn set_audio_playout_enabled(&self, enabled: bool) {
         unsafe { pc::Rust_setAudioPlayoutEnabled(self.rffi.as_borrowed(), enabled) };
     }
@@ -345,6 +431,50 @@ impl PeerConnection {
         (captured_level, received_levels)
     }
 
+    /// Rust wrapper around C++ AudioSourceInterface::SetVolume() for the
+    /// remote audio track received with the given demux ID.
+    /// A volume of 0.0 silences it, 1.0 leaves it unchanged, and
+    /// anything up to MAX_REMOTE_AUDIO_VOLUME amplifies it.
+    pub fn set_remote_audio_volume(&self, demux_id: u32, volume: f64) -> Result<()> {
+        let volume = volume.clamp(0.0, MAX_REMOTE_AUDIO_VOLUME);
+        let ok =
+            unsafe { pc::Rust_setRemoteAudioVolume(self.rffi.as_borrowed(), demux_id, volume) };
+        if ok {
+            Ok(())
+        } else {
+            Err(RingRtcError::SetRemoteAudioVolume(demux_id).into())
+        }
+    }
+
+    // None goes back to the default encodings (which are simulcast in group calls).
+    // Fails without changing anything if there are more than MAX_VIDEO_ENCODING_LAYERS layers.
+    pub fn set_video_encoding_layers(&self, config: Option<&VideoEncodingConfig>) -> Result<()> {
+        info!("PeerConnection.set_video_encoding_layers({:?})", config);
+        if let Some(config) = config {
+            config.check_layer_count()?;
+        }
+        let (svc, layers): (bool, Vec<RffiVideoEncodingLayer>) = match config {
+            Some(config) => (
+                config.layering == VideoLayering::Svc,
+                config.layers.iter().map(|layer| layer.into()).collect(),
+            ),
+            None => (false, vec![]),
+        };
+        let ok = unsafe {
+            pc::Rust_setVideoEncodingLayers(
+                self.rffi.as_borrowed(),
+                svc,
+                webrtc::ptr::Borrowed::from_ptr(layers.as_ptr()),
+                layers.len(),
+            )
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(RingRtcError::SetVideoEncodingLayers.into())
+        }
+    }
+
     pub fn close(&self) {
         unsafe { pc::Rust_closePeerConnection(self.rffi.as_borrowed()) };
     }
@@ -9,17 +9,21 @@
 use std::ffi::CStr;
 use std::ffi::CString;
 use std::os::raw::c_char;
+use std::sync::Arc;
 
 use crate::common::Result;
 use crate::error::RingRtcError;
 use crate::webrtc;
 #[cfg(feature = "simnet")]
 use crate::webrtc::injectable_network::InjectableNetwork;
-use crate::webrtc::media::{AudioTrack, VideoSource, VideoTrack};
+use crate::webrtc::media::{
+    AudioFrame, AudioTrack, VideoSource, VideoTrack, VIDEO_FRAME_PROCESSOR_CBS_PTR,
+};
 use crate::webrtc::peer_connection::PeerConnection;
 use crate::webrtc::peer_connection_observer::{
     PeerConnectionObserver, PeerConnectionObserverTrait,
 };
+use crate::webrtc::video_frame_processor::VideoFrameProcessorChain;
 
 #[cfg(not(feature = "sim"))]
 use crate::webrtc::ffi::peer_connection_factory as pcf;
@@ -132,6 +136,9 @@ pub struct PeerConnectionFactory {
 pub struct Config {
     pub use_new_audio_device_module: bool,
     pub use_injectable_network: bool,
+    /// Lets push_outgoing_audio_samples replace the recording device's audio.
+    /// Only for tests and the CLI; apps use the recording device as is.
+    pub use_injectable_audio: bool,
 }
 
 impl PeerConnectionFactory {
@@ -146,6 +153,7 @@ impl PeerConnectionFactory {
                 webrtc::Arc::from_owned(pcf::Rust_createPeerConnectionFactory(
                     config.use_new_audio_device_module,
                     config.use_injectable_network,
+                    config.use_injectable_audio,
                 ))
             };
 
@@ -260,6 +268,22 @@ impl PeerConnectionFactory {
         Ok(AudioTrack::new(rffi, Some(self.rffi.clone())))
     }
 
+    /// Sends the given audio instead of what the recording device captures,
+    /// for as long as samples keep being pushed (e.g. synthetic test audio).
+    /// Samples should be pushed in real time, 10ms at a time.
+    /// Samples are dropped unless the factory was created with use_injectable_audio.
+    pub fn push_outgoing_audio_samples(&self, frame: AudioFrame) {
+        unsafe {
+            pcf::Rust_pushOutgoingAudioSamples(
+                self.rffi.as_borrowed(),
+                frame.sample_rate,
+                frame.channels as usize,
+                webrtc::ptr::Borrowed::from_ptr(frame.samples.as_ptr()),
+                frame.samples.len(),
+            )
+        }
+    }
+
     pub fn create_outgoing_video_source(&self) -> Result<VideoSource> {
         debug!("PeerConnectionFactory::create_outgoing_video_source()");
         let rffi = webrtc::Arc::from_owned(unsafe { pcf::Rust_createVideoSource() });
@@ -269,6 +293,30 @@ impl PeerConnectionFactory {
         Ok(VideoSource::new(rffi))
     }
 
+    /// Unlike with create_outgoing_video_source, frames aren't pushed to the returned source.
+    /// They come from the input track (such as one fed by a platform's camera capturer),
+    /// each going through the processors first.
+    pub fn create_processed_video_source(
+        &self,
+        input_track: &VideoTrack,
+        processors: Arc<VideoFrameProcessorChain>,
+    ) -> Result<VideoSource> {
+        debug!("PeerConnectionFactory::create_processed_video_source()");
+        // Released by the source with deleteProcessor.
+        let processors_ptr = Arc::into_raw(processors.clone());
+        let rffi = webrtc::Arc::from_owned(unsafe {
+            pcf::Rust_createProcessedVideoSource(
+                input_track.rffi().as_borrowed(),
+                webrtc::ptr::Owned::from_ptr(processors_ptr as *const std::ffi::c_void),
+                webrtc::ptr::Borrowed::from_ptr(VIDEO_FRAME_PROCESSOR_CBS_PTR).to_void(),
+            )
+        });
+        if rffi.is_null() {
+            return Err(RingRtcError::CreateVideoSource.into());
+        }
+        Ok(VideoSource::with_frame_processors(rffi, processors))
+    }
+
     // We take ownership of the VideoSource because Rust_createVideoTrack takes ownership
     // of one takes ownership of one ref count to the source.
     pub fn create_outgoing_video_track(
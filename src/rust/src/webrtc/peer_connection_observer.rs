@@ -18,8 +18,8 @@ use crate::core::signaling;
 use crate::error::RingRtcError;
 use crate::webrtc;
 use crate::webrtc::media::{
-    AudioTrack, MediaStream, RffiAudioTrack, RffiMediaStream, RffiVideoFrameBuffer, RffiVideoTrack,
-    VideoFrame, VideoFrameMetadata, VideoTrack,
+    AudioFrame, AudioTrack, MediaStream, RffiAudioTrack, RffiMediaStream, RffiVideoFrameBuffer,
+    RffiVideoTrack, VideoFrame, VideoFrameMetadata, VideoTrack,
 };
 use crate::webrtc::network::RffiIpPort;
 use crate::webrtc::rtp;
@@ -129,6 +129,8 @@ pub trait PeerConnectionObserverTrait {
     fn handle_incoming_video_added(&mut self, _incoming_track: VideoTrack) -> Result<()> {
         Ok(())
     }
+    // The frame is only given if enable_video_frame_content was set when the
+    // observer was created or by PeerConnection::set_incoming_video_frame_content_enabled.
     fn handle_incoming_video_frame(
         &mut self,
         _track_id: u32,
@@ -137,6 +139,10 @@ pub trait PeerConnectionObserverTrait {
     ) -> Result<()> {
         Ok(())
     }
+    // Only called after PeerConnection::set_incoming_audio_frames_enabled(true).
+    fn handle_incoming_audio_frame(&mut self, _track_id: u32, _frame: AudioFrame) -> Result<()> {
+        Ok(())
+    }
 
     // RTP data events
     // Warning: this runs on the WebRTC network thread, so doing anything that
@@ -416,6 +422,39 @@ extern "C" fn pc_observer_OnVideoFrame<T>(
     }
 }
 
+/// PeerConnectionObserver OnAudioFrame() callback for decoded audio.
+#[allow(non_snake_case)]
+extern "C" fn pc_observer_OnAudioFrame<T>(
+    observer: webrtc::ptr::Borrowed<T>,
+    track_id: u32,
+    sample_rate: u32,
+    channels: size_t,
+    samples: webrtc::ptr::Borrowed<i16>,
+    samples_len: size_t,
+) where
+    T: PeerConnectionObserverTrait,
+{
+    // Safe because the observer should still be alive (it was just passed to us)
+    if let Some(observer) = unsafe { observer.as_mut() } {
+        let samples = if samples.is_null() || samples_len == 0 {
+            &[]
+        } else {
+            // Safe because the samples should still be alive (they were just passed to us)
+            unsafe { slice::from_raw_parts(samples.as_ptr(), samples_len) }
+        };
+        let frame = AudioFrame {
+            sample_rate,
+            channels: channels as u16,
+            samples,
+        };
+        observer
+            .handle_incoming_audio_frame(track_id, frame)
+            .unwrap_or_else(|e| error!("Problems handling incoming audio frame: {}", e));
+    } else {
+        error!("pc_observer_OnAudioFrame called with null observer");
+    }
+}
+
 #[allow(non_snake_case)]
 extern "C" fn pc_observer_OnRtpReceived<T>(
     observer: webrtc::ptr::Borrowed<T>,
@@ -614,6 +653,14 @@ where
         VideoFrameMetadata,
         webrtc::ptr::OwnedRc<RffiVideoFrameBuffer>,
     ),
+    onAudioFrame: extern "C" fn(
+        webrtc::ptr::Borrowed<T>,
+        track_id: u32,
+        sample_rate: u32,
+        channels: size_t,
+        webrtc::ptr::Borrowed<i16>,
+        size_t,
+    ),
 
     // RTP data events
     onRtpReceived: extern "C" fn(
@@ -709,6 +756,9 @@ where
             // Used for group calls.
             onAddVideoRtpReceiver: pc_observer_OnAddVideoRtpReceiver::<T>,
             onVideoFrame: pc_observer_OnVideoFrame::<T>,
+            // Triggered for each remote audio track while enabled with
+            // PeerConnection::set_incoming_audio_frames_enabled.
+            onAudioFrame: pc_observer_OnAudioFrame::<T>,
 
             // RTP data events
             onRtpReceived: pc_observer_OnRtpReceived::<T>,
@@ -27,7 +27,19 @@ use std::fmt;
 // Technically unsafe because we're reinterpreting the pointee based on the
 // presence of this trait, given that all the adopting types are placeholder
 // types anyway, adding unsafe won't make anything more clear.
-pub trait RefCounted {}
+pub trait RefCounted {
+    // The simulated ref_count::inc and ref_count::dec call these.
+    // Most simulated types are static placeholders with nothing to count,
+    // but the ones that are really allocated override these to free themselves.
+    /// # Safety
+    /// The pointee must be alive.
+    #[cfg(feature = "sim")]
+    unsafe fn sim_inc_ref(_ptr: *const Self) {}
+    /// # Safety
+    /// The pointee must own a ref count, which is consumed.
+    #[cfg(feature = "sim")]
+    unsafe fn sim_dec_ref(_ptr: *const Self) {}
+}
 
 pub trait Borrow<T> {
     fn borrow(&self) -> Borrowed<T>;
@@ -9,8 +9,7 @@ use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 use std::sync::{Arc, Condvar, Mutex};
 
-use crate::common::Result;
-use crate::core::bandwidth_mode::BandwidthMode;
+use crate::common::{units::DataRate, Result};
 use crate::core::util::FutureResult;
 use crate::error::RingRtcError;
 use crate::protobuf;
@@ -195,7 +194,7 @@ impl SessionDescription {
     pub fn to_v4(
         &self,
         public_key: Vec<u8>,
-        bandwidth_mode: BandwidthMode,
+        max_receive_rate: DataRate,
     ) -> Result<protobuf::signaling::ConnectionParametersV4> {
         let rffi_v4_ptr = webrtc::ptr::Unique::from(unsafe {
             sdp::Rust_sessionDescriptionToV4(self.rffi.borrow())
@@ -243,7 +242,7 @@ impl SessionDescription {
             ice_ufrag: Some(ice_ufrag),
             ice_pwd: Some(ice_pwd),
             receive_video_codecs,
-            max_bitrate_bps: Some(bandwidth_mode.max_bitrate().as_bps()),
+            max_bitrate_bps: Some(max_receive_rate.as_bps()),
         })
     }
 
@@ -330,6 +329,7 @@ impl SessionDescription {
         ice_pwd: &str,
         server_srtp_key: &SrtpKey,
         rtp_demux_ids: &[u32],
+        receive_video: bool,
     ) -> Result<Self> {
         let rffi_ice_ufrag = CString::new(ice_ufrag.as_bytes())?;
         let rffi_ice_pwd = CString::new(ice_pwd.as_bytes())?;
@@ -341,6 +341,7 @@ impl SessionDescription {
                 server_srtp_key.rffi(),
                 webrtc::ptr::Borrowed::from_ptr(rtp_demux_ids.as_ptr()),
                 rtp_demux_ids.len(),
+                receive_video,
             )
         });
         if sdi.is_null() {
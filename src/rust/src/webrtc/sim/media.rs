@@ -3,7 +3,10 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use std::sync::atomic::{self, AtomicUsize};
+
 use crate::webrtc;
+use crate::webrtc::video_frame_buffer::I420Buffer;
 
 pub use crate::webrtc::media::VideoRotation;
 
@@ -21,9 +24,35 @@ pub type RffiVideoTrack = u32;
 
 pub static FAKE_VIDEO_TRACK: RffiVideoSource = 23;
 
-pub type RffiVideoFrameBuffer = u32;
+/// Simulation type for VideoFrameBuffer, backed by a Rust I420Buffer.
+/// Unlike the other simulated types, it's really allocated and ref counted,
+/// so it's freed when the last ref goes away.
+pub struct RffiVideoFrameBuffer {
+    pub buffer: I420Buffer,
+    ref_count: AtomicUsize,
+}
+
+impl webrtc::RefCounted for RffiVideoFrameBuffer {
+    unsafe fn sim_inc_ref(ptr: *const Self) {
+        (*ptr).ref_count.fetch_add(1, atomic::Ordering::Relaxed);
+    }
 
-pub static FAKE_VIDEO_FRAME_BUFFER: RffiVideoFrameBuffer = 24;
+    unsafe fn sim_dec_ref(ptr: *const Self) {
+        if (*ptr).ref_count.fetch_sub(1, atomic::Ordering::AcqRel) == 1 {
+            drop(Box::from_raw(ptr as *mut Self));
+        }
+    }
+}
+
+impl RffiVideoFrameBuffer {
+    fn into_owned_rc(buffer: I420Buffer) -> webrtc::ptr::OwnedRc<Self> {
+        let rffi = Box::new(Self {
+            buffer,
+            ref_count: AtomicUsize::new(1),
+        });
+        unsafe { webrtc::ptr::OwnedRc::from_ptr(Box::into_raw(rffi)) }
+    }
+}
 
 #[allow(non_snake_case, clippy::missing_safety_doc)]
 pub unsafe fn Rust_getTrackIdAsUint32(_track: webrtc::ptr::BorrowedRc<RffiVideoTrack>) -> u32 {
@@ -65,47 +94,74 @@ pub unsafe fn Rust_pushVideoFrame(
 
 #[allow(non_snake_case, clippy::missing_safety_doc)]
 pub unsafe fn Rust_copyVideoFrameBufferFromI420(
-    _width: u32,
-    _height: u32,
-    _src: webrtc::ptr::Borrowed<u8>,
+    width: u32,
+    height: u32,
+    src: webrtc::ptr::Borrowed<u8>,
 ) -> webrtc::ptr::OwnedRc<RffiVideoFrameBuffer> {
     info!("Rust_copyVideoFrameBufferFromI420()");
-    webrtc::ptr::OwnedRc::from_ptr(&FAKE_VIDEO_FRAME_BUFFER)
+    let size = I420Buffer::size(width, height);
+    let src = std::slice::from_raw_parts(src.as_ptr(), size);
+    RffiVideoFrameBuffer::into_owned_rc(I420Buffer::from_i420(width, height, src))
 }
 
 #[allow(non_snake_case, clippy::missing_safety_doc)]
 pub unsafe fn Rust_copyVideoFrameBufferFromNv12(
-    _width: u32,
-    _height: u32,
-    _src: webrtc::ptr::Borrowed<u8>,
+    width: u32,
+    height: u32,
+    src: webrtc::ptr::Borrowed<u8>,
 ) -> webrtc::ptr::OwnedRc<RffiVideoFrameBuffer> {
     info!("Rust_copyVideoFrameBufferFromNv12()");
-    webrtc::ptr::OwnedRc::from_ptr(&FAKE_VIDEO_FRAME_BUFFER)
+    let size = I420Buffer::size(width, height);
+    let src = std::slice::from_raw_parts(src.as_ptr(), size);
+    RffiVideoFrameBuffer::into_owned_rc(I420Buffer::from_nv12(width, height, src))
 }
 
 #[allow(non_snake_case, clippy::missing_safety_doc)]
 pub unsafe fn Rust_copyVideoFrameBufferFromRgba(
-    _width: u32,
-    _height: u32,
-    _src: webrtc::ptr::Borrowed<u8>,
+    width: u32,
+    height: u32,
+    src: webrtc::ptr::Borrowed<u8>,
 ) -> webrtc::ptr::OwnedRc<RffiVideoFrameBuffer> {
     info!("Rust_copyVideoFrameBufferFromRgba()");
-    webrtc::ptr::OwnedRc::from_ptr(&FAKE_VIDEO_FRAME_BUFFER)
+    let size = (width as usize) * (height as usize) * 4;
+    let src = std::slice::from_raw_parts(src.as_ptr(), size);
+    RffiVideoFrameBuffer::into_owned_rc(I420Buffer::from_rgba(width, height, src))
 }
 
 #[allow(non_snake_case, clippy::missing_safety_doc)]
 pub unsafe fn Rust_convertVideoFrameBufferToRgba(
-    _buffer: webrtc::ptr::BorrowedRc<RffiVideoFrameBuffer>,
-    _rgba_out: *mut u8,
+    buffer: webrtc::ptr::BorrowedRc<RffiVideoFrameBuffer>,
+    rgba_out: *mut u8,
 ) {
     info!("Rust_convertVideoFrameBufferToRgba()");
+    if let Some(RffiVideoFrameBuffer { buffer, .. }) = buffer.as_ref() {
+        let size = (buffer.width() as usize) * (buffer.height() as usize) * 4;
+        buffer.to_rgba(std::slice::from_raw_parts_mut(rgba_out, size));
+    }
+}
+
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+pub unsafe fn Rust_convertVideoFrameBufferToI420(
+    buffer: webrtc::ptr::BorrowedRc<RffiVideoFrameBuffer>,
+    i420_out: *mut u8,
+) {
+    info!("Rust_convertVideoFrameBufferToI420()");
+    if let Some(RffiVideoFrameBuffer { buffer, .. }) = buffer.as_ref() {
+        let size = I420Buffer::size(buffer.width(), buffer.height());
+        buffer.to_i420(std::slice::from_raw_parts_mut(i420_out, size));
+    }
 }
 
 #[allow(non_snake_case, clippy::missing_safety_doc)]
 pub unsafe fn Rust_copyAndRotateVideoFrameBuffer(
-    _buffer: webrtc::ptr::BorrowedRc<RffiVideoFrameBuffer>,
-    _rotation: VideoRotation,
+    buffer: webrtc::ptr::BorrowedRc<RffiVideoFrameBuffer>,
+    rotation: VideoRotation,
 ) -> webrtc::ptr::OwnedRc<RffiVideoFrameBuffer> {
     info!("Rust_copyAndRotateVideoFrameBuffer()");
-    webrtc::ptr::OwnedRc::from_ptr(&FAKE_VIDEO_FRAME_BUFFER)
+    match buffer.as_ref() {
+        Some(RffiVideoFrameBuffer { buffer, .. }) => {
+            RffiVideoFrameBuffer::into_owned_rc(buffer.rotate(rotation))
+        }
+        None => webrtc::ptr::OwnedRc::null(),
+    }
 }
@@ -5,6 +5,7 @@
 
 //! WebRTC Simulation Peer Connection Interface
 
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::os::raw::c_char;
 use std::sync::{Arc, Mutex};
@@ -13,7 +14,7 @@ use prost::Message;
 
 use crate::core::platform::PlatformItem;
 use crate::webrtc;
-use crate::webrtc::media::RffiAudioEncoderConfig;
+use crate::webrtc::media::{RffiAudioEncoderConfig, RffiVideoEncodingLayer};
 use crate::webrtc::network::RffiIpPort;
 use crate::webrtc::peer_connection::{RffiAudioLevel, RffiReceivedAudioLevel};
 use crate::webrtc::rtp;
@@ -21,6 +22,7 @@ use crate::webrtc::sdp_observer::{
     RffiCreateSessionDescriptionObserver, RffiSessionDescription, RffiSetSessionDescriptionObserver,
 };
 use crate::webrtc::sim::ice_gatherer::{RffiIceGatherer, FAKE_ICE_GATHERER};
+use crate::webrtc::sim::sdp_observer::delete_session_description;
 use crate::webrtc::stats_observer::RffiStatsObserver;
 
 /// Simulation type for PeerConnection.
@@ -54,10 +56,20 @@ impl RffiPeerConnection {
                 local_description_set: false,
                 remote_description_set: false,
                 outgoing_audio_enabled: true,
+                outgoing_video_enabled: true,
+                incoming_video_receivers_enabled: vec![],
+                incoming_audio_frames_enabled: false,
+                incoming_video_frame_content_enabled: false,
                 rtp_packet_sink: None,
                 removed_ice_candidates: vec![],
                 max_bitrate_bps: None,
                 last_sent_rtp_data: None,
+                remote_audio_demux_ids: vec![],
+                remote_video_demux_ids: vec![],
+                remote_audio_volumes: HashMap::new(),
+                captured_audio_level: 0,
+                received_audio_levels: vec![],
+                video_encoding_layers: (false, vec![]),
             })),
         }
     }
@@ -67,9 +79,29 @@ impl RffiPeerConnection {
         state.local_description_set = true;
     }
 
-    fn set_remote_description(&self) {
+    fn set_remote_description(&self, remote_demux_ids: Vec<u32>, receive_video: bool) {
         let mut state = self.state.lock().unwrap();
         state.remote_description_set = true;
+        // Like WebRTC, a new remote description may add a video receiver,
+        // which starts out enabled.
+        state.incoming_video_receivers_enabled.push(true);
+        // In a group call, it has an audio receiver for each remote device.
+        // Receivers of devices that are no longer there go away with their volume.
+        state
+            .remote_audio_volumes
+            .retain(|demux_id, _| remote_demux_ids.contains(demux_id));
+        state.remote_video_demux_ids = if receive_video {
+            remote_demux_ids.clone()
+        } else {
+            vec![]
+        };
+        state.remote_audio_demux_ids = remote_demux_ids;
+    }
+
+    /// The remote devices of a group call that have video receivers.
+    pub fn remote_video_demux_ids(&self) -> Vec<u32> {
+        let state = self.state.lock().unwrap();
+        state.remote_video_demux_ids.clone()
     }
 
     fn set_outgoing_media_enabled(&self, enabled: bool) {
@@ -78,6 +110,11 @@ impl RffiPeerConnection {
             panic!("Can't Rust_setOutgoingMediaEnabled if you haven't received an answer yet.");
         }
         state.outgoing_audio_enabled = enabled;
+        state.outgoing_video_enabled = enabled;
+        // Like RtpSender encodings, every layer follows the outgoing media state.
+        for layer in &mut state.video_encoding_layers.1 {
+            layer.active = enabled;
+        }
     }
 
     pub fn outgoing_audio_enabled(&self) -> bool {
@@ -85,6 +122,48 @@ impl RffiPeerConnection {
         state.outgoing_audio_enabled
     }
 
+    fn set_outgoing_video_enabled(&self, enabled: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.outgoing_video_enabled = enabled;
+    }
+
+    pub fn outgoing_video_enabled(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.outgoing_video_enabled
+    }
+
+    fn set_incoming_video_enabled(&self, enabled: bool) {
+        let mut state = self.state.lock().unwrap();
+        for receiver_enabled in &mut state.incoming_video_receivers_enabled {
+            *receiver_enabled = enabled;
+        }
+    }
+
+    pub fn incoming_video_receivers_enabled(&self) -> Vec<bool> {
+        let state = self.state.lock().unwrap();
+        state.incoming_video_receivers_enabled.clone()
+    }
+
+    fn set_incoming_audio_frames_enabled(&self, enabled: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.incoming_audio_frames_enabled = enabled;
+    }
+
+    pub fn incoming_audio_frames_enabled(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.incoming_audio_frames_enabled
+    }
+
+    fn set_incoming_video_frame_content_enabled(&self, enabled: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.incoming_video_frame_content_enabled = enabled;
+    }
+
+    pub fn incoming_video_frame_content_enabled(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.incoming_video_frame_content_enabled
+    }
+
     fn set_incoming_media_enabled(&self, _enabled: bool) {
         let _state = self.state.lock().unwrap();
         // Do nothing; the sim implementation doesn't use this.
@@ -121,6 +200,42 @@ impl RffiPeerConnection {
         )
     }
 
+    /// Only demux IDs with a remote audio receiver have a volume.
+    pub fn remote_audio_volume(&self, demux_id: u32) -> Option<f64> {
+        let state = self.state.lock().unwrap();
+        state.remote_audio_volumes.get(&demux_id).copied()
+    }
+
+    fn set_remote_audio_volume(&self, demux_id: u32, volume: f64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if !state.remote_audio_demux_ids.contains(&demux_id) {
+            return false;
+        }
+        state.remote_audio_volumes.insert(demux_id, volume);
+        true
+    }
+
+    /// Sets what Rust_getAudioLevels returns.
+    pub fn set_audio_levels(
+        &self,
+        captured_level: RffiAudioLevel,
+        received_levels: Vec<RffiReceivedAudioLevel>,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        state.captured_audio_level = captured_level;
+        state.received_audio_levels = received_levels;
+    }
+
+    pub fn video_encoding_layers(&self) -> (bool, Vec<RffiVideoEncodingLayer>) {
+        let state = self.state.lock().unwrap();
+        state.video_encoding_layers.clone()
+    }
+
+    fn set_video_encoding_layers(&self, svc: bool, layers: Vec<RffiVideoEncodingLayer>) {
+        let mut state = self.state.lock().unwrap();
+        state.video_encoding_layers = (svc, layers);
+    }
+
     pub fn last_sent_max_bitrate_bps(&self) -> Option<u64> {
         self.last_sent_rtp_message()?
             .receiver_status?
@@ -134,10 +249,21 @@ struct RffiPeerConnectionState {
     local_description_set: bool,
     remote_description_set: bool,
     outgoing_audio_enabled: bool,
+    outgoing_video_enabled: bool,
+    incoming_video_receivers_enabled: Vec<bool>,
+    incoming_audio_frames_enabled: bool,
+    incoming_video_frame_content_enabled: bool,
     rtp_packet_sink: Option<BoxedRtpPacketSink>,
     removed_ice_candidates: Vec<SocketAddr>,
     max_bitrate_bps: Option<i32>,
     last_sent_rtp_data: Option<Vec<u8>>,
+    remote_audio_demux_ids: Vec<u32>,
+    remote_video_demux_ids: Vec<u32>,
+    remote_audio_volumes: HashMap<u32, f64>,
+    captured_audio_level: RffiAudioLevel,
+    received_audio_levels: Vec<RffiReceivedAudioLevel>,
+    // (svc, layers)
+    video_encoding_layers: (bool, Vec<RffiVideoEncodingLayer>),
 }
 
 #[allow(non_snake_case, clippy::missing_safety_doc)]
@@ -152,9 +278,10 @@ pub unsafe fn Rust_createOffer(
 pub unsafe fn Rust_setLocalDescription(
     peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
     _ssd_observer: webrtc::ptr::BorrowedRc<RffiSetSessionDescriptionObserver>,
-    _local_desc: webrtc::ptr::Owned<RffiSessionDescription>,
+    local_desc: webrtc::ptr::Owned<RffiSessionDescription>,
 ) {
     info!("Rust_setLocalDescription():");
+    delete_session_description(local_desc);
     (*peer_connection.as_ptr()).set_local_description();
 }
 
@@ -170,10 +297,13 @@ pub unsafe fn Rust_createAnswer(
 pub unsafe fn Rust_setRemoteDescription(
     peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
     _ssd_observer: webrtc::ptr::BorrowedRc<RffiSetSessionDescriptionObserver>,
-    _remote_desc: webrtc::ptr::Owned<RffiSessionDescription>,
+    remote_desc: webrtc::ptr::Owned<RffiSessionDescription>,
 ) {
     info!("Rust_setRemoteDescription():");
-    (*peer_connection.as_ptr()).set_remote_description();
+    let (remote_demux_ids, receive_video) = delete_session_description(remote_desc)
+        .map(|remote_desc| (remote_desc.remote_demux_ids, remote_desc.receive_video))
+        .unwrap_or((vec![], true));
+    (*peer_connection.as_ptr()).set_remote_description(remote_demux_ids, receive_video);
 }
 
 #[allow(non_snake_case, clippy::missing_safety_doc)]
@@ -195,6 +325,42 @@ pub unsafe fn Rust_setIncomingMediaEnabled(
     true
 }
 
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+pub unsafe fn Rust_setOutgoingVideoEnabled(
+    peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
+    enabled: bool,
+) {
+    info!("Rust_setOutgoingVideoEnabled({})", enabled);
+    (*peer_connection.as_ptr()).set_outgoing_video_enabled(enabled);
+}
+
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+pub unsafe fn Rust_setIncomingVideoEnabled(
+    peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
+    enabled: bool,
+) {
+    info!("Rust_setIncomingVideoEnabled({})", enabled);
+    (*peer_connection.as_ptr()).set_incoming_video_enabled(enabled);
+}
+
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+pub unsafe fn Rust_setIncomingAudioFramesEnabled(
+    peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
+    enabled: bool,
+) {
+    info!("Rust_setIncomingAudioFramesEnabled({})", enabled);
+    (*peer_connection.as_ptr()).set_incoming_audio_frames_enabled(enabled);
+}
+
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+pub unsafe fn Rust_setIncomingVideoFrameContentEnabled(
+    peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
+    enabled: bool,
+) {
+    info!("Rust_setIncomingVideoFrameContentEnabled({})", enabled);
+    (*peer_connection.as_ptr()).set_incoming_video_frame_content_enabled(enabled);
+}
+
 #[allow(non_snake_case, clippy::missing_safety_doc)]
 pub unsafe fn Rust_setAudioPlayoutEnabled(
     _peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
@@ -327,23 +493,62 @@ pub unsafe fn Rust_configureAudioEncoders(
 
 #[allow(non_snake_case, clippy::missing_safety_doc)]
 pub fn Rust_getAudioLevels(
-    _peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
+    peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
     captured_out: webrtc::ptr::Borrowed<RffiAudioLevel>,
-    _received_out: webrtc::ptr::Borrowed<RffiReceivedAudioLevel>,
-    _received_out_size: usize,
+    received_out: webrtc::ptr::Borrowed<RffiReceivedAudioLevel>,
+    received_out_size: usize,
     received_size_out: webrtc::ptr::Borrowed<usize>,
 ) {
     info!("Rust_getAudioLevels:");
     unsafe {
+        let state = (*peer_connection.as_ptr()).state.lock().unwrap();
         if let Some(captured_out) = captured_out.as_mut() {
-            *captured_out = 0;
+            *captured_out = state.captured_audio_level;
+        }
+        let received_size = state.received_audio_levels.len().min(received_out_size);
+        if !received_out.as_ptr().is_null() {
+            std::ptr::copy_nonoverlapping(
+                state.received_audio_levels.as_ptr(),
+                received_out.as_ptr() as *mut RffiReceivedAudioLevel,
+                received_size,
+            );
         }
         if let Some(received_size_out) = received_size_out.as_mut() {
-            *received_size_out = 0;
+            *received_size_out = received_size;
         }
     }
 }
 
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+pub unsafe fn Rust_setRemoteAudioVolume(
+    peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
+    demux_id: u32,
+    volume: f64,
+) -> bool {
+    info!("Rust_setRemoteAudioVolume: demux_id: {}", demux_id);
+    (*peer_connection.as_ptr()).set_remote_audio_volume(demux_id, volume)
+}
+
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+pub unsafe fn Rust_setVideoEncodingLayers(
+    peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
+    svc: bool,
+    layers_data: webrtc::ptr::Borrowed<RffiVideoEncodingLayer>,
+    layers_len: usize,
+) -> bool {
+    info!(
+        "Rust_setVideoEncodingLayers: svc: {} len: {}",
+        svc, layers_len
+    );
+    let layers = if layers_len == 0 {
+        vec![]
+    } else {
+        std::slice::from_raw_parts(layers_data.as_ptr(), layers_len).to_vec()
+    };
+    (*peer_connection.as_ptr()).set_video_encoding_layers(svc, layers);
+    true
+}
+
 #[allow(non_snake_case, clippy::missing_safety_doc)]
 pub unsafe fn Rust_closePeerConnection(
     _peer_connection: webrtc::ptr::BorrowedRc<RffiPeerConnection>,
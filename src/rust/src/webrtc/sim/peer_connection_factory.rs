@@ -4,6 +4,7 @@
 //
 
 use crate::webrtc;
+use crate::webrtc::media::VideoFrameProcessorCallbacks;
 use crate::webrtc::peer_connection_factory::RffiIceServer;
 use crate::webrtc::sim::media::{
     RffiAudioTrack, RffiVideoSource, RffiVideoTrack, FAKE_AUDIO_TRACK, FAKE_VIDEO_SOURCE,
@@ -11,6 +12,7 @@ use crate::webrtc::sim::media::{
 };
 use crate::webrtc::sim::peer_connection::RffiPeerConnection;
 use crate::webrtc::sim::peer_connection_observer::RffiPeerConnectionObserver;
+use crate::webrtc::video_frame_processor::VideoFrameProcessorChain;
 use std::ffi::CString;
 use std::os::raw::c_char;
 use std::ptr::copy_nonoverlapping;
@@ -29,6 +31,7 @@ pub type RffiAudioDeviceModule = u32;
 pub unsafe fn Rust_createPeerConnectionFactory(
     _use_new_audio_device_module: bool,
     _use_injectable_network: bool,
+    _use_injectable_audio: bool,
 ) -> webrtc::ptr::OwnedRc<RffiPeerConnectionFactoryOwner> {
     info!("Rust_createPeerConnectionFactory()");
     webrtc::ptr::OwnedRc::from_ptr(&FAKE_PEER_CONNECTION_FACTORY)
@@ -62,12 +65,38 @@ pub unsafe fn Rust_createAudioTrack(
     webrtc::ptr::OwnedRc::from_ptr(&FAKE_AUDIO_TRACK)
 }
 
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+pub unsafe fn Rust_pushOutgoingAudioSamples(
+    _factory: webrtc::ptr::BorrowedRc<RffiPeerConnectionFactoryOwner>,
+    _sample_rate: u32,
+    _channels: usize,
+    _samples: webrtc::ptr::Borrowed<i16>,
+    _samples_size: usize,
+) {
+    info!("Rust_pushOutgoingAudioSamples()");
+}
+
 #[allow(non_snake_case, clippy::missing_safety_doc)]
 pub unsafe fn Rust_createVideoSource() -> webrtc::ptr::OwnedRc<RffiVideoSource> {
     info!("Rust_createVideoSource()");
     webrtc::ptr::OwnedRc::from_ptr(&FAKE_VIDEO_SOURCE)
 }
 
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+pub unsafe fn Rust_createProcessedVideoSource(
+    _input_track: webrtc::ptr::BorrowedRc<RffiVideoTrack>,
+    processor: webrtc::ptr::Owned<std::ffi::c_void>,
+    callbacks: webrtc::ptr::Borrowed<std::ffi::c_void>,
+) -> webrtc::ptr::OwnedRc<RffiVideoSource> {
+    info!("Rust_createProcessedVideoSource()");
+    // No frames come from the fake input track, so the processor is done with right away.
+    let callbacks = callbacks.as_ptr() as *const VideoFrameProcessorCallbacks;
+    ((*callbacks).deleteProcessor)(webrtc::ptr::Owned::from_ptr(
+        processor.as_ptr() as *const VideoFrameProcessorChain
+    ));
+    webrtc::ptr::OwnedRc::from_ptr(&FAKE_VIDEO_SOURCE)
+}
+
 #[allow(non_snake_case, clippy::missing_safety_doc)]
 pub unsafe fn Rust_createVideoTrack(
     _factory: webrtc::ptr::BorrowedRc<RffiPeerConnectionFactoryOwner>,
@@ -8,8 +8,12 @@
 use crate::webrtc;
 
 /// # Safety
-pub fn dec<T: webrtc::ptr::RefCounted>(_rc: webrtc::ptr::OwnedRc<T>) {
+pub fn dec<T: webrtc::ptr::RefCounted>(rc: webrtc::ptr::OwnedRc<T>) {
     info!("ref_count::dec()");
+    if !rc.is_null() {
+        // Safe because the OwnedRc owns a ref count.
+        unsafe { T::sim_dec_ref(rc.as_ptr()) };
+    }
 }
 
 /// # Safety
@@ -17,5 +21,8 @@ pub unsafe fn inc<T: webrtc::ptr::RefCounted>(
     rc: webrtc::ptr::BorrowedRc<T>,
 ) -> webrtc::ptr::OwnedRc<T> {
     info!("ref_count::inc()");
+    if !rc.is_null() {
+        T::sim_inc_ref(rc.as_ptr());
+    }
     webrtc::ptr::OwnedRc::from_ptr(rc.as_ptr())
 }
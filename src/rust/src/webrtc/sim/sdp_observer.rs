@@ -18,11 +18,47 @@ use crate::webrtc::sdp_observer::{
 };
 
 /// Simulation type for SessionDescription.
-pub type RffiSessionDescription = &'static str;
+pub struct RffiSessionDescription {
+    sdp: &'static str,
+    /// For the remote description of a group call, the remote devices
+    /// which get receivers when it's applied.
+    pub remote_demux_ids: Vec<u32>,
+    /// Whether those remote devices also get video receivers.
+    pub receive_video: bool,
+}
+
+const FAKE_SDP: &str = "FAKE SDP";
+const FAKE_SDP_OFFER: &str = "FAKE SDP OFFER";
+const FAKE_SDP_ANSWER: &str = "FAKE SDP ANSWER";
+
+fn new_session_description(
+    sdp: &'static str,
+    remote_demux_ids: Vec<u32>,
+    receive_video: bool,
+) -> webrtc::ptr::Owned<RffiSessionDescription> {
+    let session_description = Box::new(RffiSessionDescription {
+        sdp,
+        remote_demux_ids,
+        receive_video,
+    });
+    unsafe { webrtc::ptr::Owned::from_ptr(Box::into_raw(session_description)) }
+}
 
-static mut FAKE_SDP: &str = "FAKE SDP";
-static mut FAKE_SDP_OFFER: &str = "FAKE SDP OFFER";
-static mut FAKE_SDP_ANSWER: &str = "FAKE SDP ANSWER";
+/// Frees a session description created by the simulation.
+///
+/// # Safety
+///
+/// The session description must not be used again.
+pub unsafe fn delete_session_description(
+    session_description: webrtc::ptr::Owned<RffiSessionDescription>,
+) -> Option<RffiSessionDescription> {
+    if session_description.is_null() {
+        return None;
+    }
+    Some(*Box::from_raw(
+        session_description.as_ptr() as *mut RffiSessionDescription
+    ))
+}
 
 /// Simulation type for webrtc::rffi::CreateSessionDescriptionObserverRffi
 pub type RffiCreateSessionDescriptionObserver = u32;
@@ -63,7 +99,7 @@ pub unsafe fn Rust_createCreateSessionDescriptionObserver(
         webrtc::ptr::Borrowed::from_ptr(
             csd_observer.as_ptr() as *mut CreateSessionDescriptionObserver
         ),
-        webrtc::ptr::Owned::from_ptr(&FAKE_SDP),
+        new_session_description(FAKE_SDP, vec![], true),
     );
 
     webrtc::ptr::OwnedRc::from_ptr(&FAKE_CSD_OBSERVER)
@@ -74,7 +110,7 @@ pub unsafe fn Rust_toSdp(
     rffi: webrtc::ptr::Borrowed<RffiSessionDescription>,
 ) -> webrtc::ptr::Owned<c_char> {
     info!("Rust_toSdp(): ");
-    match CString::new(*rffi.as_ptr()) {
+    match CString::new((*rffi.as_ptr()).sdp) {
         Ok(cstr) => webrtc::ptr::Owned::from_ptr(strdup(cstr.as_ptr())),
         Err(_) => webrtc::ptr::Owned::null(),
     }
@@ -85,7 +121,7 @@ pub unsafe fn Rust_offerFromSdp(
     _sdp: webrtc::ptr::Borrowed<c_char>,
 ) -> webrtc::ptr::Owned<RffiSessionDescription> {
     info!("Rust_offerFromSdp(): ");
-    webrtc::ptr::Owned::from_ptr(&FAKE_SDP_ANSWER)
+    new_session_description(FAKE_SDP_ANSWER, vec![], true)
 }
 
 #[allow(non_snake_case, clippy::missing_safety_doc)]
@@ -93,7 +129,7 @@ pub unsafe fn Rust_answerFromSdp(
     _sdp: webrtc::ptr::Borrowed<c_char>,
 ) -> webrtc::ptr::Owned<RffiSessionDescription> {
     info!("Rust_answerFromSdp(): ");
-    webrtc::ptr::Owned::from_ptr(&FAKE_SDP_OFFER)
+    new_session_description(FAKE_SDP_OFFER, vec![], true)
 }
 
 #[allow(non_snake_case, clippy::missing_safety_doc)]
@@ -134,9 +170,9 @@ pub unsafe fn Rust_sessionDescriptionFromV4(
 ) -> webrtc::ptr::Owned<RffiSessionDescription> {
     info!("Rust_sessionDescriptionFromV4(): ");
     if offer {
-        webrtc::ptr::Owned::from_ptr(&FAKE_SDP_OFFER)
+        new_session_description(FAKE_SDP_OFFER, vec![], true)
     } else {
-        webrtc::ptr::Owned::from_ptr(&FAKE_SDP_ANSWER)
+        new_session_description(FAKE_SDP_ANSWER, vec![], true)
     }
 }
 
@@ -148,7 +184,7 @@ pub unsafe fn Rust_localDescriptionForGroupCall(
     _demux_id: u32,
 ) -> webrtc::ptr::Owned<RffiSessionDescription> {
     info!("Rust_localDescriptionForGroupCall(): ");
-    webrtc::ptr::Owned::from_ptr(&FAKE_SDP_OFFER)
+    new_session_description(FAKE_SDP_OFFER, vec![], true)
 }
 
 #[allow(non_snake_case, clippy::missing_safety_doc)]
@@ -156,14 +192,21 @@ pub unsafe fn Rust_remoteDescriptionForGroupCall(
     _ice_ufrag: webrtc::ptr::Borrowed<c_char>,
     _ice_pwd: webrtc::ptr::Borrowed<c_char>,
     _server_srtp_key: RffiSrtpKey,
-    _demux_ids_data: webrtc::ptr::Borrowed<u32>,
-    _demux_ids_len: size_t,
+    demux_ids_data: webrtc::ptr::Borrowed<u32>,
+    demux_ids_len: size_t,
+    receive_video: bool,
 ) -> webrtc::ptr::Owned<RffiSessionDescription> {
     info!("Rust_remoteDescriptionForGroupCall(): ");
-    webrtc::ptr::Owned::from_ptr(&FAKE_SDP_ANSWER)
+    let demux_ids = if demux_ids_data.is_null() {
+        vec![]
+    } else {
+        std::slice::from_raw_parts(demux_ids_data.as_ptr(), demux_ids_len).to_vec()
+    };
+    new_session_description(FAKE_SDP_ANSWER, demux_ids, receive_video)
 }
 
 #[allow(non_snake_case, clippy::missing_safety_doc)]
-pub unsafe fn Rust_deleteSessionDescription(_sdi: webrtc::ptr::Owned<RffiSessionDescription>) {
+pub unsafe fn Rust_deleteSessionDescription(sdi: webrtc::ptr::Owned<RffiSessionDescription>) {
     info!("Rust_deleteSessionDescription(): ");
+    delete_session_description(sdi);
 }
@@ -5,9 +5,9 @@
 
 //! WebRTC Create Session Description
 
-use std::{borrow::Cow, slice};
+use std::{borrow::Cow, slice, sync::Mutex};
 
-use crate::webrtc;
+use crate::webrtc::{self, video_frame_processor::VideoFrameProcessorStats};
 
 #[cfg(not(feature = "sim"))]
 use crate::webrtc::ffi::stats_observer as stats;
@@ -23,6 +23,8 @@ pub use crate::webrtc::sim::stats_observer::RffiStatsObserver;
 #[derive(Debug)]
 pub struct StatsObserver {
     rffi: webrtc::Arc<RffiStatsObserver>,
+    // Kept for audio adaptation until taken.
+    latest_audio_sender_statistics: Mutex<Option<AudioSenderStatistics>>,
 }
 
 impl StatsObserver {
@@ -102,9 +104,20 @@ impl StatsObserver {
                 frame_width,\
                 frame_height"
         );
+        info!(
+            "ringrtc_stats!,\
+                video,\
+                processor,\
+                name,\
+                frames_processed,\
+                frames_dropped,\
+                total_processing_time,\
+                max_processing_time"
+        );
 
         Self {
             rffi: webrtc::Arc::null(),
+            latest_audio_sender_statistics: Mutex::new(None),
         }
     }
 
@@ -146,6 +159,12 @@ impl StatsObserver {
                     audio_sender.echo_likelihood,
                 );
             }
+            if let (Some(audio_sender), Ok(mut latest)) = (
+                audio_senders.first(),
+                self.latest_audio_sender_statistics.lock(),
+            ) {
+                *latest = Some(*audio_sender);
+            }
         }
 
         if media_statistics.video_sender_statistics_size > 0 {
@@ -240,6 +259,30 @@ impl StatsObserver {
         }
     }
 
+    /// Returns the statistics of the (first) audio sender if any have
+    /// arrived since the last call.
+    pub fn take_audio_sender_statistics(&self) -> Option<AudioSenderStatistics> {
+        self.latest_audio_sender_statistics
+            .lock()
+            .ok()
+            .and_then(|mut latest| latest.take())
+    }
+
+    /// Logs the outgoing video frame processors alongside the WebRTC stats,
+    /// since they aren't part of what WebRTC reports.
+    pub fn log_video_frame_processor_stats(&self, stats: &[VideoFrameProcessorStats]) {
+        for processor in stats {
+            info!(
+                "ringrtc_stats!,video,processor,{},{},{},{:.3},{:.3}",
+                processor.name,
+                processor.frames_processed,
+                processor.frames_dropped,
+                processor.total_processing_time.as_secs_f64(),
+                processor.max_processing_time.as_secs_f64(),
+            );
+        }
+    }
+
     /// Set the RFFI observer object.
     pub fn set_rffi(&mut self, rffi: webrtc::Arc<RffiStatsObserver>) {
         self.rffi = rffi
@@ -252,7 +295,7 @@ impl StatsObserver {
 }
 
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct AudioSenderStatistics {
     pub ssrc: u32,
     pub packets_sent: u32,
@@ -0,0 +1,996 @@
+//
+// Copyright 2022 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Synthetic media for testing calls end to end, and analyzers to
+//! recognize it on the other side.
+//!
+//! Test video frames carry two machine-readable codes across the top:
+//! the frame number and the (wall clock) time they were generated.
+//! They are big blocks of black and white, so they survive scaling
+//! and lossy encoding.  Test audio is a sine, chirp, or DTMF tones,
+//! which can be detected with the Goertzel algorithm.  A MediaQualityMeter
+//! turns what the analyzers saw into latency, A/V sync, and freeze reports.
+
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    f64::consts::PI,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::webrtc::{
+    media::{AudioFrame, AudioSink, VideoFrame, VideoSink},
+    video_frame_buffer::I420Buffer,
+};
+
+// A code is a white start block, 32 bits (MSB first), and a black stop block.
+const CODE_BLOCKS: u32 = 34;
+// Each code takes up this fraction of the frame height.
+const CODE_HEIGHT_DIVISOR: u32 = 8;
+const BLACK: u8 = 16;
+const WHITE: u8 = 235;
+
+/// The time used for timestamps in test video frames: milliseconds since
+/// the UNIX epoch, wrapped to 32 bits.  Both sides must share a clock.
+pub fn timestamp_ms() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u32
+}
+
+/// How long ago the given timestamp_ms() was.
+pub fn time_since(timestamp_ms: u32) -> Option<Duration> {
+    let elapsed = self::timestamp_ms().wrapping_sub(timestamp_ms);
+    // Anything "older" than this was probably from the future.
+    if elapsed < u32::MAX / 2 {
+        Some(Duration::from_millis(elapsed as u64))
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestPattern {
+    /// The 75% SMPTE color bars.
+    ColorBars,
+    /// A diagonal gradient that moves every frame.
+    MovingGradient,
+}
+
+#[derive(Clone, Debug)]
+pub struct TestVideoConfig {
+    pub width: u32,
+    pub height: u32,
+    pub pattern: TestPattern,
+    /// Encode the frame number at the top.  Needs at least 68x16.
+    pub frame_counter: bool,
+    /// Encode timestamp_ms() below the frame number.  Needs at least 68x16.
+    pub timestamp: bool,
+}
+
+impl Default for TestVideoConfig {
+    fn default() -> Self {
+        Self {
+            width: 640,
+            height: 360,
+            pattern: TestPattern::ColorBars,
+            frame_counter: true,
+            timestamp: true,
+        }
+    }
+}
+
+pub struct TestVideoGenerator {
+    config: TestVideoConfig,
+    next_frame_number: u32,
+    color_bars: Option<I420Buffer>,
+}
+
+impl TestVideoGenerator {
+    pub fn new(config: TestVideoConfig) -> Self {
+        let color_bars = if config.pattern == TestPattern::ColorBars {
+            Some(color_bars(config.width, config.height))
+        } else {
+            None
+        };
+        Self {
+            config,
+            next_frame_number: 0,
+            color_bars,
+        }
+    }
+
+    /// The next frame, numbered and stamped with the current time.
+    pub fn next_frame(&mut self) -> VideoFrame {
+        VideoFrame::from_i420_buffer(&self.next_buffer())
+    }
+
+    pub fn next_buffer(&mut self) -> I420Buffer {
+        let frame_number = self.next_frame_number;
+        self.next_frame_number = self.next_frame_number.wrapping_add(1);
+        self.buffer(frame_number, timestamp_ms())
+    }
+
+    pub fn buffer(&self, frame_number: u32, timestamp_ms: u32) -> I420Buffer {
+        let (width, height) = (self.config.width, self.config.height);
+        let mut buffer = match &self.color_bars {
+            Some(color_bars) => color_bars.clone(),
+            None => moving_gradient(width, height, frame_number),
+        };
+        if self.config.frame_counter {
+            draw_code(&mut buffer, 0, frame_number);
+        }
+        if self.config.timestamp {
+            draw_code(&mut buffer, 1, timestamp_ms);
+        }
+        buffer
+    }
+}
+
+fn color_bars(width: u32, height: u32) -> I420Buffer {
+    const BARS: [[u8; 4]; 7] = [
+        [191, 191, 191, 255], // white
+        [191, 191, 0, 255],   // yellow
+        [0, 191, 191, 255],   // cyan
+        [0, 191, 0, 255],     // green
+        [191, 0, 191, 255],   // magenta
+        [191, 0, 0, 255],     // red
+        [0, 0, 191, 255],     // blue
+    ];
+    let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+    for _y in 0..height {
+        for x in 0..width {
+            let bar = (x as usize) * BARS.len() / (width as usize);
+            rgba.extend_from_slice(&BARS[bar]);
+        }
+    }
+    I420Buffer::from_rgba(width, height, &rgba)
+}
+
+fn moving_gradient(width: u32, height: u32, frame_number: u32) -> I420Buffer {
+    let mut buffer = I420Buffer::new(width, height);
+    let (y_plane, u_plane, v_plane) = buffer.planes_mut();
+    for (y, row) in y_plane.chunks_mut(width.max(1) as usize).enumerate() {
+        for (x, luma) in row.iter_mut().enumerate() {
+            *luma = (x as u32)
+                .wrapping_add(y as u32)
+                .wrapping_add(frame_number.wrapping_mul(4)) as u8;
+        }
+    }
+    u_plane.fill(96);
+    v_plane.fill(160);
+    buffer
+}
+
+// Which rows of the frame the code at the given index covers.
+fn code_rows(height: u32, index: u32) -> std::ops::Range<u32> {
+    let code_height = height / CODE_HEIGHT_DIVISOR;
+    (index * code_height)..((index + 1) * code_height)
+}
+
+fn code_columns(width: u32, block: u32) -> std::ops::Range<u32> {
+    (block * width / CODE_BLOCKS)..((block + 1) * width / CODE_BLOCKS)
+}
+
+fn code_is_possible(width: u32, height: u32) -> bool {
+    width >= CODE_BLOCKS * 2 && height >= CODE_HEIGHT_DIVISOR * 2
+}
+
+fn draw_code(buffer: &mut I420Buffer, index: u32, value: u32) {
+    let (width, height) = (buffer.width(), buffer.height());
+    if !code_is_possible(width, height) {
+        return;
+    }
+    let rows = code_rows(height, index);
+    let chroma_width = buffer.chroma_width();
+    let (y_plane, u_plane, v_plane) = buffer.planes_mut();
+    for block in 0..CODE_BLOCKS {
+        let bit = match block {
+            0 => true,
+            _ if block == CODE_BLOCKS - 1 => false,
+            _ => (value >> (32 - block)) & 1 == 1,
+        };
+        let luma = if bit { WHITE } else { BLACK };
+        for y in rows.clone() {
+            for x in code_columns(width, block) {
+                y_plane[(y * width + x) as usize] = luma;
+            }
+        }
+    }
+    // No color in the code.
+    for y in (rows.start / 2)..(rows.end / 2) {
+        let row = (y * chroma_width) as usize..((y + 1) * chroma_width) as usize;
+        u_plane[row.clone()].fill(128);
+        v_plane[row].fill(128);
+    }
+}
+
+fn read_code(buffer: &I420Buffer, index: u32) -> Option<u32> {
+    let (width, height) = (buffer.width(), buffer.height());
+    if !code_is_possible(width, height) {
+        return None;
+    }
+    let rows = code_rows(height, index);
+    let y_plane = buffer.planes().0;
+    // Average the middle of each block, away from edges blurred by encoding.
+    let block_is_white = |block: u32| -> bool {
+        let columns = code_columns(width, block);
+        let (x_margin, y_margin) = (columns.len() as u32 / 4, rows.len() as u32 / 4);
+        let (mut sum, mut count) = (0u32, 0u32);
+        for y in (rows.start + y_margin)..(rows.end - y_margin) {
+            for x in (columns.start + x_margin)..(columns.end - x_margin) {
+                sum += y_plane[(y * width + x) as usize] as u32;
+                count += 1;
+            }
+        }
+        count > 0 && sum / count >= (BLACK as u32 + WHITE as u32) / 2
+    };
+    if !block_is_white(0) || block_is_white(CODE_BLOCKS - 1) {
+        return None;
+    }
+    Some((1..(CODE_BLOCKS - 1)).fold(0u32, |value, block| {
+        (value << 1) | (block_is_white(block) as u32)
+    }))
+}
+
+/// What could be read from a test video frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TestFrameInfo {
+    pub frame_number: Option<u32>,
+    pub timestamp_ms: Option<u32>,
+}
+
+/// Reads the codes from a frame made by a TestVideoGenerator (possibly
+/// scaled).  Returns None if it doesn't look like a test frame.
+pub fn analyze_test_frame(buffer: &I420Buffer) -> Option<TestFrameInfo> {
+    let frame_number = read_code(buffer, 0);
+    let timestamp_ms = read_code(buffer, 1);
+    if frame_number.is_none() && timestamp_ms.is_none() {
+        return None;
+    }
+    Some(TestFrameInfo {
+        frame_number,
+        timestamp_ms,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReceivedTestFrame {
+    pub track_id: u32,
+    pub frame_number: Option<u32>,
+    /// From the time in the frame until it was analyzed.
+    pub latency: Option<Duration>,
+    pub received: Instant,
+}
+
+/// A VideoSink that reads the codes from received test frames.
+#[derive(Clone, Default)]
+pub struct TestVideoAnalyzer {
+    received: Arc<Mutex<Vec<ReceivedTestFrame>>>,
+}
+
+impl TestVideoAnalyzer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Everything received since the last time this was called.
+    pub fn take_received(&self) -> Vec<ReceivedTestFrame> {
+        match self.received.lock() {
+            Ok(mut received) => std::mem::take(&mut *received),
+            Err(_) => vec![],
+        }
+    }
+}
+
+impl VideoSink for TestVideoAnalyzer {
+    fn on_video_frame(&self, track_id: u32, frame: VideoFrame) {
+        let received = Instant::now();
+        let info = match analyze_test_frame(&frame.apply_rotation().to_i420_buffer()) {
+            Some(info) => info,
+            None => return,
+        };
+        if let Ok(mut frames) = self.received.lock() {
+            frames.push(ReceivedTestFrame {
+                track_id,
+                frame_number: info.frame_number,
+                latency: info.timestamp_ms.and_then(time_since),
+                received,
+            });
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn VideoSink> {
+        Box::new(self.clone())
+    }
+}
+
+const DTMF_ROW_FREQUENCIES: [f64; 4] = [697.0, 770.0, 852.0, 941.0];
+const DTMF_COLUMN_FREQUENCIES: [f64; 4] = [1209.0, 1336.0, 1477.0, 1633.0];
+const DTMF_DIGITS: [[char; 4]; 4] = [
+    ['1', '2', '3', 'A'],
+    ['4', '5', '6', 'B'],
+    ['7', '8', '9', 'C'],
+    ['*', '0', '#', 'D'],
+];
+
+fn dtmf_frequencies(digit: char) -> Option<(f64, f64)> {
+    for (row, digits) in DTMF_DIGITS.iter().enumerate() {
+        if let Some(column) = digits.iter().position(|d| *d == digit) {
+            return Some((DTMF_ROW_FREQUENCIES[row], DTMF_COLUMN_FREQUENCIES[column]));
+        }
+    }
+    None
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TestTone {
+    Sine {
+        frequency: f64,
+    },
+    /// Sweeps linearly from the start frequency to the end over the period,
+    /// then starts over.
+    Chirp {
+        start_frequency: f64,
+        end_frequency: f64,
+        period: Duration,
+    },
+    /// Plays each digit for the tone duration followed by the gap duration
+    /// of silence, then repeats.  Unknown digits are silent.
+    Dtmf {
+        digits: String,
+        tone_duration: Duration,
+        gap_duration: Duration,
+    },
+}
+
+pub struct TestAudioGenerator {
+    tone: TestTone,
+    sample_rate: u32,
+    /// Between 0.0 and 1.0 (full scale).
+    amplitude: f64,
+    samples_generated: u64,
+    phases: [f64; 2],
+}
+
+impl TestAudioGenerator {
+    pub fn new(tone: TestTone, sample_rate: u32, amplitude: f64) -> Self {
+        Self {
+            tone,
+            sample_rate,
+            amplitude: amplitude.clamp(0.0, 1.0),
+            samples_generated: 0,
+            phases: [0.0, 0.0],
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The DTMF digit (if any) playing at the next sample.
+    pub fn current_dtmf_digit(&self) -> Option<char> {
+        self.dtmf_digit_at(self.samples_generated)
+    }
+
+    fn dtmf_digit_at(&self, sample: u64) -> Option<char> {
+        if let TestTone::Dtmf {
+            digits,
+            tone_duration,
+            gap_duration,
+        } = &self.tone
+        {
+            let rate = self.sample_rate as u128;
+            let tone_samples = (tone_duration.as_millis() * rate / 1000) as u64;
+            let gap_samples = (gap_duration.as_millis() * rate / 1000) as u64;
+            let digit_samples = tone_samples + gap_samples;
+            let count = digits.chars().count() as u64;
+            if digit_samples == 0 || count == 0 {
+                return None;
+            }
+            let index = (sample / digit_samples) % count;
+            if sample % digit_samples < tone_samples {
+                return digits.chars().nth(index as usize);
+            }
+        }
+        None
+    }
+
+    fn frequencies_at(&self, sample: u64) -> [Option<f64>; 2] {
+        match &self.tone {
+            TestTone::Sine { frequency } => [Some(*frequency), None],
+            TestTone::Chirp {
+                start_frequency,
+                end_frequency,
+                period,
+            } => {
+                let period_samples = (period.as_secs_f64() * self.sample_rate as f64) as u64;
+                let progress = if period_samples == 0 {
+                    0.0
+                } else {
+                    (sample % period_samples) as f64 / period_samples as f64
+                };
+                [
+                    Some(start_frequency + (end_frequency - start_frequency) * progress),
+                    None,
+                ]
+            }
+            TestTone::Dtmf { .. } => match self.dtmf_digit_at(sample).and_then(dtmf_frequencies) {
+                Some((row, column)) => [Some(row), Some(column)],
+                None => [None, None],
+            },
+        }
+    }
+
+    /// Fills in the next mono samples.
+    pub fn fill(&mut self, samples: &mut [i16]) {
+        for sample in samples.iter_mut() {
+            let frequencies = self.frequencies_at(self.samples_generated);
+            let tones = frequencies.iter().filter(|f| f.is_some()).count().max(1) as f64;
+            let mut value = 0.0;
+            for (phase, frequency) in self.phases.iter_mut().zip(frequencies.iter()) {
+                match frequency {
+                    Some(frequency) => {
+                        value += phase.sin() / tones;
+                        *phase =
+                            (*phase + 2.0 * PI * frequency / self.sample_rate as f64) % (2.0 * PI);
+                    }
+                    None => *phase = 0.0,
+                }
+            }
+            *sample = (value * self.amplitude * i16::MAX as f64) as i16;
+            self.samples_generated += 1;
+        }
+    }
+
+    /// The next 10ms of mono samples, which is how WebRTC likes them.
+    pub fn next_10ms(&mut self) -> Vec<i16> {
+        let mut samples = vec![0; (self.sample_rate / 100) as usize];
+        self.fill(&mut samples);
+        samples
+    }
+}
+
+/// The amplitude (between 0.0 and 1.0 of full scale) of the given frequency
+/// in the samples, using the Goertzel algorithm.
+pub fn tone_level(samples: &[i16], sample_rate: u32, frequency: f64) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let coefficient = 2.0 * (2.0 * PI * frequency / sample_rate as f64).cos();
+    let (mut previous, mut before_previous) = (0.0, 0.0);
+    for sample in samples {
+        let current = (*sample as f64 / i16::MAX as f64) + coefficient * previous - before_previous;
+        before_previous = previous;
+        previous = current;
+    }
+    let power = previous * previous + before_previous * before_previous
+        - coefficient * previous * before_previous;
+    power.max(0.0).sqrt() / (samples.len() as f64 / 2.0)
+}
+
+/// The DTMF digit in the samples, if there's a clear one.
+/// At least 20ms of samples are needed to tell the frequencies apart.
+pub fn detect_dtmf(samples: &[i16], sample_rate: u32) -> Option<char> {
+    const MIN_LEVEL: f64 = 0.02;
+    let strongest = |frequencies: &[f64; 4]| -> Option<usize> {
+        let levels: Vec<f64> = frequencies
+            .iter()
+            .map(|frequency| tone_level(samples, sample_rate, *frequency))
+            .collect();
+        let (index, level) = levels
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        let clearly_strongest = levels
+            .iter()
+            .enumerate()
+            .all(|(i, other)| i == index || *other * 2.0 < level);
+        if level >= MIN_LEVEL && clearly_strongest {
+            Some(index)
+        } else {
+            None
+        }
+    };
+    let row = strongest(&DTMF_ROW_FREQUENCIES)?;
+    let column = strongest(&DTMF_COLUMN_FREQUENCIES)?;
+    Some(DTMF_DIGITS[row][column])
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DetectedDtmf {
+    pub track_id: u32,
+    pub digit: char,
+    pub detected: Instant,
+}
+
+// How much audio to collect before looking for DTMF.
+const DTMF_WINDOW: Duration = Duration::from_millis(30);
+
+#[derive(Default)]
+struct TrackAudioAnalysis {
+    frames_received: u64,
+    window: Vec<i16>,
+    last_digit: Option<char>,
+}
+
+#[derive(Default)]
+struct TestAudioAnalysis {
+    tracks: HashMap<u32, TrackAudioAnalysis>,
+    detected: Vec<DetectedDtmf>,
+}
+
+/// An AudioSink that counts received audio and detects DTMF digits in it.
+/// Each digit is reported once when it starts.
+#[derive(Clone, Default)]
+pub struct TestAudioAnalyzer {
+    analysis: Arc<Mutex<TestAudioAnalysis>>,
+}
+
+impl TestAudioAnalyzer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn frames_received(&self, track_id: u32) -> u64 {
+        match self.analysis.lock() {
+            Ok(analysis) => analysis
+                .tracks
+                .get(&track_id)
+                .map(|track| track.frames_received)
+                .unwrap_or(0),
+            Err(_) => 0,
+        }
+    }
+
+    /// Every digit detected since the last time this was called.
+    pub fn take_detected(&self) -> Vec<DetectedDtmf> {
+        match self.analysis.lock() {
+            Ok(mut analysis) => std::mem::take(&mut analysis.detected),
+            Err(_) => vec![],
+        }
+    }
+}
+
+impl AudioSink for TestAudioAnalyzer {
+    fn on_audio_frame(&self, track_id: u32, frame: AudioFrame) {
+        let detected = Instant::now();
+        let mut analysis = match self.analysis.lock() {
+            Ok(analysis) => analysis,
+            Err(_) => return,
+        };
+        let analysis = &mut *analysis;
+        let track = analysis.tracks.entry(track_id).or_default();
+        track.frames_received += 1;
+
+        // Only the first channel is analyzed.
+        let channels = frame.channels.max(1) as usize;
+        track
+            .window
+            .extend(frame.samples.iter().step_by(channels).copied());
+        let window_size = (frame.sample_rate as u128 * DTMF_WINDOW.as_millis() / 1000) as usize;
+        if track.window.len() < window_size {
+            return;
+        }
+        let digit = detect_dtmf(&track.window, frame.sample_rate);
+        track.window.clear();
+        if digit != track.last_digit {
+            if let Some(digit) = digit {
+                analysis.detected.push(DetectedDtmf {
+                    track_id,
+                    digit,
+                    detected,
+                });
+            }
+            track.last_digit = digit;
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn AudioSink> {
+        Box::new(self.clone())
+    }
+}
+
+/// Latencies over a reporting interval, in milliseconds.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub count: usize,
+    pub min_ms: f64,
+    pub median_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencySummary {
+    fn from_latencies(latencies: &mut [Duration]) -> Option<Self> {
+        if latencies.is_empty() {
+            return None;
+        }
+        latencies.sort();
+        let last = latencies.len() - 1;
+        let percentile = |fraction: f64| {
+            let index = (last as f64 * fraction).round() as usize;
+            latencies[index].as_secs_f64() * 1000.0
+        };
+        Some(Self {
+            count: latencies.len(),
+            min_ms: percentile(0.0),
+            median_ms: percentile(0.5),
+            p95_ms: percentile(0.95),
+            max_ms: percentile(1.0),
+        })
+    }
+}
+
+/// What a MediaQualityMeter measured over one reporting interval.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MediaQualityReport {
+    /// From when the meter was created until the end of the interval.
+    pub elapsed_ms: u64,
+    pub interval_ms: u64,
+    pub video_frames_received: usize,
+    /// Glass-to-glass, from the timestamp in each frame.
+    pub video_latency: Option<LatencySummary>,
+    pub audio_markers_received: usize,
+    /// From when each DTMF marker started playing until it was detected.
+    pub audio_latency: Option<LatencySummary>,
+    /// Median audio latency minus median video latency, so positive
+    /// values mean the audio is behind the video.
+    pub av_skew_ms: Option<f64>,
+    pub freeze_count: u32,
+    pub freeze_duration_ms: u64,
+}
+
+// A gap between video frames is a freeze if it is longer than this many
+// times the average frame interval, and at least FREEZE_MIN_EXTRA_DELAY
+// longer.  This is the same definition the WebRTC stats use.
+const FREEZE_INTERVAL_MULTIPLIER: u32 = 3;
+const FREEZE_MIN_EXTRA_DELAY: Duration = Duration::from_millis(150);
+// How many frame intervals go into the average.
+const FRAME_INTERVAL_HISTORY: usize = 30;
+// Markers are matched by digit, so only a few are needed.
+const MAX_PENDING_AUDIO_MARKERS: usize = 64;
+
+/// Turns what the TestVideoAnalyzer and TestAudioAnalyzer saw into reports
+/// of latency, A/V sync, and freezes.  The video carries its own timestamps,
+/// but audio markers are DTMF digits, so the sender must say when it started
+/// playing each one.  Both sides must be in the same process (or at least
+/// share a clock).
+pub struct MediaQualityMeter {
+    created: Instant,
+    interval_started: Instant,
+    audio_markers_sent: VecDeque<(char, Instant)>,
+
+    video_frames_received: usize,
+    video_latencies: Vec<Duration>,
+    audio_latencies: Vec<Duration>,
+    freeze_count: u32,
+    freeze_duration: Duration,
+
+    last_video_frame_received: Option<Instant>,
+    frame_intervals: VecDeque<Duration>,
+}
+
+impl Default for MediaQualityMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MediaQualityMeter {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            created: now,
+            interval_started: now,
+            audio_markers_sent: VecDeque::new(),
+
+            video_frames_received: 0,
+            video_latencies: Vec::new(),
+            audio_latencies: Vec::new(),
+            freeze_count: 0,
+            freeze_duration: Duration::ZERO,
+
+            last_video_frame_received: None,
+            frame_intervals: VecDeque::new(),
+        }
+    }
+
+    /// The sender started playing the given DTMF digit.
+    pub fn audio_marker_sent(&mut self, digit: char, sent: Instant) {
+        if self.audio_markers_sent.len() >= MAX_PENDING_AUDIO_MARKERS {
+            self.audio_markers_sent.pop_front();
+        }
+        self.audio_markers_sent.push_back((digit, sent));
+    }
+
+    pub fn audio_marker_received(&mut self, detected: &DetectedDtmf) {
+        // The latest matching marker sent before it was detected.
+        let index = self
+            .audio_markers_sent
+            .iter()
+            .rposition(|(digit, sent)| *digit == detected.digit && *sent <= detected.detected);
+        if let Some((_, sent)) = index.and_then(|index| self.audio_markers_sent.remove(index)) {
+            self.audio_latencies.push(detected.detected - sent);
+        }
+    }
+
+    /// Frames must be given in the order they were received.
+    pub fn video_frame_received(&mut self, frame: &ReceivedTestFrame) {
+        self.video_frames_received += 1;
+        if let Some(latency) = frame.latency {
+            self.video_latencies.push(latency);
+        }
+
+        if let Some(last_received) = self.last_video_frame_received {
+            let interval = frame.received.saturating_duration_since(last_received);
+            if self.is_freeze(interval) {
+                self.freeze_count += 1;
+                self.freeze_duration += interval;
+            } else {
+                if self.frame_intervals.len() >= FRAME_INTERVAL_HISTORY {
+                    self.frame_intervals.pop_front();
+                }
+                self.frame_intervals.push_back(interval);
+            }
+        }
+        self.last_video_frame_received = Some(frame.received);
+    }
+
+    fn is_freeze(&self, interval: Duration) -> bool {
+        if self.frame_intervals.is_empty() {
+            return false;
+        }
+        let average =
+            self.frame_intervals.iter().sum::<Duration>() / self.frame_intervals.len() as u32;
+        interval > average * FREEZE_INTERVAL_MULTIPLIER
+            && interval > average + FREEZE_MIN_EXTRA_DELAY
+    }
+
+    /// Reports everything since the last report and starts a new interval.
+    pub fn take_report(&mut self, now: Instant) -> MediaQualityReport {
+        let video_latency = LatencySummary::from_latencies(&mut self.video_latencies);
+        let audio_latency = LatencySummary::from_latencies(&mut self.audio_latencies);
+        let av_skew_ms = match (&video_latency, &audio_latency) {
+            (Some(video), Some(audio)) => Some(audio.median_ms - video.median_ms),
+            _ => None,
+        };
+        let report = MediaQualityReport {
+            elapsed_ms: now.saturating_duration_since(self.created).as_millis() as u64,
+            interval_ms: now
+                .saturating_duration_since(self.interval_started)
+                .as_millis() as u64,
+            video_frames_received: self.video_frames_received,
+            video_latency,
+            audio_markers_received: self.audio_latencies.len(),
+            audio_latency,
+            av_skew_ms,
+            freeze_count: self.freeze_count,
+            freeze_duration_ms: self.freeze_duration.as_millis() as u64,
+        };
+
+        self.interval_started = now;
+        self.video_frames_received = 0;
+        self.video_latencies.clear();
+        self.audio_latencies.clear();
+        self.freeze_count = 0;
+        self.freeze_duration = Duration::ZERO;
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_survive_scaling() {
+        let generator = TestVideoGenerator::new(TestVideoConfig::default());
+        let buffer = generator.buffer(0x12345678, 0xdeadbeef);
+        let expected = TestFrameInfo {
+            frame_number: Some(0x12345678),
+            timestamp_ms: Some(0xdeadbeef),
+        };
+        assert_eq!(Some(expected), analyze_test_frame(&buffer));
+        assert_eq!(Some(expected), analyze_test_frame(&buffer.scale(320, 180)));
+        assert_eq!(Some(expected), analyze_test_frame(&buffer.scale(160, 90)));
+        // Too small
+        assert_eq!(None, analyze_test_frame(&buffer.scale(32, 18)));
+
+        let generator = TestVideoGenerator::new(TestVideoConfig {
+            pattern: TestPattern::MovingGradient,
+            timestamp: false,
+            ..Default::default()
+        });
+        let buffer = generator.buffer(7, 0);
+        assert_eq!(
+            Some(TestFrameInfo {
+                frame_number: Some(7),
+                timestamp_ms: None,
+            }),
+            analyze_test_frame(&buffer.scale(320, 180))
+        );
+
+        // Not a test frame
+        assert_eq!(None, analyze_test_frame(&I420Buffer::new(640, 360)));
+    }
+
+    #[test]
+    fn frame_numbers_count_up() {
+        let mut generator = TestVideoGenerator::new(TestVideoConfig {
+            width: 160,
+            height: 90,
+            ..Default::default()
+        });
+        for expected in 0..3 {
+            let info = analyze_test_frame(&generator.next_buffer()).unwrap();
+            assert_eq!(Some(expected), info.frame_number);
+            let latency = time_since(info.timestamp_ms.unwrap()).unwrap();
+            assert!(latency < Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn color_bars() {
+        let generator = TestVideoGenerator::new(TestVideoConfig {
+            width: 140,
+            height: 80,
+            frame_counter: false,
+            timestamp: false,
+            ..Default::default()
+        });
+        let mut rgba = vec![0; 140 * 80 * 4];
+        generator.buffer(0, 0).to_rgba(&mut rgba);
+        // The middle of each bar on the bottom row.
+        let row = 79 * 140 * 4;
+        let bar_colors: Vec<&[u8]> = (0..7)
+            .map(|bar| {
+                let i = row + (bar * 20 + 10) * 4;
+                &rgba[i..i + 3]
+            })
+            .collect();
+        let expected: [[u8; 3]; 7] = [
+            [191, 191, 191],
+            [191, 191, 0],
+            [0, 191, 191],
+            [0, 191, 0],
+            [191, 0, 191],
+            [191, 0, 0],
+            [0, 0, 191],
+        ];
+        for (actual, expected) in bar_colors.iter().zip(expected.iter()) {
+            for (a, e) in actual.iter().zip(expected.iter()) {
+                assert!((*a as i32 - *e as i32).abs() <= 4, "{:?}", actual);
+            }
+        }
+    }
+
+    #[test]
+    fn sine_and_chirp() {
+        let mut generator =
+            TestAudioGenerator::new(TestTone::Sine { frequency: 1000.0 }, 48000, 0.5);
+        let mut samples = vec![0; 960];
+        generator.fill(&mut samples);
+        assert!((tone_level(&samples, 48000, 1000.0) - 0.5).abs() < 0.02);
+        assert!(tone_level(&samples, 48000, 2000.0) < 0.02);
+
+        let mut generator = TestAudioGenerator::new(
+            TestTone::Chirp {
+                start_frequency: 500.0,
+                end_frequency: 4000.0,
+                period: Duration::from_secs(1),
+            },
+            48000,
+            0.5,
+        );
+        let mut start = vec![0; 960];
+        generator.fill(&mut start);
+        let mut rest = vec![0; 48000 - 960 * 2];
+        generator.fill(&mut rest);
+        let mut end = vec![0; 960];
+        generator.fill(&mut end);
+        assert!(tone_level(&start, 48000, 600.0) > tone_level(&start, 48000, 3900.0));
+        assert!(tone_level(&end, 48000, 3900.0) > tone_level(&end, 48000, 600.0));
+    }
+
+    #[test]
+    fn dtmf() {
+        let mut generator = TestAudioGenerator::new(
+            TestTone::Dtmf {
+                digits: "15#".to_string(),
+                tone_duration: Duration::from_millis(100),
+                gap_duration: Duration::from_millis(50),
+            },
+            16000,
+            0.5,
+        );
+        let analyzer = TestAudioAnalyzer::new();
+        let mut digits = vec![];
+        for _ in 0..45 {
+            digits.push(generator.current_dtmf_digit());
+            let samples = generator.next_10ms();
+            analyzer.on_audio_frame(
+                1,
+                AudioFrame {
+                    sample_rate: 16000,
+                    channels: 1,
+                    samples: &samples,
+                },
+            );
+        }
+        assert_eq!(Some('1'), digits[0]);
+        assert_eq!(None, digits[10]);
+        assert_eq!(Some('5'), digits[15]);
+        assert_eq!(Some('#'), digits[30]);
+        assert_eq!(45, analyzer.frames_received(1));
+        assert_eq!(0, analyzer.frames_received(2));
+        let detected: Vec<char> = analyzer
+            .take_detected()
+            .iter()
+            .map(|detected| detected.digit)
+            .collect();
+        assert_eq!(vec!['1', '5', '#'], detected);
+        assert!(analyzer.take_detected().is_empty());
+
+        let silence = vec![0i16; 480];
+        assert_eq!(None, detect_dtmf(&silence, 16000));
+    }
+
+    #[test]
+    fn media_quality_meter() {
+        let mut meter = MediaQualityMeter::new();
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let frame = |received_ms: u64, latency_ms: u64| ReceivedTestFrame {
+            track_id: 1,
+            frame_number: None,
+            latency: Some(Duration::from_millis(latency_ms)),
+            received: at(received_ms),
+        };
+        let detected = |digit: char, detected_ms: u64| DetectedDtmf {
+            track_id: 1,
+            digit,
+            detected: at(detected_ms),
+        };
+
+        // 30fps with one 500ms freeze.
+        for i in 0..10 {
+            meter.video_frame_received(&frame(i * 33, 100));
+        }
+        meter.video_frame_received(&frame(797, 120));
+        meter.video_frame_received(&frame(830, 100));
+        meter.audio_marker_sent('1', at(0));
+        meter.audio_marker_sent('2', at(500));
+        meter.audio_marker_received(&detected('1', 150));
+        meter.audio_marker_received(&detected('2', 650));
+        // Never sent
+        meter.audio_marker_received(&detected('3', 700));
+
+        let report = meter.take_report(at(1000));
+        assert_eq!(12, report.video_frames_received);
+        assert_eq!(1, report.freeze_count);
+        assert_eq!(500, report.freeze_duration_ms);
+        let video_latency = report.video_latency.unwrap();
+        assert_eq!(12, video_latency.count);
+        assert_eq!(100.0, video_latency.median_ms);
+        assert_eq!(120.0, video_latency.max_ms);
+        assert_eq!(2, report.audio_markers_received);
+        assert_eq!(150.0, report.audio_latency.unwrap().median_ms);
+        assert_eq!(Some(50.0), report.av_skew_ms);
+
+        // Each report only covers its own interval.
+        meter.video_frame_received(&frame(863, 100));
+        let report = meter.take_report(at(2000));
+        assert_eq!(2000, report.elapsed_ms);
+        assert_eq!(1000, report.interval_ms);
+        assert_eq!(1, report.video_frames_received);
+        assert_eq!(0, report.freeze_count);
+        assert_eq!(None, report.audio_latency);
+        assert_eq!(None, report.av_skew_ms);
+    }
+}
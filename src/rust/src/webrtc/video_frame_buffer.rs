@@ -0,0 +1,977 @@
+//
+// Copyright 2022 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! A video frame buffer that lives entirely in Rust, for when going
+//! through WebRTC's C++ buffers isn't possible (the sim build) or is
+//! more trouble than it's worth (post-processing decoded frames for
+//! rendering).
+//!
+//! Frames are kept as I420, like WebRTC does.  Conversions to and from
+//! other pixel formats use the same BT.601 limited-range math as libyuv.
+
+use crate::webrtc::media::{VideoPixelFormat, VideoRotation};
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct I420Buffer {
+    width: u32,
+    height: u32,
+    // The Y plane followed by the U and V planes.
+    data: Vec<u8>,
+}
+
+impl std::fmt::Debug for I420Buffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("I420Buffer")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+// Where each component is within a pixel of a packed RGB format.
+#[derive(Clone, Copy)]
+struct PackedLayout {
+    red: usize,
+    green: usize,
+    blue: usize,
+    alpha: usize,
+}
+
+const RGBA: PackedLayout = PackedLayout {
+    red: 0,
+    green: 1,
+    blue: 2,
+    alpha: 3,
+};
+
+const BGRA: PackedLayout = PackedLayout {
+    red: 2,
+    green: 1,
+    blue: 0,
+    alpha: 3,
+};
+
+const ARGB: PackedLayout = PackedLayout {
+    red: 1,
+    green: 2,
+    blue: 3,
+    alpha: 0,
+};
+
+fn chroma_size(size: u32) -> u32 {
+    size / 2 + size % 2
+}
+
+impl I420Buffer {
+    /// The number of bytes needed for an I420 frame of the given size:
+    /// the Y plane followed by the U and V planes, each of which is
+    /// half the width and height (rounded up).
+    pub fn size(width: u32, height: u32) -> usize {
+        let luma = (width as usize) * (height as usize);
+        let chroma = (chroma_size(width) as usize) * (chroma_size(height) as usize);
+        luma + 2 * chroma
+    }
+
+    /// A black frame.
+    pub fn new(width: u32, height: u32) -> Self {
+        let luma = (width as usize) * (height as usize);
+        let mut data = vec![128u8; Self::size(width, height)];
+        data[..luma].fill(16);
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn chroma_width(&self) -> u32 {
+        chroma_size(self.width)
+    }
+
+    pub fn chroma_height(&self) -> u32 {
+        chroma_size(self.height)
+    }
+
+    /// The Y, U, and V planes together, in the layout expected by
+    /// VideoFrame::copy_from_slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// The Y, U, and V planes, in that order.
+    pub fn planes(&self) -> (&[u8], &[u8], &[u8]) {
+        let luma = (self.width as usize) * (self.height as usize);
+        let chroma = (self.chroma_width() as usize) * (self.chroma_height() as usize);
+        let (y, uv) = self.data.split_at(luma);
+        let (u, v) = uv.split_at(chroma);
+        (y, u, v)
+    }
+
+    pub fn planes_mut(&mut self) -> (&mut [u8], &mut [u8], &mut [u8]) {
+        let luma = (self.width as usize) * (self.height as usize);
+        let chroma = (self.chroma_width() as usize) * (self.chroma_height() as usize);
+        let (y, uv) = self.data.split_at_mut(luma);
+        let (u, v) = uv.split_at_mut(chroma);
+        (y, u, v)
+    }
+
+    /// Copies an I420 frame laid out like as_bytes().
+    /// Panics if the source is smaller than I420Buffer::size().
+    pub fn from_i420(width: u32, height: u32, src: &[u8]) -> Self {
+        let size = Self::size(width, height);
+        assert!(src.len() >= size, "I420 source is too small");
+        Self {
+            width,
+            height,
+            data: src[..size].to_vec(),
+        }
+    }
+
+    /// Copies an NV12 frame: the Y plane followed by interleaved U and V.
+    /// Panics if the source is smaller than I420Buffer::size().
+    pub fn from_nv12(width: u32, height: u32, src: &[u8]) -> Self {
+        assert!(
+            src.len() >= Self::size(width, height),
+            "NV12 source is too small"
+        );
+        let mut buffer = Self::new(width, height);
+        let (y, u, v) = buffer.planes_mut();
+        y.copy_from_slice(&src[..y.len()]);
+        let uv = &src[y.len()..];
+        for (i, (u, v)) in u.iter_mut().zip(v.iter_mut()).enumerate() {
+            *u = uv[2 * i];
+            *v = uv[2 * i + 1];
+        }
+        buffer
+    }
+
+    /// Converts an RGBA frame (4 bytes per pixel, alpha ignored).
+    /// Panics if the source is smaller than width * height * 4.
+    pub fn from_rgba(width: u32, height: u32, src: &[u8]) -> Self {
+        Self::from_packed_rgb(width, height, src, RGBA)
+    }
+
+    /// Converts a BGRA frame (4 bytes per pixel, alpha ignored).
+    /// Panics if the source is smaller than width * height * 4.
+    pub fn from_bgra(width: u32, height: u32, src: &[u8]) -> Self {
+        Self::from_packed_rgb(width, height, src, BGRA)
+    }
+
+    /// Converts an ARGB frame (4 bytes per pixel, alpha first and ignored).
+    /// Panics if the source is smaller than width * height * 4.
+    pub fn from_argb(width: u32, height: u32, src: &[u8]) -> Self {
+        Self::from_packed_rgb(width, height, src, ARGB)
+    }
+
+    fn from_packed_rgb(width: u32, height: u32, src: &[u8], layout: PackedLayout) -> Self {
+        let (w, h) = (width as usize, height as usize);
+        assert!(src.len() >= w * h * 4, "RGB source is too small");
+        let rgb = |x: usize, y: usize| -> (i32, i32, i32) {
+            let i = (y * w + x) * 4;
+            (
+                src[i + layout.red] as i32,
+                src[i + layout.green] as i32,
+                src[i + layout.blue] as i32,
+            )
+        };
+
+        let mut buffer = Self::new(width, height);
+        let cw = buffer.chroma_width() as usize;
+        let (y_plane, u_plane, v_plane) = buffer.planes_mut();
+        for y in 0..h {
+            for x in 0..w {
+                let (r, g, b) = rgb(x, y);
+                y_plane[y * w + x] = rgb_to_y(r, g, b);
+            }
+        }
+        // Each chroma sample covers up to 2x2 pixels.
+        for (cy, (u_row, v_row)) in u_plane
+            .chunks_mut(cw.max(1))
+            .zip(v_plane.chunks_mut(cw.max(1)))
+            .enumerate()
+        {
+            for cx in 0..cw {
+                let (mut r, mut g, mut b, mut count) = (0, 0, 0, 0);
+                for y in (2 * cy)..(2 * cy + 2).min(h) {
+                    for x in (2 * cx)..(2 * cx + 2).min(w) {
+                        let (pr, pg, pb) = rgb(x, y);
+                        r += pr;
+                        g += pg;
+                        b += pb;
+                        count += 1;
+                    }
+                }
+                let (r, g, b) = (
+                    (r + count / 2) / count,
+                    (g + count / 2) / count,
+                    (b + count / 2) / count,
+                );
+                u_row[cx] = rgb_to_u(r, g, b);
+                v_row[cx] = rgb_to_v(r, g, b);
+            }
+        }
+        buffer
+    }
+
+    /// Copies to the layout of as_bytes().
+    /// Panics if the destination is smaller than I420Buffer::size().
+    pub fn to_i420(&self, dst: &mut [u8]) {
+        dst[..self.data.len()].copy_from_slice(&self.data);
+    }
+
+    /// Copies to NV12: the Y plane followed by interleaved U and V.
+    /// Panics if the destination is smaller than I420Buffer::size().
+    pub fn to_nv12(&self, dst: &mut [u8]) {
+        let (y, u, v) = self.planes();
+        dst[..y.len()].copy_from_slice(y);
+        let uv = &mut dst[y.len()..y.len() + u.len() + v.len()];
+        for (i, (u, v)) in u.iter().zip(v.iter()).enumerate() {
+            uv[2 * i] = *u;
+            uv[2 * i + 1] = *v;
+        }
+    }
+
+    /// Converts to RGBA with an opaque alpha.
+    /// Panics if the destination is smaller than width * height * 4.
+    pub fn to_rgba(&self, dst: &mut [u8]) {
+        self.to_packed_rgb(dst, RGBA)
+    }
+
+    /// Converts to BGRA with an opaque alpha.
+    /// Panics if the destination is smaller than width * height * 4.
+    pub fn to_bgra(&self, dst: &mut [u8]) {
+        self.to_packed_rgb(dst, BGRA)
+    }
+
+    /// Converts to ARGB with an opaque alpha.
+    /// Panics if the destination is smaller than width * height * 4.
+    pub fn to_argb(&self, dst: &mut [u8]) {
+        self.to_packed_rgb(dst, ARGB)
+    }
+
+    fn to_packed_rgb(&self, dst: &mut [u8], layout: PackedLayout) {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let cw = self.chroma_width() as usize;
+        let (y_plane, u_plane, v_plane) = self.planes();
+        for (y, row) in dst[..w * h * 4].chunks_mut((w * 4).max(1)).enumerate() {
+            for (x, pixel) in row.chunks_mut(4).enumerate() {
+                let c = (y / 2) * cw + x / 2;
+                let (r, g, b) = yuv_to_rgb(y_plane[y * w + x], u_plane[c], v_plane[c]);
+                pixel[layout.red] = r;
+                pixel[layout.green] = g;
+                pixel[layout.blue] = b;
+                pixel[layout.alpha] = 255;
+            }
+        }
+    }
+
+    /// Converts an I444 frame: the Y, U, and V planes, all full size.
+    /// The chroma is averaged down to half size.
+    /// Panics if the source is smaller than width * height * 3.
+    pub fn from_i444(width: u32, height: u32, src: &[u8]) -> Self {
+        let (w, h) = (width as usize, height as usize);
+        assert!(src.len() >= w * h * 3, "I444 source is too small");
+        let mut buffer = Self::new(width, height);
+        let (cw, ch) = (
+            buffer.chroma_width() as usize,
+            buffer.chroma_height() as usize,
+        );
+        let (y_plane, u_plane, v_plane) = buffer.planes_mut();
+        let (src_y, src_uv) = src.split_at(w * h);
+        let (src_u, src_v) = src_uv.split_at(w * h);
+        y_plane.copy_from_slice(src_y);
+        for cy in 0..ch {
+            for cx in 0..cw {
+                let (mut u, mut v, mut count) = (0u32, 0u32, 0u32);
+                for y in (2 * cy)..(2 * cy + 2).min(h) {
+                    for x in (2 * cx)..(2 * cx + 2).min(w) {
+                        u += src_u[y * w + x] as u32;
+                        v += src_v[y * w + x] as u32;
+                        count += 1;
+                    }
+                }
+                u_plane[cy * cw + cx] = ((u + count / 2) / count) as u8;
+                v_plane[cy * cw + cx] = ((v + count / 2) / count) as u8;
+            }
+        }
+        buffer
+    }
+
+    /// Converts to I444: the Y, U, and V planes, all full size.
+    /// Panics if the destination is smaller than width * height * 3.
+    pub fn to_i444(&self, dst: &mut [u8]) {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let cw = self.chroma_width() as usize;
+        let (y_plane, u_plane, v_plane) = self.planes();
+        let (dst_y, dst_uv) = dst[..w * h * 3].split_at_mut(w * h);
+        let (dst_u, dst_v) = dst_uv.split_at_mut(w * h);
+        dst_y.copy_from_slice(y_plane);
+        for y in 0..h {
+            for x in 0..w {
+                let c = (y / 2) * cw + x / 2;
+                dst_u[y * w + x] = u_plane[c];
+                dst_v[y * w + x] = v_plane[c];
+            }
+        }
+    }
+
+    /// Converts a P010 frame: like NV12, but each sample is 16 bits (little-endian)
+    /// with the 10 significant bits at the top.
+    /// Panics if the source is smaller than I420Buffer::size() * 2.
+    pub fn from_p010(width: u32, height: u32, src: &[u8]) -> Self {
+        assert!(
+            src.len() >= Self::size(width, height) * 2,
+            "P010 source is too small"
+        );
+        let sample = |i: usize| -> u8 {
+            let ten_bits = u16::from_le_bytes([src[2 * i], src[2 * i + 1]]) >> 6;
+            (ten_bits >> 2) as u8
+        };
+        let mut buffer = Self::new(width, height);
+        let (y, u, v) = buffer.planes_mut();
+        for (i, y) in y.iter_mut().enumerate() {
+            *y = sample(i);
+        }
+        let luma = y.len();
+        for (i, (u, v)) in u.iter_mut().zip(v.iter_mut()).enumerate() {
+            *u = sample(luma + 2 * i);
+            *v = sample(luma + 2 * i + 1);
+        }
+        buffer
+    }
+
+    /// Converts to P010: like NV12, but each sample is 16 bits (little-endian)
+    /// with the 10 significant bits at the top.
+    /// Panics if the destination is smaller than I420Buffer::size() * 2.
+    pub fn to_p010(&self, dst: &mut [u8]) {
+        let mut nv12 = vec![0u8; self.data.len()];
+        self.to_nv12(&mut nv12);
+        for (value, out) in nv12.iter().zip(dst.chunks_mut(2)) {
+            // Repeat the top bits in the bottom bits so that 255 becomes 1023.
+            let ten_bits = ((*value as u16) << 2) | ((*value as u16) >> 6);
+            out.copy_from_slice(&(ten_bits << 6).to_le_bytes());
+        }
+    }
+
+    /// Converts from any of the supported pixel formats.
+    /// Panics if the source is smaller than pixel_format.size().
+    pub fn from_format(
+        width: u32,
+        height: u32,
+        pixel_format: VideoPixelFormat,
+        src: &[u8],
+    ) -> Self {
+        match pixel_format {
+            VideoPixelFormat::I420 => Self::from_i420(width, height, src),
+            VideoPixelFormat::Nv12 => Self::from_nv12(width, height, src),
+            VideoPixelFormat::Rgba => Self::from_rgba(width, height, src),
+            VideoPixelFormat::Bgra => Self::from_bgra(width, height, src),
+            VideoPixelFormat::Argb => Self::from_argb(width, height, src),
+            VideoPixelFormat::I444 => Self::from_i444(width, height, src),
+            VideoPixelFormat::P010 => Self::from_p010(width, height, src),
+        }
+    }
+
+    /// Converts to any of the supported pixel formats.
+    /// Panics if the destination is smaller than pixel_format.size().
+    pub fn to_format(&self, pixel_format: VideoPixelFormat, dst: &mut [u8]) {
+        match pixel_format {
+            VideoPixelFormat::I420 => self.to_i420(dst),
+            VideoPixelFormat::Nv12 => self.to_nv12(dst),
+            VideoPixelFormat::Rgba => self.to_rgba(dst),
+            VideoPixelFormat::Bgra => self.to_bgra(dst),
+            VideoPixelFormat::Argb => self.to_argb(dst),
+            VideoPixelFormat::I444 => self.to_i444(dst),
+            VideoPixelFormat::P010 => self.to_p010(dst),
+        }
+    }
+
+    /// Scales to the given size using bilinear filtering.
+    /// Intended for downscaling; upscaling works but is blurry.
+    #[must_use]
+    pub fn scale(&self, width: u32, height: u32) -> Self {
+        if width == self.width && height == self.height {
+            return self.clone();
+        }
+        let mut scaled = Self::new(width, height);
+        let (src_cw, src_ch) = (self.chroma_width(), self.chroma_height());
+        let (dst_cw, dst_ch) = (scaled.chroma_width(), scaled.chroma_height());
+        let (src_y, src_u, src_v) = self.planes();
+        let (dst_y, dst_u, dst_v) = scaled.planes_mut();
+        scale_plane(src_y, self.width, self.height, dst_y, width, height);
+        scale_plane(src_u, src_cw, src_ch, dst_u, dst_cw, dst_ch);
+        scale_plane(src_v, src_cw, src_ch, dst_v, dst_cw, dst_ch);
+        scaled
+    }
+
+    /// Copies out a rectangle of the frame.  The rectangle is clamped
+    /// to the frame, and its origin is rounded down to even coordinates
+    /// so that it lines up with the chroma planes.
+    #[must_use]
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        let x = x.min(self.width) & !1;
+        let y = y.min(self.height) & !1;
+        let width = width.min(self.width - x);
+        let height = height.min(self.height - y);
+
+        let mut cropped = Self::new(width, height);
+        let (src_cw, dst_cw, dst_ch) = (
+            self.chroma_width() as usize,
+            cropped.chroma_width() as usize,
+            cropped.chroma_height() as usize,
+        );
+        let (src_y, src_u, src_v) = self.planes();
+        let (dst_y, dst_u, dst_v) = cropped.planes_mut();
+        copy_rect(
+            src_y,
+            self.width as usize,
+            (x as usize, y as usize),
+            dst_y,
+            (width as usize, height as usize),
+        );
+        let chroma_origin = (x as usize / 2, y as usize / 2);
+        copy_rect(src_u, src_cw, chroma_origin, dst_u, (dst_cw, dst_ch));
+        copy_rect(src_v, src_cw, chroma_origin, dst_v, (dst_cw, dst_ch));
+        cropped
+    }
+
+    /// Crops the edges of the frame (left and right, or top and bottom)
+    /// so that what's left in the center has the given aspect ratio,
+    /// such as 16:9 or 1:1.
+    #[must_use]
+    pub fn center_crop_to_aspect_ratio(&self, aspect_width: u32, aspect_height: u32) -> Self {
+        if aspect_width == 0 || aspect_height == 0 {
+            return self.clone();
+        }
+        let (w, h) = (self.width as u64, self.height as u64);
+        let (aw, ah) = (aspect_width as u64, aspect_height as u64);
+        let (width, height) = if w * ah > h * aw {
+            // Too wide
+            ((h * aw / ah) as u32, self.height)
+        } else {
+            // Too tall (or just right)
+            (self.width, (w * ah / aw) as u32)
+        };
+        // Keep the size even so the crop stays centered after rounding the origin.
+        let width = if width < self.width {
+            width & !1
+        } else {
+            width
+        };
+        let height = if height < self.height {
+            height & !1
+        } else {
+            height
+        };
+        self.crop(
+            (self.width - width) / 2,
+            (self.height - height) / 2,
+            width,
+            height,
+        )
+    }
+
+    /// Flips the frame left-to-right, like a mirror (or a self view).
+    #[must_use]
+    pub fn mirror_horizontally(&self) -> Self {
+        let mut mirrored = self.clone();
+        let (w, cw) = (self.width as usize, self.chroma_width() as usize);
+        let (y, u, v) = mirrored.planes_mut();
+        for row in y.chunks_mut(w.max(1)) {
+            row.reverse();
+        }
+        for row in u.chunks_mut(cw.max(1)).chain(v.chunks_mut(cw.max(1))) {
+            row.reverse();
+        }
+        mirrored
+    }
+
+    /// Draws another frame on top of this one (e.g. a watermark), with its
+    /// top-left corner at (x, y), rounded down to even coordinates.  An opacity
+    /// of 255 covers what's underneath.  Whatever falls outside is left out.
+    pub fn blend(&mut self, overlay: &I420Buffer, x: u32, y: u32, opacity: u8) {
+        let (x, y) = ((x & !1) as usize, (y & !1) as usize);
+        let (w, cw) = (self.width as usize, self.chroma_width() as usize);
+        let (h, ch) = (self.height as usize, self.chroma_height() as usize);
+        let (overlay_w, overlay_cw) = (overlay.width as usize, overlay.chroma_width() as usize);
+        let (overlay_h, overlay_ch) = (overlay.height as usize, overlay.chroma_height() as usize);
+        let (src_y, src_u, src_v) = overlay.planes();
+        let (dst_y, dst_u, dst_v) = self.planes_mut();
+        blend_rect(
+            src_y,
+            (overlay_w, overlay_h),
+            dst_y,
+            (w, h),
+            (x, y),
+            opacity,
+        );
+        for (src, dst) in [(src_u, dst_u), (src_v, dst_v)] {
+            blend_rect(
+                src,
+                (overlay_cw, overlay_ch),
+                dst,
+                (cw, ch),
+                (x / 2, y / 2),
+                opacity,
+            );
+        }
+    }
+
+    /// Rotates the frame clockwise.
+    #[must_use]
+    pub fn rotate(&self, rotation: VideoRotation) -> Self {
+        let (width, height) = match rotation {
+            VideoRotation::None => return self.clone(),
+            VideoRotation::Clockwise180 => (self.width, self.height),
+            VideoRotation::Clockwise90 | VideoRotation::Clockwise270 => (self.height, self.width),
+        };
+        let mut rotated = Self::new(width, height);
+        let (src_cw, src_ch) = (self.chroma_width(), self.chroma_height());
+        let (src_y, src_u, src_v) = self.planes();
+        let (dst_y, dst_u, dst_v) = rotated.planes_mut();
+        rotate_plane(src_y, self.width, self.height, dst_y, rotation);
+        rotate_plane(src_u, src_cw, src_ch, dst_u, rotation);
+        rotate_plane(src_v, src_cw, src_ch, dst_v, rotation);
+        rotated
+    }
+}
+
+fn clamp_u8(value: i32) -> u8 {
+    value.clamp(0, 255) as u8
+}
+
+fn rgb_to_y(r: i32, g: i32, b: i32) -> u8 {
+    clamp_u8(((66 * r + 129 * g + 25 * b + 128) >> 8) + 16)
+}
+
+fn rgb_to_u(r: i32, g: i32, b: i32) -> u8 {
+    clamp_u8(((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128)
+}
+
+fn rgb_to_v(r: i32, g: i32, b: i32) -> u8 {
+    clamp_u8(((112 * r - 94 * g - 18 * b + 128) >> 8) + 128)
+}
+
+fn yuv_to_rgb(y: u8, u: u8, v: u8) -> (u8, u8, u8) {
+    let c = 298 * (y as i32 - 16);
+    let d = u as i32 - 128;
+    let e = v as i32 - 128;
+    (
+        clamp_u8((c + 409 * e + 128) >> 8),
+        clamp_u8((c - 100 * d - 208 * e + 128) >> 8),
+        clamp_u8((c + 516 * d + 128) >> 8),
+    )
+}
+
+// Bilinear scaling with 16.16 fixed point positions, sampling at pixel centers.
+fn scale_plane(src: &[u8], src_w: u32, src_h: u32, dst: &mut [u8], dst_w: u32, dst_h: u32) {
+    if src_w == 0 || src_h == 0 || dst_w == 0 || dst_h == 0 {
+        return;
+    }
+    let (src_w, src_h) = (src_w as i64, src_h as i64);
+    let (dst_w, dst_h) = (dst_w as i64, dst_h as i64);
+    let position = |dst_i: i64, src_len: i64, dst_len: i64| -> (usize, usize, i64) {
+        let fixed = (((2 * dst_i + 1) * src_len) << 16) / (2 * dst_len) - (1 << 15);
+        let fixed = fixed.clamp(0, (src_len - 1) << 16);
+        let i0 = (fixed >> 16) as usize;
+        let i1 = (i0 + 1).min(src_len as usize - 1);
+        (i0, i1, fixed & 0xffff)
+    };
+    let xs: Vec<_> = (0..dst_w).map(|x| position(x, src_w, dst_w)).collect();
+    for (y, row) in dst.chunks_mut(dst_w as usize).enumerate() {
+        let (y0, y1, fy) = position(y as i64, src_h, dst_h);
+        let row0 = &src[y0 * src_w as usize..];
+        let row1 = &src[y1 * src_w as usize..];
+        for (out, &(x0, x1, fx)) in row.iter_mut().zip(xs.iter()) {
+            let top = (row0[x0] as i64) * (0x10000 - fx) + (row0[x1] as i64) * fx;
+            let bottom = (row1[x0] as i64) * (0x10000 - fx) + (row1[x1] as i64) * fx;
+            let value = (top * (0x10000 - fy) + bottom * fy + (1 << 31)) >> 32;
+            *out = value as u8;
+        }
+    }
+}
+
+fn blend_rect(
+    src: &[u8],
+    (src_w, src_h): (usize, usize),
+    dst: &mut [u8],
+    (dst_w, dst_h): (usize, usize),
+    (x, y): (usize, usize),
+    opacity: u8,
+) {
+    let opacity = opacity as u32;
+    let width = src_w.min(dst_w.saturating_sub(x));
+    let height = src_h.min(dst_h.saturating_sub(y));
+    for row in 0..height {
+        let src_row = &src[row * src_w..][..width];
+        let dst_row = &mut dst[(y + row) * dst_w + x..][..width];
+        for (dst, src) in dst_row.iter_mut().zip(src_row) {
+            *dst = ((*src as u32 * opacity + *dst as u32 * (255 - opacity) + 127) / 255) as u8;
+        }
+    }
+}
+
+fn copy_rect(
+    src: &[u8],
+    src_stride: usize,
+    (x, y): (usize, usize),
+    dst: &mut [u8],
+    (width, height): (usize, usize),
+) {
+    for (row, dst_row) in dst.chunks_mut(width.max(1)).take(height).enumerate() {
+        let start = (y + row) * src_stride + x;
+        dst_row.copy_from_slice(&src[start..start + width]);
+    }
+}
+
+fn rotate_plane(src: &[u8], src_w: u32, src_h: u32, dst: &mut [u8], rotation: VideoRotation) {
+    let (w, h) = (src_w as usize, src_h as usize);
+    for y in 0..h {
+        for x in 0..w {
+            let dst_index = match rotation {
+                VideoRotation::None => y * w + x,
+                VideoRotation::Clockwise90 => x * h + (h - 1 - y),
+                VideoRotation::Clockwise180 => (h - 1 - y) * w + (w - 1 - x),
+                VideoRotation::Clockwise270 => (w - 1 - x) * h + y,
+            };
+            dst[dst_index] = src[y * w + x];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid_rgba(width: u32, height: u32, rgba: [u8; 4]) -> Vec<u8> {
+        rgba.iter()
+            .copied()
+            .cycle()
+            .take((width * height * 4) as usize)
+            .collect()
+    }
+
+    // A frame where each Y value tells where it came from.
+    fn numbered(width: u32, height: u32) -> I420Buffer {
+        let mut buffer = I420Buffer::new(width, height);
+        let (y, u, v) = buffer.planes_mut();
+        for (i, value) in y.iter_mut().enumerate() {
+            *value = i as u8;
+        }
+        for (i, (u, v)) in u.iter_mut().zip(v.iter_mut()).enumerate() {
+            *u = 100 + i as u8;
+            *v = 200 + i as u8;
+        }
+        buffer
+    }
+
+    #[test]
+    fn size() {
+        assert_eq!(6, I420Buffer::size(2, 2));
+        assert_eq!(4 * 3 + 2 * 2 * 2, I420Buffer::size(4, 3));
+        assert_eq!(3 * 3 + 2 * 2 * 2, I420Buffer::size(3, 3));
+        assert_eq!(0, I420Buffer::size(0, 0));
+
+        let buffer = I420Buffer::new(5, 3);
+        assert_eq!(I420Buffer::size(5, 3), buffer.as_bytes().len());
+        let (y, u, v) = buffer.planes();
+        assert_eq!((15, 6, 6), (y.len(), u.len(), v.len()));
+        assert!(y.iter().all(|&y| y == 16));
+        assert!(u.iter().chain(v.iter()).all(|&c| c == 128));
+    }
+
+    #[test]
+    fn rgb_round_trip() {
+        for rgba in [
+            [0, 0, 0, 255],
+            [255, 255, 255, 255],
+            [128, 128, 128, 255],
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 255],
+            [20, 150, 90, 255],
+        ] {
+            let buffer = I420Buffer::from_rgba(4, 2, &solid_rgba(4, 2, rgba));
+            let mut rgba_out = vec![0; 4 * 2 * 4];
+            buffer.to_rgba(&mut rgba_out);
+            for pixel in rgba_out.chunks(4) {
+                for (expected, actual) in rgba.iter().zip(pixel) {
+                    assert!(
+                        (*expected as i32 - *actual as i32).abs() <= 3,
+                        "{:?} became {:?}",
+                        rgba,
+                        pixel
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rgba_and_bgra_are_swapped() {
+        let rgba = solid_rgba(3, 3, [200, 100, 50, 255]);
+        let bgra = solid_rgba(3, 3, [50, 100, 200, 255]);
+        let from_rgba = I420Buffer::from_rgba(3, 3, &rgba);
+        assert_eq!(from_rgba, I420Buffer::from_bgra(3, 3, &bgra));
+
+        let mut out_rgba = vec![0; rgba.len()];
+        let mut out_bgra = vec![0; bgra.len()];
+        from_rgba.to_rgba(&mut out_rgba);
+        from_rgba.to_bgra(&mut out_bgra);
+        for (rgba, bgra) in out_rgba.chunks(4).zip(out_bgra.chunks(4)) {
+            assert_eq!([rgba[2], rgba[1], rgba[0], rgba[3]], bgra);
+        }
+    }
+
+    #[test]
+    fn known_colors() {
+        let white = I420Buffer::from_rgba(2, 2, &solid_rgba(2, 2, [255, 255, 255, 255]));
+        assert_eq!(&[235, 235, 235, 235, 128, 128], white.as_bytes());
+        let black = I420Buffer::from_rgba(2, 2, &solid_rgba(2, 2, [0, 0, 0, 255]));
+        assert_eq!(I420Buffer::new(2, 2), black);
+        let empty = I420Buffer::from_rgba(0, 0, &[]);
+        empty.to_rgba(&mut []);
+        assert_eq!(0, empty.as_bytes().len());
+    }
+
+    #[test]
+    fn nv12_round_trip() {
+        let original = numbered(5, 3);
+        let mut nv12 = vec![0; I420Buffer::size(5, 3)];
+        original.to_nv12(&mut nv12);
+        assert_eq!(&original.as_bytes()[..15], &nv12[..15]);
+        assert_eq!(&[100, 200, 101, 201, 102, 202], &nv12[15..21]);
+        assert_eq!(original, I420Buffer::from_nv12(5, 3, &nv12));
+
+        let mut i420 = vec![0; I420Buffer::size(5, 3)];
+        original.to_i420(&mut i420);
+        assert_eq!(original, I420Buffer::from_i420(5, 3, &i420));
+    }
+
+    #[test]
+    fn argb() {
+        let rgba = solid_rgba(2, 2, [200, 100, 50, 255]);
+        let argb = solid_rgba(2, 2, [255, 200, 100, 50]);
+        let buffer = I420Buffer::from_rgba(2, 2, &rgba);
+        assert_eq!(buffer, I420Buffer::from_argb(2, 2, &argb));
+
+        let mut out = vec![0; argb.len()];
+        buffer.to_argb(&mut out);
+        assert_eq!(255, out[0]);
+        assert!((out[1] as i32 - 200).abs() <= 3);
+        assert!((out[3] as i32 - 50).abs() <= 3);
+    }
+
+    #[test]
+    fn i444() {
+        let original = numbered(3, 3);
+        let mut i444 = vec![0; VideoPixelFormat::I444.size(3, 3)];
+        original.to_i444(&mut i444);
+        assert_eq!(original.planes().0, &i444[..9]);
+        assert_eq!(&[100, 100, 101, 100, 100, 101, 102, 102, 103], &i444[9..18]);
+        assert_eq!(original, I420Buffer::from_i444(3, 3, &i444));
+
+        // Chroma gets averaged.
+        let mut i444 = vec![16; 2 * 2 * 3];
+        i444[4..8].copy_from_slice(&[10, 20, 30, 40]);
+        let buffer = I420Buffer::from_i444(2, 2, &i444);
+        assert_eq!(&[25], buffer.planes().1);
+    }
+
+    #[test]
+    fn p010() {
+        let original = numbered(4, 2);
+        let mut p010 = vec![0; VideoPixelFormat::P010.size(4, 2)];
+        original.to_p010(&mut p010);
+        // Y = 1 becomes 10-bit 4, shifted up by 6.
+        assert_eq!(&[0, 1], &p010[2..4]);
+        // 255 becomes 1023, shifted up by 6.
+        let mut white = I420Buffer::new(2, 2);
+        white.planes_mut().0.fill(255);
+        let mut white_p010 = vec![0; VideoPixelFormat::P010.size(2, 2)];
+        white.to_p010(&mut white_p010);
+        assert_eq!(&[0xc0, 0xff], &white_p010[..2]);
+
+        assert_eq!(original, I420Buffer::from_p010(4, 2, &p010));
+        assert_eq!(white, I420Buffer::from_p010(2, 2, &white_p010));
+    }
+
+    #[test]
+    fn all_formats_round_trip() {
+        let original = I420Buffer::from_rgba(6, 4, &solid_rgba(6, 4, [20, 150, 90, 255]));
+        for pixel_format in [
+            VideoPixelFormat::I420,
+            VideoPixelFormat::Nv12,
+            VideoPixelFormat::Rgba,
+            VideoPixelFormat::Bgra,
+            VideoPixelFormat::Argb,
+            VideoPixelFormat::I444,
+            VideoPixelFormat::P010,
+        ] {
+            let mut converted = vec![0; pixel_format.size(6, 4)];
+            original.to_format(pixel_format, &mut converted);
+            let round_tripped = I420Buffer::from_format(6, 4, pixel_format, &converted);
+            for (expected, actual) in original.as_bytes().iter().zip(round_tripped.as_bytes()) {
+                assert!(
+                    (*expected as i32 - *actual as i32).abs() <= 2,
+                    "{:?}",
+                    pixel_format
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn sim_video_frame() {
+        use crate::webrtc::media::VideoFrame;
+
+        let original = numbered(4, 2);
+        let mut bgra = vec![0; VideoPixelFormat::Bgra.size(4, 2)];
+        original.to_bgra(&mut bgra);
+        let frame = VideoFrame::copy_from_slice(4, 2, VideoPixelFormat::Bgra, &bgra);
+        assert_eq!(I420Buffer::from_bgra(4, 2, &bgra), frame.to_i420_buffer());
+
+        let frame = VideoFrame::from_i420_buffer(&original);
+        assert_eq!(original, frame.to_i420_buffer());
+        let mut i444 = vec![0; VideoPixelFormat::I444.size(4, 2)];
+        frame.to_format(VideoPixelFormat::I444, &mut i444);
+        assert_eq!(original, I420Buffer::from_i444(4, 2, &i444));
+    }
+
+    #[test]
+    fn scale() {
+        let solid = I420Buffer::from_rgba(64, 48, &solid_rgba(64, 48, [20, 150, 90, 255]));
+        let scaled = solid.scale(16, 9);
+        assert_eq!((16, 9), (scaled.width(), scaled.height()));
+        assert_eq!(I420Buffer::size(16, 9), scaled.as_bytes().len());
+        let (y, u, v) = solid.planes();
+        let (scaled_y, scaled_u, scaled_v) = scaled.planes();
+        assert!(scaled_y.iter().all(|&value| value == y[0]));
+        assert!(scaled_u.iter().all(|&value| value == u[0]));
+        assert!(scaled_v.iter().all(|&value| value == v[0]));
+
+        // Halving averages pairs of pixels.
+        let mut gradient = I420Buffer::new(4, 2);
+        gradient
+            .planes_mut()
+            .0
+            .copy_from_slice(&[0, 100, 200, 250, 0, 100, 200, 250]);
+        let halved = gradient.scale(2, 1);
+        assert_eq!(&[50, 225], halved.planes().0);
+
+        assert_eq!(gradient, gradient.scale(4, 2));
+        assert_eq!(0, gradient.scale(0, 0).as_bytes().len());
+    }
+
+    #[test]
+    fn crop() {
+        let original = numbered(6, 4);
+        let cropped = original.crop(2, 2, 3, 2);
+        assert_eq!((3, 2), (cropped.width(), cropped.height()));
+        let (y, u, v) = cropped.planes();
+        assert_eq!(&[14, 15, 16, 20, 21, 22], y);
+        assert_eq!(&[104, 105], u);
+        assert_eq!(&[204, 205], v);
+
+        // Odd origins are rounded down and sizes are clamped.
+        let cropped = original.crop(3, 1, 100, 100);
+        assert_eq!((4, 4), (cropped.width(), cropped.height()));
+        assert_eq!(2, cropped.planes().0[0]);
+
+        assert_eq!(original, original.crop(0, 0, 6, 4));
+    }
+
+    #[test]
+    fn center_crop_to_aspect_ratio() {
+        let wide = I420Buffer::new(1280, 720);
+        let square = wide.center_crop_to_aspect_ratio(1, 1);
+        assert_eq!((720, 720), (square.width(), square.height()));
+        let portrait = wide.center_crop_to_aspect_ratio(9, 16);
+        assert_eq!((404, 720), (portrait.width(), portrait.height()));
+        let same = wide.center_crop_to_aspect_ratio(16, 9);
+        assert_eq!((1280, 720), (same.width(), same.height()));
+
+        let tall = numbered(4, 8);
+        let square = tall.center_crop_to_aspect_ratio(1, 1);
+        assert_eq!((4, 4), (square.width(), square.height()));
+        // Rows 2 through 5
+        assert_eq!(8, square.planes().0[0]);
+        assert_eq!(&[102, 103, 104, 105], square.planes().1);
+    }
+
+    #[test]
+    fn mirror_horizontally() {
+        let original = numbered(3, 2);
+        let mirrored = original.mirror_horizontally();
+        let (y, u, v) = mirrored.planes();
+        assert_eq!(&[2, 1, 0, 5, 4, 3], y);
+        assert_eq!(&[101, 100], u);
+        assert_eq!(&[201, 200], v);
+        assert_eq!(original, mirrored.mirror_horizontally());
+    }
+
+    #[test]
+    fn blend() {
+        let mut frame = I420Buffer::new(6, 4);
+        let mut overlay = I420Buffer::new(4, 4);
+        overlay.planes_mut().0.fill(235);
+        overlay.planes_mut().1.fill(0);
+
+        frame.blend(&overlay, 3, 2, 255);
+        let (y, u, _) = frame.planes();
+        // Starts at (2, 2) and is clipped on the right and bottom.
+        assert_eq!(&[16, 16, 16, 16, 16, 16], &y[6..12]);
+        assert_eq!(&[16, 16, 235, 235, 235, 235], &y[12..18]);
+        assert_eq!(&[16, 16, 235, 235, 235, 235], &y[18..24]);
+        assert_eq!(&[128, 128, 128, 128, 0, 0], u);
+
+        let mut frame = I420Buffer::new(2, 2);
+        frame.blend(&overlay, 0, 0, 128);
+        assert_eq!(&[126, 126, 126, 126, 64, 128], frame.as_bytes());
+
+        let mut frame = I420Buffer::new(2, 2);
+        frame.blend(&overlay, 10, 10, 255);
+        assert_eq!(I420Buffer::new(2, 2), frame);
+    }
+
+    #[test]
+    fn rotate() {
+        let original = numbered(3, 2);
+        // 0 1 2
+        // 3 4 5
+        let rotated = original.rotate(VideoRotation::Clockwise90);
+        assert_eq!((2, 3), (rotated.width(), rotated.height()));
+        assert_eq!(&[3, 0, 4, 1, 5, 2], rotated.planes().0);
+        assert_eq!(&[100, 101], rotated.planes().1);
+
+        let rotated = original.rotate(VideoRotation::Clockwise180);
+        assert_eq!(&[5, 4, 3, 2, 1, 0], rotated.planes().0);
+
+        let rotated = original.rotate(VideoRotation::Clockwise270);
+        assert_eq!(&[2, 5, 1, 4, 0, 3], rotated.planes().0);
+        assert_eq!(&[101, 100], rotated.planes().1);
+
+        assert_eq!(original, original.rotate(VideoRotation::None));
+        assert_eq!(
+            original,
+            original
+                .rotate(VideoRotation::Clockwise90)
+                .rotate(VideoRotation::Clockwise270)
+        );
+    }
+}
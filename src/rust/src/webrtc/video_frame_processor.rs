@@ -0,0 +1,473 @@
+//
+// Copyright 2022 Signal Messenger, LLC
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Processing of outgoing video frames before they are sent.
+//!
+//! A VideoSource can have a chain of VideoFrameProcessors which are
+//! applied, in order, to every pushed frame.  This is the place for
+//! effects like background blur, so they can be written once for all
+//! platforms.  Some simple processors are included here.
+//!
+//! On Android and iOS, the camera's frames don't come through a
+//! VideoSource the app pushes to.  Instead, each group call sends them
+//! through the CallManager's chain with
+//! PeerConnectionFactory::create_processed_video_source.
+
+use std::{
+    convert::TryFrom,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::common::Result;
+use crate::error::RingRtcError;
+use crate::webrtc::{
+    media::{VideoFrame, VideoRotation},
+    video_frame_buffer::I420Buffer,
+};
+
+pub trait VideoFrameProcessor: Send {
+    /// Identifies the processor in stats and logs.
+    fn name(&self) -> &str;
+
+    /// Returns the processed frame, or None to drop the frame.
+    fn process(&mut self, frame: I420Buffer) -> Option<I420Buffer>;
+}
+
+/// Timing of one processor in a chain.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VideoFrameProcessorStats {
+    pub name: String,
+    pub frames_processed: u64,
+    pub frames_dropped: u64,
+    pub total_processing_time: Duration,
+    pub max_processing_time: Duration,
+}
+
+struct ChainedProcessor {
+    // Held while processing a frame, so only frames going through
+    // the same processor wait on it.
+    processor: Mutex<Box<dyn VideoFrameProcessor>>,
+    // Only held briefly, so stats can be read while a frame is processed.
+    stats: Mutex<VideoFrameProcessorStats>,
+}
+
+/// An ordered list of processors, applied one after another.
+/// Processors can be added, cleared and queried while frames are being
+/// processed; frames already in the chain finish with the processors they started with.
+pub struct VideoFrameProcessorChain {
+    processors: Mutex<Vec<Arc<ChainedProcessor>>>,
+}
+
+impl fmt::Debug for VideoFrameProcessorChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.stats().into_iter().map(|stats| stats.name))
+            .finish()
+    }
+}
+
+impl Default for VideoFrameProcessorChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VideoFrameProcessorChain {
+    pub fn new() -> Self {
+        Self {
+            processors: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshot().is_empty()
+    }
+
+    /// Adds a processor to the end of the chain.
+    pub fn add(&self, processor: Box<dyn VideoFrameProcessor>) {
+        info!("Adding video frame processor: {}", processor.name());
+        let chained = Arc::new(ChainedProcessor {
+            stats: Mutex::new(VideoFrameProcessorStats {
+                name: processor.name().to_string(),
+                ..Default::default()
+            }),
+            processor: Mutex::new(processor),
+        });
+        if let Ok(mut processors) = self.processors.lock() {
+            processors.push(chained);
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut processors) = self.processors.lock() {
+            processors.clear();
+        }
+    }
+
+    pub fn stats(&self) -> Vec<VideoFrameProcessorStats> {
+        self.snapshot()
+            .iter()
+            .filter_map(|chained| chained.stats.lock().ok().map(|stats| stats.clone()))
+            .collect()
+    }
+
+    fn snapshot(&self) -> Vec<Arc<ChainedProcessor>> {
+        match self.processors.lock() {
+            Ok(processors) => processors.clone(),
+            Err(_) => vec![],
+        }
+    }
+
+    /// Runs the frame through each processor.  If any drops it, so does the chain.
+    pub fn process(&self, frame: VideoFrame) -> Option<VideoFrame> {
+        // Don't hold the lock while processing, which can take a while.
+        let processors = self.snapshot();
+        if processors.is_empty() {
+            return Some(frame);
+        }
+        let mut buffer = frame.apply_rotation().to_i420_buffer();
+        for chained in &processors {
+            let (processed, elapsed) = match chained.processor.lock() {
+                Ok(mut processor) => {
+                    let start = Instant::now();
+                    let processed = processor.process(buffer);
+                    (processed, start.elapsed())
+                }
+                Err(_) => {
+                    warn!("Skipping poisoned video frame processor");
+                    continue;
+                }
+            };
+
+            let dropped = processed.is_none();
+            if let Ok(mut stats) = chained.stats.lock() {
+                stats.total_processing_time += elapsed;
+                stats.max_processing_time = stats.max_processing_time.max(elapsed);
+                if dropped {
+                    stats.frames_dropped += 1;
+                } else {
+                    stats.frames_processed += 1;
+                }
+            }
+            buffer = processed?;
+        }
+        Some(VideoFrame::from_i420_buffer(&buffer))
+    }
+}
+
+/// Scales every frame to the same size.
+pub struct ScaleProcessor {
+    width: u32,
+    height: u32,
+}
+
+impl ScaleProcessor {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+}
+
+impl VideoFrameProcessor for ScaleProcessor {
+    fn name(&self) -> &str {
+        "scale"
+    }
+
+    fn process(&mut self, frame: I420Buffer) -> Option<I420Buffer> {
+        Some(frame.scale(self.width, self.height))
+    }
+}
+
+/// Checks a size or aspect ratio given to a platform API, where it's signed.
+pub fn checked_frame_size(width: i64, height: i64) -> Result<(u32, u32)> {
+    match (u32::try_from(width), u32::try_from(height)) {
+        (Ok(w), Ok(h)) if w > 0 && h > 0 => Ok((w, h)),
+        _ => Err(RingRtcError::InvalidVideoFrameSize(width, height).into()),
+    }
+}
+
+enum Crop {
+    Rect {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    CenterToAspectRatio {
+        width: u32,
+        height: u32,
+    },
+}
+
+/// Crops every frame, either to a fixed rectangle or to an aspect ratio.
+pub struct CropProcessor {
+    crop: Crop,
+}
+
+impl CropProcessor {
+    pub fn rect(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            crop: Crop::Rect {
+                x,
+                y,
+                width,
+                height,
+            },
+        }
+    }
+
+    pub fn center_to_aspect_ratio(width: u32, height: u32) -> Self {
+        Self {
+            crop: Crop::CenterToAspectRatio { width, height },
+        }
+    }
+}
+
+impl VideoFrameProcessor for CropProcessor {
+    fn name(&self) -> &str {
+        "crop"
+    }
+
+    fn process(&mut self, frame: I420Buffer) -> Option<I420Buffer> {
+        Some(match self.crop {
+            Crop::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => frame.crop(x, y, width, height),
+            Crop::CenterToAspectRatio { width, height } => {
+                frame.center_crop_to_aspect_ratio(width, height)
+            }
+        })
+    }
+}
+
+/// Rotates every frame clockwise.
+pub struct RotateProcessor {
+    rotation: VideoRotation,
+}
+
+impl RotateProcessor {
+    pub fn new(rotation: VideoRotation) -> Self {
+        Self { rotation }
+    }
+}
+
+impl VideoFrameProcessor for RotateProcessor {
+    fn name(&self) -> &str {
+        "rotate"
+    }
+
+    fn process(&mut self, frame: I420Buffer) -> Option<I420Buffer> {
+        Some(frame.rotate(self.rotation))
+    }
+}
+
+/// Draws an image over every frame, offset from the top-left corner.
+pub struct WatermarkProcessor {
+    image: I420Buffer,
+    x: u32,
+    y: u32,
+    opacity: u8,
+}
+
+impl WatermarkProcessor {
+    pub fn new(image: I420Buffer, x: u32, y: u32, opacity: u8) -> Self {
+        Self {
+            image,
+            x,
+            y,
+            opacity,
+        }
+    }
+}
+
+impl VideoFrameProcessor for WatermarkProcessor {
+    fn name(&self) -> &str {
+        "watermark"
+    }
+
+    fn process(&mut self, mut frame: I420Buffer) -> Option<I420Buffer> {
+        frame.blend(&self.image, self.x, self.y, self.opacity);
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct DropEveryOther {
+        drop_next: bool,
+    }
+
+    impl VideoFrameProcessor for DropEveryOther {
+        fn name(&self) -> &str {
+            "drop-every-other"
+        }
+
+        fn process(&mut self, frame: I420Buffer) -> Option<I420Buffer> {
+            self.drop_next = !self.drop_next;
+            if self.drop_next {
+                None
+            } else {
+                Some(frame)
+            }
+        }
+    }
+
+    #[test]
+    fn built_in_processors() {
+        let mut frame = I420Buffer::new(64, 48);
+        frame.planes_mut().0.fill(100);
+
+        let cropped = CropProcessor::center_to_aspect_ratio(1, 1)
+            .process(frame.clone())
+            .unwrap();
+        assert_eq!((48, 48), (cropped.width(), cropped.height()));
+        let cropped = CropProcessor::rect(2, 2, 10, 20)
+            .process(frame.clone())
+            .unwrap();
+        assert_eq!((10, 20), (cropped.width(), cropped.height()));
+
+        let scaled = ScaleProcessor::new(32, 24).process(frame.clone()).unwrap();
+        assert_eq!((32, 24), (scaled.width(), scaled.height()));
+
+        let rotated = RotateProcessor::new(VideoRotation::Clockwise90)
+            .process(frame.clone())
+            .unwrap();
+        assert_eq!((48, 64), (rotated.width(), rotated.height()));
+
+        let mut image = I420Buffer::new(4, 4);
+        image.planes_mut().0.fill(200);
+        let watermarked = WatermarkProcessor::new(image, 60, 44, 255)
+            .process(frame)
+            .unwrap();
+        let y = watermarked.planes().0;
+        assert_eq!(100, y[0]);
+        assert_eq!(200, y[47 * 64 + 63]);
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn chain() {
+        let chain = VideoFrameProcessorChain::new();
+        assert!(chain.is_empty());
+
+        let frame = VideoFrame::from_i420_buffer(&I420Buffer::new(64, 48));
+        let frame = chain.process(frame).unwrap();
+        assert_eq!((64, 48), (frame.width(), frame.height()));
+
+        chain.add(Box::new(CropProcessor::center_to_aspect_ratio(1, 1)));
+        chain.add(Box::new(DropEveryOther { drop_next: false }));
+        chain.add(Box::new(ScaleProcessor::new(16, 16)));
+        assert!(!chain.is_empty());
+
+        assert!(chain.process(frame).is_none());
+        let frame = VideoFrame::from_i420_buffer(&I420Buffer::new(64, 48));
+        let frame = chain.process(frame).unwrap();
+        assert_eq!((16, 16), (frame.width(), frame.height()));
+
+        let stats = chain.stats();
+        assert_eq!(
+            vec!["crop", "drop-every-other", "scale"],
+            stats.iter().map(|s| s.name.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(2, 0), (1, 1), (1, 0)],
+            stats
+                .iter()
+                .map(|s| (s.frames_processed, s.frames_dropped))
+                .collect::<Vec<_>>()
+        );
+        assert!(stats
+            .iter()
+            .all(|s| s.max_processing_time <= s.total_processing_time));
+
+        chain.clear();
+        assert!(chain.is_empty());
+        assert!(chain.stats().is_empty());
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn processed_video_source_shares_and_releases_chain() {
+        use crate::webrtc::peer_connection_factory::{Config, PeerConnectionFactory};
+
+        let pcf = PeerConnectionFactory::new(Config::default()).unwrap();
+        let input_source = pcf.create_outgoing_video_source().unwrap();
+        let input_track = pcf.create_outgoing_video_track(&input_source).unwrap();
+        let chain = Arc::new(VideoFrameProcessorChain::new());
+
+        let source = pcf
+            .create_processed_video_source(&input_track, chain.clone())
+            .unwrap();
+        chain.add(Box::new(ScaleProcessor::new(16, 16)));
+        assert_eq!(1, source.frame_processor_stats().len());
+
+        // The native source's reference was given back with deleteProcessor.
+        drop(source);
+        assert_eq!(1, Arc::strong_count(&chain));
+    }
+
+    #[test]
+    fn frame_sizes_from_platforms() {
+        assert_eq!((16, 9), checked_frame_size(16, 9).unwrap());
+        assert!(checked_frame_size(0, 9).is_err());
+        assert!(checked_frame_size(16, -9).is_err());
+        assert!(checked_frame_size(1 << 32, 9).is_err());
+    }
+
+    struct WaitForSignal {
+        started: std::sync::mpsc::Sender<()>,
+        finish: std::sync::mpsc::Receiver<()>,
+    }
+
+    impl VideoFrameProcessor for WaitForSignal {
+        fn name(&self) -> &str {
+            "wait-for-signal"
+        }
+
+        fn process(&mut self, frame: I420Buffer) -> Option<I420Buffer> {
+            self.started.send(()).unwrap();
+            self.finish.recv().unwrap();
+            Some(frame)
+        }
+    }
+
+    #[cfg(feature = "sim")]
+    #[test]
+    fn chain_is_usable_while_processing() {
+        let (started_sender, started_receiver) = std::sync::mpsc::channel();
+        let (finish_sender, finish_receiver) = std::sync::mpsc::channel();
+        let chain = Arc::new(VideoFrameProcessorChain::new());
+        chain.add(Box::new(WaitForSignal {
+            started: started_sender,
+            finish: finish_receiver,
+        }));
+
+        let processing = {
+            let chain = chain.clone();
+            std::thread::spawn(move || {
+                chain.process(VideoFrame::from_i420_buffer(&I420Buffer::new(64, 48)))
+            })
+        };
+        started_receiver.recv().unwrap();
+
+        // None of these wait for the frame being processed.
+        assert_eq!(0, chain.stats()[0].frames_processed);
+        chain.add(Box::new(ScaleProcessor::new(16, 16)));
+        assert_eq!(2, chain.stats().len());
+
+        finish_sender.send(()).unwrap();
+        let frame = processing.join().unwrap().unwrap();
+        // The frame started before the scale processor was added.
+        assert_eq!((64, 48), (frame.width(), frame.height()));
+        assert_eq!(1, chain.stats()[0].frames_processed);
+        assert_eq!(0, chain.stats()[1].frames_processed);
+    }
+}
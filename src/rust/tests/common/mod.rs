@@ -161,6 +161,11 @@ impl TestContext {
         platform.offers_sent()
     }
 
+    pub fn talking_while_muted_count(&self) -> usize {
+        let platform = self.call_manager.platform().unwrap();
+        platform.talking_while_muted_count()
+    }
+
     pub fn answers_sent(&self) -> usize {
         let platform = self.call_manager.platform().unwrap();
         platform.answers_sent()
@@ -12,13 +12,14 @@ extern crate log;
 
 use std::net::SocketAddr;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use prost::Message;
 use ringrtc::common::{
     units::DataRate, ApplicationEvent, CallId, CallMediaType, CallState, ConnectionState, DeviceId,
 };
 use ringrtc::core::bandwidth_mode::BandwidthMode;
+use ringrtc::core::speaker_detector::TalkingWhileMutedConfig;
 use ringrtc::core::{group_call, signaling};
 use ringrtc::protobuf;
 use ringrtc::sim::error::SimError;
@@ -915,6 +916,47 @@ fn update_sender_status() {
     );
 }
 
+#[test]
+fn talking_while_muted() {
+    test_init();
+
+    let context = connected_and_accepted_outbound_call();
+    let mut cm = context.cm();
+    let active_connection = context.active_connection();
+
+    cm.set_talking_while_muted_config(TalkingWhileMutedConfig {
+        min_duration: Duration::from_millis(400),
+        ..TalkingWhileMutedConfig::default()
+    })
+    .expect(error_line!());
+
+    // Feed the captured levels with our own timestamps rather than from the tick.
+    let start = Instant::now();
+    let mut detect = |millis: u64| {
+        active_connection
+            .detect_talking_while_muted(start + Duration::from_millis(millis), 5000)
+            .expect(error_line!());
+        cm.synchronize().expect(error_line!());
+        context.talking_while_muted_count()
+    };
+
+    // Not muted
+    assert_eq!(0, detect(0));
+    assert_eq!(0, detect(1000));
+
+    context
+        .cm()
+        .set_direct_outgoing_audio_muted(true)
+        .expect(error_line!());
+    assert_eq!(0, detect(1200));
+    assert_eq!(0, detect(1400));
+    assert_eq!(1, detect(1600));
+    // Not again right away.
+    assert_eq!(1, detect(1800));
+
+    assert_eq!(context.error_count(), 0);
+}
+
 #[test]
 fn update_bandwidth_mode_default() {
     test_init();